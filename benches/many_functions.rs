@@ -73,7 +73,12 @@ fn nops(c: &mut Criterion) {
         deserialize.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, _| {
             b.iter(|| unsafe {
                 let deserialized = UniversalExecutableRef::deserialize(&serialized).unwrap();
-                black_box(store.engine().load(&deserialized).unwrap());
+                black_box(
+                    store
+                        .engine()
+                        .load(&deserialized, store.tunables())
+                        .unwrap(),
+                );
             })
         });
     }
@@ -34,6 +34,11 @@ fn main() -> anyhow::Result<()> {
                 "tests/wast/spec/proposals/multi-value",
                 wast_processor,
             )?;
+            test_directory_module(
+                spectests,
+                "tests/wast/spec/proposals/multi-memory",
+                wast_processor,
+            )?;
             test_directory_module(spectests, "tests/wast/spec/proposals/simd", wast_processor)?;
             // test_directory_module(spectests, "tests/wast/spec/proposals/bulk-memory-operations", wast_processor)?;
             Ok(())
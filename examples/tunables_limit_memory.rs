@@ -129,6 +129,23 @@ impl<T: Tunables> Tunables for LimitingTunables<T> {
     ) -> Result<Arc<dyn vm::Table>, String> {
         self.base.create_vm_table(ty, style, vm_definition_location)
     }
+
+    /// Allocate the data of an instance.
+    ///
+    /// Delegated to base.
+    fn allocate_instance(
+        &self,
+        offsets: vm::VMOffsets,
+    ) -> Result<
+        (
+            vm::InstanceAllocator,
+            Vec<NonNull<VMMemoryDefinition>>,
+            Vec<NonNull<VMTableDefinition>>,
+        ),
+        String,
+    > {
+        self.base.allocate_instance(offsets)
+    }
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -1,4 +1,4 @@
-use crate::sys::externals::{Extern, Function, Global, Memory, Table};
+use crate::sys::externals::{Extern, Function, Global, Memory, Table, Tag};
 use crate::sys::import_object::LikeNamespace;
 use crate::sys::native::NativeFunc;
 use crate::sys::WasmTypeList;
@@ -268,6 +268,14 @@ where
             _ => None,
         })
     }
+
+    /// Get only the tags.
+    pub fn tags(self) -> impl Iterator<Item = (&'a String, &'a Tag)> + Sized {
+        self.iter.filter_map(|(name, export)| match export {
+            Extern::Tag(tag) => Some((name, tag)),
+            _ => None,
+        })
+    }
 }
 
 impl FromIterator<(String, Extern)> for Exports {
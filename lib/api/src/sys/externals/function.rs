@@ -1,5 +1,6 @@
 use crate::sys::exports::{ExportError, Exportable};
 use crate::sys::externals::Extern;
+use crate::sys::fiber;
 use crate::sys::store::Store;
 use crate::sys::types::{Val, ValFuncRef};
 use crate::sys::FunctionType;
@@ -11,6 +12,7 @@ pub use inner::{FromToNativeWasmType, HostFunction, WasmTypeList, WithEnv, Witho
 use std::cmp::max;
 use std::ffi::c_void;
 use std::fmt;
+use std::future::Future;
 use std::sync::Arc;
 use wasmer_vm::{
     raise_user_trap, resume_panic, wasmer_call_trampoline, Export, ExportFunction,
@@ -249,6 +251,72 @@ impl Function {
         }
     }
 
+    /// Creates a new asynchronous host `Function` (dynamic) with the provided
+    /// signature.
+    ///
+    /// The guest waits for the future returned by `func` without blocking
+    /// the thread. This requires it to be called through
+    /// [`NativeFunc::call_async`]: called in any other way, the function
+    /// returns an error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmer::{Function, FunctionType, Type, Store, Value};
+    /// # let store = Store::default();
+    /// #
+    /// let signature = FunctionType::new(vec![Type::I32, Type::I32], vec![Type::I32]);
+    ///
+    /// let f = Function::new_async(&store, &signature, |args| {
+    ///     let sum = args[0].unwrap_i32() + args[1].unwrap_i32();
+    ///     async move { Ok(vec![Value::I32(sum)]) }
+    /// });
+    /// ```
+    pub fn new_async<FT, F, Fut>(store: &Store, ty: FT, func: F) -> Self
+    where
+        FT: Into<FunctionType>,
+        F: Fn(&[Val]) -> Fut + 'static + Send + Sync,
+        Fut: Future<Output = Result<Vec<Val>, RuntimeError>>,
+    {
+        Self::new(store, ty, move |args| fiber::wait(func(args)))
+    }
+
+    /// Creates a new asynchronous host `Function` (dynamic) with the provided
+    /// signature and environment.
+    ///
+    /// See [`Function::new_async`] for how the returned future is waited on.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmer::{Function, FunctionType, Type, Store, Value, WasmerEnv};
+    /// # let store = Store::default();
+    /// #
+    /// #[derive(WasmerEnv, Clone)]
+    /// struct Env {
+    ///   multiplier: i32,
+    /// };
+    /// let env = Env { multiplier: 2 };
+    ///
+    /// let signature = FunctionType::new(vec![Type::I32, Type::I32], vec![Type::I32]);
+    ///
+    /// let f = Function::new_async_with_env(&store, &signature, env, |env, args| {
+    ///     let result = env.multiplier * (args[0].unwrap_i32() + args[1].unwrap_i32());
+    ///     async move { Ok(vec![Value::I32(result)]) }
+    /// });
+    /// ```
+    pub fn new_async_with_env<FT, F, Fut, Env>(store: &Store, ty: FT, env: Env, func: F) -> Self
+    where
+        FT: Into<FunctionType>,
+        F: Fn(&Env, &[Val]) -> Fut + 'static + Send + Sync,
+        Fut: Future<Output = Result<Vec<Val>, RuntimeError>>,
+        Env: Sized + WasmerEnv + 'static,
+    {
+        Self::new_with_env(store, ty, env, move |env, args| {
+            fiber::wait(func(env, args))
+        })
+    }
+
     /// Creates a new host `Function` from a native function.
     ///
     /// The function signature is automatically retrieved using the
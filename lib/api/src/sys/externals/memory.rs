@@ -1,7 +1,7 @@
 use crate::sys::exports::{ExportError, Exportable};
 use crate::sys::externals::Extern;
 use crate::sys::store::Store;
-use crate::sys::{MemoryType, MemoryView};
+use crate::sys::{MemoryType, MemoryView, MemoryView64};
 use std::convert::TryInto;
 use std::slice;
 use std::sync::Arc;
@@ -34,6 +34,10 @@ impl Memory {
     /// This function will construct the `Memory` using the store
     /// [`BaseTunables`][crate::sys::BaseTunables].
     ///
+    /// A shared memory must have a maximum size. It never moves when it
+    /// grows, so clones of it can be imported by instances running on
+    /// other threads.
+    ///
     /// # Example
     ///
     /// ```
@@ -41,6 +45,7 @@ impl Memory {
     /// # let store = Store::default();
     /// #
     /// let m = Memory::new(&store, MemoryType::new(1, None, false)).unwrap();
+    /// let shared = Memory::new(&store, MemoryType::new(1, Some(16), true)).unwrap();
     /// ```
     pub fn new(store: &Store, ty: MemoryType) -> Result<Self, MemoryError> {
         let tunables = store.tunables();
@@ -224,6 +229,19 @@ impl Memory {
         unsafe { MemoryView::new(base as _, length as u32) }
     }
 
+    /// Return a "view" of the currently accessible memory, indexed with
+    /// `u64` offsets.
+    ///
+    /// This is the counterpart of [`Memory::view`] for memories indexed
+    /// with 64-bit addresses, whose size can exceed 4 GiB.
+    pub fn view64<T: ValueType>(&self) -> MemoryView64<T> {
+        let base = self.data_ptr();
+
+        let length = self.data_size() / std::mem::size_of::<T>() as u64;
+
+        unsafe { MemoryView64::new(base as _, length) }
+    }
+
     /// A shortcut to [`Self::view::<u8>`][self::view].
     ///
     /// This code is going to be refactored. Use it as your own risks.
@@ -2,6 +2,7 @@ pub(crate) mod function;
 mod global;
 mod memory;
 mod table;
+mod tag;
 
 pub use self::function::{
     FromToNativeWasmType, Function, HostFunction, WasmTypeList, WithEnv, WithoutEnv,
@@ -10,6 +11,7 @@ pub use self::function::{
 pub use self::global::Global;
 pub use self::memory::Memory;
 pub use self::table::Table;
+pub use self::tag::Tag;
 
 use crate::sys::exports::{ExportError, Exportable};
 use crate::sys::store::{Store, StoreObject};
@@ -31,6 +33,8 @@ pub enum Extern {
     Table(Table),
     /// A external [`Memory`].
     Memory(Memory),
+    /// A external [`Tag`].
+    Tag(Tag),
 }
 
 impl Extern {
@@ -41,6 +45,7 @@ impl Extern {
             Self::Memory(ft) => ExternType::Memory(ft.ty()),
             Self::Table(tt) => ExternType::Table(*tt.ty()),
             Self::Global(gt) => ExternType::Global(*gt.ty()),
+            Self::Tag(tt) => ExternType::Tag(tt.ty().clone()),
         }
     }
 
@@ -51,6 +56,7 @@ impl Extern {
             Export::Memory(m) => Self::Memory(Memory::from_vm_export(store, m)),
             Export::Global(g) => Self::Global(Global::from_vm_export(store, g)),
             Export::Table(t) => Self::Table(Table::from_vm_export(store, t)),
+            Export::Tag(t) => Self::Tag(Tag::from_vm_export(store, t)),
         }
     }
 }
@@ -62,6 +68,7 @@ impl<'a> Exportable<'a> for Extern {
             Self::Global(g) => g.to_export(),
             Self::Memory(m) => m.to_export(),
             Self::Table(t) => t.to_export(),
+            Self::Tag(t) => t.to_export(),
         }
     }
 
@@ -76,6 +83,7 @@ impl<'a> Exportable<'a> for Extern {
             Self::Global(g) => g.into_weak_instance_ref(),
             Self::Memory(m) => m.into_weak_instance_ref(),
             Self::Table(t) => t.into_weak_instance_ref(),
+            Self::Tag(t) => t.into_weak_instance_ref(),
         }
     }
 }
@@ -87,6 +95,7 @@ impl StoreObject for Extern {
             Self::Global(g) => g.store(),
             Self::Memory(m) => m.store(),
             Self::Table(t) => t.store(),
+            Self::Tag(t) => t.store(),
         };
         Store::same(my_store, store)
     }
@@ -102,6 +111,7 @@ impl fmt::Debug for Extern {
                 Self::Global(_) => "Global(...)",
                 Self::Memory(_) => "Memory(...)",
                 Self::Table(_) => "Table(...)",
+                Self::Tag(_) => "Tag(...)",
             }
         )
     }
@@ -130,3 +140,9 @@ impl From<Table> for Extern {
         Self::Table(r)
     }
 }
+
+impl From<Tag> for Extern {
+    fn from(r: Tag) -> Self {
+        Self::Tag(r)
+    }
+}
@@ -0,0 +1,135 @@
+use crate::sys::exports::{ExportError, Exportable};
+use crate::sys::externals::Extern;
+use crate::sys::store::Store;
+use crate::sys::{TagType, Val};
+use std::sync::Arc;
+use wasmer_vm::{Exception, Export, VMTag};
+
+/// A WebAssembly `tag` instance.
+///
+/// A tag identifies the exceptions thrown with it: `catch` clauses only
+/// catch the exceptions whose tag is the same instance as theirs. Instances
+/// that want to throw exceptions to each other have to share a tag, by
+/// exporting and importing it.
+///
+/// Spec: <https://webassembly.github.io/exception-handling/core/exec/runtime.html#tag-instances>
+#[derive(Debug)]
+pub struct Tag {
+    store: Store,
+    vm_tag: VMTag,
+}
+
+impl Tag {
+    /// Creates a new host `Tag` from the provided [`TagType`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Store, Tag, TagType, Type};
+    /// # let store = Store::default();
+    /// #
+    /// let t = Tag::new(&store, TagType::new(vec![Type::I32]));
+    /// ```
+    pub fn new(store: &Store, ty: TagType) -> Self {
+        Self {
+            store: store.clone(),
+            vm_tag: VMTag {
+                from: Arc::new(wasmer_vm::Tag::new(ty)),
+                // We are creating it from the host, and therefore there is no
+                // associated instance with this tag
+                instance_ref: None,
+            },
+        }
+    }
+
+    /// Returns the [`TagType`] of the `Tag`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Store, Tag, TagType, Type};
+    /// # let store = Store::default();
+    /// #
+    /// let tt = TagType::new(vec![Type::I32]);
+    /// let t = Tag::new(&store, tt.clone());
+    ///
+    /// assert_eq!(t.ty(), &tt);
+    /// ```
+    pub fn ty(&self) -> &TagType {
+        self.vm_tag.from.ty()
+    }
+
+    /// Returns the [`Store`] where the `Tag` belongs.
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    /// Returns the values of `exception` if it was thrown with this tag.
+    ///
+    /// Exceptions that no WebAssembly code catches are returned to the host
+    /// as a [`RuntimeError`][crate::sys::RuntimeError], see
+    /// [`RuntimeError::exception`][crate::sys::RuntimeError::exception].
+    pub fn values(&self, exception: &Exception) -> Option<Vec<Val>> {
+        if Arc::ptr_eq(&self.vm_tag.from, exception.tag()) {
+            Some(exception.values())
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn from_vm_export(store: &Store, vm_tag: VMTag) -> Self {
+        Self {
+            store: store.clone(),
+            vm_tag,
+        }
+    }
+
+    /// Returns whether or not these two tags are the same tag.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Store, Tag, TagType};
+    /// # let store = Store::default();
+    /// #
+    /// let t = Tag::new(&store, TagType::new(vec![]));
+    /// let u = Tag::new(&store, TagType::new(vec![]));
+    ///
+    /// assert!(t.same(&t));
+    /// assert!(!t.same(&u));
+    /// ```
+    pub fn same(&self, other: &Self) -> bool {
+        self.vm_tag.same(&other.vm_tag)
+    }
+}
+
+impl Clone for Tag {
+    fn clone(&self) -> Self {
+        let mut vm_tag = self.vm_tag.clone();
+        vm_tag.upgrade_instance_ref().unwrap();
+
+        Self {
+            store: self.store.clone(),
+            vm_tag,
+        }
+    }
+}
+
+impl<'a> Exportable<'a> for Tag {
+    fn to_export(&self) -> Export {
+        self.vm_tag.clone().into()
+    }
+
+    fn get_self_from_extern(_extern: Extern) -> Result<Self, ExportError> {
+        match _extern {
+            Extern::Tag(tag) => Ok(tag),
+            _ => Err(ExportError::IncompatibleType),
+        }
+    }
+
+    fn into_weak_instance_ref(&mut self) {
+        if let Some(v) = self.vm_tag.instance_ref.as_mut() {
+            *v = v.downgrade();
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Asynchronous calls into WebAssembly.
+//!
+//! [`NativeFunc::call_async`] runs the guest on a fiber with a stack of its
+//! own. When an asynchronous host function created with
+//! [`Function::new_async`] is called and its future is not ready yet, the
+//! fiber is suspended and the [`CallAsync`] future returns
+//! [`Poll::Pending`]. The next poll resumes the guest where it left off.
+//!
+//! [`NativeFunc::call_async`]: crate::NativeFunc::call_async
+//! [`Function::new_async`]: crate::Function::new_async
+
+use crate::sys::RuntimeError;
+use std::cell::Cell;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::ptr;
+use std::task::{Context, Poll};
+use wasmer_vm::{Fiber, FiberStack, Suspend, DEFAULT_FIBER_STACK_SIZE};
+
+/// The state shared between a [`CallAsync`] future and the asynchronous
+/// host functions called on its fiber.
+struct AsyncState {
+    /// Set by the fiber when it starts.
+    suspend: Cell<*const Suspend>,
+    /// Set by [`CallAsync::poll`] while the fiber runs.
+    cx: Cell<*mut Context<'static>>,
+}
+
+thread_local! {
+    /// The state of the fiber currently running on this thread, if any.
+    static CURRENT: Cell<*const AsyncState> = Cell::new(ptr::null());
+}
+
+/// The future returned by [`NativeFunc::call_async`].
+///
+/// Dropping it before it completes makes the pending asynchronous host
+/// functions return an error, which unwinds the guest.
+///
+/// [`NativeFunc::call_async`]: crate::NativeFunc::call_async
+#[must_use = "futures do nothing unless polled"]
+pub struct CallAsync<'a, Rets> {
+    // Declared first so that the fiber is dropped before the state it uses.
+    fiber: Option<Fiber<'a, Result<Rets, RuntimeError>>>,
+    call: Option<Box<dyn FnOnce() -> Result<Rets, RuntimeError> + 'a>>,
+    state: Box<AsyncState>,
+}
+
+impl<'a, Rets: 'a> CallAsync<'a, Rets> {
+    pub(crate) fn new(call: impl FnOnce() -> Result<Rets, RuntimeError> + 'a) -> Self {
+        Self {
+            fiber: None,
+            call: Some(Box::new(call)),
+            state: Box::new(AsyncState {
+                suspend: Cell::new(ptr::null()),
+                cx: Cell::new(ptr::null_mut()),
+            }),
+        }
+    }
+
+    fn start(&mut self) -> Result<(), RuntimeError> {
+        let call = self
+            .call
+            .take()
+            .expect("`CallAsync` polled after completion");
+        let state = &*self.state as *const AsyncState;
+        let stack = FiberStack::new(DEFAULT_FIBER_STACK_SIZE).map_err(RuntimeError::new)?;
+        let fiber = Fiber::new(stack, move |suspend| {
+            unsafe { (*state).suspend.set(suspend) };
+            call()
+        })
+        .map_err(RuntimeError::new)?;
+        self.fiber = Some(fiber);
+        Ok(())
+    }
+}
+
+impl<'a, Rets: 'a> Future for CallAsync<'a, Rets> {
+    type Output = Result<Rets, RuntimeError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        struct Reset<'s>(&'s AsyncState, *const AsyncState);
+
+        impl Drop for Reset<'_> {
+            fn drop(&mut self) {
+                CURRENT.with(|current| current.set(self.1));
+                self.0.cx.set(ptr::null_mut());
+            }
+        }
+
+        let this = self.get_mut();
+        if this.fiber.is_none() {
+            if let Err(error) = this.start() {
+                return Poll::Ready(Err(error));
+            }
+        }
+        let fiber = this.fiber.as_mut().unwrap();
+        assert!(!fiber.is_done(), "`CallAsync` polled after completion");
+
+        let state = &*this.state;
+        state.cx.set((cx as *mut Context<'_>).cast());
+        let _reset = Reset(
+            state,
+            CURRENT.with(|current| current.replace(state as *const AsyncState)),
+        );
+        match fiber.resume() {
+            Some(result) => Poll::Ready(result),
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl<'a, Rets> fmt::Debug for CallAsync<'a, Rets> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CallAsync")
+            .field("started", &self.fiber.is_some())
+            .finish()
+    }
+}
+
+/// Wait for `future` to complete, suspending the current fiber whenever it
+/// is pending.
+///
+/// Returns an error if there is no current fiber, i.e. when the host
+/// function was not called through [`CallAsync`], or if the fiber is
+/// cancelled.
+pub(crate) fn wait<T>(
+    future: impl Future<Output = Result<T, RuntimeError>>,
+) -> Result<T, RuntimeError> {
+    let state = CURRENT.with(|current| current.get());
+    if state.is_null() {
+        return Err(RuntimeError::new(
+            "async host functions can only be called through `NativeFunc::call_async`",
+        ));
+    }
+    let state = unsafe { &*state };
+    let mut future = Box::pin(future);
+    loop {
+        let cx = unsafe { &mut *state.cx.get() };
+        if let Poll::Ready(result) = future.as_mut().poll(cx) {
+            return result;
+        }
+        let suspend = unsafe { &*state.suspend.get() };
+        suspend
+            .suspend()
+            .map_err(|error| RuntimeError::new(error.to_string()))?;
+    }
+}
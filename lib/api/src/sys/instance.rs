@@ -6,7 +6,7 @@ use std::fmt;
 use std::sync::{Arc, Mutex};
 use thiserror::Error;
 use wasmer_types::InstanceConfig;
-use wasmer_vm::{InstanceHandle, Resolver, VMContext};
+use wasmer_vm::{InstanceHandle, InstanceSnapshot, Resolver, SnapshotError, VMContext};
 
 use super::exports::ExportableWithGenerics;
 
@@ -64,6 +64,10 @@ pub enum InstantiationError {
     /// Error occurred when initializing the host environment.
     #[error(transparent)]
     HostEnvInitialization(HostEnvInitError),
+
+    /// The snapshot to restore does not match the module.
+    #[error("could not restore the snapshot: {0}")]
+    Snapshot(SnapshotError),
 }
 
 impl From<wasmer_engine::InstantiationError> for InstantiationError {
@@ -126,6 +130,45 @@ impl Instance {
         config: InstanceConfig,
         resolver: &dyn Resolver,
     ) -> Result<Self, InstantiationError> {
+        Self::check_gas_config(&config)?;
+        let handle = module.instantiate(resolver, config)?;
+        Self::from_handle(module, handle)
+    }
+
+    /// Creates a new `Instance` of `module` in the state captured by
+    /// [`Instance::snapshot`], instead of running the initializers and the
+    /// `start` function of the module.
+    ///
+    /// The snapshot must have been taken from an instance of the same
+    /// module. Its burnt gas is written to the gas counter of `config`.
+    ///
+    /// ```
+    /// # use wasmer::{imports, Store, Module, Instance, InstanceSnapshot};
+    /// # use wasmer_types::InstanceConfig;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let store = Store::default();
+    /// let module = Module::new(&store, "(module (global (export \"g\") (mut i32) (i32.const 0)))")?;
+    /// let instance = Instance::new(&module, &imports! {})?;
+    /// let bytes = instance.snapshot()?.serialize()?;
+    ///
+    /// let snapshot = unsafe { InstanceSnapshot::deserialize(&bytes)? };
+    /// let restored =
+    ///     Instance::new_from_snapshot(&module, InstanceConfig::default(), &imports! {}, &snapshot)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new_from_snapshot(
+        module: &Module,
+        config: InstanceConfig,
+        resolver: &dyn Resolver,
+        snapshot: &InstanceSnapshot,
+    ) -> Result<Self, InstantiationError> {
+        Self::check_gas_config(&config)?;
+        let handle = module.instantiate_from_snapshot(resolver, config, snapshot)?;
+        Self::from_handle(module, handle)
+    }
+
+    fn check_gas_config(config: &InstanceConfig) -> Result<(), InstantiationError> {
         unsafe {
             if (*config.gas_counter).opcode_cost > i32::MAX as u64 {
                 // Fast gas counter logic assumes that individual opcode cost is not too big.
@@ -134,7 +177,10 @@ impl Instance {
                 ));
             }
         }
-        let handle = module.instantiate(resolver, config)?;
+        Ok(())
+    }
+
+    fn from_handle(module: &Module, handle: InstanceHandle) -> Result<Self, InstantiationError> {
         let instance = Self {
             handle: Arc::new(Mutex::new(handle)),
             module: module.clone(),
@@ -168,6 +214,23 @@ impl Instance {
         self.module.store()
     }
 
+    /// Set the epoch deadline of this instance to `ticks` increments of
+    /// the engine epoch from now.
+    ///
+    /// Only code compiled with epoch interruption enabled checks the
+    /// deadline, trapping with `TrapCode::Interrupted` once it is reached.
+    pub fn set_epoch_deadline(&self, ticks: u64) {
+        self.handle.lock().unwrap().set_epoch_deadline(ticks)
+    }
+
+    /// Take a snapshot of the state of this instance: its memories, tables,
+    /// globals, remaining passive segments and burnt gas.
+    ///
+    /// See [`Instance::new_from_snapshot`] to restore it.
+    pub fn snapshot(&self) -> Result<InstanceSnapshot, SnapshotError> {
+        self.handle.lock().unwrap().snapshot()
+    }
+
     /// Lookup an exported entity by its name.
     pub fn lookup(&self, field: &str) -> Option<crate::Export> {
         let vmextern = self.handle.lock().unwrap().lookup(field)?;
@@ -183,6 +246,35 @@ impl Instance {
         }
     }
 
+    /// Lookup all the exports, with their names.
+    ///
+    /// The returned [`Exports`] can be registered in an [`ImportObject`] to
+    /// link another module against this instance.
+    ///
+    /// [`Exports`]: crate::Exports
+    /// [`ImportObject`]: crate::ImportObject
+    pub fn exports(&self) -> crate::Exports {
+        let exports = self.handle.lock().unwrap().exports();
+        exports
+            .into_iter()
+            .map(|(name, vmextern)| {
+                let export = crate::Extern::from_vm_export(self.store(), vmextern.into());
+                (name, export)
+            })
+            .collect()
+    }
+
+    /// Lookup all the exported memories, with their names.
+    ///
+    /// Modules using the multi-memory proposal can export several memories.
+    pub fn memories(&self) -> Vec<(String, crate::Memory)> {
+        let memories = self.handle.lock().unwrap().memories();
+        memories
+            .into_iter()
+            .map(|(name, memory)| (name, crate::Memory::from_vm_export(self.store(), memory)))
+            .collect()
+    }
+
     /// Get an export as a `NativeFunc`.
     pub fn get_native_function<Args, Rets>(
         &self,
@@ -2,6 +2,7 @@ mod cell;
 mod env;
 mod exports;
 mod externals;
+mod fiber;
 mod import_object;
 mod instance;
 mod module;
@@ -31,24 +32,28 @@ pub use crate::sys::cell::WasmCell;
 pub use crate::sys::env::{HostEnvInitError, LazyInit, WasmerEnv};
 pub use crate::sys::exports::{ExportError, Exportable, Exports, ExportsIterator};
 pub use crate::sys::externals::{
-    Extern, FromToNativeWasmType, Function, Global, HostFunction, Memory, Table, WasmTypeList,
+    Extern, FromToNativeWasmType, Function, Global, HostFunction, Memory, Table, Tag, WasmTypeList,
 };
+pub use crate::sys::fiber::CallAsync;
 pub use crate::sys::import_object::{ImportObject, ImportObjectIterator, LikeNamespace};
 pub use crate::sys::instance::{Instance, InstantiationError};
-pub use crate::sys::module::Module;
+pub use crate::sys::module::{IoCompileError, Module};
 pub use crate::sys::native::NativeFunc;
-pub use crate::sys::ptr::{Array, Item, WasmPtr};
+pub use crate::sys::ptr::{Array, Item, WasmPtr, WasmPtr64};
 pub use crate::sys::store::{Store, StoreObject};
-pub use crate::sys::tunables::BaseTunables;
+pub use crate::sys::tunables::{BaseTunables, PoolingTunables};
 pub use crate::sys::types::{
-    ExportType, ExternType, FunctionType, GlobalType, MemoryType, Mutability, TableType, Val,
-    ValType,
+    ExportType, ExternType, FunctionType, GlobalType, MemoryType, Mutability, TableType, TagType,
+    Val, ValType,
 };
 pub use crate::sys::types::{Val as Value, ValType as Type};
 pub use crate::sys::utils::is_wasm;
 pub use target_lexicon::{Architecture, CallingConvention, OperatingSystem, Triple, HOST};
 #[cfg(feature = "compiler")]
-pub use wasmer_compiler::{wasmparser, CompilerConfig};
+pub use wasmer_compiler::{
+    wasmparser, CompilerConfig, FunctionMiddleware, MiddlewareError, MiddlewareReaderState,
+    ModuleMiddleware,
+};
 pub use wasmer_compiler::{
     CompileError, CpuFeature, Features, ParseCpuFeatureError, Target, WasmError, WasmResult,
 };
@@ -56,11 +61,13 @@ pub use wasmer_engine::{DeserializeError, Engine, FrameInfo, LinkError, RuntimeE
 #[cfg(feature = "experimental-reference-types-extern-ref")]
 pub use wasmer_types::ExternRef;
 pub use wasmer_types::{
-    Atomically, Bytes, ExportIndex, GlobalInit, LocalFunctionIndex, MemoryView, Pages, ValueType,
-    WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
+    Atomically, Bytes, ExportIndex, GlobalInit, LocalFunctionIndex, MemoryView, MemoryView64,
+    MemoryViewIndex, Pages, ValueType, WASM64_MAX_PAGES, WASM_MAX_PAGES, WASM_MIN_PAGES,
+    WASM_PAGE_SIZE,
 };
 pub use wasmer_vm::{
-    ChainableNamedResolver, Export, NamedResolver, NamedResolverChain, Resolver, Tunables,
+    ChainableNamedResolver, Exception, Export, InstanceSnapshot, NamedResolver, NamedResolverChain,
+    PoolingLimits, Resolver, SnapshotError, Tunables,
 };
 
 // TODO: should those be moved into wasmer::vm as well?
@@ -69,8 +76,8 @@ pub mod vm {
     //! The `vm` module re-exports wasmer-vm types.
 
     pub use wasmer_vm::{
-        Memory, MemoryError, MemoryStyle, Table, TableStyle, VMExtern, VMMemoryDefinition,
-        VMTableDefinition,
+        InstanceAllocator, InstancePool, Memory, MemoryError, MemoryStyle, Table, TableStyle,
+        VMExtern, VMMemoryDefinition, VMOffsets, VMTableDefinition,
     };
 }
 
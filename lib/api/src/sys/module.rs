@@ -8,11 +8,12 @@ use thiserror::Error;
 use wasmer_compiler::CompileError;
 #[cfg(feature = "wat")]
 use wasmer_compiler::WasmError;
-use wasmer_engine::RuntimeError;
-use wasmer_engine_universal::UniversalArtifact;
+use wasmer_engine::{DeserializeError, RuntimeError};
+use wasmer_engine_universal::{UniversalArtifact, UniversalEngine};
 use wasmer_types::InstanceConfig;
-use wasmer_vm::{InstanceHandle, Instantiatable, Resolver};
+use wasmer_vm::{InstanceHandle, InstanceSnapshot, Instantiatable, Resolver};
 
+/// An error while reading or compiling a WebAssembly module.
 #[derive(Error, Debug)]
 pub enum IoCompileError {
     /// An IO error
@@ -120,6 +121,33 @@ impl Module {
         Ok(module)
     }
 
+    /// Creates a new WebAssembly module from the binary read from `reader`.
+    ///
+    /// Opposed to [`Module::from_binary`], the functions of the module are
+    /// compiled while the rest of it is still being read, if the compiler
+    /// of the Store supports it.
+    #[cfg(feature = "compiler")]
+    pub fn from_reader(store: &Store, mut reader: impl io::Read) -> Result<Self, IoCompileError> {
+        let engine: &dyn wasmer_engine::Engine = &**store.engine();
+        let engine = engine
+            .downcast_ref::<wasmer_engine_universal::UniversalEngine>()
+            .ok_or(CompileError::EngineDowncast)?;
+        let mut compilation = engine.compile_universal_streaming(store.tunables())?;
+        let mut chunk = vec![0; 64 * 1024];
+        loop {
+            let read = match reader.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            compilation.push(&chunk[..read])?;
+        }
+        let executable = compilation.finish()?;
+        let artifact = engine.load_universal_executable(&executable)?;
+        Ok(Self::from_universal_artifact(store, Arc::new(artifact)))
+    }
+
     /// Creates a new WebAssembly module from a binary.
     ///
     /// Opposed to [`Module::new`], this function is not compatible with
@@ -157,7 +185,15 @@ impl Module {
 
     fn compile(store: &Store, binary: &[u8]) -> Result<Self, CompileError> {
         let executable = store.engine().compile(binary, store.tunables())?;
-        let artifact = store.engine().load(&*executable)?;
+        let artifact = store
+            .engine()
+            .load(&*executable, store.tunables())
+            .map_err(|e| match e {
+                DeserializeError::Compiler(e) => e,
+                // Only the checks of a serialized executable fail otherwise,
+                // and the executable was just compiled with these tunables.
+                e => CompileError::Codegen(e.to_string()),
+            })?;
         match artifact.downcast_arc::<UniversalArtifact>() {
             Ok(universal) => Ok(Self::from_universal_artifact(store, universal)),
             // We're are probably given an externally defined artifact type
@@ -167,6 +203,32 @@ impl Module {
         }
     }
 
+    /// Deserializes the module serialized in the file at `path`, as
+    /// written by [`Executable::serialize`](wasmer_engine::Executable::serialize).
+    ///
+    /// The file is mapped in memory and its code is used in place when
+    /// the host allows it, see
+    /// [`UniversalEngine::load_universal_executable_file`]. The module
+    /// has to be compiled for the target of the engine of `store`, with
+    /// the memory styles given by its tunables.
+    ///
+    /// # Safety
+    ///
+    /// The serialized module is not validated: the file must come from a
+    /// trusted source, like a compiled module cache, and must not be
+    /// modified while it is loaded.
+    pub unsafe fn deserialize_from_file(
+        store: &Store,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, DeserializeError> {
+        let engine: &dyn wasmer_engine::Engine = &**store.engine();
+        let engine = engine
+            .downcast_ref::<UniversalEngine>()
+            .ok_or(DeserializeError::Compiler(CompileError::EngineDowncast))?;
+        let artifact = engine.load_universal_executable_file(path, store.tunables())?;
+        Ok(Self::from_universal_artifact(store, Arc::new(artifact)))
+    }
+
     /// Make a Module from Artifact...
     pub fn from_universal_artifact(
         store: &Store,
@@ -182,6 +244,34 @@ impl Module {
         &self,
         resolver: &dyn Resolver,
         config: InstanceConfig,
+    ) -> Result<InstanceHandle, InstantiationError> {
+        self.instantiate_with(resolver, config, |instance_handle| unsafe {
+            instance_handle
+                .finish_instantiation()
+                .map_err(|t| InstantiationError::Start(RuntimeError::from_trap(t)))
+        })
+    }
+
+    /// Instantiate the module in the state of `snapshot`, without running
+    /// its initializers and its start function.
+    pub(crate) fn instantiate_from_snapshot(
+        &self,
+        resolver: &dyn Resolver,
+        config: InstanceConfig,
+        snapshot: &InstanceSnapshot,
+    ) -> Result<InstanceHandle, InstantiationError> {
+        self.instantiate_with(resolver, config, |instance_handle| unsafe {
+            instance_handle
+                .restore_snapshot(snapshot)
+                .map_err(InstantiationError::Snapshot)
+        })
+    }
+
+    fn instantiate_with(
+        &self,
+        resolver: &dyn Resolver,
+        config: InstanceConfig,
+        finish: impl FnOnce(&InstanceHandle) -> Result<(), InstantiationError>,
     ) -> Result<InstanceHandle, InstantiationError> {
         unsafe {
             let instance_handle = Arc::clone(&self.artifact).instantiate(
@@ -196,9 +286,7 @@ impl Module {
             // of this steps traps, we still need to keep the instance alive
             // as some of the Instance elements may have placed in other
             // instance tables.
-            instance_handle
-                .finish_instantiation()
-                .map_err(|t| InstantiationError::Start(RuntimeError::from_trap(t)))?;
+            finish(&instance_handle)?;
 
             Ok(instance_handle)
         }
@@ -208,6 +296,11 @@ impl Module {
     pub fn store(&self) -> &Store {
         &self.store
     }
+
+    /// Returns the compiled contents of the module.
+    pub fn artifact(&self) -> &Arc<UniversalArtifact> {
+        &self.artifact
+    }
 }
 
 impl fmt::Debug for Module {
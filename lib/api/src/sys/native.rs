@@ -10,6 +10,7 @@
 use std::marker::PhantomData;
 
 use crate::sys::externals::function::{DynamicFunction, VMDynamicFunction};
+use crate::sys::fiber::CallAsync;
 use crate::sys::{FromToNativeWasmType, Function, RuntimeError, Store, WasmTypeList};
 use std::panic::{catch_unwind, AssertUnwindSafe};
 use wasmer_types::NativeWasmType;
@@ -220,6 +221,19 @@ macro_rules! impl_native_traits {
                 }
             }
 
+            /// Call the typed func asynchronously, on a fiber with a stack
+            /// of its own.
+            ///
+            /// The returned future is pending while an asynchronous host
+            /// function called by the guest, created with
+            /// [`Function::new_async`], waits on its own future.
+            pub fn call_async<'a>(&'a self, $( $x: $x, )* ) -> CallAsync<'a, Rets>
+            where
+                $( $x: 'a, )*
+                Rets: 'a,
+            {
+                CallAsync::new(move || self.call($( $x, )*))
+            }
         }
 
         #[allow(unused_parens)]
@@ -93,6 +93,100 @@ fn align_pointer(ptr: usize, align: usize) -> usize {
     ptr & !(align - 1)
 }
 
+/// Dereference the `T` at `offset` in `memory`, if it is in bounds.
+fn deref_item<T: Copy + ValueType>(memory: &Memory, offset: u64) -> Option<WasmCell<'_, T>> {
+    let item_size = mem::size_of::<T>() as u64;
+    if offset
+        .checked_add(item_size)
+        .map_or(true, |end| end > memory.data_size())
+        || item_size == 0
+    {
+        return None;
+    }
+    unsafe {
+        let cell_ptr = align_pointer(
+            memory.data_ptr().add(offset as usize) as usize,
+            mem::align_of::<T>(),
+        ) as *const Cell<T>;
+        Some(WasmCell::new(&*cell_ptr))
+    }
+}
+
+/// Dereference the `length` items of the array at `offset` in `memory`,
+/// starting at `index`, if they are in bounds.
+fn deref_array<T: Copy + ValueType>(
+    memory: &Memory,
+    offset: u64,
+    index: u64,
+    length: u64,
+) -> Option<Vec<WasmCell<'_, T>>> {
+    // gets the size of the item in the array with padding added such that
+    // for any index, we will always result an aligned memory access
+    let item_size = mem::size_of::<T>() as u64;
+    let slice_full_len = index.checked_add(length)?;
+    let memory_size = memory.data_size();
+
+    if slice_full_len
+        .checked_mul(item_size)
+        .and_then(|len| offset.checked_add(len))
+        .map_or(true, |end| end > memory_size)
+        || offset >= memory_size
+        || item_size == 0
+    {
+        return None;
+    }
+    let cell_ptrs = unsafe {
+        let cell_ptr = align_pointer(
+            memory.data_ptr().add(offset as usize) as usize,
+            mem::align_of::<T>(),
+        ) as *const Cell<T>;
+        &std::slice::from_raw_parts(cell_ptr, slice_full_len as usize)
+            [index as usize..slice_full_len as usize]
+    };
+
+    let wasm_cells = cell_ptrs
+        .iter()
+        .map(|ptr| WasmCell::new(ptr))
+        .collect::<Vec<_>>();
+    Some(wasm_cells)
+}
+
+/// Get the `str_len` bytes at `offset` in `memory`, if they are in bounds.
+///
+/// # Safety
+/// See [`WasmPtr::get_utf8_str`].
+unsafe fn get_bytes(memory: &Memory, offset: u64, str_len: u64) -> Option<&[u8]> {
+    let memory_size = memory.data_size();
+
+    if offset
+        .checked_add(str_len)
+        .map_or(true, |end| end > memory_size)
+        || offset >= memory_size
+    {
+        return None;
+    }
+    let ptr = memory.data_ptr().add(offset as usize) as *const u8;
+    Some(std::slice::from_raw_parts(ptr, str_len as usize))
+}
+
+/// Get the length of the nul-terminated string at `offset` in `memory`.
+fn nul_terminated_len(memory: &Memory, offset: u64) -> Option<u64> {
+    let memory_size = memory.data_size();
+    if offset > memory_size {
+        return None;
+    }
+    let bytes = unsafe {
+        std::slice::from_raw_parts(
+            memory.data_ptr().add(offset as usize) as *const u8,
+            (memory_size - offset) as usize,
+        )
+    };
+    bytes
+        .iter()
+        .position(|byte| *byte == 0)
+        .map(|length| length as u64)
+}
+
 /// Methods for `WasmPtr`s to data that can be dereferenced, namely to types
 /// that implement [`ValueType`], meaning that they're valid for all possible
 /// bit patterns.
@@ -105,18 +199,7 @@ impl<T: Copy + ValueType> WasmPtr<T, Item> {
     /// This invariant will be enforced in the future.
     #[inline]
     pub fn deref<'a>(self, memory: &'a Memory) -> Option<WasmCell<'a, T>> {
-        if (self.offset as usize) + mem::size_of::<T>() > memory.size().bytes().0
-            || mem::size_of::<T>() == 0
-        {
-            return None;
-        }
-        unsafe {
-            let cell_ptr = align_pointer(
-                memory.view::<u8>().as_ptr().add(self.offset as usize) as usize,
-                mem::align_of::<T>(),
-            ) as *const Cell<T>;
-            Some(WasmCell::new(&*cell_ptr))
-        }
+        deref_item(memory, self.offset as u64)
     }
 }
 
@@ -137,39 +220,11 @@ impl<T: Copy + ValueType> WasmPtr<T, Array> {
         index: u32,
         length: u32,
     ) -> Option<Vec<WasmCell<'a, T>>> {
-        // gets the size of the item in the array with padding added such that
-        // for any index, we will always result an aligned memory access
-        let item_size = mem::size_of::<T>();
-        let slice_full_len = index as usize + length as usize;
-        let memory_size = memory.size().bytes().0;
-
-        if (self.offset as usize) + (item_size * slice_full_len) > memory_size
-            || (self.offset as usize) >= memory_size
-            || item_size == 0
-        {
-            return None;
-        }
-        let cell_ptrs = unsafe {
-            let cell_ptr = align_pointer(
-                memory.view::<u8>().as_ptr().add(self.offset as usize) as usize,
-                mem::align_of::<T>(),
-            ) as *const Cell<T>;
-            &std::slice::from_raw_parts(cell_ptr, slice_full_len)[index as usize..slice_full_len]
-        };
-
-        let wasm_cells = cell_ptrs
-            .iter()
-            .map(|ptr| WasmCell::new(ptr))
-            .collect::<Vec<_>>();
-        Some(wasm_cells)
+        deref_array(memory, self.offset as u64, index as u64, length as u64)
     }
 
     /// Get a UTF-8 string from the `WasmPtr` with the given length.
     ///
-    /// Note that . The
-    /// underlying data can be mutated if the Wasm is allowed to execute or
-    /// an aliasing `WasmPtr` is used to mutate memory.
-    ///
     /// # Safety
     /// This method returns a reference to Wasm linear memory. The underlying
     /// data can be mutated if the Wasm is allowed to execute or an aliasing
@@ -182,41 +237,16 @@ impl<T: Copy + ValueType> WasmPtr<T, Array> {
     /// Additionally, if `memory` is dynamic, the caller must also ensure that `memory`
     /// is not grown while the reference is held.
     pub unsafe fn get_utf8_str<'a>(self, memory: &'a Memory, str_len: u32) -> Option<&'a str> {
-        let memory_size = memory.size().bytes().0;
-
-        if self.offset as usize + str_len as usize > memory.size().bytes().0
-            || self.offset as usize >= memory_size
-        {
-            return None;
-        }
-        let ptr = memory.view::<u8>().as_ptr().add(self.offset as usize) as *const u8;
-        let slice: &[u8] = std::slice::from_raw_parts(ptr, str_len as usize);
-        std::str::from_utf8(slice).ok()
+        get_bytes(memory, self.offset as u64, str_len as u64)
+            .and_then(|bytes| std::str::from_utf8(bytes).ok())
     }
 
     /// Get a UTF-8 `String` from the `WasmPtr` with the given length.
     ///
     /// an aliasing `WasmPtr` is used to mutate memory.
     pub fn get_utf8_string(self, memory: &Memory, str_len: u32) -> Option<String> {
-        let memory_size = memory.size().bytes().0;
-        if self.offset as usize + str_len as usize > memory.size().bytes().0
-            || self.offset as usize >= memory_size
-        {
-            return None;
-        }
-
-        // TODO: benchmark the internals of this function: there is likely room for
-        // micro-optimization here and this may be a fairly common function in user code.
-        let view = memory.view::<u8>();
-
-        let mut vec: Vec<u8> = Vec::with_capacity(str_len as usize);
-        let base = self.offset as usize;
-        for i in 0..(str_len as usize) {
-            let byte = view[base + i].get();
-            vec.push(byte);
-        }
-
-        String::from_utf8(vec).ok()
+        let bytes = unsafe { get_bytes(memory, self.offset as u64, str_len as u64) }?;
+        String::from_utf8(bytes.to_vec()).ok()
     }
 
     /// Get a UTF-8 string from the `WasmPtr`, where the string is nul-terminated.
@@ -228,10 +258,7 @@ impl<T: Copy + ValueType> WasmPtr<T, Array> {
     /// This method behaves similarly to [`WasmPtr::get_utf8_str`], all safety invariants on
     /// that method must also be upheld here.
     pub unsafe fn get_utf8_str_with_nul<'a>(self, memory: &'a Memory) -> Option<&'a str> {
-        memory.view::<u8>()[(self.offset as usize)..]
-            .iter()
-            .map(|cell| cell.get())
-            .position(|byte| byte == 0)
+        nul_terminated_len(memory, self.offset as u64)
             .and_then(|length| self.get_utf8_str(memory, length as u32))
     }
 
@@ -291,6 +318,148 @@ impl<T: Copy, Ty> fmt::Debug for WasmPtr<T, Ty> {
     }
 }
 
+/// A zero-cost type that represents a pointer to something in the Wasm
+/// linear memory of a memory indexed with 64-bit addresses.
+///
+/// This is the counterpart of [`WasmPtr`] for 64-bit memories: its offset
+/// is a `u64`, and it is passed to and from Wasm as an `i64`.
+/// ```
+/// # use wasmer::Memory;
+/// # use wasmer::WasmPtr64;
+/// pub fn host_import(memory: Memory, ptr: WasmPtr64<u32>) {
+///     let derefed_ptr = ptr.deref(&memory).expect("pointer in bounds");
+///     derefed_ptr.set(derefed_ptr.get() + 1);
+/// }
+/// ```
+#[repr(transparent)]
+pub struct WasmPtr64<T: Copy, Ty = Item> {
+    offset: u64,
+    _phantom: PhantomData<(T, Ty)>,
+}
+
+/// Methods relevant to all types of `WasmPtr64`.
+impl<T: Copy, Ty> WasmPtr64<T, Ty> {
+    /// Create a new `WasmPtr64` at the given offset.
+    #[inline]
+    pub fn new(offset: u64) -> Self {
+        Self {
+            offset,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Get the offset into Wasm linear memory for this `WasmPtr64`.
+    #[inline]
+    pub fn offset(self) -> u64 {
+        self.offset
+    }
+}
+
+impl<T: Copy + ValueType> WasmPtr64<T, Item> {
+    /// Dereference the `WasmPtr64` getting access to a `&Cell<T>` allowing for
+    /// reading and mutating of the inner value.
+    ///
+    /// This method is unsound if used with unsynchronized shared memory.
+    #[inline]
+    pub fn deref<'a>(self, memory: &'a Memory) -> Option<WasmCell<'a, T>> {
+        deref_item(memory, self.offset)
+    }
+}
+
+impl<T: Copy + ValueType> WasmPtr64<T, Array> {
+    /// Dereference the `WasmPtr64` getting access to a `&[Cell<T>]` allowing
+    /// for reading and mutating of the inner values.
+    ///
+    /// This method is unsound if used with unsynchronized shared memory.
+    #[inline]
+    pub fn deref<'a>(
+        self,
+        memory: &'a Memory,
+        index: u64,
+        length: u64,
+    ) -> Option<Vec<WasmCell<'a, T>>> {
+        deref_array(memory, self.offset, index, length)
+    }
+
+    /// Get a UTF-8 string from the `WasmPtr64` with the given length.
+    ///
+    /// # Safety
+    /// This method behaves similarly to [`WasmPtr::get_utf8_str`], all safety
+    /// invariants on that method must also be upheld here.
+    pub unsafe fn get_utf8_str<'a>(self, memory: &'a Memory, str_len: u64) -> Option<&'a str> {
+        get_bytes(memory, self.offset, str_len).and_then(|bytes| std::str::from_utf8(bytes).ok())
+    }
+
+    /// Get a UTF-8 `String` from the `WasmPtr64` with the given length.
+    pub fn get_utf8_string(self, memory: &Memory, str_len: u64) -> Option<String> {
+        let bytes = unsafe { get_bytes(memory, self.offset, str_len) }?;
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+
+    /// Get a UTF-8 string from the `WasmPtr64`, where the string is
+    /// nul-terminated.
+    ///
+    /// # Safety
+    /// This method behaves similarly to [`WasmPtr::get_utf8_str`], all safety
+    /// invariants on that method must also be upheld here.
+    pub unsafe fn get_utf8_str_with_nul<'a>(self, memory: &'a Memory) -> Option<&'a str> {
+        nul_terminated_len(memory, self.offset).and_then(|length| self.get_utf8_str(memory, length))
+    }
+
+    /// Get a UTF-8 `String` from the `WasmPtr64`, where the string is
+    /// nul-terminated.
+    pub fn get_utf8_string_with_nul(self, memory: &Memory) -> Option<String> {
+        unsafe { self.get_utf8_str_with_nul(memory) }.map(|s| s.to_owned())
+    }
+}
+
+unsafe impl<T: Copy, Ty> FromToNativeWasmType for WasmPtr64<T, Ty> {
+    type Native = i64;
+
+    fn to_native(self) -> Self::Native {
+        self.offset as i64
+    }
+    fn from_native(n: Self::Native) -> Self {
+        Self {
+            offset: n as u64,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+unsafe impl<T: Copy, Ty> ValueType for WasmPtr64<T, Ty> {}
+
+impl<T: Copy, Ty> Clone for WasmPtr64<T, Ty> {
+    fn clone(&self) -> Self {
+        Self {
+            offset: self.offset,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: Copy, Ty> Copy for WasmPtr64<T, Ty> {}
+
+impl<T: Copy, Ty> PartialEq for WasmPtr64<T, Ty> {
+    fn eq(&self, other: &Self) -> bool {
+        self.offset == other.offset
+    }
+}
+
+impl<T: Copy, Ty> Eq for WasmPtr64<T, Ty> {}
+
+impl<T: Copy, Ty> fmt::Debug for WasmPtr64<T, Ty> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "WasmPtr64(offset: {}, pointer: {:#x}, align: {})",
+            self.offset,
+            self.offset,
+            mem::align_of::<T>()
+        )
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -368,4 +537,27 @@ mod test {
             assert!(oob_end_array_ptr.deref(&memory, 1, 0).is_none());
         }
     }
+
+    /// Ensure that 64-bit pointers are bounds checked without overflowing.
+    #[test]
+    fn wasm_ptr64_memory_bounds_checks_hold() {
+        let store = Store::default();
+        let memory = Memory::new(&store, MemoryType::new64(1, Some(1), false)).unwrap();
+        let last_valid_address_for_u32 = memory.data_size() - 4;
+
+        let ptr: WasmPtr64<u32> = WasmPtr64::new(last_valid_address_for_u32);
+        ptr.deref(&memory).unwrap().set(42);
+        assert_eq!(ptr.deref(&memory).unwrap().get(), 42);
+        assert!(WasmPtr64::<u32>::new(last_valid_address_for_u32 + 1)
+            .deref(&memory)
+            .is_none());
+        assert!(WasmPtr64::<u32>::new(u64::MAX - 1).deref(&memory).is_none());
+
+        let array: WasmPtr64<u32, Array> = WasmPtr64::new(0);
+        assert!(array.deref(&memory, 0, memory.data_size() / 4).is_some());
+        assert!(array.deref(&memory, 1, memory.data_size() / 4).is_none());
+        assert!(array.deref(&memory, u64::MAX, 1).is_none());
+        assert!(array.get_utf8_string(&memory, u64::MAX).is_none());
+        assert_eq!(array.get_utf8_string_with_nul(&memory).unwrap(), "");
+    }
 }
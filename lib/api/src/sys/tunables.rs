@@ -5,8 +5,8 @@ use target_lexicon::PointerWidth;
 use wasmer_compiler::Target;
 use wasmer_vm::MemoryError;
 use wasmer_vm::{
-    LinearMemory, LinearTable, Memory, MemoryStyle, Table, TableStyle, Tunables,
-    VMMemoryDefinition, VMTableDefinition,
+    InstanceAllocation, InstancePool, LinearMemory, LinearTable, Memory, MemoryStyle,
+    PoolingLimits, Table, TableStyle, Tunables, VMMemoryDefinition, VMOffsets, VMTableDefinition,
 };
 
 /// Tunable parameters for WebAssembly compilation.
@@ -72,8 +72,10 @@ impl Tunables for BaseTunables {
         // tunables make it static.
         //
         // If the module doesn't declare an explicit maximum treat it as 4GiB.
+        // Memories indexed with 64-bit addresses are always dynamic, as guard
+        // pages can't cover the whole range of their addresses.
         let maximum = memory.maximum.unwrap_or_else(Pages::max_value);
-        if maximum <= self.static_memory_bound {
+        if !memory.memory64 && maximum <= self.static_memory_bound {
             MemoryStyle::Static {
                 // Bound can be larger than the maximum for performance reasons
                 bound: self.static_memory_bound,
@@ -146,6 +148,105 @@ impl Tunables for BaseTunables {
     }
 }
 
+/// Tunable parameters that allocate instances, and their memories and
+/// tables, from an [`InstancePool`] instead of reserving fresh memory for
+/// each instantiation.
+///
+/// Memories and tables owned by the host are still created by the
+/// wrapped tunables. Instantiation fails with an
+/// [`InstantiationError`](crate::InstantiationError) when a module
+/// exceeds the [`PoolingLimits`] or when the pool is exhausted.
+#[derive(Clone)]
+pub struct PoolingTunables<T: Tunables> {
+    base: T,
+    pool: Arc<InstancePool>,
+}
+
+impl<T: Tunables> PoolingTunables<T> {
+    /// Reserve a new pool with the given limits, wrapping `base`.
+    pub fn new(base: T, limits: PoolingLimits) -> Result<Self, String> {
+        Ok(Self {
+            base,
+            pool: Arc::new(InstancePool::new(limits)?),
+        })
+    }
+
+    /// Get the pool instances are allocated from.
+    pub fn pool(&self) -> &Arc<InstancePool> {
+        &self.pool
+    }
+}
+
+impl<T: Tunables> Tunables for PoolingTunables<T> {
+    /// Get the `MemoryStyle` of the pool: all memories are static and
+    /// bounded by the pool limits.
+    fn memory_style(&self, _memory: &MemoryType) -> MemoryStyle {
+        self.pool.memory_style()
+    }
+
+    /// Get a [`TableStyle`] for the provided [`TableType`].
+    ///
+    /// Delegated to base.
+    fn table_style(&self, table: &TableType) -> TableStyle {
+        self.base.table_style(table)
+    }
+
+    /// Create a memory owned by the host given a [`MemoryType`] and a [`MemoryStyle`].
+    ///
+    /// Delegated to base.
+    fn create_host_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+    ) -> Result<Arc<dyn Memory>, MemoryError> {
+        self.base.create_host_memory(ty, style)
+    }
+
+    /// Create a memory owned by the VM in a memory slot of the pool.
+    ///
+    /// # Safety
+    /// - `vm_definition_location` must point to a valid, owned `VMMemoryDefinition`,
+    ///   for example in `VMContext`.
+    unsafe fn create_vm_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<Arc<dyn Memory>, MemoryError> {
+        self.pool.create_memory(ty, style, vm_definition_location)
+    }
+
+    /// Create a table owned by the host given a [`TableType`] and a [`TableStyle`].
+    ///
+    /// Delegated to base.
+    fn create_host_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+    ) -> Result<Arc<dyn Table>, String> {
+        self.base.create_host_table(ty, style)
+    }
+
+    /// Create a table owned by the VM in a table slot of the pool.
+    ///
+    /// # Safety
+    /// - `vm_definition_location` must point to a valid, owned `VMTableDefinition`,
+    ///   for example in `VMContext`.
+    unsafe fn create_vm_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<Arc<dyn Table>, String> {
+        self.pool.create_table(ty, style, vm_definition_location)
+    }
+
+    /// Allocate the data of an instance in an instance slot of the pool.
+    fn allocate_instance(&self, offsets: VMOffsets) -> Result<InstanceAllocation, String> {
+        self.pool.allocate_instance(offsets)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -3,7 +3,7 @@ use crate::sys::store::{Store, StoreObject};
 use crate::sys::RuntimeError;
 use wasmer_types::Value;
 pub use wasmer_types::{
-    ExportType, ExternType, FunctionType, GlobalType, MemoryType, Mutability, TableType,
+    ExportType, ExternType, FunctionType, GlobalType, MemoryType, Mutability, TableType, TagType,
     Type as ValType,
 };
 use wasmer_vm::VMFuncRef;
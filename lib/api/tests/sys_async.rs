@@ -0,0 +1,167 @@
+#[cfg(feature = "sys")]
+mod sys {
+    use anyhow::Result;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+    use wasmer::*;
+
+    /// Counts the wake-ups of the futures it is given to.
+    #[derive(Default)]
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// A single-threaded executor: polls `future` until it completes, and
+    /// returns its output with the number of times it was pending.
+    fn block_on<F: Future>(future: F) -> (F::Output, usize) {
+        let waker = Arc::new(CountingWaker::default());
+        let mut pending = 0;
+        let mut future = Box::pin(future);
+        loop {
+            let cx_waker = Waker::from(waker.clone());
+            let mut cx = Context::from_waker(&cx_waker);
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => {
+                    assert_eq!(waker.0.load(Ordering::SeqCst), pending);
+                    return (output, pending);
+                }
+                Poll::Pending => pending += 1,
+            }
+        }
+    }
+
+    /// A future which is pending `ticks` times, waking its task each time.
+    struct Ticks {
+        ticks: u32,
+        dropped: Arc<AtomicUsize>,
+    }
+
+    impl Future for Ticks {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.ticks == 0 {
+                return Poll::Ready(());
+            }
+            self.ticks -= 1;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    impl Drop for Ticks {
+        fn drop(&mut self) {
+            self.dropped.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn instance(store: &Store, dropped: Arc<AtomicUsize>) -> Result<Instance> {
+        let module = Module::new(
+            store,
+            r#"
+    (module
+      (import "host" "wait" (func $wait (param i32) (result i32)))
+      (func (export "run") (param i32) (result i32)
+        (i32.add (call $wait (local.get 0)) (i32.const 1)))
+      (func (export "run_then_trap") (param i32)
+        (drop (call $wait (local.get 0)))
+        unreachable))
+"#,
+        )?;
+        let signature = FunctionType::new(vec![Type::I32], vec![Type::I32]);
+        let wait = Function::new_async(store, &signature, move |args| {
+            let ticks = args[0].unwrap_i32();
+            let future = Ticks {
+                ticks: ticks as u32,
+                dropped: dropped.clone(),
+            };
+            async move {
+                future.await;
+                Ok(vec![Value::I32(ticks * 10)])
+            }
+        });
+        let import_object = imports! {
+            "host" => {
+                "wait" => wait,
+            },
+        };
+        Ok(Instance::new(&module, &import_object)?)
+    }
+
+    #[test]
+    fn async_host_functions_suspend_the_guest() -> Result<()> {
+        let store = Store::default();
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let instance = instance(&store, dropped.clone())?;
+        let run = instance.get_native_function::<i32, i32>("run")?;
+
+        let (result, pending) = block_on(run.call_async(3));
+        assert_eq!(result?, 31);
+        assert_eq!(pending, 3);
+
+        let (result, pending) = block_on(run.call_async(0));
+        assert_eq!(result?, 1);
+        assert_eq!(pending, 0);
+        assert_eq!(dropped.load(Ordering::SeqCst), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn traps_after_resuming_are_caught() -> Result<()> {
+        let store = Store::default();
+        let instance = instance(&store, Arc::new(AtomicUsize::new(0)))?;
+        let run_then_trap = instance.get_native_function::<i32, ()>("run_then_trap")?;
+
+        let (result, pending) = block_on(run_then_trap.call_async(2));
+        assert_eq!(pending, 2);
+        let error = result.unwrap_err();
+        assert_eq!(error.message(), "unreachable");
+
+        // The trap handling state of the thread is left intact.
+        let run = instance.get_native_function::<i32, i32>("run")?;
+        assert_eq!(block_on(run.call_async(1)).0?, 11);
+
+        Ok(())
+    }
+
+    #[test]
+    fn dropping_the_future_cancels_the_call() -> Result<()> {
+        let store = Store::default();
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let instance = instance(&store, dropped.clone())?;
+        let run = instance.get_native_function::<i32, i32>("run")?;
+
+        let waker = Waker::from(Arc::new(CountingWaker::default()));
+        let mut cx = Context::from_waker(&waker);
+        let mut call = Box::pin(run.call_async(5));
+        assert!(call.as_mut().poll(&mut cx).is_pending());
+        assert!(call.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(dropped.load(Ordering::SeqCst), 0);
+        drop(call);
+        assert_eq!(dropped.load(Ordering::SeqCst), 1);
+
+        assert_eq!(block_on(run.call_async(1)).0?, 11);
+
+        Ok(())
+    }
+
+    #[test]
+    fn async_host_functions_require_call_async() -> Result<()> {
+        let store = Store::default();
+        let instance = instance(&store, Arc::new(AtomicUsize::new(0)))?;
+        let run = instance.get_native_function::<i32, i32>("run")?;
+
+        let error = run.call(1).unwrap_err();
+        assert!(error.message().contains("call_async"));
+
+        Ok(())
+    }
+}
@@ -143,6 +143,7 @@ mod sys {
             shared: false,
             minimum: Pages(0),
             maximum: Some(Pages(10)),
+            memory64: false,
         };
         let memory = Memory::new(&store, memory_type)?;
         assert_eq!(memory.size(), Pages(0));
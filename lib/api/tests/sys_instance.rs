@@ -40,6 +40,158 @@ mod sys {
         Ok(())
     }
 
+    #[test]
+    fn pooled_instances_are_limited_and_recycled() -> Result<()> {
+        let engine = Store::default().engine().clone();
+        let tunables = PoolingTunables::new(
+            BaseTunables::for_target(engine.target()),
+            PoolingLimits {
+                instances: 2,
+                memory_pages: Pages(2),
+                ..Default::default()
+            },
+        )
+        .map_err(anyhow::Error::msg)?;
+        let store = Store::new_with_tunables(&*engine, tunables);
+        let module = Module::new(
+            &store,
+            "
+    (module
+      (memory (export \"memory\") 1)
+      (func (export \"grow\") (param i32) (result i32)
+        local.get 0
+        memory.grow))
+",
+        )?;
+
+        let import_object = ImportObject::new();
+        let first = Instance::new(&module, &import_object)?;
+        let second = Instance::new(&module, &import_object)?;
+        assert!(matches!(
+            Instance::new(&module, &import_object),
+            Err(InstantiationError::Link(LinkError::Resource(_)))
+        ));
+
+        // Memories can only grow up to the pool limit.
+        let grow = first.get_native_function::<i32, i32>("grow")?;
+        assert_eq!(grow.call(1)?, 1);
+        assert_eq!(grow.call(1)?, -1);
+        first
+            .get_with_generics::<Memory, (), ()>("memory")?
+            .view::<u8>()[0]
+            .set(42);
+
+        // Dropping an instance gives its slots back, cleared.
+        drop(grow);
+        drop(first);
+        let third = Instance::new(&module, &import_object)?;
+        let memory: Memory = third.get_with_generics::<_, (), ()>("memory")?;
+        assert_eq!(memory.size(), Pages(1));
+        assert_eq!(memory.view::<u8>()[0].get(), 0);
+        drop(second);
+
+        // Modules must fit in the limits.
+        let module = Module::new(&store, "(module (memory 3))")?;
+        assert!(matches!(
+            Instance::new(&module, &import_object),
+            Err(InstantiationError::Link(LinkError::Resource(_)))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn data_segments_are_private_to_each_instance() -> Result<()> {
+        let engine = Store::default().engine().clone();
+        let pooling = PoolingTunables::new(
+            BaseTunables::for_target(engine.target()),
+            PoolingLimits {
+                instances: 2,
+                memory_pages: Pages(2),
+                ..Default::default()
+            },
+        )
+        .map_err(anyhow::Error::msg)?;
+        for store in vec![
+            Store::new(&*engine),
+            Store::new_with_tunables(&*engine, pooling),
+        ] {
+            let module = Module::new(
+                &store,
+                "
+    (module
+      (memory (export \"memory\") 1)
+      (data (i32.const 65530) \"hello\"))
+",
+            )?;
+            let import_object = ImportObject::new();
+            let memory = |instance: &Instance| -> Result<Vec<u8>> {
+                let memory: Memory = instance.get_with_generics::<_, (), ()>("memory")?;
+                Ok(memory.view::<u8>()[65530..65535]
+                    .iter()
+                    .map(|b| b.get())
+                    .collect())
+            };
+
+            let first = Instance::new(&module, &import_object)?;
+            let second = Instance::new(&module, &import_object)?;
+            first
+                .get_with_generics::<Memory, (), ()>("memory")?
+                .view::<u8>()[65530]
+                .set(b'j');
+            assert_eq!(memory(&first)?, b"jello");
+            assert_eq!(memory(&second)?, b"hello");
+
+            drop(first);
+            let third = Instance::new(&module, &import_object)?;
+            assert_eq!(memory(&third)?, b"hello");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn exports_link_other_instances() -> Result<()> {
+        let store = Store::default();
+        let library = Module::new(
+            &store,
+            "
+    (module
+      (memory (export \"memory\") 1)
+      (global (export \"base\") i32 (i32.const 40))
+      (func (export \"add\") (param i32 i32) (result i32)
+        local.get 0
+        local.get 1
+        i32.add))
+",
+        )?;
+        let library = Instance::new(&library, &ImportObject::new())?;
+        let exports = library.exports();
+        assert_eq!(exports.len(), 3);
+
+        let module = Module::new(
+            &store,
+            "
+    (module
+      (import \"lib\" \"add\" (func $add (param i32 i32) (result i32)))
+      (import \"lib\" \"base\" (global $base i32))
+      (import \"lib\" \"memory\" (memory 1))
+      (func (export \"run\") (result i32)
+        (i32.store8 (i32.const 0) (i32.const 7))
+        (call $add (global.get $base) (i32.const 2))))
+",
+        )?;
+        let mut import_object = ImportObject::new();
+        import_object.register("lib", exports);
+        let instance = Instance::new(&module, &import_object)?;
+        let run = instance.lookup_function("run").unwrap();
+        assert_eq!(run.call(&[])?.into_vec(), vec![Value::I32(42)]);
+        let memory: Memory = library.get_with_generics::<_, (), ()>("memory")?;
+        assert_eq!(memory.view::<u8>()[0].get(), 7);
+
+        Ok(())
+    }
+
     #[test]
     fn unit_native_function_env() -> Result<()> {
         let store = Store::default();
@@ -0,0 +1,147 @@
+//! Generates `wasmer.h`, the header of the C API, in `OUT_DIR`.
+//!
+//! The header is also checked in next to this file, for the packages
+//! and the programs that aren't built by Cargo: `make capi-header`
+//! updates it, by setting `WASMER_H_PATH` to where the header should
+//! be copied.
+
+use cbindgen::{Builder, Config, Language};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// The objects that share the layout of `wasm_extern_t`: cbindgen
+/// would declare them as aliases of `wasm_extern_t`, so they are
+/// declared as opaque types instead.
+const EXTERNS: &[&str] = &[
+    "wasm_func_t",
+    "wasm_global_t",
+    "wasm_memory_t",
+    "wasm_table_t",
+];
+
+/// The items no function refers to, which cbindgen would skip.
+const UNREFERENCED: &[&str] = &[
+    "wasm_name_t",
+    "wasm_valkind_enum",
+    "wasm_mutability_enum",
+    "wasm_externkind_enum",
+];
+
+/// The helpers of `wasm.h` that are defined in C.
+const TRAILER: &str = r#"
+#define wasm_name wasm_byte_vec
+#define wasm_name_new wasm_byte_vec_new
+#define wasm_name_new_empty wasm_byte_vec_new_empty
+#define wasm_name_new_uninitialized wasm_byte_vec_new_uninitialized
+#define wasm_name_copy wasm_byte_vec_copy
+#define wasm_name_delete wasm_byte_vec_delete
+
+static inline void wasm_name_new_from_string(wasm_name_t* out, const char* s) {
+  wasm_name_new(out, strlen(s), s);
+}
+
+static inline void wasm_name_new_from_string_nt(wasm_name_t* out, const char* s) {
+  wasm_name_new(out, strlen(s) + 1, s);
+}
+
+static inline wasm_valtype_t* wasm_valtype_new_i32(void) { return wasm_valtype_new(WASM_I32); }
+static inline wasm_valtype_t* wasm_valtype_new_i64(void) { return wasm_valtype_new(WASM_I64); }
+static inline wasm_valtype_t* wasm_valtype_new_f32(void) { return wasm_valtype_new(WASM_F32); }
+static inline wasm_valtype_t* wasm_valtype_new_f64(void) { return wasm_valtype_new(WASM_F64); }
+static inline wasm_valtype_t* wasm_valtype_new_anyref(void) { return wasm_valtype_new(WASM_ANYREF); }
+static inline wasm_valtype_t* wasm_valtype_new_funcref(void) { return wasm_valtype_new(WASM_FUNCREF); }
+
+static inline wasm_functype_t* wasm_functype_new_0_0(void) {
+  wasm_valtype_vec_t params, results;
+  wasm_valtype_vec_new_empty(&params);
+  wasm_valtype_vec_new_empty(&results);
+  return wasm_functype_new(&params, &results);
+}
+
+static inline wasm_functype_t* wasm_functype_new_1_0(wasm_valtype_t* p) {
+  wasm_valtype_t* ps[1] = {p};
+  wasm_valtype_vec_t params, results;
+  wasm_valtype_vec_new(&params, 1, ps);
+  wasm_valtype_vec_new_empty(&results);
+  return wasm_functype_new(&params, &results);
+}
+
+static inline wasm_functype_t* wasm_functype_new_0_1(wasm_valtype_t* r) {
+  wasm_valtype_t* rs[1] = {r};
+  wasm_valtype_vec_t params, results;
+  wasm_valtype_vec_new_empty(&params);
+  wasm_valtype_vec_new(&results, 1, rs);
+  return wasm_functype_new(&params, &results);
+}
+
+static inline wasm_functype_t* wasm_functype_new_1_1(wasm_valtype_t* p, wasm_valtype_t* r) {
+  wasm_valtype_t* ps[1] = {p};
+  wasm_valtype_t* rs[1] = {r};
+  wasm_valtype_vec_t params, results;
+  wasm_valtype_vec_new(&params, 1, ps);
+  wasm_valtype_vec_new(&results, 1, rs);
+  return wasm_functype_new(&params, &results);
+}
+
+static inline wasm_functype_t* wasm_functype_new_2_1(
+  wasm_valtype_t* p1, wasm_valtype_t* p2, wasm_valtype_t* r
+) {
+  wasm_valtype_t* ps[2] = {p1, p2};
+  wasm_valtype_t* rs[1] = {r};
+  wasm_valtype_vec_t params, results;
+  wasm_valtype_vec_new(&params, 2, ps);
+  wasm_valtype_vec_new(&results, 1, rs);
+  return wasm_functype_new(&params, &results);
+}
+
+#define WASM_EMPTY_VEC {0, NULL}
+#define WASM_ARRAY_VEC(array) {sizeof(array)/sizeof(*(array)), array}
+
+#define WASM_I32_VAL(x) {.kind = WASM_I32, .of = {.i32 = x}}
+#define WASM_I64_VAL(x) {.kind = WASM_I64, .of = {.i64 = x}}
+#define WASM_F32_VAL(x) {.kind = WASM_F32, .of = {.f32 = x}}
+#define WASM_F64_VAL(x) {.kind = WASM_F64, .of = {.f64 = x}}
+#define WASM_REF_VAL(x) {.kind = WASM_ANYREF, .of = {.ref = x}}
+#define WASM_INIT_VAL {.kind = WASM_ANYREF, .of = {.ref = NULL}}
+
+#endif /* WASMER_H */
+"#;
+
+fn main() {
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-env-changed=WASMER_H_PATH");
+
+    let mut config = Config {
+        language: Language::C,
+        // The trailer needs to be in the include guard too.
+        header: Some("#ifndef WASMER_H\n#define WASMER_H".to_string()),
+        cpp_compat: true,
+        usize_is_size_t: true,
+        sys_includes: vec!["string.h".to_string()],
+        trailer: Some(TRAILER.to_string()),
+        ..Config::default()
+    };
+    let mut after_includes = String::new();
+    for name in EXTERNS {
+        config.export.exclude.push(name.to_string());
+        after_includes.push_str(&format!("\ntypedef struct {0} {0};", name));
+    }
+    config.after_includes = Some(after_includes);
+    config
+        .export
+        .include
+        .extend(UNREFERENCED.iter().map(|name| name.to_string()));
+
+    Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("could not generate the header of the C API")
+        .write_to_file(out_dir.join("wasmer.h"));
+    if let Some(path) = env::var_os("WASMER_H_PATH") {
+        fs::copy(out_dir.join("wasmer.h"), path).expect("could not copy the header of the C API");
+    }
+}
@@ -0,0 +1,68 @@
+//! The last error of the current thread.
+//!
+//! The functions of `wasm.h` report a failure with a null pointer or
+//! `false`, without saying what went wrong. The message of the error is
+//! kept per thread, until it's read with [`wasmer_last_error_message`]:
+//!
+//! ```c
+//! wasm_module_t* module = wasm_module_new(store, &bytes);
+//!
+//! if (!module) {
+//!     int length = wasmer_last_error_length();
+//!     char* message = malloc(length);
+//!     wasmer_last_error_message(message, length);
+//!     fprintf(stderr, "%s\n", message);
+//!     free(message);
+//! }
+//! ```
+
+use std::cell::RefCell;
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<String>> = RefCell::new(None);
+}
+
+/// Records `error` as the last error of the current thread.
+pub(crate) fn update_last_error<E: ToString>(error: E) {
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(error.to_string()));
+}
+
+fn take_last_error() -> Option<String> {
+    LAST_ERROR.with(|last_error| last_error.borrow_mut().take())
+}
+
+/// Returns the length of the last error message of the current thread,
+/// including its trailing nul byte, or 0 if there's no error.
+#[no_mangle]
+pub extern "C" fn wasmer_last_error_length() -> c_int {
+    LAST_ERROR.with(|last_error| match *last_error.borrow() {
+        Some(ref message) => message.len() as c_int + 1,
+        None => 0,
+    })
+}
+
+/// Writes the last error message of the current thread in `buffer`, as
+/// a nul-terminated string, and clears it.
+///
+/// Returns the number of bytes written, 0 if there's no error, and -1
+/// if `buffer` is null or shorter than [`wasmer_last_error_length`].
+/// The error is kept in the latter case.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_last_error_message(buffer: *mut c_char, length: c_int) -> c_int {
+    if buffer.is_null() {
+        return -1;
+    }
+    let message = match take_last_error() {
+        Some(message) => message,
+        None => return 0,
+    };
+    if length < 0 || message.len() >= length as usize {
+        update_last_error(message);
+        return -1;
+    }
+    ptr::copy_nonoverlapping(message.as_ptr(), buffer as *mut u8, message.len());
+    *buffer.add(message.len()) = 0;
+    message.len() as c_int + 1
+}
@@ -0,0 +1,30 @@
+//! The `wasmer-c-api` crate implements the standard [`wasm.h`] C API of
+//! WebAssembly on top of Wasmer, so that programs written in C, or in
+//! any language with a C FFI, can embed the runtime.
+//!
+//! The header of the library, `wasmer.h`, is generated by `build.rs`.
+//! Besides the declarations of `wasm.h`, it contains a few Wasmer
+//! extensions, prefixed with `wasmer_`:
+//!
+//! * [`error`] reports the error behind a failed call, such as a
+//!   module that didn't compile,
+//! * [`wasm_c_api::instance`] instantiates a module with a
+//!   `wasmer_instance_config_t`, to meter the gas burnt by the
+//!   instance in a counter owned by the caller, and to limit its
+//!   stack.
+//!
+//! [`wasm_c_api::wasi`] provides the imports of the programs compiled
+//! to WASI, with functions prefixed with `wasi_`.
+//!
+//! [`wasm.h`]: https://github.com/WebAssembly/wasm-c-api/blob/master/include/wasm.h
+
+#![deny(trivial_numeric_casts, unused_extern_crates)]
+#![warn(unused_import_braces)]
+#![allow(
+    non_camel_case_types,
+    non_upper_case_globals,
+    clippy::missing_safety_doc
+)]
+
+pub mod error;
+pub mod wasm_c_api;
@@ -0,0 +1,42 @@
+//! Engines, which compile the modules.
+
+use std::sync::Arc;
+use wasmer::{Engine, Singlepass, Universal};
+
+/// The configuration of an engine.
+///
+/// The engines of the C API always compile with Singlepass into the
+/// universal engine, which has nothing to configure yet.
+#[derive(Default)]
+pub struct wasm_config_t {
+    _private: (),
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_config_new() -> Box<wasm_config_t> {
+    Box::new(wasm_config_t::default())
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_config_delete(_config: Option<Box<wasm_config_t>>) {}
+
+pub struct wasm_engine_t {
+    pub(crate) inner: Arc<dyn Engine + Send + Sync>,
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_engine_new() -> Box<wasm_engine_t> {
+    wasm_engine_new_with_config(wasm_config_new())
+}
+
+/// Creates an engine, taking the ownership of `config`.
+#[no_mangle]
+pub extern "C" fn wasm_engine_new_with_config(_config: Box<wasm_config_t>) -> Box<wasm_engine_t> {
+    let engine = Universal::new(Singlepass::default()).engine();
+    Box::new(wasm_engine_t {
+        inner: Arc::new(engine),
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_engine_delete(_engine: Option<Box<wasm_engine_t>>) {}
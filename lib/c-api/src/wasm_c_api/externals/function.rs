@@ -0,0 +1,186 @@
+use super::super::store::wasm_store_t;
+use super::super::trap::wasm_trap_t;
+use super::super::types::wasm_functype_t;
+use super::super::value::{wasm_val_t, wasm_val_vec_t};
+use super::wasm_extern_t;
+use crate::error::update_last_error;
+use std::convert::TryFrom;
+use std::ffi::c_void;
+use wasmer::{Function, FunctionType, RuntimeError, Val};
+
+#[derive(Clone)]
+#[repr(transparent)]
+pub struct wasm_func_t {
+    pub(crate) extern_: wasm_extern_t,
+}
+
+impl_extern_casts!(wasm_func_t, Function, Function);
+
+/// A host function. It writes the results of the call in the
+/// preallocated `results`, or returns a trap.
+pub type wasm_func_callback_t = Option<
+    unsafe extern "C" fn(
+        args: &wasm_val_vec_t,
+        results: &mut wasm_val_vec_t,
+    ) -> Option<Box<wasm_trap_t>>,
+>;
+
+/// A host function called with the `env` it was created with.
+pub type wasm_func_callback_with_env_t = Option<
+    unsafe extern "C" fn(
+        env: *mut c_void,
+        args: &wasm_val_vec_t,
+        results: &mut wasm_val_vec_t,
+    ) -> Option<Box<wasm_trap_t>>,
+>;
+
+/// The environment of a host function, finalized when the function is
+/// dropped.
+struct FunctionEnv {
+    env: *mut c_void,
+    finalizer: Option<unsafe extern "C" fn(env: *mut c_void)>,
+}
+
+// The environment is only ever used by the callback, and it's up to the
+// C code to synchronize the callbacks of its environments.
+unsafe impl Send for FunctionEnv {}
+unsafe impl Sync for FunctionEnv {}
+
+impl Drop for FunctionEnv {
+    fn drop(&mut self) {
+        if let Some(finalizer) = self.finalizer {
+            unsafe { finalizer(self.env) }
+        }
+    }
+}
+
+/// Calls `callback` with `args` converted to C values, and converts
+/// back the results it writes.
+fn call_host<F>(ty: &FunctionType, args: &[Val], callback: F) -> Result<Vec<Val>, RuntimeError>
+where
+    F: FnOnce(&wasm_val_vec_t, &mut wasm_val_vec_t) -> Option<Box<wasm_trap_t>>,
+{
+    let args = args
+        .iter()
+        .map(wasm_val_t::try_from)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(RuntimeError::new)?;
+    let args = wasm_val_vec_t::from_vec(args);
+    let mut results = wasm_val_vec_t::from_vec(vec![wasm_val_t::default(); ty.results().len()]);
+    if let Some(trap) = callback(&args, &mut results) {
+        return Err(trap.inner);
+    }
+    let results = results.to_vals().map_err(RuntimeError::new)?;
+    let types = results.iter().map(Val::ty).collect::<Vec<_>>();
+    if types != ty.results() {
+        return Err(RuntimeError::new(format!(
+            "the host function returned values of types {:?}, expected {:?}",
+            types,
+            ty.results()
+        )));
+    }
+    Ok(results)
+}
+
+fn non_null_callback<F>(callback: Option<F>) -> Option<F> {
+    if callback.is_none() {
+        update_last_error("the callback of the function is null");
+    }
+    callback
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_func_new(
+    store: &wasm_store_t,
+    functype: &wasm_functype_t,
+    callback: wasm_func_callback_t,
+) -> Option<Box<wasm_func_t>> {
+    let ty = functype.to_type()?;
+    let callback = non_null_callback(callback)?;
+    let inner_ty = ty.clone();
+    let function = Function::new(&store.inner, ty, move |args| {
+        call_host(&inner_ty, args, |args, results| unsafe {
+            callback(args, results)
+        })
+    });
+    Some(wasm_func_t::new(function))
+}
+
+/// Creates a host function called with `env`. `finalizer`, if not
+/// null, is called with `env` once the function is dropped.
+#[no_mangle]
+pub extern "C" fn wasm_func_new_with_env(
+    store: &wasm_store_t,
+    functype: &wasm_functype_t,
+    callback: wasm_func_callback_with_env_t,
+    env: *mut c_void,
+    finalizer: Option<unsafe extern "C" fn(env: *mut c_void)>,
+) -> Option<Box<wasm_func_t>> {
+    let ty = functype.to_type()?;
+    let callback = non_null_callback(callback)?;
+    let env = FunctionEnv { env, finalizer };
+    let inner_ty = ty.clone();
+    let function = Function::new(&store.inner, ty, move |args| {
+        call_host(&inner_ty, args, |args, results| unsafe {
+            callback(env.env, args, results)
+        })
+    });
+    Some(wasm_func_t::new(function))
+}
+
+/// Calls `func`, and writes its results in the preallocated `results`.
+///
+/// Returns null on success, or the trap that stopped the call.
+#[no_mangle]
+pub extern "C" fn wasm_func_call(
+    func: &wasm_func_t,
+    args: Option<&wasm_val_vec_t>,
+    results: Option<&mut wasm_val_vec_t>,
+) -> Option<Box<wasm_trap_t>> {
+    let call = || -> Result<(), RuntimeError> {
+        let args = match args {
+            Some(args) => args.to_vals().map_err(RuntimeError::new)?,
+            None => Vec::new(),
+        };
+        let values = func.inner().call(&args)?;
+        let results = match results {
+            Some(results) => results.as_mut_slice(),
+            None => &mut [],
+        };
+        if results.len() != values.len() {
+            return Err(RuntimeError::new(format!(
+                "the function returns {} values, but {} results were given",
+                values.len(),
+                results.len()
+            )));
+        }
+        for (result, value) in results.iter_mut().zip(values.iter()) {
+            *result = wasm_val_t::try_from(value).map_err(RuntimeError::new)?;
+        }
+        Ok(())
+    };
+    call().err().map(|error| Box::new(error.into()))
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_func_type(func: &wasm_func_t) -> Option<Box<wasm_functype_t>> {
+    wasm_functype_t::from_type(&func.inner().ty())
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_func_param_arity(func: &wasm_func_t) -> usize {
+    func.inner().param_arity()
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_func_result_arity(func: &wasm_func_t) -> usize {
+    func.inner().result_arity()
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_func_copy(func: &wasm_func_t) -> Box<wasm_func_t> {
+    Box::new(func.clone())
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_func_delete(_func: Option<Box<wasm_func_t>>) {}
@@ -0,0 +1,88 @@
+use super::super::store::wasm_store_t;
+use super::super::types::wasm_globaltype_t;
+use super::super::value::wasm_val_t;
+use super::wasm_extern_t;
+use crate::error::update_last_error;
+use std::convert::TryFrom;
+use std::ptr;
+use wasmer::{Global, Mutability, Val};
+
+#[derive(Clone)]
+#[repr(transparent)]
+pub struct wasm_global_t {
+    pub(crate) extern_: wasm_extern_t,
+}
+
+impl_extern_casts!(wasm_global_t, Global, Global);
+
+/// Creates a global of type `globaltype`, holding `value`.
+///
+/// Returns null if `value` doesn't have the type of the global.
+#[no_mangle]
+pub extern "C" fn wasm_global_new(
+    store: &wasm_store_t,
+    globaltype: &wasm_globaltype_t,
+    value: &wasm_val_t,
+) -> Option<Box<wasm_global_t>> {
+    let ty = globaltype.to_type();
+    let value = match Val::try_from(value) {
+        Ok(value) if value.ty() == ty.ty => value,
+        Ok(value) => {
+            update_last_error(format!(
+                "a global of type {} can't hold a value of type {}",
+                ty.ty,
+                value.ty()
+            ));
+            return None;
+        }
+        Err(error) => {
+            update_last_error(error);
+            return None;
+        }
+    };
+    let global = match ty.mutability {
+        Mutability::Const => Global::new(&store.inner, value),
+        Mutability::Var => Global::new_mut(&store.inner, value),
+    };
+    Some(wasm_global_t::new(global))
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_global_type(global: &wasm_global_t) -> Option<Box<wasm_globaltype_t>> {
+    wasm_globaltype_t::from_type(global.inner().ty())
+}
+
+/// Writes the value of `global` in `out`.
+#[no_mangle]
+pub unsafe extern "C" fn wasm_global_get(global: &wasm_global_t, out: *mut wasm_val_t) {
+    match wasm_val_t::try_from(&global.inner().get()) {
+        Ok(value) => ptr::write(out, value),
+        Err(error) => update_last_error(error),
+    }
+}
+
+/// Sets the value of a mutable global.
+///
+/// Returns false if the global is immutable, or if `value` doesn't have
+/// its type.
+#[no_mangle]
+pub extern "C" fn wasm_global_set(global: &wasm_global_t, value: &wasm_val_t) -> bool {
+    let result = Val::try_from(value)
+        .map_err(|error| error.to_string())
+        .and_then(|value| global.inner().set(value).map_err(|error| error.message()));
+    match result {
+        Ok(()) => true,
+        Err(error) => {
+            update_last_error(error);
+            false
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_global_copy(global: &wasm_global_t) -> Box<wasm_global_t> {
+    Box::new(global.clone())
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_global_delete(_global: Option<Box<wasm_global_t>>) {}
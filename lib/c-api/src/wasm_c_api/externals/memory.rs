@@ -0,0 +1,77 @@
+use super::super::store::wasm_store_t;
+use super::super::types::{byte_t, wasm_memorytype_t};
+use super::wasm_extern_t;
+use crate::error::update_last_error;
+use wasmer::{Memory, Pages};
+
+#[derive(Clone)]
+#[repr(transparent)]
+pub struct wasm_memory_t {
+    pub(crate) extern_: wasm_extern_t,
+}
+
+impl_extern_casts!(wasm_memory_t, Memory, Memory);
+
+pub type wasm_memory_pages_t = u32;
+
+/// The size of a page of memory, in bytes.
+pub const MEMORY_PAGE_SIZE: usize = 0x10000;
+
+#[no_mangle]
+pub extern "C" fn wasm_memory_new(
+    store: &wasm_store_t,
+    memorytype: &wasm_memorytype_t,
+) -> Option<Box<wasm_memory_t>> {
+    match Memory::new(&store.inner, memorytype.to_type()) {
+        Ok(memory) => Some(wasm_memory_t::new(memory)),
+        Err(error) => {
+            update_last_error(error);
+            None
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_memory_type(memory: &wasm_memory_t) -> Box<wasm_memorytype_t> {
+    wasm_memorytype_t::from_type(&memory.inner().ty())
+}
+
+/// Returns a pointer to the bytes of `memory`. The pointer is
+/// invalidated when the memory grows.
+#[no_mangle]
+pub extern "C" fn wasm_memory_data(memory: &wasm_memory_t) -> *mut byte_t {
+    memory.inner().data_ptr() as *mut byte_t
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_memory_data_size(memory: &wasm_memory_t) -> usize {
+    memory.inner().data_size() as usize
+}
+
+/// Returns the size of `memory`, in pages.
+#[no_mangle]
+pub extern "C" fn wasm_memory_size(memory: &wasm_memory_t) -> wasm_memory_pages_t {
+    memory.inner().size().0
+}
+
+/// Grows `memory` by `delta` pages.
+///
+/// Returns false if the memory can't grow that much.
+#[no_mangle]
+pub extern "C" fn wasm_memory_grow(memory: &wasm_memory_t, delta: wasm_memory_pages_t) -> bool {
+    match memory.inner().grow(Pages(delta)) {
+        Ok(_) => true,
+        Err(error) => {
+            update_last_error(error);
+            false
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_memory_copy(memory: &wasm_memory_t) -> Box<wasm_memory_t> {
+    Box::new(memory.clone())
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_memory_delete(_memory: Option<Box<wasm_memory_t>>) {}
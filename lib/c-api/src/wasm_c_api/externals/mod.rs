@@ -0,0 +1,237 @@
+//! Externs, the objects a module imports and exports.
+//!
+//! `wasm_func_t`, `wasm_global_t`, `wasm_memory_t` and `wasm_table_t`
+//! share the layout of `wasm_extern_t`, so that an extern converts to
+//! and from them in place, as `wasm.h` requires.
+
+/// Implements the conversions between `wasm_extern_t` and the extern
+/// wrapping an `Extern::$variant`.
+macro_rules! impl_extern_casts {
+    ($ty:ident, $variant:ident, $inner:ty) => {
+        impl $ty {
+            pub(crate) fn new(inner: $inner) -> Box<Self> {
+                Box::new(Self {
+                    extern_: $crate::wasm_c_api::externals::wasm_extern_t::new(inner),
+                })
+            }
+
+            pub(crate) fn inner(&self) -> &$inner {
+                match self.extern_.inner {
+                    wasmer::Extern::$variant(ref inner) => inner,
+                    _ => unreachable!(),
+                }
+            }
+
+            pub(crate) fn from_extern(
+                extern_: &$crate::wasm_c_api::externals::wasm_extern_t,
+            ) -> Option<&Self> {
+                match extern_.inner {
+                    // Sound, as `Self` is a transparent wrapper.
+                    wasmer::Extern::$variant(_) => Some(unsafe {
+                        &*(extern_ as *const $crate::wasm_c_api::externals::wasm_extern_t
+                            as *const Self)
+                    }),
+                    _ => None,
+                }
+            }
+
+            pub(crate) fn from_extern_mut(
+                extern_: &mut $crate::wasm_c_api::externals::wasm_extern_t,
+            ) -> Option<&mut Self> {
+                match extern_.inner {
+                    // Sound, as `Self` is a transparent wrapper.
+                    wasmer::Extern::$variant(_) => Some(unsafe {
+                        &mut *(extern_ as *mut $crate::wasm_c_api::externals::wasm_extern_t
+                            as *mut Self)
+                    }),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+mod function;
+mod global;
+mod memory;
+mod table;
+
+pub use self::function::*;
+pub use self::global::*;
+pub use self::memory::*;
+pub use self::table::*;
+
+use std::ptr;
+use wasmer::Extern;
+
+#[derive(Clone)]
+pub struct wasm_extern_t {
+    pub(crate) inner: Extern,
+}
+
+impl wasm_extern_t {
+    pub(crate) fn new(inner: impl Into<Extern>) -> Self {
+        Self {
+            inner: inner.into(),
+        }
+    }
+}
+
+pub type wasm_externkind_t = u8;
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum wasm_externkind_enum {
+    WASM_EXTERN_FUNC = 0,
+    WASM_EXTERN_GLOBAL = 1,
+    WASM_EXTERN_TABLE = 2,
+    WASM_EXTERN_MEMORY = 3,
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_extern_kind(extern_: &wasm_extern_t) -> wasm_externkind_t {
+    let kind = match extern_.inner {
+        Extern::Function(_) => wasm_externkind_enum::WASM_EXTERN_FUNC,
+        Extern::Global(_) => wasm_externkind_enum::WASM_EXTERN_GLOBAL,
+        Extern::Table(_) => wasm_externkind_enum::WASM_EXTERN_TABLE,
+        Extern::Memory(_) => wasm_externkind_enum::WASM_EXTERN_MEMORY,
+        Extern::Tag(_) => unreachable!("tags are not exposed to the C API"),
+    };
+    kind as wasm_externkind_t
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_extern_copy(extern_: &wasm_extern_t) -> Box<wasm_extern_t> {
+    Box::new(extern_.clone())
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_extern_delete(_extern: Option<Box<wasm_extern_t>>) {}
+
+#[no_mangle]
+pub extern "C" fn wasm_extern_as_func(extern_: &mut wasm_extern_t) -> Option<&mut wasm_func_t> {
+    wasm_func_t::from_extern_mut(extern_)
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_extern_as_func_const(extern_: &wasm_extern_t) -> Option<&wasm_func_t> {
+    wasm_func_t::from_extern(extern_)
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_extern_as_global(extern_: &mut wasm_extern_t) -> Option<&mut wasm_global_t> {
+    wasm_global_t::from_extern_mut(extern_)
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_extern_as_global_const(extern_: &wasm_extern_t) -> Option<&wasm_global_t> {
+    wasm_global_t::from_extern(extern_)
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_extern_as_memory(extern_: &mut wasm_extern_t) -> Option<&mut wasm_memory_t> {
+    wasm_memory_t::from_extern_mut(extern_)
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_extern_as_memory_const(extern_: &wasm_extern_t) -> Option<&wasm_memory_t> {
+    wasm_memory_t::from_extern(extern_)
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_extern_as_table(extern_: &mut wasm_extern_t) -> Option<&mut wasm_table_t> {
+    wasm_table_t::from_extern_mut(extern_)
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_extern_as_table_const(extern_: &wasm_extern_t) -> Option<&wasm_table_t> {
+    wasm_table_t::from_extern(extern_)
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_func_as_extern(func: &mut wasm_func_t) -> &mut wasm_extern_t {
+    &mut func.extern_
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_func_as_extern_const(func: &wasm_func_t) -> &wasm_extern_t {
+    &func.extern_
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_global_as_extern(global: &mut wasm_global_t) -> &mut wasm_extern_t {
+    &mut global.extern_
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_global_as_extern_const(global: &wasm_global_t) -> &wasm_extern_t {
+    &global.extern_
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_memory_as_extern(memory: &mut wasm_memory_t) -> &mut wasm_extern_t {
+    &mut memory.extern_
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_memory_as_extern_const(memory: &wasm_memory_t) -> &wasm_extern_t {
+    &memory.extern_
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_table_as_extern(table: &mut wasm_table_t) -> &mut wasm_extern_t {
+    &mut table.extern_
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_table_as_extern_const(table: &wasm_table_t) -> &wasm_extern_t {
+    &table.extern_
+}
+
+/// A vector of owned externs.
+#[repr(C)]
+pub struct wasm_extern_vec_t {
+    pub size: usize,
+    pub data: *mut Option<Box<wasm_extern_t>>,
+}
+
+impl_vec!(wasm_extern_vec_t, Option<Box<wasm_extern_t>>);
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_extern_vec_new_empty(out: *mut wasm_extern_vec_t) {
+    ptr::write(out, wasm_extern_vec_t::from_vec(Vec::new()));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_extern_vec_new_uninitialized(
+    out: *mut wasm_extern_vec_t,
+    size: usize,
+) {
+    ptr::write(out, wasm_extern_vec_t::from_vec(vec![None; size]));
+}
+
+/// Creates a vector of `size` externs, taking the ownership of the
+/// externs at `data`.
+#[no_mangle]
+pub unsafe extern "C" fn wasm_extern_vec_new(
+    out: *mut wasm_extern_vec_t,
+    size: usize,
+    data: *const Option<Box<wasm_extern_t>>,
+) {
+    ptr::write(out, wasm_extern_vec_t::from_raw(data, size));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_extern_vec_copy(
+    out: *mut wasm_extern_vec_t,
+    src: &wasm_extern_vec_t,
+) {
+    ptr::write(out, src.clone());
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_extern_vec_delete(vec: Option<&mut wasm_extern_vec_t>) {
+    if let Some(vec) = vec {
+        vec.take();
+    }
+}
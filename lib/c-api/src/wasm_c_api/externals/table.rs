@@ -0,0 +1,89 @@
+use super::super::store::wasm_store_t;
+use super::super::types::wasm_tabletype_t;
+use super::super::value::wasm_ref_t;
+use super::wasm_extern_t;
+use crate::error::update_last_error;
+use wasmer::{Table, Val, ValType};
+use wasmer_types::ExternRef;
+
+#[derive(Clone)]
+#[repr(transparent)]
+pub struct wasm_table_t {
+    pub(crate) extern_: wasm_extern_t,
+}
+
+impl_extern_casts!(wasm_table_t, Table, Table);
+
+pub type wasm_table_size_t = u32;
+
+/// Returns the value of `init` for a table of `element`s. Only null
+/// references are supported.
+fn init_value(element: ValType, init: Option<&wasm_ref_t>) -> Option<Val> {
+    if init.is_some() {
+        update_last_error("the C API only supports null references");
+        return None;
+    }
+    Some(match element {
+        ValType::FuncRef => Val::FuncRef(None),
+        _ => Val::ExternRef(ExternRef::null()),
+    })
+}
+
+/// Creates a table of type `tabletype`, with its elements set to
+/// `init`, which must be null.
+#[no_mangle]
+pub extern "C" fn wasm_table_new(
+    store: &wasm_store_t,
+    tabletype: &wasm_tabletype_t,
+    init: Option<&wasm_ref_t>,
+) -> Option<Box<wasm_table_t>> {
+    let ty = tabletype.to_type();
+    let init = init_value(ty.ty, init)?;
+    match Table::new(&store.inner, ty, init) {
+        Ok(table) => Some(wasm_table_t::new(table)),
+        Err(error) => {
+            update_last_error(error.message());
+            None
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_table_type(table: &wasm_table_t) -> Option<Box<wasm_tabletype_t>> {
+    wasm_tabletype_t::from_type(table.inner().ty())
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_table_size(table: &wasm_table_t) -> wasm_table_size_t {
+    table.inner().size()
+}
+
+/// Grows `table` by `delta` elements set to `init`, which must be null.
+///
+/// Returns false if the table can't grow that much.
+#[no_mangle]
+pub extern "C" fn wasm_table_grow(
+    table: &wasm_table_t,
+    delta: wasm_table_size_t,
+    init: Option<&wasm_ref_t>,
+) -> bool {
+    let init = match init_value(table.inner().ty().ty, init) {
+        Some(init) => init,
+        None => return false,
+    };
+    match table.inner().grow(delta, init) {
+        Ok(_) => true,
+        Err(error) => {
+            update_last_error(error.message());
+            false
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_table_copy(table: &wasm_table_t) -> Box<wasm_table_t> {
+    Box::new(table.clone())
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_table_delete(_table: Option<Box<wasm_table_t>>) {}
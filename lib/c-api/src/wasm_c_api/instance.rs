@@ -0,0 +1,192 @@
+//! Instances of modules, and the Wasmer extensions to configure them.
+//!
+//! An instance created with [`wasmer_instance_new_with_config`] meters
+//! the gas it burns in a `wasmer_gas_counter_t` owned by the caller:
+//!
+//! ```c
+//! wasmer_gas_counter_t counter = { .burnt_gas = 0, .gas_limit = 1000, .opcode_cost = 1 };
+//!
+//! wasmer_instance_config_t* config = wasmer_instance_config_new();
+//! wasmer_instance_config_set_gas_counter(config, &counter);
+//! wasmer_instance_config_set_stack_limit(config, 1024);
+//!
+//! wasm_trap_t* trap = NULL;
+//! wasm_instance_t* instance =
+//!     wasmer_instance_new_with_config(store, module, &imports, config, &trap);
+//! ```
+
+use super::externals::{wasm_extern_t, wasm_extern_vec_t};
+use super::module::wasm_module_t;
+use super::store::wasm_store_t;
+use super::trap::wasm_trap_t;
+use crate::error::update_last_error;
+use std::ffi::CStr;
+use std::mem;
+use std::os::raw::c_char;
+use std::ptr;
+use wasmer::{Export, Exportable, Extern, Instance, InstantiationError, Resolver, RuntimeError};
+use wasmer_types::{FastGasCounter, InstanceConfig};
+
+pub struct wasm_instance_t {
+    pub(crate) inner: Instance,
+}
+
+/// Resolves the imports of a module by their position, as `wasm.h`
+/// requires.
+struct ExternsResolver<'a> {
+    externs: &'a [Option<Box<wasm_extern_t>>],
+}
+
+impl Resolver for ExternsResolver<'_> {
+    fn resolve(&self, index: u32, _module: &str, _field: &str) -> Option<Export> {
+        self.externs
+            .get(index as usize)?
+            .as_ref()
+            .map(|extern_| extern_.inner.to_export())
+    }
+}
+
+fn instantiate(
+    module: &wasm_module_t,
+    imports: &wasm_extern_vec_t,
+    config: InstanceConfig,
+    trap: Option<&mut *mut wasm_trap_t>,
+) -> Option<Box<wasm_instance_t>> {
+    let resolver = ExternsResolver {
+        externs: imports.as_slice(),
+    };
+    let error = match Instance::new_with_config(&module.inner, config, &resolver) {
+        Ok(instance) => return Some(Box::new(wasm_instance_t { inner: instance })),
+        Err(error) => error,
+    };
+    let error = match error {
+        InstantiationError::Start(error) => error,
+        error => {
+            let message = error.to_string();
+            update_last_error(&message);
+            RuntimeError::new(message)
+        }
+    };
+    if let Some(trap) = trap {
+        *trap = Box::into_raw(Box::new(error.into()));
+    }
+    None
+}
+
+/// Instantiates `module`, with `imports` in the order of the imports
+/// of the module.
+///
+/// Returns null if the instantiation fails, and stores the reason in
+/// `*trap` if `trap` isn't null.
+#[no_mangle]
+pub extern "C" fn wasm_instance_new(
+    _store: &wasm_store_t,
+    module: &wasm_module_t,
+    imports: &wasm_extern_vec_t,
+    trap: Option<&mut *mut wasm_trap_t>,
+) -> Option<Box<wasm_instance_t>> {
+    instantiate(module, imports, InstanceConfig::default(), trap)
+}
+
+/// Writes the exports of `instance` in `out`, sorted by name.
+#[no_mangle]
+pub unsafe extern "C" fn wasm_instance_exports(
+    instance: &wasm_instance_t,
+    out: *mut wasm_extern_vec_t,
+) {
+    let externs = instance
+        .inner
+        .exports()
+        .iter()
+        .filter(|(_, extern_)| !matches!(extern_, Extern::Tag(_)))
+        .map(|(_, extern_)| Some(Box::new(wasm_extern_t::new(extern_.clone()))))
+        .collect();
+    ptr::write(out, wasm_extern_vec_t::from_vec(externs));
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_instance_delete(_instance: Option<Box<wasm_instance_t>>) {}
+
+/// Returns the export of `instance` named `name`, a nul-terminated
+/// string, or null if there's none.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_instance_get_export(
+    instance: &wasm_instance_t,
+    name: *const c_char,
+) -> Option<Box<wasm_extern_t>> {
+    let name = CStr::from_ptr(name).to_string_lossy();
+    match instance.inner.exports().get_extern(&name) {
+        Some(Extern::Tag(_)) | None => {
+            update_last_error(format!("the instance has no export named `{}`", name));
+            None
+        }
+        Some(extern_) => Some(Box::new(wasm_extern_t::new(extern_.clone()))),
+    }
+}
+
+/// A gas counter shared with the instances it's configured for.
+///
+/// Every instruction burns `opcode_cost`, and the instance traps once
+/// `burnt_gas` would exceed `gas_limit`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct wasmer_gas_counter_t {
+    pub burnt_gas: u64,
+    pub gas_limit: u64,
+    pub opcode_cost: u64,
+}
+
+// The instances write the counter through a `*mut FastGasCounter`.
+const _: [(); mem::size_of::<FastGasCounter>()] = [(); mem::size_of::<wasmer_gas_counter_t>()];
+
+/// The configuration of an instance.
+pub struct wasmer_instance_config_t {
+    inner: InstanceConfig,
+}
+
+/// Creates a configuration with an unlimited gas counter owned by the
+/// instance, and the default stack limit.
+#[no_mangle]
+pub extern "C" fn wasmer_instance_config_new() -> Box<wasmer_instance_config_t> {
+    Box::new(wasmer_instance_config_t {
+        inner: InstanceConfig::default(),
+    })
+}
+
+/// Makes the instances of `config` burn their gas in `counter`, which
+/// must outlive them.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_instance_config_set_gas_counter(
+    config: &mut wasmer_instance_config_t,
+    counter: *mut wasmer_gas_counter_t,
+) {
+    let inner = mem::replace(&mut config.inner, InstanceConfig::default());
+    config.inner = inner.with_counter(counter as *mut FastGasCounter);
+}
+
+/// Limits the stack of the instances of `config` to `stack_limit`
+/// slots of 8 bytes. Calls nested too deeply trap.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_instance_config_set_stack_limit(
+    config: &mut wasmer_instance_config_t,
+    stack_limit: i32,
+) {
+    let inner = mem::replace(&mut config.inner, InstanceConfig::default());
+    config.inner = inner.with_stack_limit(stack_limit);
+}
+
+#[no_mangle]
+pub extern "C" fn wasmer_instance_config_delete(_config: Option<Box<wasmer_instance_config_t>>) {}
+
+/// Like [`wasm_instance_new`], but configured with `config`, which can
+/// be reused for other instances.
+#[no_mangle]
+pub extern "C" fn wasmer_instance_new_with_config(
+    _store: &wasm_store_t,
+    module: &wasm_module_t,
+    imports: &wasm_extern_vec_t,
+    config: &wasmer_instance_config_t,
+    trap: Option<&mut *mut wasm_trap_t>,
+) -> Option<Box<wasm_instance_t>> {
+    instantiate(module, imports, config.inner.clone(), trap)
+}
@@ -0,0 +1,18 @@
+//! The standard `wasm.h` API, and the Wasmer extensions built on it.
+//!
+//! Objects are owned by the caller when returned by a `*_new` or
+//! `*_copy` function, and must be released with the matching
+//! `*_delete` function. The other pointers are borrowed.
+
+#[macro_use]
+mod vec;
+
+pub mod engine;
+pub mod externals;
+pub mod instance;
+pub mod module;
+pub mod store;
+pub mod trap;
+pub mod types;
+pub mod value;
+pub mod wasi;
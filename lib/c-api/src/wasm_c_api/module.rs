@@ -0,0 +1,124 @@
+//! Compiled modules.
+
+use super::store::wasm_store_t;
+use super::types::wasm_byte_vec_t;
+use crate::error::update_last_error;
+use std::os::raw::c_void;
+use std::sync::Arc;
+use wasmer::{Engine, Module, UniversalEngine};
+use wasmer_engine_object::load_linked_object_file;
+use wasmer_engine_universal::UniversalExecutableRef;
+
+pub struct wasm_module_t {
+    pub(crate) inner: Module,
+}
+
+/// Compiles `binary`, which may also be a module in the text format.
+///
+/// Returns null if the module doesn't compile.
+#[no_mangle]
+pub extern "C" fn wasm_module_new(
+    store: &wasm_store_t,
+    binary: &wasm_byte_vec_t,
+) -> Option<Box<wasm_module_t>> {
+    match Module::new(&store.inner, binary.as_bytes()) {
+        Ok(module) => Some(Box::new(wasm_module_t { inner: module })),
+        Err(error) => {
+            update_last_error(error);
+            None
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_module_validate(store: &wasm_store_t, binary: &wasm_byte_vec_t) -> bool {
+    match Module::validate(&store.inner, binary.as_bytes()) {
+        Ok(()) => true,
+        Err(error) => {
+            update_last_error(error);
+            false
+        }
+    }
+}
+
+/// Loads a module serialized by the universal engine, such as the
+/// files written by `wasmer compile` with the universal engine.
+///
+/// Returns null if `bytes` is not a serialized module.
+///
+/// # Safety
+///
+/// The serialized module is not validated: `bytes` must come from a
+/// trusted source.
+#[no_mangle]
+pub unsafe extern "C" fn wasm_module_deserialize(
+    store: &wasm_store_t,
+    bytes: &wasm_byte_vec_t,
+) -> Option<Box<wasm_module_t>> {
+    let engine: &dyn Engine = &**store.inner.engine();
+    let engine = match engine.downcast_ref::<UniversalEngine>() {
+        Some(engine) => engine,
+        None => {
+            update_last_error("the engine of the store is not the universal engine");
+            return None;
+        }
+    };
+    // The archive has to be aligned, which `bytes` may not be.
+    let mut aligned = rkyv::AlignedVec::with_capacity(bytes.as_bytes().len());
+    aligned.extend_from_slice(bytes.as_bytes());
+    let artifact = UniversalExecutableRef::deserialize(&aligned)
+        .map_err(|e| e.to_string())
+        .and_then(|executable| {
+            engine
+                .load_universal_executable_ref(&executable, store.inner.tunables())
+                .map_err(|e| e.to_string())
+        });
+    match artifact {
+        Ok(artifact) => Some(Box::new(wasm_module_t {
+            inner: Module::from_universal_artifact(&store.inner, Arc::new(artifact)),
+        })),
+        Err(error) => {
+            update_last_error(error);
+            None
+        }
+    }
+}
+
+/// Loads the module of an object file written by `wasmer compile` and
+/// linked into the program, like the programs of `wasmer create-exe`,
+/// from `code`, the address of its `WASMER_CODE` symbol.
+///
+/// The code of the module runs where it is linked in the program.
+///
+/// Returns null if the module can't be loaded.
+///
+/// # Safety
+///
+/// The module is not validated: `code` must be the `WASMER_CODE` of an
+/// object file linked into the program.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_module_load_object_file(
+    store: &wasm_store_t,
+    code: *const c_void,
+) -> Option<Box<wasm_module_t>> {
+    let engine: &dyn Engine = &**store.inner.engine();
+    let engine = match engine.downcast_ref::<UniversalEngine>() {
+        Some(engine) => engine,
+        None => {
+            update_last_error("the engine of the store is not the universal engine");
+            return None;
+        }
+    };
+    match load_linked_object_file(engine, code as *const u8, store.inner.tunables()) {
+        Ok(artifact) => Some(Box::new(wasm_module_t {
+            inner: Module::from_universal_artifact(&store.inner, Arc::new(artifact)),
+        })),
+        Err(error) => {
+            update_last_error(error);
+            None
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_module_delete(_module: Option<Box<wasm_module_t>>) {}
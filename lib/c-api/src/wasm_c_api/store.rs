@@ -0,0 +1,18 @@
+//! Stores, which own the objects of the instances.
+
+use super::engine::wasm_engine_t;
+use wasmer::Store;
+
+pub struct wasm_store_t {
+    pub(crate) inner: Store,
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_store_new(engine: &wasm_engine_t) -> Box<wasm_store_t> {
+    Box::new(wasm_store_t {
+        inner: Store::new(&*engine.inner),
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_store_delete(_store: Option<Box<wasm_store_t>>) {}
@@ -0,0 +1,40 @@
+//! Traps, raised by WebAssembly or by host functions.
+
+use super::store::wasm_store_t;
+use super::types::{wasm_byte_vec_t, wasm_message_t};
+use std::ptr;
+use wasmer::RuntimeError;
+
+pub struct wasm_trap_t {
+    pub(crate) inner: RuntimeError,
+}
+
+impl From<RuntimeError> for wasm_trap_t {
+    fn from(error: RuntimeError) -> Self {
+        Self { inner: error }
+    }
+}
+
+/// Creates a trap with a nul-terminated `message`, to be returned by a
+/// host function.
+#[no_mangle]
+pub extern "C" fn wasm_trap_new(
+    _store: &wasm_store_t,
+    message: &wasm_message_t,
+) -> Box<wasm_trap_t> {
+    let bytes = message.as_bytes();
+    let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+    let message = String::from_utf8_lossy(bytes);
+    Box::new(RuntimeError::new(message).into())
+}
+
+/// Writes the message of `trap` in `out`, as a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn wasm_trap_message(trap: &wasm_trap_t, out: *mut wasm_message_t) {
+    let mut message = trap.inner.message().into_bytes();
+    message.push(0);
+    ptr::write(out, wasm_byte_vec_t::from_bytes(&message));
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_trap_delete(_trap: Option<Box<wasm_trap_t>>) {}
@@ -0,0 +1,452 @@
+//! Byte vectors and the types of values and externs.
+
+use crate::error::update_last_error;
+use std::os::raw::c_char;
+use std::ptr;
+use wasmer::{GlobalType, MemoryType, Mutability, Pages, TableType, ValType};
+
+pub type byte_t = c_char;
+pub type float32_t = f32;
+pub type float64_t = f64;
+
+/// A vector of bytes, such as a module binary.
+#[repr(C)]
+pub struct wasm_byte_vec_t {
+    pub size: usize,
+    pub data: *mut byte_t,
+}
+
+impl_vec!(wasm_byte_vec_t, byte_t);
+
+impl wasm_byte_vec_t {
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        let slice = self.as_slice();
+        unsafe { std::slice::from_raw_parts(slice.as_ptr() as *const u8, slice.len()) }
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Self {
+        Self::from_vec(bytes.iter().map(|&byte| byte as byte_t).collect())
+    }
+}
+
+/// A name, not nul-terminated.
+pub type wasm_name_t = wasm_byte_vec_t;
+
+/// A message, nul-terminated.
+pub type wasm_message_t = wasm_byte_vec_t;
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_byte_vec_new_empty(out: *mut wasm_byte_vec_t) {
+    ptr::write(out, wasm_byte_vec_t::from_vec(Vec::new()));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_byte_vec_new_uninitialized(out: *mut wasm_byte_vec_t, size: usize) {
+    ptr::write(out, wasm_byte_vec_t::from_vec(vec![0; size]));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_byte_vec_new(
+    out: *mut wasm_byte_vec_t,
+    size: usize,
+    data: *const byte_t,
+) {
+    ptr::write(out, wasm_byte_vec_t::from_raw(data, size));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_byte_vec_copy(out: *mut wasm_byte_vec_t, src: &wasm_byte_vec_t) {
+    ptr::write(out, src.clone());
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_byte_vec_delete(vec: Option<&mut wasm_byte_vec_t>) {
+    if let Some(vec) = vec {
+        vec.take();
+    }
+}
+
+pub type wasm_valkind_t = u8;
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum wasm_valkind_enum {
+    WASM_I32 = 0,
+    WASM_I64 = 1,
+    WASM_F32 = 2,
+    WASM_F64 = 3,
+    WASM_ANYREF = 128,
+    WASM_FUNCREF = 129,
+}
+
+impl wasm_valkind_enum {
+    pub(crate) fn from_raw(kind: wasm_valkind_t) -> Option<Self> {
+        Some(match kind {
+            0 => Self::WASM_I32,
+            1 => Self::WASM_I64,
+            2 => Self::WASM_F32,
+            3 => Self::WASM_F64,
+            128 => Self::WASM_ANYREF,
+            129 => Self::WASM_FUNCREF,
+            _ => return None,
+        })
+    }
+
+    pub(crate) fn from_type(ty: ValType) -> Option<Self> {
+        Some(match ty {
+            ValType::I32 => Self::WASM_I32,
+            ValType::I64 => Self::WASM_I64,
+            ValType::F32 => Self::WASM_F32,
+            ValType::F64 => Self::WASM_F64,
+            ValType::ExternRef => Self::WASM_ANYREF,
+            ValType::FuncRef => Self::WASM_FUNCREF,
+            ValType::V128 => {
+                update_last_error("the C API doesn't support `v128` values");
+                return None;
+            }
+        })
+    }
+
+    pub(crate) fn to_type(self) -> ValType {
+        match self {
+            Self::WASM_I32 => ValType::I32,
+            Self::WASM_I64 => ValType::I64,
+            Self::WASM_F32 => ValType::F32,
+            Self::WASM_F64 => ValType::F64,
+            Self::WASM_ANYREF => ValType::ExternRef,
+            Self::WASM_FUNCREF => ValType::FuncRef,
+        }
+    }
+}
+
+/// The type of a value.
+#[derive(Clone, Debug)]
+pub struct wasm_valtype_t {
+    kind: wasm_valkind_enum,
+}
+
+impl wasm_valtype_t {
+    pub(crate) fn from_type(ty: ValType) -> Option<Box<Self>> {
+        wasm_valkind_enum::from_type(ty).map(|kind| Box::new(Self { kind }))
+    }
+
+    pub(crate) fn to_type(&self) -> ValType {
+        self.kind.to_type()
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_valtype_new(kind: wasm_valkind_t) -> Option<Box<wasm_valtype_t>> {
+    match wasm_valkind_enum::from_raw(kind) {
+        Some(kind) => Some(Box::new(wasm_valtype_t { kind })),
+        None => {
+            update_last_error(format!("unknown value kind {}", kind));
+            None
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_valtype_kind(valtype: &wasm_valtype_t) -> wasm_valkind_t {
+    valtype.kind as wasm_valkind_t
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_valtype_copy(valtype: &wasm_valtype_t) -> Box<wasm_valtype_t> {
+    Box::new(valtype.clone())
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_valtype_delete(_valtype: Option<Box<wasm_valtype_t>>) {}
+
+/// A vector of owned value types.
+#[repr(C)]
+pub struct wasm_valtype_vec_t {
+    pub size: usize,
+    pub data: *mut Option<Box<wasm_valtype_t>>,
+}
+
+impl_vec!(wasm_valtype_vec_t, Option<Box<wasm_valtype_t>>);
+
+impl wasm_valtype_vec_t {
+    fn from_types(types: &[ValType]) -> Option<Self> {
+        types
+            .iter()
+            .map(|&ty| wasm_valtype_t::from_type(ty).map(Some))
+            .collect::<Option<Vec<_>>>()
+            .map(Self::from_vec)
+    }
+
+    /// Returns `None` if one of the value types is null.
+    fn to_types(&self) -> Option<Vec<ValType>> {
+        self.as_slice()
+            .iter()
+            .map(|valtype| valtype.as_ref().map(|valtype| valtype.to_type()))
+            .collect()
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_valtype_vec_new_empty(out: *mut wasm_valtype_vec_t) {
+    ptr::write(out, wasm_valtype_vec_t::from_vec(Vec::new()));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_valtype_vec_new_uninitialized(
+    out: *mut wasm_valtype_vec_t,
+    size: usize,
+) {
+    ptr::write(out, wasm_valtype_vec_t::from_vec(vec![None; size]));
+}
+
+/// Creates a vector of `size` value types, taking the ownership of
+/// the value types at `data`.
+#[no_mangle]
+pub unsafe extern "C" fn wasm_valtype_vec_new(
+    out: *mut wasm_valtype_vec_t,
+    size: usize,
+    data: *const Option<Box<wasm_valtype_t>>,
+) {
+    ptr::write(out, wasm_valtype_vec_t::from_raw(data, size));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_valtype_vec_copy(
+    out: *mut wasm_valtype_vec_t,
+    src: &wasm_valtype_vec_t,
+) {
+    ptr::write(out, src.clone());
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_valtype_vec_delete(vec: Option<&mut wasm_valtype_vec_t>) {
+    if let Some(vec) = vec {
+        vec.take();
+    }
+}
+
+/// The type of a function.
+#[derive(Clone)]
+pub struct wasm_functype_t {
+    params: wasm_valtype_vec_t,
+    results: wasm_valtype_vec_t,
+}
+
+impl wasm_functype_t {
+    pub(crate) fn from_type(ty: &wasmer::FunctionType) -> Option<Box<Self>> {
+        Some(Box::new(Self {
+            params: wasm_valtype_vec_t::from_types(ty.params())?,
+            results: wasm_valtype_vec_t::from_types(ty.results())?,
+        }))
+    }
+
+    pub(crate) fn to_type(&self) -> Option<wasmer::FunctionType> {
+        match (self.params.to_types(), self.results.to_types()) {
+            (Some(params), Some(results)) => Some(wasmer::FunctionType::new(params, results)),
+            _ => {
+                update_last_error("the function type has a null value type");
+                None
+            }
+        }
+    }
+}
+
+/// Creates a function type, taking the ownership of the value types in
+/// `params` and `results`, which are left empty.
+#[no_mangle]
+pub extern "C" fn wasm_functype_new(
+    params: &mut wasm_valtype_vec_t,
+    results: &mut wasm_valtype_vec_t,
+) -> Box<wasm_functype_t> {
+    Box::new(wasm_functype_t {
+        params: wasm_valtype_vec_t::from_vec(params.take()),
+        results: wasm_valtype_vec_t::from_vec(results.take()),
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_functype_params(functype: &wasm_functype_t) -> &wasm_valtype_vec_t {
+    &functype.params
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_functype_results(functype: &wasm_functype_t) -> &wasm_valtype_vec_t {
+    &functype.results
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_functype_copy(functype: &wasm_functype_t) -> Box<wasm_functype_t> {
+    Box::new(functype.clone())
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_functype_delete(_functype: Option<Box<wasm_functype_t>>) {}
+
+pub type wasm_mutability_t = u8;
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum wasm_mutability_enum {
+    WASM_CONST = 0,
+    WASM_VAR = 1,
+}
+
+/// The type of a global.
+#[derive(Clone)]
+pub struct wasm_globaltype_t {
+    content: Box<wasm_valtype_t>,
+    mutability: Mutability,
+}
+
+impl wasm_globaltype_t {
+    pub(crate) fn from_type(ty: &GlobalType) -> Option<Box<Self>> {
+        Some(Box::new(Self {
+            content: wasm_valtype_t::from_type(ty.ty)?,
+            mutability: ty.mutability,
+        }))
+    }
+
+    pub(crate) fn to_type(&self) -> GlobalType {
+        GlobalType::new(self.content.to_type(), self.mutability)
+    }
+}
+
+/// Creates a global type, taking the ownership of `content`.
+#[no_mangle]
+pub extern "C" fn wasm_globaltype_new(
+    content: Option<Box<wasm_valtype_t>>,
+    mutability: wasm_mutability_t,
+) -> Option<Box<wasm_globaltype_t>> {
+    let mutability = match mutability {
+        0 => Mutability::Const,
+        1 => Mutability::Var,
+        _ => {
+            update_last_error(format!("unknown mutability {}", mutability));
+            return None;
+        }
+    };
+    Some(Box::new(wasm_globaltype_t {
+        content: content?,
+        mutability,
+    }))
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_globaltype_content(globaltype: &wasm_globaltype_t) -> &wasm_valtype_t {
+    &globaltype.content
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_globaltype_mutability(globaltype: &wasm_globaltype_t) -> wasm_mutability_t {
+    match globaltype.mutability {
+        Mutability::Const => wasm_mutability_enum::WASM_CONST as wasm_mutability_t,
+        Mutability::Var => wasm_mutability_enum::WASM_VAR as wasm_mutability_t,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_globaltype_delete(_globaltype: Option<Box<wasm_globaltype_t>>) {}
+
+/// The limits of a memory, in pages, or of a table, in elements.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct wasm_limits_t {
+    pub min: u32,
+    pub max: u32,
+}
+
+/// The `max` of unbounded limits.
+pub const wasm_limits_max_default: u32 = u32::MAX;
+
+impl wasm_limits_t {
+    fn new(min: u32, max: Option<u32>) -> Self {
+        Self {
+            min,
+            max: max.unwrap_or(wasm_limits_max_default),
+        }
+    }
+
+    fn max(&self) -> Option<u32> {
+        if self.max == wasm_limits_max_default {
+            None
+        } else {
+            Some(self.max)
+        }
+    }
+}
+
+/// The type of a memory.
+#[derive(Clone)]
+pub struct wasm_memorytype_t {
+    limits: wasm_limits_t,
+}
+
+impl wasm_memorytype_t {
+    pub(crate) fn from_type(ty: &MemoryType) -> Box<Self> {
+        Box::new(Self {
+            limits: wasm_limits_t::new(ty.minimum.0, ty.maximum.map(|pages| pages.0)),
+        })
+    }
+
+    pub(crate) fn to_type(&self) -> MemoryType {
+        MemoryType::new(Pages(self.limits.min), self.limits.max().map(Pages), false)
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_memorytype_new(limits: &wasm_limits_t) -> Box<wasm_memorytype_t> {
+    Box::new(wasm_memorytype_t { limits: *limits })
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_memorytype_limits(memorytype: &wasm_memorytype_t) -> &wasm_limits_t {
+    &memorytype.limits
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_memorytype_delete(_memorytype: Option<Box<wasm_memorytype_t>>) {}
+
+/// The type of a table.
+#[derive(Clone)]
+pub struct wasm_tabletype_t {
+    element: Box<wasm_valtype_t>,
+    limits: wasm_limits_t,
+}
+
+impl wasm_tabletype_t {
+    pub(crate) fn from_type(ty: &TableType) -> Option<Box<Self>> {
+        Some(Box::new(Self {
+            element: wasm_valtype_t::from_type(ty.ty)?,
+            limits: wasm_limits_t::new(ty.minimum, ty.maximum),
+        }))
+    }
+
+    pub(crate) fn to_type(&self) -> TableType {
+        TableType::new(self.element.to_type(), self.limits.min, self.limits.max())
+    }
+}
+
+/// Creates a table type, taking the ownership of `element`.
+#[no_mangle]
+pub extern "C" fn wasm_tabletype_new(
+    element: Option<Box<wasm_valtype_t>>,
+    limits: &wasm_limits_t,
+) -> Option<Box<wasm_tabletype_t>> {
+    Some(Box::new(wasm_tabletype_t {
+        element: element?,
+        limits: *limits,
+    }))
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_tabletype_element(tabletype: &wasm_tabletype_t) -> &wasm_valtype_t {
+    &tabletype.element
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_tabletype_limits(tabletype: &wasm_tabletype_t) -> &wasm_limits_t {
+    &tabletype.limits
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_tabletype_delete(_tabletype: Option<Box<wasm_tabletype_t>>) {}
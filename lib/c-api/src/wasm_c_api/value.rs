@@ -0,0 +1,170 @@
+//! Values, and the references they may hold.
+
+use super::types::{float32_t, float64_t, wasm_valkind_enum, wasm_valkind_t};
+use std::convert::TryFrom;
+use std::ptr;
+use wasmer::Val;
+use wasmer_types::ExternRef;
+
+/// A reference to a host or WebAssembly object.
+///
+/// Only null references can cross the C API for now.
+pub struct wasm_ref_t {
+    _private: (),
+}
+
+/// The payload of a [`wasm_val_t`].
+///
+/// cbindgen:field-names=[i32, i64, f32, f64, ref]
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union wasm_val_inner {
+    pub int32_t: i32,
+    pub int64_t: i64,
+    pub float32_t: float32_t,
+    pub float64_t: float64_t,
+    pub wref: *mut wasm_ref_t,
+}
+
+/// A value, tagged with its kind.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct wasm_val_t {
+    pub kind: wasm_valkind_t,
+    pub of: wasm_val_inner,
+}
+
+impl Default for wasm_val_t {
+    fn default() -> Self {
+        Self {
+            kind: wasm_valkind_enum::WASM_I32 as wasm_valkind_t,
+            of: wasm_val_inner { int64_t: 0 },
+        }
+    }
+}
+
+impl TryFrom<&wasm_val_t> for Val {
+    type Error = String;
+
+    fn try_from(value: &wasm_val_t) -> Result<Self, Self::Error> {
+        let kind = wasm_valkind_enum::from_raw(value.kind)
+            .ok_or_else(|| format!("unknown value kind {}", value.kind))?;
+        if let wasm_valkind_enum::WASM_ANYREF | wasm_valkind_enum::WASM_FUNCREF = kind {
+            if !unsafe { value.of.wref }.is_null() {
+                return Err("the C API only supports null references".to_string());
+            }
+        }
+        Ok(unsafe {
+            match kind {
+                wasm_valkind_enum::WASM_I32 => Val::I32(value.of.int32_t),
+                wasm_valkind_enum::WASM_I64 => Val::I64(value.of.int64_t),
+                wasm_valkind_enum::WASM_F32 => Val::F32(value.of.float32_t),
+                wasm_valkind_enum::WASM_F64 => Val::F64(value.of.float64_t),
+                wasm_valkind_enum::WASM_ANYREF => Val::ExternRef(ExternRef::null()),
+                wasm_valkind_enum::WASM_FUNCREF => Val::FuncRef(None),
+            }
+        })
+    }
+}
+
+impl TryFrom<&Val> for wasm_val_t {
+    type Error = String;
+
+    fn try_from(value: &Val) -> Result<Self, Self::Error> {
+        let (kind, of) = match value {
+            Val::I32(value) => (
+                wasm_valkind_enum::WASM_I32,
+                wasm_val_inner { int32_t: *value },
+            ),
+            Val::I64(value) => (
+                wasm_valkind_enum::WASM_I64,
+                wasm_val_inner { int64_t: *value },
+            ),
+            Val::F32(value) => (
+                wasm_valkind_enum::WASM_F32,
+                wasm_val_inner { float32_t: *value },
+            ),
+            Val::F64(value) => (
+                wasm_valkind_enum::WASM_F64,
+                wasm_val_inner { float64_t: *value },
+            ),
+            Val::ExternRef(value) if value.is_null() => (
+                wasm_valkind_enum::WASM_ANYREF,
+                wasm_val_inner {
+                    wref: ptr::null_mut(),
+                },
+            ),
+            Val::FuncRef(None) => (
+                wasm_valkind_enum::WASM_FUNCREF,
+                wasm_val_inner {
+                    wref: ptr::null_mut(),
+                },
+            ),
+            Val::ExternRef(_) | Val::FuncRef(Some(_)) => {
+                return Err("the C API only supports null references".to_string())
+            }
+            Val::V128(_) => return Err("the C API doesn't support `v128` values".to_string()),
+        };
+        Ok(Self {
+            kind: kind as wasm_valkind_t,
+            of,
+        })
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_val_copy(out: *mut wasm_val_t, value: &wasm_val_t) {
+    ptr::write(out, *value);
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_val_delete(_value: Option<&mut wasm_val_t>) {}
+
+/// A vector of values.
+#[repr(C)]
+pub struct wasm_val_vec_t {
+    pub size: usize,
+    pub data: *mut wasm_val_t,
+}
+
+impl_vec!(wasm_val_vec_t, wasm_val_t);
+
+impl wasm_val_vec_t {
+    pub(crate) fn to_vals(&self) -> Result<Vec<Val>, String> {
+        self.as_slice().iter().map(Val::try_from).collect()
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_val_vec_new_empty(out: *mut wasm_val_vec_t) {
+    ptr::write(out, wasm_val_vec_t::from_vec(Vec::new()));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_val_vec_new_uninitialized(out: *mut wasm_val_vec_t, size: usize) {
+    ptr::write(
+        out,
+        wasm_val_vec_t::from_vec(vec![wasm_val_t::default(); size]),
+    );
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_val_vec_new(
+    out: *mut wasm_val_vec_t,
+    size: usize,
+    data: *const wasm_val_t,
+) {
+    ptr::write(out, wasm_val_vec_t::from_raw(data, size));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_val_vec_copy(out: *mut wasm_val_vec_t, src: &wasm_val_vec_t) {
+    ptr::write(out, src.clone());
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_val_vec_delete(vec: Option<&mut wasm_val_vec_t>) {
+    if let Some(vec) = vec {
+        vec.take();
+    }
+}
@@ -0,0 +1,74 @@
+/// Implements the Rust side of a `wasm_*_vec_t` vector, which owns
+/// `size` elements allocated by Rust at `data`.
+///
+/// The C functions of each vector are written out by hand, for
+/// `cbindgen` to see them.
+macro_rules! impl_vec {
+    ($vec:ident, $elem:ty) => {
+        #[allow(dead_code)]
+        impl $vec {
+            /// Moves the elements of `vec` into a vector.
+            pub(crate) fn from_vec(vec: Vec<$elem>) -> Self {
+                if vec.is_empty() {
+                    return Self {
+                        size: 0,
+                        data: std::ptr::null_mut(),
+                    };
+                }
+                let boxed = vec.into_boxed_slice();
+                Self {
+                    size: boxed.len(),
+                    data: Box::into_raw(boxed) as *mut $elem,
+                }
+            }
+
+            /// Moves `size` elements out of the array at `data`, which
+            /// may be null when `size` is 0.
+            pub(crate) unsafe fn from_raw(data: *const $elem, size: usize) -> Self {
+                if size == 0 {
+                    return Self::from_vec(Vec::new());
+                }
+                Self::from_vec((0..size).map(|i| std::ptr::read(data.add(i))).collect())
+            }
+
+            pub(crate) fn as_slice(&self) -> &[$elem] {
+                if self.data.is_null() {
+                    return &[];
+                }
+                unsafe { std::slice::from_raw_parts(self.data, self.size) }
+            }
+
+            pub(crate) fn as_mut_slice(&mut self) -> &mut [$elem] {
+                if self.data.is_null() {
+                    return &mut [];
+                }
+                unsafe { std::slice::from_raw_parts_mut(self.data, self.size) }
+            }
+
+            /// Takes the elements out, leaving the vector empty.
+            pub(crate) fn take(&mut self) -> Vec<$elem> {
+                if self.data.is_null() {
+                    return Vec::new();
+                }
+                let elements = unsafe {
+                    Box::from_raw(std::ptr::slice_from_raw_parts_mut(self.data, self.size))
+                };
+                self.data = std::ptr::null_mut();
+                self.size = 0;
+                elements.into_vec()
+            }
+        }
+
+        impl Clone for $vec {
+            fn clone(&self) -> Self {
+                Self::from_vec(self.as_slice().to_vec())
+            }
+        }
+
+        impl Drop for $vec {
+            fn drop(&mut self) {
+                self.take();
+            }
+        }
+    };
+}
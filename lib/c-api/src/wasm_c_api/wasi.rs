@@ -0,0 +1,180 @@
+//! WASI, for the programs compiled to `wasi_snapshot_preview1`.
+//!
+//! A `wasi_config_t` collects the arguments, the environment variables
+//! and the preopened directories of a program, and becomes the
+//! `wasi_env_t` providing its imports:
+//!
+//! ```c
+//! wasi_config_t* config = wasi_config_new("program");
+//! wasi_config_arg(config, "--verbose");
+//! wasi_config_mapdir(config, "/data", "./data");
+//! wasi_env_t* wasi_env = wasi_env_new(config);
+//!
+//! wasm_extern_vec_t imports;
+//! wasi_get_imports(store, module, wasi_env, &imports);
+//! wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+//! ```
+
+use super::externals::{wasm_extern_t, wasm_extern_vec_t};
+use super::module::wasm_module_t;
+use super::store::wasm_store_t;
+use super::trap::wasm_trap_t;
+use crate::error::update_last_error;
+use std::ffi::CStr;
+use std::mem;
+use std::os::raw::c_char;
+use std::ptr;
+use wasmer_wasi::{HostFs, WasiEnv, WasiError, WasiState, WasiStateBuilder, WASI_NAMESPACE};
+
+/// The state of a WASI program, before it's turned into a
+/// `wasi_env_t`.
+pub struct wasi_config_t {
+    inner: WasiStateBuilder,
+}
+
+impl wasi_config_t {
+    fn update(&mut self, f: impl FnOnce(WasiStateBuilder) -> WasiStateBuilder) {
+        let inner = mem::replace(&mut self.inner, WasiState::new(""));
+        self.inner = f(inner);
+    }
+
+    /// Preopens the host directory `dir` as `alias`.
+    fn preopen(&mut self, alias: String, dir: &str) -> bool {
+        match HostFs::new(dir) {
+            Ok(fs) => {
+                self.update(|inner| inner.preopen(alias, fs));
+                true
+            }
+            Err(error) => {
+                update_last_error(format!("failed to preopen `{}`: {}", dir, error));
+                false
+            }
+        }
+    }
+}
+
+/// Creates the state of the program named `program_name`, a
+/// nul-terminated string, which is its first argument.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_config_new(program_name: *const c_char) -> Box<wasi_config_t> {
+    let program_name = CStr::from_ptr(program_name).to_string_lossy();
+    Box::new(wasi_config_t {
+        inner: WasiState::new(program_name),
+    })
+}
+
+/// Adds the nul-terminated argument `arg`.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_config_arg(config: &mut wasi_config_t, arg: *const c_char) {
+    let arg = CStr::from_ptr(arg).to_string_lossy();
+    config.update(|inner| inner.arg(arg));
+}
+
+/// Adds the environment variable `key`, set to `value`, both
+/// nul-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_config_env(
+    config: &mut wasi_config_t,
+    key: *const c_char,
+    value: *const c_char,
+) {
+    let key = CStr::from_ptr(key).to_string_lossy();
+    let value = CStr::from_ptr(value).to_string_lossy();
+    config.update(|inner| inner.env(key, value));
+}
+
+/// Preopens the host directory `dir`, a nul-terminated path, under the
+/// same path for the program.
+///
+/// Returns false if `dir` isn't a directory.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_config_preopen_dir(
+    config: &mut wasi_config_t,
+    dir: *const c_char,
+) -> bool {
+    let dir = CStr::from_ptr(dir).to_string_lossy();
+    config.preopen(dir.to_string(), &dir)
+}
+
+/// Preopens the host directory `dir` as the directory `alias` of the
+/// program, both nul-terminated.
+///
+/// Returns false if `dir` isn't a directory.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_config_mapdir(
+    config: &mut wasi_config_t,
+    alias: *const c_char,
+    dir: *const c_char,
+) -> bool {
+    let alias = CStr::from_ptr(alias).to_string_lossy();
+    let dir = CStr::from_ptr(dir).to_string_lossy();
+    config.preopen(alias.to_string(), &dir)
+}
+
+/// The environment of the WASI imports of a program.
+pub struct wasi_env_t {
+    inner: WasiEnv,
+}
+
+/// Creates the environment of the program from `config`, which is
+/// consumed.
+///
+/// Returns null if an argument or an environment variable is invalid.
+#[no_mangle]
+pub extern "C" fn wasi_env_new(config: Box<wasi_config_t>) -> Option<Box<wasi_env_t>> {
+    match config.inner.finalize() {
+        Ok(inner) => Some(Box::new(wasi_env_t { inner })),
+        Err(error) => {
+            update_last_error(error);
+            None
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wasi_env_delete(_env: Option<Box<wasi_env_t>>) {}
+
+/// Writes the imports of `module` provided by `env` in `imports`, in
+/// the order `wasm_instance_new` expects them.
+///
+/// Returns false if the module has imports other than the WASI
+/// functions, in which case `imports` is left uninitialized.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_get_imports(
+    store: &wasm_store_t,
+    module: &wasm_module_t,
+    env: &wasi_env_t,
+    imports: *mut wasm_extern_vec_t,
+) -> bool {
+    let exports = env.inner.exports(&store.inner);
+    let module_imports = module.inner.artifact().imports();
+    let mut externs = vec![None; module_imports.len()];
+    for import in module_imports {
+        let extern_ = match exports.get_extern(&import.field) {
+            Some(extern_) if import.module == WASI_NAMESPACE => extern_,
+            _ => {
+                update_last_error(format!(
+                    "`{}` `{}` is not a WASI import",
+                    import.module, import.field
+                ));
+                return false;
+            }
+        };
+        externs[import.import_no as usize] = Some(Box::new(wasm_extern_t::new(extern_.clone())));
+    }
+    ptr::write(imports, wasm_extern_vec_t::from_vec(externs));
+    true
+}
+
+/// Tells whether `trap` is the exit of the program, through
+/// `proc_exit`, and writes its exit code in `*exit_code` if so.
+#[no_mangle]
+pub extern "C" fn wasi_trap_exit_code(trap: &wasm_trap_t, exit_code: &mut u32) -> bool {
+    match trap.inner.downcast_ref::<WasiError>() {
+        Some(WasiError::Exit(code)) => {
+            *exit_code = *code;
+            true
+        }
+        None => false,
+    }
+}
@@ -0,0 +1,139 @@
+// The Wasmer extensions configuring an instance: a gas counter owned by
+// the caller, and a stack limit.
+
+#include "test.h"
+
+static const char* GAS_WAT =
+    "(module\n"
+    "  (import \"host\" \"gas\" (func $gas (param i32)))\n"
+    "  (func (export \"burn\")\n"
+    "    (call $gas (i32.const 100))))";
+
+static const char* STACK_WAT =
+    "(module\n"
+    "  (func $depth (export \"depth\") (param i32) (result i32)\n"
+    "    (if (result i32) (local.get 0)\n"
+    "      (then (i32.add (call $depth (i32.sub (local.get 0) (i32.const 1))) (i32.const 1)))\n"
+    "      (else (i32.const 0)))))";
+
+// Calls of the `gas` import with a constant are compiled to an update of
+// the gas counter, so it's never called.
+static wasm_trap_t* gas_callback(const wasm_val_vec_t* args, wasm_val_vec_t* results) {
+  check(0);
+  return NULL;
+}
+
+// Burns 100 times the opcode cost.
+static wasm_trap_t* burn(wasm_instance_t* instance) {
+  wasm_extern_t* extern_ = wasmer_instance_get_export(instance, "burn");
+  check(extern_ != NULL);
+  wasm_val_vec_t no_values = WASM_EMPTY_VEC;
+  wasm_trap_t* trap = wasm_func_call(wasm_extern_as_func(extern_), &no_values, &no_values);
+  wasm_extern_delete(extern_);
+  return trap;
+}
+
+static wasm_trap_t* depth(wasm_instance_t* instance, int depth) {
+  wasm_extern_t* extern_ = wasmer_instance_get_export(instance, "depth");
+  check(extern_ != NULL);
+  wasm_val_t args_data[] = {WASM_I32_VAL(depth)};
+  wasm_val_t results_data[] = {WASM_INIT_VAL};
+  wasm_val_vec_t args = WASM_ARRAY_VEC(args_data);
+  wasm_val_vec_t results = WASM_ARRAY_VEC(results_data);
+  wasm_trap_t* trap = wasm_func_call(wasm_extern_as_func(extern_), &args, &results);
+  wasm_extern_delete(extern_);
+  if (!trap) {
+    check(results_data[0].of.i32 == depth);
+  }
+  return trap;
+}
+
+static void test_gas(wasm_store_t* store) {
+  wasm_module_t* module = compile(store, GAS_WAT);
+  check(module != NULL);
+  wasm_functype_t* gas_type = wasm_functype_new_1_0(wasm_valtype_new_i32());
+  wasm_func_t* gas = wasm_func_new(store, gas_type, gas_callback);
+  wasm_functype_delete(gas_type);
+  wasm_extern_t* externs[] = {wasm_func_as_extern(gas)};
+  wasm_extern_vec_t imports = WASM_ARRAY_VEC(externs);
+
+  wasmer_gas_counter_t counter = {.burnt_gas = 0, .gas_limit = 500, .opcode_cost = 2};
+  wasmer_instance_config_t* config = wasmer_instance_config_new();
+  wasmer_instance_config_set_gas_counter(config, &counter);
+
+  wasm_instance_t* instance = wasmer_instance_new_with_config(store, module, &imports, config, NULL);
+  check(instance != NULL);
+  check(burn(instance) == NULL);
+  check(counter.burnt_gas == 200);
+  check(burn(instance) == NULL);
+  check(counter.burnt_gas == 400);
+  check_trap(burn(instance), "gas limit exceeded");
+  check(counter.burnt_gas > counter.gas_limit);
+
+  // Another instance with the same configuration shares the counter.
+  counter.burnt_gas = 0;
+  wasm_instance_t* other = wasmer_instance_new_with_config(store, module, &imports, config, NULL);
+  check(other != NULL);
+  check(burn(other) == NULL);
+  check(burn(instance) == NULL);
+  check(counter.burnt_gas == 400);
+  wasm_instance_delete(other);
+  wasm_instance_delete(instance);
+
+  // Without a counter, the gas is unlimited.
+  wasmer_instance_config_t* unlimited = wasmer_instance_config_new();
+  instance = wasmer_instance_new_with_config(store, module, &imports, unlimited, NULL);
+  check(instance != NULL);
+  for (int i = 0; i < 10; ++i) {
+    check(burn(instance) == NULL);
+  }
+  wasm_instance_delete(instance);
+  wasmer_instance_config_delete(unlimited);
+
+  // Opcode costs must fit in an `i32`.
+  counter.opcode_cost = 1ull << 40;
+  wasm_trap_t* trap = NULL;
+  check(wasmer_instance_new_with_config(store, module, &imports, config, &trap) == NULL);
+  check(trap != NULL);
+  wasm_trap_delete(trap);
+
+  wasmer_instance_config_delete(config);
+  wasm_func_delete(gas);
+  wasm_module_delete(module);
+}
+
+static void test_stack_limit(wasm_store_t* store) {
+  wasm_module_t* module = compile(store, STACK_WAT);
+  check(module != NULL);
+  wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+
+  wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+  check(instance != NULL);
+  check(depth(instance, 1000) == NULL);
+  wasm_instance_delete(instance);
+
+  wasmer_instance_config_t* config = wasmer_instance_config_new();
+  wasmer_instance_config_set_stack_limit(config, 1000);
+  instance = wasmer_instance_new_with_config(store, module, &imports, config, NULL);
+  check(instance != NULL);
+  check(depth(instance, 10) == NULL);
+  check_trap(depth(instance, 1000), "");
+  // The instance is still usable after the trap.
+  check(depth(instance, 10) == NULL);
+  wasm_instance_delete(instance);
+  wasmer_instance_config_delete(config);
+
+  wasm_module_delete(module);
+}
+
+int main(void) {
+  wasm_engine_t* engine = wasm_engine_new();
+  wasm_store_t* store = wasm_store_new(engine);
+
+  test_gas(store);
+  test_stack_limit(store);
+
+  wasm_store_delete(store);
+  wasm_engine_delete(engine);
+  return 0;
+}
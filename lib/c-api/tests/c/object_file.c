@@ -0,0 +1,41 @@
+// Loads the module of an object file written by the object file engine
+// and linked into the program, like `wasmer create-exe` does.
+
+#include <stdint.h>
+
+#include "test.h"
+
+extern uint8_t WASMER_CODE[];
+
+int main(void) {
+  wasm_engine_t* engine = wasm_engine_new();
+  wasm_store_t* store = wasm_store_new(engine);
+
+  wasm_byte_vec_t garbage;
+  wasm_name_new_from_string(&garbage, "not a module");
+  check(wasm_module_deserialize(store, &garbage) == NULL);
+  check(wasmer_last_error_length() > 0);
+  wasm_byte_vec_delete(&garbage);
+
+  wasm_module_t* module = wasmer_module_load_object_file(store, WASMER_CODE);
+  check(module != NULL);
+
+  wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+  wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+  check(instance != NULL);
+  wasm_extern_t* add = wasmer_instance_get_export(instance, "add");
+  check(add != NULL);
+  wasm_val_t args_data[] = {WASM_I32_VAL(40), WASM_I32_VAL(2)};
+  wasm_val_t results_data[] = {WASM_INIT_VAL};
+  wasm_val_vec_t args = WASM_ARRAY_VEC(args_data);
+  wasm_val_vec_t results = WASM_ARRAY_VEC(results_data);
+  check(wasm_func_call(wasm_extern_as_func(add), &args, &results) == NULL);
+  check(results_data[0].of.i32 == 42);
+
+  wasm_extern_delete(add);
+  wasm_instance_delete(instance);
+  wasm_module_delete(module);
+  wasm_store_delete(store);
+  wasm_engine_delete(engine);
+  return 0;
+}
@@ -0,0 +1,53 @@
+#ifndef TEST_H
+#define TEST_H
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "wasmer.h"
+
+#define check(condition)                                                    \
+  do {                                                                      \
+    if (!(condition)) {                                                     \
+      fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__,     \
+              #condition);                                                  \
+      print_last_error();                                                   \
+      exit(1);                                                              \
+    }                                                                       \
+  } while (0)
+
+static inline void print_last_error(void) {
+  int length = wasmer_last_error_length();
+  if (length > 0) {
+    char* message = malloc(length);
+    wasmer_last_error_message(message, length);
+    fprintf(stderr, "last error: %s\n", message);
+    free(message);
+  }
+}
+
+// Compiles a module in the text format.
+static inline wasm_module_t* compile(wasm_store_t* store, const char* wat) {
+  wasm_byte_vec_t bytes;
+  wasm_byte_vec_new(&bytes, strlen(wat), wat);
+  wasm_module_t* module = wasm_module_new(store, &bytes);
+  wasm_byte_vec_delete(&bytes);
+  return module;
+}
+
+// Checks that `trap` isn't null and that its message contains `expected`.
+static inline void check_trap(wasm_trap_t* trap, const char* expected) {
+  check(trap != NULL);
+  wasm_message_t message;
+  wasm_trap_message(trap, &message);
+  check(message.data[message.size - 1] == '\0');
+  if (!strstr(message.data, expected)) {
+    fprintf(stderr, "unexpected trap: %s\n", message.data);
+    exit(1);
+  }
+  wasm_byte_vec_delete(&message);
+  wasm_trap_delete(trap);
+}
+
+#endif /* TEST_H */
@@ -0,0 +1,97 @@
+// The imports of WASI programs.
+
+#include "test.h"
+
+// Exits with the number of arguments, plus 10 times the error of
+// `fd_prestat_get` for the first preopened directory, which is 8 when
+// there's none.
+static const char* PROGRAM_WAT =
+    "(module\n"
+    "  (import \"wasi_snapshot_preview1\" \"args_sizes_get\"\n"
+    "    (func $args_sizes_get (param i32 i32) (result i32)))\n"
+    "  (import \"wasi_snapshot_preview1\" \"fd_prestat_get\"\n"
+    "    (func $fd_prestat_get (param i32 i32) (result i32)))\n"
+    "  (import \"wasi_snapshot_preview1\" \"proc_exit\" (func $proc_exit (param i32)))\n"
+    "  (memory (export \"memory\") 1)\n"
+    "  (func (export \"_start\")\n"
+    "    (drop (call $args_sizes_get (i32.const 0) (i32.const 4)))\n"
+    "    (call $proc_exit\n"
+    "      (i32.add\n"
+    "        (i32.load (i32.const 0))\n"
+    "        (i32.mul (call $fd_prestat_get (i32.const 3) (i32.const 8)) (i32.const 10))))))";
+
+static const char* HOST_IMPORT_WAT =
+    "(module\n"
+    "  (import \"wasi_snapshot_preview1\" \"proc_exit\" (func (param i32)))\n"
+    "  (import \"host\" \"log\" (func (param i32))))";
+
+// Runs the program with `config`, and returns its exit code.
+static uint32_t run(wasm_store_t* store, wasm_module_t* module, wasi_config_t* config) {
+  wasi_env_t* wasi_env = wasi_env_new(config);
+  check(wasi_env != NULL);
+  wasm_extern_vec_t imports;
+  check(wasi_get_imports(store, module, wasi_env, &imports));
+  check(imports.size == 3);
+  wasi_env_delete(wasi_env);
+
+  wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+  check(instance != NULL);
+  wasm_extern_vec_delete(&imports);
+  wasm_extern_t* start = wasmer_instance_get_export(instance, "_start");
+  check(start != NULL);
+  wasm_val_vec_t no_values = WASM_EMPTY_VEC;
+  wasm_trap_t* trap = wasm_func_call(wasm_extern_as_func(start), &no_values, &no_values);
+  check(trap != NULL);
+  uint32_t exit_code = 0;
+  check(wasi_trap_exit_code(trap, &exit_code));
+  wasm_trap_delete(trap);
+  wasm_extern_delete(start);
+  wasm_instance_delete(instance);
+  return exit_code;
+}
+
+int main(void) {
+  wasm_engine_t* engine = wasm_engine_new();
+  wasm_store_t* store = wasm_store_new(engine);
+  wasm_module_t* module = compile(store, PROGRAM_WAT);
+  check(module != NULL);
+
+  wasi_config_t* config = wasi_config_new("program");
+  check(run(store, module, config) == 81);
+
+  config = wasi_config_new("program");
+  wasi_config_arg(config, "--verbose");
+  wasi_config_arg(config, "input");
+  wasi_config_env(config, "LOG", "debug");
+  check(wasi_config_mapdir(config, "/data", "."));
+  check(run(store, module, config) == 3);
+
+  config = wasi_config_new("program");
+  check(wasi_config_preopen_dir(config, "."));
+  check(!wasi_config_preopen_dir(config, "./not a directory"));
+  check(run(store, module, config) == 1);
+
+  // Only the WASI imports can be provided.
+  wasm_module_t* host_import = compile(store, HOST_IMPORT_WAT);
+  check(host_import != NULL);
+  wasi_env_t* wasi_env = wasi_env_new(wasi_config_new("program"));
+  wasm_extern_vec_t imports;
+  check(!wasi_get_imports(store, host_import, wasi_env, &imports));
+  check(wasmer_last_error_length() > 0);
+  wasi_env_delete(wasi_env);
+  wasm_module_delete(host_import);
+
+  // Other traps aren't exits.
+  wasm_message_t message;
+  wasm_name_new_from_string_nt(&message, "failure");
+  wasm_trap_t* trap = wasm_trap_new(store, &message);
+  wasm_byte_vec_delete(&message);
+  uint32_t exit_code = 0;
+  check(!wasi_trap_exit_code(trap, &exit_code));
+  wasm_trap_delete(trap);
+
+  wasm_module_delete(module);
+  wasm_store_delete(store);
+  wasm_engine_delete(engine);
+  return 0;
+}
@@ -0,0 +1,269 @@
+// The standard `wasm.h` API: host functions, calls, memories, globals,
+// tables, traps and errors.
+
+#include "test.h"
+
+static const char* WAT =
+    "(module\n"
+    "  (import \"host\" \"log\" (func $log (param i32) (result i32)))\n"
+    "  (import \"host\" \"fail\" (func $fail))\n"
+    "  (global (export \"counter\") (mut i32) (i32.const 1))\n"
+    "  (memory (export \"memory\") 1 4)\n"
+    "  (table (export \"table\") 2 funcref)\n"
+    "  (func (export \"add\") (param i32 i32) (result i32)\n"
+    "    (i32.add (local.get 0) (local.get 1)))\n"
+    "  (func (export \"call_log\") (param i32) (result i32)\n"
+    "    (call $log (local.get 0)))\n"
+    "  (func (export \"call_fail\") (call $fail))\n"
+    "  (func (export \"div\") (param i32 i32) (result i32)\n"
+    "    (i32.div_s (local.get 0) (local.get 1)))\n"
+    "  (func (export \"store\") (param i32 i32)\n"
+    "    (i32.store8 (local.get 0) (local.get 1))))";
+
+typedef struct {
+  int calls;
+  int finalized;
+} log_env_t;
+
+static wasm_trap_t* log_callback(void* env, const wasm_val_vec_t* args, wasm_val_vec_t* results) {
+  log_env_t* log_env = env;
+  log_env->calls += 1;
+  check(args->size == 1 && args->data[0].kind == WASM_I32);
+  check(results->size == 1);
+  results->data[0].kind = WASM_I32;
+  results->data[0].of.i32 = args->data[0].of.i32 * 10;
+  return NULL;
+}
+
+static void log_finalizer(void* env) {
+  ((log_env_t*) env)->finalized += 1;
+}
+
+static wasm_store_t* fail_store;
+
+static wasm_trap_t* fail_callback(const wasm_val_vec_t* args, wasm_val_vec_t* results) {
+  wasm_message_t message;
+  wasm_name_new_from_string_nt(&message, "the host failed");
+  wasm_trap_t* trap = wasm_trap_new(fail_store, &message);
+  wasm_byte_vec_delete(&message);
+  return trap;
+}
+
+static const wasm_func_t* get_func(wasm_instance_t* instance, const char* name, wasm_extern_t** extern_) {
+  *extern_ = wasmer_instance_get_export(instance, name);
+  check(*extern_ != NULL);
+  check(wasm_extern_kind(*extern_) == WASM_EXTERN_FUNC);
+  return wasm_extern_as_func_const(*extern_);
+}
+
+static void test_errors(wasm_store_t* store) {
+  // A module that doesn't compile.
+  check(compile(store, "(module (func (result i32)))") == NULL);
+  int length = wasmer_last_error_length();
+  check(length > 0);
+  char* message = malloc(length);
+  check(wasmer_last_error_message(message, 1) == -1);
+  check(wasmer_last_error_message(message, length) == length);
+  check(message[length - 1] == '\0');
+  free(message);
+  check(wasmer_last_error_length() == 0);
+
+  wasm_byte_vec_t garbage;
+  wasm_name_new_from_string(&garbage, "\x01garbage");
+  check(!wasm_module_validate(store, &garbage));
+  wasm_byte_vec_delete(&garbage);
+
+  // Missing imports.
+  wasm_module_t* module = compile(store, WAT);
+  check(module != NULL);
+  wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+  wasm_trap_t* trap = NULL;
+  check(wasm_instance_new(store, module, &imports, &trap) == NULL);
+  check_trap(trap, "host");
+  check(wasmer_last_error_length() > 0);
+  wasm_module_delete(module);
+}
+
+static void test_types(void) {
+  wasm_functype_t* functype = wasm_functype_new_2_1(
+      wasm_valtype_new_i32(), wasm_valtype_new_f64(), wasm_valtype_new_i64());
+  const wasm_valtype_vec_t* params = wasm_functype_params(functype);
+  check(params->size == 2);
+  check(wasm_valtype_kind(params->data[0]) == WASM_I32);
+  check(wasm_valtype_kind(params->data[1]) == WASM_F64);
+  check(wasm_valtype_kind(wasm_functype_results(functype)->data[0]) == WASM_I64);
+  wasm_functype_t* copy = wasm_functype_copy(functype);
+  wasm_functype_delete(functype);
+  check(wasm_functype_params(copy)->size == 2);
+  wasm_functype_delete(copy);
+
+  check(wasm_valtype_new(42) == NULL);
+
+  wasm_limits_t limits = {.min = 1, .max = wasm_limits_max_default};
+  wasm_memorytype_t* memorytype = wasm_memorytype_new(&limits);
+  check(wasm_memorytype_limits(memorytype)->max == wasm_limits_max_default);
+  wasm_memorytype_delete(memorytype);
+}
+
+int main(void) {
+  wasm_engine_t* engine = wasm_engine_new();
+  wasm_store_t* store = wasm_store_new(engine);
+  fail_store = store;
+
+  test_errors(store);
+  test_types();
+
+  wasm_module_t* module = compile(store, WAT);
+  check(module != NULL);
+
+  log_env_t log_env = {0, 0};
+  wasm_functype_t* log_type = wasm_functype_new_1_1(wasm_valtype_new_i32(), wasm_valtype_new_i32());
+  wasm_func_t* log = wasm_func_new_with_env(store, log_type, log_callback, &log_env, log_finalizer);
+  wasm_functype_delete(log_type);
+  check(wasm_func_param_arity(log) == 1 && wasm_func_result_arity(log) == 1);
+
+  wasm_functype_t* fail_type = wasm_functype_new_0_0();
+  wasm_func_t* fail = wasm_func_new(store, fail_type, fail_callback);
+  wasm_functype_delete(fail_type);
+
+  wasm_extern_t* externs[] = {wasm_func_as_extern(log), wasm_func_as_extern(fail)};
+  wasm_extern_vec_t imports = WASM_ARRAY_VEC(externs);
+  wasm_trap_t* trap = NULL;
+  wasm_instance_t* instance = wasm_instance_new(store, module, &imports, &trap);
+  check(instance != NULL);
+  check(trap == NULL);
+
+  // The exports, sorted by name.
+  wasm_extern_vec_t exports;
+  wasm_instance_exports(instance, &exports);
+  check(exports.size == 8);
+  wasm_externkind_t kinds[] = {
+      WASM_EXTERN_FUNC, WASM_EXTERN_FUNC, WASM_EXTERN_FUNC, WASM_EXTERN_GLOBAL,
+      WASM_EXTERN_FUNC, WASM_EXTERN_MEMORY, WASM_EXTERN_FUNC, WASM_EXTERN_TABLE,
+  };
+  for (size_t i = 0; i < exports.size; ++i) {
+    check(wasm_extern_kind(exports.data[i]) == kinds[i]);
+  }
+  check(wasm_extern_as_memory(exports.data[0]) == NULL);
+
+  // Calls.
+  wasm_extern_t* extern_;
+  const wasm_func_t* add = wasm_extern_as_func(exports.data[0]);
+  wasm_val_t add_args[] = {WASM_I32_VAL(2), WASM_I32_VAL(40)};
+  wasm_val_t add_results[] = {WASM_INIT_VAL};
+  wasm_val_vec_t args = WASM_ARRAY_VEC(add_args);
+  wasm_val_vec_t results = WASM_ARRAY_VEC(add_results);
+  check(wasm_func_call(add, &args, &results) == NULL);
+  check(add_results[0].kind == WASM_I32 && add_results[0].of.i32 == 42);
+
+  wasm_functype_t* add_type = wasm_func_type(add);
+  check(wasm_functype_params(add_type)->size == 2);
+  wasm_functype_delete(add_type);
+
+  // Wrong arguments.
+  wasm_val_t wrong_args[] = {WASM_I32_VAL(2), WASM_F64_VAL(40.0)};
+  args = (wasm_val_vec_t) WASM_ARRAY_VEC(wrong_args);
+  check_trap(wasm_func_call(add, &args, &results), "");
+
+  const wasm_func_t* call_log = get_func(instance, "call_log", &extern_);
+  wasm_val_t log_args[] = {WASM_I32_VAL(7)};
+  args = (wasm_val_vec_t) WASM_ARRAY_VEC(log_args);
+  check(wasm_func_call(call_log, &args, &results) == NULL);
+  check(add_results[0].of.i32 == 70);
+  check(log_env.calls == 1);
+  wasm_extern_delete(extern_);
+
+  // Traps, raised by WebAssembly and by the host.
+  const wasm_func_t* div = get_func(instance, "div", &extern_);
+  wasm_val_t div_args[] = {WASM_I32_VAL(1), WASM_I32_VAL(0)};
+  args = (wasm_val_vec_t) WASM_ARRAY_VEC(div_args);
+  check_trap(wasm_func_call(div, &args, &results), "divide by zero");
+  wasm_extern_delete(extern_);
+
+  const wasm_func_t* call_fail = get_func(instance, "call_fail", &extern_);
+  wasm_val_vec_t no_values = WASM_EMPTY_VEC;
+  check_trap(wasm_func_call(call_fail, &no_values, &no_values), "the host failed");
+  wasm_extern_delete(extern_);
+
+  // Memories.
+  extern_ = wasmer_instance_get_export(instance, "memory");
+  wasm_memory_t* memory = wasm_extern_as_memory(extern_);
+  check(memory != NULL);
+  check(wasm_memory_size(memory) == 1);
+  check(wasm_memory_data_size(memory) == MEMORY_PAGE_SIZE);
+  wasm_extern_t* store_extern;
+  const wasm_func_t* store_func = get_func(instance, "store", &store_extern);
+  wasm_val_t store_args[] = {WASM_I32_VAL(16), WASM_I32_VAL(0xab)};
+  args = (wasm_val_vec_t) WASM_ARRAY_VEC(store_args);
+  check(wasm_func_call(store_func, &args, &no_values) == NULL);
+  check((unsigned char) wasm_memory_data(memory)[16] == 0xab);
+  check(wasm_memory_grow(memory, 2));
+  check(wasm_memory_size(memory) == 3);
+  check(!wasm_memory_grow(memory, 2));
+  check(wasmer_last_error_length() > 0);
+  wasm_memorytype_t* memorytype = wasm_memory_type(memory);
+  check(wasm_memorytype_limits(memorytype)->min == 3);
+  check(wasm_memorytype_limits(memorytype)->max == 4);
+  wasm_memorytype_delete(memorytype);
+  wasm_extern_delete(store_extern);
+  wasm_memory_delete(memory);
+
+  // Globals.
+  extern_ = wasmer_instance_get_export(instance, "counter");
+  const wasm_global_t* counter = wasm_extern_as_global_const(extern_);
+  wasm_val_t value;
+  wasm_global_get(counter, &value);
+  check(value.kind == WASM_I32 && value.of.i32 == 1);
+  wasm_val_t new_value = WASM_I32_VAL(5);
+  check(wasm_global_set(counter, &new_value));
+  wasm_global_get(counter, &value);
+  check(value.of.i32 == 5);
+  wasm_val_t wrong_value = WASM_I64_VAL(5);
+  check(!wasm_global_set(counter, &wrong_value));
+  wasm_extern_delete(extern_);
+
+  wasm_globaltype_t* const_type = wasm_globaltype_new(wasm_valtype_new_f64(), WASM_CONST);
+  wasm_val_t pi = WASM_F64_VAL(3.14);
+  wasm_global_t* global = wasm_global_new(store, const_type, &pi);
+  check(global != NULL);
+  check(!wasm_global_set(global, &pi));
+  check(wasm_global_new(store, const_type, &new_value) == NULL);
+  wasm_globaltype_delete(const_type);
+  wasm_globaltype_t* global_type = wasm_global_type(global);
+  check(wasm_globaltype_mutability(global_type) == WASM_CONST);
+  check(wasm_valtype_kind(wasm_globaltype_content(global_type)) == WASM_F64);
+  wasm_globaltype_delete(global_type);
+  wasm_global_delete(global);
+
+  // Tables.
+  extern_ = wasmer_instance_get_export(instance, "table");
+  const wasm_table_t* table = wasm_extern_as_table_const(extern_);
+  check(wasm_table_size(table) == 2);
+  check(wasm_table_grow(table, 3, NULL));
+  check(wasm_table_size(table) == 5);
+  wasm_tabletype_t* tabletype = wasm_table_type(table);
+  check(wasm_valtype_kind(wasm_tabletype_element(tabletype)) == WASM_FUNCREF);
+  wasm_tabletype_delete(tabletype);
+  wasm_extern_delete(extern_);
+
+  wasm_limits_t limits = {.min = 1, .max = 2};
+  tabletype = wasm_tabletype_new(wasm_valtype_new_anyref(), &limits);
+  wasm_table_t* host_table = wasm_table_new(store, tabletype, NULL);
+  check(host_table != NULL);
+  check(!wasm_table_grow(host_table, 2, NULL));
+  wasm_tabletype_delete(tabletype);
+  wasm_table_delete(host_table);
+
+  check(wasmer_instance_get_export(instance, "missing") == NULL);
+
+  wasm_extern_vec_delete(&exports);
+  wasm_instance_delete(instance);
+  wasm_func_delete(log);
+  wasm_func_delete(fail);
+  wasm_module_delete(module);
+  check(log_env.finalized == 1);
+
+  wasm_store_delete(store);
+  wasm_engine_delete(engine);
+  return 0;
+}
@@ -0,0 +1,104 @@
+//! Compiles the C programs of `tests/c` against `wasmer.h` and the
+//! shared library, and runs them.
+//!
+//! Cargo builds the shared library with the test executables, in the
+//! same directory, and generates the header in `OUT_DIR`.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use wasmer::{wat2wasm, BaseTunables, Engine, Singlepass};
+use wasmer_engine_object::ObjectFile;
+
+/// The directory of the shared library, which is the directory of the
+/// test executables.
+fn library_dir() -> PathBuf {
+    let exe = env::current_exe().unwrap();
+    exe.parent().unwrap().to_path_buf()
+}
+
+/// Compiles `tests/c/{name}.c`, linked with the given object files.
+fn run_c_test(name: &str, objects: &[PathBuf]) {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let library_dir = library_dir();
+    let output_dir = tempfile::tempdir().unwrap();
+    let executable = output_dir.path().join(name);
+
+    let status = Command::new(env::var("CC").unwrap_or_else(|_| "cc".to_string()))
+        .arg(crate_dir.join("tests/c").join(format!("{}.c", name)))
+        .args(objects)
+        .arg("-Wall")
+        .arg("-Wno-unused-parameter")
+        .arg("-I")
+        .arg(env!("OUT_DIR"))
+        .arg("-L")
+        .arg(&library_dir)
+        .arg(format!("-Wl,-rpath,{}", library_dir.display()))
+        .arg("-lwasmer_c_api")
+        .arg("-o")
+        .arg(&executable)
+        .status()
+        .expect("could not run the C compiler");
+    assert!(status.success(), "could not compile {}.c", name);
+
+    // Cargo runs the tests with a library path which may hold an older
+    // copy of the library, found before the one of the rpath.
+    let mut library_path = vec![library_dir];
+    let paths = env::var_os("LD_LIBRARY_PATH");
+    library_path.extend(paths.iter().flat_map(env::split_paths));
+    let output = Command::new(&executable)
+        .env("LD_LIBRARY_PATH", env::join_paths(library_path).unwrap())
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{} failed:\n{}{}",
+        name,
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn header_is_up_to_date() {
+    let checked_in = Path::new(env!("CARGO_MANIFEST_DIR")).join("wasmer.h");
+    let generated = Path::new(env!("OUT_DIR")).join("wasmer.h");
+    assert!(
+        fs::read(checked_in).unwrap() == fs::read(generated).unwrap(),
+        "`wasmer.h` is out of date, run `make capi-header` to update it"
+    );
+}
+
+#[test]
+fn wasm_c_api() {
+    run_c_test("wasm_c_api", &[]);
+}
+
+#[test]
+fn gas_and_stack_limits() {
+    run_c_test("gas_and_stack_limits", &[]);
+}
+
+#[test]
+fn wasi() {
+    run_c_test("wasi", &[]);
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn object_file() {
+    let wasm = wat2wasm(
+        br#"(module
+              (func (export "add") (param i32 i32) (result i32)
+                (i32.add (local.get 0) (local.get 1))))"#,
+    )
+    .unwrap();
+    let engine = ObjectFile::new(Singlepass::default()).engine();
+    let tunables = BaseTunables::for_target(engine.target());
+    let executable = engine.compile(&wasm, &tunables).unwrap();
+    let object_dir = tempfile::tempdir().unwrap();
+    let object = object_dir.path().join("module.o");
+    fs::write(&object, executable.serialize().unwrap()).unwrap();
+    run_c_test("object_file", &[object]);
+}
@@ -0,0 +1,802 @@
+#ifndef WASMER_H
+#define WASMER_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+#include <string.h>
+
+typedef struct wasm_func_t wasm_func_t;
+typedef struct wasm_global_t wasm_global_t;
+typedef struct wasm_memory_t wasm_memory_t;
+typedef struct wasm_table_t wasm_table_t;
+
+/**
+ * The size of a page of memory, in bytes.
+ */
+#define MEMORY_PAGE_SIZE 65536
+
+/**
+ * The `max` of unbounded limits.
+ */
+#define wasm_limits_max_default UINT32_MAX
+
+enum wasm_externkind_enum
+#ifdef __cplusplus
+  : uint8_t
+#endif // __cplusplus
+ {
+  WASM_EXTERN_FUNC = 0,
+  WASM_EXTERN_GLOBAL = 1,
+  WASM_EXTERN_TABLE = 2,
+  WASM_EXTERN_MEMORY = 3,
+};
+#ifndef __cplusplus
+typedef uint8_t wasm_externkind_enum;
+#endif // __cplusplus
+
+enum wasm_mutability_enum
+#ifdef __cplusplus
+  : uint8_t
+#endif // __cplusplus
+ {
+  WASM_CONST = 0,
+  WASM_VAR = 1,
+};
+#ifndef __cplusplus
+typedef uint8_t wasm_mutability_enum;
+#endif // __cplusplus
+
+enum wasm_valkind_enum
+#ifdef __cplusplus
+  : uint8_t
+#endif // __cplusplus
+ {
+  WASM_I32 = 0,
+  WASM_I64 = 1,
+  WASM_F32 = 2,
+  WASM_F64 = 3,
+  WASM_ANYREF = 128,
+  WASM_FUNCREF = 129,
+};
+#ifndef __cplusplus
+typedef uint8_t wasm_valkind_enum;
+#endif // __cplusplus
+
+/**
+ * The state of a WASI program, before it's turned into a
+ * `wasi_env_t`.
+ */
+typedef struct wasi_config_t wasi_config_t;
+
+/**
+ * The environment of the WASI imports of a program.
+ */
+typedef struct wasi_env_t wasi_env_t;
+
+/**
+ * The configuration of an engine.
+ *
+ * The engines of the C API always compile with Singlepass into the
+ * universal engine, which has nothing to configure yet.
+ */
+typedef struct wasm_config_t wasm_config_t;
+
+typedef struct wasm_engine_t wasm_engine_t;
+
+typedef struct wasm_extern_t wasm_extern_t;
+
+/**
+ * The type of a function.
+ */
+typedef struct wasm_functype_t wasm_functype_t;
+
+/**
+ * The type of a global.
+ */
+typedef struct wasm_globaltype_t wasm_globaltype_t;
+
+typedef struct wasm_instance_t wasm_instance_t;
+
+/**
+ * The type of a memory.
+ */
+typedef struct wasm_memorytype_t wasm_memorytype_t;
+
+typedef struct wasm_module_t wasm_module_t;
+
+/**
+ * A reference to a host or WebAssembly object.
+ *
+ * Only null references can cross the C API for now.
+ */
+typedef struct wasm_ref_t wasm_ref_t;
+
+typedef struct wasm_store_t wasm_store_t;
+
+/**
+ * The type of a table.
+ */
+typedef struct wasm_tabletype_t wasm_tabletype_t;
+
+typedef struct wasm_trap_t wasm_trap_t;
+
+/**
+ * The type of a value.
+ */
+typedef struct wasm_valtype_t wasm_valtype_t;
+
+/**
+ * The configuration of an instance.
+ */
+typedef struct wasmer_instance_config_t wasmer_instance_config_t;
+
+typedef uint8_t wasm_externkind_t;
+
+/**
+ * A vector of owned externs.
+ */
+typedef struct wasm_extern_vec_t {
+  size_t size;
+  struct wasm_extern_t **data;
+} wasm_extern_vec_t;
+
+typedef uint8_t wasm_valkind_t;
+
+typedef float float32_t;
+
+typedef double float64_t;
+
+/**
+ * The payload of a [`wasm_val_t`].
+ *
+ */
+typedef union wasm_val_inner {
+  int32_t i32;
+  int64_t i64;
+  float32_t f32;
+  float64_t f64;
+  struct wasm_ref_t *ref;
+} wasm_val_inner;
+
+/**
+ * A value, tagged with its kind.
+ */
+typedef struct wasm_val_t {
+  wasm_valkind_t kind;
+  union wasm_val_inner of;
+} wasm_val_t;
+
+/**
+ * A vector of values.
+ */
+typedef struct wasm_val_vec_t {
+  size_t size;
+  struct wasm_val_t *data;
+} wasm_val_vec_t;
+
+/**
+ * A host function. It writes the results of the call in the
+ * preallocated `results`, or returns a trap.
+ */
+typedef struct wasm_trap_t *(*wasm_func_callback_t)(const struct wasm_val_vec_t *args,
+                                                    struct wasm_val_vec_t *results);
+
+/**
+ * A host function called with the `env` it was created with.
+ */
+typedef struct wasm_trap_t *(*wasm_func_callback_with_env_t)(void *env,
+                                                             const struct wasm_val_vec_t *args,
+                                                             struct wasm_val_vec_t *results);
+
+typedef char byte_t;
+
+typedef uint32_t wasm_memory_pages_t;
+
+typedef uint32_t wasm_table_size_t;
+
+/**
+ * A gas counter shared with the instances it's configured for.
+ *
+ * Every instruction burns `opcode_cost`, and the instance traps once
+ * `burnt_gas` would exceed `gas_limit`.
+ */
+typedef struct wasmer_gas_counter_t {
+  uint64_t burnt_gas;
+  uint64_t gas_limit;
+  uint64_t opcode_cost;
+} wasmer_gas_counter_t;
+
+/**
+ * A vector of bytes, such as a module binary.
+ */
+typedef struct wasm_byte_vec_t {
+  size_t size;
+  byte_t *data;
+} wasm_byte_vec_t;
+
+/**
+ * A message, nul-terminated.
+ */
+typedef struct wasm_byte_vec_t wasm_message_t;
+
+/**
+ * A vector of owned value types.
+ */
+typedef struct wasm_valtype_vec_t {
+  size_t size;
+  struct wasm_valtype_t **data;
+} wasm_valtype_vec_t;
+
+typedef uint8_t wasm_mutability_t;
+
+/**
+ * The limits of a memory, in pages, or of a table, in elements.
+ */
+typedef struct wasm_limits_t {
+  uint32_t min;
+  uint32_t max;
+} wasm_limits_t;
+
+/**
+ * A name, not nul-terminated.
+ */
+typedef struct wasm_byte_vec_t wasm_name_t;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Returns the length of the last error message of the current thread,
+ * including its trailing nul byte, or 0 if there's no error.
+ */
+int wasmer_last_error_length(void);
+
+/**
+ * Writes the last error message of the current thread in `buffer`, as
+ * a nul-terminated string, and clears it.
+ *
+ * Returns the number of bytes written, 0 if there's no error, and -1
+ * if `buffer` is null or shorter than [`wasmer_last_error_length`].
+ * The error is kept in the latter case.
+ */
+int wasmer_last_error_message(char *buffer, int length);
+
+struct wasm_config_t *wasm_config_new(void);
+
+void wasm_config_delete(struct wasm_config_t *_config);
+
+struct wasm_engine_t *wasm_engine_new(void);
+
+/**
+ * Creates an engine, taking the ownership of `config`.
+ */
+struct wasm_engine_t *wasm_engine_new_with_config(struct wasm_config_t *_config);
+
+void wasm_engine_delete(struct wasm_engine_t *_engine);
+
+wasm_externkind_t wasm_extern_kind(const struct wasm_extern_t *extern_);
+
+struct wasm_extern_t *wasm_extern_copy(const struct wasm_extern_t *extern_);
+
+void wasm_extern_delete(struct wasm_extern_t *_extern);
+
+wasm_func_t *wasm_extern_as_func(struct wasm_extern_t *extern_);
+
+const wasm_func_t *wasm_extern_as_func_const(const struct wasm_extern_t *extern_);
+
+wasm_global_t *wasm_extern_as_global(struct wasm_extern_t *extern_);
+
+const wasm_global_t *wasm_extern_as_global_const(const struct wasm_extern_t *extern_);
+
+wasm_memory_t *wasm_extern_as_memory(struct wasm_extern_t *extern_);
+
+const wasm_memory_t *wasm_extern_as_memory_const(const struct wasm_extern_t *extern_);
+
+wasm_table_t *wasm_extern_as_table(struct wasm_extern_t *extern_);
+
+const wasm_table_t *wasm_extern_as_table_const(const struct wasm_extern_t *extern_);
+
+struct wasm_extern_t *wasm_func_as_extern(wasm_func_t *func);
+
+const struct wasm_extern_t *wasm_func_as_extern_const(const wasm_func_t *func);
+
+struct wasm_extern_t *wasm_global_as_extern(wasm_global_t *global);
+
+const struct wasm_extern_t *wasm_global_as_extern_const(const wasm_global_t *global);
+
+struct wasm_extern_t *wasm_memory_as_extern(wasm_memory_t *memory);
+
+const struct wasm_extern_t *wasm_memory_as_extern_const(const wasm_memory_t *memory);
+
+struct wasm_extern_t *wasm_table_as_extern(wasm_table_t *table);
+
+const struct wasm_extern_t *wasm_table_as_extern_const(const wasm_table_t *table);
+
+void wasm_extern_vec_new_empty(struct wasm_extern_vec_t *out);
+
+void wasm_extern_vec_new_uninitialized(struct wasm_extern_vec_t *out, size_t size);
+
+/**
+ * Creates a vector of `size` externs, taking the ownership of the
+ * externs at `data`.
+ */
+void wasm_extern_vec_new(struct wasm_extern_vec_t *out,
+                         size_t size,
+                         struct wasm_extern_t *const *data);
+
+void wasm_extern_vec_copy(struct wasm_extern_vec_t *out, const struct wasm_extern_vec_t *src);
+
+void wasm_extern_vec_delete(struct wasm_extern_vec_t *vec);
+
+wasm_func_t *wasm_func_new(const struct wasm_store_t *store,
+                           const struct wasm_functype_t *functype,
+                           wasm_func_callback_t callback);
+
+/**
+ * Creates a host function called with `env`. `finalizer`, if not
+ * null, is called with `env` once the function is dropped.
+ */
+wasm_func_t *wasm_func_new_with_env(const struct wasm_store_t *store,
+                                    const struct wasm_functype_t *functype,
+                                    wasm_func_callback_with_env_t callback,
+                                    void *env,
+                                    void (*finalizer)(void *env));
+
+/**
+ * Calls `func`, and writes its results in the preallocated `results`.
+ *
+ * Returns null on success, or the trap that stopped the call.
+ */
+struct wasm_trap_t *wasm_func_call(const wasm_func_t *func,
+                                   const struct wasm_val_vec_t *args,
+                                   struct wasm_val_vec_t *results);
+
+struct wasm_functype_t *wasm_func_type(const wasm_func_t *func);
+
+size_t wasm_func_param_arity(const wasm_func_t *func);
+
+size_t wasm_func_result_arity(const wasm_func_t *func);
+
+wasm_func_t *wasm_func_copy(const wasm_func_t *func);
+
+void wasm_func_delete(wasm_func_t *_func);
+
+/**
+ * Creates a global of type `globaltype`, holding `value`.
+ *
+ * Returns null if `value` doesn't have the type of the global.
+ */
+wasm_global_t *wasm_global_new(const struct wasm_store_t *store,
+                               const struct wasm_globaltype_t *globaltype,
+                               const struct wasm_val_t *value);
+
+struct wasm_globaltype_t *wasm_global_type(const wasm_global_t *global);
+
+/**
+ * Writes the value of `global` in `out`.
+ */
+void wasm_global_get(const wasm_global_t *global, struct wasm_val_t *out);
+
+/**
+ * Sets the value of a mutable global.
+ *
+ * Returns false if the global is immutable, or if `value` doesn't have
+ * its type.
+ */
+bool wasm_global_set(const wasm_global_t *global, const struct wasm_val_t *value);
+
+wasm_global_t *wasm_global_copy(const wasm_global_t *global);
+
+void wasm_global_delete(wasm_global_t *_global);
+
+wasm_memory_t *wasm_memory_new(const struct wasm_store_t *store,
+                               const struct wasm_memorytype_t *memorytype);
+
+struct wasm_memorytype_t *wasm_memory_type(const wasm_memory_t *memory);
+
+/**
+ * Returns a pointer to the bytes of `memory`. The pointer is
+ * invalidated when the memory grows.
+ */
+byte_t *wasm_memory_data(const wasm_memory_t *memory);
+
+size_t wasm_memory_data_size(const wasm_memory_t *memory);
+
+/**
+ * Returns the size of `memory`, in pages.
+ */
+wasm_memory_pages_t wasm_memory_size(const wasm_memory_t *memory);
+
+/**
+ * Grows `memory` by `delta` pages.
+ *
+ * Returns false if the memory can't grow that much.
+ */
+bool wasm_memory_grow(const wasm_memory_t *memory, wasm_memory_pages_t delta);
+
+wasm_memory_t *wasm_memory_copy(const wasm_memory_t *memory);
+
+void wasm_memory_delete(wasm_memory_t *_memory);
+
+/**
+ * Creates a table of type `tabletype`, with its elements set to
+ * `init`, which must be null.
+ */
+wasm_table_t *wasm_table_new(const struct wasm_store_t *store,
+                             const struct wasm_tabletype_t *tabletype,
+                             const struct wasm_ref_t *init);
+
+struct wasm_tabletype_t *wasm_table_type(const wasm_table_t *table);
+
+wasm_table_size_t wasm_table_size(const wasm_table_t *table);
+
+/**
+ * Grows `table` by `delta` elements set to `init`, which must be null.
+ *
+ * Returns false if the table can't grow that much.
+ */
+bool wasm_table_grow(const wasm_table_t *table,
+                     wasm_table_size_t delta,
+                     const struct wasm_ref_t *init);
+
+wasm_table_t *wasm_table_copy(const wasm_table_t *table);
+
+void wasm_table_delete(wasm_table_t *_table);
+
+/**
+ * Instantiates `module`, with `imports` in the order of the imports
+ * of the module.
+ *
+ * Returns null if the instantiation fails, and stores the reason in
+ * `*trap` if `trap` isn't null.
+ */
+struct wasm_instance_t *wasm_instance_new(const struct wasm_store_t *_store,
+                                          const struct wasm_module_t *module,
+                                          const struct wasm_extern_vec_t *imports,
+                                          struct wasm_trap_t **trap);
+
+/**
+ * Writes the exports of `instance` in `out`, sorted by name.
+ */
+void wasm_instance_exports(const struct wasm_instance_t *instance, struct wasm_extern_vec_t *out);
+
+void wasm_instance_delete(struct wasm_instance_t *_instance);
+
+/**
+ * Returns the export of `instance` named `name`, a nul-terminated
+ * string, or null if there's none.
+ */
+struct wasm_extern_t *wasmer_instance_get_export(const struct wasm_instance_t *instance,
+                                                 const char *name);
+
+/**
+ * Creates a configuration with an unlimited gas counter owned by the
+ * instance, and the default stack limit.
+ */
+struct wasmer_instance_config_t *wasmer_instance_config_new(void);
+
+/**
+ * Makes the instances of `config` burn their gas in `counter`, which
+ * must outlive them.
+ */
+void wasmer_instance_config_set_gas_counter(struct wasmer_instance_config_t *config,
+                                            struct wasmer_gas_counter_t *counter);
+
+/**
+ * Limits the stack of the instances of `config` to `stack_limit`
+ * slots of 8 bytes. Calls nested too deeply trap.
+ */
+void wasmer_instance_config_set_stack_limit(struct wasmer_instance_config_t *config,
+                                            int32_t stack_limit);
+
+void wasmer_instance_config_delete(struct wasmer_instance_config_t *_config);
+
+/**
+ * Like [`wasm_instance_new`], but configured with `config`, which can
+ * be reused for other instances.
+ */
+struct wasm_instance_t *wasmer_instance_new_with_config(const struct wasm_store_t *_store,
+                                                        const struct wasm_module_t *module,
+                                                        const struct wasm_extern_vec_t *imports,
+                                                        const struct wasmer_instance_config_t *config,
+                                                        struct wasm_trap_t **trap);
+
+/**
+ * Compiles `binary`, which may also be a module in the text format.
+ *
+ * Returns null if the module doesn't compile.
+ */
+struct wasm_module_t *wasm_module_new(const struct wasm_store_t *store,
+                                      const struct wasm_byte_vec_t *binary);
+
+bool wasm_module_validate(const struct wasm_store_t *store, const struct wasm_byte_vec_t *binary);
+
+/**
+ * Loads a module serialized by the universal engine, such as the
+ * files written by `wasmer compile` with the universal engine.
+ *
+ * Returns null if `bytes` is not a serialized module.
+ *
+ * # Safety
+ *
+ * The serialized module is not validated: `bytes` must come from a
+ * trusted source.
+ */
+struct wasm_module_t *wasm_module_deserialize(const struct wasm_store_t *store,
+                                              const struct wasm_byte_vec_t *bytes);
+
+/**
+ * Loads the module of an object file written by `wasmer compile` and
+ * linked into the program, like the programs of `wasmer create-exe`,
+ * from `code`, the address of its `WASMER_CODE` symbol.
+ *
+ * The code of the module runs where it is linked in the program.
+ *
+ * Returns null if the module can't be loaded.
+ *
+ * # Safety
+ *
+ * The module is not validated: `code` must be the `WASMER_CODE` of an
+ * object file linked into the program.
+ */
+struct wasm_module_t *wasmer_module_load_object_file(const struct wasm_store_t *store,
+                                                     const void *code);
+
+void wasm_module_delete(struct wasm_module_t *_module);
+
+struct wasm_store_t *wasm_store_new(const struct wasm_engine_t *engine);
+
+void wasm_store_delete(struct wasm_store_t *_store);
+
+/**
+ * Creates a trap with a nul-terminated `message`, to be returned by a
+ * host function.
+ */
+struct wasm_trap_t *wasm_trap_new(const struct wasm_store_t *_store, const wasm_message_t *message);
+
+/**
+ * Writes the message of `trap` in `out`, as a nul-terminated string.
+ */
+void wasm_trap_message(const struct wasm_trap_t *trap, wasm_message_t *out);
+
+void wasm_trap_delete(struct wasm_trap_t *_trap);
+
+void wasm_byte_vec_new_empty(struct wasm_byte_vec_t *out);
+
+void wasm_byte_vec_new_uninitialized(struct wasm_byte_vec_t *out, size_t size);
+
+void wasm_byte_vec_new(struct wasm_byte_vec_t *out, size_t size, const byte_t *data);
+
+void wasm_byte_vec_copy(struct wasm_byte_vec_t *out, const struct wasm_byte_vec_t *src);
+
+void wasm_byte_vec_delete(struct wasm_byte_vec_t *vec);
+
+struct wasm_valtype_t *wasm_valtype_new(wasm_valkind_t kind);
+
+wasm_valkind_t wasm_valtype_kind(const struct wasm_valtype_t *valtype);
+
+struct wasm_valtype_t *wasm_valtype_copy(const struct wasm_valtype_t *valtype);
+
+void wasm_valtype_delete(struct wasm_valtype_t *_valtype);
+
+void wasm_valtype_vec_new_empty(struct wasm_valtype_vec_t *out);
+
+void wasm_valtype_vec_new_uninitialized(struct wasm_valtype_vec_t *out, size_t size);
+
+/**
+ * Creates a vector of `size` value types, taking the ownership of
+ * the value types at `data`.
+ */
+void wasm_valtype_vec_new(struct wasm_valtype_vec_t *out,
+                          size_t size,
+                          struct wasm_valtype_t *const *data);
+
+void wasm_valtype_vec_copy(struct wasm_valtype_vec_t *out, const struct wasm_valtype_vec_t *src);
+
+void wasm_valtype_vec_delete(struct wasm_valtype_vec_t *vec);
+
+/**
+ * Creates a function type, taking the ownership of the value types in
+ * `params` and `results`, which are left empty.
+ */
+struct wasm_functype_t *wasm_functype_new(struct wasm_valtype_vec_t *params,
+                                          struct wasm_valtype_vec_t *results);
+
+const struct wasm_valtype_vec_t *wasm_functype_params(const struct wasm_functype_t *functype);
+
+const struct wasm_valtype_vec_t *wasm_functype_results(const struct wasm_functype_t *functype);
+
+struct wasm_functype_t *wasm_functype_copy(const struct wasm_functype_t *functype);
+
+void wasm_functype_delete(struct wasm_functype_t *_functype);
+
+/**
+ * Creates a global type, taking the ownership of `content`.
+ */
+struct wasm_globaltype_t *wasm_globaltype_new(struct wasm_valtype_t *content,
+                                              wasm_mutability_t mutability);
+
+const struct wasm_valtype_t *wasm_globaltype_content(const struct wasm_globaltype_t *globaltype);
+
+wasm_mutability_t wasm_globaltype_mutability(const struct wasm_globaltype_t *globaltype);
+
+void wasm_globaltype_delete(struct wasm_globaltype_t *_globaltype);
+
+struct wasm_memorytype_t *wasm_memorytype_new(const struct wasm_limits_t *limits);
+
+const struct wasm_limits_t *wasm_memorytype_limits(const struct wasm_memorytype_t *memorytype);
+
+void wasm_memorytype_delete(struct wasm_memorytype_t *_memorytype);
+
+/**
+ * Creates a table type, taking the ownership of `element`.
+ */
+struct wasm_tabletype_t *wasm_tabletype_new(struct wasm_valtype_t *element,
+                                            const struct wasm_limits_t *limits);
+
+const struct wasm_valtype_t *wasm_tabletype_element(const struct wasm_tabletype_t *tabletype);
+
+const struct wasm_limits_t *wasm_tabletype_limits(const struct wasm_tabletype_t *tabletype);
+
+void wasm_tabletype_delete(struct wasm_tabletype_t *_tabletype);
+
+void wasm_val_copy(struct wasm_val_t *out, const struct wasm_val_t *value);
+
+void wasm_val_delete(struct wasm_val_t *_value);
+
+void wasm_val_vec_new_empty(struct wasm_val_vec_t *out);
+
+void wasm_val_vec_new_uninitialized(struct wasm_val_vec_t *out, size_t size);
+
+void wasm_val_vec_new(struct wasm_val_vec_t *out, size_t size, const struct wasm_val_t *data);
+
+void wasm_val_vec_copy(struct wasm_val_vec_t *out, const struct wasm_val_vec_t *src);
+
+void wasm_val_vec_delete(struct wasm_val_vec_t *vec);
+
+/**
+ * Creates the state of the program named `program_name`, a
+ * nul-terminated string, which is its first argument.
+ */
+struct wasi_config_t *wasi_config_new(const char *program_name);
+
+/**
+ * Adds the nul-terminated argument `arg`.
+ */
+void wasi_config_arg(struct wasi_config_t *config, const char *arg);
+
+/**
+ * Adds the environment variable `key`, set to `value`, both
+ * nul-terminated.
+ */
+void wasi_config_env(struct wasi_config_t *config, const char *key, const char *value);
+
+/**
+ * Preopens the host directory `dir`, a nul-terminated path, under the
+ * same path for the program.
+ *
+ * Returns false if `dir` isn't a directory.
+ */
+bool wasi_config_preopen_dir(struct wasi_config_t *config, const char *dir);
+
+/**
+ * Preopens the host directory `dir` as the directory `alias` of the
+ * program, both nul-terminated.
+ *
+ * Returns false if `dir` isn't a directory.
+ */
+bool wasi_config_mapdir(struct wasi_config_t *config, const char *alias, const char *dir);
+
+/**
+ * Creates the environment of the program from `config`, which is
+ * consumed.
+ *
+ * Returns null if an argument or an environment variable is invalid.
+ */
+struct wasi_env_t *wasi_env_new(struct wasi_config_t *config);
+
+void wasi_env_delete(struct wasi_env_t *_env);
+
+/**
+ * Writes the imports of `module` provided by `env` in `imports`, in
+ * the order `wasm_instance_new` expects them.
+ *
+ * Returns false if the module has imports other than the WASI
+ * functions, in which case `imports` is left uninitialized.
+ */
+bool wasi_get_imports(const struct wasm_store_t *store,
+                      const struct wasm_module_t *module,
+                      const struct wasi_env_t *env,
+                      struct wasm_extern_vec_t *imports);
+
+/**
+ * Tells whether `trap` is the exit of the program, through
+ * `proc_exit`, and writes its exit code in `*exit_code` if so.
+ */
+bool wasi_trap_exit_code(const struct wasm_trap_t *trap, uint32_t *exit_code);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+
+#define wasm_name wasm_byte_vec
+#define wasm_name_new wasm_byte_vec_new
+#define wasm_name_new_empty wasm_byte_vec_new_empty
+#define wasm_name_new_uninitialized wasm_byte_vec_new_uninitialized
+#define wasm_name_copy wasm_byte_vec_copy
+#define wasm_name_delete wasm_byte_vec_delete
+
+static inline void wasm_name_new_from_string(wasm_name_t* out, const char* s) {
+  wasm_name_new(out, strlen(s), s);
+}
+
+static inline void wasm_name_new_from_string_nt(wasm_name_t* out, const char* s) {
+  wasm_name_new(out, strlen(s) + 1, s);
+}
+
+static inline wasm_valtype_t* wasm_valtype_new_i32(void) { return wasm_valtype_new(WASM_I32); }
+static inline wasm_valtype_t* wasm_valtype_new_i64(void) { return wasm_valtype_new(WASM_I64); }
+static inline wasm_valtype_t* wasm_valtype_new_f32(void) { return wasm_valtype_new(WASM_F32); }
+static inline wasm_valtype_t* wasm_valtype_new_f64(void) { return wasm_valtype_new(WASM_F64); }
+static inline wasm_valtype_t* wasm_valtype_new_anyref(void) { return wasm_valtype_new(WASM_ANYREF); }
+static inline wasm_valtype_t* wasm_valtype_new_funcref(void) { return wasm_valtype_new(WASM_FUNCREF); }
+
+static inline wasm_functype_t* wasm_functype_new_0_0(void) {
+  wasm_valtype_vec_t params, results;
+  wasm_valtype_vec_new_empty(&params);
+  wasm_valtype_vec_new_empty(&results);
+  return wasm_functype_new(&params, &results);
+}
+
+static inline wasm_functype_t* wasm_functype_new_1_0(wasm_valtype_t* p) {
+  wasm_valtype_t* ps[1] = {p};
+  wasm_valtype_vec_t params, results;
+  wasm_valtype_vec_new(&params, 1, ps);
+  wasm_valtype_vec_new_empty(&results);
+  return wasm_functype_new(&params, &results);
+}
+
+static inline wasm_functype_t* wasm_functype_new_0_1(wasm_valtype_t* r) {
+  wasm_valtype_t* rs[1] = {r};
+  wasm_valtype_vec_t params, results;
+  wasm_valtype_vec_new_empty(&params);
+  wasm_valtype_vec_new(&results, 1, rs);
+  return wasm_functype_new(&params, &results);
+}
+
+static inline wasm_functype_t* wasm_functype_new_1_1(wasm_valtype_t* p, wasm_valtype_t* r) {
+  wasm_valtype_t* ps[1] = {p};
+  wasm_valtype_t* rs[1] = {r};
+  wasm_valtype_vec_t params, results;
+  wasm_valtype_vec_new(&params, 1, ps);
+  wasm_valtype_vec_new(&results, 1, rs);
+  return wasm_functype_new(&params, &results);
+}
+
+static inline wasm_functype_t* wasm_functype_new_2_1(
+  wasm_valtype_t* p1, wasm_valtype_t* p2, wasm_valtype_t* r
+) {
+  wasm_valtype_t* ps[2] = {p1, p2};
+  wasm_valtype_t* rs[1] = {r};
+  wasm_valtype_vec_t params, results;
+  wasm_valtype_vec_new(&params, 2, ps);
+  wasm_valtype_vec_new(&results, 1, rs);
+  return wasm_functype_new(&params, &results);
+}
+
+#define WASM_EMPTY_VEC {0, NULL}
+#define WASM_ARRAY_VEC(array) {sizeof(array)/sizeof(*(array)), array}
+
+#define WASM_I32_VAL(x) {.kind = WASM_I32, .of = {.i32 = x}}
+#define WASM_I64_VAL(x) {.kind = WASM_I64, .of = {.i64 = x}}
+#define WASM_F32_VAL(x) {.kind = WASM_F32, .of = {.f32 = x}}
+#define WASM_F64_VAL(x) {.kind = WASM_F64, .of = {.f64 = x}}
+#define WASM_REF_VAL(x) {.kind = WASM_ANYREF, .of = {.ref = x}}
+#define WASM_INIT_VAL {.kind = WASM_ANYREF, .of = {.ref = NULL}}
+
+#endif /* WASMER_H */
@@ -0,0 +1,42 @@
+//! The cache module provides the common data structures used by
+//! compiler caches.
+
+use crate::ModuleKey;
+use std::error::Error;
+use wasmer_engine_universal::{UniversalArtifact, UniversalEngine, UniversalExecutable};
+use wasmer_vm::Tunables;
+
+/// A generic cache for storing and loading compiled WebAssembly
+/// modules.
+pub trait Cache {
+    /// The serialization error for the implementation
+    type SerializeError: Error + Send + Sync;
+    /// The deserialization error for the implementation
+    type DeserializeError: Error + Send + Sync;
+
+    /// Loads the executable stored under `key` with `engine`.
+    ///
+    /// The executable is checked like it is by
+    /// [`UniversalEngine::load_universal_executable_ref`]: it has to be
+    /// compiled for the target of `engine`, and with the memory styles
+    /// given by `tunables`.
+    ///
+    /// # Safety
+    ///
+    /// The stored data is deserialized without being validated, so
+    /// the caller has to make sure the cache hasn't been tampered
+    /// with.
+    unsafe fn load(
+        &self,
+        key: &ModuleKey,
+        engine: &UniversalEngine,
+        tunables: &dyn Tunables,
+    ) -> Result<UniversalArtifact, Self::DeserializeError>;
+
+    /// Stores `executable` under `key`.
+    fn store(
+        &mut self,
+        key: &ModuleKey,
+        executable: &UniversalExecutable,
+    ) -> Result<(), Self::SerializeError>;
+}
@@ -0,0 +1,322 @@
+use crate::{Cache, DeserializeError, ModuleKey};
+use filetime::FileTime;
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use wasmer_engine::Executable;
+use wasmer_engine_universal::{UniversalArtifact, UniversalEngine, UniversalExecutable};
+use wasmer_vm::Tunables;
+
+/// The extension of the files the modules are stored in.
+const FILE_EXTENSION: &str = "wasmu";
+
+/// The error that can happen when storing a module in a
+/// [`FileSystemCache`].
+#[derive(Error, Debug)]
+pub enum SerializeError {
+    /// An IO error
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// The executable couldn't be serialized
+    #[error("could not serialize the executable: {0}")]
+    Executable(Box<dyn Error + Send + Sync>),
+}
+
+/// Representation of a directory that contains compiled WebAssembly
+/// modules.
+///
+/// Each module is stored in a file named after its key, which is
+/// written to a temporary file first and then renamed, so that
+/// concurrent readers never see a partially written module.
+///
+/// The total size of the stored modules can be bounded, in which case
+/// the least recently used modules are removed to make room for new
+/// ones.
+///
+/// # Usage
+///
+/// ```
+/// use wasmer_cache::FileSystemCache;
+///
+/// # fn make_cache(path: &std::path::Path) -> std::io::Result<()> {
+/// let mut cache = FileSystemCache::new(path)?;
+/// cache.set_max_size(Some(512 * 1024 * 1024));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FileSystemCache {
+    path: PathBuf,
+    max_size: Option<u64>,
+}
+
+impl FileSystemCache {
+    /// Construct a new `FileSystemCache` around the specified
+    /// directory, which is created if it doesn't exist.
+    pub fn new<P: Into<PathBuf>>(path: P) -> io::Result<Self> {
+        let path: PathBuf = path.into();
+        if path.exists() {
+            let metadata = path.metadata()?;
+            if !metadata.is_dir() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    "the supplied path already points to a file",
+                ));
+            }
+            if metadata.permissions().readonly() {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    "the supplied path is readonly",
+                ));
+            }
+        } else {
+            // Create the directory and any parent directories if they don't yet exist.
+            fs::create_dir_all(&path)?;
+        }
+        Ok(Self {
+            path,
+            max_size: None,
+        })
+    }
+
+    /// Bounds the total size of the stored modules to `max_size`
+    /// bytes, or lifts the bound if `None`.
+    ///
+    /// The bound is enforced when storing a module, by removing the
+    /// least recently used modules other than the one just stored.
+    pub fn set_max_size(&mut self, max_size: Option<u64>) {
+        self.max_size = max_size;
+    }
+
+    fn entry_path(&self, key: &ModuleKey) -> PathBuf {
+        self.path.join(format!("{}.{}", key, FILE_EXTENSION))
+    }
+
+    /// Removes the least recently used modules, apart from `keep`,
+    /// until the stored modules fit in the maximum size.
+    fn evict(&self, keep: &Path) -> io::Result<()> {
+        let max_size = match self.max_size {
+            Some(max_size) => max_size,
+            None => return Ok(()),
+        };
+
+        let mut entries = Vec::new();
+        let mut total_size = 0;
+        for entry in fs::read_dir(&self.path)? {
+            let path = entry?.path();
+            if path.extension() != Some(OsStr::new(FILE_EXTENSION)) {
+                continue;
+            }
+            // Another process may have removed the file in the meantime.
+            let metadata = match fs::metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            total_size += metadata.len();
+            entries.push((metadata.modified()?, metadata.len(), path));
+        }
+
+        entries.sort();
+        for (_, size, path) in entries {
+            if total_size <= max_size {
+                break;
+            }
+            if path == keep {
+                continue;
+            }
+            match fs::remove_file(&path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+            total_size -= size;
+        }
+        Ok(())
+    }
+}
+
+impl Cache for FileSystemCache {
+    type DeserializeError = DeserializeError;
+    type SerializeError = SerializeError;
+
+    unsafe fn load(
+        &self,
+        key: &ModuleKey,
+        engine: &UniversalEngine,
+        tunables: &dyn Tunables,
+    ) -> Result<UniversalArtifact, Self::DeserializeError> {
+        let path = self.entry_path(key);
+        let artifact = unsafe { engine.load_universal_executable_file(&path, tunables)? };
+        // The modification time of a module is the last time it was
+        // used, which the eviction relies on. Failing to update it
+        // only makes the module more likely to be evicted.
+        let _ = filetime::set_file_mtime(&path, FileTime::now());
+        Ok(artifact)
+    }
+
+    fn store(
+        &mut self,
+        key: &ModuleKey,
+        executable: &UniversalExecutable,
+    ) -> Result<(), Self::SerializeError> {
+        let bytes = executable.serialize().map_err(SerializeError::Executable)?;
+        let path = self.entry_path(key);
+        let mut file = tempfile::NamedTempFile::new_in(&self.path)?;
+        file.write_all(&bytes)?;
+        file.persist(&path).map_err(|e| e.error)?;
+        self.evict(&path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Hash;
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
+    use wasmer::{
+        imports, wat2wasm, BaseTunables, Engine, Instance, Module, Pages, Singlepass, Store,
+        Universal, UniversalEngine,
+    };
+
+    fn compile(engine: &UniversalEngine, wat: &str) -> (ModuleKey, UniversalExecutable) {
+        let wasm = wat2wasm(wat.as_bytes()).unwrap();
+        let key = ModuleKey::new(
+            Hash::generate(&wasm),
+            "singlepass",
+            engine.epoch_interruption().unwrap_or(false),
+            &engine.features(),
+            engine.target(),
+        );
+        let tunables = BaseTunables::for_target(engine.target());
+        let executable = engine.compile_universal(&wasm, &tunables).unwrap();
+        (key, executable)
+    }
+
+    #[test]
+    fn store_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = FileSystemCache::new(dir.path().join("cache")).unwrap();
+        let engine = Universal::new(Singlepass::default()).engine();
+        let (key, executable) = compile(
+            &engine,
+            r#"(module (func (export "answer") (result i32) i32.const 42))"#,
+        );
+
+        let store = Store::new(&engine);
+        match unsafe { cache.load(&key, &engine, store.tunables()) } {
+            Err(DeserializeError::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::NotFound),
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("the module isn't stored yet"),
+        }
+        cache.store(&key, &executable).unwrap();
+        let artifact = unsafe { cache.load(&key, &engine, store.tunables()) }.unwrap();
+        let module = Module::from_universal_artifact(&store, Arc::new(artifact));
+        let instance = Instance::new(&module, &imports! {}).unwrap();
+        let answer = instance.get_native_function::<(), i32>("answer").unwrap();
+        assert_eq!(answer.call().unwrap(), 42);
+    }
+
+    #[test]
+    fn corrupted_module() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = FileSystemCache::new(dir.path()).unwrap();
+        let engine = Universal::new(Singlepass::default()).engine();
+        let (key, executable) = compile(&engine, "(module)");
+        cache.store(&key, &executable).unwrap();
+        fs::write(cache.entry_path(&key), b"garbage").unwrap();
+        let tunables = BaseTunables::for_target(engine.target());
+        match unsafe { cache.load(&key, &engine, &tunables) } {
+            Err(DeserializeError::Incompatible(_)) => {}
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("a corrupted module was loaded"),
+        }
+    }
+
+    #[test]
+    fn corrupted_code() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = FileSystemCache::new(dir.path()).unwrap();
+        let engine = Universal::new(Singlepass::default()).engine();
+        let (key, executable) = compile(
+            &engine,
+            r#"(module (func (export "answer") (result i32) i32.const 42))"#,
+        );
+        cache.store(&key, &executable).unwrap();
+        // The last byte of the code image, which is followed by 24 bytes
+        // of positions.
+        let path = cache.entry_path(&key);
+        let mut contents = fs::read(&path).unwrap();
+        let index = contents.len() - 25;
+        contents[index] ^= 0xFF;
+        fs::write(&path, contents).unwrap();
+        let tunables = BaseTunables::for_target(engine.target());
+        match unsafe { cache.load(&key, &engine, &tunables) } {
+            Err(DeserializeError::ContentHashMismatch) => {}
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("a corrupted module was loaded"),
+        }
+    }
+
+    #[test]
+    fn other_tunables() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = FileSystemCache::new(dir.path()).unwrap();
+        let engine = Universal::new(Singlepass::default()).engine();
+        let (key, executable) = compile(&engine, "(module (memory 1))");
+        cache.store(&key, &executable).unwrap();
+        let mut tunables = BaseTunables::for_target(engine.target());
+        tunables.static_memory_bound = Pages(16);
+        match unsafe { cache.load(&key, &engine, &tunables) } {
+            Err(DeserializeError::IncompatibleMemoryStyle { index: 0, .. }) => {}
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("the memory styles weren't checked"),
+        }
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = FileSystemCache::new(dir.path()).unwrap();
+        let engine = Universal::new(Singlepass::default()).engine();
+        let modules = (0..4)
+            .map(|i| compile(&engine, &format!("(module (func (export \"f{}\")))", i)))
+            .collect::<Vec<_>>();
+        let size = |i: usize| modules[i].1.serialize().unwrap().len() as u64;
+        let set_last_use = |cache: &FileSystemCache, i: usize, seconds: u64| {
+            let time = SystemTime::UNIX_EPOCH + Duration::from_secs(seconds);
+            filetime::set_file_mtime(
+                cache.entry_path(&modules[i].0),
+                FileTime::from_system_time(time),
+            )
+            .unwrap();
+        };
+
+        for (i, (key, executable)) in modules.iter().take(3).enumerate() {
+            cache.store(key, executable).unwrap();
+            set_last_use(&cache, i, 1000 + i as u64);
+        }
+        // Use the oldest module, so that the second one is now the
+        // least recently used.
+        let tunables = BaseTunables::for_target(engine.target());
+        unsafe { cache.load(&modules[0].0, &engine, &tunables) }.unwrap();
+
+        cache.set_max_size(Some(size(0) + size(2) + size(3)));
+        cache.store(&modules[3].0, &modules[3].1).unwrap();
+        for (i, (key, _)) in modules.iter().enumerate() {
+            assert_eq!(cache.entry_path(key).exists(), i != 1, "module {}", i);
+        }
+
+        // The module just stored is kept even if it doesn't fit.
+        cache.set_max_size(Some(0));
+        cache.store(&modules[1].0, &modules[1].1).unwrap();
+        for (i, (key, _)) in modules.iter().enumerate() {
+            assert_eq!(cache.entry_path(key).exists(), i == 1, "module {}", i);
+        }
+    }
+}
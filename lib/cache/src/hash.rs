@@ -0,0 +1,73 @@
+use crate::DeserializeError;
+use std::fmt;
+use std::str::FromStr;
+
+/// A hash used as a key when loading and storing modules in a
+/// [`Cache`](crate::Cache).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Hash([u8; 32]);
+
+impl Hash {
+    /// Creates a new instance from 32 raw bytes.
+    /// Does not perform any hashing. In order to create a hash from data,
+    /// use `Hash::generate`.
+    pub fn new(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// Creates a new hash from a slice of bytes.
+    pub fn generate(bytes: &[u8]) -> Self {
+        Self(blake3::hash(bytes).into())
+    }
+
+    /// The raw bytes of the hash.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.0))
+    }
+}
+
+impl FromStr for Hash {
+    type Err = DeserializeError;
+    /// Create hash from hexadecimal representation
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = hex::decode(s).map_err(|e| {
+            DeserializeError::Generic(format!(
+                "Could not decode prehashed key as hexadecimal: {}",
+                e
+            ))
+        })?;
+        if bytes.len() != 32 {
+            return Err(DeserializeError::Generic(
+                "Prehashed keys must deserialize into exactly 32 bytes".to_string(),
+            ));
+        }
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(&bytes);
+        Ok(Self(hash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_to_string_and_back() {
+        let hash = Hash::generate(b"\0asm\x01\0\0\0");
+        let string = hash.to_string();
+        assert_eq!(string.len(), 64);
+        assert_eq!(string.parse::<Hash>().unwrap(), hash);
+    }
+
+    #[test]
+    fn hash_from_invalid_string() {
+        assert!("not hexadecimal".parse::<Hash>().is_err());
+        assert!("abcd".parse::<Hash>().is_err());
+    }
+}
@@ -0,0 +1,118 @@
+use crate::Hash;
+use std::fmt;
+use wasmer_compiler::{Features, Target};
+
+/// The key a compiled module is cached under.
+///
+/// Besides the hash of the WebAssembly binary, it covers everything
+/// that affects the code generated for it, so that a module compiled
+/// differently is never loaded from the cache by mistake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ModuleKey(Hash);
+
+impl ModuleKey {
+    /// Creates the key of the module with hash `module` compiled by
+    /// `compiler` with `features` for `target`, with epoch interruption
+    /// checks if `epoch_interruption` is set.
+    ///
+    /// `compiler` names the compiler, and should also account for
+    /// any other configuration of it changing the generated code, such
+    /// as its middlewares.
+    pub fn new(
+        module: Hash,
+        compiler: &str,
+        epoch_interruption: bool,
+        features: &Features,
+        target: &Target,
+    ) -> Self {
+        // Destructure the features so that adding one can't be
+        // forgotten here.
+        let Features {
+            threads,
+            reference_types,
+            simd,
+            bulk_memory,
+            multi_value,
+            tail_call,
+            module_linking,
+            multi_memory,
+            memory64,
+            exceptions,
+        } = *features;
+
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(module.as_bytes());
+        hasher.update(&(compiler.len() as u64).to_le_bytes());
+        hasher.update(compiler.as_bytes());
+        hasher.update(&[epoch_interruption as u8]);
+        hasher.update(&[
+            threads as u8,
+            reference_types as u8,
+            simd as u8,
+            bulk_memory as u8,
+            multi_value as u8,
+            tail_call as u8,
+            module_linking as u8,
+            multi_memory as u8,
+            memory64 as u8,
+            exceptions as u8,
+        ]);
+        let triple = target.triple().to_string();
+        hasher.update(&(triple.len() as u64).to_le_bytes());
+        hasher.update(triple.as_bytes());
+        hasher.update(&target.cpu_features().as_u64().to_le_bytes());
+        hasher.update(crate::VERSION.as_bytes());
+        Self(Hash::new(hasher.finalize().into()))
+    }
+}
+
+impl fmt::Display for ModuleKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmer_compiler::CpuFeature;
+
+    #[test]
+    fn key_covers_compilation_settings() {
+        let module = Hash::generate(b"\0asm\x01\0\0\0");
+        let features = Features::default();
+        let target = Target::default();
+        let key = ModuleKey::new(module, "singlepass", false, &features, &target);
+        assert_eq!(
+            key,
+            ModuleKey::new(module, "singlepass", false, &features, &target)
+        );
+
+        let other = Hash::generate(b"\0asm\x01\0\0\0\0");
+        assert_ne!(
+            key,
+            ModuleKey::new(other, "singlepass", false, &features, &target)
+        );
+        assert_ne!(
+            key,
+            ModuleKey::new(module, "cranelift", false, &features, &target)
+        );
+        assert_ne!(
+            key,
+            ModuleKey::new(module, "singlepass", true, &features, &target)
+        );
+        let mut threads = features.clone();
+        threads.threads(!features.threads);
+        assert_ne!(
+            key,
+            ModuleKey::new(module, "singlepass", false, &threads, &target)
+        );
+        let baseline = Target::new(target.triple().clone(), CpuFeature::set());
+        if baseline != target {
+            assert_ne!(
+                key,
+                ModuleKey::new(module, "singlepass", false, &features, &baseline)
+            );
+        }
+    }
+}
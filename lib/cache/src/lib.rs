@@ -0,0 +1,37 @@
+//! The `wasmer-cache` crate allows to cache compiled WebAssembly
+//! modules, keyed by the hash of the WebAssembly binary and by
+//! everything that affects how it was compiled.
+
+#![deny(missing_docs, trivial_numeric_casts, unused_extern_crates)]
+#![warn(unused_import_braces)]
+#![cfg_attr(
+    feature = "cargo-clippy",
+    allow(clippy::new_without_default, clippy::new_without_default)
+)]
+#![cfg_attr(
+    feature = "cargo-clippy",
+    warn(
+        clippy::float_arithmetic,
+        clippy::mut_mut,
+        clippy::nonminimal_bool,
+        clippy::option_map_unwrap_or,
+        clippy::option_map_unwrap_or_else,
+        clippy::print_stdout,
+        clippy::unicode_not_nfc,
+        clippy::use_self
+    )
+)]
+
+mod cache;
+mod filesystem;
+mod hash;
+mod key;
+
+pub use crate::cache::Cache;
+pub use crate::filesystem::{FileSystemCache, SerializeError};
+pub use crate::hash::Hash;
+pub use crate::key::ModuleKey;
+pub use wasmer_engine::DeserializeError;
+
+/// Version number of this crate.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -0,0 +1,42 @@
+//! Generate a header file for the object file produced by the object file engine.
+
+use super::{generate_c, CStatement, CType};
+use wasmer_engine_object::CODE_SYMBOL;
+
+/// Helper functions to simplify the usage of the object file engine.
+const HELPER_FUNCTIONS: &str = r#"
+wasm_module_t* wasmer_object_file_module_new(wasm_store_t* store) {
+        return wasmer_module_load_object_file(store, &WASMER_CODE[0]);
+}
+"#;
+
+/// Generate the header file that goes with the generated object file.
+///
+/// The object file defines the descriptor of its code, which
+/// `wasmer_object_file_module_new` loads into a store.
+pub fn generate_header_file() -> String {
+    let mut c_statements = vec![];
+    c_statements.push(CStatement::LiteralConstant {
+        value: "#include <stdlib.h>\n#include <string.h>\n\n".to_string(),
+    });
+    c_statements.push(CStatement::LiteralConstant {
+        value: "#ifdef __cplusplus\nextern \"C\" {\n#endif\n\n".to_string(),
+    });
+    c_statements.push(CStatement::Declaration {
+        name: CODE_SYMBOL.to_string(),
+        is_extern: true,
+        is_const: false,
+        ctype: CType::Array {
+            inner: Box::new(CType::U8),
+        },
+        definition: None,
+    });
+    c_statements.push(CStatement::LiteralConstant {
+        value: HELPER_FUNCTIONS.to_string(),
+    });
+    c_statements.push(CStatement::LiteralConstant {
+        value: "\n#ifdef __cplusplus\n}\n#endif\n\n".to_string(),
+    });
+
+    generate_c(&c_statements)
+}
@@ -4,6 +4,7 @@ use anyhow::{Context, Result};
 use std::path::PathBuf;
 use structopt::StructOpt;
 use wasmer::*;
+use wasmer_engine::Executable;
 
 #[derive(Debug, StructOpt)]
 /// The options for the `wasmer compile` subcommand
@@ -44,16 +45,14 @@ impl Compile {
     ) -> Result<&'static str> {
         Ok(match engine_type {
             #[cfg(feature = "dylib")]
-            EngineType::Dylib => {
-                wasmer_engine_dylib::DylibArtifact::get_default_extension(target_triple)
-            }
+            EngineType::Dylib => wasmer_engine_object::ObjectKind::SharedObject.default_extension(),
             #[cfg(feature = "universal")]
             EngineType::Universal => {
                 wasmer_engine_universal::UniversalArtifact::get_default_extension(target_triple)
             }
             #[cfg(feature = "staticlib")]
             EngineType::Staticlib => {
-                wasmer_engine_staticlib::StaticlibArtifact::get_default_extension(target_triple)
+                wasmer_engine_object::ObjectKind::Relocatable.default_extension()
             }
             #[cfg(not(all(feature = "dylib", feature = "universal", feature = "staticlib")))]
             _ => bail!("selected engine type is not compiled in"),
@@ -98,9 +97,13 @@ impl Compile {
         println!("Compiler: {}", compiler_type.to_string());
         println!("Target: {}", target.triple());
 
-        let module = Module::from_file(&store, &self.path)?;
-
-        let _ = module.serialize_to_file(&self.output)?;
+        let wasm_bytes = std::fs::read(&self.path)?;
+        let wasm_bytes = wat2wasm(&wasm_bytes)?;
+        let executable = store.engine().compile(&wasm_bytes, store.tunables())?;
+        let serialized = executable
+            .serialize()
+            .map_err(|e| anyhow!("failed to serialize the module: {}", e))?;
+        std::fs::write(&self.output, serialized)?;
         eprintln!(
             "✔ File compiled successfully to `{}`.",
             self.output.display(),
@@ -108,16 +111,7 @@ impl Compile {
 
         #[cfg(feature = "staticlib")]
         if engine_type == EngineType::Staticlib {
-            let artifact: &wasmer_engine_staticlib::StaticlibArtifact =
-                module.artifact().as_ref().downcast_ref().context("Engine type is Staticlib but could not downcast artifact into StaticlibArtifact")?;
-            let symbol_registry = artifact.symbol_registry();
-            let metadata_length = artifact.metadata_length();
-            let module_info = module.info();
-            let header_file_src = crate::c_gen::staticlib_header::generate_header_file(
-                module_info,
-                symbol_registry,
-                metadata_length,
-            );
+            let header_file_src = crate::c_gen::object_header::generate_header_file();
 
             let header_path = self.header_path.as_ref().cloned().unwrap_or_else(|| {
                 let mut hp = PathBuf::from(
@@ -8,6 +8,7 @@ use std::path::{Path, PathBuf};
 use std::process::Command;
 use structopt::StructOpt;
 use wasmer::*;
+use wasmer_engine::Executable;
 
 const WASMER_MAIN_C_SOURCE: &[u8] = include_bytes!("wasmer_create_exe_main.c");
 
@@ -76,24 +77,20 @@ impl CreateExe {
         let wasm_object_path = PathBuf::from("wasm.obj");
 
         let wasm_module_path = starting_cd.join(&self.path);
-
-        let module =
-            Module::from_file(&store, &wasm_module_path).context("failed to compile Wasm")?;
-        let _ = module.serialize_to_file(&wasm_object_path)?;
-
-        let artifact: &wasmer_engine_staticlib::StaticlibArtifact =
-            module.artifact().as_ref().downcast_ref().context(
-                "Engine type is Staticlib but could not downcast artifact into StaticlibArtifact",
-            )?;
-        let symbol_registry = artifact.symbol_registry();
-        let metadata_length = artifact.metadata_length();
-        let module_info = module.info();
-        let header_file_src = crate::c_gen::staticlib_header::generate_header_file(
-            module_info,
-            symbol_registry,
-            metadata_length,
-        );
-
+        let wasm_bytes = fs::read(&wasm_module_path)
+            .with_context(|| format!("failed to read `{}`", wasm_module_path.display()))?;
+        let wasm_bytes = wat2wasm(&wasm_bytes)?;
+
+        let executable = store
+            .engine()
+            .compile(&wasm_bytes, store.tunables())
+            .context("failed to compile Wasm")?;
+        let object = executable
+            .serialize()
+            .map_err(|e| anyhow!("failed to write the object file: {}", e))?;
+        fs::write(&wasm_object_path, object)?;
+
+        let header_file_src = crate::c_gen::object_header::generate_header_file();
         generate_header(header_file_src.as_bytes())?;
         self.compile_c(wasm_object_path, output_path)?;
 
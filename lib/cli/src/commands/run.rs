@@ -3,11 +3,26 @@ use crate::common::get_cache_dir;
 use crate::logging;
 use crate::store::{CompilerType, EngineType, StoreOptions};
 use crate::suggestions::suggest_function_exports;
+use crate::utils::parse_preload;
+#[cfg(feature = "wasi")]
+use crate::utils::{parse_envvar, parse_mapdir};
 use crate::warning;
 use anyhow::{anyhow, Context, Result};
 use std::path::PathBuf;
+use std::ptr;
 use std::str::FromStr;
+#[cfg(feature = "cache")]
+use std::sync::Arc;
 use wasmer::*;
+#[cfg(feature = "cache")]
+use wasmer_cache::{Cache, FileSystemCache, Hash, ModuleKey};
+use wasmer_types::{FastGasCounter, InstanceConfig};
+#[cfg(feature = "wasi")]
+use wasmer_wasi::{HostFs, WasiEnv, WasiError, WasiState, WASI_NAMESPACE};
+
+/// The maximum size in bytes of the modules in the cache.
+#[cfg(feature = "cache")]
+const CACHE_MAX_SIZE: u64 = 1 << 30;
 
 use structopt::StructOpt;
 
@@ -22,12 +37,76 @@ pub struct Run {
     #[structopt(long = "invoke", short = "i")]
     invoke: Option<String>,
 
+    /// Instantiate a module and link it as the import namespace `NAME`.
+    /// Modules are instantiated in order, and can import the ones preloaded
+    /// before them
+    #[structopt(
+        long = "preload",
+        value_name = "NAME=PATH",
+        number_of_values = 1,
+        parse(try_from_str = parse_preload)
+    )]
+    preload: Vec<(String, PathBuf)>,
+
+    /// The amount of gas that can be burnt before execution traps
+    #[structopt(long = "gas-limit")]
+    gas_limit: Option<u64>,
+
+    /// The gas burnt for each unit of gas the module charges
+    #[structopt(long = "opcode-cost", default_value = "1")]
+    opcode_cost: u64,
+
+    /// The stack limit, in 8-byte slots
+    #[structopt(long = "stack-limit")]
+    stack_limit: Option<i32>,
+
+    /// Print the results, the burnt gas and the trap code as JSON
+    #[structopt(long = "json")]
+    json: bool,
+
+    /// WASI pre-opened directory
+    #[cfg(feature = "wasi")]
+    #[structopt(long = "dir", name = "DIR", number_of_values = 1, parse(from_os_str))]
+    pre_opened_directories: Vec<PathBuf>,
+
+    /// Map a host directory to a different location for the Wasm module
+    #[cfg(feature = "wasi")]
+    #[structopt(
+        long = "mapdir",
+        name = "GUEST_DIR:HOST_DIR",
+        number_of_values = 1,
+        parse(try_from_str = parse_mapdir)
+    )]
+    mapped_dirs: Vec<(String, PathBuf)>,
+
+    /// Pass custom environment variables
+    #[cfg(feature = "wasi")]
+    #[structopt(
+        long = "env",
+        name = "KEY=VALUE",
+        number_of_values = 1,
+        parse(try_from_str = parse_envvar)
+    )]
+    env_vars: Vec<(String, String)>,
+
     /// The command name is a string that will override the first argument passed
     /// to the wasm program. This is used in wapm to provide nicer output in
     /// help commands and error messages of the running wasm program
     #[structopt(long = "command-name", hidden = true)]
     command_name: Option<String>,
 
+    /// Disable the cache
+    #[cfg(feature = "cache")]
+    #[structopt(long = "disable-cache")]
+    disable_cache: bool,
+
+    /// A prehashed string, used to speed up start times by avoiding hashing the
+    /// wasm module. If the specified hash is not found, Wasmer will hash the module
+    /// as if no `cache-key` argument was passed.
+    #[cfg(feature = "cache")]
+    #[structopt(long = "cache-key", hidden = true)]
+    cache_key: Option<String>,
+
     #[structopt(flatten)]
     store: StoreOptions,
 
@@ -67,55 +146,158 @@ impl Run {
 
     fn inner_execute(&self) -> Result<()> {
         let module = self.get_module()?;
-        let instance = Instance::new(&module, &imports! {})?;
+        // All the instances burn gas from this counter, which outlives them.
+        let mut gas_counter =
+            FastGasCounter::new(self.gas_limit.unwrap_or(u64::MAX), self.opcode_cost);
+        let config = unsafe {
+            let config = InstanceConfig::default().with_counter(ptr::addr_of_mut!(gas_counter));
+            match self.stack_limit {
+                Some(stack_limit) => config.with_stack_limit(stack_limit),
+                None => config,
+            }
+        };
+        #[allow(unused_mut)]
+        let mut imports = self.preload(module.store(), &config)?;
+        #[cfg(feature = "wasi")]
+        imports.register(WASI_NAMESPACE, self.wasi_env()?.exports(module.store()));
+        let instance = Instance::new_with_config(&module, config, &imports)?;
 
         // If this module exports an _initialize function, run that first.
-        if let Ok(initialize) = instance.exports.get_function("_initialize") {
+        if let Some(initialize) = instance.lookup_function("_initialize") {
             initialize
                 .call(&[])
                 .with_context(|| "failed to run _initialize function")?;
         }
 
         // Do we want to invoke a function?
-        if let Some(ref invoke) = self.invoke {
-            let imports = imports! {};
-            let instance = Instance::new(&module, &imports)?;
-            let result = self.invoke_function(&instance, &invoke, &self.args)?;
+        let result = if let Some(ref invoke) = self.invoke {
+            self.invoke_function(&instance, invoke, &self.args)
+        } else {
+            let start: Function = self.try_find_function(&instance, "_start", &[])?;
+            start.call(&[]).map_err(Into::into)
+        };
+        #[cfg(feature = "wasi")]
+        let exit_code = result.as_ref().err().and_then(wasi_exit_code);
+        #[cfg(not(feature = "wasi"))]
+        let exit_code: Option<u32> = None;
+        let result = match exit_code {
+            // Exiting successfully isn't an error.
+            Some(0) => Ok(Box::new([]) as Box<[Val]>),
+            _ => result,
+        };
+
+        if self.json {
+            println!("{}", json_report(&result, gas_counter.burnt()));
+        }
+        if let Some(code) = exit_code.filter(|code| *code != 0) {
+            // The program reported its failure itself.
+            std::process::exit(code as i32);
+        }
+        if self.json {
+            result?;
+        } else if self.invoke.is_some() {
             println!(
                 "{}",
-                result
+                result?
                     .iter()
                     .map(|val| val.to_string())
                     .collect::<Vec<String>>()
                     .join(" ")
             );
         } else {
-            let start: Function = self.try_find_function(&instance, "_start", &[])?;
-            let result = start.call(&[]);
             result?;
         }
 
         Ok(())
     }
 
+    /// Instantiate the preloaded modules, in order, into the imports of the
+    /// module to run.
+    fn preload(&self, store: &Store, config: &InstanceConfig) -> Result<ImportObject> {
+        let mut imports = ImportObject::new();
+        for (name, path) in &self.preload {
+            let module = Module::from_file(store, path).with_context(|| {
+                format!("failed to compile preloaded module `{}`", path.display())
+            })?;
+            let instance = Instance::new_with_config(&module, config.clone(), &imports)
+                .with_context(|| format!("failed to instantiate preloaded module `{}`", name))?;
+            imports.register(name, instance.exports());
+        }
+        Ok(imports)
+    }
+
+    /// Create the WASI environment of the module, from the arguments, the
+    /// environment variables and the directories it's given.
+    #[cfg(feature = "wasi")]
+    fn wasi_env(&self) -> Result<WasiEnv> {
+        let program_name = self
+            .command_name
+            .clone()
+            .unwrap_or_else(|| self.path.to_string_lossy().into_owned());
+        let mut state = WasiState::new(program_name).envs(self.env_vars.clone());
+        // When invoking a function, the arguments are its parameters.
+        if self.invoke.is_none() {
+            state = state.args(self.args.clone());
+        }
+        for dir in &self.pre_opened_directories {
+            let fs = HostFs::new(dir)
+                .with_context(|| format!("failed to preopen `{}`", dir.display()))?;
+            state = state.preopen(dir.to_string_lossy(), fs);
+        }
+        for (alias, dir) in &self.mapped_dirs {
+            let fs = HostFs::new(dir)
+                .with_context(|| format!("failed to preopen `{}`", dir.display()))?;
+            state = state.preopen(alias.clone(), fs);
+        }
+        Ok(state.finalize()?)
+    }
+
     fn get_module(&self) -> Result<Module> {
         let contents = std::fs::read(self.path.clone())?;
         #[cfg(feature = "universal")]
         {
-            use wasmer_engine_universal::{Universal, UniversalArtifact, UniversalExecutable};
-
-            if UniversalExecutable::verify_serialized(&contents) {
-                unsafe {
-                    let executable = UniversalExecutable::archive_from_slice(&contents)?;
-                    let engine = wasmer_engine_universal::Universal::headless().engine();
-                    let artifact = engine.load(&executable);
-                    let store = Store::new(&engine);
-                    let module = unsafe { Module::deserialize_from_file(&store, &self.path)? };
-                }
+            use wasmer_engine_universal::{Universal, UniversalExecutableRef};
+
+            if UniversalExecutableRef::verify_serialized(&contents).is_ok() {
+                let engine = Universal::headless().engine();
+                let store = Store::new(&engine);
+                let module = unsafe { Module::deserialize_from_file(&store, &self.path)? };
                 return Ok(module);
             }
         }
+        #[cfg(any(feature = "dylib", feature = "staticlib"))]
+        if contents.starts_with(b"\x7fELF") {
+            // An object file written by `wasmer compile` with the dylib
+            // or the staticlib engine.
+            use wasmer_engine_object::{read_kind, ObjectFile, ObjectKind};
+            let engine = ObjectFile::headless().engine();
+            let store = Store::new(&engine);
+            let artifact = match read_kind(&contents)? {
+                ObjectKind::Relocatable => unsafe {
+                    engine.load_object_file(&contents, store.tunables())?
+                },
+                #[cfg(unix)]
+                ObjectKind::SharedObject => unsafe {
+                    engine.load_shared_object(&self.path, store.tunables())?
+                },
+                #[cfg(not(unix))]
+                ObjectKind::SharedObject => {
+                    return Err(anyhow!("shared objects can only be loaded on unix"))
+                }
+            };
+            return Ok(Module::from_universal_artifact(
+                &store,
+                std::sync::Arc::new(artifact),
+            ));
+        }
         let (store, engine_type, compiler_type) = self.store.get_store()?;
+        #[cfg(feature = "cache")]
+        let module_result: Result<Module> = if !self.disable_cache && contents.len() > 0x1000 {
+            self.get_module_from_cache(&store, &contents, &compiler_type)
+        } else {
+            Module::new(&store, &contents).map_err(|e| e.into())
+        };
+        #[cfg(not(feature = "cache"))]
         let module_result = Module::new(&store, &contents);
 
         let mut module = module_result.with_context(|| {
@@ -136,14 +318,17 @@ impl Run {
         &self,
         store: &Store,
         contents: &[u8],
-        engine_type: &EngineType,
         compiler_type: &CompilerType,
     ) -> Result<Module> {
         // We try to get it from cache, in case caching is enabled
         // and the file length is greater than 4KB.
         // For files smaller than 4KB caching is not worth,
         // as it takes space and the speedup is minimal.
-        let mut cache = self.get_cache(engine_type, compiler_type)?;
+        let engine: &dyn Engine = &**store.engine();
+        let engine = engine
+            .downcast_ref::<UniversalEngine>()
+            .ok_or_else(|| anyhow!("the module cache requires the universal engine"))?;
+        let mut cache = self.get_cache()?;
         // Try to get the hash from the provided `--cache-key`, otherwise
         // generate one from the provided file `.wasm` contents.
         let hash = self
@@ -151,8 +336,15 @@ impl Run {
             .as_ref()
             .and_then(|key| Hash::from_str(&key).ok())
             .unwrap_or_else(|| Hash::generate(&contents));
-        match unsafe { cache.load(&store, hash) } {
-            Ok(module) => Ok(module),
+        let key = ModuleKey::new(
+            hash,
+            &compiler_type.to_string(),
+            engine.epoch_interruption().unwrap_or(false),
+            &engine.features(),
+            engine.target(),
+        );
+        let artifact = match unsafe { cache.load(&key, engine, store.tunables()) } {
+            Ok(artifact) => artifact,
             Err(e) => {
                 match e {
                     DeserializeError::Io(_) => {
@@ -162,44 +354,26 @@ impl Run {
                         warning!("cached module is corrupted: {}", err);
                     }
                 }
-                let module = Module::new(&store, &contents)?;
-                // Store the compiled Module in cache
-                cache.store(hash, &module)?;
-                Ok(module)
+                #[cfg(feature = "wat")]
+                let contents = wat2wasm(contents)?;
+                engine.validate(&contents)?;
+                let executable = engine.compile_universal(&contents, store.tunables())?;
+                // Store the compiled Module in cache, failing to do so
+                // shouldn't prevent running it.
+                if let Err(err) = cache.store(&key, &executable) {
+                    warning!("could not cache the module: {}", err);
+                }
+                engine.load_universal_executable(&executable)?
             }
-        }
+        };
+        Ok(Module::from_universal_artifact(store, Arc::new(artifact)))
     }
 
     #[cfg(feature = "cache")]
-    /// Get the Compiler Filesystem cache
-    fn get_cache(
-        &self,
-        engine_type: &EngineType,
-        compiler_type: &CompilerType,
-    ) -> Result<FileSystemCache> {
-        let mut cache_dir_root = get_cache_dir();
-        cache_dir_root.push(compiler_type.to_string());
-        let mut cache = FileSystemCache::new(cache_dir_root)?;
-
-        // Important: Dylib files need to have a `.dll` extension on
-        // Windows, otherwise they will not load, so we just add an
-        // extension always to make it easier to recognize as well.
-        #[allow(unreachable_patterns)]
-        let extension = match *engine_type {
-            #[cfg(feature = "dylib")]
-            EngineType::Dylib => {
-                wasmer_engine_dylib::DylibArtifact::get_default_extension(&Triple::host())
-                    .to_string()
-            }
-            #[cfg(feature = "universal")]
-            EngineType::Universal => {
-                wasmer_engine_universal::UniversalArtifact::get_default_extension(&Triple::host())
-                    .to_string()
-            }
-            // We use the compiler type as the default extension
-            _ => compiler_type.to_string(),
-        };
-        cache.set_cache_extension(Some(extension));
+    /// Get the Filesystem cache
+    fn get_cache(&self) -> Result<FileSystemCache> {
+        let mut cache = FileSystemCache::new(get_cache_dir())?;
+        cache.set_max_size(Some(CACHE_MAX_SIZE));
         Ok(cache)
     }
 
@@ -210,7 +384,7 @@ impl Run {
         args: &[String],
     ) -> Result<Function> {
         Ok(instance
-            .exports
+            .exports()
             .get_function(&name)
             .map_err(|e| {
                 if instance.module().info().functions.is_empty() {
@@ -273,33 +447,7 @@ impl Run {
         let invoke_args = args
             .iter()
             .zip(func_ty.params().iter())
-            .map(|(arg, param_type)| match param_type {
-                ValType::I32 => {
-                    Ok(Val::I32(arg.parse().map_err(|_| {
-                        anyhow!("Can't convert `{}` into a i32", arg)
-                    })?))
-                }
-                ValType::I64 => {
-                    Ok(Val::I64(arg.parse().map_err(|_| {
-                        anyhow!("Can't convert `{}` into a i64", arg)
-                    })?))
-                }
-                ValType::F32 => {
-                    Ok(Val::F32(arg.parse().map_err(|_| {
-                        anyhow!("Can't convert `{}` into a f32", arg)
-                    })?))
-                }
-                ValType::F64 => {
-                    Ok(Val::F64(arg.parse().map_err(|_| {
-                        anyhow!("Can't convert `{}` into a f64", arg)
-                    })?))
-                }
-                _ => Err(anyhow!(
-                    "Don't know how to convert {} into {:?}",
-                    arg,
-                    param_type
-                )),
-            })
+            .map(|(arg, param_type)| parse_argument(arg, param_type))
             .collect::<Result<Vec<_>>>()?;
         Ok(func.call(&invoke_args)?)
     }
@@ -358,3 +506,91 @@ impl Run {
         bail!("binfmt_misc is only available on linux.")
     }
 }
+
+/// Parses an argument of type `ty`, given either as a plain value or
+/// prefixed by its type, like `i64:42`.
+fn parse_argument(arg: &str, ty: &ValType) -> Result<Val> {
+    let value = match arg.split_once(':') {
+        Some((prefix, value)) => {
+            if !prefix.eq_ignore_ascii_case(&ty.to_string()) {
+                bail!(
+                    "Argument `{}` is a {}, but a {} was expected",
+                    arg,
+                    prefix,
+                    type_name(ty)
+                );
+            }
+            value
+        }
+        None => arg,
+    };
+    match ty {
+        ValType::I32 => {
+            Ok(Val::I32(value.parse().map_err(|_| {
+                anyhow!("Can't convert `{}` into a i32", value)
+            })?))
+        }
+        ValType::I64 => {
+            Ok(Val::I64(value.parse().map_err(|_| {
+                anyhow!("Can't convert `{}` into a i64", value)
+            })?))
+        }
+        ValType::F32 => {
+            Ok(Val::F32(value.parse().map_err(|_| {
+                anyhow!("Can't convert `{}` into a f32", value)
+            })?))
+        }
+        ValType::F64 => {
+            Ok(Val::F64(value.parse().map_err(|_| {
+                anyhow!("Can't convert `{}` into a f64", value)
+            })?))
+        }
+        _ => Err(anyhow!("Don't know how to convert {} into {:?}", arg, ty)),
+    }
+}
+
+/// The exit code of a WASI program that failed by calling `proc_exit`.
+#[cfg(feature = "wasi")]
+fn wasi_exit_code(error: &anyhow::Error) -> Option<u32> {
+    match error
+        .downcast_ref::<RuntimeError>()?
+        .downcast_ref::<WasiError>()?
+    {
+        WasiError::Exit(code) => Some(*code),
+    }
+}
+
+/// The name of `ty` in the WebAssembly text format.
+fn type_name(ty: &ValType) -> String {
+    ty.to_string().to_lowercase()
+}
+
+/// Reports the outcome of running a function: its results, the gas burnt
+/// and, if it failed, its trap code and error.
+///
+/// Values are written as strings, so that they round-trip exactly.
+fn json_report(result: &Result<Box<[Val]>>, burnt_gas: u64) -> serde_json::Value {
+    let (results, trap, error) = match result {
+        Ok(results) => (
+            results
+                .iter()
+                .map(|val| serde_json::json!({ "type": type_name(&val.ty()), "value": val.to_string() }))
+                .collect(),
+            None,
+            None,
+        ),
+        Err(e) => (
+            Vec::new(),
+            e.downcast_ref::<RuntimeError>()
+                .and_then(|e| e.clone().to_trap())
+                .map(|code| code.to_string()),
+            Some(format!("{:#}", e)),
+        ),
+    };
+    serde_json::json!({
+        "results": results,
+        "burnt_gas": burnt_gas,
+        "trap": trap,
+        "error": error,
+    })
+}
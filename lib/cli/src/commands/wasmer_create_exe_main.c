@@ -7,9 +7,6 @@
 
 #define own
 
-// TODO: make this define templated so that the Rust code can toggle it on/off
-#define WASI
-
 static void print_wasmer_error() {
   int error_len = wasmer_last_error_length();
   printf("Error len: `%d`\n", error_len);
@@ -19,27 +16,33 @@ static void print_wasmer_error() {
   free(error_str);
 }
 
-#ifdef WASI
 static void pass_mapdir_arg(wasi_config_t *wasi_config, char *mapdir) {
-  int colon_location = strchr(mapdir, ':') - mapdir;
-  if (colon_location == 0) {
+  char *colon = strchr(mapdir, ':');
+  if (!colon || colon == mapdir) {
     // error malformed argument
     fprintf(stderr, "Expected mapdir argument of the form alias:directory\n");
     exit(-1);
   }
+  int colon_location = colon - mapdir;
 
   char *alias = (char *)malloc(colon_location + 1);
   memcpy(alias, mapdir, colon_location);
   alias[colon_location] = '\0';
 
-  int dir_len = strlen(mapdir) - colon_location;
-  char *dir = (char *)malloc(dir_len + 1);
-  memcpy(dir, &mapdir[colon_location + 1], dir_len);
-  dir[dir_len] = '\0';
-
-  wasi_config_mapdir(wasi_config, alias, dir);
+  if (!wasi_config_mapdir(wasi_config, alias, colon + 1)) {
+    fprintf(stderr, "Failed to preopen `%s`\n", colon + 1);
+    print_wasmer_error();
+    exit(-1);
+  }
   free(alias);
-  free(dir);
+}
+
+static void pass_dir_arg(wasi_config_t *wasi_config, char *dir) {
+  if (!wasi_config_preopen_dir(wasi_config, dir)) {
+    fprintf(stderr, "Failed to preopen `%s`\n", dir);
+    print_wasmer_error();
+    exit(-1);
+  }
 }
 
 // We try to parse out `--dir` and `--mapdir` ahead of time and process those
@@ -53,7 +56,7 @@ static void handle_arguments(wasi_config_t *wasi_config, int argc,
       // next arg is a preopen directory
       if ((i + 1) < argc) {
         i++;
-        wasi_config_preopen_dir(wasi_config, argv[i]);
+        pass_dir_arg(wasi_config, argv[i]);
       } else {
         fprintf(stderr, "--dir expects a following argument specifying which "
                         "directory to preopen\n");
@@ -73,7 +76,7 @@ static void handle_arguments(wasi_config_t *wasi_config, int argc,
     } else if (strncmp(argv[i], "--dir=", strlen("--dir=")) == 0) {
       // this arg is a preopen dir
       char *dir = argv[i] + strlen("--dir=");
-      wasi_config_preopen_dir(wasi_config, dir);
+      pass_dir_arg(wasi_config, dir);
     } else if (strncmp(argv[i], "--mapdir=", strlen("--mapdir=")) == 0) {
       // this arg is a mapdir
       char *mapdir = argv[i] + strlen("--mapdir=");
@@ -84,15 +87,12 @@ static void handle_arguments(wasi_config_t *wasi_config, int argc,
     }
   }
 }
-#endif
 
 int main(int argc, char *argv[]) {
-  wasm_config_t *config = wasm_config_new();
-  wasm_config_set_engine(config, STATICLIB);
-  wasm_engine_t *engine = wasm_engine_new_with_config(config);
+  wasm_engine_t *engine = wasm_engine_new();
   wasm_store_t *store = wasm_store_new(engine);
 
-  wasm_module_t *module = wasmer_staticlib_engine_new(store, argv[0]);
+  wasm_module_t *module = wasmer_object_file_module_new(store);
 
   if (!module) {
     fprintf(stderr, "Failed to create module\n");
@@ -100,10 +100,6 @@ int main(int argc, char *argv[]) {
     return -1;
   }
 
-  // We have now finished the memory buffer book keeping and we have a valid
-  // Module.
-
-#ifdef WASI
   wasi_config_t *wasi_config = wasi_config_new(argv[0]);
   handle_arguments(wasi_config, argc, argv);
 
@@ -113,16 +109,8 @@ int main(int argc, char *argv[]) {
     print_wasmer_error();
     return 1;
   }
-#endif
-
-  wasm_importtype_vec_t import_types;
-  wasm_module_imports(module, &import_types);
 
   wasm_extern_vec_t imports;
-  wasm_extern_vec_new_uninitialized(&imports, import_types.size);
-  wasm_importtype_vec_delete(&import_types);
-
-#ifdef WASI
   bool get_imports_result = wasi_get_imports(store, module, wasi_env, &imports);
   wasi_env_delete(wasi_env);
 
@@ -132,9 +120,9 @@ int main(int argc, char *argv[]) {
 
     return 1;
   }
-#endif
 
   wasm_instance_t *instance = wasm_instance_new(store, module, &imports, NULL);
+  wasm_extern_vec_delete(&imports);
 
   if (!instance) {
     fprintf(stderr, "Failed to create instance\n");
@@ -142,8 +130,7 @@ int main(int argc, char *argv[]) {
     return -1;
   }
 
-#ifdef WASI
-  own wasm_func_t *start_function = wasi_get_start_function(instance);
+  own wasm_extern_t *start_function = wasmer_instance_get_export(instance, "_start");
   if (!start_function) {
     fprintf(stderr, "`_start` function not found\n");
     print_wasmer_error();
@@ -152,15 +139,24 @@ int main(int argc, char *argv[]) {
 
   wasm_val_vec_t args = WASM_EMPTY_VEC;
   wasm_val_vec_t results = WASM_EMPTY_VEC;
-  own wasm_trap_t *trap = wasm_func_call(start_function, &args, &results);
+  own wasm_trap_t *trap =
+      wasm_func_call(wasm_extern_as_func(start_function), &args, &results);
   if (trap) {
-    fprintf(stderr, "Trap is not NULL: TODO:\n");
+    uint32_t exit_code;
+    if (wasi_trap_exit_code(trap, &exit_code)) {
+      // The program exited through `proc_exit`.
+      wasm_trap_delete(trap);
+      return exit_code;
+    }
+    wasm_message_t message;
+    wasm_trap_message(trap, &message);
+    fprintf(stderr, "%s\n", message.data);
+    wasm_byte_vec_delete(&message);
+    wasm_trap_delete(trap);
     return -1;
   }
-#endif
-
-  // TODO: handle non-WASI start (maybe with invoke?)
 
+  wasm_extern_delete(start_function);
   wasm_instance_delete(instance);
   wasm_module_delete(module);
   wasm_store_delete(store);
@@ -152,16 +152,18 @@ impl CompilerOptions {
             ),
             #[cfg(feature = "dylib")]
             EngineType::Dylib => Box::new(
-                wasmer_engine_dylib::Dylib::new(compiler_config)
+                wasmer_engine_object::ObjectFile::new(compiler_config)
                     .target(target)
                     .features(features)
+                    .kind(wasmer_engine_object::ObjectKind::SharedObject)
                     .engine(),
             ),
             #[cfg(feature = "staticlib")]
             EngineType::Staticlib => Box::new(
-                wasmer_engine_staticlib::Staticlib::new(compiler_config)
+                wasmer_engine_object::ObjectFile::new(compiler_config)
                     .target(target)
                     .features(features)
+                    .kind(wasmer_engine_object::ObjectKind::Relocatable)
                     .engine(),
             ),
             #[cfg(not(all(feature = "universal", feature = "dylib", feature = "staticlib")))]
@@ -440,10 +442,14 @@ impl StoreOptions {
                 Arc::new(wasmer_engine_universal::Universal::headless().engine())
             }
             #[cfg(feature = "dylib")]
-            EngineType::Dylib => Arc::new(wasmer_engine_dylib::Dylib::headless().engine()),
+            EngineType::Dylib => Arc::new(
+                wasmer_engine_object::ObjectFile::headless()
+                    .kind(wasmer_engine_object::ObjectKind::SharedObject)
+                    .engine(),
+            ),
             #[cfg(feature = "staticlib")]
             EngineType::Staticlib => {
-                Arc::new(wasmer_engine_staticlib::Staticlib::headless().engine())
+                Arc::new(wasmer_engine_object::ObjectFile::headless().engine())
             }
             #[cfg(not(all(feature = "universal", feature = "dylib", feature = "staticlib")))]
             engine => bail!(
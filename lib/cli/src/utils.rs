@@ -64,9 +64,24 @@ pub fn parse_envvar(entry: &str) -> Result<(String, String)> {
     }
 }
 
+/// Parses a module to preload, of the form `<name>=<path>`.
+pub fn parse_preload(entry: &str) -> Result<(String, PathBuf)> {
+    match entry.find('=') {
+        Some(position) if position > 0 && position < entry.len() - 1 => Ok((
+            entry[..position].into(),
+            PathBuf::from(&entry[position + 1..]),
+        )),
+        _ => bail!(
+            "Preloaded modules must be of the form `<name>=<path>`; found `{}`",
+            &entry
+        ),
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::parse_envvar;
+    use super::{parse_envvar, parse_preload};
+    use std::path::PathBuf;
 
     #[test]
     fn test_parse_envvar() {
@@ -89,4 +104,21 @@ mod tests {
             ("A".into(), "B=C=D".into())
         );
     }
+
+    #[test]
+    fn test_parse_preload() {
+        assert_eq!(
+            parse_preload("env=lib/env.wasm").unwrap(),
+            ("env".into(), PathBuf::from("lib/env.wasm"))
+        );
+        for entry in &["env", "=env.wasm", "env="] {
+            assert_eq!(
+                parse_preload(entry).unwrap_err().to_string(),
+                format!(
+                    "Preloaded modules must be of the form `<name>=<path>`; found `{}`",
+                    entry
+                )
+            );
+        }
+    }
 }
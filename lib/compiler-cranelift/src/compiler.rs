@@ -14,22 +14,27 @@ use crate::translator::{
     CraneliftUnwindInfo, FuncTranslator,
 };
 use cranelift_codegen::ir;
+use cranelift_codegen::isa::TargetIsa;
 use cranelift_codegen::print_errors::pretty_error;
 use cranelift_codegen::{binemit, Context};
 #[cfg(feature = "unwind")]
 use gimli::write::{Address, EhFrame, FrameTable};
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+use std::sync::Arc;
 use target_lexicon::{Architecture, OperatingSystem};
+#[cfg(feature = "unwind")]
+use wasmer_compiler::CallingConvention;
 use wasmer_compiler::CompileError;
-use wasmer_compiler::{CallingConvention, ModuleTranslationState, Target};
 use wasmer_compiler::{
     Compilation, CompileModuleInfo, CompiledFunction, CompiledFunctionFrameInfo,
-    CompiledFunctionUnwindInfo, Compiler, Dwarf, FunctionBody, FunctionBodyData, SectionIndex,
+    CompiledFunctionUnwindInfo, Compiler, FunctionBody, FunctionBodyData, FunctionCompilation,
+    ModuleMiddleware, ModuleMiddlewareChain, SectionIndex,
 };
 use wasmer_compiler::{
     CustomSection, CustomSectionProtection, Relocation, RelocationKind, RelocationTarget,
     SectionBody,
 };
+use wasmer_compiler::{ModuleTranslationState, Target};
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{FunctionIndex, LocalFunctionIndex, SignatureIndex};
 use wasmer_vm::libcalls::LibCall;
@@ -50,201 +55,272 @@ impl CraneliftCompiler {
     pub fn config(&self) -> &Cranelift {
         &self.config
     }
-}
 
-impl Compiler for CraneliftCompiler {
-    /// Compile the module using Cranelift, producing a compilation result with
-    /// associated relocations.
-    fn compile_module(
+    /// Compiles the function `i` of a module whose signatures are
+    /// `signatures` in Cranelift IR.
+    ///
+    /// The function refers to its own custom sections: the trampoline to
+    /// the probestack libcall if it calls it, and its exception frames.
+    #[cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
+    fn compile_function_with(
         &self,
+        isa: &dyn TargetIsa,
         target: &Target,
         compile_info: &CompileModuleInfo,
         module_translation_state: &ModuleTranslationState,
-        function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
-    ) -> Result<Compilation, CompileError> {
-        let isa = self.config().isa(target);
-        let frontend_config = isa.frontend_config();
-        let memory_styles = &compile_info.memory_styles;
-        let table_styles = &compile_info.table_styles;
+        signatures: &PrimaryMap<SignatureIndex, ir::Signature>,
+        func_translator: &mut FuncTranslator,
+        i: LocalFunctionIndex,
+        input: &FunctionBodyData<'_>,
+    ) -> Result<FunctionCompilation, CompileError> {
         let module = &compile_info.module;
-        let signatures = module
-            .signatures
-            .iter()
-            .map(|(_sig_index, func_type)| signature_to_cranelift_ir(func_type, frontend_config))
-            .collect::<PrimaryMap<SignatureIndex, ir::Signature>>();
+        let func_index = module.func_index(i);
+        let mut context = Context::new();
+        let mut func_env = FuncEnvironment::new(
+            isa.frontend_config(),
+            module,
+            signatures,
+            &compile_info.memory_styles,
+            &compile_info.table_styles,
+            self.config.enable_epoch_interruption,
+            compile_info.features.exceptions,
+        );
+        context.func.name = get_function_name(func_index);
+        context.func.signature = signatures[module.functions[func_index]].clone();
+        // if generate_debug_info {
+        //     context.func.collect_debug_info();
+        // }
+        let mut reader = wasmer_compiler::FunctionReader::new(input.module_offset, input.data);
+        func_translator.translate(
+            module_translation_state,
+            &mut reader,
+            &mut context.func,
+            &mut func_env,
+            i,
+            self.config
+                .middlewares
+                .generate_function_middleware_chain(i),
+        )?;
+
+        let mut custom_sections = PrimaryMap::new();
+        let probestack_trampoline = probestack_trampoline(target);
+        let probestack_trampoline_relocation_target =
+            probestack_trampoline.as_ref().map(|_| SectionIndex::new(0));
+
+        let mut code_buf: Vec<u8> = Vec::new();
+        let mut reloc_sink =
+            RelocSink::new(module, func_index, probestack_trampoline_relocation_target);
+        let mut trap_sink = TrapSink::new();
+        let mut stackmap_sink = binemit::NullStackMapSink {};
+        context
+            .compile_and_emit(
+                isa,
+                &mut code_buf,
+                &mut reloc_sink,
+                &mut trap_sink,
+                &mut stackmap_sink,
+            )
+            .map_err(|error| {
+                CompileError::Codegen(pretty_error(&context.func, Some(isa), error))
+            })?;
 
-        // Generate the frametable
-        #[cfg(feature = "unwind")]
-        let dwarf_frametable = if function_body_inputs.is_empty() {
-            // If we have no function body inputs, we don't need to
-            // construct the `FrameTable`. Constructing it, with empty
-            // FDEs will cause some issues in Linux.
-            None
-        } else {
-            match target.triple().default_calling_convention() {
-                Ok(CallingConvention::SystemV) => {
-                    match isa.create_systemv_cie() {
-                        Some(cie) => {
-                            let mut dwarf_frametable = FrameTable::default();
-                            let cie_id = dwarf_frametable.add_cie(cie);
-                            Some((dwarf_frametable, cie_id))
-                        }
-                        // Even though we are in a SystemV system, Cranelift doesn't support it
-                        None => None,
-                    }
+        if let Some(probestack_trampoline) = probestack_trampoline {
+            if reloc_sink.func_relocs.iter().any(|reloc| {
+                reloc.reloc_target == RelocationTarget::CustomSection(SectionIndex::new(0))
+            }) {
+                custom_sections.push(probestack_trampoline);
+            }
+        }
+
+        #[cfg_attr(not(feature = "unwind"), allow(unused_mut))]
+        let mut eh_frame_sections = Vec::new();
+        let unwind_info = match compiled_function_unwind_info(isa, &context)? {
+            #[cfg(feature = "unwind")]
+            CraneliftUnwindInfo::FDE(fde) => {
+                let cie = match target.triple().default_calling_convention() {
+                    Ok(CallingConvention::SystemV) => isa.create_systemv_cie(),
+                    _ => None,
+                };
+                // Even though we may be in a SystemV system, Cranelift
+                // may not support it
+                if let Some(cie) = cie {
+                    let mut dwarf_frametable = FrameTable::default();
+                    let cie_id = dwarf_frametable.add_cie(cie);
+                    let fde = fde.to_fde(Address::Symbol {
+                        // The symbol is the kind of relocation.
+                        // "0" is used for functions
+                        symbol: WriterRelocate::FUNCTION_SYMBOL,
+                        // We use the addend as a way to specify the
+                        // function index
+                        addend: i.index() as _,
+                    });
+                    dwarf_frametable.add_fde(cie_id, fde);
+                    let mut eh_frame =
+                        EhFrame(WriterRelocate::new(target.triple().endianness().ok()));
+                    dwarf_frametable.write_eh_frame(&mut eh_frame).unwrap();
+                    // The unwind information is inserted into the dwarf section
+                    eh_frame_sections.push(custom_sections.push(eh_frame.0.into_section()));
+                    Some(CompiledFunctionUnwindInfo::Dwarf)
+                } else {
+                    None
                 }
-                _ => None,
             }
+            other => other.maybe_into_to_windows_unwind(),
         };
 
-        let mut custom_sections = PrimaryMap::new();
+        let range = reader.range();
+        let address_map = get_function_address_map(&context, range, code_buf.len(), isa);
 
-        let probestack_trampoline_relocation_target = if target.triple().operating_system
-            == OperatingSystem::Linux
-            && target.triple().architecture == Architecture::X86_64
-        {
-            let probestack_trampoline = CustomSection {
-                protection: CustomSectionProtection::ReadExecute,
-                // We create a jump to an absolute 64bits address
-                // with an indrect jump immediatly followed but the absolute address
-                // JMP [IP+0]   FF 25 00 00 00 00
-                // 64bits ADDR  00 00 00 00 00 00 00 00 preset to 0 until the relocation takes place
-                bytes: SectionBody::new_with_vec(vec![
-                    0xff, 0x25, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-                    0x00,
-                ]),
-                relocations: vec![Relocation {
-                    kind: RelocationKind::Abs8,
-                    reloc_target: RelocationTarget::LibCall(LibCall::Probestack),
-                    // 6 is the size of the jmp instruction. The relocated address must follow
-                    offset: 6,
-                    addend: 0,
-                }],
-            };
-            custom_sections.push(probestack_trampoline);
+        // We transform the Cranelift JumpTable's into compiler JumpTables
+        let func_jt_offsets = transform_jump_table(context.func.jt_offsets);
 
-            Some(SectionIndex::new(custom_sections.len() - 1))
-        } else {
-            None
-        };
+        Ok(FunctionCompilation {
+            function: CompiledFunction {
+                body: FunctionBody {
+                    body: code_buf,
+                    unwind_info,
+                },
+                jt_offsets: func_jt_offsets,
+                relocations: reloc_sink.func_relocs,
+                frame_info: CompiledFunctionFrameInfo {
+                    address_map,
+                    traps: trap_sink.traps,
+                },
+            },
+            custom_sections,
+            eh_frame_sections,
+        })
+    }
+}
 
-        let (functions, fdes): (Vec<CompiledFunction>, Vec<_>) = function_body_inputs
-            .iter()
-            .collect::<Vec<(LocalFunctionIndex, &FunctionBodyData<'_>)>>()
-            .par_iter()
-            .map_init(FuncTranslator::new, |func_translator, (i, input)| {
-                let func_index = module.func_index(*i);
-                let mut context = Context::new();
-                let mut func_env = FuncEnvironment::new(
-                    isa.frontend_config(),
-                    module,
-                    &signatures,
-                    &memory_styles,
-                    &table_styles,
-                );
-                context.func.name = get_function_name(func_index);
-                context.func.signature = signatures[module.functions[func_index]].clone();
-                // if generate_debug_info {
-                //     context.func.collect_debug_info();
-                // }
-                let mut reader =
-                    wasmer_compiler::FunctionReader::new(input.module_offset, input.data);
-                func_translator.translate(
-                    module_translation_state,
-                    &mut reader,
-                    &mut context.func,
-                    &mut func_env,
-                    *i,
-                )?;
+/// The signatures of `compile_info` in Cranelift IR.
+fn signatures(
+    isa: &dyn TargetIsa,
+    compile_info: &CompileModuleInfo,
+) -> PrimaryMap<SignatureIndex, ir::Signature> {
+    compile_info
+        .module
+        .signatures
+        .iter()
+        .map(|(_sig_index, func_type)| signature_to_cranelift_ir(func_type, isa.frontend_config()))
+        .collect()
+}
 
-                let mut code_buf: Vec<u8> = Vec::new();
-                let mut reloc_sink =
-                    RelocSink::new(&module, func_index, probestack_trampoline_relocation_target);
-                let mut trap_sink = TrapSink::new();
-                let mut stackmap_sink = binemit::NullStackMapSink {};
-                context
-                    .compile_and_emit(
-                        &*isa,
-                        &mut code_buf,
-                        &mut reloc_sink,
-                        &mut trap_sink,
-                        &mut stackmap_sink,
-                    )
-                    .map_err(|error| {
-                        CompileError::Codegen(pretty_error(&context.func, Some(&*isa), error))
-                    })?;
+/// The trampoline the functions call the probestack libcall through on
+/// `target`, if they call it through one.
+fn probestack_trampoline(target: &Target) -> Option<CustomSection> {
+    if target.triple().operating_system != OperatingSystem::Linux
+        || target.triple().architecture != Architecture::X86_64
+    {
+        return None;
+    }
+    Some(CustomSection {
+        protection: CustomSectionProtection::ReadExecute,
+        // We create a jump to an absolute 64bits address
+        // with an indrect jump immediatly followed but the absolute address
+        // JMP [IP+0]   FF 25 00 00 00 00
+        // 64bits ADDR  00 00 00 00 00 00 00 00 preset to 0 until the relocation takes place
+        bytes: SectionBody::new_with_vec(vec![
+            0xff, 0x25, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ]),
+        relocations: vec![Relocation {
+            kind: RelocationKind::Abs8,
+            reloc_target: RelocationTarget::LibCall(LibCall::Probestack),
+            // 6 is the size of the jmp instruction. The relocated address must follow
+            offset: 6,
+            addend: 0,
+        }],
+    })
+}
 
-                let (unwind_info, fde) = match compiled_function_unwind_info(&*isa, &context)? {
-                    #[cfg(feature = "unwind")]
-                    CraneliftUnwindInfo::FDE(fde) => {
-                        if dwarf_frametable.is_some() {
-                            let fde = fde.to_fde(Address::Symbol {
-                                // The symbol is the kind of relocation.
-                                // "0" is used for functions
-                                symbol: WriterRelocate::FUNCTION_SYMBOL,
-                                // We use the addend as a way to specify the
-                                // function index
-                                addend: i.index() as _,
-                            });
-                            // The unwind information is inserted into the dwarf section
-                            (Some(CompiledFunctionUnwindInfo::Dwarf), Some(fde))
-                        } else {
-                            (None, None)
-                        }
-                    }
-                    #[cfg(feature = "unwind")]
-                    other => (other.maybe_into_to_windows_unwind(), None),
+impl Compiler for CraneliftCompiler {
+    fn name(&self) -> &str {
+        "cranelift"
+    }
 
-                    // This is a bit hacky, but necessary since gimli is not
-                    // available when the "unwind" feature is disabled.
-                    #[cfg(not(feature = "unwind"))]
-                    other => (other.maybe_into_to_windows_unwind(), None::<()>),
-                };
+    fn version(&self) -> &str {
+        env!("CARGO_PKG_VERSION")
+    }
 
-                let range = reader.range();
-                let address_map = get_function_address_map(&context, range, code_buf.len(), &*isa);
+    fn epoch_interruption(&self) -> bool {
+        self.config.enable_epoch_interruption
+    }
 
-                // We transform the Cranelift JumpTable's into compiler JumpTables
-                let func_jt_offsets = transform_jump_table(context.func.jt_offsets);
+    /// Get the middlewares for this compiler
+    fn get_middlewares(&self) -> &[Arc<dyn ModuleMiddleware>] {
+        &self.config.middlewares
+    }
 
-                Ok((
-                    CompiledFunction {
-                        body: FunctionBody {
-                            body: code_buf,
-                            unwind_info,
-                        },
-                        jt_offsets: func_jt_offsets,
-                        relocations: reloc_sink.func_relocs,
-                        frame_info: CompiledFunctionFrameInfo {
-                            address_map,
-                            traps: trap_sink.traps,
-                        },
-                    },
-                    fde,
-                ))
+    /// Compile the module using Cranelift, producing a compilation result with
+    /// associated relocations.
+    fn compile_module(
+        &self,
+        target: &Target,
+        compile_info: &CompileModuleInfo,
+        module_translation_state: &ModuleTranslationState,
+        function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+    ) -> Result<Compilation, CompileError> {
+        let isa = self.config().isa(target);
+        let signatures = signatures(&*isa, compile_info);
+        let functions = function_body_inputs
+            .iter()
+            .collect::<Vec<(LocalFunctionIndex, &FunctionBodyData<'_>)>>()
+            .par_iter()
+            .map_init(FuncTranslator::new, |func_translator, (i, input)| {
+                self.compile_function_with(
+                    &*isa,
+                    target,
+                    compile_info,
+                    module_translation_state,
+                    &signatures,
+                    func_translator,
+                    *i,
+                    input,
+                )
             })
             .collect::<Result<Vec<_>, CompileError>>()?
             .into_iter()
-            .unzip();
+            .collect();
+        self.compile_module_with_functions(target, compile_info, functions)
+    }
 
-        #[cfg(feature = "unwind")]
-        let dwarf = if let Some((mut dwarf_frametable, cie_id)) = dwarf_frametable {
-            for fde in fdes {
-                if let Some(fde) = fde {
-                    dwarf_frametable.add_fde(cie_id, fde);
-                }
-            }
-            let mut eh_frame = EhFrame(WriterRelocate::new(target.triple().endianness().ok()));
-            dwarf_frametable.write_eh_frame(&mut eh_frame).unwrap();
+    fn supports_function_compilation(&self) -> bool {
+        true
+    }
 
-            let eh_frame_section = eh_frame.0.into_section();
-            custom_sections.push(eh_frame_section);
-            Some(Dwarf::new(SectionIndex::new(custom_sections.len() - 1)))
-        } else {
-            None
-        };
-        #[cfg(not(feature = "unwind"))]
-        let dwarf = None;
+    fn compile_function(
+        &self,
+        target: &Target,
+        compile_info: &CompileModuleInfo,
+        module_translation_state: &ModuleTranslationState,
+        index: LocalFunctionIndex,
+        function_body: &FunctionBodyData<'_>,
+    ) -> Result<FunctionCompilation, CompileError> {
+        let isa = self.config().isa(target);
+        self.compile_function_with(
+            &*isa,
+            target,
+            compile_info,
+            module_translation_state,
+            &signatures(&*isa, compile_info),
+            &mut FuncTranslator::new(),
+            index,
+            function_body,
+        )
+    }
+
+    fn compile_module_with_functions(
+        &self,
+        target: &Target,
+        compile_info: &CompileModuleInfo,
+        functions: PrimaryMap<LocalFunctionIndex, FunctionCompilation>,
+    ) -> Result<Compilation, CompileError> {
+        let isa = self.config().isa(target);
+        let frontend_config = isa.frontend_config();
+        let module = &compile_info.module;
+
+        let mut custom_sections = PrimaryMap::new();
+        let (functions, dwarf) = FunctionCompilation::merge(functions, &mut custom_sections);
 
         // function call trampolines (only for local functions, by signature)
         let function_call_trampolines = module
@@ -274,7 +350,7 @@ impl Compiler for CraneliftCompiler {
             .collect::<PrimaryMap<FunctionIndex, FunctionBody>>();
 
         Ok(Compilation::new(
-            functions.into_iter().collect(),
+            functions,
             custom_sections,
             function_call_trampolines,
             dynamic_function_trampolines,
@@ -1,7 +1,10 @@
 use crate::compiler::CraneliftCompiler;
 use cranelift_codegen::isa::{lookup, TargetIsa};
 use cranelift_codegen::settings::{self, Configurable};
-use wasmer_compiler::{Architecture, Compiler, CompilerConfig, CpuFeature, Target};
+use std::sync::Arc;
+use wasmer_compiler::{
+    Architecture, Compiler, CompilerConfig, CpuFeature, ModuleMiddleware, Target,
+};
 
 // Runtime Environment
 
@@ -29,7 +32,10 @@ pub struct Cranelift {
     enable_nan_canonicalization: bool,
     enable_verifier: bool,
     enable_pic: bool,
+    pub(crate) enable_epoch_interruption: bool,
     opt_level: CraneliftOptLevel,
+    /// The middleware chain.
+    pub(crate) middlewares: Vec<Arc<dyn ModuleMiddleware>>,
 }
 
 impl Cranelift {
@@ -41,6 +47,8 @@ impl Cranelift {
             enable_verifier: false,
             opt_level: CraneliftOptLevel::Speed,
             enable_pic: false,
+            enable_epoch_interruption: false,
+            middlewares: vec![],
         }
     }
 
@@ -53,6 +61,15 @@ impl Cranelift {
         self
     }
 
+    /// Enable epoch interruption.
+    ///
+    /// When enabled, the compiled code traps with `TrapCode::Interrupted`
+    /// once the epoch of the engine reaches the deadline of its instance.
+    pub fn epoch_interruption(&mut self, enable: bool) -> &mut Self {
+        self.enable_epoch_interruption = enable;
+        self
+    }
+
     /// The optimization levels when optimizing the IR.
     pub fn opt_level(&mut self, opt_level: CraneliftOptLevel) -> &mut Self {
         self.opt_level = opt_level;
@@ -186,10 +203,19 @@ impl CompilerConfig for Cranelift {
         self.enable_nan_canonicalization = enable;
     }
 
+    fn epoch_interruption(&mut self, enable: bool) {
+        self.enable_epoch_interruption = enable;
+    }
+
     /// Transform it into the compiler
     fn compiler(self: Box<Self>) -> Box<dyn Compiler> {
         Box::new(CraneliftCompiler::new(*self))
     }
+
+    /// Pushes a middleware onto the back of the middleware chain.
+    fn push_middleware(&mut self, middleware: Arc<dyn ModuleMiddleware>) {
+        self.middlewares.push(middleware);
+    }
 }
 
 impl Default for Cranelift {
@@ -26,9 +26,9 @@ impl WriterRelocate {
         }
     }
 
-    pub fn into_section(mut self) -> CustomSection {
-        // GCC expects a terminating "empty" length, so write a 0 length at the end of the table.
-        self.writer.write_u32(0).unwrap();
+    pub fn into_section(self) -> CustomSection {
+        // The terminating "empty" length GCC expects at the end of the table
+        // is written once the sections of all the functions are joined.
         let data = self.writer.into_vec();
         CustomSection {
             protection: CustomSectionProtection::Read,
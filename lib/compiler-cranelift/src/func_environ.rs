@@ -4,6 +4,7 @@
 use crate::translator::{
     type_to_irtype, FuncEnvironment as BaseFuncEnvironment, GlobalVariable, TargetEnvironment,
 };
+use crate::HashMap;
 use cranelift_codegen::cursor::FuncCursor;
 use cranelift_codegen::ir;
 use cranelift_codegen::ir::condcodes::*;
@@ -19,11 +20,11 @@ use wasmer_types::entity::EntityRef;
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::{
     FunctionIndex, FunctionType, GlobalIndex, LocalFunctionIndex, MemoryIndex, ModuleInfo,
-    SignatureIndex, TableIndex, Type as WasmerType,
+    SignatureIndex, TableIndex, TagIndex, TagType, Type as WasmerType,
 };
-use wasmer_vm::VMBuiltinFunctionIndex;
 use wasmer_vm::VMOffsets;
 use wasmer_vm::{MemoryStyle, TableStyle};
+use wasmer_vm::{TrapCode, VMBuiltinFunctionIndex};
 
 /// Compute an `ir::ExternalName` for a given wasm function index.
 pub fn get_function_name(func_index: FunctionIndex) -> ir::ExternalName {
@@ -89,9 +90,45 @@ pub struct FuncEnvironment<'module_environment> {
     /// The external function signature for implementing wasm's `memory.init`.
     memory_init_sig: Option<ir::SigRef>,
 
+    /// The external function signature for implementing wasm's `memory.size`
+    /// for 64-bit memories.
+    memory64_size_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's `memory.grow`
+    /// for 64-bit memories.
+    memory64_grow_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's `memory.copy`
+    /// for 64-bit memories.
+    memory64_copy_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's `memory.fill`
+    /// for 64-bit memories.
+    memory64_fill_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's `memory.copy`
+    /// between two different memories.
+    memory_copy_between_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's `memory.init`
+    /// for 64-bit memories.
+    memory64_init_sig: Option<ir::SigRef>,
+
     /// The external function signature for implementing wasm's `data.drop`.
     data_drop_sig: Option<ir::SigRef>,
 
+    /// The external function signature for implementing wasm's
+    /// `memory.atomic.wait32`.
+    memory_atomic_wait32_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's
+    /// `memory.atomic.wait64`.
+    memory_atomic_wait64_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's
+    /// `memory.atomic.notify`.
+    memory_atomic_notify_sig: Option<ir::SigRef>,
+
     /// The external function signature for implementing wasm's `table.get`.
     table_get_sig: Option<ir::SigRef>,
 
@@ -109,6 +146,9 @@ pub struct FuncEnvironment<'module_environment> {
 
     /// The external function signature for implementing reference decrement for `extern.ref`.
     externref_dec_sig: Option<ir::SigRef>,
+
+    /// The signature of the instance's trap handler, used to raise traps without signals.
+    trap_handler_sig: Option<ir::SigRef>,
     /// Offsets to struct fields accessed by JIT code.
     offsets: VMOffsets,
 
@@ -117,6 +157,15 @@ pub struct FuncEnvironment<'module_environment> {
 
     /// The table styles
     table_styles: &'module_environment PrimaryMap<TableIndex, TableStyle>,
+
+    /// Whether to check the epoch deadline on function entry and at loop headers.
+    epoch_interruption: bool,
+
+    /// Whether the module may catch exceptions.
+    exceptions: bool,
+
+    /// The external function signatures for implementing exception handling, by builtin.
+    exception_sigs: HashMap<u32, ir::SigRef>,
 }
 
 impl<'module_environment> FuncEnvironment<'module_environment> {
@@ -126,6 +175,8 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         signatures: &'module_environment PrimaryMap<SignatureIndex, ir::Signature>,
         memory_styles: &'module_environment PrimaryMap<MemoryIndex, MemoryStyle>,
         table_styles: &'module_environment PrimaryMap<TableIndex, TableStyle>,
+        epoch_interruption: bool,
+        exceptions: bool,
     ) -> Self {
         Self {
             target_config,
@@ -143,16 +194,29 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
             memory_copy_sig: None,
             memory_fill_sig: None,
             memory_init_sig: None,
+            memory64_size_sig: None,
+            memory64_grow_sig: None,
+            memory64_copy_sig: None,
+            memory64_fill_sig: None,
+            memory_copy_between_sig: None,
+            memory64_init_sig: None,
             table_get_sig: None,
             table_set_sig: None,
             data_drop_sig: None,
+            memory_atomic_wait32_sig: None,
+            memory_atomic_wait64_sig: None,
+            memory_atomic_notify_sig: None,
             func_ref_sig: None,
             table_fill_sig: None,
             externref_inc_sig: None,
             externref_dec_sig: None,
+            trap_handler_sig: None,
             offsets: VMOffsets::new(target_config.pointer_bytes()).with_module_info(module),
             memory_styles,
             table_styles,
+            epoch_interruption,
+            exceptions,
+            exception_sigs: HashMap::new(),
         }
     }
 
@@ -246,6 +310,77 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         )
     }
 
+    fn get_trap_handler_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.trap_handler_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![AbiParam::new(self.pointer_type()), AbiParam::new(I32)],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.trap_handler_sig = Some(sig);
+        sig
+    }
+
+    /// Raises `trap_code` through the instance's trap handler if `condition` is non-zero.
+    ///
+    /// The handler unwinds straight back to the host, so the code after it is unreachable.
+    fn trap_if(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        condition: ir::Value,
+        trap_code: TrapCode,
+    ) {
+        let trap_block = builder.create_block();
+        let continuation_block = builder.create_block();
+        builder.ins().brnz(condition, trap_block, &[]);
+        builder.ins().jump(continuation_block, &[]);
+        builder.seal_block(trap_block);
+        builder.seal_block(continuation_block);
+
+        builder.switch_to_block(trap_block);
+        let pointer_type = self.pointer_type();
+        let vmctx = self.vmctx(builder.func);
+        let base = builder.ins().global_value(pointer_type, vmctx);
+        let trap_handler_offset = i32::try_from(self.offsets.vmctx_trap_handler()).unwrap();
+        let trap_handler = builder.ins().load(
+            pointer_type,
+            ir::MemFlags::trusted(),
+            base,
+            trap_handler_offset,
+        );
+        let sig = self.get_trap_handler_sig(builder.func);
+        let pc = builder.ins().iconst(pointer_type, 0);
+        let trap_code = builder.ins().iconst(I32, trap_code as i64);
+        builder
+            .ins()
+            .call_indirect(sig, trap_handler, &[pc, trap_code]);
+        builder.ins().trap(ir::TrapCode::UnreachableCodeReached);
+
+        builder.switch_to_block(continuation_block);
+    }
+
+    /// Traps with `TrapCode::Interrupted` if the epoch of the engine has reached the
+    /// deadline of the instance.
+    fn epoch_check(&mut self, builder: &mut FunctionBuilder) {
+        let pointer_type = self.pointer_type();
+        let mem_flags = ir::MemFlags::trusted();
+        let vmctx = self.vmctx(builder.func);
+        let base = builder.ins().global_value(pointer_type, vmctx);
+
+        let epoch_pointer_offset = i32::try_from(self.offsets.vmctx_epoch_pointer()).unwrap();
+        let epoch_pointer = builder
+            .ins()
+            .load(pointer_type, mem_flags, base, epoch_pointer_offset);
+        let epoch = builder.ins().load(I64, mem_flags, epoch_pointer, 0);
+        let deadline_offset = i32::try_from(self.offsets.vmctx_epoch_deadline()).unwrap();
+        let deadline = builder.ins().load(I64, mem_flags, base, deadline_offset);
+        let expired = builder
+            .ins()
+            .icmp(IntCC::UnsignedGreaterThanOrEqual, epoch, deadline);
+        self.trap_if(builder, expired, TrapCode::Interrupted);
+    }
+
     fn get_func_ref_sig(&mut self, func: &mut Function) -> ir::SigRef {
         let sig = self.func_ref_sig.unwrap_or_else(|| {
             func.import_signature(Signature {
@@ -386,19 +521,37 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         }
     }
 
-    fn get_memory_grow_sig(&mut self, func: &mut Function) -> ir::SigRef {
-        let sig = self.memory_grow_sig.unwrap_or_else(|| {
+    /// The type of the addresses of the memory at `index`.
+    fn memory_index_type(&self, index: MemoryIndex) -> ir::Type {
+        if self.module.memories[index].memory64 {
+            I64
+        } else {
+            I32
+        }
+    }
+
+    fn get_memory_grow_sig(&mut self, func: &mut Function, index_type: ir::Type) -> ir::SigRef {
+        let cached = if index_type == I64 {
+            self.memory64_grow_sig
+        } else {
+            self.memory_grow_sig
+        };
+        let sig = cached.unwrap_or_else(|| {
             func.import_signature(Signature {
                 params: vec![
                     AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
-                    AbiParam::new(I32),
+                    AbiParam::new(index_type),
                     AbiParam::new(I32),
                 ],
-                returns: vec![AbiParam::new(I32)],
+                returns: vec![AbiParam::new(index_type)],
                 call_conv: self.target_config.default_call_conv,
             })
         });
-        self.memory_grow_sig = Some(sig);
+        if index_type == I64 {
+            self.memory64_grow_sig = Some(sig);
+        } else {
+            self.memory_grow_sig = Some(sig);
+        }
         sig
     }
 
@@ -409,18 +562,29 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         func: &mut Function,
         index: MemoryIndex,
     ) -> (ir::SigRef, usize, VMBuiltinFunctionIndex) {
-        if self.module.is_imported_memory(index) {
-            (
-                self.get_memory_grow_sig(func),
+        let index_type = self.memory_index_type(index);
+        let sig = self.get_memory_grow_sig(func, index_type);
+        match (self.module.is_imported_memory(index), index_type == I64) {
+            (true, false) => (
+                sig,
                 index.index(),
                 VMBuiltinFunctionIndex::get_imported_memory32_grow_index(),
-            )
-        } else {
-            (
-                self.get_memory_grow_sig(func),
+            ),
+            (false, false) => (
+                sig,
                 self.module.local_memory_index(index).unwrap().index(),
                 VMBuiltinFunctionIndex::get_memory32_grow_index(),
-            )
+            ),
+            (true, true) => (
+                sig,
+                index.index(),
+                VMBuiltinFunctionIndex::get_imported_memory64_grow_index(),
+            ),
+            (false, true) => (
+                sig,
+                self.module.local_memory_index(index).unwrap().index(),
+                VMBuiltinFunctionIndex::get_memory64_grow_index(),
+            ),
         }
     }
 
@@ -461,18 +625,27 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         }
     }
 
-    fn get_memory32_size_sig(&mut self, func: &mut Function) -> ir::SigRef {
-        let sig = self.memory32_size_sig.unwrap_or_else(|| {
+    fn get_memory_size_sig(&mut self, func: &mut Function, index_type: ir::Type) -> ir::SigRef {
+        let cached = if index_type == I64 {
+            self.memory64_size_sig
+        } else {
+            self.memory32_size_sig
+        };
+        let sig = cached.unwrap_or_else(|| {
             func.import_signature(Signature {
                 params: vec![
                     AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
                     AbiParam::new(I32),
                 ],
-                returns: vec![AbiParam::new(I32)],
+                returns: vec![AbiParam::new(index_type)],
                 call_conv: self.target_config.default_call_conv,
             })
         });
-        self.memory32_size_sig = Some(sig);
+        if index_type == I64 {
+            self.memory64_size_sig = Some(sig);
+        } else {
+            self.memory32_size_sig = Some(sig);
+        }
         sig
     }
 
@@ -483,18 +656,29 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         func: &mut Function,
         index: MemoryIndex,
     ) -> (ir::SigRef, usize, VMBuiltinFunctionIndex) {
-        if self.module.is_imported_memory(index) {
-            (
-                self.get_memory32_size_sig(func),
+        let index_type = self.memory_index_type(index);
+        let sig = self.get_memory_size_sig(func, index_type);
+        match (self.module.is_imported_memory(index), index_type == I64) {
+            (true, false) => (
+                sig,
                 index.index(),
                 VMBuiltinFunctionIndex::get_imported_memory32_size_index(),
-            )
-        } else {
-            (
-                self.get_memory32_size_sig(func),
+            ),
+            (false, false) => (
+                sig,
                 self.module.local_memory_index(index).unwrap().index(),
                 VMBuiltinFunctionIndex::get_memory32_size_index(),
-            )
+            ),
+            (true, true) => (
+                sig,
+                index.index(),
+                VMBuiltinFunctionIndex::get_imported_memory64_size_index(),
+            ),
+            (false, true) => (
+                sig,
+                self.module.local_memory_index(index).unwrap().index(),
+                VMBuiltinFunctionIndex::get_memory64_size_index(),
+            ),
         }
     }
 
@@ -596,25 +780,58 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         (sig, VMBuiltinFunctionIndex::get_elem_drop_index())
     }
 
-    fn get_memory_copy_sig(&mut self, func: &mut Function) -> ir::SigRef {
-        let sig = self.memory_copy_sig.unwrap_or_else(|| {
+    fn get_memory_copy_sig(&mut self, func: &mut Function, index_type: ir::Type) -> ir::SigRef {
+        let cached = if index_type == I64 {
+            self.memory64_copy_sig
+        } else {
+            self.memory_copy_sig
+        };
+        let sig = cached.unwrap_or_else(|| {
             func.import_signature(Signature {
                 params: vec![
                     AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
                     // Memory index.
                     AbiParam::new(I32),
                     // Destination address.
-                    AbiParam::new(I32),
+                    AbiParam::new(index_type),
                     // Source address.
-                    AbiParam::new(I32),
+                    AbiParam::new(index_type),
                     // Length.
+                    AbiParam::new(index_type),
+                ],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        if index_type == I64 {
+            self.memory64_copy_sig = Some(sig);
+        } else {
+            self.memory_copy_sig = Some(sig);
+        }
+        sig
+    }
+
+    fn get_memory_copy_between_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.memory_copy_between_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Destination memory index.
+                    AbiParam::new(I32),
+                    // Source memory index.
                     AbiParam::new(I32),
+                    // Destination address.
+                    AbiParam::new(I64),
+                    // Source address.
+                    AbiParam::new(I64),
+                    // Length.
+                    AbiParam::new(I64),
                 ],
                 returns: vec![],
                 call_conv: self.target_config.default_call_conv,
             })
         });
-        self.memory_copy_sig = Some(sig);
+        self.memory_copy_between_sig = Some(sig);
         sig
     }
 
@@ -623,41 +840,63 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         func: &mut Function,
         memory_index: MemoryIndex,
     ) -> (ir::SigRef, usize, VMBuiltinFunctionIndex) {
-        let sig = self.get_memory_copy_sig(func);
-        if let Some(local_memory_index) = self.module.local_memory_index(memory_index) {
-            (
+        let index_type = self.memory_index_type(memory_index);
+        let sig = self.get_memory_copy_sig(func, index_type);
+        match (
+            self.module.local_memory_index(memory_index),
+            index_type == I64,
+        ) {
+            (Some(local_memory_index), false) => (
                 sig,
                 local_memory_index.index(),
                 VMBuiltinFunctionIndex::get_memory_copy_index(),
-            )
-        } else {
-            (
+            ),
+            (None, false) => (
                 sig,
                 memory_index.index(),
                 VMBuiltinFunctionIndex::get_imported_memory_copy_index(),
-            )
+            ),
+            (Some(local_memory_index), true) => (
+                sig,
+                local_memory_index.index(),
+                VMBuiltinFunctionIndex::get_memory64_copy_index(),
+            ),
+            (None, true) => (
+                sig,
+                memory_index.index(),
+                VMBuiltinFunctionIndex::get_imported_memory64_copy_index(),
+            ),
         }
     }
 
-    fn get_memory_fill_sig(&mut self, func: &mut Function) -> ir::SigRef {
-        let sig = self.memory_fill_sig.unwrap_or_else(|| {
+    fn get_memory_fill_sig(&mut self, func: &mut Function, index_type: ir::Type) -> ir::SigRef {
+        let cached = if index_type == I64 {
+            self.memory64_fill_sig
+        } else {
+            self.memory_fill_sig
+        };
+        let sig = cached.unwrap_or_else(|| {
             func.import_signature(Signature {
                 params: vec![
                     AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
                     // Memory index.
                     AbiParam::new(I32),
                     // Destination address.
-                    AbiParam::new(I32),
+                    AbiParam::new(index_type),
                     // Value.
                     AbiParam::new(I32),
                     // Length.
-                    AbiParam::new(I32),
+                    AbiParam::new(index_type),
                 ],
                 returns: vec![],
                 call_conv: self.target_config.default_call_conv,
             })
         });
-        self.memory_fill_sig = Some(sig);
+        if index_type == I64 {
+            self.memory64_fill_sig = Some(sig);
+        } else {
+            self.memory_fill_sig = Some(sig);
+        }
         sig
     }
 
@@ -666,24 +905,42 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         func: &mut Function,
         memory_index: MemoryIndex,
     ) -> (ir::SigRef, usize, VMBuiltinFunctionIndex) {
-        let sig = self.get_memory_fill_sig(func);
-        if let Some(local_memory_index) = self.module.local_memory_index(memory_index) {
-            (
+        let index_type = self.memory_index_type(memory_index);
+        let sig = self.get_memory_fill_sig(func, index_type);
+        match (
+            self.module.local_memory_index(memory_index),
+            index_type == I64,
+        ) {
+            (Some(local_memory_index), false) => (
                 sig,
                 local_memory_index.index(),
                 VMBuiltinFunctionIndex::get_memory_fill_index(),
-            )
-        } else {
-            (
+            ),
+            (None, false) => (
                 sig,
                 memory_index.index(),
                 VMBuiltinFunctionIndex::get_imported_memory_fill_index(),
-            )
+            ),
+            (Some(local_memory_index), true) => (
+                sig,
+                local_memory_index.index(),
+                VMBuiltinFunctionIndex::get_memory64_fill_index(),
+            ),
+            (None, true) => (
+                sig,
+                memory_index.index(),
+                VMBuiltinFunctionIndex::get_imported_memory64_fill_index(),
+            ),
         }
     }
 
-    fn get_memory_init_sig(&mut self, func: &mut Function) -> ir::SigRef {
-        let sig = self.memory_init_sig.unwrap_or_else(|| {
+    fn get_memory_init_sig(&mut self, func: &mut Function, index_type: ir::Type) -> ir::SigRef {
+        let cached = if index_type == I64 {
+            self.memory64_init_sig
+        } else {
+            self.memory_init_sig
+        };
+        let sig = cached.unwrap_or_else(|| {
             func.import_signature(Signature {
                 params: vec![
                     AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
@@ -692,7 +949,7 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
                     // Data index.
                     AbiParam::new(I32),
                     // Destination address.
-                    AbiParam::new(I32),
+                    AbiParam::new(index_type),
                     // Source index within the data segment.
                     AbiParam::new(I32),
                     // Length.
@@ -702,16 +959,26 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
                 call_conv: self.target_config.default_call_conv,
             })
         });
-        self.memory_init_sig = Some(sig);
+        if index_type == I64 {
+            self.memory64_init_sig = Some(sig);
+        } else {
+            self.memory_init_sig = Some(sig);
+        }
         sig
     }
 
     fn get_memory_init_func(
         &mut self,
         func: &mut Function,
+        memory_index: MemoryIndex,
     ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
-        let sig = self.get_memory_init_sig(func);
-        (sig, VMBuiltinFunctionIndex::get_memory_init_index())
+        let index_type = self.memory_index_type(memory_index);
+        let sig = self.get_memory_init_sig(func, index_type);
+        if index_type == I64 {
+            (sig, VMBuiltinFunctionIndex::get_memory64_init_index())
+        } else {
+            (sig, VMBuiltinFunctionIndex::get_memory_init_index())
+        }
     }
 
     fn get_data_drop_sig(&mut self, func: &mut Function) -> ir::SigRef {
@@ -735,6 +1002,124 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         (sig, VMBuiltinFunctionIndex::get_data_drop_index())
     }
 
+    fn get_memory_atomic_wait_sig(&mut self, func: &mut Function, ty: ir::Type) -> ir::SigRef {
+        let cached = if ty == I64 {
+            self.memory_atomic_wait64_sig
+        } else {
+            self.memory_atomic_wait32_sig
+        };
+        let sig = cached.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Memory index.
+                    AbiParam::new(I32),
+                    // Address.
+                    AbiParam::new(I64),
+                    // Expected value.
+                    AbiParam::new(ty),
+                    // Timeout.
+                    AbiParam::new(I64),
+                ],
+                returns: vec![AbiParam::new(I32)],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        if ty == I64 {
+            self.memory_atomic_wait64_sig = Some(sig);
+        } else {
+            self.memory_atomic_wait32_sig = Some(sig);
+        }
+        sig
+    }
+
+    fn get_memory_atomic_wait_func(
+        &mut self,
+        func: &mut Function,
+        ty: ir::Type,
+    ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.get_memory_atomic_wait_sig(func, ty);
+        if ty == I64 {
+            (
+                sig,
+                VMBuiltinFunctionIndex::get_memory_atomic_wait64_index(),
+            )
+        } else {
+            (
+                sig,
+                VMBuiltinFunctionIndex::get_memory_atomic_wait32_index(),
+            )
+        }
+    }
+
+    fn get_memory_atomic_notify_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.memory_atomic_notify_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Memory index.
+                    AbiParam::new(I32),
+                    // Address.
+                    AbiParam::new(I64),
+                    // Count.
+                    AbiParam::new(I32),
+                ],
+                returns: vec![AbiParam::new(I32)],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.memory_atomic_notify_sig = Some(sig);
+        sig
+    }
+
+    fn get_memory_atomic_notify_func(
+        &mut self,
+        func: &mut Function,
+    ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.get_memory_atomic_notify_sig(func);
+        (
+            sig,
+            VMBuiltinFunctionIndex::get_memory_atomic_notify_index(),
+        )
+    }
+
+    /// Calls the exception handling builtin `callee_func_idx` with the vmctx and `args`, and
+    /// returns its result if it has one of type `returns`.
+    fn call_exception_builtin(
+        &mut self,
+        pos: &mut FuncCursor<'_>,
+        callee_func_idx: VMBuiltinFunctionIndex,
+        args: &[ir::Value],
+        returns: Option<ir::Type>,
+    ) -> Option<ir::Value> {
+        let sig = match self.exception_sigs.get(&callee_func_idx.index()) {
+            Some(sig) => *sig,
+            None => {
+                let mut params = vec![AbiParam::special(
+                    self.pointer_type(),
+                    ArgumentPurpose::VMContext,
+                )];
+                params.extend(
+                    args.iter()
+                        .map(|arg| AbiParam::new(pos.func.dfg.value_type(*arg))),
+                );
+                let sig = pos.func.import_signature(Signature {
+                    params,
+                    returns: returns.into_iter().map(AbiParam::new).collect(),
+                    call_conv: self.target_config.default_call_conv,
+                });
+                self.exception_sigs.insert(callee_func_idx.index(), sig);
+                sig
+            }
+        };
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(pos, callee_func_idx);
+        let mut call_args = Vec::with_capacity(args.len() + 1);
+        call_args.push(vmctx);
+        call_args.extend_from_slice(args);
+        let call_inst = pos.ins().call_indirect(sig, func_addr, &call_args);
+        pos.func.dfg.inst_results(call_inst).first().copied()
+    }
+
     /// Translates load of builtin function and returns a pair of values `vmctx`
     /// and address of the loaded function.
     fn translate_load_builtin_function_address(
@@ -1078,7 +1463,7 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
             min_size: 0.into(),
             offset_guard_size,
             style: heap_style,
-            index_type: I32,
+            index_type: self.memory_index_type(index),
         }))
     }
 
@@ -1268,6 +1653,56 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         Ok(pos.ins().call_indirect(sig_ref, func_addr, &real_call_args))
     }
 
+    fn translate_gas_intrinsic(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        count: u32,
+    ) -> WasmResult<()> {
+        let pointer_type = self.pointer_type();
+        let mem_flags = ir::MemFlags::trusted();
+        let vmctx = self.vmctx(builder.func);
+        let base = builder.ins().global_value(pointer_type, vmctx);
+
+        // Load the gas counter of the instance.
+        let gas_limiter_offset = i32::try_from(self.offsets.vmctx_gas_limiter_pointer()).unwrap();
+        let counter = builder
+            .ins()
+            .load(pointer_type, mem_flags, base, gas_limiter_offset);
+        let burnt_gas_offset = i32::from(self.offsets.fast_gas_counter_burnt_gas());
+        let gas_limit_offset = i32::from(self.offsets.fast_gas_counter_gas_limit());
+        let opcode_cost_offset = i32::from(self.offsets.fast_gas_counter_opcode_cost());
+        let burnt_gas = builder
+            .ins()
+            .load(I64, mem_flags, counter, burnt_gas_offset);
+        let opcode_cost = builder
+            .ins()
+            .load(I64, mem_flags, counter, opcode_cost_offset);
+
+        // Compute the new burnt gas, trapping on signed overflow like singlepass does.
+        let cost = builder.ins().imul_imm(opcode_cost, i64::from(count));
+        let new_burnt_gas = builder.ins().iadd(burnt_gas, cost);
+        let lhs_sign = builder.ins().bxor(burnt_gas, new_burnt_gas);
+        let rhs_sign = builder.ins().bxor(cost, new_burnt_gas);
+        let overflow = builder.ins().band(lhs_sign, rhs_sign);
+        let overflow = builder.ins().icmp_imm(IntCC::SignedLessThan, overflow, 0);
+        self.trap_if(builder, overflow, TrapCode::IntegerOverflow);
+
+        // Write the new burnt gas unconditionally, so that the runtime can sort out the limits
+        // case, then compare it with the limit.
+        builder
+            .ins()
+            .store(mem_flags, new_burnt_gas, counter, burnt_gas_offset);
+        let gas_limit = builder
+            .ins()
+            .load(I64, mem_flags, counter, gas_limit_offset);
+        let exceeded =
+            builder
+                .ins()
+                .icmp(IntCC::UnsignedGreaterThanOrEqual, new_burnt_gas, gas_limit);
+        self.trap_if(builder, exceeded, TrapCode::GasExceeded);
+        Ok(())
+    }
+
     fn translate_memory_grow(
         &mut self,
         mut pos: FuncCursor<'_>,
@@ -1304,12 +1739,37 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         mut pos: FuncCursor,
         src_index: MemoryIndex,
         _src_heap: ir::Heap,
-        _dst_index: MemoryIndex,
+        dst_index: MemoryIndex,
         _dst_heap: ir::Heap,
         dst: ir::Value,
         src: ir::Value,
         len: ir::Value,
     ) -> WasmResult<()> {
+        if src_index != dst_index {
+            let func_sig = self.get_memory_copy_between_sig(&mut pos.func);
+            let dst_index_arg = pos.ins().iconst(I32, dst_index.index() as i64);
+            let src_index_arg = pos.ins().iconst(I32, src_index.index() as i64);
+            // The builtin takes 64-bit operands whatever the memories.
+            let mut extend = |value: ir::Value| {
+                if pos.func.dfg.value_type(value) == I64 {
+                    value
+                } else {
+                    pos.ins().uextend(I64, value)
+                }
+            };
+            let (dst, src, len) = (extend(dst), extend(src), extend(len));
+            let (vmctx, func_addr) = self.translate_load_builtin_function_address(
+                &mut pos,
+                VMBuiltinFunctionIndex::get_memory_copy_between_index(),
+            );
+            pos.ins().call_indirect(
+                func_sig,
+                func_addr,
+                &[vmctx, dst_index_arg, src_index_arg, dst, src, len],
+            );
+            return Ok(());
+        }
+
         let (func_sig, src_index, func_idx) = self.get_memory_copy_func(&mut pos.func, src_index);
 
         let src_index_arg = pos.ins().iconst(I32, src_index as i64);
@@ -1357,7 +1817,7 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         src: ir::Value,
         len: ir::Value,
     ) -> WasmResult<()> {
-        let (func_sig, func_idx) = self.get_memory_init_func(&mut pos.func);
+        let (func_sig, func_idx) = self.get_memory_init_func(&mut pos.func, memory_index);
 
         let memory_index_arg = pos.ins().iconst(I32, memory_index.index() as i64);
         let seg_index_arg = pos.ins().iconst(I32, seg_index as i64);
@@ -1474,29 +1934,257 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
 
     fn translate_atomic_wait(
         &mut self,
-        _pos: FuncCursor,
-        _index: MemoryIndex,
+        mut pos: FuncCursor,
+        memory_index: MemoryIndex,
         _heap: ir::Heap,
-        _addr: ir::Value,
-        _expected: ir::Value,
-        _timeout: ir::Value,
+        addr: ir::Value,
+        expected: ir::Value,
+        timeout: ir::Value,
     ) -> WasmResult<ir::Value> {
-        Err(WasmError::Unsupported(
-            "wasm atomics (fn translate_atomic_wait)".to_string(),
-        ))
+        let expected_ty = pos.func.dfg.value_type(expected);
+        let (func_sig, func_idx) = self.get_memory_atomic_wait_func(&mut pos.func, expected_ty);
+
+        let memory_index_arg = pos.ins().iconst(I32, memory_index.index() as i64);
+        let addr = if pos.func.dfg.value_type(addr) == I32 {
+            pos.ins().uextend(I64, addr)
+        } else {
+            addr
+        };
+
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+
+        let call_inst = pos.ins().call_indirect(
+            func_sig,
+            func_addr,
+            &[vmctx, memory_index_arg, addr, expected, timeout],
+        );
+
+        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
     }
 
     fn translate_atomic_notify(
         &mut self,
-        _pos: FuncCursor,
-        _index: MemoryIndex,
+        mut pos: FuncCursor,
+        memory_index: MemoryIndex,
         _heap: ir::Heap,
-        _addr: ir::Value,
-        _count: ir::Value,
+        addr: ir::Value,
+        count: ir::Value,
+    ) -> WasmResult<ir::Value> {
+        let (func_sig, func_idx) = self.get_memory_atomic_notify_func(&mut pos.func);
+
+        let memory_index_arg = pos.ins().iconst(I32, memory_index.index() as i64);
+        let addr = if pos.func.dfg.value_type(addr) == I32 {
+            pos.ins().uextend(I64, addr)
+        } else {
+            addr
+        };
+
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+
+        let call_inst =
+            pos.ins()
+                .call_indirect(func_sig, func_addr, &[vmctx, memory_index_arg, addr, count]);
+
+        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
+    }
+
+    fn catches_exceptions(&self) -> bool {
+        self.exceptions
+    }
+
+    fn translate_exception_pending(
+        &mut self,
+        builder: &mut FunctionBuilder,
     ) -> WasmResult<ir::Value> {
-        Err(WasmError::Unsupported(
-            "wasm atomics (fn translate_atomic_notify)".to_string(),
-        ))
+        let pointer_type = self.pointer_type();
+        let mem_flags = ir::MemFlags::trusted();
+        let vmctx = self.vmctx(builder.func);
+        let base = builder.ins().global_value(pointer_type, vmctx);
+
+        // The runtime is only asked whether the callee threw when some thread is propagating an
+        // exception.
+        let pending_exceptions_offset =
+            i32::try_from(self.offsets.vmctx_pending_exceptions_pointer()).unwrap();
+        let pending_exceptions =
+            builder
+                .ins()
+                .load(pointer_type, mem_flags, base, pending_exceptions_offset);
+        let count = builder.ins().load(I32, mem_flags, pending_exceptions, 0);
+        let ask_block = builder.create_block();
+        let done_block = builder.create_block();
+        builder.append_block_param(done_block, I32);
+        builder.ins().brnz(count, ask_block, &[]);
+        builder.ins().jump(done_block, &[count]);
+        builder.seal_block(ask_block);
+
+        builder.switch_to_block(ask_block);
+        let pending = self
+            .call_exception_builtin(
+                &mut builder.cursor(),
+                VMBuiltinFunctionIndex::get_exception_pending_index(),
+                &[],
+                Some(I32),
+            )
+            .unwrap();
+        builder.ins().jump(done_block, &[pending]);
+        builder.seal_block(done_block);
+
+        builder.switch_to_block(done_block);
+        Ok(builder.block_params(done_block)[0])
+    }
+
+    fn translate_throw(
+        &mut self,
+        mut pos: FuncCursor,
+        tag_index: TagIndex,
+        args: &[ir::Value],
+    ) -> WasmResult<()> {
+        let tag_index = pos.ins().iconst(I32, tag_index.index() as i64);
+        self.call_exception_builtin(
+            &mut pos,
+            VMBuiltinFunctionIndex::get_exception_new_index(),
+            &[tag_index],
+            None,
+        );
+        for arg in args {
+            // The values are thrown as their bits.
+            let bits = match pos.func.dfg.value_type(*arg) {
+                I32 => pos.ins().uextend(I64, *arg),
+                I64 => *arg,
+                F32 => {
+                    let bits = pos.ins().bitcast(I32, *arg);
+                    pos.ins().uextend(I64, bits)
+                }
+                F64 => pos.ins().bitcast(I64, *arg),
+                ty => {
+                    return Err(WasmError::Unsupported(format!(
+                        "throwing values of type {}",
+                        ty
+                    )))
+                }
+            };
+            self.call_exception_builtin(
+                &mut pos,
+                VMBuiltinFunctionIndex::get_exception_push_index(),
+                &[bits],
+                None,
+            );
+        }
+        Ok(())
+    }
+
+    fn translate_rethrow(
+        &mut self,
+        mut pos: FuncCursor,
+        caught: ir::Value,
+        slot: u32,
+    ) -> WasmResult<()> {
+        let slot = pos.ins().iconst(I32, slot as i64);
+        self.call_exception_builtin(
+            &mut pos,
+            VMBuiltinFunctionIndex::get_exception_rethrow_index(),
+            &[caught, slot],
+            None,
+        );
+        Ok(())
+    }
+
+    fn translate_caught_exceptions_new(&mut self, mut pos: FuncCursor) -> WasmResult<ir::Value> {
+        let pointer_type = self.pointer_type();
+        Ok(self
+            .call_exception_builtin(
+                &mut pos,
+                VMBuiltinFunctionIndex::get_exception_caught_new_index(),
+                &[],
+                Some(pointer_type),
+            )
+            .unwrap())
+    }
+
+    fn translate_caught_exceptions_drop(
+        &mut self,
+        mut pos: FuncCursor,
+        caught: ir::Value,
+    ) -> WasmResult<()> {
+        self.call_exception_builtin(
+            &mut pos,
+            VMBuiltinFunctionIndex::get_exception_caught_drop_index(),
+            &[caught],
+            None,
+        );
+        Ok(())
+    }
+
+    fn translate_catch(
+        &mut self,
+        mut pos: FuncCursor,
+        caught: ir::Value,
+        slot: u32,
+        tag_index: Option<TagIndex>,
+    ) -> WasmResult<ir::Value> {
+        let slot = pos.ins().iconst(I32, slot as i64);
+        // `catch_all` matches any tag.
+        let tag_index = match tag_index {
+            Some(tag_index) => pos.ins().iconst(I32, tag_index.index() as i64),
+            None => pos.ins().iconst(I32, u32::MAX as i64),
+        };
+        Ok(self
+            .call_exception_builtin(
+                &mut pos,
+                VMBuiltinFunctionIndex::get_exception_catch_index(),
+                &[caught, slot, tag_index],
+                Some(I32),
+            )
+            .unwrap())
+    }
+
+    fn translate_exception_payload(
+        &mut self,
+        mut pos: FuncCursor,
+        caught: ir::Value,
+        slot: u32,
+        index: u32,
+        ty: WasmerType,
+    ) -> WasmResult<ir::Value> {
+        let slot = pos.ins().iconst(I32, slot as i64);
+        let index = pos.ins().iconst(I32, index as i64);
+        let bits = self
+            .call_exception_builtin(
+                &mut pos,
+                VMBuiltinFunctionIndex::get_exception_payload_index(),
+                &[caught, slot, index],
+                Some(I64),
+            )
+            .unwrap();
+        Ok(match ty {
+            WasmerType::I32 => pos.ins().ireduce(I32, bits),
+            WasmerType::I64 => bits,
+            WasmerType::F32 => {
+                let bits = pos.ins().ireduce(I32, bits);
+                pos.ins().bitcast(F32, bits)
+            }
+            WasmerType::F64 => pos.ins().bitcast(F64, bits),
+            ty => {
+                return Err(WasmError::Unsupported(format!(
+                    "catching values of type {}",
+                    ty
+                )))
+            }
+        })
+    }
+
+    fn translate_function_entry(&mut self, builder: &mut FunctionBuilder) -> WasmResult<()> {
+        if self.epoch_interruption {
+            self.epoch_check(builder);
+        }
+        Ok(())
+    }
+
+    fn translate_loop_header(&mut self, builder: &mut FunctionBuilder) -> WasmResult<()> {
+        if self.epoch_interruption {
+            self.epoch_check(builder);
+        }
+        Ok(())
     }
 
     fn get_global_type(&self, global_index: GlobalIndex) -> Option<WasmerType> {
@@ -1533,6 +2221,10 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         self.module.signatures.get(sig_index)
     }
 
+    fn get_tag_type(&self, tag_index: TagIndex) -> Option<&TagType> {
+        self.module.tags.get(tag_index)
+    }
+
     fn translate_drop_locals(&mut self, builder: &mut FunctionBuilder) -> WasmResult<()> {
         // TODO: this allocation can be removed without too much effort but it will require
         //       maneuvering around the borrow checker
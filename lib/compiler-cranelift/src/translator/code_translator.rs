@@ -96,7 +96,8 @@ use wasmer_compiler::wasmparser::{MemoryImmediate, Operator, Type as WPType};
 use wasmer_compiler::WasmResult;
 use wasmer_compiler::{wasm_unsupported, ModuleTranslationState};
 use wasmer_types::{
-    FunctionIndex, GlobalIndex, MemoryIndex, SignatureIndex, TableIndex, Type as WasmerType,
+    FunctionIndex, GlobalIndex, MemoryIndex, SignatureIndex, TableIndex, TagIndex,
+    Type as WasmerType,
 };
 
 // Clippy warns about "align: _" but its important to document that the align field is ignored
@@ -315,7 +316,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 .extend_from_slice(builder.block_params(loop_body));
 
             builder.switch_to_block(loop_body);
-            environ.translate_loop_header(builder.cursor())?;
+            environ.translate_loop_header(builder)?;
         }
         Operator::If { ty } => {
             let (val, _) = state.pop1();
@@ -425,7 +426,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 _ => unreachable!(),
             }
         }
-        Operator::End => {
+        Operator::End | Operator::Delegate { .. } => {
             let frame = state.control_stack.pop().unwrap();
             let next_block = frame.following_code();
 
@@ -440,6 +441,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 // since we truncate the stack back to the original height
                 // below.
             }
+            translate_try_end(op, &frame, builder, state);
 
             builder.switch_to_block(next_block);
             builder.seal_block(next_block);
@@ -577,6 +579,9 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             state.reachable = false;
         }
         Operator::Return => {
+            if environ.return_mode() == ReturnMode::NormalReturns {
+                translate_caught_exceptions_drop(builder, state, environ)?;
+            }
             let (return_count, br_destination) = {
                 let frame = &mut state.control_stack[0];
                 if environ.return_mode() == ReturnMode::FallthroughReturn {
@@ -604,14 +609,48 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             state.popn(return_count);
             state.reachable = false;
         }
-        /********************************** Exception handing **********************************/
-        Operator::Try { .. }
-        | Operator::Catch { .. }
-        | Operator::Throw { .. }
-        | Operator::Unwind
-        | Operator::Rethrow { .. }
-        | Operator::Delegate { .. }
-        | Operator::CatchAll => {
+        /********************************** Exception handing **********************************
+         *  Exceptions are propagated explicitly: after every call, an exception thrown by the
+         *  callee branches to the catch clauses of the innermost `try` body around the call, or
+         *  else out of the function, which returns with the exception still pending for its
+         *  caller to propagate in turn.
+         *
+         *  The `try` blocks are translated like `block`s, with a `landing` block where the
+         *  exceptions propagating through them arrive. Each catch clause tries to catch the
+         *  exception there, and moves the landing to the next clause if it doesn't match.
+         ***********************************************************************************/
+        Operator::Try { ty } => {
+            let (params, results) = module_translation_state.blocktype_params_results(*ty)?;
+            let next = block_with_params(builder, results, environ)?;
+            let landing = builder.create_block();
+            state.push_try(next, landing, params.len(), results.len());
+        }
+        Operator::Catch { .. } | Operator::CatchAll => {
+            translate_catch(op, builder, state, environ)?;
+        }
+        Operator::Throw { index } => {
+            let tag_index = TagIndex::from_u32(*index);
+            let num_args = environ.get_tag_type(tag_index).unwrap().params().len();
+            let (args, _) = state.peekn(num_args);
+            environ.translate_throw(builder.cursor(), tag_index, args)?;
+            state.popn(num_args);
+            let target = exception_target(state.control_stack.len(), builder, state);
+            builder.ins().jump(target, &[]);
+            state.reachable = false;
+        }
+        Operator::Rethrow { relative_depth } => {
+            let i = state.control_stack.len() - 1 - (*relative_depth as usize);
+            let slot = match state.control_stack[i] {
+                ControlStackFrame::Try { slot, .. } => slot,
+                _ => unreachable!(),
+            };
+            let caught = builder.use_var(state.caught_exceptions.unwrap());
+            environ.translate_rethrow(builder.cursor(), caught, slot)?;
+            let target = exception_target(state.control_stack.len(), builder, state);
+            builder.ins().jump(target, &[]);
+            state.reachable = false;
+        }
+        Operator::Unwind => {
             return Err(wasm_unsupported!(
                 "proposed exception handling operator {:?}",
                 op
@@ -623,6 +662,19 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
          * argument referring to an index in the external functions table of the module.
          ************************************************************************************/
         Operator::Call { function_index } => {
+            let func_index = FunctionIndex::from_u32(*function_index);
+            if let Some(count) = gas_intrinsic_count(
+                module_translation_state,
+                func_index,
+                builder,
+                state,
+                environ,
+            ) {
+                environ.translate_gas_intrinsic(builder, count)?;
+                state.pop1();
+                return Ok(());
+            }
+
             let (fref, num_args) = state.get_direct_func(builder.func, *function_index, environ)?;
 
             let (args, _args_metadata) = state.peekn_mut(num_args);
@@ -634,7 +686,6 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 environ.is_wasm_parameter(&callee_signature, i)
             });
             bitcast_arguments(args, &types, builder);
-            let func_index = FunctionIndex::from_u32(*function_index);
 
             let call = environ.translate_call(builder.cursor(), func_index, fref, args)?;
             let inst_results = builder.inst_results(call);
@@ -659,6 +710,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             }
             state.popn(num_args);
             state.pushn(inst_results, &results_metadata);
+            translate_exception_check(builder, state, environ)?;
         }
         Operator::CallIndirect { index, table_index } => {
             // `index` is the index of the function's signature and `table_index` is the index of
@@ -707,6 +759,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             }
             state.popn(num_args);
             state.pushn(inst_results, &results_metadata);
+            translate_exception_check(builder, state, environ)?;
         }
         /******************************* Memory management ***********************************
          * Memory management is handled by environment. It is usually translated into calls to
@@ -2007,6 +2060,12 @@ fn translate_unreachable_operator<FE: FuncEnvironment + ?Sized>(
         Operator::Loop { ty: _ } | Operator::Block { ty: _ } => {
             state.push_block(ir::Block::reserved_value(), 0, 0);
         }
+        Operator::Try { ty: _ } => {
+            state.push_try(ir::Block::reserved_value(), ir::Block::reserved_value(), 0, 0);
+        }
+        Operator::Catch { .. } | Operator::CatchAll => {
+            translate_catch(op, builder, state, environ)?;
+        }
         Operator::Else => {
             let i = state.control_stack.len() - 1;
             match state.control_stack[i] {
@@ -2055,10 +2114,10 @@ fn translate_unreachable_operator<FE: FuncEnvironment + ?Sized>(
                 _ => unreachable!(),
             }
         }
-        Operator::End => {
+        Operator::End | Operator::Delegate { .. } => {
+            let frame = state.control_stack.pop().unwrap();
+            translate_try_end(op, &frame, builder, state);
             let stack = &mut state.stack;
-            let control_stack = &mut state.control_stack;
-            let frame = control_stack.pop().unwrap();
 
             // Pop unused parameters from stack.
             frame.truncate_value_stack_to_original_size(stack);
@@ -2110,6 +2169,44 @@ fn translate_unreachable_operator<FE: FuncEnvironment + ?Sized>(
     Ok(())
 }
 
+/// Returns the number of opcodes to charge inline if a call to `callee_index` is a call to the
+/// `gas` intrinsic.
+///
+/// Only calls passing a constant in `0..i32::MAX` to an imported `gas` function of type
+/// `[i32] -> []` are intrinsified, the same as in singlepass; any other call to it is left as
+/// an ordinary call to the host.
+fn gas_intrinsic_count<FE: FuncEnvironment + ?Sized>(
+    module_translation_state: &ModuleTranslationState,
+    callee_index: FunctionIndex,
+    builder: &FunctionBuilder,
+    state: &FuncTranslationState,
+    environ: &FE,
+) -> Option<u32> {
+    if module_translation_state.import_map.get(&callee_index)? != "gas" {
+        return None;
+    }
+    let func_type = environ.get_function_type(callee_index)?;
+    if func_type.params() != [WasmerType::I32] || !func_type.results().is_empty() {
+        return None;
+    }
+    let arg = *state.stack.last()?;
+    let inst = builder.func.dfg.value_def(arg).inst()?;
+    match builder.func.dfg[inst] {
+        ir::InstructionData::UnaryImm {
+            opcode: ir::Opcode::Iconst,
+            imm,
+        } => {
+            let count = imm.bits();
+            if (0..i64::from(i32::MAX)).contains(&count) {
+                Some(count as u32)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
 /// Get the address+offset to use for a heap access.
 fn get_heap_addr(
     heap: ir::Heap,
@@ -2295,7 +2392,19 @@ fn fold_atomic_mem_addr(
     builder: &mut FunctionBuilder,
 ) -> Value {
     let access_ty_bytes = access_ty.bytes();
-    let final_lma = if memarg.offset > 0 {
+    let final_lma = if memarg.offset > 0 && builder.func.dfg.value_type(linear_mem_addr) == I64 {
+        // A 64-bit address can't be widened to catch the overflow.
+        let a = builder
+            .ins()
+            .iadd_imm(linear_mem_addr, i64::from(memarg.offset));
+        let overflow = builder
+            .ins()
+            .icmp(IntCC::UnsignedLessThan, a, linear_mem_addr);
+        builder
+            .ins()
+            .trapnz(overflow, ir::TrapCode::HeapOutOfBounds);
+        a
+    } else if memarg.offset > 0 {
         assert!(builder.func.dfg.value_type(linear_mem_addr) == I32);
         let linear_mem_addr = builder.ins().uextend(I64, linear_mem_addr);
         let a = builder
@@ -2339,6 +2448,14 @@ fn finalise_atomic_mem_addr<FE: FuncEnvironment + ?Sized>(
     let final_lma = builder
         .ins()
         .iadd_imm(linear_mem_addr, i64::from(memarg.offset));
+    if memarg.offset > 0 && builder.func.dfg.value_type(linear_mem_addr) == I64 {
+        let overflow = builder
+            .ins()
+            .icmp(IntCC::UnsignedLessThan, final_lma, linear_mem_addr);
+        builder
+            .ins()
+            .trapnz(overflow, ir::TrapCode::HeapOutOfBounds);
+    }
     if access_ty_bytes != 1 {
         assert!(access_ty_bytes == 2 || access_ty_bytes == 4 || access_ty_bytes == 8);
         let final_lma_misalignment = builder
@@ -2624,6 +2741,214 @@ fn translate_br_if_args(
     (br_destination, inputs)
 }
 
+/// Where the exceptions propagating out of the `depth` outermost control frames branch to: the
+/// catch clauses of the innermost `try` body among them, or else the exit of the function, which
+/// returns with the exception still pending for the caller to propagate.
+fn exception_target(
+    depth: usize,
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+) -> ir::Block {
+    let landing = state.control_stack[..depth]
+        .iter()
+        .rev()
+        .find_map(|frame| match *frame {
+            ControlStackFrame::Try {
+                landing,
+                catching: false,
+                ..
+            } => Some(landing),
+            _ => None,
+        });
+    match landing {
+        Some(landing) => landing,
+        None => *state
+            .exception_exit
+            .get_or_insert_with(|| builder.create_block()),
+    }
+}
+
+/// Propagates the exception thrown by the function just called, if any.
+fn translate_exception_check<FE: FuncEnvironment + ?Sized>(
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    let pending = environ.translate_exception_pending(builder)?;
+    let target = exception_target(state.control_stack.len(), builder, state);
+    canonicalise_then_brnz(builder, pending, target, (&[], &[]));
+
+    let next_block = builder.create_block();
+    canonicalise_then_jump(builder, next_block, (&[], &[]));
+    builder.seal_block(next_block); // The only predecessor is the current block.
+    builder.switch_to_block(next_block);
+    Ok(())
+}
+
+/// Translates a `catch` or `catch_all` clause of the innermost `try` block.
+fn translate_catch<FE: FuncEnvironment + ?Sized>(
+    op: &Operator,
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    let i = state.control_stack.len() - 1;
+    let (destination, landing, slot, num_return_values, head_is_reachable) =
+        match state.control_stack[i] {
+            ControlStackFrame::Try {
+                destination,
+                landing,
+                slot,
+                num_return_values,
+                head_is_reachable,
+                ..
+            } => (
+                destination,
+                landing,
+                slot,
+                num_return_values,
+                head_is_reachable,
+            ),
+            _ => unreachable!(),
+        };
+
+    // The body, or the previous clause, ends by branching to the code after the block.
+    if state.reachable {
+        canonicalise_then_jump(builder, destination, state.peekn(num_return_values));
+        state.control_stack[i].set_branched_to_exit();
+    }
+    state.control_stack[i].truncate_value_stack_to_original_size(&mut state.stack);
+    if let ControlStackFrame::Try {
+        ref mut catching, ..
+    } = state.control_stack[i]
+    {
+        *catching = true;
+    }
+    if !head_is_reachable {
+        state.reachable = false;
+        return Ok(());
+    }
+
+    // All the exceptions of the body, or the previous clause, have branched to the landing.
+    builder.switch_to_block(landing);
+    builder.seal_block(landing);
+    let caught = translate_caught_exceptions(builder, state, environ)?;
+    let tag_index = match *op {
+        Operator::Catch { index } => Some(TagIndex::from_u32(index)),
+        _ => None,
+    };
+    let matches = environ.translate_catch(builder.cursor(), caught, slot, tag_index)?;
+    let next_landing = builder.create_block();
+    canonicalise_then_brz(builder, matches, next_landing, (&[], &[]));
+    let clause_block = builder.create_block();
+    canonicalise_then_jump(builder, clause_block, (&[], &[]));
+    builder.seal_block(clause_block); // The only predecessor is the current block.
+    builder.switch_to_block(clause_block);
+    if let ControlStackFrame::Try {
+        ref mut landing, ..
+    } = state.control_stack[i]
+    {
+        *landing = next_landing;
+    }
+
+    if let Some(tag_index) = tag_index {
+        let params = environ.get_tag_type(tag_index).unwrap().params().to_vec();
+        for (index, ty) in params.into_iter().enumerate() {
+            let value = environ.translate_exception_payload(
+                builder.cursor(),
+                caught,
+                slot,
+                index as u32,
+                ty,
+            )?;
+            state.push1(value);
+        }
+    }
+    state.reachable = true;
+    Ok(())
+}
+
+/// Branches from the landing of the `try` block `frame` that just ended, where the exceptions
+/// none of its catch clauses caught arrive, to the catch clauses around the label a `delegate`
+/// targets, or else around the block.
+fn translate_try_end(
+    op: &Operator,
+    frame: &ControlStackFrame,
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+) {
+    if let ControlStackFrame::Try {
+        landing,
+        head_is_reachable: true,
+        ..
+    } = *frame
+    {
+        let depth = match *op {
+            Operator::Delegate { relative_depth } => {
+                state.control_stack.len() - relative_depth as usize
+            }
+            _ => state.control_stack.len(),
+        };
+        let target = exception_target(depth, builder, state);
+        builder.switch_to_block(landing);
+        builder.seal_block(landing);
+        canonicalise_then_jump(builder, target, (&[], &[]));
+    }
+}
+
+/// Allocates the exceptions caught by the activation unless it already has them, and returns
+/// them.
+fn translate_caught_exceptions<FE: FuncEnvironment + ?Sized>(
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<Value> {
+    let caught_exceptions = state.caught_exceptions.unwrap();
+    let caught = builder.use_var(caught_exceptions);
+    let new_block = builder.create_block();
+    let done_block = builder.create_block();
+    builder.append_block_param(done_block, environ.pointer_type());
+    builder.ins().brz(caught, new_block, &[]);
+    builder.ins().jump(done_block, &[caught]);
+    builder.seal_block(new_block);
+
+    builder.switch_to_block(new_block);
+    let caught = environ.translate_caught_exceptions_new(builder.cursor())?;
+    builder.ins().jump(done_block, &[caught]);
+    builder.seal_block(done_block);
+
+    builder.switch_to_block(done_block);
+    let caught = builder.block_params(done_block)[0];
+    builder.def_var(caught_exceptions, caught);
+    Ok(caught)
+}
+
+/// Frees the exceptions caught by the activation, if any, before it returns.
+pub fn translate_caught_exceptions_drop<FE: FuncEnvironment + ?Sized>(
+    builder: &mut FunctionBuilder,
+    state: &FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    let caught_exceptions = match state.caught_exceptions {
+        Some(caught_exceptions) => caught_exceptions,
+        None => return Ok(()),
+    };
+    let caught = builder.use_var(caught_exceptions);
+    let drop_block = builder.create_block();
+    let done_block = builder.create_block();
+    builder.ins().brnz(caught, drop_block, &[]);
+    builder.ins().jump(done_block, &[]);
+    builder.seal_block(drop_block);
+
+    builder.switch_to_block(drop_block);
+    environ.translate_caught_exceptions_drop(builder.cursor(), caught)?;
+    builder.ins().jump(done_block, &[]);
+    builder.seal_block(done_block);
+
+    builder.switch_to_block(done_block);
+    Ok(())
+}
+
 /// Determine the returned value type of a WebAssembly operator
 fn type_of(operator: &Operator) -> Type {
     match operator {
@@ -16,7 +16,7 @@ use wasmer_compiler::wasmparser::{Operator, Type};
 use wasmer_compiler::WasmResult;
 use wasmer_types::{
     FunctionIndex, FunctionType, GlobalIndex, LocalFunctionIndex, MemoryIndex, SignatureIndex,
-    TableIndex, Type as WasmerType,
+    TableIndex, TagIndex, TagType, Type as WasmerType,
 };
 
 /// The value of a WebAssembly global variable.
@@ -196,6 +196,16 @@ pub trait FuncEnvironment: TargetEnvironment {
         Ok(pos.ins().call(callee, call_args))
     }
 
+    /// Translate a `call` to the `gas` intrinsic.
+    ///
+    /// Insert instructions charging `count` opcodes against the instance's gas counter in
+    /// place of the call to the imported function, trapping once the gas limit is reached.
+    fn translate_gas_intrinsic(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        count: u32,
+    ) -> WasmResult<()>;
+
     /// Translate a `memory.grow` WebAssembly instruction.
     ///
     /// The `index` provided identifies the linear memory to grow, and `heap` is the heap reference
@@ -454,11 +464,83 @@ pub trait FuncEnvironment: TargetEnvironment {
         count: ir::Value,
     ) -> WasmResult<ir::Value>;
 
+    /// Whether the function may catch exceptions, in which case it holds on to the exceptions it
+    /// catches until it returns.
+    fn catches_exceptions(&self) -> bool {
+        false
+    }
+
+    /// Translate the check following every call for an exception thrown by the callee.
+    ///
+    /// Returns an i32, which is non-zero if an exception is being propagated.
+    fn translate_exception_pending(&mut self, builder: &mut FunctionBuilder)
+        -> WasmResult<ir::Value>;
+
+    /// Translate a `throw` WebAssembly instruction, starting to propagate an exception with the
+    /// tag at `tag_index` and the values `args`.
+    fn translate_throw(
+        &mut self,
+        pos: FuncCursor,
+        tag_index: TagIndex,
+        args: &[ir::Value],
+    ) -> WasmResult<()>;
+
+    /// Translate a `rethrow` WebAssembly instruction, starting to propagate the exception caught
+    /// in `slot` of `caught` again.
+    fn translate_rethrow(
+        &mut self,
+        pos: FuncCursor,
+        caught: ir::Value,
+        slot: u32,
+    ) -> WasmResult<()>;
+
+    /// Allocate the storage for the exceptions caught by the activation.
+    fn translate_caught_exceptions_new(&mut self, pos: FuncCursor) -> WasmResult<ir::Value>;
+
+    /// Free the storage allocated by `translate_caught_exceptions_new`.
+    fn translate_caught_exceptions_drop(
+        &mut self,
+        pos: FuncCursor,
+        caught: ir::Value,
+    ) -> WasmResult<()>;
+
+    /// Translate a `catch` WebAssembly instruction, or a `catch_all` one if `tag_index` is
+    /// `None`, catching the exception being propagated into `slot` of `caught` if its tag
+    /// matches.
+    ///
+    /// Returns an i32, which is non-zero if the exception was caught.
+    fn translate_catch(
+        &mut self,
+        pos: FuncCursor,
+        caught: ir::Value,
+        slot: u32,
+        tag_index: Option<TagIndex>,
+    ) -> WasmResult<ir::Value>;
+
+    /// Read the value at `index`, of type `ty`, thrown with the exception caught in `slot` of
+    /// `caught`.
+    fn translate_exception_payload(
+        &mut self,
+        pos: FuncCursor,
+        caught: ir::Value,
+        slot: u32,
+        index: u32,
+        ty: WasmerType,
+    ) -> WasmResult<ir::Value>;
+
+    /// Emit code at the beginning of every wasm function, after the locals are declared.
+    ///
+    /// This can be used to insert explicit interrupt checking on function entry.
+    fn translate_function_entry(&mut self, _builder: &mut FunctionBuilder) -> WasmResult<()> {
+        // By default, don't emit anything.
+        Ok(())
+    }
+
     /// Emit code at the beginning of every wasm loop.
     ///
     /// This can be used to insert explicit interrupt or safepoint checking at
     /// the beginnings of loops.
-    fn translate_loop_header(&mut self, _pos: FuncCursor) -> WasmResult<()> {
+    fn translate_loop_header(&mut self, _builder: &mut FunctionBuilder) -> WasmResult<()> {
         // By default, don't emit anything.
         Ok(())
     }
@@ -506,6 +588,9 @@ pub trait FuncEnvironment: TargetEnvironment {
     /// Get the type of a function with the given signature index.
     fn get_function_sig(&self, sig_index: SignatureIndex) -> Option<&FunctionType>;
 
+    /// Get the type of the exception tag at the given index.
+    fn get_tag_type(&self, tag_index: TagIndex) -> Option<&TagType>;
+
     /// Drops all locals that need to be dropped. Useful for returning from functions.
     fn translate_drop_locals(&mut self, builder: &mut FunctionBuilder) -> WasmResult<()>;
 }
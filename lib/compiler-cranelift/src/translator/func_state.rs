@@ -12,6 +12,7 @@
 use super::func_environ::{FuncEnvironment, GlobalVariable};
 use crate::{HashMap, Occupied, Vacant};
 use cranelift_codegen::ir::{self, Block, Inst, Value};
+use cranelift_frontend::Variable;
 use std::vec::Vec;
 use wasmer_compiler::WasmResult;
 use wasmer_types::{FunctionIndex, GlobalIndex, MemoryIndex, SignatureIndex, TableIndex};
@@ -43,8 +44,8 @@ pub enum ElseData {
     },
 }
 
-/// A control stack frame can be an `if`, a `block` or a `loop`, each one having the following
-/// fields:
+/// A control stack frame can be an `if`, a `block`, a `loop` or a `try`, each one having the
+/// following fields:
 ///
 /// - `destination`: reference to the `Block` that will hold the code after the control block;
 /// - `num_return_values`: number of values returned by the control block;
@@ -52,7 +53,8 @@ pub enum ElseData {
 ///
 /// Moreover, the `if` frame has the `branch_inst` field that points to the `brz` instruction
 /// separating the `true` and `false` branch. The `loop` frame has a `header` field that references
-/// the `Block` that contains the beginning of the body of the loop.
+/// the `Block` that contains the beginning of the body of the loop, and the `try` frame has a
+/// `landing` field that references the `Block` the exceptions propagating through it branch to.
 #[derive(Debug)]
 pub enum ControlStackFrame {
     If {
@@ -89,6 +91,23 @@ pub enum ControlStackFrame {
         num_return_values: usize,
         original_stack_size: usize,
     },
+    Try {
+        destination: Block,
+        /// Where the exceptions propagating through the block branch to: the first catch clause
+        /// from the body, and the next one from the catch clauses.
+        landing: Block,
+        /// Ordinal of the `try` in the function, which is also the slot its catch clauses store
+        /// caught exceptions into.
+        slot: u32,
+        /// Whether the body is over and the catch clauses are being translated.
+        catching: bool,
+        num_param_values: usize,
+        num_return_values: usize,
+        original_stack_size: usize,
+        exit_is_branched_to: bool,
+        /// Was the head of the `try` reachable?
+        head_is_reachable: bool,
+    },
 }
 
 /// Helper methods for the control stack objects.
@@ -103,6 +122,9 @@ impl ControlStackFrame {
             }
             | Self::Loop {
                 num_return_values, ..
+            }
+            | Self::Try {
+                num_return_values, ..
             } => num_return_values,
         }
     }
@@ -116,6 +138,9 @@ impl ControlStackFrame {
             }
             | Self::Loop {
                 num_param_values, ..
+            }
+            | Self::Try {
+                num_param_values, ..
             } => num_param_values,
         }
     }
@@ -123,12 +148,15 @@ impl ControlStackFrame {
         match *self {
             Self::If { destination, .. }
             | Self::Block { destination, .. }
-            | Self::Loop { destination, .. } => destination,
+            | Self::Loop { destination, .. }
+            | Self::Try { destination, .. } => destination,
         }
     }
     pub fn br_destination(&self) -> Block {
         match *self {
-            Self::If { destination, .. } | Self::Block { destination, .. } => destination,
+            Self::If { destination, .. }
+            | Self::Block { destination, .. }
+            | Self::Try { destination, .. } => destination,
             Self::Loop { header, .. } => header,
         }
     }
@@ -147,12 +175,16 @@ impl ControlStackFrame {
             | Self::Loop {
                 original_stack_size,
                 ..
+            }
+            | Self::Try {
+                original_stack_size,
+                ..
             } => original_stack_size,
         }
     }
     pub fn is_loop(&self) -> bool {
         match *self {
-            Self::If { .. } | Self::Block { .. } => false,
+            Self::If { .. } | Self::Block { .. } | Self::Try { .. } => false,
             Self::Loop { .. } => true,
         }
     }
@@ -166,6 +198,10 @@ impl ControlStackFrame {
             | Self::Block {
                 exit_is_branched_to,
                 ..
+            }
+            | Self::Try {
+                exit_is_branched_to,
+                ..
             } => exit_is_branched_to,
             Self::Loop { .. } => false,
         }
@@ -180,6 +216,10 @@ impl ControlStackFrame {
             | Self::Block {
                 ref mut exit_is_branched_to,
                 ..
+            }
+            | Self::Try {
+                ref mut exit_is_branched_to,
+                ..
             } => *exit_is_branched_to = true,
             Self::Loop { .. } => {}
         }
@@ -253,6 +293,17 @@ pub struct FuncTranslationState {
     // `FuncEnvironment::make_direct_func()`.
     // Stores both the function reference and the number of WebAssembly arguments
     functions: HashMap<FunctionIndex, (ir::FuncRef, usize)>,
+
+    /// The variable holding the exceptions caught by the activation, allocated by the first
+    /// catch clause, if the function may catch exceptions.
+    pub(crate) caught_exceptions: Option<Variable>,
+
+    /// The block returning with the exception still pending for the caller to propagate, once
+    /// an exception may propagate out of the function.
+    pub(crate) exception_exit: Option<Block>,
+
+    /// The number of `try` blocks met so far.
+    tries: u32,
 }
 
 // Public methods that are exposed to non-`cranelift_wasm` API consumers.
@@ -279,6 +330,9 @@ impl FuncTranslationState {
             tables: HashMap::new(),
             signatures: HashMap::new(),
             functions: HashMap::new(),
+            caught_exceptions: None,
+            exception_exit: None,
+            tries: 0,
         }
     }
 
@@ -291,6 +345,9 @@ impl FuncTranslationState {
         self.tables.clear();
         self.signatures.clear();
         self.functions.clear();
+        self.caught_exceptions = None;
+        self.exception_exit = None;
+        self.tries = 0;
     }
 
     /// Initialize the state for compiling a function with the given signature.
@@ -491,6 +548,29 @@ impl FuncTranslationState {
             blocktype,
         });
     }
+
+    /// Push a try on the control stack.
+    pub(crate) fn push_try(
+        &mut self,
+        following_code: Block,
+        landing: Block,
+        num_param_types: usize,
+        num_result_types: usize,
+    ) {
+        debug_assert!(num_param_types <= self.stack.len());
+        self.control_stack.push(ControlStackFrame::Try {
+            destination: following_code,
+            landing,
+            slot: self.tries,
+            catching: false,
+            original_stack_size: self.stack.len() - num_param_types,
+            num_param_values: num_param_types,
+            num_return_values: num_result_types,
+            exit_is_branched_to: false,
+            head_is_reachable: self.reachable,
+        });
+        self.tries += 1;
+    }
 }
 
 /// Methods for handling entity references.
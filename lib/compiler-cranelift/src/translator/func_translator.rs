@@ -7,7 +7,9 @@
 //! function to Cranelift IR guided by a `FuncEnvironment` which provides information about the
 //! WebAssembly module and the runtime environment.
 
-use super::code_translator::{bitcast_arguments, translate_operator, wasm_param_types};
+use super::code_translator::{
+    bitcast_arguments, translate_caught_exceptions_drop, translate_operator, wasm_param_types,
+};
 use super::func_environ::{FuncEnvironment, ReturnMode};
 use super::func_state::FuncTranslationState;
 use super::translation_utils::get_vmctx_value_label;
@@ -16,7 +18,10 @@ use cranelift_codegen::ir::{self, Block, InstBuilder, ValueLabel};
 use cranelift_codegen::timing;
 use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
 use wasmer_compiler::wasmparser;
-use wasmer_compiler::{wasm_unsupported, wptype_to_type, ModuleTranslationState, WasmResult};
+use wasmer_compiler::{
+    wasm_unsupported, wptype_to_type, FunctionMiddleware, MiddlewareOperatorsReader,
+    ModuleTranslationState, WasmResult,
+};
 use wasmer_types::LocalFunctionIndex;
 
 /// WebAssembly to Cranelift IR function translator.
@@ -56,6 +61,8 @@ impl FuncTranslator {
     /// regarded as WebAssembly local variables. Any signature arguments marked as
     /// `ArgumentPurpose::Normal` are made accessible as WebAssembly local variables.
     ///
+    /// The operators of the function body are run through the `middlewares` chain before being
+    /// translated.
     pub fn translate<FE: FuncEnvironment + ?Sized>(
         &mut self,
         module_translation_state: &ModuleTranslationState,
@@ -63,9 +70,10 @@ impl FuncTranslator {
         func: &mut ir::Function,
         environ: &mut FE,
         local_function_index: LocalFunctionIndex,
+        middlewares: Vec<Box<dyn FunctionMiddleware>>,
     ) -> WasmResult<()> {
         environ.push_params_on_stack(local_function_index);
-        self.translate_from_reader(module_translation_state, reader, func, environ)
+        self.translate_from_reader(module_translation_state, reader, func, environ, middlewares)
     }
 
     /// Translate a binary WebAssembly function from a `FunctionBinaryReader`.
@@ -75,6 +83,7 @@ impl FuncTranslator {
         reader: &mut wasmer_compiler::FunctionReader,
         func: &mut ir::Function,
         environ: &mut FE,
+        middlewares: Vec<Box<dyn FunctionMiddleware>>,
     ) -> WasmResult<()> {
         let _tt = timing::wasm_translate_function();
         let _span = tracing::info_span!(
@@ -109,10 +118,21 @@ impl FuncTranslator {
         builder.append_block_params_for_function_returns(exit_block);
         self.state.initialize(&builder.func.signature, exit_block);
 
-        parse_local_decls(reader, &mut builder, num_params, environ)?;
+        let num_locals = parse_local_decls(reader, &mut builder, num_params, environ)?;
+        if environ.catches_exceptions() {
+            // The exceptions caught by the activation, allocated by the first catch clause.
+            let caught_exceptions = Variable::new(num_locals);
+            let pointer_type = environ.pointer_type();
+            builder.declare_var(caught_exceptions, pointer_type);
+            let null = builder.ins().iconst(pointer_type, 0);
+            builder.def_var(caught_exceptions, null);
+            self.state.caught_exceptions = Some(caught_exceptions);
+        }
+        environ.translate_function_entry(&mut builder)?;
         parse_function_body(
             module_translation_state,
             reader,
+            middlewares,
             &mut builder,
             &mut self.state,
             environ,
@@ -157,13 +177,14 @@ fn declare_wasm_parameters<FE: FuncEnvironment + ?Sized>(
 
 /// Parse the local variable declarations that precede the function body.
 ///
-/// Declare local variables, starting from `num_params`.
+/// Declare local variables, starting from `num_params`, and return the number of local
+/// variables declared, including the parameters.
 fn parse_local_decls<FE: FuncEnvironment + ?Sized>(
     reader: &wasmer_compiler::FunctionReader,
     builder: &mut FunctionBuilder,
     num_params: usize,
     environ: &mut FE,
-) -> WasmResult<()> {
+) -> WasmResult<usize> {
     let mut next_local = num_params;
     let mut local_reader = reader.get_locals_reader()?;
     let local_count = local_reader.get_count();
@@ -173,7 +194,7 @@ fn parse_local_decls<FE: FuncEnvironment + ?Sized>(
         declare_locals(builder, count, ty, &mut next_local, environ)?;
     }
 
-    Ok(())
+    Ok(next_local)
 }
 
 /// Declare `count` local variables of the same type, starting from `next_local`.
@@ -222,17 +243,18 @@ fn declare_locals<FE: FuncEnvironment + ?Sized>(
 fn parse_function_body<FE: FuncEnvironment + ?Sized>(
     module_translation_state: &ModuleTranslationState,
     reader: &wasmer_compiler::FunctionReader,
+    middlewares: Vec<Box<dyn FunctionMiddleware>>,
     builder: &mut FunctionBuilder,
     state: &mut FuncTranslationState,
     environ: &mut FE,
 ) -> WasmResult<()> {
     // The control stack is initialized with a single block representing the whole function.
     debug_assert_eq!(state.control_stack.len(), 1, "State not initialized");
-    let mut reader = reader.get_operators_reader()?.into_iter_with_offsets();
+    let mut reader = MiddlewareOperatorsReader::new(reader.get_operators_reader()?, middlewares);
 
     // Keep going until the final `End` operator which pops the outermost block.
     while !state.control_stack.is_empty() {
-        let (op, pos) = reader.next().unwrap()?;
+        let (op, pos) = reader.expect_operator()?;
         builder.set_srcloc(ir::SourceLoc::new(pos as u32));
         environ.before_translate_operator(&op, builder, state)?;
         translate_operator(module_translation_state, &op, builder, state, environ)?;
@@ -249,6 +271,7 @@ fn parse_function_body<FE: FuncEnvironment + ?Sized>(
     if state.reachable {
         debug_assert!(builder.is_pristine());
         if !builder.is_unreachable() {
+            translate_caught_exceptions_drop(builder, state, environ)?;
             environ.translate_drop_locals(builder)?;
 
             let _num_elems_to_drop = state.stack.len() - builder.func.signature.returns.len();
@@ -284,5 +307,44 @@ fn parse_function_body<FE: FuncEnvironment + ?Sized>(
     state.stack.clear();
     //state.metadata_stack.clear();
 
+    // The exceptions propagating out of the function return with placeholder values, which
+    // the caller doesn't use as it propagates the exception in turn.
+    if let Some(exception_exit) = state.exception_exit {
+        builder.switch_to_block(exception_exit);
+        builder.seal_block(exception_exit);
+        translate_caught_exceptions_drop(builder, state, environ)?;
+        let return_types = builder
+            .func
+            .signature
+            .returns
+            .iter()
+            .map(|ret| ret.value_type)
+            .collect::<Vec<_>>();
+        let return_values = return_types
+            .into_iter()
+            .map(|ty| zero_value(builder, ty))
+            .collect::<Vec<_>>();
+        match environ.return_mode() {
+            ReturnMode::NormalReturns => builder.ins().return_(&return_values),
+            ReturnMode::FallthroughReturn => builder.ins().fallthrough_return(&return_values),
+        };
+    }
+
     Ok(())
 }
+
+/// A zero value of type `ty`.
+fn zero_value(builder: &mut FunctionBuilder, ty: ir::Type) -> ir::Value {
+    if ty.is_ref() {
+        builder.ins().null(ty)
+    } else if ty.is_vector() {
+        let constant_handle = builder.func.dfg.constants.insert([0; 16].to_vec().into());
+        builder.ins().vconst(ty, constant_handle)
+    } else if ty == ir::types::F32 {
+        builder.ins().f32const(ir::immediates::Ieee32::with_bits(0))
+    } else if ty == ir::types::F64 {
+        builder.ins().f64const(ir::immediates::Ieee64::with_bits(0))
+    } else {
+        builder.ins().iconst(ty, 0)
+    }
+}
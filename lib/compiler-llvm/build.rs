@@ -0,0 +1,24 @@
+//! Builds the helpers reaching the parts of the LLVM API which are not
+//! exposed through its C API, against the LLVM found by `llvm-sys`.
+
+use std::env;
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-changed=cpp/must_tail.cpp");
+
+    let llvm_config = env::var("DEP_LLVM_12_CONFIG_PATH")
+        .expect("llvm-sys should export the path of llvm-config");
+    let output = Command::new(&llvm_config)
+        .arg("--cxxflags")
+        .output()
+        .expect("failed to run llvm-config");
+    let cxxflags = String::from_utf8(output.stdout).expect("llvm-config printed invalid UTF-8");
+
+    let mut build = cc::Build::new();
+    build.cpp(true).file("cpp/must_tail.cpp");
+    for flag in cxxflags.split_whitespace() {
+        build.flag(flag);
+    }
+    build.compile("wasmer_llvm_extras");
+}
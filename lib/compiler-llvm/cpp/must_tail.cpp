@@ -0,0 +1,8 @@
+// Parts of the LLVM API which are not exposed through its C API.
+
+#include <llvm-c/Types.h>
+#include <llvm/IR/Instructions.h>
+
+extern "C" void wasmer_llvm_set_must_tail_call(LLVMValueRef call) {
+  llvm::unwrap<llvm::CallInst>(call)->setTailCallKind(llvm::CallInst::TCK_MustTail);
+}
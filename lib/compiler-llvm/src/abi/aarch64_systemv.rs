@@ -38,6 +38,12 @@ impl Abi for Aarch64SystemV {
             .into_pointer_value()
     }
 
+    // x0 to x7, less the vmctx pointer, as the sret one is passed in x8, and
+    // v0 to v7.
+    fn param_registers(&self, _sret: bool) -> (u32, u32) {
+        (7, 8)
+    }
+
     // Given a wasm function type, produce an llvm function declaration.
     fn func_type_to_llvm<'ctx>(
         &self,
@@ -46,10 +52,10 @@ impl Abi for Aarch64SystemV {
         offsets: Option<&VMOffsets>,
         sig: &FuncSig,
     ) -> Result<(FunctionType<'ctx>, Vec<(Attribute, AttributeLoc)>), CompileError> {
-        let user_param_types = sig.params().iter().map(|&ty| type_to_llvm(intrinsics, ty));
-
-        let param_types =
-            std::iter::once(Ok(intrinsics.ctx_ptr_ty.as_basic_type_enum())).chain(user_param_types);
+        let param_types = self
+            .param_types_to_llvm(intrinsics, sig)?
+            .into_iter()
+            .map(Ok::<_, CompileError>);
 
         let vmctx_attributes = |i: u32| {
             vec![
@@ -241,6 +247,8 @@ impl Abi for Aarch64SystemV {
     fn args_to_call<'ctx>(
         &self,
         alloca_builder: &Builder<'ctx>,
+        builder: &Builder<'ctx>,
+        intrinsics: &Intrinsics<'ctx>,
         func_sig: &FuncSig,
         ctx_ptr: PointerValue<'ctx>,
         llvm_fn_ty: &FunctionType<'ctx>,
@@ -261,7 +269,13 @@ impl Abi for Aarch64SystemV {
             None
         };
 
-        let values = std::iter::once(ctx_ptr.as_basic_value_enum()).chain(values.iter().copied());
+        let values = std::iter::once(ctx_ptr.as_basic_value_enum()).chain(self.values_to_params(
+            builder,
+            intrinsics,
+            func_sig,
+            sret.is_some(),
+            values,
+        ));
 
         if let Some(sret) = sret {
             std::iter::once(sret.as_basic_value_enum())
@@ -5,17 +5,17 @@
 
 #![deny(dead_code, missing_docs)]
 
-use crate::translator::intrinsics::Intrinsics;
+use crate::translator::intrinsics::{type_to_llvm, Intrinsics};
 use inkwell::{
     attributes::{Attribute, AttributeLoc},
     builder::Builder,
     context::Context,
     targets::TargetMachine,
-    types::FunctionType,
-    values::{BasicValueEnum, CallSiteValue, FunctionValue, PointerValue},
+    types::{BasicType, BasicTypeEnum, FunctionType},
+    values::{BasicValueEnum, CallSiteValue, FunctionValue, IntValue, PointerValue},
 };
 use wasmer_compiler::CompileError;
-use wasmer_types::FunctionType as FuncSig;
+use wasmer_types::{FunctionType as FuncSig, Type};
 use wasmer_vm::VMOffsets;
 
 mod aarch64_systemv;
@@ -24,6 +24,76 @@ mod x86_64_systemv;
 use aarch64_systemv::Aarch64SystemV;
 use x86_64_systemv::X86_64SystemV;
 
+/// The stack argument slots of the uniform function declarations.
+///
+/// Every call reserves them, even when the callee takes fewer arguments on
+/// the stack, like the calls the singlepass compiler makes: the callee can
+/// tail call any function taking as many, passing them in its own.
+pub const RESERVED_STACK_ARGS: u32 = 8;
+
+/// Where a uniform function declaration takes a wasm parameter.
+#[derive(Clone, Copy)]
+enum ParamSlot {
+    /// An integer register.
+    Int(u32),
+    /// A vector register.
+    Vector(u32),
+    /// A stack argument slot.
+    Stack(u32),
+}
+
+impl ParamSlot {
+    /// The index of the slot among the parameters following the vmctx
+    /// pointer, given the number of integer and vector registers.
+    fn index(self, (ints, vectors): (u32, u32)) -> u32 {
+        match self {
+            ParamSlot::Int(index) => index,
+            ParamSlot::Vector(index) => ints + index,
+            ParamSlot::Stack(index) => ints + vectors + index,
+        }
+    }
+}
+
+/// Where the uniform declaration of functions of type `sig` takes their
+/// parameters, given the number of integer and vector registers, or `None` if
+/// they take `v128` ones or more than [`RESERVED_STACK_ARGS`] on the stack.
+fn uniform_param_slots((ints, vectors): (u32, u32), sig: &FuncSig) -> Option<Vec<ParamSlot>> {
+    let (mut next_int, mut next_vector, mut next_stack) = (0, 0, 0);
+    let mut slots = Vec::with_capacity(sig.params().len());
+    for ty in sig.params() {
+        let slot = match ty {
+            Type::I32 | Type::I64 | Type::ExternRef | Type::FuncRef if next_int < ints => {
+                next_int += 1;
+                ParamSlot::Int(next_int - 1)
+            }
+            Type::F32 | Type::F64 if next_vector < vectors => {
+                next_vector += 1;
+                ParamSlot::Vector(next_vector - 1)
+            }
+            Type::V128 => return None,
+            _ => {
+                next_stack += 1;
+                ParamSlot::Stack(next_stack - 1)
+            }
+        };
+        slots.push(slot);
+    }
+    if next_stack > RESERVED_STACK_ARGS {
+        return None;
+    }
+    Some(slots)
+}
+
+/// One `int` per integer register, one `vector` per vector register and one
+/// `int` per stack argument slot of a uniform function declaration.
+fn uniform_params<T: Copy>((ints, vectors): (u32, u32), int: T, vector: T) -> Vec<T> {
+    (0..ints)
+        .map(|_| int)
+        .chain((0..vectors).map(|_| vector))
+        .chain((0..RESERVED_STACK_ARGS).map(|_| int))
+        .collect()
+}
+
 pub fn get_abi(target_machine: &TargetMachine) -> Box<dyn Abi> {
     if target_machine
         .get_triple()
@@ -45,6 +115,150 @@ pub trait Abi {
     /// Given a function definition, retrieve the parameter that is the vmctx pointer.
     fn get_vmctx_ptr_param<'ctx>(&self, func_value: &FunctionValue<'ctx>) -> PointerValue<'ctx>;
 
+    /// The number of integer and vector registers passing the parameters
+    /// which follow the vmctx pointer, for functions returning through an
+    /// sret pointer if `sret` is set.
+    fn param_registers(&self, sret: bool) -> (u32, u32);
+
+    /// Given a wasm function type, produce the parameters of its llvm
+    /// function declaration, vmctx pointer included.
+    ///
+    /// The declaration is uniform where possible: it takes every argument
+    /// register and the [`RESERVED_STACK_ARGS`] stack argument slots, the
+    /// parameters of `sig` being where a declaration with only them would
+    /// have them. Functions returning the same values then share their
+    /// declaration, which lets them tail call each other, while they are
+    /// called like the ones of the other compilers.
+    fn param_types_to_llvm<'ctx>(
+        &self,
+        intrinsics: &Intrinsics<'ctx>,
+        sig: &FuncSig,
+    ) -> Result<Vec<BasicTypeEnum<'ctx>>, CompileError> {
+        let registers = self.param_registers(self.is_sret(sig)?);
+        let params = if uniform_param_slots(registers, sig).is_some() {
+            uniform_params(
+                registers,
+                intrinsics.i64_ty.as_basic_type_enum(),
+                intrinsics.i64x2_ty.as_basic_type_enum(),
+            )
+        } else {
+            sig.params()
+                .iter()
+                .map(|&ty| type_to_llvm(intrinsics, ty))
+                .collect::<Result<Vec<_>, _>>()?
+        };
+        Ok(std::iter::once(intrinsics.ctx_ptr_ty.as_basic_type_enum())
+            .chain(params)
+            .collect())
+    }
+
+    /// Marshall wasm stack values into the parameters following the vmctx
+    /// pointer of a function of type `sig`.
+    fn values_to_params<'ctx>(
+        &self,
+        builder: &Builder<'ctx>,
+        intrinsics: &Intrinsics<'ctx>,
+        sig: &FuncSig,
+        sret: bool,
+        values: &[BasicValueEnum<'ctx>],
+    ) -> Vec<BasicValueEnum<'ctx>> {
+        let registers = self.param_registers(sret);
+        let slots = match uniform_param_slots(registers, sig) {
+            Some(slots) => slots,
+            None => return values.to_vec(),
+        };
+        let mut params = uniform_params(
+            registers,
+            intrinsics.i64_ty.get_undef().into(),
+            intrinsics.i64x2_ty.get_undef().into(),
+        );
+        for ((&value, &ty), slot) in values.iter().zip(sig.params()).zip(slots) {
+            let bits = match ty {
+                Type::I32 | Type::I64 => value.into_int_value(),
+                Type::F32 => builder
+                    .build_bitcast(value, intrinsics.i32_ty, "")
+                    .into_int_value(),
+                Type::F64 => builder
+                    .build_bitcast(value, intrinsics.i64_ty, "")
+                    .into_int_value(),
+                Type::ExternRef | Type::FuncRef => {
+                    builder.build_ptr_to_int(value.into_pointer_value(), intrinsics.i64_ty, "")
+                }
+                Type::V128 => unreachable!("v128 parameters are never passed uniformly"),
+            };
+            let bits = builder.build_int_z_extend_or_bit_cast(bits, intrinsics.i64_ty, "");
+            params[slot.index(registers) as usize] = match slot {
+                ParamSlot::Vector(_) => builder
+                    .build_insert_element(
+                        intrinsics.i64x2_ty.get_undef(),
+                        bits,
+                        intrinsics.i32_zero,
+                        "",
+                    )
+                    .into(),
+                ParamSlot::Int(_) | ParamSlot::Stack(_) => bits.into(),
+            };
+        }
+        params
+    }
+
+    /// Given a function definition of type `sig`, retrieve its wasm
+    /// parameters.
+    fn get_wasm_params<'ctx>(
+        &self,
+        builder: &Builder<'ctx>,
+        intrinsics: &Intrinsics<'ctx>,
+        func_value: &FunctionValue<'ctx>,
+        sig: &FuncSig,
+    ) -> Result<Vec<BasicValueEnum<'ctx>>, CompileError> {
+        let sret = self.is_sret(sig)?;
+        let first_param = if sret { 2 } else { 1 };
+        let registers = self.param_registers(sret);
+        let slots = match uniform_param_slots(registers, sig) {
+            Some(slots) => slots,
+            None => {
+                return Ok((0..sig.params().len() as u32)
+                    .map(|index| func_value.get_nth_param(first_param + index).unwrap())
+                    .collect())
+            }
+        };
+        slots
+            .into_iter()
+            .zip(sig.params())
+            .map(|(slot, &ty)| {
+                let param = func_value
+                    .get_nth_param(first_param + slot.index(registers))
+                    .unwrap();
+                let bits: IntValue = match slot {
+                    ParamSlot::Vector(_) => builder
+                        .build_extract_element(param.into_vector_value(), intrinsics.i32_zero, "")
+                        .into_int_value(),
+                    ParamSlot::Int(_) | ParamSlot::Stack(_) => param.into_int_value(),
+                };
+                Ok(match ty {
+                    Type::I32 => builder
+                        .build_int_truncate(bits, intrinsics.i32_ty, "")
+                        .into(),
+                    Type::I64 => bits.into(),
+                    Type::F32 => builder.build_bitcast(
+                        builder.build_int_truncate(bits, intrinsics.i32_ty, ""),
+                        intrinsics.f32_ty,
+                        "",
+                    ),
+                    Type::F64 => builder.build_bitcast(bits, intrinsics.f64_ty, ""),
+                    Type::ExternRef | Type::FuncRef => builder
+                        .build_int_to_ptr(
+                            bits,
+                            type_to_llvm(intrinsics, ty)?.into_pointer_type(),
+                            "",
+                        )
+                        .into(),
+                    Type::V128 => unreachable!("v128 parameters are never passed uniformly"),
+                })
+            })
+            .collect()
+    }
+
     /// Given a wasm function type, produce an llvm function declaration.
     fn func_type_to_llvm<'ctx>(
         &self,
@@ -55,9 +269,12 @@ pub trait Abi {
     ) -> Result<(FunctionType<'ctx>, Vec<(Attribute, AttributeLoc)>), CompileError>;
 
     /// Marshall wasm stack values into function parameters.
+    #[allow(clippy::too_many_arguments)]
     fn args_to_call<'ctx>(
         &self,
         alloca_builder: &Builder<'ctx>,
+        builder: &Builder<'ctx>,
+        intrinsics: &Intrinsics<'ctx>,
         func_sig: &FuncSig,
         ctx_ptr: PointerValue<'ctx>,
         llvm_fn_ty: &FunctionType<'ctx>,
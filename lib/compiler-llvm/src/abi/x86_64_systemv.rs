@@ -41,6 +41,12 @@ impl Abi for X86_64SystemV {
             .into_pointer_value()
     }
 
+    // rdi, rsi, rdx, rcx, r8 and r9, less the sret and vmctx pointers, and
+    // xmm0 to xmm7.
+    fn param_registers(&self, sret: bool) -> (u32, u32) {
+        (if sret { 4 } else { 5 }, 8)
+    }
+
     // Given a wasm function type, produce an llvm function declaration.
     fn func_type_to_llvm<'ctx>(
         &self,
@@ -49,10 +55,10 @@ impl Abi for X86_64SystemV {
         offsets: Option<&VMOffsets>,
         sig: &FuncSig,
     ) -> Result<(FunctionType<'ctx>, Vec<(Attribute, AttributeLoc)>), CompileError> {
-        let user_param_types = sig.params().iter().map(|&ty| type_to_llvm(intrinsics, ty));
-
-        let param_types =
-            std::iter::once(Ok(intrinsics.ctx_ptr_ty.as_basic_type_enum())).chain(user_param_types);
+        let param_types = self
+            .param_types_to_llvm(intrinsics, sig)?
+            .into_iter()
+            .map(Ok::<_, CompileError>);
 
         // TODO: figure out how many bytes long vmctx is, and mark it dereferenceable. (no need to mark it nonnull once we do this.)
         let vmctx_attributes = |i: u32| {
@@ -296,6 +302,8 @@ impl Abi for X86_64SystemV {
     fn args_to_call<'ctx>(
         &self,
         alloca_builder: &Builder<'ctx>,
+        builder: &Builder<'ctx>,
+        intrinsics: &Intrinsics<'ctx>,
         func_sig: &FuncSig,
         ctx_ptr: PointerValue<'ctx>,
         llvm_fn_ty: &FunctionType<'ctx>,
@@ -316,7 +324,13 @@ impl Abi for X86_64SystemV {
             None
         };
 
-        let values = std::iter::once(ctx_ptr.as_basic_value_enum()).chain(values.iter().copied());
+        let values = std::iter::once(ctx_ptr.as_basic_value_enum()).chain(self.values_to_params(
+            builder,
+            intrinsics,
+            func_sig,
+            sret.is_some(),
+            values,
+        ));
 
         if let Some(sret) = sret {
             std::iter::once(sret.as_basic_value_enum())
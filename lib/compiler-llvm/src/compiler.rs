@@ -9,10 +9,12 @@ use inkwell::targets::FileType;
 use inkwell::DLLStorageClass;
 use rayon::iter::ParallelBridge;
 use rayon::prelude::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use std::sync::Arc;
 use wasmer_compiler::{
     Architecture, Compilation, CompileError, CompileModuleInfo, Compiler, CustomSection,
-    CustomSectionProtection, Dwarf, FunctionBodyData, ModuleTranslationState, RelocationTarget,
-    SectionBody, SectionIndex, Symbol, SymbolRegistry, Target, TrampolinesSection,
+    CustomSectionProtection, FunctionBodyData, FunctionCompilation, ModuleMiddleware,
+    ModuleTranslationState, SectionBody, SectionIndex, Symbol, SymbolRegistry, Target,
+    TrampolinesSection,
 };
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{FunctionIndex, LocalFunctionIndex, SignatureIndex};
@@ -185,6 +187,23 @@ impl LLVMCompiler {
 }
 
 impl Compiler for LLVMCompiler {
+    fn name(&self) -> &str {
+        "llvm"
+    }
+
+    fn version(&self) -> &str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn epoch_interruption(&self) -> bool {
+        self.config.enable_epoch_interruption
+    }
+
+    /// Get the middlewares for this compiler
+    fn get_middlewares(&self) -> &[Arc<dyn ModuleMiddleware>] {
+        &self.config.middlewares
+    }
+
     fn experimental_native_compile_module<'data, 'module>(
         &self,
         target: &Target,
@@ -223,9 +242,6 @@ impl Compiler for LLVMCompiler {
 
         // TODO: merge constants in sections.
 
-        let mut module_custom_sections = PrimaryMap::new();
-        let mut frame_section_bytes = vec![];
-        let mut frame_section_relocations = vec![];
         let functions = function_body_inputs
             .iter()
             .collect::<Vec<(LocalFunctionIndex, &FunctionBodyData<'_>)>>()
@@ -238,62 +254,64 @@ impl Compiler for LLVMCompiler {
                 |func_translator, (i, input)| {
                     // TODO: remove (to serialize)
                     //let _data = data.lock().unwrap();
-                    func_translator.translate(
-                        module,
-                        module_translation,
-                        i,
-                        input,
-                        self.config(),
-                        memory_styles,
-                        &table_styles,
-                        &ShortNames {},
-                    )
+                    func_translator
+                        .translate(
+                            module,
+                            module_translation,
+                            i,
+                            input,
+                            self.config(),
+                            memory_styles,
+                            &table_styles,
+                            &ShortNames {},
+                        )
+                        .map(FunctionCompilation::from)
                 },
             )
             .collect::<Result<Vec<_>, CompileError>>()?
             .into_iter()
-            .map(|mut compiled_function| {
-                let first_section = module_custom_sections.len() as u32;
-                for (section_index, custom_section) in compiled_function.custom_sections.iter() {
-                    // TODO: remove this call to clone()
-                    let mut custom_section = custom_section.clone();
-                    for mut reloc in &mut custom_section.relocations {
-                        if let RelocationTarget::CustomSection(index) = reloc.reloc_target {
-                            reloc.reloc_target = RelocationTarget::CustomSection(
-                                SectionIndex::from_u32(first_section + index.as_u32()),
-                            )
-                        }
-                    }
-                    if compiled_function
-                        .eh_frame_section_indices
-                        .contains(&section_index)
-                    {
-                        let offset = frame_section_bytes.len() as u32;
-                        for mut reloc in &mut custom_section.relocations {
-                            reloc.offset += offset;
-                        }
-                        frame_section_bytes.extend_from_slice(custom_section.bytes.as_slice());
-                        frame_section_relocations.extend(custom_section.relocations);
-                        // TODO: we do this to keep the count right, remove it.
-                        module_custom_sections.push(CustomSection {
-                            protection: CustomSectionProtection::Read,
-                            bytes: SectionBody::new_with_vec(vec![]),
-                            relocations: vec![],
-                        });
-                    } else {
-                        module_custom_sections.push(custom_section);
-                    }
-                }
-                for mut reloc in &mut compiled_function.compiled_function.relocations {
-                    if let RelocationTarget::CustomSection(index) = reloc.reloc_target {
-                        reloc.reloc_target = RelocationTarget::CustomSection(
-                            SectionIndex::from_u32(first_section + index.as_u32()),
-                        )
-                    }
-                }
-                compiled_function.compiled_function
-            })
-            .collect::<PrimaryMap<LocalFunctionIndex, _>>();
+            .collect();
+        self.compile_module_with_functions(target, compile_info, functions)
+    }
+
+    fn supports_function_compilation(&self) -> bool {
+        true
+    }
+
+    fn compile_function(
+        &self,
+        target: &Target,
+        compile_info: &CompileModuleInfo,
+        module_translation: &ModuleTranslationState,
+        index: LocalFunctionIndex,
+        function_body: &FunctionBodyData<'_>,
+    ) -> Result<FunctionCompilation, CompileError> {
+        let target_machine = self.config().target_machine(target);
+        FuncTranslator::new(target_machine)
+            .translate(
+                &compile_info.module,
+                module_translation,
+                &index,
+                function_body,
+                self.config(),
+                &compile_info.memory_styles,
+                &compile_info.table_styles,
+                &ShortNames {},
+            )
+            .map(FunctionCompilation::from)
+    }
+
+    fn compile_module_with_functions(
+        &self,
+        target: &Target,
+        compile_info: &CompileModuleInfo,
+        functions: PrimaryMap<LocalFunctionIndex, FunctionCompilation>,
+    ) -> Result<Compilation, CompileError> {
+        let module = &compile_info.module;
+
+        // The relocations of each function refer to its own sections.
+        let mut module_custom_sections = PrimaryMap::new();
+        let (functions, dwarf) = FunctionCompilation::merge(functions, &mut module_custom_sections);
 
         let trampolines = match target.triple().architecture {
             Architecture::Aarch64(_) => {
@@ -326,31 +344,6 @@ impl Compiler for LLVMCompiler {
             _ => None,
         };
 
-        let dwarf = if !frame_section_bytes.is_empty() {
-            let dwarf = Some(Dwarf::new(SectionIndex::from_u32(
-                module_custom_sections.len() as u32,
-            )));
-            // Terminating zero-length CIE.
-            frame_section_bytes.extend(vec![
-                0x00, 0x00, 0x00, 0x00, // Length
-                0x00, 0x00, 0x00, 0x00, // CIE ID
-                0x10, // Version (must be 1)
-                0x00, // Augmentation data
-                0x00, // Code alignment factor
-                0x00, // Data alignment factor
-                0x00, // Return address register
-                0x00, 0x00, 0x00, // Padding to a multiple of 4 bytes
-            ]);
-            module_custom_sections.push(CustomSection {
-                protection: CustomSectionProtection::Read,
-                bytes: SectionBody::new_with_vec(frame_section_bytes),
-                relocations: frame_section_relocations,
-            });
-            dwarf
-        } else {
-            None
-        };
-
         let function_call_trampolines = module
             .signatures
             .values()
@@ -8,7 +8,7 @@ use itertools::Itertools;
 use std::fmt::Debug;
 use std::sync::Arc;
 use target_lexicon::Architecture;
-use wasmer_compiler::{Compiler, CompilerConfig, Target, Triple};
+use wasmer_compiler::{Compiler, CompilerConfig, ModuleMiddleware, Target, Triple};
 use wasmer_types::{FunctionType, LocalFunctionIndex};
 
 /// The InkWell ModuleInfo type
@@ -42,8 +42,11 @@ pub struct LLVM {
     pub(crate) enable_nan_canonicalization: bool,
     pub(crate) enable_verifier: bool,
     pub(crate) opt_level: LLVMOptLevel,
+    pub(crate) enable_epoch_interruption: bool,
     is_pic: bool,
     pub(crate) callbacks: Option<Arc<dyn LLVMCallbacks>>,
+    /// The middleware chain.
+    pub(crate) middlewares: Vec<Arc<dyn ModuleMiddleware>>,
 }
 
 impl LLVM {
@@ -54,8 +57,10 @@ impl LLVM {
             enable_nan_canonicalization: false,
             enable_verifier: false,
             opt_level: LLVMOptLevel::Aggressive,
+            enable_epoch_interruption: false,
             is_pic: false,
             callbacks: None,
+            middlewares: vec![],
         }
     }
 
@@ -65,6 +70,15 @@ impl LLVM {
         self
     }
 
+    /// Enable epoch interruption.
+    ///
+    /// When enabled, the compiled code traps with `TrapCode::Interrupted`
+    /// once the epoch of the engine reaches the deadline of its instance.
+    pub fn epoch_interruption(&mut self, enable: bool) -> &mut Self {
+        self.enable_epoch_interruption = enable;
+        self
+    }
+
     /// Callbacks that will triggered in the different compilation
     /// phases in LLVM.
     pub fn callbacks(&mut self, callbacks: Option<Arc<dyn LLVMCallbacks>>) -> &mut Self {
@@ -207,10 +221,19 @@ impl CompilerConfig for LLVM {
         self.enable_nan_canonicalization = enable;
     }
 
+    fn epoch_interruption(&mut self, enable: bool) {
+        self.enable_epoch_interruption = enable;
+    }
+
     /// Transform it into the compiler.
     fn compiler(self: Box<Self>) -> Box<dyn Compiler> {
         Box::new(LLVMCompiler::new(*self))
     }
+
+    /// Pushes a middleware onto the back of the middleware chain.
+    fn push_middleware(&mut self, middleware: Arc<dyn ModuleMiddleware>) {
+        self.middlewares.push(middleware);
+    }
 }
 
 impl Default for LLVM {
@@ -6,8 +6,8 @@ use std::num::TryFromIntError;
 
 use wasmer_compiler::{
     CompileError, CompiledFunctionFrameInfo, CustomSection, CustomSectionProtection,
-    CustomSections, FunctionAddressMap, FunctionBody, InstructionAddressMap, Relocation,
-    RelocationKind, RelocationTarget, SectionBody, SectionIndex, SourceLoc,
+    CustomSections, FunctionAddressMap, FunctionBody, FunctionCompilation, InstructionAddressMap,
+    Relocation, RelocationKind, RelocationTarget, SectionBody, SectionIndex, SourceLoc,
 };
 use wasmer_types::entity::{PrimaryMap, SecondaryMap};
 use wasmer_vm::libcalls::LibCall;
@@ -26,6 +26,16 @@ pub struct CompiledFunction {
     pub eh_frame_section_indices: Vec<SectionIndex>,
 }
 
+impl From<CompiledFunction> for FunctionCompilation {
+    fn from(function: CompiledFunction) -> Self {
+        Self {
+            function: function.compiled_function,
+            custom_sections: function.custom_sections,
+            eh_frame_sections: function.eh_frame_section_indices,
+        }
+    }
+}
+
 pub fn load_object_file<F>(
     contents: &[u8],
     root_section: &str,
@@ -58,6 +68,11 @@ where
         "wasmer_vm_imported_memory32_size".to_string(),
         LibCall::ImportedMemory32Size,
     );
+    libcalls.insert("wasmer_vm_memory64_size".to_string(), LibCall::Memory64Size);
+    libcalls.insert(
+        "wasmer_vm_imported_memory64_size".to_string(),
+        LibCall::ImportedMemory64Size,
+    );
     libcalls.insert("wasmer_vm_table_copy".to_string(), LibCall::TableCopy);
     libcalls.insert("wasmer_vm_table_init".to_string(), LibCall::TableInit);
     libcalls.insert("wasmer_vm_table_fill".to_string(), LibCall::TableFill);
@@ -94,7 +109,34 @@ where
         LibCall::ImportedMemory32Fill,
     );
     libcalls.insert("wasmer_vm_memory32_init".to_string(), LibCall::Memory32Init);
+    libcalls.insert("wasmer_vm_memory64_copy".to_string(), LibCall::Memory64Copy);
+    libcalls.insert(
+        "wasmer_vm_imported_memory64_copy".to_string(),
+        LibCall::ImportedMemory64Copy,
+    );
+    libcalls.insert("wasmer_vm_memory64_fill".to_string(), LibCall::Memory64Fill);
+    libcalls.insert(
+        "wasmer_vm_imported_memory64_fill".to_string(),
+        LibCall::ImportedMemory64Fill,
+    );
+    libcalls.insert("wasmer_vm_memory64_init".to_string(), LibCall::Memory64Init);
+    libcalls.insert(
+        "wasmer_vm_memory_copy_between".to_string(),
+        LibCall::MemoryCopyBetween,
+    );
     libcalls.insert("wasmer_vm_data_drop".to_string(), LibCall::DataDrop);
+    libcalls.insert(
+        "wasmer_vm_memory32_atomic_wait32".to_string(),
+        LibCall::MemoryAtomicWait32,
+    );
+    libcalls.insert(
+        "wasmer_vm_memory32_atomic_wait64".to_string(),
+        LibCall::MemoryAtomicWait64,
+    );
+    libcalls.insert(
+        "wasmer_vm_memory32_atomic_notify".to_string(),
+        LibCall::MemoryAtomicNotify,
+    );
     libcalls.insert("wasmer_vm_raise_trap".to_string(), LibCall::RaiseTrap);
     libcalls.insert("wasmer_vm_probestack".to_string(), LibCall::Probestack);
 
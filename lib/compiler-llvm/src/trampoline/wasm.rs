@@ -319,22 +319,7 @@ impl FuncTrampoline {
                 }
             };
 
-        let mut args_vec: Vec<BasicMetadataValueEnum> =
-            Vec::with_capacity(func_sig.params().len() + 1);
-
-        if self.abi.is_sret(func_sig)? {
-            let basic_types: Vec<_> = func_sig
-                .results()
-                .iter()
-                .map(|&ty| type_to_llvm(intrinsics, ty))
-                .collect::<Result<_, _>>()?;
-
-            let sret_ty = context.struct_type(&basic_types, false);
-            args_vec.push(builder.build_alloca(sret_ty, "sret").into());
-        }
-
-        args_vec.push(callee_vmctx_ptr.into());
-
+        let mut values = Vec::with_capacity(func_sig.params().len());
         for (i, param_ty) in func_sig.params().iter().enumerate() {
             let index = intrinsics.i32_ty.const_int(i as _, false);
             let item_pointer =
@@ -346,9 +331,25 @@ impl FuncTrampoline {
                 builder.build_pointer_cast(item_pointer, casted_pointer_type, "typed_arg_pointer");
 
             let arg = builder.build_load(typed_item_pointer, "arg");
-            args_vec.push(arg.into());
+            values.push(arg);
         }
 
+        let callee_ty = func_ptr.get_type().get_element_type().into_function_type();
+        let args_vec = self
+            .abi
+            .args_to_call(
+                &builder,
+                &builder,
+                intrinsics,
+                func_sig,
+                callee_vmctx_ptr.into_pointer_value(),
+                &callee_ty,
+                &values,
+            )
+            .into_iter()
+            .map(Into::into)
+            .collect::<Vec<BasicMetadataValueEnum>>();
+
         let callable_func = inkwell::values::CallableValue::try_from(func_ptr).unwrap();
         let call_site = builder.build_call(callable_func, args_vec.as_slice().into(), "call");
         for (attr, attr_loc) in func_attrs {
@@ -401,8 +402,10 @@ impl FuncTrampoline {
         );
 
         // Copy params to 'values'.
-        let first_user_param = if self.abi.is_sret(func_sig)? { 2 } else { 1 };
-        for i in 0..func_sig.params().len() {
+        let params = self
+            .abi
+            .get_wasm_params(&builder, intrinsics, &trampoline_func, func_sig)?;
+        for (i, param) in params.into_iter().enumerate() {
             let ptr = unsafe {
                 builder.build_in_bounds_gep(
                     values,
@@ -416,12 +419,7 @@ impl FuncTrampoline {
             let ptr = builder
                 .build_bitcast(ptr, type_to_llvm_ptr(intrinsics, func_sig.params()[i])?, "")
                 .into_pointer_value();
-            builder.build_store(
-                ptr,
-                trampoline_func
-                    .get_nth_param(i as u32 + first_user_param)
-                    .unwrap(),
-            );
+            builder.build_store(ptr, param);
         }
 
         let callee_ty = intrinsics
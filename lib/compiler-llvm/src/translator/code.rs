@@ -7,6 +7,7 @@ use super::{
 };
 use inkwell::{
     attributes::AttributeLoc,
+    basic_block::BasicBlock,
     builder::Builder,
     context::Context,
     module::{Linkage, Module},
@@ -14,28 +15,35 @@ use inkwell::{
     targets::{FileType, TargetMachine},
     types::{BasicType, FloatMathType, IntType, PointerType, VectorType},
     values::{
-        BasicMetadataValueEnum, BasicValue, BasicValueEnum, FloatValue, FunctionValue,
-        InstructionOpcode, InstructionValue, IntValue, PhiValue, PointerValue, VectorValue,
+        AsValueRef, BasicMetadataValueEnum, BasicValue, BasicValueEnum, CallSiteValue, FloatValue,
+        FunctionValue, InstructionOpcode, InstructionValue, IntValue, PhiValue, PointerValue,
+        VectorValue,
     },
     AddressSpace, AtomicOrdering, AtomicRMWBinOp, DLLStorageClass, FloatPredicate, IntPredicate,
 };
+use llvm_sys::prelude::LLVMValueRef;
 use smallvec::SmallVec;
 
-use crate::abi::{get_abi, Abi};
+use crate::abi::{get_abi, Abi, RESERVED_STACK_ARGS};
 use crate::config::{CompiledKind, LLVM};
 use crate::object_file::{load_object_file, CompiledFunction};
 use std::convert::TryFrom;
 use wasmer_compiler::wasmparser::{MemoryImmediate, Operator};
 use wasmer_compiler::{
-    wptype_to_type, CompileError, FunctionBodyData, ModuleTranslationState, RelocationTarget,
-    Symbol, SymbolRegistry,
+    wptype_to_type, CompileError, FunctionBodyData, MiddlewareOperatorsReader,
+    ModuleMiddlewareChain, ModuleTranslationState, RelocationTarget, Symbol, SymbolRegistry,
 };
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::{
     FunctionIndex, FunctionType, GlobalIndex, LocalFunctionIndex, MemoryIndex, ModuleInfo,
-    SignatureIndex, TableIndex, Type,
+    SignatureIndex, TableIndex, TagIndex, Type,
 };
-use wasmer_vm::{MemoryStyle, TableStyle, VMOffsets};
+use wasmer_vm::{MemoryStyle, TableStyle, VMBuiltinFunctionIndex, VMOffsets};
+
+extern "C" {
+    /// Marks a call `musttail`, which the C API of LLVM cannot do.
+    fn wasmer_llvm_set_must_tail_call(call: LLVMValueRef);
+}
 
 const FUNCTION_SECTION: &str = "__TEXT,wasmer_function";
 
@@ -137,12 +145,6 @@ impl FuncTranslator {
             wasmer_compiler::FunctionReader::new(function_body.module_offset, function_body.data);
 
         let mut params = vec![];
-        let first_param =
-            if func_type.get_return_type().is_none() && wasm_fn_type.results().len() > 1 {
-                2
-            } else {
-                1
-            };
         let mut is_first_alloca = true;
         let mut insert_alloca = |ty, name| {
             let alloca = alloca_builder.build_alloca(ty, name);
@@ -153,12 +155,11 @@ impl FuncTranslator {
             alloca
         };
 
-        for idx in 0..wasm_fn_type.params().len() {
-            let ty = wasm_fn_type.params()[idx];
+        let param_values =
+            self.abi
+                .get_wasm_params(&cache_builder, &intrinsics, &func, wasm_fn_type)?;
+        for (&ty, value) in wasm_fn_type.params().iter().zip(param_values) {
             let ty = type_to_llvm(&intrinsics, ty)?;
-            let value = func
-                .get_nth_param((idx as u32).checked_add(first_param).unwrap())
-                .unwrap();
             let alloca = insert_alloca(ty, "param");
             cache_builder.build_store(alloca, value);
             params.push(alloca);
@@ -178,6 +179,13 @@ impl FuncTranslator {
             }
         }
 
+        // The exceptions caught by the activation, allocated by the first catch clause.
+        let caught_exceptions = insert_alloca(
+            intrinsics.i8_ptr_ty.as_basic_type_enum(),
+            "caught_exceptions",
+        );
+        cache_builder.build_store(caught_exceptions, intrinsics.i8_ptr_ty.const_null());
+
         let mut params_locals = params.clone();
         params_locals.extend(locals.iter().cloned());
 
@@ -189,8 +197,11 @@ impl FuncTranslator {
             state,
             function: func,
             locals: params_locals,
+            caught_exceptions,
+            exception_exit: None,
             ctx: CtxType::new(wasm_module, &func, &cache_builder, &*self.abi),
             unreachable_depth: 0,
+            wasm_fn_type,
             memory_styles,
             _table_styles,
             module: &module,
@@ -207,9 +218,17 @@ impl FuncTranslator {
             &func_attrs,
         );
 
-        let mut operator_reader = reader.get_operators_reader()?.into_iter_with_offsets();
+        let mut operator_reader = MiddlewareOperatorsReader::new(
+            reader.get_operators_reader()?,
+            config
+                .middlewares
+                .generate_function_middleware_chain(*local_func_index),
+        );
+        if config.enable_epoch_interruption {
+            fcg.translate_epoch_check();
+        }
         while fcg.state.has_control_frames() {
-            let (op, pos) = operator_reader.next().unwrap()?;
+            let (op, pos) = operator_reader.expect_operator()?;
             fcg.translate_operator(op, pos as u32)?;
         }
 
@@ -1039,6 +1058,103 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
         Ok(())
     }
 
+    /// Computes the 64-bit address of an atomic wait or notify, whose bounds
+    /// and alignment are checked by the runtime.
+    fn effective_atomic_address(
+        &self,
+        memarg: &MemoryImmediate,
+        var_offset: IntValue<'ctx>,
+    ) -> IntValue<'ctx> {
+        self.effective_address(MemoryIndex::from_u32(memarg.memory), memarg, var_offset)
+    }
+
+    /// Whether the memory is indexed with 64-bit addresses.
+    fn memory64(&self, memory_index: MemoryIndex) -> bool {
+        self.wasm_module.memories[memory_index].memory64
+    }
+
+    /// The index identifying the memory to the memory builtins, which take
+    /// local indices for locally defined memories.
+    fn memory_builtin_index(&self, memory_index: MemoryIndex) -> u32 {
+        match self.wasm_module.local_memory_index(memory_index) {
+            Some(local_memory_index) => local_memory_index.as_u32(),
+            None => memory_index.as_u32(),
+        }
+    }
+
+    /// Adds the static offset of `memarg` to the address `var_offset`, as a
+    /// 64-bit integer.
+    ///
+    /// For memories indexed with 64-bit addresses, the addition traps
+    /// instead of wrapping around.
+    fn effective_address(
+        &self,
+        memory_index: MemoryIndex,
+        memarg: &MemoryImmediate,
+        var_offset: IntValue<'ctx>,
+    ) -> IntValue<'ctx> {
+        let imm_offset = self
+            .intrinsics
+            .i64_ty
+            .const_int(memarg.offset as u64, false);
+        if !self.memory64(memory_index) {
+            let var_offset =
+                self.builder
+                    .build_int_z_extend(var_offset, self.intrinsics.i64_ty, "");
+            return self.builder.build_int_add(var_offset, imm_offset, "");
+        }
+        let offset = self.builder.build_int_add(var_offset, imm_offset, "");
+        if memarg.offset != 0 {
+            let no_overflow =
+                self.builder
+                    .build_int_compare(IntPredicate::UGE, offset, var_offset, "");
+            self.trap_memory_oob_unless(no_overflow);
+        }
+        offset
+    }
+
+    /// Emits a runtime check trapping with an out-of-bounds memory access
+    /// unless `in_bounds` is true.
+    fn trap_memory_oob_unless(&self, in_bounds: IntValue<'ctx>) {
+        if in_bounds.is_constant_int() && in_bounds.get_zero_extended_constant() == Some(1) {
+            return;
+        }
+        let in_bounds = self
+            .builder
+            .build_call(
+                self.intrinsics.expect_i1,
+                &[
+                    in_bounds.into(),
+                    self.intrinsics.i1_ty.const_int(1, true).into(),
+                ],
+                "ptr_in_bounds_expect",
+            )
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value();
+
+        let in_bounds_continue_block = self
+            .context
+            .append_basic_block(self.function, "in_bounds_continue_block");
+        let not_in_bounds_block = self
+            .context
+            .append_basic_block(self.function, "not_in_bounds_block");
+        self.builder.build_conditional_branch(
+            in_bounds,
+            in_bounds_continue_block,
+            not_in_bounds_block,
+        );
+        self.builder.position_at_end(not_in_bounds_block);
+        self.builder.build_call(
+            self.intrinsics.throw_trap,
+            &[self.intrinsics.trap_memory_oob.into()],
+            "throw",
+        );
+        self.builder.build_unreachable();
+        self.builder.position_at_end(in_bounds_continue_block);
+    }
+
     fn resolve_memory_ptr(
         &mut self,
         memory_index: MemoryIndex,
@@ -1047,15 +1163,23 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
         var_offset: IntValue<'ctx>,
         value_size: usize,
     ) -> Result<PointerValue<'ctx>, CompileError> {
+        // Compute the offset into the storage.
+        let offset = self.effective_address(memory_index, memarg, var_offset);
+        if self.memory64(memory_index) {
+            // Make sure the end of the access doesn't wrap around either.
+            let no_overflow = self.builder.build_int_compare(
+                IntPredicate::ULE,
+                offset,
+                self.intrinsics
+                    .i64_ty
+                    .const_int(u64::MAX - value_size as u64, false),
+                "",
+            );
+            self.trap_memory_oob_unless(no_overflow);
+        }
+
         let builder = &self.builder;
         let intrinsics = &self.intrinsics;
-        let context = &self.context;
-        let function = &self.function;
-
-        // Compute the offset into the storage.
-        let imm_offset = intrinsics.i64_ty.const_int(memarg.offset as u64, false);
-        let var_offset = builder.build_int_z_extend(var_offset, intrinsics.i64_ty, "");
-        let offset = builder.build_int_add(var_offset, imm_offset, "");
 
         // Look up the memory base (as pointer) and bounds (as unsigned integer).
         let base_ptr =
@@ -1108,46 +1232,12 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                             "",
                         )
                     });
-                    if !ptr_in_bounds.is_constant_int()
-                        || ptr_in_bounds.get_zero_extended_constant().unwrap() != 1
-                    {
-                        // LLVM may have folded this into 'i1 true' in which case we know
-                        // the pointer is in bounds. LLVM may also have folded it into a
-                        // constant expression, not known to be either true or false yet.
-                        // If it's false, unknown-but-constant, or not-a-constant, emit a
-                        // runtime bounds check. LLVM may yet succeed at optimizing it away.
-                        let ptr_in_bounds = builder
-                            .build_call(
-                                intrinsics.expect_i1,
-                                &[
-                                    ptr_in_bounds.into(),
-                                    intrinsics.i1_ty.const_int(1, true).into(),
-                                ],
-                                "ptr_in_bounds_expect",
-                            )
-                            .try_as_basic_value()
-                            .left()
-                            .unwrap()
-                            .into_int_value();
-
-                        let in_bounds_continue_block =
-                            context.append_basic_block(*function, "in_bounds_continue_block");
-                        let not_in_bounds_block =
-                            context.append_basic_block(*function, "not_in_bounds_block");
-                        builder.build_conditional_branch(
-                            ptr_in_bounds,
-                            in_bounds_continue_block,
-                            not_in_bounds_block,
-                        );
-                        builder.position_at_end(not_in_bounds_block);
-                        builder.build_call(
-                            intrinsics.throw_trap,
-                            &[intrinsics.trap_memory_oob.into()],
-                            "throw",
-                        );
-                        builder.build_unreachable();
-                        builder.position_at_end(in_bounds_continue_block);
-                    }
+                    // LLVM may have folded this into 'i1 true' in which case we know
+                    // the pointer is in bounds. LLVM may also have folded it into a
+                    // constant expression, not known to be either true or false yet.
+                    // If it's false, unknown-but-constant, or not-a-constant, emit a
+                    // runtime bounds check. LLVM may yet succeed at optimizing it away.
+                    self.trap_memory_oob_unless(ptr_in_bounds);
                     let ptr_to_base = builder.build_load(ptr_to_base_ptr, "").into_pointer_value();
                     tbaa_label(
                         self.module,
@@ -1157,7 +1247,29 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     );
                     ptr_to_base
                 }
-                MemoryCache::Static { base_ptr } => base_ptr,
+                MemoryCache::Static { base_ptr } => {
+                    if self.memory64(memory_index) {
+                        // The guard pages can't cover 64-bit addresses, check
+                        // them against the bound of the memory instead.
+                        let bound = match self.memory_styles[memory_index] {
+                            MemoryStyle::Static { bound, .. } => bound.bytes().0 as u64,
+                            MemoryStyle::Dynamic { .. } => unreachable!(),
+                        };
+                        let load_offset_end = builder.build_int_add(
+                            offset,
+                            intrinsics.i64_ty.const_int(value_size as u64, false),
+                            "",
+                        );
+                        let ptr_in_bounds = builder.build_int_compare(
+                            IntPredicate::ULE,
+                            load_offset_end,
+                            intrinsics.i64_ty.const_int(bound, false),
+                            "",
+                        );
+                        self.trap_memory_oob_unless(ptr_in_bounds);
+                    }
+                    base_ptr
+                }
             };
         let value_ptr = unsafe { builder.build_gep(base_ptr, &[offset], "") };
         Ok(builder
@@ -1165,6 +1277,149 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             .into_pointer_value())
     }
 
+    /// Returns the number of opcodes to charge inline if a call to `func_index` is a call to the
+    /// `gas` intrinsic.
+    ///
+    /// Only calls passing a constant in `0..i32::MAX` to an imported `gas` function of type
+    /// `[i32] -> []` are intrinsified, the same as in singlepass; any other call to it is left as
+    /// an ordinary call to the host.
+    fn gas_intrinsic_count(&self, func_index: FunctionIndex) -> Option<u32> {
+        if self.module_translation.import_map.get(&func_index)? != "gas" {
+            return None;
+        }
+        let func_type = &self.wasm_module.signatures[self.wasm_module.functions[func_index]];
+        if func_type.params() != [Type::I32] || !func_type.results().is_empty() {
+            return None;
+        }
+        let (arg, _) = self.state.peek1_extra().ok()?;
+        let count = arg.into_int_value().get_zero_extended_constant()?;
+        if count < i32::MAX as u64 {
+            Some(count as u32)
+        } else {
+            None
+        }
+    }
+
+    /// Charges `count` opcodes against the instance's gas counter.
+    ///
+    /// This emits the same sequence as singlepass: the new burnt gas is checked for signed
+    /// overflow, written back unconditionally, and then compared with the gas limit.
+    fn translate_gas_intrinsic(&mut self, count: u32) {
+        let offsets = self.ctx.get_offsets();
+        let burnt_gas_offset = offsets.fast_gas_counter_burnt_gas();
+        let gas_limit_offset = offsets.fast_gas_counter_gas_limit();
+        let opcode_cost_offset = offsets.fast_gas_counter_opcode_cost();
+        let gas_counter = self.ctx.gas_counter(self.intrinsics);
+        let field_ptr = |offset: u8, name: &str| {
+            let offset = self.intrinsics.i32_ty.const_int(offset.into(), false);
+            let ptr = unsafe { self.builder.build_gep(gas_counter, &[offset], "") };
+            self.builder
+                .build_bitcast(ptr, self.intrinsics.i64_ptr_ty, name)
+                .into_pointer_value()
+        };
+        let burnt_gas_ptr = field_ptr(burnt_gas_offset, "burnt_gas_ptr");
+        let gas_limit_ptr = field_ptr(gas_limit_offset, "gas_limit_ptr");
+        let opcode_cost_ptr = field_ptr(opcode_cost_offset, "opcode_cost_ptr");
+
+        let burnt_gas = self
+            .builder
+            .build_load(burnt_gas_ptr, "burnt_gas")
+            .into_int_value();
+        let opcode_cost = self
+            .builder
+            .build_load(opcode_cost_ptr, "opcode_cost")
+            .into_int_value();
+        let cost = self.builder.build_int_mul(
+            opcode_cost,
+            self.intrinsics.i64_ty.const_int(count.into(), false),
+            "gas_cost",
+        );
+        let new_burnt_gas = self.builder.build_int_add(burnt_gas, cost, "new_burnt_gas");
+
+        // Signed overflow happened iff both operands have a sign different from the result.
+        let overflow = self.builder.build_and(
+            self.builder.build_xor(burnt_gas, new_burnt_gas, ""),
+            self.builder.build_xor(cost, new_burnt_gas, ""),
+            "",
+        );
+        let overflow = self.builder.build_int_compare(
+            IntPredicate::SLT,
+            overflow,
+            self.intrinsics.i64_zero,
+            "gas_overflow",
+        );
+        self.trap_if_expected_false(overflow, self.intrinsics.trap_illegal_arithmetic);
+
+        // Write the new burnt gas unconditionally, so that the runtime can sort out the limits
+        // case.
+        self.builder.build_store(burnt_gas_ptr, new_burnt_gas);
+        let gas_limit = self
+            .builder
+            .build_load(gas_limit_ptr, "gas_limit")
+            .into_int_value();
+        let exceeded = self.builder.build_int_compare(
+            IntPredicate::UGE,
+            new_burnt_gas,
+            gas_limit,
+            "gas_exceeded",
+        );
+        self.trap_if_expected_false(exceeded, self.intrinsics.trap_gas_exceeded);
+    }
+
+    /// Traps if the epoch of the engine has reached the deadline of the instance.
+    ///
+    /// The epoch is loaded as volatile, so that the check isn't hoisted out of loops.
+    fn translate_epoch_check(&mut self) {
+        let epoch_ptr = self.ctx.epoch(self.intrinsics);
+        let deadline_ptr = self.ctx.epoch_deadline(self.intrinsics);
+        let epoch = self.builder.build_load(epoch_ptr, "epoch");
+        epoch
+            .as_instruction_value()
+            .unwrap()
+            .set_volatile(true)
+            .unwrap();
+        let deadline = self
+            .builder
+            .build_load(deadline_ptr, "epoch_deadline")
+            .into_int_value();
+        let expired = self.builder.build_int_compare(
+            IntPredicate::UGE,
+            epoch.into_int_value(),
+            deadline,
+            "epoch_expired",
+        );
+        self.trap_if_expected_false(expired, self.intrinsics.trap_interrupted);
+    }
+
+    /// Raises `trap_code` if `condition` holds, hinting that it doesn't.
+    fn trap_if_expected_false(&self, condition: IntValue<'ctx>, trap_code: BasicValueEnum<'ctx>) {
+        let condition = self
+            .builder
+            .build_call(
+                self.intrinsics.expect_i1,
+                &[condition.into(), self.intrinsics.i1_ty.const_zero().into()],
+                "",
+            )
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value();
+
+        let continue_block = self
+            .context
+            .append_basic_block(self.function, "no_trap_block");
+        let trap_block = self.context.append_basic_block(self.function, "trap_block");
+        self.builder
+            .build_conditional_branch(condition, trap_block, continue_block);
+
+        self.builder.position_at_end(trap_block);
+        self.builder
+            .build_call(self.intrinsics.throw_trap, &[trap_code.into()], "throw");
+        self.builder.build_unreachable();
+
+        self.builder.position_at_end(continue_block);
+    }
+
     fn trap_if_misaligned(&self, memarg: &MemoryImmediate, ptr: PointerValue<'ctx>) {
         let align = memarg.align;
         let value = self
@@ -1213,8 +1468,363 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
         self.builder.position_at_end(continue_block);
     }
 
+    /// Calls the builtin function `index`, of type `ptr_ty`, with the vmctx
+    /// followed by `args`.
+    fn call_builtin(
+        &mut self,
+        index: VMBuiltinFunctionIndex,
+        ptr_ty: PointerType<'ctx>,
+        args: &[BasicMetadataValueEnum<'ctx>],
+    ) -> CallSiteValue<'ctx> {
+        let func_ptr = self.ctx.builtin(index, ptr_ty, self.intrinsics);
+        let callable_func = inkwell::values::CallableValue::try_from(func_ptr).unwrap();
+        let mut call_args: Vec<BasicMetadataValueEnum> = vec![self.ctx.basic().into()];
+        call_args.extend_from_slice(args);
+        self.builder.build_call(callable_func, &call_args, "")
+    }
+
+    /// Where the exceptions propagating out of the `depth` outermost control
+    /// frames branch to: the catch clauses of the innermost `try` body among
+    /// them, or else the exit of the function, which returns with the
+    /// exception still pending for the caller to propagate.
+    fn exception_target(&mut self, depth: usize) -> Result<BasicBlock<'ctx>, CompileError> {
+        let landing = self.state.control_frames()[..depth]
+            .iter()
+            .rev()
+            .find_map(|frame| match *frame {
+                ControlFrame::Try {
+                    landing,
+                    catching: false,
+                    ..
+                } => Some(landing),
+                _ => None,
+            });
+        if let Some(landing) = landing.or(self.exception_exit) {
+            return Ok(landing);
+        }
+
+        // The returned values are never read.
+        let current_block = self
+            .builder
+            .get_insert_block()
+            .ok_or_else(|| CompileError::Codegen("not currently in a block".to_string()))?;
+        let exit = self
+            .context
+            .append_basic_block(self.function, "exception_exit");
+        let frame = self.state.outermost_frame()?;
+        for phi in frame.phis() {
+            let zero = phi.as_basic_value().get_type().const_zero();
+            phi.add_incoming(&[(&zero, exit)]);
+        }
+        self.builder.position_at_end(exit);
+        self.builder.build_unconditional_branch(*frame.br_dest());
+        self.builder.position_at_end(current_block);
+        self.exception_exit = Some(exit);
+        Ok(exit)
+    }
+
+    /// Propagates the exception thrown by the function just called, if any.
+    ///
+    /// The runtime is only asked whether the callee threw when some thread
+    /// is propagating an exception.
+    fn translate_exception_check(&mut self) -> Result<(), CompileError> {
+        let pending_ptr = self.ctx.pending_exceptions(self.intrinsics);
+        let pending = self
+            .builder
+            .build_load(pending_ptr, "pending_exceptions")
+            .into_int_value();
+        let pending =
+            self.builder
+                .build_int_compare(IntPredicate::NE, pending, self.intrinsics.i32_zero, "");
+        let pending = self
+            .builder
+            .build_call(
+                self.intrinsics.expect_i1,
+                &[pending.into(), self.intrinsics.i1_ty.const_zero().into()],
+                "",
+            )
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value();
+
+        let pending_block = self
+            .context
+            .append_basic_block(self.function, "exception_pending_block");
+        let continue_block = self
+            .context
+            .append_basic_block(self.function, "no_exception_block");
+        self.builder
+            .build_conditional_branch(pending, pending_block, continue_block);
+
+        self.builder.position_at_end(pending_block);
+        let thrown = self
+            .call_builtin(
+                VMBuiltinFunctionIndex::get_exception_pending_index(),
+                self.intrinsics.exception_pending_ptr_ty,
+                &[],
+            )
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value();
+        let thrown =
+            self.builder
+                .build_int_compare(IntPredicate::NE, thrown, self.intrinsics.i32_zero, "");
+        let target = self.exception_target(self.state.control_frames().len())?;
+        self.builder
+            .build_conditional_branch(thrown, target, continue_block);
+
+        self.builder.position_at_end(continue_block);
+        Ok(())
+    }
+
+    /// Allocates the exceptions caught by the activation unless it already
+    /// has them, and returns them.
+    fn translate_caught_exceptions(&mut self) -> PointerValue<'ctx> {
+        let caught = self
+            .builder
+            .build_load(self.caught_exceptions, "caught_exceptions")
+            .into_pointer_value();
+        let is_null = self.builder.build_is_null(caught, "");
+        let new_block = self
+            .context
+            .append_basic_block(self.function, "caught_exceptions_new_block");
+        let done_block = self
+            .context
+            .append_basic_block(self.function, "caught_exceptions_block");
+        self.builder
+            .build_conditional_branch(is_null, new_block, done_block);
+
+        self.builder.position_at_end(new_block);
+        let new_caught = self
+            .call_builtin(
+                VMBuiltinFunctionIndex::get_exception_caught_new_index(),
+                self.intrinsics.exception_caught_new_ptr_ty,
+                &[],
+            )
+            .try_as_basic_value()
+            .left()
+            .unwrap();
+        self.builder.build_store(self.caught_exceptions, new_caught);
+        self.builder.build_unconditional_branch(done_block);
+
+        self.builder.position_at_end(done_block);
+        self.builder
+            .build_load(self.caught_exceptions, "caught_exceptions")
+            .into_pointer_value()
+    }
+
+    /// Frees the exceptions caught by the activation, if any, before it
+    /// returns or is replaced by a tail call.
+    fn translate_caught_exceptions_drop(&mut self) {
+        let caught = self
+            .builder
+            .build_load(self.caught_exceptions, "caught_exceptions")
+            .into_pointer_value();
+        let is_null = self.builder.build_is_null(caught, "");
+        let drop_block = self
+            .context
+            .append_basic_block(self.function, "caught_exceptions_drop_block");
+        let done_block = self
+            .context
+            .append_basic_block(self.function, "caught_exceptions_dropped_block");
+        self.builder
+            .build_conditional_branch(is_null, done_block, drop_block);
+
+        self.builder.position_at_end(drop_block);
+        self.call_builtin(
+            VMBuiltinFunctionIndex::get_exception_caught_drop_index(),
+            self.intrinsics.exception_caught_drop_ptr_ty,
+            &[caught.into()],
+        );
+        self.builder.build_unconditional_branch(done_block);
+
+        self.builder.position_at_end(done_block);
+    }
+
+    /// Translates a `catch` or `catch_all` clause of the innermost `try`
+    /// block.
+    fn translate_catch(&mut self, tag_index: Option<TagIndex>) -> Result<(), CompileError> {
+        // The body, or the previous clause, ends by branching to the code after the block.
+        if self.state.reachable {
+            let frame = self.state.frame_at_depth(0)?;
+            let current_block = self
+                .builder
+                .get_insert_block()
+                .ok_or_else(|| CompileError::Codegen("not currently in a block".to_string()))?;
+
+            for phi in frame.phis().to_vec().iter().rev() {
+                let (value, info) = self.state.pop1_extra()?;
+                let value = self.apply_pending_canonicalization(value, info);
+                phi.add_incoming(&[(&value, current_block)])
+            }
+
+            let frame = self.state.frame_at_depth(0)?;
+            self.builder.build_unconditional_branch(*frame.code_after());
+        }
+
+        let (landing, slot, stack_size_snapshot) = match self.state.frame_at_depth_mut(0)? {
+            ControlFrame::Try {
+                landing,
+                slot,
+                stack_size_snapshot,
+                catching,
+                ..
+            } => {
+                *catching = true;
+                (*landing, *slot, *stack_size_snapshot)
+            }
+            _ => unreachable!(),
+        };
+        self.state.stack.truncate(stack_size_snapshot);
+
+        // All the exceptions of the body, or of the previous clause, have branched to the
+        // landing.
+        self.builder.position_at_end(landing);
+        let caught = self.translate_caught_exceptions();
+        let slot = self.intrinsics.i32_ty.const_int(slot.into(), false);
+        // `catch_all` matches any tag.
+        let tag = self.intrinsics.i32_ty.const_int(
+            tag_index
+                .map_or(u32::MAX, |tag_index| tag_index.as_u32())
+                .into(),
+            false,
+        );
+        let matches = self
+            .call_builtin(
+                VMBuiltinFunctionIndex::get_exception_catch_index(),
+                self.intrinsics.exception_catch_ptr_ty,
+                &[caught.into(), slot.into(), tag.into()],
+            )
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value();
+        let matches =
+            self.builder
+                .build_int_compare(IntPredicate::NE, matches, self.intrinsics.i32_zero, "");
+        let catch_block = self.context.append_basic_block(self.function, "catch");
+        let next_landing = self.context.append_basic_block(self.function, "landing");
+        self.builder
+            .build_conditional_branch(matches, catch_block, next_landing);
+        if let ControlFrame::Try { landing, .. } = self.state.frame_at_depth_mut(0)? {
+            *landing = next_landing;
+        }
+
+        self.builder.position_at_end(catch_block);
+        if let Some(tag_index) = tag_index {
+            let params = self.wasm_module.tags[tag_index].params().to_vec();
+            for (index, ty) in params.into_iter().enumerate() {
+                let index = self.intrinsics.i32_ty.const_int(index as u64, false);
+                let bits = self
+                    .call_builtin(
+                        VMBuiltinFunctionIndex::get_exception_payload_index(),
+                        self.intrinsics.exception_payload_ptr_ty,
+                        &[caught.into(), slot.into(), index.into()],
+                    )
+                    .try_as_basic_value()
+                    .left()
+                    .unwrap()
+                    .into_int_value();
+                let value = match ty {
+                    Type::I32 => self
+                        .builder
+                        .build_int_truncate(bits, self.intrinsics.i32_ty, "")
+                        .as_basic_value_enum(),
+                    Type::I64 => bits.as_basic_value_enum(),
+                    Type::F32 => {
+                        let bits =
+                            self.builder
+                                .build_int_truncate(bits, self.intrinsics.i32_ty, "");
+                        self.builder.build_bitcast(bits, self.intrinsics.f32_ty, "")
+                    }
+                    Type::F64 => self.builder.build_bitcast(bits, self.intrinsics.f64_ty, ""),
+                    ty => {
+                        return Err(CompileError::UnsupportedFeature(format!(
+                            "catching values of type {}",
+                            ty
+                        )))
+                    }
+                };
+                self.state.push1(value);
+            }
+        }
+        self.state.reachable = true;
+        Ok(())
+    }
+
+    /// Branches from the landing of the `try` block `frame` that just ended,
+    /// where the exceptions none of its catch clauses caught arrive, to the
+    /// catch clauses around the label a `delegate` targets, or else around
+    /// the block.
+    fn translate_try_end(
+        &mut self,
+        op: &Operator,
+        frame: &ControlFrame<'ctx>,
+    ) -> Result<(), CompileError> {
+        if let ControlFrame::Try { landing, .. } = *frame {
+            let depth = match *op {
+                Operator::Delegate { relative_depth } => {
+                    self.state.control_frames().len() - relative_depth as usize
+                }
+                _ => self.state.control_frames().len(),
+            };
+            let target = self.exception_target(depth)?;
+            self.builder.position_at_end(landing);
+            self.builder.build_unconditional_branch(target);
+        }
+        Ok(())
+    }
+
+    /// Checks that a `return_call` or `return_call_indirect` to a function
+    /// of type `callee_type` can be made.
+    ///
+    /// The call is marked `musttail`, which LLVM only guarantees between
+    /// functions of the same prototype: the uniform one of the functions
+    /// returning the same values, unless either function takes `v128`
+    /// arguments or more than [`RESERVED_STACK_ARGS`] on the stack.
+    fn check_return_call(
+        &self,
+        callee_type: inkwell::types::FunctionType<'ctx>,
+    ) -> Result<(), CompileError> {
+        if callee_type != self.function.get_type() {
+            return Err(CompileError::UnsupportedFeature(format!(
+                "return_call between functions of different signatures, when either takes \
+                 v128 arguments or more than {} arguments on the stack",
+                RESERVED_STACK_ARGS
+            )));
+        }
+        Ok(())
+    }
+
+    /// Turns the call that was just made into a tail call, and returns its
+    /// results from the function.
+    ///
+    /// Calls returning through an sret slot must have been given the one of
+    /// the function, as the callee of a `musttail` call cannot access the
+    /// caller's allocas.
+    fn translate_return_call(
+        &mut self,
+        call_site: CallSiteValue<'ctx>,
+        func_type: &FunctionType,
+    ) -> Result<(), CompileError> {
+        call_site.set_call_convention(self.function.get_call_conventions());
+        // SAFETY: `call_site` is a call instruction.
+        unsafe { wasmer_llvm_set_must_tail_call(call_site.as_value_ref()) };
+        if func_type.results().is_empty() || self.abi.is_sret(func_type)? {
+            self.builder.build_return(None);
+        } else {
+            let results = call_site.try_as_basic_value().left().unwrap();
+            self.builder.build_return(Some(&results));
+        }
+        self.state.reachable = false;
+        Ok(())
+    }
+
     fn finalize(&mut self, wasm_fn_type: &FunctionType) -> Result<(), CompileError> {
         let func_type = self.function.get_type();
+        self.translate_caught_exceptions_drop();
 
         let results = self.state.popn_save_extra(wasm_fn_type.results().len())?;
         let results = results
@@ -1353,8 +1963,13 @@ pub struct LLVMFunctionCodeGenerator<'ctx, 'a> {
     state: State<'ctx>,
     function: FunctionValue<'ctx>,
     locals: Vec<PointerValue<'ctx>>, // Contains params and locals
+    /// The `i8**` where the exceptions caught by the activation are, or null.
+    caught_exceptions: PointerValue<'ctx>,
+    /// Where the exceptions no `try` block catches branch to, once needed.
+    exception_exit: Option<BasicBlock<'ctx>>,
     ctx: CtxType<'ctx, 'a>,
     unreachable_depth: usize,
+    wasm_fn_type: &'a FunctionType,
     memory_styles: &'a PrimaryMap<MemoryIndex, MemoryStyle>,
     _table_styles: &'a PrimaryMap<TableIndex, TableStyle>,
 
@@ -1383,16 +1998,19 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
 
         if !self.state.reachable {
             match op {
-                Operator::Block { ty: _ } | Operator::Loop { ty: _ } | Operator::If { ty: _ } => {
+                Operator::Block { ty: _ }
+                | Operator::Loop { ty: _ }
+                | Operator::If { ty: _ }
+                | Operator::Try { ty: _ } => {
                     self.unreachable_depth += 1;
                     return Ok(());
                 }
-                Operator::Else => {
+                Operator::Else | Operator::Catch { .. } | Operator::CatchAll => {
                     if self.unreachable_depth != 0 {
                         return Ok(());
                     }
                 }
-                Operator::End => {
+                Operator::End | Operator::Delegate { .. } => {
                     if self.unreachable_depth != 0 {
                         self.unreachable_depth -= 1;
                         return Ok(());
@@ -1478,6 +2096,9 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 for phi in &loop_phis {
                     self.state.push1(phi.as_basic_value());
                 }
+                if self.config.enable_epoch_interruption {
+                    self.translate_epoch_check();
+                }
 
                 /*
                 if self.track_state {
@@ -1763,7 +2384,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 };
             }
 
-            Operator::End => {
+            Operator::End | Operator::Delegate { .. } => {
                 let frame = self.state.pop_frame()?;
                 let current_block = self
                     .builder
@@ -1796,6 +2417,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                         self.builder.build_unconditional_branch(*next);
                     }
                 }
+                self.translate_try_end(&op, &frame)?;
 
                 self.builder.position_at_end(*frame.code_after());
                 self.state.reset_stack(&frame);
@@ -1814,6 +2436,122 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     }
                 }
             }
+
+            /***************************
+             * Exception handling instructions.
+             * https://github.com/WebAssembly/exception-handling/blob/main/proposals/exception-handling/Exceptions.md
+             ***************************/
+            // Exceptions are propagated explicitly: `throw` records the exception in the runtime
+            // and branches to the catch clauses of the innermost `try` body, or else to the exit
+            // of the function, which returns with the exception still pending. Every call is
+            // followed by a check for an exception to propagate.
+            Operator::Try { ty } => {
+                let current_block = self
+                    .builder
+                    .get_insert_block()
+                    .ok_or_else(|| CompileError::Codegen("not currently in a block".to_string()))?;
+
+                let end_block = self.context.append_basic_block(self.function, "try_end");
+                let landing = self.context.append_basic_block(self.function, "landing");
+                self.builder.position_at_end(end_block);
+
+                let blocktypes = self.module_translation.blocktype_params_results(ty)?;
+                let phis: SmallVec<[PhiValue<'ctx>; 1]> = blocktypes
+                    .1
+                    .iter()
+                    .map(|&wp_ty| {
+                        wptype_to_type(wp_ty)
+                            .map_err(to_compile_error)
+                            .and_then(|wasm_ty| {
+                                type_to_llvm(self.intrinsics, wasm_ty)
+                                    .map(|ty| self.builder.build_phi(ty, ""))
+                            })
+                    })
+                    .collect::<Result<_, _>>()?;
+
+                self.state
+                    .push_try(end_block, landing, phis, blocktypes.0.len());
+                self.builder.position_at_end(current_block);
+            }
+            Operator::Catch { index } => {
+                self.translate_catch(Some(TagIndex::from_u32(index)))?;
+            }
+            Operator::CatchAll => {
+                self.translate_catch(None)?;
+            }
+            Operator::Throw { index } => {
+                let tag_index = TagIndex::from_u32(index);
+                let params = self.wasm_module.tags[tag_index].params().to_vec();
+                let args = self.state.popn_save_extra(params.len())?;
+                self.call_builtin(
+                    VMBuiltinFunctionIndex::get_exception_new_index(),
+                    self.intrinsics.exception_new_ptr_ty,
+                    &[self.intrinsics.i32_ty.const_int(index.into(), false).into()],
+                );
+                for ((value, info), ty) in args.into_iter().zip(params.iter()) {
+                    // The values are thrown as their bits.
+                    let value = self.apply_pending_canonicalization(value, info);
+                    let bits = match ty {
+                        Type::I32 => self.builder.build_int_z_extend(
+                            value.into_int_value(),
+                            self.intrinsics.i64_ty,
+                            "",
+                        ),
+                        Type::I64 => value.into_int_value(),
+                        Type::F32 => {
+                            let bits = self
+                                .builder
+                                .build_bitcast(value, self.intrinsics.i32_ty, "")
+                                .into_int_value();
+                            self.builder
+                                .build_int_z_extend(bits, self.intrinsics.i64_ty, "")
+                        }
+                        Type::F64 => self
+                            .builder
+                            .build_bitcast(value, self.intrinsics.i64_ty, "")
+                            .into_int_value(),
+                        ty => {
+                            return Err(CompileError::UnsupportedFeature(format!(
+                                "throwing values of type {}",
+                                ty
+                            )))
+                        }
+                    };
+                    self.call_builtin(
+                        VMBuiltinFunctionIndex::get_exception_push_index(),
+                        self.intrinsics.exception_push_ptr_ty,
+                        &[bits.into()],
+                    );
+                }
+                let target = self.exception_target(self.state.control_frames().len())?;
+                self.builder.build_unconditional_branch(target);
+                self.state.reachable = false;
+            }
+            Operator::Rethrow { relative_depth } => {
+                let slot = match *self.state.frame_at_depth(relative_depth)? {
+                    ControlFrame::Try { slot, .. } => slot,
+                    _ => {
+                        return Err(CompileError::Codegen(
+                            "rethrow: the label isn't a try block".to_string(),
+                        ))
+                    }
+                };
+                let caught = self
+                    .builder
+                    .build_load(self.caught_exceptions, "caught_exceptions");
+                self.call_builtin(
+                    VMBuiltinFunctionIndex::get_exception_rethrow_index(),
+                    self.intrinsics.exception_rethrow_ptr_ty,
+                    &[
+                        caught.into(),
+                        self.intrinsics.i32_ty.const_int(slot.into(), false).into(),
+                    ],
+                );
+                let target = self.exception_target(self.state.control_frames().len())?;
+                self.builder.build_unconditional_branch(target);
+                self.state.reachable = false;
+            }
+
             Operator::Return => {
                 let current_block = self
                     .builder
@@ -2141,10 +2879,20 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 };
                 self.state.push1_extra(res, info);
             }
-            Operator::Call { function_index } => {
+            Operator::Call { function_index } | Operator::ReturnCall { function_index } => {
+                let tail_call = matches!(op, Operator::ReturnCall { .. });
                 let func_index = FunctionIndex::from_u32(function_index);
                 let sigindex = &self.wasm_module.functions[func_index];
                 let func_type = &self.wasm_module.signatures[*sigindex];
+                if let Some(count) = self.gas_intrinsic_count(func_index) {
+                    self.state.pop1()?;
+                    self.translate_gas_intrinsic(count);
+                    if tail_call {
+                        self.finalize(self.wasm_fn_type)?;
+                        self.state.reachable = false;
+                    }
+                    return Ok(());
+                }
 
                 let FunctionCache {
                     func,
@@ -2170,6 +2918,10 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let func = *func;
                 let callee_vmctx = *callee_vmctx;
                 let attrs = attrs.clone();
+                let llvm_func_type = func.get_type().get_element_type().into_function_type();
+                if tail_call {
+                    self.check_return_call(llvm_func_type)?;
+                }
 
                 /*
                 let func_ptr = self.llvm.functions.borrow_mut()[&func_index];
@@ -2198,13 +2950,18 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                             _ => *v,
                         });
 
-                let params = self.abi.args_to_call(
+                let mut params = self.abi.args_to_call(
                     &self.alloca_builder,
+                    &self.builder,
+                    self.intrinsics,
                     func_type,
                     callee_vmctx.into_pointer_value(),
-                    &func.get_type().get_element_type().into_function_type(),
+                    &llvm_func_type,
                     params.collect::<Vec<_>>().as_slice(),
                 );
+                if tail_call && self.abi.is_sret(func_type)? {
+                    params[0] = self.function.get_first_param().unwrap();
+                }
 
                 /*
                 if self.track_state {
@@ -2226,6 +2983,9 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 }
                 */
 
+                if tail_call {
+                    self.translate_caught_exceptions_drop();
+                }
                 let callable_func = inkwell::values::CallableValue::try_from(func).unwrap();
                 let call_site = self.builder.build_call(
                     callable_func,
@@ -2256,12 +3016,19 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 }
                 */
 
-                self.abi
-                    .rets_from_call(&self.builder, &self.intrinsics, call_site, func_type)
-                    .iter()
-                    .for_each(|ret| self.state.push1(*ret));
+                if tail_call {
+                    self.translate_return_call(call_site, func_type)?;
+                } else {
+                    self.abi
+                        .rets_from_call(&self.builder, &self.intrinsics, call_site, func_type)
+                        .iter()
+                        .for_each(|ret| self.state.push1(*ret));
+                    self.translate_exception_check()?;
+                }
             }
-            Operator::CallIndirect { index, table_index } => {
+            Operator::CallIndirect { index, table_index }
+            | Operator::ReturnCallIndirect { index, table_index } => {
+                let tail_call = matches!(op, Operator::ReturnCallIndirect { .. });
                 let sigindex = SignatureIndex::from_u32(index);
                 let func_type = &self.wasm_module.signatures[sigindex];
                 let expected_dynamic_sigindex =
@@ -2463,6 +3230,9 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     Some(self.ctx.get_offsets()),
                     func_type,
                 )?;
+                if tail_call {
+                    self.check_return_call(llvm_func_type)?;
+                }
 
                 let params = self.state.popn_save_extra(func_type.params().len())?;
 
@@ -2486,13 +3256,18 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                             _ => *v,
                         });
 
-                let params = self.abi.args_to_call(
+                let mut params = self.abi.args_to_call(
                     &self.alloca_builder,
+                    &self.builder,
+                    self.intrinsics,
                     func_type,
                     ctx_ptr.into_pointer_value(),
                     &llvm_func_type,
                     params.collect::<Vec<_>>().as_slice(),
                 );
+                if tail_call && self.abi.is_sret(func_type)? {
+                    params[0] = self.function.get_first_param().unwrap();
+                }
 
                 let typed_func_ptr = self.builder.build_pointer_cast(
                     func_ptr,
@@ -2519,6 +3294,9 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     }
                 }
                 */
+                if tail_call {
+                    self.translate_caught_exceptions_drop();
+                }
                 let callable_func =
                     inkwell::values::CallableValue::try_from(typed_func_ptr).unwrap();
                 let call_site = self.builder.build_call(
@@ -2550,10 +3328,15 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 }
                 */
 
-                self.abi
-                    .rets_from_call(&self.builder, &self.intrinsics, call_site, func_type)
-                    .iter()
-                    .for_each(|ret| self.state.push1(*ret));
+                if tail_call {
+                    self.translate_return_call(call_site, func_type)?;
+                } else {
+                    self.abi
+                        .rets_from_call(&self.builder, &self.intrinsics, call_site, func_type)
+                        .iter()
+                        .for_each(|ret| self.state.push1(*ret));
+                    self.translate_exception_check()?;
+                }
             }
 
             /***************************
@@ -7609,7 +8392,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
              ***************************/
             Operator::I32Load { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7628,7 +8411,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64Load { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7647,7 +8430,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::F32Load { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7666,7 +8449,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::F64Load { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7685,7 +8468,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7802,7 +8585,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32Store { ref memarg } => {
                 let value = self.state.pop1()?;
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7823,7 +8606,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64Store { ref memarg } => {
                 let value = self.state.pop1()?;
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7845,7 +8628,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let (v, i) = self.state.pop1_extra()?;
                 let v = self.apply_pending_canonicalization(v, i);
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7867,7 +8650,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let (v, i) = self.state.pop1_extra()?;
                 let v = self.apply_pending_canonicalization(v, i);
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7889,7 +8672,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let (v, i) = self.state.pop1_extra()?;
                 let v = self.apply_pending_canonicalization(v, i);
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8009,7 +8792,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I32Load8S { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8033,7 +8816,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I32Load16S { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8057,7 +8840,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64Load8S { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8082,7 +8865,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64Load16S { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8107,7 +8890,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64Load32S { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8132,7 +8915,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
 
             Operator::I32Load8U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8156,7 +8939,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I32Load16U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8180,7 +8963,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64Load8U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8204,7 +8987,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64Load16U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8228,7 +9011,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64Load32U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8254,7 +9037,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32Store8 { ref memarg } | Operator::I64Store8 { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8278,7 +9061,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32Store16 { ref memarg } | Operator::I64Store16 { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8302,7 +9085,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64Store32 { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8684,7 +9467,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load8x8S { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8705,7 +9488,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load8x8U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8726,7 +9509,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load16x4S { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8747,7 +9530,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load16x4U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8768,7 +9551,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load32x2S { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8789,7 +9572,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load32x2U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8810,7 +9593,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load32Zero { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8834,7 +9617,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load64Zero { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8858,7 +9641,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load8Splat { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8879,7 +9662,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load16Splat { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8900,7 +9683,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load32Splat { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8921,7 +9704,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load64Splat { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8951,7 +9734,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I32AtomicLoad { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8969,7 +9752,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64AtomicLoad { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8987,7 +9770,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I32AtomicLoad8U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9011,7 +9794,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I32AtomicLoad16U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9035,7 +9818,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64AtomicLoad8U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9059,7 +9842,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64AtomicLoad16U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9083,7 +9866,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64AtomicLoad32U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9108,7 +9891,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicStore { ref memarg } => {
                 let value = self.state.pop1()?;
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9126,7 +9909,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicStore { ref memarg } => {
                 let value = self.state.pop1()?;
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9144,7 +9927,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicStore8 { ref memarg } | Operator::I64AtomicStore8 { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9166,7 +9949,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             | Operator::I64AtomicStore16 { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9187,7 +9970,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicStore32 { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9208,7 +9991,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw8AddU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9243,7 +10026,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw16AddU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9278,7 +10061,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmwAdd { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9307,7 +10090,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw8AddU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9342,7 +10125,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw16AddU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9377,7 +10160,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw32AddU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9412,7 +10195,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmwAdd { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9441,7 +10224,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw8SubU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9476,7 +10259,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw16SubU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9511,7 +10294,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmwSub { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9540,7 +10323,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw8SubU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9575,7 +10358,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw16SubU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9610,7 +10393,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw32SubU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9645,7 +10428,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmwSub { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9674,7 +10457,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw8AndU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9709,7 +10492,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw16AndU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9744,7 +10527,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmwAnd { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9773,7 +10556,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw8AndU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9808,7 +10591,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw16AndU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9843,7 +10626,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw32AndU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9878,7 +10661,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmwAnd { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9907,7 +10690,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw8OrU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9942,7 +10725,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw16OrU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9977,7 +10760,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmwOr { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10009,7 +10792,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw8OrU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10044,7 +10827,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw16OrU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10079,7 +10862,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw32OrU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10114,7 +10897,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmwOr { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10143,7 +10926,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw8XorU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10178,7 +10961,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw16XorU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10213,7 +10996,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmwXor { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10242,7 +11025,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw8XorU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10277,7 +11060,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw16XorU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10312,7 +11095,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw32XorU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10347,7 +11130,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmwXor { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10376,7 +11159,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw8XchgU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10411,7 +11194,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw16XchgU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10446,7 +11229,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmwXchg { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10475,7 +11258,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw8XchgU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10510,7 +11293,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw16XchgU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10545,7 +11328,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw32XchgU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10580,7 +11363,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmwXchg { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10612,7 +11395,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let new = self.apply_pending_canonicalization(new, new_info);
                 let (cmp, new) = (cmp.into_int_value(), new.into_int_value());
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10659,7 +11442,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let new = self.apply_pending_canonicalization(new, new_info);
                 let (cmp, new) = (cmp.into_int_value(), new.into_int_value());
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10706,7 +11489,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let new = self.apply_pending_canonicalization(new, new_info);
                 let (cmp, new) = (cmp.into_int_value(), new.into_int_value());
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10740,7 +11523,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let new = self.apply_pending_canonicalization(new, new_info);
                 let (cmp, new) = (cmp.into_int_value(), new.into_int_value());
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10787,7 +11570,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let new = self.apply_pending_canonicalization(new, new_info);
                 let (cmp, new) = (cmp.into_int_value(), new.into_int_value());
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10834,7 +11617,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let new = self.apply_pending_canonicalization(new, new_info);
                 let (cmp, new) = (cmp.into_int_value(), new.into_int_value());
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10881,7 +11664,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let new = self.apply_pending_canonicalization(new, new_info);
                 let (cmp, new) = (cmp.into_int_value(), new.into_int_value());
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -10915,6 +11698,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let delta = self.state.pop1()?;
                 let grow_fn_ptr = self.ctx.memory_grow(memory_index, self.intrinsics);
                 let callable_func = inkwell::values::CallableValue::try_from(grow_fn_ptr).unwrap();
+                let mem = self.memory_builtin_index(memory_index);
                 let grow = self.builder.build_call(
                     callable_func,
                     &[
@@ -10930,6 +11714,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let memory_index = MemoryIndex::from_u32(mem);
                 let size_fn_ptr = self.ctx.memory_size(memory_index, self.intrinsics);
                 let callable_func = inkwell::values::CallableValue::try_from(size_fn_ptr).unwrap();
+                let mem = self.memory_builtin_index(memory_index);
                 let size = self.builder.build_call(
                     callable_func,
                     &[
@@ -10943,10 +11728,15 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::MemoryInit { segment, mem } => {
                 let (dest, src, len) = self.state.pop3()?;
+                let memory_init = if self.memory64(MemoryIndex::from_u32(mem)) {
+                    self.intrinsics.memory64_init
+                } else {
+                    self.intrinsics.memory_init
+                };
                 let mem = self.intrinsics.i32_ty.const_int(mem.into(), false);
                 let segment = self.intrinsics.i32_ty.const_int(segment.into(), false);
                 self.builder.build_call(
-                    self.intrinsics.memory_init,
+                    memory_init,
                     &[
                         vmctx.as_basic_value_enum().into(),
                         mem.into(),
@@ -10966,16 +11756,93 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     "",
                 );
             }
-            Operator::MemoryCopy { src, dst } => {
-                // ignored until we support multiple memories
-                let _dst = dst;
-                let (memory_copy, src) = if let Some(local_memory_index) = self
-                    .wasm_module
-                    .local_memory_index(MemoryIndex::from_u32(src))
-                {
-                    (self.intrinsics.memory_copy, local_memory_index.as_u32())
-                } else {
-                    (self.intrinsics.imported_memory_copy, src)
+            Operator::MemoryAtomicWait32 { ref memarg }
+            | Operator::MemoryAtomicWait64 { ref memarg } => {
+                let memory_atomic_wait = match op {
+                    Operator::MemoryAtomicWait32 { .. } => self.intrinsics.memory_atomic_wait32,
+                    _ => self.intrinsics.memory_atomic_wait64,
+                };
+                let (addr, expected, timeout) = self.state.pop3()?;
+                let addr = self.effective_atomic_address(memarg, addr.into_int_value());
+                let mem = self
+                    .intrinsics
+                    .i32_ty
+                    .const_int(memarg.memory.into(), false);
+                let result = self.builder.build_call(
+                    memory_atomic_wait,
+                    &[
+                        vmctx.as_basic_value_enum().into(),
+                        mem.into(),
+                        addr.into(),
+                        expected.into(),
+                        timeout.into(),
+                    ],
+                    "",
+                );
+                self.state
+                    .push1(result.try_as_basic_value().left().unwrap());
+            }
+            Operator::MemoryAtomicNotify { ref memarg } => {
+                let (addr, count) = self.state.pop2()?;
+                let addr = self.effective_atomic_address(memarg, addr.into_int_value());
+                let mem = self
+                    .intrinsics
+                    .i32_ty
+                    .const_int(memarg.memory.into(), false);
+                let result = self.builder.build_call(
+                    self.intrinsics.memory_atomic_notify,
+                    &[
+                        vmctx.as_basic_value_enum().into(),
+                        mem.into(),
+                        addr.into(),
+                        count.into(),
+                    ],
+                    "",
+                );
+                self.state
+                    .push1(result.try_as_basic_value().left().unwrap());
+            }
+            Operator::MemoryCopy { src, dst } if src != dst => {
+                let (dest_pos, src_pos, len) = self.state.pop3()?;
+                // The builtin takes 64-bit operands whatever the memories.
+                let extend = |value: BasicValueEnum<'ctx>| {
+                    self.builder.build_int_z_extend_or_bit_cast(
+                        value.into_int_value(),
+                        self.intrinsics.i64_ty,
+                        "",
+                    )
+                };
+                let (dest_pos, src_pos, len) = (extend(dest_pos), extend(src_pos), extend(len));
+                let dst_index = self.intrinsics.i32_ty.const_int(dst.into(), false);
+                let src_index = self.intrinsics.i32_ty.const_int(src.into(), false);
+                self.builder.build_call(
+                    self.intrinsics.memory_copy_between,
+                    &[
+                        vmctx.as_basic_value_enum().into(),
+                        dst_index.into(),
+                        src_index.into(),
+                        dest_pos.into(),
+                        src_pos.into(),
+                        len.into(),
+                    ],
+                    "",
+                );
+            }
+            Operator::MemoryCopy { src, dst: _ } => {
+                let memory64 = self.memory64(MemoryIndex::from_u32(src));
+                let (memory_copy, src) = match (
+                    self.wasm_module
+                        .local_memory_index(MemoryIndex::from_u32(src)),
+                    memory64,
+                ) {
+                    (Some(local_memory_index), false) => {
+                        (self.intrinsics.memory_copy, local_memory_index.as_u32())
+                    }
+                    (None, false) => (self.intrinsics.imported_memory_copy, src),
+                    (Some(local_memory_index), true) => {
+                        (self.intrinsics.memory64_copy, local_memory_index.as_u32())
+                    }
+                    (None, true) => (self.intrinsics.imported_memory64_copy, src),
                 };
 
                 let (dest_pos, src_pos, len) = self.state.pop3()?;
@@ -10993,13 +11860,20 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 );
             }
             Operator::MemoryFill { mem } => {
-                let (memory_fill, mem) = if let Some(local_memory_index) = self
-                    .wasm_module
-                    .local_memory_index(MemoryIndex::from_u32(mem))
-                {
-                    (self.intrinsics.memory_fill, local_memory_index.as_u32())
-                } else {
-                    (self.intrinsics.imported_memory_fill, mem)
+                let memory64 = self.memory64(MemoryIndex::from_u32(mem));
+                let (memory_fill, mem) = match (
+                    self.wasm_module
+                        .local_memory_index(MemoryIndex::from_u32(mem)),
+                    memory64,
+                ) {
+                    (Some(local_memory_index), false) => {
+                        (self.intrinsics.memory_fill, local_memory_index.as_u32())
+                    }
+                    (None, false) => (self.intrinsics.imported_memory_fill, mem),
+                    (Some(local_memory_index), true) => {
+                        (self.intrinsics.memory64_fill, local_memory_index.as_u32())
+                    }
+                    (None, true) => (self.intrinsics.imported_memory64_fill, mem),
                 };
 
                 let (dst, val, len) = self.state.pop3()?;
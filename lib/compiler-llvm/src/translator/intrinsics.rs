@@ -218,6 +218,8 @@ pub struct Intrinsics<'ctx> {
     pub trap_bad_conversion_to_integer: BasicValueEnum<'ctx>,
     pub trap_unaligned_atomic: BasicValueEnum<'ctx>,
     pub trap_table_access_oob: BasicValueEnum<'ctx>,
+    pub trap_gas_exceeded: BasicValueEnum<'ctx>,
+    pub trap_interrupted: BasicValueEnum<'ctx>,
 
     pub experimental_stackmap: FunctionValue<'ctx>,
 
@@ -235,12 +237,21 @@ pub struct Intrinsics<'ctx> {
     pub imported_table_grow: FunctionValue<'ctx>,
     pub memory_init: FunctionValue<'ctx>,
     pub data_drop: FunctionValue<'ctx>,
+    pub memory_atomic_wait32: FunctionValue<'ctx>,
+    pub memory_atomic_wait64: FunctionValue<'ctx>,
+    pub memory_atomic_notify: FunctionValue<'ctx>,
     pub func_ref: FunctionValue<'ctx>,
     pub elem_drop: FunctionValue<'ctx>,
     pub memory_copy: FunctionValue<'ctx>,
     pub imported_memory_copy: FunctionValue<'ctx>,
     pub memory_fill: FunctionValue<'ctx>,
     pub imported_memory_fill: FunctionValue<'ctx>,
+    pub memory64_init: FunctionValue<'ctx>,
+    pub memory64_copy: FunctionValue<'ctx>,
+    pub imported_memory64_copy: FunctionValue<'ctx>,
+    pub memory64_fill: FunctionValue<'ctx>,
+    pub imported_memory64_fill: FunctionValue<'ctx>,
+    pub memory_copy_between: FunctionValue<'ctx>,
 
     pub throw_trap: FunctionValue<'ctx>,
 
@@ -257,6 +268,19 @@ pub struct Intrinsics<'ctx> {
     pub imported_memory32_grow_ptr_ty: PointerType<'ctx>,
     pub memory32_size_ptr_ty: PointerType<'ctx>,
     pub imported_memory32_size_ptr_ty: PointerType<'ctx>,
+    pub memory64_grow_ptr_ty: PointerType<'ctx>,
+    pub imported_memory64_grow_ptr_ty: PointerType<'ctx>,
+    pub memory64_size_ptr_ty: PointerType<'ctx>,
+    pub imported_memory64_size_ptr_ty: PointerType<'ctx>,
+
+    pub exception_new_ptr_ty: PointerType<'ctx>,
+    pub exception_push_ptr_ty: PointerType<'ctx>,
+    pub exception_pending_ptr_ty: PointerType<'ctx>,
+    pub exception_caught_new_ptr_ty: PointerType<'ctx>,
+    pub exception_caught_drop_ptr_ty: PointerType<'ctx>,
+    pub exception_catch_ptr_ty: PointerType<'ctx>,
+    pub exception_payload_ptr_ty: PointerType<'ctx>,
+    pub exception_rethrow_ptr_ty: PointerType<'ctx>,
 
     // Pointer to the VM.
     pub ctx_ptr_ty: PointerType<'ctx>,
@@ -335,6 +359,7 @@ impl<'ctx> Intrinsics<'ctx> {
 
         let i8_ptr_ty_basic = i8_ptr_ty.as_basic_type_enum();
 
+        let i8_ptr_ty_basic_md: BasicMetadataTypeEnum = i8_ptr_ty.into();
         let i1_ty_basic_md: BasicMetadataTypeEnum = i1_ty.into();
         let i32_ty_basic_md: BasicMetadataTypeEnum = i32_ty.into();
         let i64_ty_basic_md: BasicMetadataTypeEnum = i64_ty.into();
@@ -781,6 +806,12 @@ impl<'ctx> Intrinsics<'ctx> {
             trap_table_access_oob: i32_ty
                 .const_int(TrapCode::TableAccessOutOfBounds as _, false)
                 .as_basic_value_enum(),
+            trap_gas_exceeded: i32_ty
+                .const_int(TrapCode::GasExceeded as _, false)
+                .as_basic_value_enum(),
+            trap_interrupted: i32_ty
+                .const_int(TrapCode::Interrupted as _, false)
+                .as_basic_value_enum(),
 
             experimental_stackmap: module.add_function(
                 "llvm.experimental.stackmap",
@@ -988,11 +1019,138 @@ impl<'ctx> Intrinsics<'ctx> {
                 ),
                 None,
             ),
+            memory64_init: module.add_function(
+                "wasmer_vm_memory64_init",
+                void_ty.fn_type(
+                    &[
+                        ctx_ptr_ty_basic_md,
+                        i32_ty_basic_md,
+                        i32_ty_basic_md,
+                        i64_ty_basic_md,
+                        i32_ty_basic_md,
+                        i32_ty_basic_md,
+                    ],
+                    false,
+                ),
+                None,
+            ),
+            memory64_copy: module.add_function(
+                "wasmer_vm_memory64_copy",
+                void_ty.fn_type(
+                    &[
+                        ctx_ptr_ty_basic_md,
+                        i32_ty_basic_md,
+                        i64_ty_basic_md,
+                        i64_ty_basic_md,
+                        i64_ty_basic_md,
+                    ],
+                    false,
+                ),
+                None,
+            ),
+            imported_memory64_copy: module.add_function(
+                "wasmer_vm_imported_memory64_copy",
+                void_ty.fn_type(
+                    &[
+                        ctx_ptr_ty_basic_md,
+                        i32_ty_basic_md,
+                        i64_ty_basic_md,
+                        i64_ty_basic_md,
+                        i64_ty_basic_md,
+                    ],
+                    false,
+                ),
+                None,
+            ),
+            memory_copy_between: module.add_function(
+                "wasmer_vm_memory_copy_between",
+                void_ty.fn_type(
+                    &[
+                        ctx_ptr_ty_basic_md,
+                        i32_ty_basic_md,
+                        i32_ty_basic_md,
+                        i64_ty_basic_md,
+                        i64_ty_basic_md,
+                        i64_ty_basic_md,
+                    ],
+                    false,
+                ),
+                None,
+            ),
+            memory64_fill: module.add_function(
+                "wasmer_vm_memory64_fill",
+                void_ty.fn_type(
+                    &[
+                        ctx_ptr_ty_basic_md,
+                        i32_ty_basic_md,
+                        i64_ty_basic_md,
+                        i32_ty_basic_md,
+                        i64_ty_basic_md,
+                    ],
+                    false,
+                ),
+                None,
+            ),
+            imported_memory64_fill: module.add_function(
+                "wasmer_vm_imported_memory64_fill",
+                void_ty.fn_type(
+                    &[
+                        ctx_ptr_ty_basic_md,
+                        i32_ty_basic_md,
+                        i64_ty_basic_md,
+                        i32_ty_basic_md,
+                        i64_ty_basic_md,
+                    ],
+                    false,
+                ),
+                None,
+            ),
             data_drop: module.add_function(
                 "wasmer_vm_data_drop",
                 void_ty.fn_type(&[ctx_ptr_ty_basic_md, i32_ty_basic_md], false),
                 None,
             ),
+            memory_atomic_wait32: module.add_function(
+                "wasmer_vm_memory32_atomic_wait32",
+                i32_ty.fn_type(
+                    &[
+                        ctx_ptr_ty_basic_md,
+                        i32_ty_basic_md,
+                        i64_ty_basic_md,
+                        i32_ty_basic_md,
+                        i64_ty_basic_md,
+                    ],
+                    false,
+                ),
+                None,
+            ),
+            memory_atomic_wait64: module.add_function(
+                "wasmer_vm_memory32_atomic_wait64",
+                i32_ty.fn_type(
+                    &[
+                        ctx_ptr_ty_basic_md,
+                        i32_ty_basic_md,
+                        i64_ty_basic_md,
+                        i64_ty_basic_md,
+                        i64_ty_basic_md,
+                    ],
+                    false,
+                ),
+                None,
+            ),
+            memory_atomic_notify: module.add_function(
+                "wasmer_vm_memory32_atomic_notify",
+                i32_ty.fn_type(
+                    &[
+                        ctx_ptr_ty_basic_md,
+                        i32_ty_basic_md,
+                        i64_ty_basic_md,
+                        i32_ty_basic_md,
+                    ],
+                    false,
+                ),
+                None,
+            ),
             func_ref: module.add_function(
                 "wasmer_vm_func_ref",
                 funcref_ty.fn_type(&[ctx_ptr_ty_basic_md, i32_ty_basic_md], false),
@@ -1010,10 +1168,18 @@ impl<'ctx> Intrinsics<'ctx> {
             ),
 
             vmfunction_import_ptr_ty: context
-                .struct_type(&[i8_ptr_ty_basic, i8_ptr_ty_basic], false)
+                .struct_type(
+                    &[
+                        i8_ptr_ty_basic,
+                        i32_ty.as_basic_type_enum(),
+                        i8_ptr_ty_basic,
+                        i8_ptr_ty_basic,
+                    ],
+                    false,
+                )
                 .ptr_type(AddressSpace::Generic),
             vmfunction_import_body_element: 0,
-            vmfunction_import_vmctx_element: 1,
+            vmfunction_import_vmctx_element: 3,
 
             vmmemory_definition_ptr_ty: context
                 .struct_type(&[i8_ptr_ty_basic, isize_ty.into()], false)
@@ -1039,6 +1205,68 @@ impl<'ctx> Intrinsics<'ctx> {
             imported_memory32_size_ptr_ty: i32_ty
                 .fn_type(&[ctx_ptr_ty_basic_md, i32_ty_basic_md], false)
                 .ptr_type(AddressSpace::Generic),
+            memory64_grow_ptr_ty: i64_ty
+                .fn_type(
+                    &[ctx_ptr_ty_basic_md, i64_ty_basic_md, i32_ty_basic_md],
+                    false,
+                )
+                .ptr_type(AddressSpace::Generic),
+            imported_memory64_grow_ptr_ty: i64_ty
+                .fn_type(
+                    &[ctx_ptr_ty_basic_md, i64_ty_basic_md, i32_ty_basic_md],
+                    false,
+                )
+                .ptr_type(AddressSpace::Generic),
+            memory64_size_ptr_ty: i64_ty
+                .fn_type(&[ctx_ptr_ty_basic_md, i32_ty_basic_md], false)
+                .ptr_type(AddressSpace::Generic),
+            imported_memory64_size_ptr_ty: i64_ty
+                .fn_type(&[ctx_ptr_ty_basic_md, i32_ty_basic_md], false)
+                .ptr_type(AddressSpace::Generic),
+
+            exception_new_ptr_ty: void_ty
+                .fn_type(&[ctx_ptr_ty_basic_md, i32_ty_basic_md], false)
+                .ptr_type(AddressSpace::Generic),
+            exception_push_ptr_ty: void_ty
+                .fn_type(&[ctx_ptr_ty_basic_md, i64_ty_basic_md], false)
+                .ptr_type(AddressSpace::Generic),
+            exception_pending_ptr_ty: i32_ty
+                .fn_type(&[ctx_ptr_ty_basic_md], false)
+                .ptr_type(AddressSpace::Generic),
+            exception_caught_new_ptr_ty: i8_ptr_ty
+                .fn_type(&[ctx_ptr_ty_basic_md], false)
+                .ptr_type(AddressSpace::Generic),
+            exception_caught_drop_ptr_ty: void_ty
+                .fn_type(&[ctx_ptr_ty_basic_md, i8_ptr_ty_basic_md], false)
+                .ptr_type(AddressSpace::Generic),
+            exception_catch_ptr_ty: i32_ty
+                .fn_type(
+                    &[
+                        ctx_ptr_ty_basic_md,
+                        i8_ptr_ty_basic_md,
+                        i32_ty_basic_md,
+                        i32_ty_basic_md,
+                    ],
+                    false,
+                )
+                .ptr_type(AddressSpace::Generic),
+            exception_payload_ptr_ty: i64_ty
+                .fn_type(
+                    &[
+                        ctx_ptr_ty_basic_md,
+                        i8_ptr_ty_basic_md,
+                        i32_ty_basic_md,
+                        i32_ty_basic_md,
+                    ],
+                    false,
+                )
+                .ptr_type(AddressSpace::Generic),
+            exception_rethrow_ptr_ty: void_ty
+                .fn_type(
+                    &[ctx_ptr_ty_basic_md, i8_ptr_ty_basic_md, i32_ty_basic_md],
+                    false,
+                )
+                .ptr_type(AddressSpace::Generic),
 
             ctx_ptr_ty,
         };
@@ -1099,6 +1327,10 @@ pub struct CtxType<'ctx, 'a> {
     cached_functions: HashMap<FunctionIndex, FunctionCache<'ctx>>,
     cached_memory_grow: HashMap<MemoryIndex, PointerValue<'ctx>>,
     cached_memory_size: HashMap<MemoryIndex, PointerValue<'ctx>>,
+    cached_gas_counter: Option<PointerValue<'ctx>>,
+    cached_epoch: Option<PointerValue<'ctx>>,
+    cached_pending_exceptions: Option<PointerValue<'ctx>>,
+    cached_builtins: HashMap<u32, PointerValue<'ctx>>,
 
     offsets: VMOffsets,
 }
@@ -1124,6 +1356,10 @@ impl<'ctx, 'a> CtxType<'ctx, 'a> {
             cached_functions: HashMap::new(),
             cached_memory_grow: HashMap::new(),
             cached_memory_size: HashMap::new(),
+            cached_gas_counter: None,
+            cached_epoch: None,
+            cached_pending_exceptions: None,
+            cached_builtins: HashMap::new(),
 
             // TODO: pointer width
             offsets: VMOffsets::new(8).with_module_info(&wasm_module),
@@ -1588,16 +1824,24 @@ impl<'ctx, 'a> CtxType<'ctx, 'a> {
             &self.ctx_ptr_value,
         );
         *cached_memory_grow.entry(memory_index).or_insert_with(|| {
-            let (grow_fn, grow_fn_ty) = if wasm_module.local_memory_index(memory_index).is_some() {
-                (
+            let local = wasm_module.local_memory_index(memory_index).is_some();
+            let (grow_fn, grow_fn_ty) = match (local, wasm_module.memories[memory_index].memory64) {
+                (true, false) => (
                     VMBuiltinFunctionIndex::get_memory32_grow_index(),
                     intrinsics.memory32_grow_ptr_ty,
-                )
-            } else {
-                (
+                ),
+                (false, false) => (
                     VMBuiltinFunctionIndex::get_imported_memory32_grow_index(),
                     intrinsics.imported_memory32_grow_ptr_ty,
-                )
+                ),
+                (true, true) => (
+                    VMBuiltinFunctionIndex::get_memory64_grow_index(),
+                    intrinsics.memory64_grow_ptr_ty,
+                ),
+                (false, true) => (
+                    VMBuiltinFunctionIndex::get_imported_memory64_grow_index(),
+                    intrinsics.imported_memory64_grow_ptr_ty,
+                ),
             };
             let offset = offsets.vmctx_builtin_function(grow_fn);
             let offset = intrinsics.i32_ty.const_int(offset.into(), false);
@@ -1629,16 +1873,24 @@ impl<'ctx, 'a> CtxType<'ctx, 'a> {
             &self.ctx_ptr_value,
         );
         *cached_memory_size.entry(memory_index).or_insert_with(|| {
-            let (size_fn, size_fn_ty) = if wasm_module.local_memory_index(memory_index).is_some() {
-                (
+            let local = wasm_module.local_memory_index(memory_index).is_some();
+            let (size_fn, size_fn_ty) = match (local, wasm_module.memories[memory_index].memory64) {
+                (true, false) => (
                     VMBuiltinFunctionIndex::get_memory32_size_index(),
                     intrinsics.memory32_size_ptr_ty,
-                )
-            } else {
-                (
+                ),
+                (false, false) => (
                     VMBuiltinFunctionIndex::get_imported_memory32_size_index(),
                     intrinsics.imported_memory32_size_ptr_ty,
-                )
+                ),
+                (true, true) => (
+                    VMBuiltinFunctionIndex::get_memory64_size_index(),
+                    intrinsics.memory64_size_ptr_ty,
+                ),
+                (false, true) => (
+                    VMBuiltinFunctionIndex::get_imported_memory64_size_index(),
+                    intrinsics.imported_memory64_size_ptr_ty,
+                ),
             };
             let offset = offsets.vmctx_builtin_function(size_fn);
             let offset = intrinsics.i32_ty.const_int(offset.into(), false);
@@ -1658,6 +1910,122 @@ impl<'ctx, 'a> CtxType<'ctx, 'a> {
         })
     }
 
+    /// Returns a pointer to the `FastGasCounter` of the instance, as an `i8*`.
+    pub fn gas_counter(&mut self, intrinsics: &Intrinsics<'ctx>) -> PointerValue<'ctx> {
+        let (cached_gas_counter, offsets, cache_builder, ctx_ptr_value) = (
+            &mut self.cached_gas_counter,
+            &self.offsets,
+            &self.cache_builder,
+            &self.ctx_ptr_value,
+        );
+        *cached_gas_counter.get_or_insert_with(|| {
+            let offset = offsets.vmctx_gas_limiter_pointer();
+            let offset = intrinsics.i32_ty.const_int(offset.into(), false);
+            let gas_counter_ptr_ptr =
+                unsafe { cache_builder.build_gep(*ctx_ptr_value, &[offset], "") };
+            let gas_counter_ptr_ptr = cache_builder
+                .build_bitcast(
+                    gas_counter_ptr_ptr,
+                    intrinsics.i8_ptr_ty.ptr_type(AddressSpace::Generic),
+                    "",
+                )
+                .into_pointer_value();
+            cache_builder
+                .build_load(gas_counter_ptr_ptr, "gas_counter")
+                .into_pointer_value()
+        })
+    }
+
+    /// Returns a pointer to the epoch counter of the engine, as an `i64*`.
+    pub fn epoch(&mut self, intrinsics: &Intrinsics<'ctx>) -> PointerValue<'ctx> {
+        let (cached_epoch, offsets, cache_builder, ctx_ptr_value) = (
+            &mut self.cached_epoch,
+            &self.offsets,
+            &self.cache_builder,
+            &self.ctx_ptr_value,
+        );
+        *cached_epoch.get_or_insert_with(|| {
+            let offset = offsets.vmctx_epoch_pointer();
+            let offset = intrinsics.i32_ty.const_int(offset.into(), false);
+            let epoch_ptr_ptr = unsafe { cache_builder.build_gep(*ctx_ptr_value, &[offset], "") };
+            let epoch_ptr_ptr = cache_builder
+                .build_bitcast(
+                    epoch_ptr_ptr,
+                    intrinsics.i64_ptr_ty.ptr_type(AddressSpace::Generic),
+                    "",
+                )
+                .into_pointer_value();
+            cache_builder
+                .build_load(epoch_ptr_ptr, "epoch")
+                .into_pointer_value()
+        })
+    }
+
+    /// Returns a pointer to the number of exceptions being propagated, as an
+    /// `i32*`.
+    pub fn pending_exceptions(&mut self, intrinsics: &Intrinsics<'ctx>) -> PointerValue<'ctx> {
+        let (cached_pending_exceptions, offsets, cache_builder, ctx_ptr_value) = (
+            &mut self.cached_pending_exceptions,
+            &self.offsets,
+            &self.cache_builder,
+            &self.ctx_ptr_value,
+        );
+        *cached_pending_exceptions.get_or_insert_with(|| {
+            let offset = offsets.vmctx_pending_exceptions_pointer();
+            let offset = intrinsics.i32_ty.const_int(offset.into(), false);
+            let pending_ptr_ptr = unsafe { cache_builder.build_gep(*ctx_ptr_value, &[offset], "") };
+            let pending_ptr_ptr = cache_builder
+                .build_bitcast(
+                    pending_ptr_ptr,
+                    intrinsics.i32_ptr_ty.ptr_type(AddressSpace::Generic),
+                    "",
+                )
+                .into_pointer_value();
+            cache_builder
+                .build_load(pending_ptr_ptr, "pending_exceptions")
+                .into_pointer_value()
+        })
+    }
+
+    /// Returns the builtin function `index`, a pointer of type `ptr_ty`.
+    pub fn builtin(
+        &mut self,
+        index: VMBuiltinFunctionIndex,
+        ptr_ty: PointerType<'ctx>,
+        intrinsics: &Intrinsics<'ctx>,
+    ) -> PointerValue<'ctx> {
+        let (cached_builtins, offsets, cache_builder, ctx_ptr_value) = (
+            &mut self.cached_builtins,
+            &self.offsets,
+            &self.cache_builder,
+            &self.ctx_ptr_value,
+        );
+        *cached_builtins.entry(index.index()).or_insert_with(|| {
+            let offset = offsets.vmctx_builtin_function(index);
+            let offset = intrinsics.i32_ty.const_int(offset.into(), false);
+            let fn_ptr_ptr = unsafe { cache_builder.build_gep(*ctx_ptr_value, &[offset], "") };
+            let fn_ptr_ptr = cache_builder
+                .build_bitcast(fn_ptr_ptr, ptr_ty.ptr_type(AddressSpace::Generic), "")
+                .into_pointer_value();
+            cache_builder
+                .build_load(fn_ptr_ptr, "")
+                .into_pointer_value()
+        })
+    }
+
+    /// Returns a pointer to the epoch deadline of the instance, as an `i64*`.
+    pub fn epoch_deadline(&self, intrinsics: &Intrinsics<'ctx>) -> PointerValue<'ctx> {
+        let offset = self.offsets.vmctx_epoch_deadline();
+        let offset = intrinsics.i32_ty.const_int(offset.into(), false);
+        let deadline_ptr = unsafe {
+            self.cache_builder
+                .build_gep(self.ctx_ptr_value, &[offset], "")
+        };
+        self.cache_builder
+            .build_bitcast(deadline_ptr, intrinsics.i64_ptr_ty, "epoch_deadline_ptr")
+            .into_pointer_value()
+    }
+
     pub fn get_offsets(&self) -> &VMOffsets {
         &self.offsets
     }
@@ -30,6 +30,22 @@ pub enum ControlFrame<'ctx> {
         stack_size_snapshot: usize,
         if_else_state: IfElseState,
     },
+    /// A `try` block, whose body or catch clauses branch to `landing` when an
+    /// exception propagates out of them.
+    Try {
+        next: BasicBlock<'ctx>,
+        phis: SmallVec<[PhiValue<'ctx>; 1]>,
+        stack_size_snapshot: usize,
+        /// Where the exceptions not caught yet arrive: the catch clauses of
+        /// the body, or the clauses following the current one.
+        landing: BasicBlock<'ctx>,
+        /// The ordinal of the block, where its catch clauses store the
+        /// exception they caught.
+        slot: u32,
+        /// Whether the catch clauses are being translated, in which case
+        /// the exceptions propagate past the block.
+        catching: bool,
+    },
 }
 
 #[derive(Debug)]
@@ -43,22 +59,25 @@ impl<'ctx> ControlFrame<'ctx> {
         match self {
             ControlFrame::Block { ref next, .. }
             | ControlFrame::Loop { ref next, .. }
-            | ControlFrame::IfElse { ref next, .. } => next,
+            | ControlFrame::IfElse { ref next, .. }
+            | ControlFrame::Try { ref next, .. } => next,
         }
     }
 
     pub fn br_dest(&self) -> &BasicBlock<'ctx> {
         match self {
-            ControlFrame::Block { ref next, .. } | ControlFrame::IfElse { ref next, .. } => next,
+            ControlFrame::Block { ref next, .. }
+            | ControlFrame::IfElse { ref next, .. }
+            | ControlFrame::Try { ref next, .. } => next,
             ControlFrame::Loop { ref body, .. } => body,
         }
     }
 
     pub fn phis(&self) -> &[PhiValue<'ctx>] {
         match self {
-            ControlFrame::Block { ref phis, .. } | ControlFrame::Loop { ref phis, .. } => {
-                phis.as_slice()
-            }
+            ControlFrame::Block { ref phis, .. }
+            | ControlFrame::Loop { ref phis, .. }
+            | ControlFrame::Try { ref phis, .. } => phis.as_slice(),
             ControlFrame::IfElse { ref next_phis, .. } => next_phis.as_slice(),
         }
     }
@@ -66,7 +85,9 @@ impl<'ctx> ControlFrame<'ctx> {
     /// PHI nodes for stack values in the loop body.
     pub fn loop_body_phis(&self) -> &[PhiValue<'ctx>] {
         match self {
-            ControlFrame::Block { .. } | ControlFrame::IfElse { .. } => &[],
+            ControlFrame::Block { .. } | ControlFrame::IfElse { .. } | ControlFrame::Try { .. } => {
+                &[]
+            }
             ControlFrame::Loop {
                 ref loop_body_phis, ..
             } => loop_body_phis.as_slice(),
@@ -205,6 +226,7 @@ impl BitAnd for ExtraInfo {
 pub struct State<'ctx> {
     pub stack: Vec<(BasicValueEnum<'ctx>, ExtraInfo)>,
     control_stack: Vec<ControlFrame<'ctx>>,
+    tries: u32,
 
     pub reachable: bool,
 }
@@ -214,6 +236,7 @@ impl<'ctx> State<'ctx> {
         Self {
             stack: vec![],
             control_stack: vec![],
+            tries: 0,
             reachable: true,
         }
     }
@@ -235,11 +258,20 @@ impl<'ctx> State<'ctx> {
             | ControlFrame::IfElse {
                 stack_size_snapshot,
                 ..
+            }
+            | ControlFrame::Try {
+                stack_size_snapshot,
+                ..
             } => *stack_size_snapshot,
         };
         self.stack.truncate(stack_size_snapshot);
     }
 
+    /// The control frames, from the outermost one.
+    pub fn control_frames(&self) -> &[ControlFrame<'ctx>] {
+        &self.control_stack
+    }
+
     pub fn outermost_frame(&self) -> Result<&ControlFrame<'ctx>, CompileError> {
         self.control_stack.get(0).ok_or_else(|| {
             CompileError::Codegen("outermost_frame: invalid control stack depth".to_string())
@@ -434,4 +466,24 @@ impl<'ctx> State<'ctx> {
             if_else_state: IfElseState::If,
         });
     }
+
+    /// Pushes a `try` block, whose `num_params` parameters are on the value
+    /// stack.
+    pub fn push_try(
+        &mut self,
+        next: BasicBlock<'ctx>,
+        landing: BasicBlock<'ctx>,
+        phis: SmallVec<[PhiValue<'ctx>; 1]>,
+        num_params: usize,
+    ) {
+        self.control_stack.push(ControlFrame::Try {
+            next,
+            phis,
+            stack_size_snapshot: self.stack.len() - num_params,
+            landing,
+            slot: self.tries,
+            catching: false,
+        });
+        self.tries += 1;
+    }
 }
@@ -0,0 +1,286 @@
+//! ARM64 structures.
+use wasmer_compiler::{CallingConvention, CompileError};
+use wasmer_types::Type;
+
+/// General-purpose registers.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[allow(dead_code)]
+pub(crate) enum GPR {
+    X0,
+    X1,
+    X2,
+    X3,
+    X4,
+    X5,
+    X6,
+    X7,
+    X8,
+    X9,
+    X10,
+    X11,
+    X12,
+    X13,
+    X14,
+    X15,
+    X16,
+    X17,
+    X18,
+    X19,
+    X20,
+    X21,
+    X22,
+    X23,
+    X24,
+    X25,
+    X26,
+    X27,
+    X28,
+    X29,
+    X30,
+    /// Either the stack pointer or the zero register, depending on the instruction.
+    XzrSp,
+}
+
+/// NEON (floating point/SIMD) registers.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[allow(dead_code)]
+pub(crate) enum NEON {
+    V0,
+    V1,
+    V2,
+    V3,
+    V4,
+    V5,
+    V6,
+    V7,
+    V8,
+    V9,
+    V10,
+    V11,
+    V12,
+    V13,
+    V14,
+    V15,
+    V16,
+    V17,
+    V18,
+    V19,
+    V20,
+    V21,
+    V22,
+    V23,
+    V24,
+    V25,
+    V26,
+    V27,
+    V28,
+    V29,
+    V30,
+    V31,
+}
+
+impl GPR {
+    /// Converts a register number into a `GPR`.
+    pub(crate) fn from_index(n: usize) -> Option<GPR> {
+        static REGS: [GPR; 32] = [
+            GPR::X0,
+            GPR::X1,
+            GPR::X2,
+            GPR::X3,
+            GPR::X4,
+            GPR::X5,
+            GPR::X6,
+            GPR::X7,
+            GPR::X8,
+            GPR::X9,
+            GPR::X10,
+            GPR::X11,
+            GPR::X12,
+            GPR::X13,
+            GPR::X14,
+            GPR::X15,
+            GPR::X16,
+            GPR::X17,
+            GPR::X18,
+            GPR::X19,
+            GPR::X20,
+            GPR::X21,
+            GPR::X22,
+            GPR::X23,
+            GPR::X24,
+            GPR::X25,
+            GPR::X26,
+            GPR::X27,
+            GPR::X28,
+            GPR::X29,
+            GPR::X30,
+            GPR::XzrSp,
+        ];
+        REGS.get(n).copied()
+    }
+
+    pub(crate) fn into_index(self) -> u32 {
+        self as u32
+    }
+}
+
+impl NEON {
+    pub(crate) fn into_index(self) -> u32 {
+        self as u32
+    }
+}
+
+/// A machine register under the ARM64 architecture.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum ARM64Register {
+    /// General-purpose registers.
+    GPR(GPR),
+    /// NEON (floating point/SIMD) registers.
+    NEON(NEON),
+}
+
+/// An allocator that allocates registers for function arguments according to the AAPCS64.
+#[derive(Default)]
+pub(crate) struct ArgumentRegisterAllocator {
+    n_gprs: usize,
+    n_neons: usize,
+}
+
+impl ArgumentRegisterAllocator {
+    /// Allocates a register for argument type `ty`. Returns `None` if no register is available for this type.
+    pub(crate) fn next(
+        &mut self,
+        ty: Type,
+        calling_convention: CallingConvention,
+    ) -> Result<Option<ARM64Register>, CompileError> {
+        match calling_convention {
+            CallingConvention::SystemV => {
+                static GPR_SEQ: &[GPR] = &[
+                    GPR::X0,
+                    GPR::X1,
+                    GPR::X2,
+                    GPR::X3,
+                    GPR::X4,
+                    GPR::X5,
+                    GPR::X6,
+                    GPR::X7,
+                ];
+                static NEON_SEQ: &[NEON] = &[
+                    NEON::V0,
+                    NEON::V1,
+                    NEON::V2,
+                    NEON::V3,
+                    NEON::V4,
+                    NEON::V5,
+                    NEON::V6,
+                    NEON::V7,
+                ];
+                Ok(match ty {
+                    Type::I32 | Type::I64 | Type::ExternRef | Type::FuncRef => {
+                        if self.n_gprs < GPR_SEQ.len() {
+                            let gpr = GPR_SEQ[self.n_gprs];
+                            self.n_gprs += 1;
+                            Some(ARM64Register::GPR(gpr))
+                        } else {
+                            None
+                        }
+                    }
+                    Type::F32 | Type::F64 => {
+                        if self.n_neons < NEON_SEQ.len() {
+                            let neon = NEON_SEQ[self.n_neons];
+                            self.n_neons += 1;
+                            Some(ARM64Register::NEON(neon))
+                        } else {
+                            None
+                        }
+                    }
+                    _ => {
+                        return Err(CompileError::Codegen(format!(
+                            "ArgumentRegisterAllocator::next: Unsupported type: {:?}",
+                            ty
+                        )))
+                    }
+                })
+            }
+            _ => Err(CompileError::UnsupportedTarget(format!(
+                "aarch64 with the {:?} calling convention",
+                calling_convention
+            ))),
+        }
+    }
+}
+
+/// How a function returning multiple values returns them, following the
+/// AAPCS64 for a `repr(C)` struct of the values, as host functions do.
+#[derive(Clone, Debug)]
+pub(crate) struct ReturnLayout {
+    /// Offset and size in bytes of each value in the struct.
+    pub(crate) fields: Vec<(usize, usize)>,
+    /// Size in bytes of the struct.
+    pub(crate) size: usize,
+    /// Registers the struct is returned in: one NEON register per value for
+    /// homogeneous floating point aggregates, and otherwise one general
+    /// purpose register per eightbyte. `None` if it's returned in memory the
+    /// caller passes a pointer to in `x8`.
+    pub(crate) registers: Option<Vec<ARM64Register>>,
+}
+
+impl ReturnLayout {
+    /// Returns the layout of the values of type `tys`, or `None` if there
+    /// are less than two of them.
+    pub(crate) fn new(tys: &[Type]) -> Option<Self> {
+        if tys.len() < 2 {
+            return None;
+        }
+        let mut fields = Vec::with_capacity(tys.len());
+        let mut size = 0;
+        let mut align = 1;
+        for ty in tys {
+            let field_size = match ty {
+                Type::I32 | Type::F32 => 4,
+                Type::I64 | Type::F64 | Type::ExternRef | Type::FuncRef => 8,
+                Type::V128 => 16,
+            };
+            size = align_to(size, field_size);
+            fields.push((size, field_size));
+            size += field_size;
+            align = std::cmp::max(align, field_size);
+        }
+        let size = align_to(size, align);
+
+        static GPR_SEQ: &[GPR] = &[GPR::X0, GPR::X1];
+        static NEON_SEQ: &[NEON] = &[NEON::V0, NEON::V1, NEON::V2, NEON::V3];
+        let homogeneous_float =
+            tys.iter().all(|&ty| ty == Type::F32) || tys.iter().all(|&ty| ty == Type::F64);
+        let registers = if homogeneous_float && tys.len() <= NEON_SEQ.len() {
+            Some(
+                NEON_SEQ[..tys.len()]
+                    .iter()
+                    .map(|&neon| ARM64Register::NEON(neon))
+                    .collect(),
+            )
+        } else if size <= 16 && tys.iter().all(|&ty| ty != Type::V128) {
+            Some(
+                GPR_SEQ[..align_to(size, 8) / 8]
+                    .iter()
+                    .map(|&gpr| ARM64Register::GPR(gpr))
+                    .collect(),
+            )
+        } else {
+            None
+        };
+
+        Some(Self {
+            fields,
+            size,
+            registers,
+        })
+    }
+}
+
+/// Rounds `size` up to a multiple of `align`, which must be a power of two.
+pub(crate) fn align_to(size: usize, align: usize) -> usize {
+    (size + align - 1) & !(align - 1)
+}
@@ -0,0 +1,3630 @@
+//! AArch64 code generation.
+//!
+//! Unlike the x86-64 backend, this backend doesn't do any register allocation:
+//! every local and every value on the WebAssembly value stack lives in a fixed
+//! 8-byte slot of the native frame, and each operator loads its operands into
+//! scratch registers, computes, and stores the result back.
+//!
+//! Frame layout, relative to the frame pointer (`x29`):
+//!
+//! ```text
+//! [x29 + 16 + 8*i]  incoming stack arguments (argument index >= 8)
+//! [x29 + 8]         saved link register
+//! [x29]             saved frame pointer
+//! [x29 - 8]         saved x28
+//! [x29 - 16]        saved x19
+//! [x29 - 24 - 8*i]  local `i`, followed by the value stack slots
+//! ```
+//!
+//! The internal calling convention is the one used by the x86-64 backend:
+//! `vmctx` is passed in `x0`, all the WebAssembly arguments are passed in
+//! general purpose registers (`x1`-`x7`, then on the stack), and a single
+//! result is returned in `x0` (and also in `d0` for floating point results).
+//! Multiple results are returned as host functions return them, following
+//! the AAPCS64 for a struct of the values (see [`ReturnLayout`]).
+//!
+//! Blocks with multiple results leave them in the slots of the values they
+//! start at, and loops keep their parameters there across iterations.
+use crate::address_map::get_function_address_map;
+use crate::codegen_x64::{
+    type_to_wp_type, CanonicalizeType, CodegenError, ControlFrame, FloatValue, IfElseState,
+    PopMany, TryState, WpTypeExt, GEF32_LT_I32_MIN, GEF32_LT_I64_MIN, GEF32_LT_U32_MIN,
+    GEF32_LT_U64_MIN, GEF64_LT_I32_MIN, GEF64_LT_I64_MIN, GEF64_LT_U32_MIN, GEF64_LT_U64_MIN,
+    LEF32_GT_I32_MAX, LEF32_GT_I64_MAX, LEF32_GT_U32_MAX, LEF32_GT_U64_MAX, LEF64_GT_I32_MAX,
+    LEF64_GT_I64_MAX, LEF64_GT_U32_MAX, LEF64_GT_U64_MAX, RESERVED_STACK_ARGS,
+};
+use crate::config::{Intrinsic, IntrinsicKind};
+use crate::{arm64_decl::*, config::Singlepass, emitter_arm64::*};
+use dynasmrt::{AssemblyOffset, DynamicLabel};
+use memoffset::offset_of;
+use smallvec::{smallvec, SmallVec};
+use std::cmp::max;
+use wasmer_compiler::wasmparser::{
+    MemoryImmediate, Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType,
+};
+use wasmer_compiler::{
+    CallingConvention, CompileError, CompiledFunction, CompiledFunctionFrameInfo, CustomSection,
+    CustomSectionProtection, FunctionBody, FunctionBodyData, InstructionAddressMap,
+    ModuleTranslationState, Relocation, RelocationKind, RelocationTarget, SectionBody,
+    SectionIndex, SourceLoc,
+};
+use wasmer_types::{
+    entity::{EntityRef, PrimaryMap, SecondaryMap},
+    FastGasCounter, FunctionType,
+};
+use wasmer_types::{
+    FunctionIndex, GlobalIndex, LocalFunctionIndex, MemoryIndex, ModuleInfo, SignatureIndex,
+    TableIndex, TagIndex, Type,
+};
+use wasmer_vm::{TableStyle, TrapCode, VMBuiltinFunctionIndex, VMOffsets};
+
+/// Register holding the `vmctx` pointer for the whole function body.
+const VMCTX: GPR = GPR::X28;
+/// Frame pointer.
+const FP: GPR = GPR::X29;
+/// Stack pointer. Only valid where the instruction accepts `sp`.
+const SP: GPR = GPR::XzrSp;
+/// Register holding the target of an outgoing call.
+const CALL_TARGET: GPR = GPR::X16;
+/// Register used to materialize floating point immediates.
+const FP_IMM: GPR = GPR::X15;
+
+/// Offset from the frame pointer of the first local.
+const LOCALS_OFFSET: i32 = 24;
+/// Number of arguments passed in registers, including `vmctx`.
+const ARG_REGS: usize = 8;
+
+/// The singlepass per-function code generator.
+pub(crate) struct FuncGen<'a> {
+    // Immutable properties assigned at creation time.
+    /// Static module information.
+    module: &'a ModuleInfo,
+
+    /// State of module translation.
+    module_translation_state: &'a ModuleTranslationState,
+
+    /// ModuleInfo compilation config.
+    config: &'a Singlepass,
+
+    /// Offsets of vmctx fields.
+    vmoffsets: &'a VMOffsets,
+
+    /// Function signature.
+    signature: FunctionType,
+
+    // Working storage.
+    /// The assembler.
+    assembler: Assembler,
+
+    /// Types of local variables, including arguments.
+    local_types: Vec<WpType>,
+
+    /// Value stack.
+    value_stack: Vec<Location>,
+
+    /// Max stack depth, as accounted by the stack limit check.
+    max_stack_depth: usize,
+
+    /// Max number of value stack slots used, to size the frame.
+    max_value_slots: usize,
+
+    /// Location to patch when we know the max stack depth.
+    stack_check_offset: AssemblyOffset,
+
+    /// Locations to patch with the max stack depth in the stack limit
+    /// releases of tail calls.
+    tail_call_stack_check_offsets: Vec<AssemblyOffset>,
+
+    /// Location to patch when we know the frame size.
+    frame_size_offset: AssemblyOffset,
+
+    /// Metadata about floating point values on the stack.
+    fp_stack: Vec<FloatValue>,
+
+    /// A list of frames describing the current control stack.
+    control_stack: Vec<ControlFrame>,
+
+    /// Nesting level of unreachable code.
+    unreachable_depth: usize,
+
+    /// Relocation information.
+    relocations: Vec<Relocation>,
+
+    /// A set of special labels for trapping.
+    special_labels: SpecialLabelSet,
+
+    /// The source location for the current operator.
+    src_loc: u32,
+
+    /// Map from byte offset into wasm function to range of native instructions.
+    ///
+    // Ordered by increasing InstructionAddressMap::srcloc.
+    instructions_address_map: Vec<InstructionAddressMap>,
+
+    /// Location of the hidden local holding the exceptions caught by the
+    /// activation, if exception handling is enabled.
+    caught_exceptions: Option<Location>,
+
+    /// Number of `try` blocks seen so far.
+    try_count: u32,
+
+    /// How the function returns its results, if it has more than one.
+    return_layout: Option<ReturnLayout>,
+
+    /// Location of the hidden local holding the pointer to the memory the
+    /// results are returned in, if they are returned in memory.
+    return_pointer: Option<Location>,
+}
+
+struct SpecialLabelSet {
+    integer_division_by_zero: DynamicLabel,
+    integer_overflow: DynamicLabel,
+    bad_conversion_to_integer: DynamicLabel,
+    heap_access_oob: DynamicLabel,
+    table_access_oob: DynamicLabel,
+    indirect_call_null: DynamicLabel,
+    bad_signature: DynamicLabel,
+    gas_limit_exceeded: DynamicLabel,
+    stack_overflow: DynamicLabel,
+    interrupted: DynamicLabel,
+}
+
+/// Abstraction for a 2-input, 1-output operator. Can be an integer/floating-point
+/// binop/cmpop.
+struct I2O1 {
+    loc_a: Location,
+    loc_b: Location,
+    ret: Location,
+}
+
+fn canonicalize_size(ty: CanonicalizeType) -> Size {
+    match ty {
+        CanonicalizeType::F32 => Size::S32,
+        CanonicalizeType::F64 => Size::S64,
+    }
+}
+
+/// The size of the values `size` bytes long.
+fn value_size(size: usize) -> Size {
+    match size {
+        4 => Size::S32,
+        8 => Size::S64,
+        _ => unreachable!(),
+    }
+}
+
+fn float_size(ty: WpType) -> Size {
+    match ty {
+        WpType::F32 => Size::S32,
+        WpType::F64 => Size::S64,
+        _ => unreachable!(),
+    }
+}
+
+/// Location of argument `index` (counting `vmctx`) in the callee, before the
+/// prologue copies it to its local slot.
+fn incoming_arg_location(index: usize) -> Location {
+    if index < ARG_REGS {
+        Location::GPR(GPR::from_index(index).unwrap())
+    } else {
+        Location::Memory(FP, 16 + ((index - ARG_REGS) * 8) as i32)
+    }
+}
+
+fn align16(n: usize) -> usize {
+    (n + 15) & !15
+}
+
+impl<'a> FuncGen<'a> {
+    /// Set the source location of the Wasm to the given offset.
+    pub(crate) fn set_srcloc(&mut self, offset: u32) {
+        self.src_loc = offset;
+    }
+
+    fn local_location(&self, index: usize) -> Location {
+        Location::Memory(FP, -(LOCALS_OFFSET + (index * 8) as i32))
+    }
+
+    fn update_max_stack_depth(&mut self) {
+        self.max_stack_depth = max(
+            self.max_stack_depth,
+            self.value_stack.len() + self.fp_stack.len(),
+        );
+    }
+
+    /// Pushes a new value onto the value stack and returns its slot.
+    fn push_value(&mut self) -> Location {
+        let depth = self.value_stack.len();
+        let loc = self.local_location(self.local_types.len() + depth);
+        self.value_stack.push(loc);
+        self.max_value_slots = max(self.max_value_slots, depth + 1);
+        loc
+    }
+
+    fn pop_value(&mut self) -> Location {
+        self.update_max_stack_depth();
+        self.value_stack
+            .pop()
+            .expect("pop_value: value stack is empty")
+    }
+
+    /// Prepare data for binary operator with 2 inputs and 1 output.
+    fn i2o1_prepare(&mut self) -> I2O1 {
+        let loc_b = self.pop_value();
+        let loc_a = self.pop_value();
+        let ret = self.push_value();
+        I2O1 { loc_a, loc_b, ret }
+    }
+
+    /// Loads the value at `src` into `dst`.
+    fn emit_load(&mut self, sz: Size, src: Location, dst: GPR) {
+        match src {
+            Location::Imm32(value) => self.assembler.emit_mov_imm(sz, dst, value as u64),
+            Location::Imm64(value) => self.assembler.emit_mov_imm(sz, dst, value),
+            Location::GPR(gpr) => {
+                if gpr != dst {
+                    self.assembler.emit_mov(sz, gpr, dst);
+                }
+            }
+            Location::Memory(base, offset) => self.assembler.emit_ldr(sz, dst, base, offset),
+        }
+    }
+
+    /// Stores `src` into the location `dst`.
+    fn emit_store(&mut self, sz: Size, src: GPR, dst: Location) {
+        match dst {
+            Location::GPR(gpr) => {
+                if gpr != src {
+                    self.assembler.emit_mov(sz, src, gpr);
+                }
+            }
+            Location::Memory(base, offset) => self.assembler.emit_str(sz, src, base, offset),
+            _ => unreachable!("emit_store: {:?}", dst),
+        }
+    }
+
+    /// Loads the floating point value at `src` into `dst`.
+    fn emit_load_neon(&mut self, sz: Size, src: Location, dst: NEON) {
+        match src {
+            Location::Imm32(_) | Location::Imm64(_) | Location::GPR(_) => {
+                self.emit_load(sz, src, FP_IMM);
+                self.assembler.emit_fmov_from_gpr(sz, dst, FP_IMM);
+            }
+            Location::Memory(base, offset) => self.assembler.emit_ldr_neon(sz, dst, base, offset),
+        }
+    }
+
+    /// Stores the floating point value in `src` into the location `dst`.
+    fn emit_store_neon(&mut self, sz: Size, src: NEON, dst: Location) {
+        match dst {
+            Location::GPR(gpr) => self.assembler.emit_fmov_to_gpr(sz, gpr, src),
+            Location::Memory(base, offset) => self.assembler.emit_str_neon(sz, src, base, offset),
+            _ => unreachable!("emit_store_neon: {:?}", dst),
+        }
+    }
+
+    /// Copies a value between two locations, through a scratch register.
+    fn emit_move(&mut self, sz: Size, src: Location, dst: Location) {
+        if src != dst {
+            self.emit_load(sz, src, GPR::X9);
+            self.emit_store(sz, GPR::X9, dst);
+        }
+    }
+
+    /// Loads a floating point constant into `dst`.
+    fn emit_load_float_const(&mut self, sz: Size, bits: u64, dst: NEON) {
+        self.assembler.emit_mov_imm(sz, FP_IMM, bits);
+        self.assembler.emit_fmov_from_gpr(sz, dst, FP_IMM);
+    }
+
+    /// Canonicalizes the floating point value at `input` into `output`.
+    fn canonicalize_nan(&mut self, sz: Size, input: Location, output: Location) {
+        self.emit_load_neon(sz, input, NEON::V0);
+        let canonical = match sz {
+            Size::S32 => 0x7FC0_0000,           // Canonical NaN
+            Size::S64 => 0x7FF8_0000_0000_0000, // Canonical NaN
+            _ => unreachable!(),
+        };
+        self.emit_load_float_const(sz, canonical, NEON::V1);
+        self.assembler.emit_fcmp(sz, NEON::V0, NEON::V0);
+        self.assembler
+            .emit_fcsel(sz, NEON::V0, NEON::V1, NEON::V0, Condition::Vs);
+        self.emit_store_neon(sz, NEON::V0, output);
+    }
+
+    /// Whether a float with the given metadata needs to be canonicalized now.
+    fn needs_canonicalization(&self, fp: &FloatValue) -> bool {
+        self.config.enable_nan_canonicalization && fp.canonicalization.is_some()
+    }
+
+    /// Moves the value on top of the stack into the return register `x0`,
+    /// canonicalizing it first if needed.
+    fn emit_result_to_x0(&mut self, ty: WpType) -> Result<(), CodegenError> {
+        let loc = *self.value_stack.last().unwrap();
+        if ty.is_float() {
+            let fp = *self.fp_stack.peek1()?;
+            if self.needs_canonicalization(&fp) {
+                self.canonicalize_nan(float_size(ty), loc, Location::GPR(GPR::X0));
+                return Ok(());
+            }
+        }
+        self.emit_load(Size::S64, loc, GPR::X0);
+        Ok(())
+    }
+
+    /// Returns the length of the FP stack below the value at `depth`.
+    fn fp_stack_depth_at(&self, depth: usize) -> usize {
+        self.fp_stack
+            .iter()
+            .take_while(|fp| fp.depth < depth)
+            .count()
+    }
+
+    /// Moves the `count` values on top of the stack to the slots of the
+    /// values at `depth` and above, canonicalizing them if needed.
+    ///
+    /// The slots are filled from the lowest one, which never overwrites a
+    /// value left to move.
+    fn emit_values_to_slots(&mut self, depth: usize, count: usize) {
+        let first = self.value_stack.len() - count;
+        for i in 0..count {
+            let src = self.value_stack[first + i];
+            let dst = self.local_location(self.local_types.len() + depth + i);
+            let fp = self
+                .fp_stack
+                .iter()
+                .find(|fp| fp.depth == first + i)
+                .copied();
+            match fp {
+                Some(fp) if self.needs_canonicalization(&fp) => {
+                    let size = canonicalize_size(fp.canonicalization.unwrap());
+                    self.canonicalize_nan(size, src, dst);
+                }
+                _ => self.emit_move(Size::S64, src, dst),
+            }
+        }
+        self.max_value_slots = max(self.max_value_slots, depth + count);
+    }
+
+    /// Moves the results of the frame at `frame_index`, on top of the stack,
+    /// to where the code following the frame expects them: `x0` for a single
+    /// result, and otherwise the slots of the values the frame starts at.
+    fn emit_block_results(&mut self, frame_index: usize) -> Result<(), CodegenError> {
+        let frame = &self.control_stack[frame_index];
+        match frame.returns.len() {
+            0 => {}
+            1 => {
+                let first_return = frame.returns[0];
+                self.emit_result_to_x0(first_return)?;
+            }
+            n => {
+                let depth = frame.value_stack_depth;
+                self.emit_values_to_slots(depth, n);
+            }
+        }
+        Ok(())
+    }
+
+    /// Pushes the results of the frame just ended, from where
+    /// [`emit_block_results`](Self::emit_block_results) moved them.
+    fn push_block_results(&mut self, returns: &[WpType]) {
+        if let [ty] = returns {
+            let loc = self.push_value();
+            self.emit_store(Size::S64, GPR::X0, loc);
+            if ty.is_float() {
+                self.fp_stack
+                    .push(FloatValue::new(self.value_stack.len() - 1));
+                // we already canonicalized at the `Br*` instruction or here previously.
+            }
+        } else {
+            for ty in returns {
+                self.push_value();
+                if ty.is_float() {
+                    self.fp_stack
+                        .push(FloatValue::new(self.value_stack.len() - 1));
+                }
+            }
+        }
+    }
+
+    /// Moves the branch results for a branch to the frame at `relative_depth`,
+    /// or the new loop parameters for a branch to a loop, and jumps to its label.
+    fn emit_br(&mut self, relative_depth: u32) -> Result<(), CodegenError> {
+        let frame_index = self.control_stack.len() - 1 - (relative_depth as usize);
+        let frame = &self.control_stack[frame_index];
+        let label = frame.label;
+        if frame.loop_like {
+            let (depth, count) = (frame.value_stack_depth, frame.params.len());
+            self.emit_values_to_slots(depth, count);
+        } else {
+            self.emit_block_results(frame_index)?;
+        }
+        self.assembler.emit_b_label(label);
+        Ok(())
+    }
+
+    fn emit_trap(&mut self, code: TrapCode) {
+        let label = self.assembler.get_label();
+        self.assembler.emit_label(label);
+        self.assembler.emit_adr_label(GPR::X0, label);
+        self.assembler
+            .emit_mov_imm(Size::S32, GPR::X1, code as u32 as u64);
+        let offset = self.vmoffsets.vmctx_trap_handler();
+        self.assembler
+            .emit_ldr(Size::S64, CALL_TARGET, VMCTX, offset as i32);
+        self.assembler.emit_blr(CALL_TARGET);
+    }
+
+    /// Emits a call with the internal calling convention. `vmctx` is passed as the
+    /// first argument, unless `cb` overrides it; `cb` must emit the call itself.
+    fn emit_call_native<F: FnOnce(&mut Self)>(&mut self, params: &[Location], cb: F) {
+        // Reserve the stack argument slots the callee may tail call with.
+        let n_stack_args = max(
+            (params.len() + 1).saturating_sub(ARG_REGS),
+            RESERVED_STACK_ARGS,
+        );
+        let stack_bytes = align16(n_stack_args * 8) as u32;
+        if stack_bytes > 0 {
+            self.assembler.emit_sub_imm(SP, SP, stack_bytes);
+        }
+        for (i, param) in params.iter().enumerate() {
+            let index = i + 1;
+            if index >= ARG_REGS {
+                self.emit_load(Size::S64, *param, GPR::X9);
+                self.assembler
+                    .emit_str(Size::S64, GPR::X9, SP, ((index - ARG_REGS) * 8) as i32);
+            }
+        }
+        for (i, param) in params.iter().enumerate() {
+            let index = i + 1;
+            if index < ARG_REGS {
+                self.emit_load(Size::S64, *param, GPR::from_index(index).unwrap());
+            }
+        }
+        self.assembler.emit_mov(Size::S64, VMCTX, GPR::X0);
+        cb(self);
+        if stack_bytes > 0 {
+            self.assembler.emit_add_imm(SP, SP, stack_bytes);
+        }
+    }
+
+    /// Calls the builtin function `index` with `vmctx` and `params`.
+    fn emit_call_builtin(&mut self, index: VMBuiltinFunctionIndex, params: &[Location]) {
+        let offset = self.vmoffsets.vmctx_builtin_function(index) as i32;
+        self.assembler
+            .emit_ldr(Size::S64, CALL_TARGET, VMCTX, offset);
+        self.emit_call_native(params, |this| this.assembler.emit_blr(CALL_TARGET));
+    }
+
+    /// Returns the offset from the frame pointer of memory for the struct of
+    /// `layout`, in the value stack slots from `depth`.
+    fn return_area(&mut self, depth: usize, layout: &ReturnLayout) -> i32 {
+        let slots = align_to(layout.size, 8) / 8;
+        self.max_value_slots = max(self.max_value_slots, depth + slots);
+        match self.local_location(self.local_types.len() + depth + slots - 1) {
+            Location::Memory(_, offset) => offset,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Points `x8` to the memory the results of a call returning them as
+    /// `layout` says are returned in, if they are returned in memory.
+    fn emit_call_return_pointer(&mut self, layout: Option<&ReturnLayout>) {
+        if let Some(layout) = layout.filter(|layout| layout.registers.is_none()) {
+            let depth = self.value_stack.len() + layout.fields.len();
+            let area = self.return_area(depth, layout);
+            self.assembler.emit_sub_imm(GPR::X8, FP, (-area) as u32);
+        }
+    }
+
+    /// Pushes the results of a call, if any, returned as `layout` says if
+    /// there are several.
+    fn push_call_result(&mut self, return_types: &[WpType], layout: Option<&ReturnLayout>) {
+        let layout = match layout {
+            Some(layout) => layout,
+            None => {
+                if !return_types.is_empty() {
+                    let ret = self.push_value();
+                    if return_types[0].is_float() {
+                        self.emit_store_neon(Size::S64, NEON::V0, ret);
+                        self.fp_stack
+                            .push(FloatValue::new(self.value_stack.len() - 1));
+                    } else {
+                        self.emit_store(Size::S64, GPR::X0, ret);
+                    }
+                }
+                return;
+            }
+        };
+
+        match layout.registers.as_deref() {
+            Some(registers @ [ARM64Register::NEON(_), ..]) => {
+                for ((ty, &(_, size)), register) in
+                    return_types.iter().zip(&layout.fields).zip(registers)
+                {
+                    let ret = self.push_value();
+                    if let ARM64Register::NEON(neon) = register {
+                        self.emit_store_neon(value_size(size), *neon, ret);
+                    }
+                    if ty.is_float() {
+                        self.fp_stack
+                            .push(FloatValue::new(self.value_stack.len() - 1));
+                    }
+                }
+            }
+            registers => {
+                // The struct is unpacked from the memory right above the
+                // values, which the callee wrote to if it's returned in memory.
+                let depth = self.value_stack.len() + return_types.len();
+                let area = self.return_area(depth, layout);
+                for (i, register) in registers.unwrap_or_default().iter().enumerate() {
+                    if let ARM64Register::GPR(gpr) = register {
+                        self.assembler
+                            .emit_str(Size::S64, *gpr, FP, area + (i * 8) as i32);
+                    }
+                }
+                for (ty, &(offset, size)) in return_types.iter().zip(&layout.fields) {
+                    let ret = self.push_value();
+                    self.assembler
+                        .emit_ldr(value_size(size), GPR::X9, FP, area + offset as i32);
+                    self.emit_store(Size::S64, GPR::X9, ret);
+                    if ty.is_float() {
+                        self.fp_stack
+                            .push(FloatValue::new(self.value_stack.len() - 1));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Moves the results of the function, in the slots of the first values,
+    /// to where the function returns them as `layout` says.
+    fn emit_return_values(&mut self, layout: &ReturnLayout) {
+        // Propagating exceptions get here without moving results there.
+        self.max_value_slots = max(self.max_value_slots, layout.fields.len());
+        let first = self.local_types.len();
+        match layout.registers.as_deref() {
+            None => {
+                self.emit_load(Size::S64, self.return_pointer.unwrap(), GPR::X10);
+                for (i, &(offset, size)) in layout.fields.iter().enumerate() {
+                    let loc = self.local_location(first + i);
+                    self.emit_load(value_size(size), loc, GPR::X9);
+                    self.assembler
+                        .emit_str(value_size(size), GPR::X9, GPR::X10, offset as i32);
+                }
+            }
+            Some(registers @ [ARM64Register::NEON(_), ..]) => {
+                for (i, register) in registers.iter().enumerate() {
+                    if let ARM64Register::NEON(neon) = register {
+                        let loc = self.local_location(first + i);
+                        self.emit_load_neon(value_size(layout.fields[i].1), loc, *neon);
+                    }
+                }
+            }
+            Some(registers) => {
+                // Pack the struct in the memory right above the values.
+                let area = self.return_area(layout.fields.len(), layout);
+                for (i, &(offset, size)) in layout.fields.iter().enumerate() {
+                    let loc = self.local_location(first + i);
+                    self.emit_load(value_size(size), loc, GPR::X9);
+                    self.assembler
+                        .emit_str(value_size(size), GPR::X9, FP, area + offset as i32);
+                }
+                for (i, register) in registers.iter().enumerate() {
+                    if let ARM64Register::GPR(gpr) = register {
+                        self.assembler
+                            .emit_ldr(Size::S64, *gpr, FP, area + (i * 8) as i32);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Pops the call arguments off the value stack and the FP stack, canonicalizing
+    /// them if needed.
+    ///
+    /// Canonicalization state will be lost across function calls, so early canonicalization
+    /// is necessary here.
+    fn pop_call_params(&mut self, count: usize) -> SmallVec<[Location; 8]> {
+        let params: SmallVec<[_; 8]> = self
+            .value_stack
+            .drain(self.value_stack.len() - count..)
+            .collect();
+        self.update_max_stack_depth();
+        while let Some(fp) = self.fp_stack.last() {
+            if fp.depth >= self.value_stack.len() {
+                let index = fp.depth - self.value_stack.len();
+                if self.needs_canonicalization(fp) {
+                    let size = canonicalize_size(fp.canonicalization.unwrap());
+                    self.canonicalize_nan(size, params[index], params[index]);
+                }
+                self.fp_stack.pop().unwrap();
+            } else {
+                break;
+            }
+        }
+        params
+    }
+
+    fn emit_call(&mut self, function_index: u32, tail_call: bool) -> Result<(), CodegenError> {
+        let function_index = function_index as usize;
+        if tail_call {
+            self.emit_caught_exceptions_drop();
+        }
+
+        let sig_index = *self
+            .module
+            .functions
+            .get(FunctionIndex::new(function_index))
+            .unwrap();
+        let sig = self.module.signatures.get(sig_index).unwrap();
+        let param_count = sig.params().len();
+        let return_types: SmallVec<[WpType; 1]> =
+            sig.results().iter().cloned().map(type_to_wp_type).collect();
+        let return_layout = ReturnLayout::new(sig.results());
+
+        let params = self.pop_call_params(param_count);
+
+        if let Some(intrinsic) = self.check_intrinsic(function_index, &params) {
+            self.emit_intrinsic(intrinsic, &params)?;
+            if tail_call {
+                self.emit_return()?;
+            }
+            return Ok(());
+        }
+
+        // Imported functions are called through trampolines placed as custom sections.
+        let imports = self.module.import_counts.functions as usize;
+        let reloc_target = if function_index < imports {
+            RelocationTarget::CustomSection(SectionIndex::new(function_index))
+        } else {
+            RelocationTarget::LocalFunc(LocalFunctionIndex::new(function_index - imports))
+        };
+        // The absolute address is materialized with a `movz`/`movk` sequence, patched by the
+        // JIT linker.
+        let reloc_at = self.assembler.get_offset().0;
+        for (i, kind) in [
+            RelocationKind::Arm64Movw0,
+            RelocationKind::Arm64Movw1,
+            RelocationKind::Arm64Movw2,
+            RelocationKind::Arm64Movw3,
+        ]
+        .iter()
+        .enumerate()
+        {
+            self.relocations.push(Relocation {
+                kind: *kind,
+                reloc_target,
+                offset: (reloc_at + i * 4) as u32,
+                addend: 0,
+            });
+        }
+        self.assembler.emit_movz(CALL_TARGET, 0, 0);
+        self.assembler.emit_movk(CALL_TARGET, 0, 16);
+        self.assembler.emit_movk(CALL_TARGET, 0, 32);
+        self.assembler.emit_movk(CALL_TARGET, 0, 48);
+
+        if tail_call {
+            self.check_tail_call(params.len())?;
+            self.emit_tail_call(&params, |this| {
+                this.assembler.emit_mov(Size::S64, VMCTX, GPR::X0);
+            });
+            self.unreachable_depth = 1;
+            return Ok(());
+        }
+        self.emit_call_return_pointer(return_layout.as_ref());
+        self.emit_call_native(&params, |this| this.assembler.emit_blr(CALL_TARGET));
+        self.push_call_result(&return_types, return_layout.as_ref());
+        self.emit_exception_check();
+        Ok(())
+    }
+
+    /// Checks that a callee taking `n_params` parameters can be tail called,
+    /// that is, that its stack arguments fit in the ones of the current
+    /// function, which span at least [`RESERVED_STACK_ARGS`] slots.
+    fn check_tail_call(&self, n_params: usize) -> Result<(), CodegenError> {
+        let stack_args = |n: usize| (n + 1).saturating_sub(ARG_REGS);
+        let available = max(
+            stack_args(self.signature.params().len()),
+            RESERVED_STACK_ARGS,
+        );
+        if stack_args(n_params) > available {
+            return Err(CodegenError {
+                message: format!(
+                    "return_call: the callee takes more than {} arguments on the stack",
+                    available
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Emits a tail call to the function whose address is in `CALL_TARGET`:
+    /// the frame of the current function is torn down and the callee is
+    /// jumped to, so that it returns straight to our caller.
+    ///
+    /// `cb` must load the vmctx of the callee into `x0`, and may only clobber
+    /// `x0`.
+    fn emit_tail_call<F: FnOnce(&mut Self)>(&mut self, params: &[Location], cb: F) {
+        cb(self);
+
+        // The callee accounts for its own stack usage, so release ours now,
+        // as the function epilogue does. The depth is patched at the end.
+        let offset = self.vmoffsets.vmctx_stack_limit_begin() as i32;
+        self.assembler.emit_ldr(Size::S32, GPR::X9, VMCTX, offset);
+        self.tail_call_stack_check_offsets
+            .push(self.assembler.get_offset());
+        self.assembler.emit_movz(GPR::X10, 0, 0);
+        self.assembler.emit_movk(GPR::X10, 0, 16);
+        self.assembler
+            .emit_binop(BinOp::Add, Size::S32, GPR::X9, GPR::X9, GPR::X10);
+        self.assembler.emit_str(Size::S32, GPR::X9, VMCTX, offset);
+
+        // Values all live below the frame pointer, so stack arguments can
+        // overwrite our own, which `check_tail_call` made sure leaves enough
+        // room.
+        for (i, param) in params.iter().enumerate() {
+            let index = i + 1;
+            if index >= ARG_REGS {
+                self.emit_move(Size::S64, *param, incoming_arg_location(index));
+            }
+        }
+        for (i, param) in params.iter().enumerate() {
+            let index = i + 1;
+            if index < ARG_REGS {
+                self.emit_load(Size::S64, *param, GPR::from_index(index).unwrap());
+            }
+        }
+        // The callee returns the same results, in the memory ours go to.
+        if let Some(return_pointer) = self.return_pointer {
+            self.emit_load(Size::S64, return_pointer, GPR::X8);
+        }
+
+        self.assembler.emit_ldp(GPR::X19, VMCTX, FP, -16);
+        self.assembler.emit_mov_to_sp(FP);
+        self.assembler.emit_pop_pair(FP, GPR::X30);
+        self.assembler.emit_br(CALL_TARGET);
+    }
+
+    /// Returns the values on top of the stack, if any, from the function.
+    fn emit_return(&mut self) -> Result<(), CodegenError> {
+        self.emit_block_results(0)?;
+        let label = self.control_stack[0].label;
+        self.assembler.emit_b_label(label);
+        self.unreachable_depth = 1;
+        Ok(())
+    }
+
+    /// Where the exceptions propagating out of the `depth` outermost control
+    /// frames jump to: the catch clauses of the innermost `try` body among
+    /// them, or else the function epilogue, which returns with the
+    /// exception still pending for the caller to propagate.
+    fn exception_target(&self, depth: usize) -> DynamicLabel {
+        self.control_stack[..depth]
+            .iter()
+            .rev()
+            .find_map(|frame| match frame.try_state {
+                Some(TryState {
+                    catching: false,
+                    landing,
+                    ..
+                }) => Some(landing),
+                _ => None,
+            })
+            .unwrap_or(self.control_stack[0].label)
+    }
+
+    /// Propagates the exception thrown by the function just called, if any.
+    ///
+    /// The runtime is only asked whether the callee threw when some thread
+    /// is propagating an exception.
+    ///
+    /// Clobbers `x9`.
+    fn emit_exception_check(&mut self) {
+        let no_exception = self.assembler.get_label();
+        self.assembler.emit_ldr(
+            Size::S64,
+            GPR::X9,
+            VMCTX,
+            self.vmoffsets.vmctx_pending_exceptions_pointer() as i32,
+        );
+        self.assembler.emit_ldr(Size::S32, GPR::X9, GPR::X9, 0);
+        self.assembler
+            .emit_cbz_label(Size::S32, GPR::X9, no_exception);
+
+        self.emit_call_builtin(VMBuiltinFunctionIndex::get_exception_pending_index(), &[]);
+        let target = self.exception_target(self.control_stack.len());
+        self.assembler.emit_cbnz_label(Size::S32, GPR::X0, target);
+        self.assembler.emit_label(no_exception);
+    }
+
+    /// Allocates the caught exceptions of the activation unless it already
+    /// has them, and returns their location.
+    fn emit_caught_exceptions(&mut self) -> Location {
+        let caught = self.caught_exceptions.unwrap();
+        let allocated = self.assembler.get_label();
+        self.emit_load(Size::S64, caught, GPR::X9);
+        self.assembler
+            .emit_cbnz_label(Size::S64, GPR::X9, allocated);
+        self.emit_call_builtin(
+            VMBuiltinFunctionIndex::get_exception_caught_new_index(),
+            &[],
+        );
+        self.emit_store(Size::S64, GPR::X0, caught);
+        self.assembler.emit_label(allocated);
+        caught
+    }
+
+    /// Frees the caught exceptions of the activation, before it returns or
+    /// is replaced by a tail call.
+    ///
+    /// Clobbers the caller-saved registers.
+    fn emit_caught_exceptions_drop(&mut self) {
+        let caught = match self.caught_exceptions {
+            Some(caught) => caught,
+            None => return,
+        };
+        let done = self.assembler.get_label();
+        self.emit_load(Size::S64, caught, GPR::X9);
+        self.assembler.emit_cbz_label(Size::S64, GPR::X9, done);
+        self.emit_call_builtin(
+            VMBuiltinFunctionIndex::get_exception_caught_drop_index(),
+            &[caught],
+        );
+        self.emit_store(Size::S64, GPR::XzrSp, caught);
+        self.assembler.emit_label(done);
+    }
+
+    fn check_intrinsic(&mut self, index: usize, params: &[Location]) -> Option<Intrinsic> {
+        let function_index = FunctionIndex::new(index);
+        let signature_index = self.module.functions[function_index];
+        let signature = &self.module.signatures[signature_index];
+        // Returns None if not imported.
+        let import_name = self
+            .module_translation_state
+            .import_map
+            .get(&function_index)?;
+        let constants: SmallVec<[Option<u32>; 8]> = params
+            .iter()
+            .map(|param| match *param {
+                Location::Imm32(value) => Some(value),
+                _ => None,
+            })
+            .collect();
+        for intrinsic in &self.config.intrinsics {
+            if intrinsic.name == *import_name
+                && intrinsic.signature == *signature
+                && intrinsic.is_params_ok(&constants)
+            {
+                return Some(intrinsic.clone());
+            }
+        }
+        None
+    }
+
+    fn emit_intrinsic(
+        &mut self,
+        intrinsic: Intrinsic,
+        params: &[Location],
+    ) -> Result<(), CodegenError> {
+        match intrinsic.kind {
+            IntrinsicKind::Gas => {
+                let counter_offset = offset_of!(FastGasCounter, burnt_gas) as i32;
+                let gas_limit_offset = offset_of!(FastGasCounter, gas_limit) as i32;
+                let opcode_cost_offset = offset_of!(FastGasCounter, opcode_cost) as i32;
+                // Recheck offsets, to make sure offsets will never change.
+                assert_eq!(counter_offset, 0);
+                assert_eq!(gas_limit_offset, 8);
+                assert_eq!(opcode_cost_offset, 16);
+                assert_eq!(params.len(), 1);
+                let count = match params[0] {
+                    Location::Imm32(imm) => imm,
+                    _ => unreachable!(),
+                };
+                let (base, burnt, cost, tmp) = (GPR::X9, GPR::X10, GPR::X11, GPR::X12);
+                // Load gas counter base.
+                self.assembler.emit_ldr(
+                    Size::S64,
+                    base,
+                    VMCTX,
+                    self.vmoffsets.vmctx_gas_limiter_pointer() as i32,
+                );
+                // Read current gas counter and opcode cost.
+                self.assembler
+                    .emit_ldr(Size::S64, burnt, base, counter_offset);
+                self.assembler
+                    .emit_ldr(Size::S64, cost, base, opcode_cost_offset);
+                // Multiply instruction count by opcode cost.
+                self.assembler.emit_mov_imm(Size::S64, tmp, count as u64);
+                self.assembler
+                    .emit_binop(BinOp::Mul, Size::S64, cost, cost, tmp);
+                // Compute new cost.
+                self.assembler
+                    .emit_binop(BinOp::Adds, Size::S64, burnt, burnt, cost);
+                self.assembler
+                    .emit_bcond_label(Condition::Vs, self.special_labels.integer_overflow);
+                // Write new gas counter unconditionally, so that runtime can sort out limits case.
+                self.assembler
+                    .emit_str(Size::S64, burnt, base, counter_offset);
+                // Compare with the limit.
+                self.assembler
+                    .emit_ldr(Size::S64, tmp, base, gas_limit_offset);
+                self.assembler.emit_cmp(Size::S64, tmp, burnt);
+                self.assembler
+                    .emit_bcond_label(Condition::Ls, self.special_labels.gas_limit_exceeded);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the memory at `index` is indexed with 64-bit addresses.
+    fn memory64(&self, index: MemoryIndex) -> bool {
+        self.module.memories[index].memory64
+    }
+
+    /// The index identifying the memory at `index` to the memory builtins,
+    /// which take local indices for locally defined memories.
+    fn memory_builtin_index(&self, index: MemoryIndex) -> u32 {
+        match self.module.local_memory_index(index) {
+            Some(local_memory_index) => local_memory_index.as_u32(),
+            None => index.as_u32(),
+        }
+    }
+
+    /// Loads the address `addr` of a memory access into `tmp_addr`, and adds
+    /// the offset of `memarg` to it, trapping if the addition overflows.
+    ///
+    /// Clobbers the scratch register.
+    fn emit_effective_address(&mut self, addr: Location, memarg: &MemoryImmediate, tmp_addr: GPR) {
+        let sz = if self.memory64(MemoryIndex::new(memarg.memory as usize)) {
+            Size::S64
+        } else {
+            Size::S32
+        };
+        self.emit_load(sz, addr, tmp_addr);
+
+        // Add offset to memory address.
+        if memarg.offset != 0 {
+            self.assembler
+                .emit_mov_imm(sz, SCRATCH, memarg.offset as u64);
+            self.assembler
+                .emit_binop(BinOp::Adds, sz, tmp_addr, tmp_addr, SCRATCH);
+
+            // Trap if offset calculation overflowed.
+            self.assembler
+                .emit_bcond_label(Condition::Cs, self.special_labels.heap_access_oob);
+        }
+    }
+
+    /// Emits a memory operation. `cb` is called with the register holding the
+    /// effective address.
+    ///
+    /// Clobbers `x9`-`x11`; `x12` and `x13` can be used to pass values to `cb`.
+    fn emit_memory_op<F: FnOnce(&mut Self, GPR)>(
+        &mut self,
+        addr: Location,
+        memarg: &MemoryImmediate,
+        check_alignment: bool,
+        value_size: usize,
+        cb: F,
+    ) {
+        let (tmp_addr, tmp_base, tmp_bound) = (GPR::X9, GPR::X10, GPR::X11);
+
+        let memory_index = MemoryIndex::new(memarg.memory as usize);
+        match self.module.local_memory_index(memory_index) {
+            Some(local_memory_index) => {
+                let offset = self.vmoffsets.vmctx_vmmemory_definition(local_memory_index) as i32;
+                self.assembler.emit_ldr(Size::S64, tmp_base, VMCTX, offset);
+                self.assembler
+                    .emit_ldr(Size::S64, tmp_bound, VMCTX, offset + 8);
+            }
+            None => {
+                // Imported memories require one level of indirection.
+                let offset = self
+                    .vmoffsets
+                    .vmctx_vmmemory_import_definition(memory_index);
+                self.assembler
+                    .emit_ldr(Size::S64, tmp_addr, VMCTX, offset as i32);
+                self.assembler.emit_ldr(Size::S64, tmp_base, tmp_addr, 0);
+                self.assembler.emit_ldr(Size::S64, tmp_bound, tmp_addr, 8);
+            }
+        }
+
+        // The upper bound of the memory is (exclusively) `tmp_bound + tmp_base`, so the
+        // maximum allowed beginning of the word is (inclusively)
+        // `tmp_bound + tmp_base - value_size`.
+        self.assembler
+            .emit_binop(BinOp::Add, Size::S64, tmp_bound, tmp_bound, tmp_base);
+        self.assembler
+            .emit_sub_imm(tmp_bound, tmp_bound, value_size as u32);
+
+        self.emit_effective_address(addr, memarg, tmp_addr);
+
+        // Wasm linear memory -> real memory
+        if self.memory64(MemoryIndex::new(memarg.memory as usize)) {
+            // 64-bit addresses can wrap around when the base is added.
+            self.assembler
+                .emit_binop(BinOp::Adds, Size::S64, tmp_addr, tmp_base, tmp_addr);
+            self.assembler
+                .emit_bcond_label(Condition::Cs, self.special_labels.heap_access_oob);
+        } else {
+            self.assembler
+                .emit_binop(BinOp::Add, Size::S64, tmp_addr, tmp_base, tmp_addr);
+        }
+
+        // `tmp_bound` is inclusive. So trap only if `tmp_addr > tmp_bound`.
+        self.assembler.emit_cmp(Size::S64, tmp_addr, tmp_bound);
+        self.assembler
+            .emit_bcond_label(Condition::Hi, self.special_labels.heap_access_oob);
+
+        // Atomic accesses must be naturally aligned. `memarg.align` is only a hint, so
+        // the check is based on the access size.
+        if check_alignment && value_size != 1 {
+            self.assembler
+                .emit_mov_imm(Size::S64, tmp_bound, (value_size - 1) as u64);
+            self.assembler.emit_tst(Size::S64, tmp_addr, tmp_bound);
+            self.assembler
+                .emit_bcond_label(Condition::Ne, self.special_labels.heap_access_oob);
+        }
+
+        cb(self, tmp_addr);
+    }
+
+    /// Calls the `memory.atomic.wait*` or `memory.atomic.notify` builtin
+    /// `index` with the effective address of `addr` followed by `args`, and
+    /// pushes its result.
+    ///
+    /// The builtin checks the bounds and alignment of the address.
+    fn emit_atomic_wait_notify(
+        &mut self,
+        index: VMBuiltinFunctionIndex,
+        memarg: &MemoryImmediate,
+        addr: Location,
+        args: &[Location],
+    ) {
+        // X9 is not an argument register, so it survives the moves of the
+        // call arguments.
+        let tmp_addr = GPR::X9;
+        self.emit_effective_address(addr, memarg, tmp_addr);
+
+        // [vmctx, memory_index, addr, args...]
+        let mut params = vec![Location::Imm32(memarg.memory), Location::GPR(tmp_addr)];
+        params.extend_from_slice(args);
+        self.emit_call_builtin(index, &params);
+        let ret = self.push_value();
+        self.emit_store(Size::S64, GPR::X0, ret);
+    }
+
+    /// Loads `sz` bytes from memory and pushes the result, optionally sign-extending
+    /// it to `sz_dst`.
+    fn emit_load_op(
+        &mut self,
+        memarg: &MemoryImmediate,
+        sz: Size,
+        sign_extend_to: Option<Size>,
+        is_float: bool,
+    ) {
+        let target = self.pop_value();
+        let ret = self.push_value();
+        if is_float {
+            self.fp_stack
+                .push(FloatValue::new(self.value_stack.len() - 1));
+        }
+        let value_size = match sz {
+            Size::S8 => 1,
+            Size::S16 => 2,
+            Size::S32 => 4,
+            Size::S64 => 8,
+        };
+        self.emit_memory_op(target, memarg, false, value_size, |this, addr| {
+            match sign_extend_to {
+                Some(sz_dst) => this.assembler.emit_ldrs(sz, sz_dst, GPR::X10, addr, 0),
+                None => this.assembler.emit_ldr(sz, GPR::X10, addr, 0),
+            }
+            this.emit_store(Size::S64, GPR::X10, ret);
+        });
+    }
+
+    /// Pops a value and an address, and stores the low `sz` bytes of the value.
+    fn emit_store_op(&mut self, memarg: &MemoryImmediate, sz: Size, is_float: bool) {
+        let value = self.pop_value();
+        let target = self.pop_value();
+        let canonicalize = if is_float {
+            let fp = self.fp_stack.pop1().unwrap();
+            self.needs_canonicalization(&fp)
+        } else {
+            false
+        };
+        let value_size = match sz {
+            Size::S8 => 1,
+            Size::S16 => 2,
+            Size::S32 => 4,
+            Size::S64 => 8,
+        };
+        self.emit_memory_op(target, memarg, false, value_size, |this, addr| {
+            if canonicalize {
+                this.canonicalize_nan(sz, value, Location::GPR(GPR::X12));
+            } else {
+                this.emit_load(Size::S64, value, GPR::X12);
+            }
+            this.assembler.emit_str(sz, GPR::X12, addr, 0);
+        });
+    }
+
+    fn emit_atomic_load(&mut self, memarg: &MemoryImmediate, sz: Size) {
+        let target = self.pop_value();
+        let ret = self.push_value();
+        let value_size = 1 << (sz as usize);
+        self.emit_memory_op(target, memarg, true, value_size, |this, addr| {
+            this.assembler.emit_ldar(sz, GPR::X10, addr);
+            this.emit_store(Size::S64, GPR::X10, ret);
+        });
+    }
+
+    fn emit_atomic_store(&mut self, memarg: &MemoryImmediate, sz: Size) {
+        let value = self.pop_value();
+        let target = self.pop_value();
+        let value_size = 1 << (sz as usize);
+        self.emit_load(Size::S64, value, GPR::X12);
+        self.emit_memory_op(target, memarg, true, value_size, |this, addr| {
+            this.assembler.emit_stlr(sz, GPR::X12, addr);
+        });
+    }
+
+    /// Atomic read-modify-write. `op` is `None` for an exchange.
+    fn emit_atomic_rmw(&mut self, memarg: &MemoryImmediate, sz: Size, op: Option<BinOp>) {
+        let value = self.pop_value();
+        let target = self.pop_value();
+        let ret = self.push_value();
+        let value_size = 1 << (sz as usize);
+        self.emit_load(Size::S64, value, GPR::X12);
+        self.emit_memory_op(target, memarg, true, value_size, |this, addr| {
+            let retry = this.assembler.get_label();
+            this.assembler.emit_label(retry);
+            this.assembler.emit_ldaxr(sz, GPR::X10, addr);
+            match op {
+                Some(op) => this
+                    .assembler
+                    .emit_binop(op, Size::S64, GPR::X11, GPR::X10, GPR::X12),
+                None => this.assembler.emit_mov(Size::S64, GPR::X12, GPR::X11),
+            }
+            this.assembler.emit_stlxr(sz, GPR::X13, GPR::X11, addr);
+            this.assembler.emit_cbnz_label(Size::S32, GPR::X13, retry);
+            this.emit_store(Size::S64, GPR::X10, ret);
+        });
+    }
+
+    fn emit_atomic_cmpxchg(&mut self, memarg: &MemoryImmediate, sz: Size) {
+        let new = self.pop_value();
+        let expected = self.pop_value();
+        let target = self.pop_value();
+        let ret = self.push_value();
+        let value_size = 1 << (sz as usize);
+        self.emit_load(Size::S64, expected, GPR::X12);
+        // Only the low `sz` bytes of the expected value take part in the comparison.
+        if sz != Size::S64 {
+            let mask = (1u64 << (value_size * 8)) - 1;
+            self.assembler.emit_mov_imm(Size::S64, GPR::X13, mask);
+            self.assembler
+                .emit_binop(BinOp::And, Size::S64, GPR::X12, GPR::X12, GPR::X13);
+        }
+        self.emit_load(Size::S64, new, GPR::X13);
+        self.emit_memory_op(target, memarg, true, value_size, |this, addr| {
+            let retry = this.assembler.get_label();
+            let done = this.assembler.get_label();
+            this.assembler.emit_label(retry);
+            this.assembler.emit_ldaxr(sz, GPR::X10, addr);
+            this.assembler.emit_cmp(Size::S64, GPR::X10, GPR::X12);
+            this.assembler.emit_bcond_label(Condition::Ne, done);
+            this.assembler.emit_stlxr(sz, GPR::X11, GPR::X13, addr);
+            this.assembler.emit_cbnz_label(Size::S32, GPR::X11, retry);
+            this.assembler.emit_label(done);
+            this.emit_store(Size::S64, GPR::X10, ret);
+        });
+    }
+
+    /// Integer binary operation.
+    fn emit_binop_i(&mut self, sz: Size, op: BinOp) {
+        let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare();
+        self.emit_load(sz, loc_a, GPR::X9);
+        self.emit_load(sz, loc_b, GPR::X10);
+        self.assembler
+            .emit_binop(op, sz, GPR::X9, GPR::X9, GPR::X10);
+        self.emit_store(sz, GPR::X9, ret);
+    }
+
+    /// Integer comparison.
+    fn emit_cmpop_i(&mut self, sz: Size, cond: Condition) {
+        let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare();
+        self.emit_load(sz, loc_a, GPR::X9);
+        self.emit_load(sz, loc_b, GPR::X10);
+        self.assembler.emit_cmp(sz, GPR::X9, GPR::X10);
+        self.assembler.emit_cset(Size::S32, GPR::X9, cond);
+        self.emit_store(Size::S32, GPR::X9, ret);
+    }
+
+    /// Compares the value on top of the stack with zero.
+    fn emit_eqz(&mut self, sz: Size) {
+        let loc = self.pop_value();
+        let ret = self.push_value();
+        self.emit_load(sz, loc, GPR::X9);
+        self.assembler.emit_cmp_imm(sz, GPR::X9, 0);
+        self.assembler.emit_cset(Size::S32, GPR::X9, Condition::Eq);
+        self.emit_store(Size::S32, GPR::X9, ret);
+    }
+
+    /// Integer unary operation, with the input loaded with size `sz_src`.
+    fn emit_unop_i<F: FnOnce(&mut Assembler, GPR)>(&mut self, sz_src: Size, sz_dst: Size, f: F) {
+        let loc = self.pop_value();
+        let ret = self.push_value();
+        self.emit_load(sz_src, loc, GPR::X9);
+        f(&mut self.assembler, GPR::X9);
+        self.emit_store(sz_dst, GPR::X9, ret);
+    }
+
+    /// Integer division and remainder.
+    fn emit_div_i(&mut self, sz: Size, signed: bool, remainder: bool) {
+        let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare();
+        self.emit_load(sz, loc_a, GPR::X9);
+        self.emit_load(sz, loc_b, GPR::X10);
+        self.assembler
+            .emit_cbz_label(sz, GPR::X10, self.special_labels.integer_division_by_zero);
+        if signed && !remainder {
+            // INT_MIN / -1 overflows. For the remainder, `sdiv` gives INT_MIN and
+            // `msub` then yields the expected 0.
+            let no_overflow = self.assembler.get_label();
+            self.assembler.emit_mov_imm(sz, GPR::X11, u64::MAX);
+            self.assembler.emit_cmp(sz, GPR::X10, GPR::X11);
+            self.assembler.emit_bcond_label(Condition::Ne, no_overflow);
+            let min = match sz {
+                Size::S32 => i32::MIN as u32 as u64,
+                _ => i64::MIN as u64,
+            };
+            self.assembler.emit_mov_imm(sz, GPR::X11, min);
+            self.assembler.emit_cmp(sz, GPR::X9, GPR::X11);
+            self.assembler
+                .emit_bcond_label(Condition::Eq, self.special_labels.integer_overflow);
+            self.assembler.emit_label(no_overflow);
+        }
+        let op = if signed { BinOp::Sdiv } else { BinOp::Udiv };
+        self.assembler
+            .emit_binop(op, sz, GPR::X11, GPR::X9, GPR::X10);
+        if remainder {
+            self.assembler
+                .emit_msub(sz, GPR::X11, GPR::X11, GPR::X10, GPR::X9);
+        }
+        self.emit_store(sz, GPR::X11, ret);
+    }
+
+    /// Rotate left, as rotate right by the negated amount.
+    fn emit_rotl(&mut self, sz: Size) {
+        let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare();
+        self.emit_load(sz, loc_a, GPR::X9);
+        self.emit_load(sz, loc_b, GPR::X10);
+        self.assembler.emit_neg(sz, GPR::X10, GPR::X10);
+        self.assembler
+            .emit_binop(BinOp::Ror, sz, GPR::X9, GPR::X9, GPR::X10);
+        self.emit_store(sz, GPR::X9, ret);
+    }
+
+    /// Floating point binary operation.
+    fn emit_fp_binop(&mut self, sz: Size, op: FBinOp) {
+        let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare();
+        self.emit_load_neon(sz, loc_a, NEON::V0);
+        self.emit_load_neon(sz, loc_b, NEON::V1);
+        self.assembler
+            .emit_fbinop(op, sz, NEON::V0, NEON::V0, NEON::V1);
+        self.emit_store_neon(sz, NEON::V0, ret);
+    }
+
+    /// `min`/`max`. NaN results are always canonical.
+    fn emit_fp_minmax(&mut self, sz: Size, op: FBinOp) {
+        let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare();
+        self.emit_load_neon(sz, loc_a, NEON::V0);
+        self.emit_load_neon(sz, loc_b, NEON::V1);
+        self.assembler
+            .emit_fbinop(op, sz, NEON::V2, NEON::V0, NEON::V1);
+        let canonical = match sz {
+            Size::S32 => 0x7FC0_0000,
+            _ => 0x7FF8_0000_0000_0000,
+        };
+        self.emit_load_float_const(sz, canonical, NEON::V3);
+        self.assembler.emit_fcmp(sz, NEON::V0, NEON::V1);
+        self.assembler
+            .emit_fcsel(sz, NEON::V2, NEON::V3, NEON::V2, Condition::Vs);
+        self.emit_store_neon(sz, NEON::V2, ret);
+    }
+
+    /// Floating point unary operation.
+    fn emit_fp_unop(&mut self, sz: Size, op: FUnOp) {
+        let loc = self.pop_value();
+        let ret = self.push_value();
+        self.emit_load_neon(sz, loc, NEON::V0);
+        self.assembler.emit_funop(op, sz, NEON::V0, NEON::V0);
+        self.emit_store_neon(sz, NEON::V0, ret);
+    }
+
+    /// Floating point comparison.
+    fn emit_fp_cmpop(&mut self, sz: Size, cond: Condition) {
+        let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare();
+        self.emit_load_neon(sz, loc_a, NEON::V0);
+        self.emit_load_neon(sz, loc_b, NEON::V1);
+        self.assembler.emit_fcmp(sz, NEON::V0, NEON::V1);
+        self.assembler.emit_cset(Size::S32, GPR::X9, cond);
+        self.emit_store(Size::S32, GPR::X9, ret);
+    }
+
+    /// Bitwise operation on the sign bit of a float, which preserves its payload.
+    fn emit_fp_sign_op(&mut self, sz: Size, op: BinOp, mask: u64) {
+        let loc = self.pop_value();
+        let ret = self.push_value();
+        self.emit_load(sz, loc, GPR::X9);
+        self.assembler.emit_mov_imm(sz, GPR::X10, mask);
+        self.assembler
+            .emit_binop(op, sz, GPR::X9, GPR::X9, GPR::X10);
+        self.emit_store(sz, GPR::X9, ret);
+    }
+
+    fn emit_fp_copysign(&mut self, sz: Size) {
+        let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare();
+        let (fp_src1, fp_src2) = self.fp_stack.pop2().unwrap();
+        self.fp_stack
+            .push(FloatValue::new(self.value_stack.len() - 1));
+
+        if self.needs_canonicalization(&fp_src1) {
+            self.canonicalize_nan(sz, loc_a, Location::GPR(GPR::X9));
+        } else {
+            self.emit_load(sz, loc_a, GPR::X9);
+        }
+        if self.needs_canonicalization(&fp_src2) {
+            self.canonicalize_nan(sz, loc_b, Location::GPR(GPR::X10));
+        } else {
+            self.emit_load(sz, loc_b, GPR::X10);
+        }
+        let (sign_mask, value_mask) = match sz {
+            Size::S32 => (0x8000_0000, 0x7fff_ffff),
+            _ => (0x8000_0000_0000_0000, 0x7fff_ffff_ffff_ffff),
+        };
+        self.assembler.emit_mov_imm(sz, GPR::X11, sign_mask);
+        self.assembler
+            .emit_binop(BinOp::And, sz, GPR::X10, GPR::X10, GPR::X11);
+        self.assembler.emit_mov_imm(sz, GPR::X11, value_mask);
+        self.assembler
+            .emit_binop(BinOp::And, sz, GPR::X9, GPR::X9, GPR::X11);
+        self.assembler
+            .emit_binop(BinOp::Orr, sz, GPR::X9, GPR::X9, GPR::X10);
+        self.emit_store(sz, GPR::X9, ret);
+    }
+
+    /// Float to integer conversion, trapping on NaN and on out of range inputs.
+    fn emit_trunc(&mut self, sz_fp: Size, sz_int: Size, signed: bool) {
+        let loc = self.pop_value();
+        let ret = self.push_value();
+        self.fp_stack.pop1().unwrap();
+
+        let (lower, upper): (u64, u64) = match (sz_fp, sz_int, signed) {
+            (Size::S32, Size::S32, true) => (
+                GEF32_LT_I32_MIN.to_bits() as u64,
+                LEF32_GT_I32_MAX.to_bits() as u64,
+            ),
+            (Size::S32, Size::S32, false) => (
+                GEF32_LT_U32_MIN.to_bits() as u64,
+                LEF32_GT_U32_MAX.to_bits() as u64,
+            ),
+            (Size::S32, Size::S64, true) => (
+                GEF32_LT_I64_MIN.to_bits() as u64,
+                LEF32_GT_I64_MAX.to_bits() as u64,
+            ),
+            (Size::S32, Size::S64, false) => (
+                GEF32_LT_U64_MIN.to_bits() as u64,
+                LEF32_GT_U64_MAX.to_bits() as u64,
+            ),
+            (Size::S64, Size::S32, true) => {
+                (GEF64_LT_I32_MIN.to_bits(), LEF64_GT_I32_MAX.to_bits())
+            }
+            (Size::S64, Size::S32, false) => {
+                (GEF64_LT_U32_MIN.to_bits(), LEF64_GT_U32_MAX.to_bits())
+            }
+            (Size::S64, Size::S64, true) => {
+                (GEF64_LT_I64_MIN.to_bits(), LEF64_GT_I64_MAX.to_bits())
+            }
+            (Size::S64, Size::S64, false) => {
+                (GEF64_LT_U64_MIN.to_bits(), LEF64_GT_U64_MAX.to_bits())
+            }
+            _ => unreachable!(),
+        };
+
+        self.emit_load_neon(sz_fp, loc, NEON::V0);
+
+        // NaN
+        self.assembler.emit_fcmp(sz_fp, NEON::V0, NEON::V0);
+        self.assembler
+            .emit_bcond_label(Condition::Vs, self.special_labels.bad_conversion_to_integer);
+
+        // x <= lower_bound
+        self.emit_load_float_const(sz_fp, lower, NEON::V1);
+        self.assembler.emit_fcmp(sz_fp, NEON::V0, NEON::V1);
+        self.assembler
+            .emit_bcond_label(Condition::Ls, self.special_labels.integer_overflow);
+
+        // x >= upper_bound
+        self.emit_load_float_const(sz_fp, upper, NEON::V1);
+        self.assembler.emit_fcmp(sz_fp, NEON::V0, NEON::V1);
+        self.assembler
+            .emit_bcond_label(Condition::Ge, self.special_labels.integer_overflow);
+
+        if signed {
+            self.assembler.emit_fcvtzs(sz_fp, sz_int, GPR::X9, NEON::V0);
+        } else {
+            self.assembler.emit_fcvtzu(sz_fp, sz_int, GPR::X9, NEON::V0);
+        }
+        self.emit_store(sz_int, GPR::X9, ret);
+    }
+
+    /// Saturating float to integer conversion. `fcvtz{s,u}` already have the
+    /// saturating semantics required by WebAssembly, NaN included.
+    fn emit_trunc_sat(&mut self, sz_fp: Size, sz_int: Size, signed: bool) {
+        let loc = self.pop_value();
+        let ret = self.push_value();
+        self.fp_stack.pop1().unwrap();
+        self.emit_load_neon(sz_fp, loc, NEON::V0);
+        if signed {
+            self.assembler.emit_fcvtzs(sz_fp, sz_int, GPR::X9, NEON::V0);
+        } else {
+            self.assembler.emit_fcvtzu(sz_fp, sz_int, GPR::X9, NEON::V0);
+        }
+        self.emit_store(sz_int, GPR::X9, ret);
+    }
+
+    /// Integer to float conversion.
+    fn emit_convert(&mut self, sz_int: Size, sz_fp: Size, signed: bool) {
+        let loc = self.pop_value();
+        let ret = self.push_value();
+        self.fp_stack
+            .push(FloatValue::new(self.value_stack.len() - 1));
+        self.emit_load(sz_int, loc, GPR::X9);
+        if signed {
+            self.assembler.emit_scvtf(sz_int, sz_fp, NEON::V0, GPR::X9);
+        } else {
+            self.assembler.emit_ucvtf(sz_int, sz_fp, NEON::V0, GPR::X9);
+        }
+        self.emit_store_neon(sz_fp, NEON::V0, ret);
+    }
+
+    fn emit_stack_check(&mut self, enter: bool, depth: usize) {
+        let offset = self.vmoffsets.vmctx_stack_limit_begin() as i32;
+        self.assembler.emit_ldr(Size::S32, GPR::X9, VMCTX, offset);
+        if enter {
+            // Here we must use value we do not yet know, so we emit a placeholder and patch it later.
+            self.stack_check_offset = self.assembler.get_offset();
+            self.assembler.emit_movz(GPR::X10, 0, 0);
+            self.assembler.emit_movk(GPR::X10, 0, 16);
+            self.assembler
+                .emit_binop(BinOp::Subs, Size::S32, GPR::X9, GPR::X9, GPR::X10);
+            self.assembler.emit_str(Size::S32, GPR::X9, VMCTX, offset);
+            self.assembler
+                .emit_bcond_label(Condition::Mi, self.special_labels.stack_overflow);
+        } else {
+            // Patch earlier stack checker with now known max stack depth.
+            assert!(self.stack_check_offset.0 > 0);
+            patch_mov_imm32(
+                &mut self.assembler,
+                self.stack_check_offset,
+                GPR::X10,
+                depth as u32,
+            );
+            for offset in self.tail_call_stack_check_offsets.iter() {
+                patch_mov_imm32(&mut self.assembler, *offset, GPR::X10, depth as u32);
+            }
+            self.assembler
+                .emit_mov_imm(Size::S32, GPR::X10, depth as u64);
+            self.assembler
+                .emit_binop(BinOp::Add, Size::S32, GPR::X9, GPR::X9, GPR::X10);
+            self.assembler.emit_str(Size::S32, GPR::X9, VMCTX, offset);
+        }
+    }
+
+    fn emit_function_stack_check(&mut self, enter: bool) {
+        // `local_types` include parameters as well.
+        let depth = self.local_types.len()
+            + self.max_stack_depth
+            // the stack argument slots reserved by the calls the function makes
+            + RESERVED_STACK_ARGS
+            // we add 4 to ensure that deep recursion is prohibited even for local and argument free
+            // functions, as they still use stack space for the saved frame base and return address,
+            // along with spill area for callee-saved registers.
+            + 4;
+        self.emit_stack_check(enter, depth);
+    }
+
+    /// Traps if the epoch of the engine has reached the deadline of the instance.
+    ///
+    /// Clobbers `x9` and `x10`.
+    fn emit_epoch_check(&mut self) {
+        if !self.config.enable_epoch_interruption {
+            return;
+        }
+        let (epoch, deadline) = (GPR::X9, GPR::X10);
+        self.assembler.emit_ldr(
+            Size::S64,
+            epoch,
+            VMCTX,
+            self.vmoffsets.vmctx_epoch_pointer() as i32,
+        );
+        self.assembler.emit_ldr(Size::S64, epoch, epoch, 0);
+        self.assembler.emit_ldr(
+            Size::S64,
+            deadline,
+            VMCTX,
+            self.vmoffsets.vmctx_epoch_deadline() as i32,
+        );
+        self.assembler.emit_cmp(Size::S64, deadline, epoch);
+        self.assembler
+            .emit_bcond_label(Condition::Ls, self.special_labels.interrupted);
+    }
+
+    fn emit_head(&mut self) -> Result<(), CodegenError> {
+        self.assembler.emit_push_pair(FP, GPR::X30);
+        self.assembler.emit_mov_from_sp(FP);
+        self.assembler.emit_push_pair(GPR::X19, VMCTX);
+        self.assembler.emit_mov(Size::S64, GPR::X0, VMCTX);
+
+        // The frame size depends on the max stack depth, so it's patched at the end.
+        self.frame_size_offset = self.assembler.get_offset();
+        self.assembler.emit_movz(GPR::X16, 0, 0);
+        self.assembler.emit_movk(GPR::X16, 0, 16);
+        self.assembler.emit_sub_sp(GPR::X16);
+
+        // Initialize locals.
+        let n_params = self.signature.params().len();
+        for i in 0..n_params {
+            let loc = self.local_location(i);
+            match incoming_arg_location(i + 1) {
+                Location::GPR(gpr) => self.emit_store(Size::S64, gpr, loc),
+                src => self.emit_move(Size::S64, src, loc),
+            }
+        }
+        for i in n_params..self.local_types.len() {
+            let loc = self.local_location(i);
+            self.emit_store(Size::S64, GPR::XzrSp, loc);
+        }
+        if let Some(return_pointer) = self.return_pointer {
+            self.emit_store(Size::S64, GPR::X8, return_pointer);
+        }
+
+        self.emit_function_stack_check(true);
+        self.emit_epoch_check();
+
+        self.control_stack.push(ControlFrame {
+            label: self.assembler.get_label(),
+            loop_like: false,
+            if_else: IfElseState::None,
+            returns: self
+                .signature
+                .results()
+                .iter()
+                .map(|&x| type_to_wp_type(x))
+                .collect(),
+            value_stack_depth: 0,
+            fp_stack_depth: 0,
+            params: smallvec![],
+            loop_params: None,
+            try_state: None,
+        });
+
+        Ok(())
+    }
+
+    /// Pushes the instruction to the address map, calculating the offset from a
+    /// provided beginning address.
+    fn mark_instruction_address_end(&mut self, begin: usize) {
+        self.instructions_address_map.push(InstructionAddressMap {
+            srcloc: SourceLoc::new(self.src_loc),
+            code_offset: begin,
+            code_len: self.assembler.get_offset().0 - begin,
+        });
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        module: &'a ModuleInfo,
+        module_translation_state: &'a ModuleTranslationState,
+        config: &'a Singlepass,
+        vmoffsets: &'a VMOffsets,
+        _table_styles: &'a PrimaryMap<TableIndex, TableStyle>,
+        local_func_index: LocalFunctionIndex,
+        local_types_excluding_arguments: &[WpType],
+        calling_convention: CallingConvention,
+        exceptions: bool,
+    ) -> Result<FuncGen<'a>, CodegenError> {
+        if calling_convention != CallingConvention::SystemV {
+            return Err(CodegenError {
+                message: format!(
+                    "unsupported calling convention for aarch64: {:?}",
+                    calling_convention
+                ),
+            });
+        }
+        let func_index = module.func_index(local_func_index);
+        let sig_index = module.functions[func_index];
+        let signature = module.signatures[sig_index].clone();
+
+        let mut local_types: Vec<_> = signature
+            .params()
+            .iter()
+            .map(|&x| type_to_wp_type(x))
+            .collect();
+        local_types.extend_from_slice(local_types_excluding_arguments);
+        if exceptions {
+            // The caught exceptions, allocated by the first catch clause.
+            local_types.push(WpType::I64);
+        }
+        let return_layout = ReturnLayout::new(signature.results());
+        let returns_in_memory =
+            matches!(&return_layout, Some(layout) if layout.registers.is_none());
+        if returns_in_memory {
+            // The pointer to the memory the results are returned in.
+            local_types.push(WpType::I64);
+        }
+
+        let mut assembler = Assembler::new(0);
+        let special_labels = SpecialLabelSet {
+            integer_division_by_zero: assembler.get_label(),
+            integer_overflow: assembler.get_label(),
+            bad_conversion_to_integer: assembler.get_label(),
+            heap_access_oob: assembler.get_label(),
+            table_access_oob: assembler.get_label(),
+            indirect_call_null: assembler.get_label(),
+            bad_signature: assembler.get_label(),
+            gas_limit_exceeded: assembler.get_label(),
+            stack_overflow: assembler.get_label(),
+            interrupted: assembler.get_label(),
+        };
+
+        let mut fg = FuncGen {
+            module,
+            module_translation_state,
+            config,
+            vmoffsets,
+            signature,
+            assembler,
+            local_types,
+            value_stack: vec![],
+            max_stack_depth: 0,
+            max_value_slots: 0,
+            stack_check_offset: AssemblyOffset(0),
+            tail_call_stack_check_offsets: vec![],
+            frame_size_offset: AssemblyOffset(0),
+            fp_stack: vec![],
+            control_stack: vec![],
+            unreachable_depth: 0,
+            relocations: vec![],
+            special_labels,
+            src_loc: 0,
+            instructions_address_map: vec![],
+            caught_exceptions: None,
+            try_count: 0,
+            return_layout,
+            return_pointer: None,
+        };
+        if returns_in_memory {
+            fg.return_pointer = Some(fg.local_location(fg.local_types.len() - 1));
+        }
+        fg.emit_head()?;
+        if exceptions {
+            // Right before the return pointer, if any.
+            let index = fg.local_types.len() - 1 - usize::from(returns_in_memory);
+            fg.caught_exceptions = Some(fg.local_location(index));
+        }
+        Ok(fg)
+    }
+
+    pub(crate) fn has_control_frames(&self) -> bool {
+        !self.control_stack.is_empty()
+    }
+
+    /// Returns the parameter and result types of a block of type `ty`.
+    fn block_signature(
+        &self,
+        ty: WpTypeOrFuncType,
+    ) -> (SmallVec<[WpType; 1]>, SmallVec<[WpType; 1]>) {
+        match ty {
+            WpTypeOrFuncType::Type(WpType::EmptyBlockType) => (smallvec![], smallvec![]),
+            WpTypeOrFuncType::Type(inner_ty) => (smallvec![], smallvec![inner_ty]),
+            WpTypeOrFuncType::FuncType(index) => {
+                let sig = &self.module.signatures[SignatureIndex::from_u32(index)];
+                (
+                    sig.params().iter().cloned().map(type_to_wp_type).collect(),
+                    sig.results().iter().cloned().map(type_to_wp_type).collect(),
+                )
+            }
+        }
+    }
+
+    /// Ends the `then` branch of the innermost `if` and starts its `else`
+    /// branch.
+    fn emit_else(&mut self, was_unreachable: bool) -> Result<(), CodegenError> {
+        if !was_unreachable {
+            self.emit_block_results(self.control_stack.len() - 1)?;
+        }
+
+        self.update_max_stack_depth();
+
+        // The `else` branch gets the parameters the `then` branch got
+        // copies of.
+        let frame = self.control_stack.last().unwrap();
+        let depth = frame.value_stack_depth + frame.params.len();
+        self.value_stack.truncate(depth);
+        self.fp_stack.truncate(self.fp_stack_depth_at(depth));
+
+        let frame = self.control_stack.last_mut().unwrap();
+        match frame.if_else {
+            IfElseState::If(label) => {
+                let end = frame.label;
+                frame.if_else = IfElseState::Else;
+                self.assembler.emit_b_label(end);
+                self.assembler.emit_label(label);
+                Ok(())
+            }
+            _ => Err(CodegenError {
+                message: "Else: frame.if_else unreachable code".to_string(),
+            }),
+        }
+    }
+
+    /// Loads the base and the number of elements of table `table_index` into
+    /// `base` and `count`.
+    fn emit_table_base_and_count(&mut self, table_index: TableIndex, base: GPR, count: GPR) {
+        if let Some(local_table_index) = self.module.local_table_index(table_index) {
+            let (vmctx_offset_base, vmctx_offset_len) = (
+                self.vmoffsets.vmctx_vmtable_definition(local_table_index),
+                self.vmoffsets
+                    .vmctx_vmtable_definition_current_elements(local_table_index),
+            );
+            self.assembler
+                .emit_ldr(Size::S64, base, VMCTX, vmctx_offset_base as i32);
+            self.assembler
+                .emit_ldr(Size::S32, count, VMCTX, vmctx_offset_len as i32);
+        } else {
+            // Do an indirection.
+            let import_offset = self.vmoffsets.vmctx_vmtable_import(table_index);
+            self.assembler
+                .emit_ldr(Size::S64, base, VMCTX, import_offset as i32);
+            self.assembler.emit_ldr(
+                Size::S32,
+                count,
+                base,
+                self.vmoffsets.vmtable_definition_current_elements() as i32,
+            );
+            self.assembler.emit_ldr(
+                Size::S64,
+                base,
+                base,
+                self.vmoffsets.vmtable_definition_base() as i32,
+            );
+        }
+    }
+
+    pub(crate) fn feed_operator(&mut self, op: Operator) -> Result<(), CodegenError> {
+        assert!(self.fp_stack.len() <= self.value_stack.len());
+
+        let was_unreachable;
+
+        if self.unreachable_depth > 0 {
+            was_unreachable = true;
+
+            match op {
+                Operator::Block { .. }
+                | Operator::Loop { .. }
+                | Operator::If { .. }
+                | Operator::Try { .. } => {
+                    self.unreachable_depth += 1;
+                }
+                Operator::End | Operator::Delegate { .. } => {
+                    self.unreachable_depth -= 1;
+                }
+                Operator::Else => {
+                    // We are in a reachable true branch
+                    if self.unreachable_depth == 1 {
+                        if let Some(IfElseState::If(_)) =
+                            self.control_stack.last().map(|x| x.if_else)
+                        {
+                            self.unreachable_depth -= 1;
+                        }
+                    }
+                }
+                // We are in a reachable catch clause
+                Operator::Catch { .. } | Operator::CatchAll
+                    if self.unreachable_depth == 1
+                        && self.control_stack.last().unwrap().try_state.is_some() =>
+                {
+                    self.unreachable_depth -= 1;
+                }
+                _ => {}
+            }
+            if self.unreachable_depth > 0 {
+                return Ok(());
+            }
+        } else {
+            was_unreachable = false;
+        }
+
+        match op {
+            Operator::GlobalGet { global_index } => {
+                let global_index = GlobalIndex::from_u32(global_index);
+
+                let ty = type_to_wp_type(self.module.globals[global_index].ty);
+                if ty.is_float() {
+                    self.fp_stack.push(FloatValue::new(self.value_stack.len()));
+                }
+                let loc = self.push_value();
+                let offset = if let Some(local_global_index) =
+                    self.module.local_global_index(global_index)
+                {
+                    self.vmoffsets.vmctx_vmglobal_definition(local_global_index)
+                } else {
+                    // Imported globals require one level of indirection.
+                    self.vmoffsets
+                        .vmctx_vmglobal_import_definition(global_index)
+                };
+                self.assembler
+                    .emit_ldr(Size::S64, GPR::X9, VMCTX, offset as i32);
+                self.assembler.emit_ldr(Size::S64, GPR::X9, GPR::X9, 0);
+                self.emit_store(Size::S64, GPR::X9, loc);
+            }
+            Operator::GlobalSet { global_index } => {
+                let global_index = GlobalIndex::from_u32(global_index);
+                let offset = if let Some(local_global_index) =
+                    self.module.local_global_index(global_index)
+                {
+                    self.vmoffsets.vmctx_vmglobal_definition(local_global_index)
+                } else {
+                    // Imported globals require one level of indirection.
+                    self.vmoffsets
+                        .vmctx_vmglobal_import_definition(global_index)
+                };
+                let ty = type_to_wp_type(self.module.globals[global_index].ty);
+                let loc = self.pop_value();
+                let canonicalize = if ty.is_float() {
+                    let fp = self.fp_stack.pop1()?;
+                    self.needs_canonicalization(&fp)
+                } else {
+                    false
+                };
+                if canonicalize {
+                    self.canonicalize_nan(float_size(ty), loc, Location::GPR(GPR::X10));
+                } else {
+                    self.emit_load(Size::S64, loc, GPR::X10);
+                }
+                self.assembler
+                    .emit_ldr(Size::S64, GPR::X9, VMCTX, offset as i32);
+                self.assembler.emit_str(Size::S64, GPR::X10, GPR::X9, 0);
+            }
+            Operator::LocalGet { local_index } => {
+                let local_index = local_index as usize;
+                let ret = self.push_value();
+                let local = self.local_location(local_index);
+                self.emit_move(Size::S64, local, ret);
+                if self.local_types[local_index].is_float() {
+                    self.fp_stack
+                        .push(FloatValue::new(self.value_stack.len() - 1));
+                }
+            }
+            Operator::LocalSet { local_index } => {
+                let local_index = local_index as usize;
+                let loc = self.pop_value();
+                let local = self.local_location(local_index);
+                let ty = self.local_types[local_index];
+
+                if ty.is_float() {
+                    let fp = self.fp_stack.pop1()?;
+                    if self.needs_canonicalization(&fp) {
+                        self.canonicalize_nan(float_size(ty), loc, local);
+                        return Ok(());
+                    }
+                }
+                self.emit_move(Size::S64, loc, local);
+            }
+            Operator::LocalTee { local_index } => {
+                let local_index = local_index as usize;
+                let loc = *self.value_stack.last().unwrap();
+                let local = self.local_location(local_index);
+                let ty = self.local_types[local_index];
+
+                if ty.is_float() {
+                    let fp = *self.fp_stack.peek1()?;
+                    if self.needs_canonicalization(&fp) {
+                        self.canonicalize_nan(float_size(ty), loc, local);
+                        return Ok(());
+                    }
+                }
+                self.emit_move(Size::S64, loc, local);
+            }
+            Operator::I32Const { value } => {
+                self.value_stack.push(Location::Imm32(value as u32));
+            }
+            Operator::I32Add => self.emit_binop_i(Size::S32, BinOp::Add),
+            Operator::I32Sub => self.emit_binop_i(Size::S32, BinOp::Sub),
+            Operator::I32Mul => self.emit_binop_i(Size::S32, BinOp::Mul),
+            Operator::I32DivU => self.emit_div_i(Size::S32, false, false),
+            Operator::I32DivS => self.emit_div_i(Size::S32, true, false),
+            Operator::I32RemU => self.emit_div_i(Size::S32, false, true),
+            Operator::I32RemS => self.emit_div_i(Size::S32, true, true),
+            Operator::I32And => self.emit_binop_i(Size::S32, BinOp::And),
+            Operator::I32Or => self.emit_binop_i(Size::S32, BinOp::Orr),
+            Operator::I32Xor => self.emit_binop_i(Size::S32, BinOp::Eor),
+            Operator::I32Eq => self.emit_cmpop_i(Size::S32, Condition::Eq),
+            Operator::I32Ne => self.emit_cmpop_i(Size::S32, Condition::Ne),
+            Operator::I32Eqz => self.emit_eqz(Size::S32),
+            Operator::I32Clz => {
+                self.emit_unop_i(Size::S32, Size::S32, |a, r| a.emit_clz(Size::S32, r, r))
+            }
+            Operator::I32Ctz => self.emit_unop_i(Size::S32, Size::S32, |a, r| {
+                a.emit_rbit(Size::S32, r, r);
+                a.emit_clz(Size::S32, r, r);
+            }),
+            Operator::I32Popcnt => self.emit_unop_i(Size::S32, Size::S32, |a, r| {
+                a.emit_popcnt(Size::S32, r, r, NEON::V0)
+            }),
+            Operator::I32Shl => self.emit_binop_i(Size::S32, BinOp::Lsl),
+            Operator::I32ShrU => self.emit_binop_i(Size::S32, BinOp::Lsr),
+            Operator::I32ShrS => self.emit_binop_i(Size::S32, BinOp::Asr),
+            Operator::I32Rotl => self.emit_rotl(Size::S32),
+            Operator::I32Rotr => self.emit_binop_i(Size::S32, BinOp::Ror),
+            Operator::I32LtU => self.emit_cmpop_i(Size::S32, Condition::Cc),
+            Operator::I32LeU => self.emit_cmpop_i(Size::S32, Condition::Ls),
+            Operator::I32GtU => self.emit_cmpop_i(Size::S32, Condition::Hi),
+            Operator::I32GeU => self.emit_cmpop_i(Size::S32, Condition::Cs),
+            Operator::I32LtS => self.emit_cmpop_i(Size::S32, Condition::Lt),
+            Operator::I32LeS => self.emit_cmpop_i(Size::S32, Condition::Le),
+            Operator::I32GtS => self.emit_cmpop_i(Size::S32, Condition::Gt),
+            Operator::I32GeS => self.emit_cmpop_i(Size::S32, Condition::Ge),
+            Operator::I64Const { value } => {
+                self.value_stack.push(Location::Imm64(value as u64));
+            }
+            Operator::I64Add => self.emit_binop_i(Size::S64, BinOp::Add),
+            Operator::I64Sub => self.emit_binop_i(Size::S64, BinOp::Sub),
+            Operator::I64Mul => self.emit_binop_i(Size::S64, BinOp::Mul),
+            Operator::I64DivU => self.emit_div_i(Size::S64, false, false),
+            Operator::I64DivS => self.emit_div_i(Size::S64, true, false),
+            Operator::I64RemU => self.emit_div_i(Size::S64, false, true),
+            Operator::I64RemS => self.emit_div_i(Size::S64, true, true),
+            Operator::I64And => self.emit_binop_i(Size::S64, BinOp::And),
+            Operator::I64Or => self.emit_binop_i(Size::S64, BinOp::Orr),
+            Operator::I64Xor => self.emit_binop_i(Size::S64, BinOp::Eor),
+            Operator::I64Eq => self.emit_cmpop_i(Size::S64, Condition::Eq),
+            Operator::I64Ne => self.emit_cmpop_i(Size::S64, Condition::Ne),
+            Operator::I64Eqz => self.emit_eqz(Size::S64),
+            Operator::I64Clz => {
+                self.emit_unop_i(Size::S64, Size::S64, |a, r| a.emit_clz(Size::S64, r, r))
+            }
+            Operator::I64Ctz => self.emit_unop_i(Size::S64, Size::S64, |a, r| {
+                a.emit_rbit(Size::S64, r, r);
+                a.emit_clz(Size::S64, r, r);
+            }),
+            Operator::I64Popcnt => self.emit_unop_i(Size::S64, Size::S64, |a, r| {
+                a.emit_popcnt(Size::S64, r, r, NEON::V0)
+            }),
+            Operator::I64Shl => self.emit_binop_i(Size::S64, BinOp::Lsl),
+            Operator::I64ShrU => self.emit_binop_i(Size::S64, BinOp::Lsr),
+            Operator::I64ShrS => self.emit_binop_i(Size::S64, BinOp::Asr),
+            Operator::I64Rotl => self.emit_rotl(Size::S64),
+            Operator::I64Rotr => self.emit_binop_i(Size::S64, BinOp::Ror),
+            Operator::I64LtU => self.emit_cmpop_i(Size::S64, Condition::Cc),
+            Operator::I64LeU => self.emit_cmpop_i(Size::S64, Condition::Ls),
+            Operator::I64GtU => self.emit_cmpop_i(Size::S64, Condition::Hi),
+            Operator::I64GeU => self.emit_cmpop_i(Size::S64, Condition::Cs),
+            Operator::I64LtS => self.emit_cmpop_i(Size::S64, Condition::Lt),
+            Operator::I64LeS => self.emit_cmpop_i(Size::S64, Condition::Le),
+            Operator::I64GtS => self.emit_cmpop_i(Size::S64, Condition::Gt),
+            Operator::I64GeS => self.emit_cmpop_i(Size::S64, Condition::Ge),
+            Operator::I64ExtendI32U => self.emit_unop_i(Size::S32, Size::S64, |_, _| {}),
+            Operator::I64ExtendI32S => self.emit_unop_i(Size::S32, Size::S64, |a, r| {
+                a.emit_sxt(Size::S32, Size::S64, r, r)
+            }),
+            Operator::I32Extend8S => self.emit_unop_i(Size::S32, Size::S32, |a, r| {
+                a.emit_sxt(Size::S8, Size::S32, r, r)
+            }),
+            Operator::I32Extend16S => self.emit_unop_i(Size::S32, Size::S32, |a, r| {
+                a.emit_sxt(Size::S16, Size::S32, r, r)
+            }),
+            Operator::I64Extend8S => self.emit_unop_i(Size::S64, Size::S64, |a, r| {
+                a.emit_sxt(Size::S8, Size::S64, r, r)
+            }),
+            Operator::I64Extend16S => self.emit_unop_i(Size::S64, Size::S64, |a, r| {
+                a.emit_sxt(Size::S16, Size::S64, r, r)
+            }),
+            Operator::I64Extend32S => self.emit_unop_i(Size::S64, Size::S64, |a, r| {
+                a.emit_sxt(Size::S32, Size::S64, r, r)
+            }),
+            Operator::I32WrapI64 => self.emit_unop_i(Size::S32, Size::S32, |_, _| {}),
+
+            Operator::F32Const { value } => {
+                self.value_stack.push(Location::Imm32(value.bits()));
+                self.fp_stack
+                    .push(FloatValue::new(self.value_stack.len() - 1));
+            }
+            Operator::F32Add
+            | Operator::F32Sub
+            | Operator::F32Mul
+            | Operator::F32Div
+            | Operator::F64Add
+            | Operator::F64Sub
+            | Operator::F64Mul
+            | Operator::F64Div => {
+                let (sz, fbinop) = match op {
+                    Operator::F32Add => (Size::S32, FBinOp::Add),
+                    Operator::F32Sub => (Size::S32, FBinOp::Sub),
+                    Operator::F32Mul => (Size::S32, FBinOp::Mul),
+                    Operator::F32Div => (Size::S32, FBinOp::Div),
+                    Operator::F64Add => (Size::S64, FBinOp::Add),
+                    Operator::F64Sub => (Size::S64, FBinOp::Sub),
+                    Operator::F64Mul => (Size::S64, FBinOp::Mul),
+                    _ => (Size::S64, FBinOp::Div),
+                };
+                self.fp_stack.pop2()?;
+                let depth = self.value_stack.len() - 2;
+                self.fp_stack.push(match sz {
+                    Size::S32 => FloatValue::cncl_f32(depth),
+                    _ => FloatValue::cncl_f64(depth),
+                });
+                self.emit_fp_binop(sz, fbinop);
+            }
+            Operator::F32Max | Operator::F32Min | Operator::F64Max | Operator::F64Min => {
+                let (sz, fbinop) = match op {
+                    Operator::F32Max => (Size::S32, FBinOp::Max),
+                    Operator::F32Min => (Size::S32, FBinOp::Min),
+                    Operator::F64Max => (Size::S64, FBinOp::Max),
+                    _ => (Size::S64, FBinOp::Min),
+                };
+                self.fp_stack.pop2()?;
+                self.fp_stack
+                    .push(FloatValue::new(self.value_stack.len() - 2));
+                self.emit_fp_minmax(sz, fbinop);
+            }
+            Operator::F32Eq => {
+                self.fp_stack.pop2()?;
+                self.emit_fp_cmpop(Size::S32, Condition::Eq)
+            }
+            Operator::F32Ne => {
+                self.fp_stack.pop2()?;
+                self.emit_fp_cmpop(Size::S32, Condition::Ne)
+            }
+            Operator::F32Lt => {
+                self.fp_stack.pop2()?;
+                self.emit_fp_cmpop(Size::S32, Condition::Mi)
+            }
+            Operator::F32Le => {
+                self.fp_stack.pop2()?;
+                self.emit_fp_cmpop(Size::S32, Condition::Ls)
+            }
+            Operator::F32Gt => {
+                self.fp_stack.pop2()?;
+                self.emit_fp_cmpop(Size::S32, Condition::Gt)
+            }
+            Operator::F32Ge => {
+                self.fp_stack.pop2()?;
+                self.emit_fp_cmpop(Size::S32, Condition::Ge)
+            }
+            Operator::F32Nearest
+            | Operator::F32Floor
+            | Operator::F32Ceil
+            | Operator::F32Trunc
+            | Operator::F32Sqrt
+            | Operator::F64Nearest
+            | Operator::F64Floor
+            | Operator::F64Ceil
+            | Operator::F64Trunc
+            | Operator::F64Sqrt => {
+                let (sz, funop) = match op {
+                    Operator::F32Nearest => (Size::S32, FUnOp::Nearest),
+                    Operator::F32Floor => (Size::S32, FUnOp::Floor),
+                    Operator::F32Ceil => (Size::S32, FUnOp::Ceil),
+                    Operator::F32Trunc => (Size::S32, FUnOp::Trunc),
+                    Operator::F32Sqrt => (Size::S32, FUnOp::Sqrt),
+                    Operator::F64Nearest => (Size::S64, FUnOp::Nearest),
+                    Operator::F64Floor => (Size::S64, FUnOp::Floor),
+                    Operator::F64Ceil => (Size::S64, FUnOp::Ceil),
+                    Operator::F64Trunc => (Size::S64, FUnOp::Trunc),
+                    _ => (Size::S64, FUnOp::Sqrt),
+                };
+                self.fp_stack.pop1()?;
+                let depth = self.value_stack.len() - 1;
+                self.fp_stack.push(match sz {
+                    Size::S32 => FloatValue::cncl_f32(depth),
+                    _ => FloatValue::cncl_f64(depth),
+                });
+                self.emit_fp_unop(sz, funop);
+            }
+            Operator::F32Copysign => self.emit_fp_copysign(Size::S32),
+            Operator::F32Abs => {
+                // Preserve canonicalization state.
+                self.emit_fp_sign_op(Size::S32, BinOp::And, 0x7fff_ffff)
+            }
+            Operator::F32Neg => {
+                // Preserve canonicalization state.
+                self.emit_fp_sign_op(Size::S32, BinOp::Eor, 0x8000_0000)
+            }
+
+            Operator::F64Const { value } => {
+                self.value_stack.push(Location::Imm64(value.bits()));
+                self.fp_stack
+                    .push(FloatValue::new(self.value_stack.len() - 1));
+            }
+            Operator::F64Eq => {
+                self.fp_stack.pop2()?;
+                self.emit_fp_cmpop(Size::S64, Condition::Eq)
+            }
+            Operator::F64Ne => {
+                self.fp_stack.pop2()?;
+                self.emit_fp_cmpop(Size::S64, Condition::Ne)
+            }
+            Operator::F64Lt => {
+                self.fp_stack.pop2()?;
+                self.emit_fp_cmpop(Size::S64, Condition::Mi)
+            }
+            Operator::F64Le => {
+                self.fp_stack.pop2()?;
+                self.emit_fp_cmpop(Size::S64, Condition::Ls)
+            }
+            Operator::F64Gt => {
+                self.fp_stack.pop2()?;
+                self.emit_fp_cmpop(Size::S64, Condition::Gt)
+            }
+            Operator::F64Ge => {
+                self.fp_stack.pop2()?;
+                self.emit_fp_cmpop(Size::S64, Condition::Ge)
+            }
+            Operator::F64Copysign => self.emit_fp_copysign(Size::S64),
+            Operator::F64Abs => {
+                // Preserve canonicalization state.
+                self.emit_fp_sign_op(Size::S64, BinOp::And, 0x7fff_ffff_ffff_ffff)
+            }
+            Operator::F64Neg => {
+                // Preserve canonicalization state.
+                self.emit_fp_sign_op(Size::S64, BinOp::Eor, 0x8000_0000_0000_0000)
+            }
+
+            Operator::F64PromoteF32 => {
+                let fp = self.fp_stack.pop1()?;
+                self.fp_stack.push(fp.promote(self.value_stack.len() - 1));
+                let loc = self.pop_value();
+                let ret = self.push_value();
+                self.emit_load_neon(Size::S32, loc, NEON::V0);
+                self.assembler
+                    .emit_fcvt(Size::S32, Size::S64, NEON::V0, NEON::V0);
+                self.emit_store_neon(Size::S64, NEON::V0, ret);
+            }
+            Operator::F32DemoteF64 => {
+                let fp = self.fp_stack.pop1()?;
+                self.fp_stack.push(fp.demote(self.value_stack.len() - 1));
+                let loc = self.pop_value();
+                let ret = self.push_value();
+                self.emit_load_neon(Size::S64, loc, NEON::V0);
+                self.assembler
+                    .emit_fcvt(Size::S64, Size::S32, NEON::V0, NEON::V0);
+                self.emit_store_neon(Size::S32, NEON::V0, ret);
+            }
+
+            Operator::I32ReinterpretF32 | Operator::I64ReinterpretF64 => {
+                let sz = match op {
+                    Operator::I32ReinterpretF32 => Size::S32,
+                    _ => Size::S64,
+                };
+                let loc = self.pop_value();
+                let ret = self.push_value();
+                let fp = self.fp_stack.pop1()?;
+
+                if self.needs_canonicalization(&fp) {
+                    self.canonicalize_nan(sz, loc, ret);
+                } else {
+                    self.emit_move(sz, loc, ret);
+                }
+            }
+            Operator::F32ReinterpretI32 | Operator::F64ReinterpretI64 => {
+                let sz = match op {
+                    Operator::F32ReinterpretI32 => Size::S32,
+                    _ => Size::S64,
+                };
+                let loc = self.pop_value();
+                let ret = self.push_value();
+                self.fp_stack
+                    .push(FloatValue::new(self.value_stack.len() - 1));
+                self.emit_move(sz, loc, ret);
+            }
+
+            Operator::I32TruncF32U => self.emit_trunc(Size::S32, Size::S32, false),
+            Operator::I32TruncF32S => self.emit_trunc(Size::S32, Size::S32, true),
+            Operator::I64TruncF32U => self.emit_trunc(Size::S32, Size::S64, false),
+            Operator::I64TruncF32S => self.emit_trunc(Size::S32, Size::S64, true),
+            Operator::I32TruncF64U => self.emit_trunc(Size::S64, Size::S32, false),
+            Operator::I32TruncF64S => self.emit_trunc(Size::S64, Size::S32, true),
+            Operator::I64TruncF64U => self.emit_trunc(Size::S64, Size::S64, false),
+            Operator::I64TruncF64S => self.emit_trunc(Size::S64, Size::S64, true),
+            Operator::I32TruncSatF32U => self.emit_trunc_sat(Size::S32, Size::S32, false),
+            Operator::I32TruncSatF32S => self.emit_trunc_sat(Size::S32, Size::S32, true),
+            Operator::I64TruncSatF32U => self.emit_trunc_sat(Size::S32, Size::S64, false),
+            Operator::I64TruncSatF32S => self.emit_trunc_sat(Size::S32, Size::S64, true),
+            Operator::I32TruncSatF64U => self.emit_trunc_sat(Size::S64, Size::S32, false),
+            Operator::I32TruncSatF64S => self.emit_trunc_sat(Size::S64, Size::S32, true),
+            Operator::I64TruncSatF64U => self.emit_trunc_sat(Size::S64, Size::S64, false),
+            Operator::I64TruncSatF64S => self.emit_trunc_sat(Size::S64, Size::S64, true),
+
+            Operator::F32ConvertI32S => self.emit_convert(Size::S32, Size::S32, true),
+            Operator::F32ConvertI32U => self.emit_convert(Size::S32, Size::S32, false),
+            Operator::F32ConvertI64S => self.emit_convert(Size::S64, Size::S32, true),
+            Operator::F32ConvertI64U => self.emit_convert(Size::S64, Size::S32, false),
+            Operator::F64ConvertI32S => self.emit_convert(Size::S32, Size::S64, true),
+            Operator::F64ConvertI32U => self.emit_convert(Size::S32, Size::S64, false),
+            Operator::F64ConvertI64S => self.emit_convert(Size::S64, Size::S64, true),
+            Operator::F64ConvertI64U => self.emit_convert(Size::S64, Size::S64, false),
+
+            Operator::Call { function_index } => self.emit_call(function_index, false)?,
+            Operator::ReturnCall { function_index } => self.emit_call(function_index, true)?,
+            Operator::CallIndirect { index, table_index }
+            | Operator::ReturnCallIndirect { index, table_index } => {
+                let tail_call = matches!(op, Operator::ReturnCallIndirect { .. });
+                if tail_call {
+                    self.emit_caught_exceptions_drop();
+                }
+                let table_index = TableIndex::new(table_index as _);
+                let index = SignatureIndex::new(index as usize);
+                let sig = self.module.signatures.get(index).unwrap();
+                let param_count = sig.params().len();
+                let return_types: SmallVec<[WpType; 1]> =
+                    sig.results().iter().cloned().map(type_to_wp_type).collect();
+                let return_layout = ReturnLayout::new(sig.results());
+
+                let func_index = self.pop_value();
+                let params = self.pop_call_params(param_count);
+
+                let (table_base, table_count, anyfunc) = (GPR::X10, GPR::X11, GPR::X10);
+                self.emit_table_base_and_count(table_index, table_base, table_count);
+
+                self.emit_load(Size::S32, func_index, GPR::X9);
+                self.assembler.emit_cmp(Size::S32, table_count, GPR::X9);
+                self.assembler
+                    .emit_bcond_label(Condition::Ls, self.special_labels.table_access_oob);
+                self.assembler.emit_mov_imm(
+                    Size::S64,
+                    GPR::X12,
+                    self.vmoffsets.size_of_vm_funcref() as u64,
+                );
+                self.assembler
+                    .emit_binop(BinOp::Mul, Size::S64, GPR::X9, GPR::X9, GPR::X12);
+                self.assembler
+                    .emit_binop(BinOp::Add, Size::S64, anyfunc, table_base, GPR::X9);
+
+                // deref the table to get a VMFuncRef
+                self.assembler.emit_ldr(
+                    Size::S64,
+                    anyfunc,
+                    anyfunc,
+                    self.vmoffsets.vm_funcref_anyfunc_ptr() as i32,
+                );
+                // Trap if the FuncRef is null
+                self.assembler.emit_cbz_label(
+                    Size::S64,
+                    anyfunc,
+                    self.special_labels.indirect_call_null,
+                );
+
+                // Trap if signature mismatches.
+                self.assembler.emit_ldr(
+                    Size::S32,
+                    GPR::X12,
+                    VMCTX,
+                    self.vmoffsets.vmctx_vmshared_signature_id(index) as i32,
+                );
+                self.assembler.emit_ldr(
+                    Size::S32,
+                    GPR::X11,
+                    anyfunc,
+                    self.vmoffsets.vmcaller_checked_anyfunc_type_index() as i32,
+                );
+                self.assembler.emit_cmp(Size::S32, GPR::X11, GPR::X12);
+                self.assembler
+                    .emit_bcond_label(Condition::Ne, self.special_labels.bad_signature);
+
+                self.assembler.emit_ldr(
+                    Size::S64,
+                    CALL_TARGET,
+                    anyfunc,
+                    self.vmoffsets.vmcaller_checked_anyfunc_func_ptr() as i32,
+                );
+                let vmctx_offset = self.vmoffsets.vmcaller_checked_anyfunc_vmctx() as i32;
+                if tail_call {
+                    self.check_tail_call(params.len())?;
+                    self.emit_tail_call(&params, |this| {
+                        this.assembler
+                            .emit_ldr(Size::S64, GPR::X0, anyfunc, vmctx_offset);
+                    });
+                    self.unreachable_depth = 1;
+                } else {
+                    self.emit_call_return_pointer(return_layout.as_ref());
+                    self.emit_call_native(&params, |this| {
+                        // We set the context pointer
+                        this.assembler
+                            .emit_ldr(Size::S64, GPR::X0, anyfunc, vmctx_offset);
+                        this.assembler.emit_blr(CALL_TARGET);
+                    });
+                    self.push_call_result(&return_types, return_layout.as_ref());
+                    self.emit_exception_check();
+                }
+            }
+            Operator::If { ty } => {
+                let label_end = self.assembler.get_label();
+                let label_else = self.assembler.get_label();
+
+                let cond = self.pop_value();
+
+                let (params, returns) = self.block_signature(ty);
+                let depth = self.value_stack.len() - params.len();
+                let frame = ControlFrame {
+                    label: label_end,
+                    loop_like: false,
+                    if_else: IfElseState::If(label_else),
+                    returns,
+                    value_stack_depth: depth,
+                    fp_stack_depth: self.fp_stack_depth_at(depth),
+                    params,
+                    loop_params: None,
+                    try_state: None,
+                };
+                self.control_stack.push(frame);
+                self.emit_load(Size::S32, cond, GPR::X9);
+                self.assembler
+                    .emit_cbz_label(Size::S32, GPR::X9, label_else);
+
+                // The `then` branch works on copies of the parameters, which
+                // the `else` branch gets.
+                for i in depth..self.value_stack.len() {
+                    let loc = self.value_stack[i];
+                    match loc {
+                        Location::Imm32(_) | Location::Imm64(_) => self.value_stack.push(loc),
+                        _ => {
+                            let copy = self.push_value();
+                            self.emit_move(Size::S64, loc, copy);
+                        }
+                    }
+                    if let Some(fp) = self.fp_stack.iter().find(|fp| fp.depth == i).copied() {
+                        self.fp_stack.push(FloatValue {
+                            depth: self.value_stack.len() - 1,
+                            ..fp
+                        });
+                    }
+                }
+            }
+            Operator::Else => self.emit_else(was_unreachable)?,
+            // `TypedSelect` must be used for extern refs so ref counting should
+            // be done with TypedSelect. But otherwise they're the same.
+            Operator::TypedSelect { .. } | Operator::Select => {
+                let cond = self.pop_value();
+                let v_b = self.pop_value();
+                let v_a = self.pop_value();
+                let cncl: Option<(Option<CanonicalizeType>, Option<CanonicalizeType>)> =
+                    if self.fp_stack.len() >= 2
+                        && self.fp_stack[self.fp_stack.len() - 2].depth == self.value_stack.len()
+                        && self.fp_stack[self.fp_stack.len() - 1].depth
+                            == self.value_stack.len() + 1
+                    {
+                        let (left, right) = self.fp_stack.pop2()?;
+                        self.fp_stack.push(FloatValue::new(self.value_stack.len()));
+                        Some((left.canonicalization, right.canonicalization))
+                    } else {
+                        None
+                    };
+                let ret = self.push_value();
+
+                let end_label = self.assembler.get_label();
+                let zero_label = self.assembler.get_label();
+
+                self.emit_load(Size::S32, cond, GPR::X10);
+                self.assembler
+                    .emit_cbz_label(Size::S32, GPR::X10, zero_label);
+                match cncl {
+                    Some((Some(fp), _)) if self.config.enable_nan_canonicalization => {
+                        self.canonicalize_nan(canonicalize_size(fp), v_a, ret);
+                    }
+                    _ => self.emit_move(Size::S64, v_a, ret),
+                }
+                self.assembler.emit_b_label(end_label);
+                self.assembler.emit_label(zero_label);
+                match cncl {
+                    Some((_, Some(fp))) if self.config.enable_nan_canonicalization => {
+                        self.canonicalize_nan(canonicalize_size(fp), v_b, ret);
+                    }
+                    _ => self.emit_move(Size::S64, v_b, ret),
+                }
+                self.assembler.emit_label(end_label);
+            }
+            Operator::Try { ty } => {
+                let (params, returns) = self.block_signature(ty);
+                let depth = self.value_stack.len() - params.len();
+                let frame = ControlFrame {
+                    label: self.assembler.get_label(),
+                    loop_like: false,
+                    if_else: IfElseState::None,
+                    returns,
+                    value_stack_depth: depth,
+                    fp_stack_depth: self.fp_stack_depth_at(depth),
+                    params,
+                    loop_params: None,
+                    // Values live at fixed offsets from the frame pointer,
+                    // so catching doesn't restore the stack pointer.
+                    try_state: Some(TryState {
+                        slot: self.try_count,
+                        stack_offset: 0,
+                        landing: self.assembler.get_label(),
+                        catching: false,
+                    }),
+                };
+                self.try_count += 1;
+                self.control_stack.push(frame);
+            }
+            Operator::Catch { .. } | Operator::CatchAll => {
+                if !was_unreachable {
+                    self.emit_block_results(self.control_stack.len() - 1)?;
+                }
+
+                self.update_max_stack_depth();
+
+                let frame = self.control_stack.last().unwrap();
+
+                self.value_stack.truncate(frame.value_stack_depth);
+                self.fp_stack.truncate(frame.fp_stack_depth);
+
+                let mut try_state = frame.try_state.ok_or_else(|| CodegenError {
+                    message: "Catch: frame.try_state unreachable code".to_string(),
+                })?;
+                self.assembler.emit_b_label(frame.label);
+                self.assembler.emit_label(try_state.landing);
+                let caught = self.emit_caught_exceptions();
+
+                let tag_index = match op {
+                    Operator::Catch { index } => Some(TagIndex::from_u32(index)),
+                    _ => None,
+                };
+                self.emit_call_builtin(
+                    VMBuiltinFunctionIndex::get_exception_catch_index(),
+                    // [vmctx, caught, slot, tag_index]
+                    &[
+                        caught,
+                        Location::Imm32(try_state.slot),
+                        Location::Imm32(tag_index.map_or(u32::MAX, |index| index.as_u32())),
+                    ],
+                );
+                try_state.landing = self.assembler.get_label();
+                try_state.catching = true;
+                self.assembler
+                    .emit_cbz_label(Size::S32, GPR::X0, try_state.landing);
+                self.control_stack.last_mut().unwrap().try_state = Some(try_state);
+
+                if let Some(tag_index) = tag_index {
+                    let params = self.module.tags[tag_index].params().to_vec();
+                    for (i, ty) in params.into_iter().enumerate() {
+                        self.emit_call_builtin(
+                            VMBuiltinFunctionIndex::get_exception_payload_index(),
+                            // [vmctx, caught, slot, index]
+                            &[
+                                caught,
+                                Location::Imm32(try_state.slot),
+                                Location::Imm32(i as u32),
+                            ],
+                        );
+                        let loc = self.push_value();
+                        self.emit_store(Size::S64, GPR::X0, loc);
+                        if type_to_wp_type(ty).is_float() {
+                            self.fp_stack
+                                .push(FloatValue::new(self.value_stack.len() - 1));
+                        }
+                    }
+                }
+            }
+            Operator::Throw { index } => {
+                let tag_index = TagIndex::from_u32(index);
+                let n_values = self.module.tags[tag_index].params().len();
+                // The values stay in their slots until pushed.
+                let values: SmallVec<[_; 8]> = self
+                    .value_stack
+                    .drain(self.value_stack.len() - n_values..)
+                    .collect();
+                self.update_max_stack_depth();
+                while let Some(fp) = self.fp_stack.last() {
+                    if fp.depth >= self.value_stack.len() {
+                        self.fp_stack.pop().unwrap();
+                    } else {
+                        break;
+                    }
+                }
+
+                self.emit_call_builtin(
+                    VMBuiltinFunctionIndex::get_exception_new_index(),
+                    // [vmctx, tag_index]
+                    &[Location::Imm32(index)],
+                );
+                for value in values.iter() {
+                    self.emit_call_builtin(
+                        VMBuiltinFunctionIndex::get_exception_push_index(),
+                        // [vmctx, bits]
+                        &[*value],
+                    );
+                }
+
+                let target = self.exception_target(self.control_stack.len());
+                self.assembler.emit_b_label(target);
+                self.unreachable_depth = 1;
+            }
+            Operator::Rethrow { relative_depth } => {
+                let frame =
+                    &self.control_stack[self.control_stack.len() - 1 - (relative_depth as usize)];
+                let try_state = frame.try_state.ok_or_else(|| CodegenError {
+                    message: "Rethrow: frame.try_state unreachable code".to_string(),
+                })?;
+                let caught = self.caught_exceptions.unwrap();
+                self.emit_call_builtin(
+                    VMBuiltinFunctionIndex::get_exception_rethrow_index(),
+                    // [vmctx, caught, slot]
+                    &[caught, Location::Imm32(try_state.slot)],
+                );
+
+                let target = self.exception_target(self.control_stack.len());
+                self.assembler.emit_b_label(target);
+                self.unreachable_depth = 1;
+            }
+            Operator::Block { ty } => {
+                let (params, returns) = self.block_signature(ty);
+                let depth = self.value_stack.len() - params.len();
+                let frame = ControlFrame {
+                    label: self.assembler.get_label(),
+                    loop_like: false,
+                    if_else: IfElseState::None,
+                    returns,
+                    value_stack_depth: depth,
+                    fp_stack_depth: self.fp_stack_depth_at(depth),
+                    params,
+                    loop_params: None,
+                    try_state: None,
+                };
+                self.control_stack.push(frame);
+            }
+            Operator::Loop { ty } => {
+                let (params, returns) = self.block_signature(ty);
+                let depth = self.value_stack.len() - params.len();
+                // Branches to the loop move the new parameters to the slots
+                // of the initial ones.
+                self.emit_values_to_slots(depth, params.len());
+                for i in depth..self.value_stack.len() {
+                    self.value_stack[i] = self.local_location(self.local_types.len() + i);
+                }
+                for fp in self.fp_stack.iter_mut().filter(|fp| fp.depth >= depth) {
+                    *fp = FloatValue::new(fp.depth);
+                }
+
+                let label = self.assembler.get_label();
+                self.control_stack.push(ControlFrame {
+                    label,
+                    loop_like: true,
+                    if_else: IfElseState::None,
+                    returns,
+                    value_stack_depth: depth,
+                    fp_stack_depth: self.fp_stack_depth_at(depth),
+                    params,
+                    loop_params: None,
+                    try_state: None,
+                });
+                self.assembler.emit_label(label);
+                self.emit_epoch_check();
+            }
+            Operator::Nop => {}
+            Operator::MemorySize { mem, mem_byte: _ } => {
+                let memory_index = MemoryIndex::new(mem as usize);
+                self.emit_call_builtin(
+                    match (
+                        self.module.local_memory_index(memory_index).is_some(),
+                        self.memory64(memory_index),
+                    ) {
+                        (true, false) => VMBuiltinFunctionIndex::get_memory32_size_index(),
+                        (false, false) => {
+                            VMBuiltinFunctionIndex::get_imported_memory32_size_index()
+                        }
+                        (true, true) => VMBuiltinFunctionIndex::get_memory64_size_index(),
+                        (false, true) => VMBuiltinFunctionIndex::get_imported_memory64_size_index(),
+                    },
+                    // [vmctx, memory_index]
+                    &[Location::Imm32(self.memory_builtin_index(memory_index))],
+                );
+                let ret = self.push_value();
+                self.emit_store(Size::S64, GPR::X0, ret);
+            }
+            Operator::MemoryInit { segment, mem } => {
+                let len = self.value_stack.pop().unwrap();
+                let src = self.value_stack.pop().unwrap();
+                let dst = self.value_stack.pop().unwrap();
+                self.emit_call_builtin(
+                    if self.memory64(MemoryIndex::new(mem as usize)) {
+                        VMBuiltinFunctionIndex::get_memory64_init_index()
+                    } else {
+                        VMBuiltinFunctionIndex::get_memory_init_index()
+                    },
+                    // [vmctx, memory_index, segment_index, dst, src, len]
+                    &[
+                        Location::Imm32(mem),
+                        Location::Imm32(segment),
+                        dst,
+                        src,
+                        len,
+                    ],
+                );
+            }
+            Operator::DataDrop { segment } => {
+                self.emit_call_builtin(
+                    VMBuiltinFunctionIndex::get_data_drop_index(),
+                    // [vmctx, segment_index]
+                    &[Location::Imm32(segment)],
+                );
+            }
+            Operator::MemoryCopy { src, dst } if src != dst => {
+                let len = self.value_stack.pop().unwrap();
+                let src_pos = self.value_stack.pop().unwrap();
+                let dst_pos = self.value_stack.pop().unwrap();
+                self.emit_call_builtin(
+                    VMBuiltinFunctionIndex::get_memory_copy_between_index(),
+                    // [vmctx, dst_memory_index, src_memory_index, dst, src, len]
+                    &[
+                        Location::Imm32(dst),
+                        Location::Imm32(src),
+                        dst_pos,
+                        src_pos,
+                        len,
+                    ],
+                );
+            }
+            Operator::MemoryCopy { src, dst: _ } => {
+                let len = self.value_stack.pop().unwrap();
+                let src_pos = self.value_stack.pop().unwrap();
+                let dst_pos = self.value_stack.pop().unwrap();
+                let memory_index = MemoryIndex::new(src as usize);
+                self.emit_call_builtin(
+                    match (
+                        self.module.local_memory_index(memory_index).is_some(),
+                        self.memory64(memory_index),
+                    ) {
+                        (true, false) => VMBuiltinFunctionIndex::get_memory_copy_index(),
+                        (false, false) => VMBuiltinFunctionIndex::get_imported_memory_copy_index(),
+                        (true, true) => VMBuiltinFunctionIndex::get_memory64_copy_index(),
+                        (false, true) => VMBuiltinFunctionIndex::get_imported_memory64_copy_index(),
+                    },
+                    // [vmctx, memory_index, dst, src, len]
+                    &[
+                        Location::Imm32(self.memory_builtin_index(memory_index)),
+                        dst_pos,
+                        src_pos,
+                        len,
+                    ],
+                );
+            }
+            Operator::MemoryFill { mem } => {
+                let len = self.value_stack.pop().unwrap();
+                let val = self.value_stack.pop().unwrap();
+                let dst = self.value_stack.pop().unwrap();
+                let memory_index = MemoryIndex::new(mem as usize);
+                self.emit_call_builtin(
+                    match (
+                        self.module.local_memory_index(memory_index).is_some(),
+                        self.memory64(memory_index),
+                    ) {
+                        (true, false) => VMBuiltinFunctionIndex::get_memory_fill_index(),
+                        (false, false) => VMBuiltinFunctionIndex::get_imported_memory_fill_index(),
+                        (true, true) => VMBuiltinFunctionIndex::get_memory64_fill_index(),
+                        (false, true) => VMBuiltinFunctionIndex::get_imported_memory64_fill_index(),
+                    },
+                    // [vmctx, memory_index, dst, src, len]
+                    &[
+                        Location::Imm32(self.memory_builtin_index(memory_index)),
+                        dst,
+                        val,
+                        len,
+                    ],
+                );
+            }
+            Operator::MemoryGrow { mem, mem_byte: _ } => {
+                let memory_index = MemoryIndex::new(mem as usize);
+                let param_pages = self.value_stack.pop().unwrap();
+                self.emit_call_builtin(
+                    match (
+                        self.module.local_memory_index(memory_index).is_some(),
+                        self.memory64(memory_index),
+                    ) {
+                        (true, false) => VMBuiltinFunctionIndex::get_memory32_grow_index(),
+                        (false, false) => {
+                            VMBuiltinFunctionIndex::get_imported_memory32_grow_index()
+                        }
+                        (true, true) => VMBuiltinFunctionIndex::get_memory64_grow_index(),
+                        (false, true) => VMBuiltinFunctionIndex::get_imported_memory64_grow_index(),
+                    },
+                    // [vmctx, val, memory_index]
+                    &[
+                        param_pages,
+                        Location::Imm32(self.memory_builtin_index(memory_index)),
+                    ],
+                );
+                let ret = self.push_value();
+                self.emit_store(Size::S64, GPR::X0, ret);
+            }
+            Operator::I32Load { ref memarg } => self.emit_load_op(memarg, Size::S32, None, false),
+            Operator::F32Load { ref memarg } => self.emit_load_op(memarg, Size::S32, None, true),
+            Operator::I32Load8U { ref memarg } => self.emit_load_op(memarg, Size::S8, None, false),
+            Operator::I32Load8S { ref memarg } => {
+                self.emit_load_op(memarg, Size::S8, Some(Size::S32), false)
+            }
+            Operator::I32Load16U { ref memarg } => {
+                self.emit_load_op(memarg, Size::S16, None, false)
+            }
+            Operator::I32Load16S { ref memarg } => {
+                self.emit_load_op(memarg, Size::S16, Some(Size::S32), false)
+            }
+            Operator::I64Load { ref memarg } => self.emit_load_op(memarg, Size::S64, None, false),
+            Operator::F64Load { ref memarg } => self.emit_load_op(memarg, Size::S64, None, true),
+            Operator::I64Load8U { ref memarg } => self.emit_load_op(memarg, Size::S8, None, false),
+            Operator::I64Load8S { ref memarg } => {
+                self.emit_load_op(memarg, Size::S8, Some(Size::S64), false)
+            }
+            Operator::I64Load16U { ref memarg } => {
+                self.emit_load_op(memarg, Size::S16, None, false)
+            }
+            Operator::I64Load16S { ref memarg } => {
+                self.emit_load_op(memarg, Size::S16, Some(Size::S64), false)
+            }
+            Operator::I64Load32U { ref memarg } => {
+                self.emit_load_op(memarg, Size::S32, None, false)
+            }
+            Operator::I64Load32S { ref memarg } => {
+                self.emit_load_op(memarg, Size::S32, Some(Size::S64), false)
+            }
+            Operator::I32Store { ref memarg } => self.emit_store_op(memarg, Size::S32, false),
+            Operator::F32Store { ref memarg } => self.emit_store_op(memarg, Size::S32, true),
+            Operator::I32Store8 { ref memarg } | Operator::I64Store8 { ref memarg } => {
+                self.emit_store_op(memarg, Size::S8, false)
+            }
+            Operator::I32Store16 { ref memarg } | Operator::I64Store16 { ref memarg } => {
+                self.emit_store_op(memarg, Size::S16, false)
+            }
+            Operator::I64Store { ref memarg } => self.emit_store_op(memarg, Size::S64, false),
+            Operator::F64Store { ref memarg } => self.emit_store_op(memarg, Size::S64, true),
+            Operator::I64Store32 { ref memarg } => self.emit_store_op(memarg, Size::S32, false),
+            Operator::Unreachable => {
+                let offset = self.assembler.get_offset().0;
+                self.emit_trap(TrapCode::UnreachableCodeReached);
+                self.mark_instruction_address_end(offset);
+                self.unreachable_depth = 1;
+            }
+            Operator::Return => self.emit_return()?,
+            Operator::Br { relative_depth } => {
+                self.emit_br(relative_depth)?;
+                self.unreachable_depth = 1;
+            }
+            Operator::BrIf { relative_depth } => {
+                let after = self.assembler.get_label();
+                let cond = self.pop_value();
+                self.emit_load(Size::S32, cond, GPR::X9);
+                self.assembler.emit_cbz_label(Size::S32, GPR::X9, after);
+                self.emit_br(relative_depth)?;
+                self.assembler.emit_label(after);
+            }
+            Operator::BrTable { ref table } => {
+                let mut targets = table
+                    .targets()
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| CodegenError {
+                        message: format!("BrTable read_table: {:?}", e),
+                    })?;
+                let default_target = targets.pop().unwrap().0;
+                let cond = self.pop_value();
+                let table_label = self.assembler.get_label();
+                let mut table: Vec<DynamicLabel> = vec![];
+                let default_br = self.assembler.get_label();
+                self.emit_load(Size::S32, cond, GPR::X9);
+                self.assembler
+                    .emit_cmp_imm(Size::S32, GPR::X9, targets.len() as u64);
+                self.assembler.emit_bcond_label(Condition::Cs, default_br);
+
+                // Each entry of the jump table is a single 4-byte `b` instruction.
+                self.assembler.emit_adr_label(GPR::X10, table_label);
+                self.assembler.emit_add_lsl(GPR::X10, GPR::X10, GPR::X9, 2);
+                self.assembler.emit_br(GPR::X10);
+
+                for (target, _) in targets.iter() {
+                    let label = self.assembler.get_label();
+                    self.assembler.emit_label(label);
+                    table.push(label);
+                    self.emit_br(*target)?;
+                }
+                self.assembler.emit_label(default_br);
+                self.emit_br(default_target)?;
+
+                self.assembler.emit_label(table_label);
+                for x in table {
+                    self.assembler.emit_b_label(x);
+                }
+                self.unreachable_depth = 1;
+            }
+            Operator::Drop => {
+                self.pop_value();
+                if let Some(x) = self.fp_stack.last() {
+                    if x.depth == self.value_stack.len() {
+                        self.fp_stack.pop1()?;
+                    }
+                }
+            }
+            Operator::End | Operator::Delegate { .. } => {
+                let mut was_unreachable = was_unreachable;
+                let frame = self.control_stack.last().unwrap();
+                if let (IfElseState::If(_), false) = (frame.if_else, frame.params.is_empty()) {
+                    // Without an `else` branch, the parameters are the results.
+                    self.emit_else(was_unreachable)?;
+                    was_unreachable = false;
+                }
+                if !was_unreachable {
+                    self.emit_block_results(self.control_stack.len() - 1)?;
+                }
+                let frame = self.control_stack.pop().unwrap();
+
+                if self.control_stack.is_empty() {
+                    self.assembler.emit_label(frame.label);
+                    if self.caught_exceptions.is_some() {
+                        // `x19` is restored right after.
+                        self.assembler.emit_mov(Size::S64, GPR::X0, GPR::X19);
+                        self.emit_caught_exceptions_drop();
+                        self.assembler.emit_mov(Size::S64, GPR::X19, GPR::X0);
+                    }
+                    self.update_max_stack_depth();
+                    self.emit_function_stack_check(false);
+
+                    if let Some(layout) = self.return_layout.clone() {
+                        self.emit_return_values(&layout);
+                    }
+                    // Make a copy of the return value in D0, as required by the AAPCS64.
+                    match self.signature.results() {
+                        [x] if *x == Type::F32 || *x == Type::F64 => {
+                            self.assembler
+                                .emit_fmov_from_gpr(Size::S64, NEON::V0, GPR::X0);
+                        }
+                        _ => {}
+                    }
+                    self.assembler.emit_ldp(GPR::X19, VMCTX, FP, -16);
+                    self.assembler.emit_mov_to_sp(FP);
+                    self.assembler.emit_pop_pair(FP, GPR::X30);
+                    self.assembler.emit_ret();
+                } else {
+                    self.update_max_stack_depth();
+                    self.value_stack.truncate(frame.value_stack_depth);
+                    self.fp_stack.truncate(frame.fp_stack_depth);
+
+                    if let Some(try_state) = frame.try_state {
+                        // The exceptions left uncaught propagate further, to
+                        // the handlers of the label a `delegate` targets.
+                        let depth = match op {
+                            Operator::Delegate { relative_depth } => {
+                                self.control_stack.len() - relative_depth as usize
+                            }
+                            _ => self.control_stack.len(),
+                        };
+                        let target = self.exception_target(depth);
+                        self.assembler.emit_b_label(frame.label);
+                        self.assembler.emit_label(try_state.landing);
+                        self.assembler.emit_b_label(target);
+                    }
+
+                    if !frame.loop_like {
+                        self.assembler.emit_label(frame.label);
+                    }
+
+                    if let IfElseState::If(label) = frame.if_else {
+                        self.assembler.emit_label(label);
+                    }
+
+                    self.push_block_results(&frame.returns);
+                }
+            }
+            Operator::AtomicFence { flags: _ } => self.assembler.emit_dmb(),
+            Operator::MemoryAtomicWait32 { ref memarg } => {
+                let timeout = self.pop_value();
+                let expected = self.pop_value();
+                let target = self.pop_value();
+                self.emit_atomic_wait_notify(
+                    VMBuiltinFunctionIndex::get_memory_atomic_wait32_index(),
+                    memarg,
+                    target,
+                    &[expected, timeout],
+                );
+            }
+            Operator::MemoryAtomicWait64 { ref memarg } => {
+                let timeout = self.pop_value();
+                let expected = self.pop_value();
+                let target = self.pop_value();
+                self.emit_atomic_wait_notify(
+                    VMBuiltinFunctionIndex::get_memory_atomic_wait64_index(),
+                    memarg,
+                    target,
+                    &[expected, timeout],
+                );
+            }
+            Operator::MemoryAtomicNotify { ref memarg } => {
+                let count = self.pop_value();
+                let target = self.pop_value();
+                self.emit_atomic_wait_notify(
+                    VMBuiltinFunctionIndex::get_memory_atomic_notify_index(),
+                    memarg,
+                    target,
+                    &[count],
+                );
+            }
+            Operator::I32AtomicLoad { ref memarg } => self.emit_atomic_load(memarg, Size::S32),
+            Operator::I32AtomicLoad8U { ref memarg } => self.emit_atomic_load(memarg, Size::S8),
+            Operator::I32AtomicLoad16U { ref memarg } => self.emit_atomic_load(memarg, Size::S16),
+            Operator::I64AtomicLoad { ref memarg } => self.emit_atomic_load(memarg, Size::S64),
+            Operator::I64AtomicLoad8U { ref memarg } => self.emit_atomic_load(memarg, Size::S8),
+            Operator::I64AtomicLoad16U { ref memarg } => self.emit_atomic_load(memarg, Size::S16),
+            Operator::I64AtomicLoad32U { ref memarg } => self.emit_atomic_load(memarg, Size::S32),
+            Operator::I32AtomicStore { ref memarg } | Operator::I64AtomicStore32 { ref memarg } => {
+                self.emit_atomic_store(memarg, Size::S32)
+            }
+            Operator::I32AtomicStore8 { ref memarg } | Operator::I64AtomicStore8 { ref memarg } => {
+                self.emit_atomic_store(memarg, Size::S8)
+            }
+            Operator::I32AtomicStore16 { ref memarg }
+            | Operator::I64AtomicStore16 { ref memarg } => {
+                self.emit_atomic_store(memarg, Size::S16)
+            }
+            Operator::I64AtomicStore { ref memarg } => self.emit_atomic_store(memarg, Size::S64),
+            Operator::I32AtomicRmwAdd { ref memarg }
+            | Operator::I32AtomicRmwSub { ref memarg }
+            | Operator::I32AtomicRmwAnd { ref memarg }
+            | Operator::I32AtomicRmwOr { ref memarg }
+            | Operator::I32AtomicRmwXor { ref memarg }
+            | Operator::I32AtomicRmwXchg { ref memarg }
+            | Operator::I64AtomicRmw32AddU { ref memarg }
+            | Operator::I64AtomicRmw32SubU { ref memarg }
+            | Operator::I64AtomicRmw32AndU { ref memarg }
+            | Operator::I64AtomicRmw32OrU { ref memarg }
+            | Operator::I64AtomicRmw32XorU { ref memarg }
+            | Operator::I64AtomicRmw32XchgU { ref memarg }
+            | Operator::I32AtomicRmw8AddU { ref memarg }
+            | Operator::I32AtomicRmw8SubU { ref memarg }
+            | Operator::I32AtomicRmw8AndU { ref memarg }
+            | Operator::I32AtomicRmw8OrU { ref memarg }
+            | Operator::I32AtomicRmw8XorU { ref memarg }
+            | Operator::I32AtomicRmw8XchgU { ref memarg }
+            | Operator::I64AtomicRmw8AddU { ref memarg }
+            | Operator::I64AtomicRmw8SubU { ref memarg }
+            | Operator::I64AtomicRmw8AndU { ref memarg }
+            | Operator::I64AtomicRmw8OrU { ref memarg }
+            | Operator::I64AtomicRmw8XorU { ref memarg }
+            | Operator::I64AtomicRmw8XchgU { ref memarg }
+            | Operator::I32AtomicRmw16AddU { ref memarg }
+            | Operator::I32AtomicRmw16SubU { ref memarg }
+            | Operator::I32AtomicRmw16AndU { ref memarg }
+            | Operator::I32AtomicRmw16OrU { ref memarg }
+            | Operator::I32AtomicRmw16XorU { ref memarg }
+            | Operator::I32AtomicRmw16XchgU { ref memarg }
+            | Operator::I64AtomicRmw16AddU { ref memarg }
+            | Operator::I64AtomicRmw16SubU { ref memarg }
+            | Operator::I64AtomicRmw16AndU { ref memarg }
+            | Operator::I64AtomicRmw16OrU { ref memarg }
+            | Operator::I64AtomicRmw16XorU { ref memarg }
+            | Operator::I64AtomicRmw16XchgU { ref memarg }
+            | Operator::I64AtomicRmwAdd { ref memarg }
+            | Operator::I64AtomicRmwSub { ref memarg }
+            | Operator::I64AtomicRmwAnd { ref memarg }
+            | Operator::I64AtomicRmwOr { ref memarg }
+            | Operator::I64AtomicRmwXor { ref memarg }
+            | Operator::I64AtomicRmwXchg { ref memarg } => {
+                let sz = match op {
+                    Operator::I32AtomicRmw8AddU { .. }
+                    | Operator::I32AtomicRmw8SubU { .. }
+                    | Operator::I32AtomicRmw8AndU { .. }
+                    | Operator::I32AtomicRmw8OrU { .. }
+                    | Operator::I32AtomicRmw8XorU { .. }
+                    | Operator::I32AtomicRmw8XchgU { .. }
+                    | Operator::I64AtomicRmw8AddU { .. }
+                    | Operator::I64AtomicRmw8SubU { .. }
+                    | Operator::I64AtomicRmw8AndU { .. }
+                    | Operator::I64AtomicRmw8OrU { .. }
+                    | Operator::I64AtomicRmw8XorU { .. }
+                    | Operator::I64AtomicRmw8XchgU { .. } => Size::S8,
+                    Operator::I32AtomicRmw16AddU { .. }
+                    | Operator::I32AtomicRmw16SubU { .. }
+                    | Operator::I32AtomicRmw16AndU { .. }
+                    | Operator::I32AtomicRmw16OrU { .. }
+                    | Operator::I32AtomicRmw16XorU { .. }
+                    | Operator::I32AtomicRmw16XchgU { .. }
+                    | Operator::I64AtomicRmw16AddU { .. }
+                    | Operator::I64AtomicRmw16SubU { .. }
+                    | Operator::I64AtomicRmw16AndU { .. }
+                    | Operator::I64AtomicRmw16OrU { .. }
+                    | Operator::I64AtomicRmw16XorU { .. }
+                    | Operator::I64AtomicRmw16XchgU { .. } => Size::S16,
+                    Operator::I64AtomicRmwAdd { .. }
+                    | Operator::I64AtomicRmwSub { .. }
+                    | Operator::I64AtomicRmwAnd { .. }
+                    | Operator::I64AtomicRmwOr { .. }
+                    | Operator::I64AtomicRmwXor { .. }
+                    | Operator::I64AtomicRmwXchg { .. } => Size::S64,
+                    _ => Size::S32,
+                };
+                let rmw_op = match op {
+                    Operator::I32AtomicRmwAdd { .. }
+                    | Operator::I64AtomicRmw32AddU { .. }
+                    | Operator::I32AtomicRmw8AddU { .. }
+                    | Operator::I64AtomicRmw8AddU { .. }
+                    | Operator::I32AtomicRmw16AddU { .. }
+                    | Operator::I64AtomicRmw16AddU { .. }
+                    | Operator::I64AtomicRmwAdd { .. } => Some(BinOp::Add),
+                    Operator::I32AtomicRmwSub { .. }
+                    | Operator::I64AtomicRmw32SubU { .. }
+                    | Operator::I32AtomicRmw8SubU { .. }
+                    | Operator::I64AtomicRmw8SubU { .. }
+                    | Operator::I32AtomicRmw16SubU { .. }
+                    | Operator::I64AtomicRmw16SubU { .. }
+                    | Operator::I64AtomicRmwSub { .. } => Some(BinOp::Sub),
+                    Operator::I32AtomicRmwAnd { .. }
+                    | Operator::I64AtomicRmw32AndU { .. }
+                    | Operator::I32AtomicRmw8AndU { .. }
+                    | Operator::I64AtomicRmw8AndU { .. }
+                    | Operator::I32AtomicRmw16AndU { .. }
+                    | Operator::I64AtomicRmw16AndU { .. }
+                    | Operator::I64AtomicRmwAnd { .. } => Some(BinOp::And),
+                    Operator::I32AtomicRmwOr { .. }
+                    | Operator::I64AtomicRmw32OrU { .. }
+                    | Operator::I32AtomicRmw8OrU { .. }
+                    | Operator::I64AtomicRmw8OrU { .. }
+                    | Operator::I32AtomicRmw16OrU { .. }
+                    | Operator::I64AtomicRmw16OrU { .. }
+                    | Operator::I64AtomicRmwOr { .. } => Some(BinOp::Orr),
+                    Operator::I32AtomicRmwXor { .. }
+                    | Operator::I64AtomicRmw32XorU { .. }
+                    | Operator::I32AtomicRmw8XorU { .. }
+                    | Operator::I64AtomicRmw8XorU { .. }
+                    | Operator::I32AtomicRmw16XorU { .. }
+                    | Operator::I64AtomicRmw16XorU { .. }
+                    | Operator::I64AtomicRmwXor { .. } => Some(BinOp::Eor),
+                    _ => None,
+                };
+                self.emit_atomic_rmw(memarg, sz, rmw_op);
+            }
+            Operator::I32AtomicRmwCmpxchg { ref memarg }
+            | Operator::I64AtomicRmw32CmpxchgU { ref memarg } => {
+                self.emit_atomic_cmpxchg(memarg, Size::S32)
+            }
+            Operator::I32AtomicRmw8CmpxchgU { ref memarg }
+            | Operator::I64AtomicRmw8CmpxchgU { ref memarg } => {
+                self.emit_atomic_cmpxchg(memarg, Size::S8)
+            }
+            Operator::I32AtomicRmw16CmpxchgU { ref memarg }
+            | Operator::I64AtomicRmw16CmpxchgU { ref memarg } => {
+                self.emit_atomic_cmpxchg(memarg, Size::S16)
+            }
+            Operator::I64AtomicRmwCmpxchg { ref memarg } => {
+                self.emit_atomic_cmpxchg(memarg, Size::S64)
+            }
+            Operator::RefNull { .. } => {
+                self.value_stack.push(Location::Imm64(0));
+            }
+            Operator::RefFunc { function_index } => {
+                self.emit_call_builtin(
+                    VMBuiltinFunctionIndex::get_func_ref_index(),
+                    // [vmctx, func_index] -> funcref
+                    &[Location::Imm32(function_index)],
+                );
+                let ret = self.push_value();
+                self.emit_store(Size::S64, GPR::X0, ret);
+            }
+            Operator::RefIsNull => self.emit_eqz(Size::S64),
+            Operator::TableSet { table: index } => {
+                let table_index = TableIndex::new(index as _);
+                let value = self.value_stack.pop().unwrap();
+                let index = self.value_stack.pop().unwrap();
+                self.emit_call_builtin(
+                    if self.module.local_table_index(table_index).is_some() {
+                        VMBuiltinFunctionIndex::get_table_set_index()
+                    } else {
+                        VMBuiltinFunctionIndex::get_imported_table_set_index()
+                    },
+                    // [vmctx, table_index, elem_index, reftype]
+                    &[Location::Imm32(table_index.index() as u32), index, value],
+                );
+            }
+            Operator::TableGet { table: index } => {
+                let table_index = TableIndex::new(index as _);
+                let index = self.value_stack.pop().unwrap();
+                self.emit_call_builtin(
+                    if self.module.local_table_index(table_index).is_some() {
+                        VMBuiltinFunctionIndex::get_table_get_index()
+                    } else {
+                        VMBuiltinFunctionIndex::get_imported_table_get_index()
+                    },
+                    // [vmctx, table_index, elem_index] -> reftype
+                    &[Location::Imm32(table_index.index() as u32), index],
+                );
+                let ret = self.push_value();
+                self.emit_store(Size::S64, GPR::X0, ret);
+            }
+            Operator::TableSize { table: index } => {
+                let table_index = TableIndex::new(index as _);
+                self.emit_call_builtin(
+                    if self.module.local_table_index(table_index).is_some() {
+                        VMBuiltinFunctionIndex::get_table_size_index()
+                    } else {
+                        VMBuiltinFunctionIndex::get_imported_table_size_index()
+                    },
+                    // [vmctx, table_index] -> i32
+                    &[Location::Imm32(table_index.index() as u32)],
+                );
+                let ret = self.push_value();
+                self.emit_store(Size::S32, GPR::X0, ret);
+            }
+            Operator::TableGrow { table: index } => {
+                let table_index = TableIndex::new(index as _);
+                let delta = self.value_stack.pop().unwrap();
+                let init_value = self.value_stack.pop().unwrap();
+                self.emit_call_builtin(
+                    if self.module.local_table_index(table_index).is_some() {
+                        VMBuiltinFunctionIndex::get_table_grow_index()
+                    } else {
+                        VMBuiltinFunctionIndex::get_imported_table_grow_index()
+                    },
+                    // [vmctx, init_value, delta, table_index] -> u32
+                    &[
+                        init_value,
+                        delta,
+                        Location::Imm32(table_index.index() as u32),
+                    ],
+                );
+                let ret = self.push_value();
+                self.emit_store(Size::S32, GPR::X0, ret);
+            }
+            Operator::TableCopy {
+                dst_table,
+                src_table,
+            } => {
+                let len = self.value_stack.pop().unwrap();
+                let src = self.value_stack.pop().unwrap();
+                let dest = self.value_stack.pop().unwrap();
+                self.emit_call_builtin(
+                    VMBuiltinFunctionIndex::get_table_copy_index(),
+                    // [vmctx, dst_table_index, src_table_index, dst, src, len]
+                    &[
+                        Location::Imm32(dst_table),
+                        Location::Imm32(src_table),
+                        dest,
+                        src,
+                        len,
+                    ],
+                );
+            }
+            Operator::TableFill { table } => {
+                let len = self.value_stack.pop().unwrap();
+                let val = self.value_stack.pop().unwrap();
+                let dest = self.value_stack.pop().unwrap();
+                self.emit_call_builtin(
+                    VMBuiltinFunctionIndex::get_table_fill_index(),
+                    // [vmctx, table_index, start_idx, item, len]
+                    &[Location::Imm32(table), dest, val, len],
+                );
+            }
+            Operator::TableInit { segment, table } => {
+                let len = self.value_stack.pop().unwrap();
+                let src = self.value_stack.pop().unwrap();
+                let dest = self.value_stack.pop().unwrap();
+                self.emit_call_builtin(
+                    VMBuiltinFunctionIndex::get_table_init_index(),
+                    // [vmctx, table_index, elem_index, dst, src, len]
+                    &[
+                        Location::Imm32(table),
+                        Location::Imm32(segment),
+                        dest,
+                        src,
+                        len,
+                    ],
+                );
+            }
+            Operator::ElemDrop { segment } => {
+                self.emit_call_builtin(
+                    VMBuiltinFunctionIndex::get_elem_drop_index(),
+                    // [vmctx, elem_index]
+                    &[Location::Imm32(segment)],
+                );
+            }
+            _ => {
+                return Err(CodegenError {
+                    message: format!("not yet implemented: {:?}", op),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn finalize(mut self, data: &FunctionBodyData) -> CompiledFunction {
+        // Generate actual code for special labels.
+        self.assembler
+            .emit_label(self.special_labels.integer_division_by_zero);
+        self.emit_trap(TrapCode::IntegerDivisionByZero);
+
+        self.assembler
+            .emit_label(self.special_labels.integer_overflow);
+        self.emit_trap(TrapCode::IntegerOverflow);
+
+        self.assembler
+            .emit_label(self.special_labels.bad_conversion_to_integer);
+        self.emit_trap(TrapCode::BadConversionToInteger);
+
+        self.assembler
+            .emit_label(self.special_labels.heap_access_oob);
+        self.emit_trap(TrapCode::HeapAccessOutOfBounds);
+
+        self.assembler
+            .emit_label(self.special_labels.table_access_oob);
+        self.emit_trap(TrapCode::TableAccessOutOfBounds);
+
+        self.assembler
+            .emit_label(self.special_labels.indirect_call_null);
+        self.emit_trap(TrapCode::IndirectCallToNull);
+
+        self.assembler.emit_label(self.special_labels.bad_signature);
+        self.emit_trap(TrapCode::BadSignature);
+
+        self.assembler
+            .emit_label(self.special_labels.gas_limit_exceeded);
+        self.emit_trap(TrapCode::GasExceeded);
+
+        self.assembler
+            .emit_label(self.special_labels.stack_overflow);
+        // The trap unwinds all the frames, so give the instance its whole
+        // stack back for the calls made after it.
+        self.assembler.emit_ldr(
+            Size::S32,
+            GPR::X9,
+            VMCTX,
+            self.vmoffsets.vmctx_stack_limit_initial_begin() as i32,
+        );
+        self.assembler.emit_str(
+            Size::S32,
+            GPR::X9,
+            VMCTX,
+            self.vmoffsets.vmctx_stack_limit_begin() as i32,
+        );
+        self.emit_trap(TrapCode::StackOverflow);
+
+        self.assembler.emit_label(self.special_labels.interrupted);
+        self.emit_trap(TrapCode::Interrupted);
+
+        // Now that the max stack depth is known, patch the frame size. `x19` and `x28`
+        // are already pushed by the prologue.
+        let slots = self.local_types.len() + self.max_value_slots;
+        let frame_size = align16(16 + slots * 8) - 16;
+        patch_mov_imm32(
+            &mut self.assembler,
+            self.frame_size_offset,
+            GPR::X16,
+            frame_size as u32,
+        );
+
+        let body_len = self.assembler.get_offset().0;
+        let instructions_address_map = self.instructions_address_map;
+        let address_map = get_function_address_map(instructions_address_map, data, body_len);
+
+        CompiledFunction {
+            body: FunctionBody {
+                body: self.assembler.finalize().unwrap().to_vec(),
+                unwind_info: None,
+            },
+            relocations: self.relocations,
+            jt_offsets: SecondaryMap::new(),
+            frame_info: CompiledFunctionFrameInfo {
+                traps: vec![],
+                address_map,
+            },
+        }
+    }
+}
+
+// Standard entry trampoline.
+pub(crate) fn gen_std_trampoline(
+    sig: &FunctionType,
+    _calling_convention: CallingConvention,
+) -> FunctionBody {
+    let mut a = Assembler::new(0);
+
+    let (func_ptr, args_rets) = (GPR::X19, GPR::X20);
+
+    a.emit_push_pair(FP, GPR::X30);
+    a.emit_mov_from_sp(FP);
+    // Used callee-saved registers
+    a.emit_push_pair(func_ptr, args_rets);
+
+    a.emit_mov(Size::S64, GPR::X1, func_ptr);
+    a.emit_mov(Size::S64, GPR::X2, args_rets);
+
+    // Multiple results are returned in registers or in a buffer we pass a
+    // pointer to in `x8`, which we unpack them from.
+    let return_layout = ReturnLayout::new(sig.results());
+    let return_buffer_size = return_layout
+        .as_ref()
+        .map_or(0, |layout| align16(layout.size) as u32);
+
+    // Prepare stack space, reserving the stack argument slots the callee may
+    // tail call with, followed by the buffer.
+    let n_stack_args = max(
+        (sig.params().len() + 1).saturating_sub(ARG_REGS),
+        RESERVED_STACK_ARGS,
+    );
+    let stack_bytes = align16(n_stack_args * 8) as u32;
+    if stack_bytes + return_buffer_size > 0 {
+        a.emit_sub_imm(SP, SP, stack_bytes + return_buffer_size);
+    }
+    if matches!(&return_layout, Some(layout) if layout.registers.is_none()) {
+        a.emit_add_imm(GPR::X8, SP, stack_bytes);
+    }
+
+    // Move arguments to their locations.
+    // `callee_vmctx` is already in the first argument register, so no need to move.
+    for i in 0..sig.params().len() {
+        let src_offset = (i * 16) as i32; // args_rets[i]
+        let index = i + 1;
+        if index < ARG_REGS {
+            a.emit_ldr(
+                Size::S64,
+                GPR::from_index(index).unwrap(),
+                args_rets,
+                src_offset,
+            );
+        } else {
+            a.emit_ldr(Size::S64, GPR::X9, args_rets, src_offset);
+            a.emit_str(Size::S64, GPR::X9, SP, ((index - ARG_REGS) * 8) as i32);
+        }
+    }
+
+    // Call.
+    a.emit_blr(func_ptr);
+
+    // Write return values.
+    if let Some(layout) = &return_layout {
+        let return_buffer = stack_bytes as i32;
+        match layout.registers.as_deref() {
+            Some(registers @ [ARM64Register::NEON(_), ..]) => {
+                for (i, (register, &(_, size))) in registers.iter().zip(&layout.fields).enumerate()
+                {
+                    if let ARM64Register::NEON(neon) = register {
+                        a.emit_str_neon(value_size(size), *neon, args_rets, (i * 16) as i32);
+                    }
+                }
+            }
+            registers => {
+                for (i, register) in registers.unwrap_or_default().iter().enumerate() {
+                    if let ARM64Register::GPR(gpr) = register {
+                        a.emit_str(Size::S64, *gpr, SP, return_buffer + (i * 8) as i32);
+                    }
+                }
+                for (i, &(offset, size)) in layout.fields.iter().enumerate() {
+                    a.emit_ldr(value_size(size), GPR::X9, SP, return_buffer + offset as i32);
+                    a.emit_str(Size::S64, GPR::X9, args_rets, (i * 16) as i32);
+                }
+            }
+        }
+    } else if !sig.results().is_empty() {
+        a.emit_str(Size::S64, GPR::X0, args_rets, 0);
+    }
+
+    // Restore callee-saved registers and the stack.
+    a.emit_ldp(func_ptr, args_rets, FP, -16);
+    a.emit_mov_to_sp(FP);
+    a.emit_pop_pair(FP, GPR::X30);
+
+    a.emit_ret();
+
+    FunctionBody {
+        body: a.finalize().unwrap().to_vec(),
+        unwind_info: None,
+    }
+}
+
+/// Generates dynamic import function call trampoline for a function type.
+pub(crate) fn gen_std_dynamic_import_trampoline(
+    vmoffsets: &VMOffsets,
+    sig: &FunctionType,
+    calling_convention: CallingConvention,
+) -> Result<FunctionBody, CompileError> {
+    let mut a = Assembler::new(0);
+
+    a.emit_push_pair(FP, GPR::X30);
+    a.emit_mov_from_sp(FP);
+
+    // Multiple results are returned as a C struct, in registers or in memory
+    // the caller passes a pointer to in `x8`.
+    let return_layout = ReturnLayout::new(sig.results());
+    let returns_in_memory = matches!(&return_layout, Some(layout) if layout.registers.is_none());
+
+    // Allocate argument array, followed by a slot saving the pointer to the
+    // return struct.
+    let values_size = 16 * max(sig.params().len(), sig.results().len());
+    let stack_offset = align16(values_size + if returns_in_memory { 8 } else { 0 }) as u32;
+    if stack_offset > 0 {
+        a.emit_sub_imm(SP, SP, stack_offset);
+    }
+    if returns_in_memory {
+        a.emit_str(Size::S64, GPR::X8, SP, values_size as i32);
+    }
+
+    // Copy arguments.
+    if !sig.params().is_empty() {
+        let mut argalloc = ArgumentRegisterAllocator::default();
+        argalloc.next(Type::I64, calling_convention)?; // skip VMContext
+
+        let mut stack_param_count: usize = 0;
+
+        for (i, ty) in sig.params().iter().enumerate() {
+            let dst_offset = (i * 16) as i32;
+            match argalloc.next(*ty, calling_convention)? {
+                Some(ARM64Register::GPR(gpr)) => a.emit_str(Size::S64, gpr, SP, dst_offset),
+                Some(ARM64Register::NEON(neon)) => a.emit_str_neon(Size::S64, neon, SP, dst_offset),
+                None => {
+                    a.emit_ldr(Size::S64, GPR::X9, FP, (16 + stack_param_count * 8) as i32);
+                    a.emit_str(Size::S64, GPR::X9, SP, dst_offset);
+                    stack_param_count += 1;
+                }
+            }
+            // Zero upper 64 bits.
+            a.emit_str(Size::S64, GPR::XzrSp, SP, dst_offset + 8);
+        }
+    }
+
+    // Load target address.
+    a.emit_ldr(
+        Size::S64,
+        GPR::X9,
+        GPR::X0,
+        vmoffsets.vmdynamicfunction_import_context_address() as i32,
+    );
+    // Load values array.
+    a.emit_mov_from_sp(GPR::X1);
+
+    // Call target.
+    a.emit_blr(GPR::X9);
+
+    // Fetch return values.
+    if let Some(layout) = &return_layout {
+        match layout.registers.as_deref() {
+            Some(registers @ [ARM64Register::NEON(_), ..]) => {
+                for (i, (register, &(_, size))) in registers.iter().zip(&layout.fields).enumerate()
+                {
+                    if let ARM64Register::NEON(neon) = register {
+                        a.emit_ldr_neon(value_size(size), *neon, SP, (i * 16) as i32);
+                    }
+                }
+            }
+            Some(registers) => {
+                // Pack the values array into the return struct in place. Each
+                // value is moved to a lower address than the ones not yet moved.
+                for (i, &(offset, size)) in layout.fields.iter().enumerate() {
+                    a.emit_ldr(value_size(size), GPR::X9, SP, (i * 16) as i32);
+                    a.emit_str(value_size(size), GPR::X9, SP, offset as i32);
+                }
+                for (i, register) in registers.iter().enumerate() {
+                    if let ARM64Register::GPR(gpr) = register {
+                        a.emit_ldr(Size::S64, *gpr, SP, (i * 8) as i32);
+                    }
+                }
+            }
+            None => {
+                a.emit_ldr(Size::S64, GPR::X10, SP, values_size as i32);
+                for (i, &(offset, size)) in layout.fields.iter().enumerate() {
+                    a.emit_ldr(value_size(size), GPR::X9, SP, (i * 16) as i32);
+                    a.emit_str(value_size(size), GPR::X9, GPR::X10, offset as i32);
+                }
+            }
+        }
+    } else if !sig.results().is_empty() {
+        a.emit_ldr(Size::S64, GPR::X0, SP, 0);
+        a.emit_ldr_neon(Size::S64, NEON::V0, SP, 0);
+    }
+
+    // Release values array and return.
+    a.emit_mov_to_sp(FP);
+    a.emit_pop_pair(FP, GPR::X30);
+    a.emit_ret();
+
+    Ok(FunctionBody {
+        body: a.finalize().unwrap().to_vec(),
+        unwind_info: None,
+    })
+}
+
+// Singlepass calls import functions through a trampoline.
+pub(crate) fn gen_import_call_trampoline(
+    vmoffsets: &VMOffsets,
+    index: FunctionIndex,
+    sig: &FunctionType,
+    calling_convention: CallingConvention,
+) -> Result<CustomSection, CompileError> {
+    let mut a = Assembler::new(0);
+
+    // Singlepass internally treats all arguments as integers, but the AAPCS64
+    // requires floating point arguments to be passed in NEON registers.
+    // Translation is expensive, so only do it if needed.
+    if sig
+        .params()
+        .iter()
+        .any(|&x| x == Type::F32 || x == Type::F64)
+    {
+        // Store all register arguments to the stack to prevent overwrite.
+        let n_reg_params = sig.params().len().min(ARG_REGS - 1);
+        let stack_offset = align16(n_reg_params * 8) as u32;
+        a.emit_sub_imm(SP, SP, stack_offset);
+        let mut param_locations: Vec<Location> = vec![];
+        for i in 0..sig.params().len() {
+            let loc = match incoming_arg_location(i + 1) {
+                Location::GPR(gpr) => {
+                    let loc = Location::Memory(SP, (i * 8) as i32);
+                    a.emit_str(Size::S64, gpr, SP, (i * 8) as i32);
+                    loc
+                }
+                _ => Location::Memory(SP, stack_offset as i32 + ((i + 1 - ARG_REGS) * 8) as i32),
+            };
+            param_locations.push(loc);
+        }
+
+        // Copy arguments.
+        let mut argalloc = ArgumentRegisterAllocator::default();
+        argalloc.next(Type::I64, calling_convention)?; // skip VMContext
+        let mut caller_stack_offset: i32 = 0;
+        for (i, ty) in sig.params().iter().enumerate() {
+            let (base, offset) = match param_locations[i] {
+                Location::Memory(base, offset) => (base, offset),
+                _ => unreachable!(),
+            };
+            match argalloc.next(*ty, calling_convention)? {
+                Some(ARM64Register::GPR(gpr)) => a.emit_ldr(Size::S64, gpr, base, offset),
+                Some(ARM64Register::NEON(neon)) => a.emit_ldr_neon(Size::S64, neon, base, offset),
+                None => {
+                    // No register can be allocated. Put this argument on the stack.
+                    //
+                    // Since here we never use fewer registers than by the original call, on the caller's frame
+                    // we always have enough space to store the rearranged arguments, and the copy "backward" between different
+                    // slots in the caller argument region will always work.
+                    a.emit_ldr(Size::S64, GPR::X9, base, offset);
+                    a.emit_str(
+                        Size::S64,
+                        GPR::X9,
+                        SP,
+                        stack_offset as i32 + caller_stack_offset,
+                    );
+                    caller_stack_offset += 8;
+                }
+            }
+        }
+
+        // Restore stack pointer.
+        a.emit_add_imm(SP, SP, stack_offset);
+    }
+
+    // Emits a tail call trampoline that loads the address of the target import function
+    // from Ctx and jumps to it.
+
+    let body_offset = vmoffsets.vmctx_vmfunction_import_body(index);
+    let vmctx_offset = vmoffsets.vmctx_vmfunction_import_vmctx(index);
+
+    // function pointer
+    a.emit_ldr(Size::S64, CALL_TARGET, GPR::X0, body_offset as i32);
+    // target vmctx
+    a.emit_ldr(Size::S64, GPR::X0, GPR::X0, vmctx_offset as i32);
+    a.emit_br(CALL_TARGET);
+
+    let section_body = SectionBody::new_with_vec(a.finalize().unwrap().to_vec());
+
+    Ok(CustomSection {
+        protection: CustomSectionProtection::ReadExecute,
+        bytes: section_body,
+        relocations: vec![],
+    })
+}
@@ -10,7 +10,7 @@ use wasmer_compiler::wasmparser::{
     MemoryImmediate, Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType,
 };
 use wasmer_compiler::{
-    CallingConvention, CompiledFunction, CompiledFunctionFrameInfo, CustomSection,
+    CallingConvention, CompileError, CompiledFunction, CompiledFunctionFrameInfo, CustomSection,
     CustomSectionProtection, FunctionBody, FunctionBodyData, InstructionAddressMap,
     ModuleTranslationState, Relocation, RelocationKind, RelocationTarget, SectionBody,
     SectionIndex, SourceLoc,
@@ -20,8 +20,8 @@ use wasmer_types::{
     FastGasCounter, FunctionType,
 };
 use wasmer_types::{
-    FunctionIndex, GlobalIndex, LocalFunctionIndex, LocalMemoryIndex, MemoryIndex, ModuleInfo,
-    SignatureIndex, TableIndex, Type,
+    FunctionIndex, GlobalIndex, LocalFunctionIndex, MemoryIndex, ModuleInfo, SignatureIndex,
+    TableIndex, TagIndex, Type,
 };
 use wasmer_vm::{TableStyle, TrapCode, VMBuiltinFunctionIndex, VMOffsets};
 
@@ -72,6 +72,10 @@ pub(crate) struct FuncGen<'a> {
     /// Location to patch when we know the max stack depth.
     stack_check_offset: AssemblyOffset,
 
+    /// Locations to patch with the max stack depth in the stack limit
+    /// releases of tail calls.
+    tail_call_stack_check_offsets: Vec<AssemblyOffset>,
+
     /// Metadata about floating point values on the stack.
     fp_stack: Vec<FloatValue>,
 
@@ -100,6 +104,30 @@ pub(crate) struct FuncGen<'a> {
 
     /// Calling convention to use.
     calling_convention: CallingConvention,
+
+    /// Location of the hidden local holding the exceptions caught by the
+    /// activation, if exception handling is enabled.
+    caught_exceptions: Option<Location>,
+
+    /// Number of `try` blocks seen so far.
+    try_count: u32,
+
+    /// Machine stack offset right after the locals.
+    locals_stack_offset: usize,
+
+    /// How the function returns its values, if it has more than one.
+    return_layout: Option<ReturnLayout>,
+
+    /// Location of the hidden local holding the pointer to the memory the
+    /// function returns its values to, if its caller provides it.
+    return_pointer: Option<Location>,
+
+    /// Size of the scratch area the values of blocks, calls and returns
+    /// with several results are moved through.
+    return_area_size: usize,
+
+    /// Location of the start of the scratch area.
+    return_area: Location,
 }
 
 struct SpecialLabelSet {
@@ -112,41 +140,42 @@ struct SpecialLabelSet {
     bad_signature: DynamicLabel,
     gas_limit_exceeded: DynamicLabel,
     stack_overflow: DynamicLabel,
+    interrupted: DynamicLabel,
 }
 
 /// Metadata about a floating-point value.
 #[derive(Copy, Clone, Debug)]
-struct FloatValue {
+pub(crate) struct FloatValue {
     /// Do we need to canonicalize the value before its bit pattern is next observed? If so, how?
-    canonicalization: Option<CanonicalizeType>,
+    pub(crate) canonicalization: Option<CanonicalizeType>,
 
     /// Corresponding depth in the main value stack.
-    depth: usize,
+    pub(crate) depth: usize,
 }
 
 impl FloatValue {
-    fn new(depth: usize) -> Self {
+    pub(crate) fn new(depth: usize) -> Self {
         FloatValue {
             canonicalization: None,
             depth,
         }
     }
 
-    fn cncl_f32(depth: usize) -> Self {
+    pub(crate) fn cncl_f32(depth: usize) -> Self {
         FloatValue {
             canonicalization: Some(CanonicalizeType::F32),
             depth,
         }
     }
 
-    fn cncl_f64(depth: usize) -> Self {
+    pub(crate) fn cncl_f64(depth: usize) -> Self {
         FloatValue {
             canonicalization: Some(CanonicalizeType::F64),
             depth,
         }
     }
 
-    fn promote(self, depth: usize) -> FloatValue {
+    pub(crate) fn promote(self, depth: usize) -> FloatValue {
         FloatValue {
             canonicalization: match self.canonicalization {
                 Some(CanonicalizeType::F32) => Some(CanonicalizeType::F64),
@@ -157,7 +186,7 @@ impl FloatValue {
         }
     }
 
-    fn demote(self, depth: usize) -> FloatValue {
+    pub(crate) fn demote(self, depth: usize) -> FloatValue {
         FloatValue {
             canonicalization: match self.canonicalization {
                 Some(CanonicalizeType::F64) => Some(CanonicalizeType::F32),
@@ -172,7 +201,7 @@ impl FloatValue {
 /// Type of a pending canonicalization floating point value.
 /// Sometimes we don't have the type information elsewhere and therefore we need to track it here.
 #[derive(Copy, Clone, Debug)]
-enum CanonicalizeType {
+pub(crate) enum CanonicalizeType {
     F32,
     F64,
 }
@@ -186,7 +215,7 @@ impl CanonicalizeType {
     }
 }
 
-trait PopMany<T> {
+pub(crate) trait PopMany<T> {
     fn peek1(&self) -> Result<&T, CodegenError>;
     fn pop1(&mut self) -> Result<T, CodegenError>;
     fn pop2(&mut self) -> Result<(T, T), CodegenError>;
@@ -216,7 +245,7 @@ impl<T> PopMany<T> for Vec<T> {
     }
 }
 
-trait WpTypeExt {
+pub(crate) trait WpTypeExt {
     fn is_float(&self) -> bool;
 }
 
@@ -234,9 +263,12 @@ pub(crate) struct ControlFrame {
     pub(crate) label: DynamicLabel,
     pub(crate) loop_like: bool,
     pub(crate) if_else: IfElseState,
+    pub(crate) params: SmallVec<[WpType; 1]>,
     pub(crate) returns: SmallVec<[WpType; 1]>,
     pub(crate) value_stack_depth: usize,
     pub(crate) fp_stack_depth: usize,
+    pub(crate) try_state: Option<TryState>,
+    pub(crate) loop_params: Option<LoopParams>,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -246,6 +278,38 @@ pub(crate) enum IfElseState {
     Else,
 }
 
+/// State of a `try` block.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct TryState {
+    /// Ordinal of the `try` in the function, which is also the slot its
+    /// catch clauses store caught exceptions into.
+    pub(crate) slot: u32,
+    /// Machine stack offset on entry, restored when catching.
+    pub(crate) stack_offset: usize,
+    /// Where the exceptions propagating through the block jump to: the first
+    /// catch clause from the body, and the next one from the catch clauses.
+    pub(crate) landing: DynamicLabel,
+    /// Whether the body is over and the catch clauses are being emitted.
+    pub(crate) catching: bool,
+}
+
+/// Where a loop holds its parameters at the start of each iteration, for
+/// branches to it to move the new ones to.
+#[derive(Debug, Clone)]
+pub(crate) struct LoopParams {
+    pub(crate) locations: SmallVec<[Location; 1]>,
+    /// Machine stack offset at the start of each iteration.
+    pub(crate) stack_offset: usize,
+}
+
+/// The stack argument slots every call reserves for the callee, even when
+/// it takes fewer arguments on the stack.
+///
+/// A tail call passes its stack arguments in the ones of the function making
+/// it, so this lets a function tail call any function taking at most as many
+/// arguments on the stack, whatever its own parameters.
+pub(crate) const RESERVED_STACK_ARGS: usize = 8;
+
 #[derive(Debug)]
 pub(crate) struct CodegenError {
     pub(crate) message: String,
@@ -297,8 +361,11 @@ impl<'a> FuncGen<'a> {
         I2O1 { loc_a, loc_b, ret }
     }
 
-    fn emit_call(&mut self, function_index: u32) -> Result<(), CodegenError> {
+    fn emit_call(&mut self, function_index: u32, tail_call: bool) -> Result<(), CodegenError> {
         let function_index = function_index as usize;
+        if tail_call {
+            self.emit_caught_exceptions_drop()?;
+        }
 
         let sig_index = *self
             .module
@@ -310,6 +377,8 @@ impl<'a> FuncGen<'a> {
             sig.params().iter().cloned().map(type_to_wp_type).collect();
         let return_types: SmallVec<[WpType; 1]> =
             sig.results().iter().cloned().map(type_to_wp_type).collect();
+        let return_layout = ReturnLayout::new(sig.results(), self.calling_convention);
+        let vmctx_param = self.vmctx_param_location(return_layout.as_ref());
 
         let params: SmallVec<[_; 8]> = self
             .value_stack
@@ -339,63 +408,650 @@ impl<'a> FuncGen<'a> {
         }
 
         if let Some(intrinsic) = self.check_intrinsic(function_index, &params) {
-            self.emit_intrinsic(intrinsic, &params)?
+            self.emit_intrinsic(intrinsic, &params)?;
+            if tail_call {
+                self.emit_return()?;
+            }
+        } else if tail_call {
+            self.check_tail_call(params.len())?;
+            self.emit_tail_call(&params, |this| {
+                this.emit_function_address(function_index);
+                this.assembler.emit_mov(
+                    Size::S64,
+                    Location::GPR(Machine::get_vmctx_reg()),
+                    vmctx_param,
+                );
+            })?;
+            self.machine
+                .release_locations_only_stack(&mut self.assembler, &params);
+            self.unreachable_depth = 1;
         } else {
-            let reloc_at = self.assembler.get_offset().0 + self.assembler.arch_mov64_imm_offset();
-            // Imported functions are called through trampolines placed as custom sections.
-            let imports = self.module.import_counts.functions as usize;
-            let reloc_target = if function_index < imports {
-                RelocationTarget::CustomSection(SectionIndex::new(function_index))
-            } else {
-                RelocationTarget::LocalFunc(LocalFunctionIndex::new(function_index - imports))
-            };
-            self.relocations.push(Relocation {
-                kind: RelocationKind::Abs8,
-                reloc_target,
-                offset: reloc_at as u32,
-                addend: 0,
-            });
-
             // RAX is preserved on entry to `emit_call_sysv` callback.
-            // The Imm64 value is relocated by the JIT linker.
-            self.assembler.emit_mov(
-                Size::S64,
-                Location::Imm64(std::u64::MAX),
-                Location::GPR(GPR::RAX),
-            );
+            self.emit_function_address(function_index);
 
-            self.emit_call_native(
+            self.emit_call_native_with_return_layout(
                 |this| {
                     this.assembler.emit_call_location(Location::GPR(GPR::RAX));
                 },
                 params.iter().copied(),
+                return_layout.as_ref(),
             )?;
 
             self.machine
                 .release_locations_only_stack(&mut self.assembler, &params);
 
-            if !return_types.is_empty() {
-                let ret = self.machine.acquire_locations(
-                    &mut self.assembler,
-                    &[(return_types[0])],
-                    false,
-                )[0];
-                self.value_stack.push(ret);
-                if return_types[0].is_float() {
-                    self.assembler
-                        .emit_mov(Size::S64, Location::XMM(XMM::XMM0), ret);
+            self.emit_call_results(&return_types, return_layout.as_ref());
+            self.emit_exception_check()?;
+        }
+
+        Ok(())
+    }
+
+    /// Loads the address of the function at `function_index` into RAX.
+    fn emit_function_address(&mut self, function_index: usize) {
+        let reloc_at = self.assembler.get_offset().0 + self.assembler.arch_mov64_imm_offset();
+        // Imported functions are called through trampolines placed as custom sections.
+        let imports = self.module.import_counts.functions as usize;
+        let reloc_target = if function_index < imports {
+            RelocationTarget::CustomSection(SectionIndex::new(function_index))
+        } else {
+            RelocationTarget::LocalFunc(LocalFunctionIndex::new(function_index - imports))
+        };
+        self.relocations.push(Relocation {
+            kind: RelocationKind::Abs8,
+            reloc_target,
+            offset: reloc_at as u32,
+            addend: 0,
+        });
+
+        // The Imm64 value is relocated by the JIT linker.
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Imm64(std::u64::MAX),
+            Location::GPR(GPR::RAX),
+        );
+    }
+
+    /// Index of the parameter location of the first wasm parameter of the
+    /// current function, after the return pointer if any and the vmctx.
+    fn first_param_index(&self) -> usize {
+        if self.return_pointer.is_some() {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Location the vmctx is passed in to a callee returning its results as
+    /// laid out by `return_layout`.
+    fn vmctx_param_location(&self, return_layout: Option<&ReturnLayout>) -> Location {
+        let index = match return_layout {
+            Some(layout) if layout.registers.is_none() => 1,
+            _ => 0,
+        };
+        Machine::get_param_location(index, self.calling_convention)
+    }
+
+    /// Checks that a callee taking `n_params` parameters can be tail called,
+    /// that is, that its stack arguments fit in the ones of the current
+    /// function, which span at least [`RESERVED_STACK_ARGS`] slots.
+    fn check_tail_call(&self, n_params: usize) -> Result<(), CodegenError> {
+        let calling_convention = self.calling_convention;
+        let first_param = self.first_param_index();
+        let stack_params = |n: usize| {
+            (0..n)
+                .filter(|i| {
+                    matches!(
+                        Machine::get_param_location(first_param + i, calling_convention),
+                        Location::Memory(_, _)
+                    )
+                })
+                .count()
+        };
+        let available = max(
+            stack_params(self.signature.params().len()),
+            RESERVED_STACK_ARGS,
+        );
+        if stack_params(n_params) > available {
+            return Err(CodegenError {
+                message: format!(
+                    "return_call: the callee takes more than {} arguments on the stack",
+                    available
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Emits a tail call: the frame of the current function is torn down and
+    /// the callee is jumped to, so that it returns straight to our caller.
+    ///
+    /// `cb` must load the vmctx of the callee into its parameter location and
+    /// its address into RAX, and may only clobber RAX.
+    ///
+    /// The callee returns the same results as the current function, so our
+    /// return pointer, if any, is passed along as is.
+    fn emit_tail_call<F: FnOnce(&mut Self)>(
+        &mut self,
+        params: &[Location],
+        cb: F,
+    ) -> Result<(), CodegenError> {
+        let calling_convention = self.calling_convention;
+        let first_param = self.first_param_index();
+
+        // Copy the arguments out of the value stack first, as they may live in
+        // the registers they are passed in.
+        let staging_size = params.len() * 8;
+        if staging_size > 0 {
+            self.assembler.emit_sub(
+                Size::S64,
+                Location::Imm32(staging_size as u32),
+                Location::GPR(GPR::RSP),
+            );
+        }
+        for (i, param) in params.iter().enumerate() {
+            let slot = Location::Memory(GPR::RSP, (i * 8) as i32);
+            if let Location::XMM(_) = *param {
+                self.assembler.emit_mov(Size::S64, *param, slot);
+            } else {
+                self.assembler
+                    .emit_mov(Size::S64, *param, Location::GPR(GPR::RCX));
+                self.assembler
+                    .emit_mov(Size::S64, Location::GPR(GPR::RCX), slot);
+            }
+        }
+
+        // The callee accounts for its own stack usage, so release ours now,
+        // as the function epilogue does. The depth is patched at the end.
+        self.assembler.emit_add(
+            Size::S32,
+            Location::Imm32(0x7fff_ffff),
+            Location::Memory(
+                Machine::get_vmctx_reg(),
+                self.vmoffsets.vmctx_stack_limit_begin() as i32,
+            ),
+        );
+        self.tail_call_stack_check_offsets
+            .push(AssemblyOffset(self.assembler.offset().0 - 4));
+
+        // Stack arguments overwrite our own, which `check_tail_call` made sure
+        // leaves enough room.
+        for i in 0..params.len() {
+            let loc = Machine::get_param_location(first_param + i, calling_convention);
+            if let Location::Memory(_, _) = loc {
+                self.assembler.emit_mov(
+                    Size::S64,
+                    Location::Memory(GPR::RSP, (i * 8) as i32),
+                    Location::GPR(GPR::RCX),
+                );
+                self.assembler
+                    .emit_mov(Size::S64, Location::GPR(GPR::RCX), loc);
+            }
+        }
+
+        cb(self);
+
+        for i in 0..params.len() {
+            let loc = Machine::get_param_location(first_param + i, calling_convention);
+            if let Location::GPR(_) = loc {
+                self.assembler
+                    .emit_mov(Size::S64, Location::Memory(GPR::RSP, (i * 8) as i32), loc);
+            }
+        }
+        if let Some(return_pointer) = self.return_pointer {
+            self.assembler.emit_mov(
+                Size::S64,
+                return_pointer,
+                Machine::get_param_location(0, calling_convention),
+            );
+        }
+
+        self.machine
+            .finalize_locals(&mut self.assembler, &self.locals, calling_convention);
+        self.assembler
+            .emit_mov(Size::S64, Location::GPR(GPR::RBP), Location::GPR(GPR::RSP));
+        self.assembler.emit_pop(Size::S64, Location::GPR(GPR::RBP));
+        self.assembler.emit_jmp_location(Location::GPR(GPR::RAX));
+
+        Ok(())
+    }
+
+    /// Returns the values on top of the stack, if any, from the function.
+    fn emit_return(&mut self) -> Result<(), CodegenError> {
+        self.emit_branch(0)?;
+        self.unreachable_depth = 1;
+        Ok(())
+    }
+
+    /// Returns the parameter and result types of a block of type `ty`.
+    fn block_signature(
+        &self,
+        ty: WpTypeOrFuncType,
+    ) -> (SmallVec<[WpType; 1]>, SmallVec<[WpType; 1]>) {
+        match ty {
+            WpTypeOrFuncType::Type(WpType::EmptyBlockType) => (smallvec![], smallvec![]),
+            WpTypeOrFuncType::Type(inner_ty) => (smallvec![], smallvec![inner_ty]),
+            WpTypeOrFuncType::FuncType(index) => {
+                let sig = &self.module.signatures[SignatureIndex::from_u32(index)];
+                (
+                    sig.params().iter().cloned().map(type_to_wp_type).collect(),
+                    sig.results().iter().cloned().map(type_to_wp_type).collect(),
+                )
+            }
+        }
+    }
+
+    /// Returns the length of the FP stack below the value at `depth`.
+    fn fp_stack_depth_at(&self, depth: usize) -> usize {
+        self.fp_stack
+            .iter()
+            .take_while(|fp| fp.depth < depth)
+            .count()
+    }
+
+    /// Returns the pending canonicalization of the value at `depth`, if it
+    /// has one and canonicalization is enabled.
+    fn pending_canonicalization(&self, depth: usize) -> Option<CanonicalizeType> {
+        if !self.assembler.arch_supports_canonicalize_nan()
+            || !self.config.enable_nan_canonicalization
+        {
+            return None;
+        }
+        self.fp_stack
+            .iter()
+            .rev()
+            .take_while(|fp| fp.depth >= depth)
+            .find(|fp| fp.depth == depth)
+            .and_then(|fp| fp.canonicalization)
+    }
+
+    /// Moves the value at `depth` to `dst`, canonicalizing it if needed.
+    fn emit_move_value(&mut self, depth: usize, dst: Location) {
+        let loc = self.value_stack[depth];
+        match self.pending_canonicalization(depth) {
+            Some(canonicalization) => self.canonicalize_nan(canonicalization.to_size(), loc, dst),
+            None => {
+                if loc != dst {
+                    self.emit_relaxed_binop(Assembler::emit_mov, Size::S64, loc, dst);
+                }
+            }
+        }
+    }
+
+    /// Returns where the values of the block at `frame_index` go in the
+    /// return area, as offsets and sizes: the values of the function are laid
+    /// out as it returns them, and those of other blocks in 8-byte slots.
+    fn return_area_fields(&self, frame_index: usize) -> SmallVec<[(i32, Size); 4]> {
+        match (frame_index, &self.return_layout) {
+            (0, Some(layout)) => return_layout_fields(layout),
+            _ => return_area_slots(self.control_stack[frame_index].returns.len()),
+        }
+    }
+
+    /// Returns the location at `offset` in the return area.
+    fn return_area_at(&self, offset: i32) -> Location {
+        memory_at(self.return_area, offset)
+    }
+
+    /// Stores the values on top of the stack to the return area.
+    fn emit_store_values(&mut self, fields: &[(i32, Size)]) {
+        let depth = self.value_stack.len() - fields.len();
+        for (i, &(offset, size)) in fields.iter().enumerate() {
+            self.emit_move_value(depth + i, Location::GPR(GPR::RAX));
+            let dst = self.return_area_at(offset);
+            self.assembler.emit_mov(size, Location::GPR(GPR::RAX), dst);
+        }
+    }
+
+    /// Pushes values of type `tys` loaded from the return area.
+    fn emit_load_values(&mut self, tys: &[WpType], fields: &[(i32, Size)]) {
+        let locs = self
+            .machine
+            .acquire_locations(&mut self.assembler, tys, false);
+        for ((loc, ty), &(offset, size)) in locs.iter().zip(tys.iter()).zip(fields.iter()) {
+            let src = self.return_area_at(offset);
+            self.emit_relaxed_binop(Assembler::emit_mov, size, src, *loc);
+            self.value_stack.push(*loc);
+            if ty.is_float() {
+                self.fp_stack
+                    .push(FloatValue::new(self.value_stack.len() - 1));
+            }
+        }
+    }
+
+    /// Moves the results of the block at `frame_index` from the top of the
+    /// stack to RAX, or to the return area if it has several.
+    fn emit_block_results(&mut self, frame_index: usize) {
+        match self.control_stack[frame_index].returns.len() {
+            0 => {}
+            1 => self.emit_move_value(self.value_stack.len() - 1, Location::GPR(GPR::RAX)),
+            _ => {
+                let fields = self.return_area_fields(frame_index);
+                self.emit_store_values(&fields);
+            }
+        }
+    }
+
+    /// Pushes the results of type `returns` of a block, moved by
+    /// `emit_block_results`.
+    fn emit_block_results_arrival(&mut self, returns: &[WpType]) {
+        match returns.len() {
+            0 => {}
+            1 => {
+                let loc = self
+                    .machine
+                    .acquire_locations(&mut self.assembler, returns, false)[0];
+                self.assembler
+                    .emit_mov(Size::S64, Location::GPR(GPR::RAX), loc);
+                self.value_stack.push(loc);
+                if returns[0].is_float() {
                     self.fp_stack
                         .push(FloatValue::new(self.value_stack.len() - 1));
-                } else {
+                    // we already canonicalized at the `Br*` instruction or here previously.
+                }
+            }
+            _ => {
+                let fields = return_area_slots(returns.len());
+                self.emit_load_values(returns, &fields);
+            }
+        }
+    }
+
+    /// Branches to the block at `frame_index`, with the values it expects on
+    /// top of the stack.
+    fn emit_branch(&mut self, frame_index: usize) -> Result<(), CodegenError> {
+        let frame = &self.control_stack[frame_index];
+        let label = frame.label;
+        match frame.loop_params.clone() {
+            Some(loop_params) => {
+                // The new parameters may be where the current ones are, so
+                // they go through the stack below everything in use.
+                let n = loop_params.locations.len();
+                let depth = self.value_stack.len() - n;
+                let low = max(self.machine.get_stack_offset(), loop_params.stack_offset) + 32;
+                self.assembler.emit_lea(
+                    Size::S64,
+                    Location::Memory(GPR::RBP, -((low + n * 8) as i32)),
+                    Location::GPR(GPR::RSP),
+                );
+                for i in 0..n {
+                    self.emit_move_value(depth + i, Location::GPR(GPR::RAX));
+                    self.assembler.emit_mov(
+                        Size::S64,
+                        Location::GPR(GPR::RAX),
+                        Location::Memory(GPR::RSP, (i * 8) as i32),
+                    );
+                }
+                for (i, loc) in loop_params.locations.iter().enumerate() {
+                    self.assembler.emit_mov(
+                        Size::S64,
+                        Location::Memory(GPR::RSP, (i * 8) as i32),
+                        Location::GPR(GPR::RAX),
+                    );
                     self.assembler
-                        .emit_mov(Size::S64, Location::GPR(GPR::RAX), ret);
+                        .emit_mov(Size::S64, Location::GPR(GPR::RAX), *loc);
+                }
+                self.assembler.emit_lea(
+                    Size::S64,
+                    Location::Memory(GPR::RBP, -((loop_params.stack_offset + 32) as i32)),
+                    Location::GPR(GPR::RSP),
+                );
+            }
+            None => {
+                if !frame.loop_like {
+                    self.emit_block_results(frame_index);
+                }
+                let frame = &self.control_stack[frame_index];
+                let released = &self.value_stack[frame.value_stack_depth..];
+                self.machine
+                    .release_locations_keep_state(&mut self.assembler, released);
+            }
+        }
+        self.assembler.emit_jmp(Condition::None, label);
+        Ok(())
+    }
+
+    /// Ends the `then` branch of the innermost `if` block and starts its
+    /// `else` branch.
+    fn emit_else(&mut self, was_unreachable: bool) -> Result<(), CodegenError> {
+        let frame_index = self.control_stack.len() - 1;
+        if !was_unreachable {
+            self.emit_block_results(frame_index);
+        }
+
+        self.update_max_stack_depth();
+
+        // The parameters stay for the `else` branch, which the `then` branch
+        // got copies of.
+        let frame = &self.control_stack[frame_index];
+        let depth = frame.value_stack_depth + frame.params.len();
+        let released: &[Location] = &self.value_stack[depth..];
+        self.machine
+            .release_locations(&mut self.assembler, released);
+        self.value_stack.truncate(depth);
+        let fp_depth = self.fp_stack_depth_at(depth);
+        self.fp_stack.truncate(fp_depth);
+
+        let frame = &mut self.control_stack[frame_index];
+        match frame.if_else {
+            IfElseState::If(label) => {
+                self.assembler.emit_jmp(Condition::None, frame.label);
+                self.assembler.emit_label(label);
+                frame.if_else = IfElseState::Else;
+            }
+            _ => {
+                return Err(CodegenError {
+                    message: "Else: frame.if_else unreachable code".to_string(),
+                })
+            }
+        }
+        Ok(())
+    }
+
+    /// Pushes the results of type `return_types` of the function just
+    /// called, returned as `layout` describes if there are several.
+    fn emit_call_results(&mut self, return_types: &[WpType], layout: Option<&ReturnLayout>) {
+        match layout {
+            None => {
+                if !return_types.is_empty() {
+                    let ret =
+                        self.machine
+                            .acquire_locations(&mut self.assembler, return_types, false)[0];
+                    self.value_stack.push(ret);
+                    if return_types[0].is_float() {
+                        self.assembler
+                            .emit_mov(Size::S64, Location::XMM(XMM::XMM0), ret);
+                        self.fp_stack
+                            .push(FloatValue::new(self.value_stack.len() - 1));
+                    } else {
+                        self.assembler
+                            .emit_mov(Size::S64, Location::GPR(GPR::RAX), ret);
+                    }
                 }
             }
+            Some(layout) => {
+                // Values returned in registers go through the return area as
+                // well, as the stack can't be allocated before they are saved.
+                emit_store_return_registers(&mut self.assembler, layout, self.return_area);
+                let fields = return_layout_fields(layout);
+                self.emit_load_values(return_types, &fields);
+            }
         }
+    }
+
+    /// Calls the builtin function at `index`, with vmctx as the first
+    /// parameter.
+    fn emit_call_builtin<I: Iterator<Item = Location>>(
+        &mut self,
+        index: VMBuiltinFunctionIndex,
+        params: I,
+    ) -> Result<(), CodegenError> {
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Memory(
+                Machine::get_vmctx_reg(),
+                self.vmoffsets.vmctx_builtin_function(index) as i32,
+            ),
+            Location::GPR(GPR::RAX),
+        );
+        self.emit_call_native(
+            |this| {
+                this.assembler.emit_call_register(GPR::RAX);
+            },
+            params,
+        )
+    }
+
+    /// Where the exceptions propagating out of the `depth` outermost control
+    /// frames jump to: the catch clauses of the innermost `try` body among
+    /// them, or else the function epilogue, which returns with the
+    /// exception still pending for the caller to propagate.
+    fn exception_target(&self, depth: usize) -> DynamicLabel {
+        self.control_stack[..depth]
+            .iter()
+            .rev()
+            .find_map(|frame| match frame.try_state {
+                Some(TryState {
+                    catching: false,
+                    landing,
+                    ..
+                }) => Some(landing),
+                _ => None,
+            })
+            .unwrap_or(self.control_stack[0].label)
+    }
 
+    /// Propagates the exception thrown by the function just called, if any.
+    ///
+    /// The runtime is only asked whether the callee threw when some thread
+    /// is propagating an exception.
+    fn emit_exception_check(&mut self) -> Result<(), CodegenError> {
+        let no_exception = self.assembler.get_label();
+        let tmp = self.machine.acquire_temp_gpr().unwrap();
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Memory(
+                Machine::get_vmctx_reg(),
+                self.vmoffsets.vmctx_pending_exceptions_pointer() as i32,
+            ),
+            Location::GPR(tmp),
+        );
+        self.assembler
+            .emit_cmp(Size::S32, Location::Imm32(0), Location::Memory(tmp, 0));
+        self.machine.release_temp_gpr(tmp);
+        self.assembler.emit_jmp(Condition::Equal, no_exception);
+
+        self.emit_call_builtin(
+            VMBuiltinFunctionIndex::get_exception_pending_index(),
+            iter::empty(),
+        )?;
+        self.assembler
+            .emit_cmp(Size::S32, Location::Imm32(0), Location::GPR(GPR::RAX));
+        let target = self.exception_target(self.control_stack.len());
+        self.assembler.emit_jmp(Condition::NotEqual, target);
+        self.assembler.emit_label(no_exception);
         Ok(())
     }
 
+    /// Allocates the caught exceptions of the activation unless it already
+    /// has them, and returns their location.
+    fn emit_caught_exceptions(&mut self) -> Result<Location, CodegenError> {
+        let caught = self.caught_exceptions.unwrap();
+        let allocated = self.assembler.get_label();
+        self.emit_relaxed_binop(Assembler::emit_cmp, Size::S64, Location::Imm32(0), caught);
+        self.assembler.emit_jmp(Condition::NotEqual, allocated);
+        self.emit_call_builtin(
+            VMBuiltinFunctionIndex::get_exception_caught_new_index(),
+            iter::empty(),
+        )?;
+        self.emit_relaxed_binop(
+            Assembler::emit_mov,
+            Size::S64,
+            Location::GPR(GPR::RAX),
+            caught,
+        );
+        self.assembler.emit_label(allocated);
+        Ok(caught)
+    }
+
+    /// Frees the caught exceptions of the activation before it is replaced
+    /// by a tail call.
+    fn emit_caught_exceptions_drop(&mut self) -> Result<(), CodegenError> {
+        let caught = match self.caught_exceptions {
+            Some(caught) => caught,
+            None => return Ok(()),
+        };
+        let done = self.assembler.get_label();
+        self.emit_relaxed_binop(Assembler::emit_cmp, Size::S64, Location::Imm32(0), caught);
+        self.assembler.emit_jmp(Condition::Equal, done);
+        self.emit_call_builtin(
+            VMBuiltinFunctionIndex::get_exception_caught_drop_index(),
+            iter::once(caught),
+        )?;
+        self.emit_relaxed_binop(Assembler::emit_mov, Size::S64, Location::Imm32(0), caught);
+        self.assembler.emit_label(done);
+        Ok(())
+    }
+
+    /// Frees the caught exceptions of the activation in the function
+    /// epilogue, keeping the return value in RAX.
+    ///
+    /// Exceptions jump to the epilogue from anywhere in the function, so
+    /// the machine state can't be used to call the builtin.
+    fn emit_epilogue_caught_exceptions_drop(&mut self) {
+        let caught = match self.caught_exceptions {
+            Some(caught) => caught,
+            None => return,
+        };
+        let calling_convention = self.calling_convention;
+        let done = self.assembler.get_label();
+        self.assembler.emit_lea(
+            Size::S64,
+            Location::Memory(GPR::RBP, -((self.locals_stack_offset + 32) as i32)),
+            Location::GPR(GPR::RSP),
+        );
+        self.emit_relaxed_binop(Assembler::emit_cmp, Size::S64, Location::Imm32(0), caught);
+        self.assembler.emit_jmp(Condition::Equal, done);
+
+        self.assembler.emit_push(Size::S64, Location::GPR(GPR::RAX));
+        // Keep the stack 16-byte aligned, given RAX is pushed.
+        let mut padding = ((self.locals_stack_offset + 8) % 16) as u32;
+        if calling_convention == CallingConvention::WindowsFastcall {
+            padding += 32;
+        }
+        if padding > 0 {
+            self.assembler
+                .emit_sub(Size::S64, Location::Imm32(padding), Location::GPR(GPR::RSP));
+        }
+        self.assembler.emit_mov(
+            Size::S64,
+            caught,
+            Machine::get_param_location(1, calling_convention),
+        );
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::GPR(Machine::get_vmctx_reg()),
+            Machine::get_param_location(0, calling_convention),
+        );
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Memory(
+                Machine::get_vmctx_reg(),
+                self.vmoffsets.vmctx_builtin_function(
+                    VMBuiltinFunctionIndex::get_exception_caught_drop_index(),
+                ) as i32,
+            ),
+            Location::GPR(GPR::RAX),
+        );
+        self.assembler.emit_call_register(GPR::RAX);
+        if padding > 0 {
+            self.assembler
+                .emit_add(Size::S64, Location::Imm32(padding), Location::GPR(GPR::RSP));
+        }
+        self.assembler.emit_pop(Size::S64, Location::GPR(GPR::RAX));
+        self.assembler.emit_label(done);
+    }
+
     fn check_intrinsic(
         &mut self,
         index: usize,
@@ -411,10 +1067,17 @@ impl<'a> FuncGen<'a> {
             .get(&function_index)?;
         // TODO: can keep intrinsics in above map, but not sure if we'll have
         //   significant amount of them to make it important.
+        let constants: SmallVec<[Option<u32>; 8]> = params
+            .iter()
+            .map(|param| match *param {
+                Location::Imm32(value) => Some(value),
+                _ => None,
+            })
+            .collect();
         for intrinsic in &self.config.intrinsics {
             if intrinsic.name == *import_name
                 && intrinsic.signature == *signature
-                && intrinsic.is_params_ok(params)
+                && intrinsic.is_params_ok(&constants)
             {
                 return Some(intrinsic.clone());
             }
@@ -1175,8 +1838,24 @@ impl<'a> FuncGen<'a> {
         &mut self,
         cb: F,
         params: I,
+    ) -> Result<(), CodegenError> {
+        self.emit_call_native_with_return_layout(cb, params, None)
+    }
+
+    /// Emits a call sequence for a callee returning its results as laid out
+    /// by `return_layout`, passing the return area as the first parameter if
+    /// they are returned in memory.
+    fn emit_call_native_with_return_layout<I: Iterator<Item = Location>, F: FnOnce(&mut Self)>(
+        &mut self,
+        cb: F,
+        params: I,
+        return_layout: Option<&ReturnLayout>,
     ) -> Result<(), CodegenError> {
         let params: Vec<_> = params.collect();
+        let vmctx_param = match return_layout {
+            Some(layout) if layout.registers.is_none() => 1,
+            _ => 0,
+        };
 
         // Save used GPRs.
         let used_gprs = self.machine.get_used_gprs();
@@ -1212,11 +1891,24 @@ impl<'a> FuncGen<'a> {
 
         // Calculate stack offset.
         for (i, _param) in params.iter().enumerate() {
-            if let Location::Memory(_, _) = Machine::get_param_location(1 + i, calling_convention) {
+            if let Location::Memory(_, _) =
+                Machine::get_param_location(vmctx_param + 1 + i, calling_convention)
+            {
                 stack_offset += 8;
             }
         }
 
+        // Reserve the stack argument slots the callee may tail call with.
+        let reserved = (RESERVED_STACK_ARGS * 8).saturating_sub(stack_offset);
+        if reserved > 0 {
+            self.assembler.emit_sub(
+                Size::S64,
+                Location::Imm32(reserved as u32),
+                Location::GPR(GPR::RSP),
+            );
+            stack_offset += reserved;
+        }
+
         // Align stack to 16 bytes.
         if (self.machine.get_stack_offset()
             + used_gprs.len() * 8
@@ -1233,7 +1925,7 @@ impl<'a> FuncGen<'a> {
         let mut call_movs: Vec<(Location, GPR)> = vec![];
         // Prepare register & stack parameters.
         for (i, param) in params.iter().enumerate().rev() {
-            let loc = Machine::get_param_location(1 + i, calling_convention);
+            let loc = Machine::get_param_location(vmctx_param + 1 + i, calling_convention);
             match loc {
                 Location::GPR(x) => {
                     call_movs.push((*param, x));
@@ -1307,12 +1999,19 @@ impl<'a> FuncGen<'a> {
             }
         }
 
-        // Put vmctx as the first parameter.
+        // Put vmctx as the first parameter, after the return area if any.
         self.assembler.emit_mov(
             Size::S64,
             Location::GPR(Machine::get_vmctx_reg()),
-            Machine::get_param_location(0, calling_convention),
+            Machine::get_param_location(vmctx_param, calling_convention),
         ); // vmctx
+        if vmctx_param == 1 {
+            self.assembler.emit_lea(
+                Size::S64,
+                self.return_area,
+                Machine::get_param_location(0, calling_convention),
+            );
+        }
 
         if stack_padding > 0 {
             self.assembler.emit_sub(
@@ -1372,6 +2071,57 @@ impl<'a> FuncGen<'a> {
         Ok(())
     }
 
+    /// Whether the memory at `index` is indexed with 64-bit addresses.
+    fn memory64(&self, index: MemoryIndex) -> bool {
+        self.module.memories[index].memory64
+    }
+
+    /// The index identifying the memory at `index` to the memory builtins,
+    /// which take local indices for locally defined memories.
+    fn memory_builtin_index(&self, index: MemoryIndex) -> u32 {
+        match self.module.local_memory_index(index) {
+            Some(local_memory_index) => local_memory_index.as_u32(),
+            None => index.as_u32(),
+        }
+    }
+
+    /// Loads the address `addr` of a memory access into `tmp_addr`, and adds
+    /// the offset of `memarg` to it, trapping if the addition overflows.
+    fn emit_effective_address(&mut self, addr: Location, memarg: &MemoryImmediate, tmp_addr: GPR) {
+        if self.memory64(MemoryIndex::new(memarg.memory as usize)) {
+            self.assembler
+                .emit_mov(Size::S64, addr, Location::GPR(tmp_addr));
+
+            // Immediates are sign-extended to 64 bits, so large offsets are
+            // added in two steps.
+            let mut offset = memarg.offset;
+            while offset != 0 {
+                let step = offset.min(i32::MAX as u32);
+                self.assembler
+                    .emit_add(Size::S64, Location::Imm32(step), Location::GPR(tmp_addr));
+                self.assembler
+                    .emit_jmp(Condition::Carry, self.special_labels.heap_access_oob);
+                offset -= step;
+            }
+        } else {
+            self.assembler
+                .emit_mov(Size::S32, addr, Location::GPR(tmp_addr));
+
+            // Add offset to memory address.
+            if memarg.offset != 0 {
+                self.assembler.emit_add(
+                    Size::S32,
+                    Location::Imm32(memarg.offset),
+                    Location::GPR(tmp_addr),
+                );
+
+                // Trap if offset calculation overflowed.
+                self.assembler
+                    .emit_jmp(Condition::Carry, self.special_labels.heap_access_oob);
+            }
+        }
+    }
+
     /// Emits a memory operation.
     fn emit_memory_op<F: FnOnce(&mut Self, GPR) -> Result<(), CodegenError>>(
         &mut self,
@@ -1385,26 +2135,28 @@ impl<'a> FuncGen<'a> {
         let tmp_addr = self.machine.acquire_temp_gpr().unwrap();
 
         // Reusing `tmp_addr` for temporary indirection here, since it's not used before the last reference to `{base,bound}_loc`.
-        let (base_loc, bound_loc) = if self.module.import_counts.memories != 0 {
-            // Imported memories require one level of indirection.
-            let offset = self
-                .vmoffsets
-                .vmctx_vmmemory_import_definition(MemoryIndex::new(0));
-            self.emit_relaxed_binop(
-                Assembler::emit_mov,
-                Size::S64,
-                Location::Memory(Machine::get_vmctx_reg(), offset as i32),
-                Location::GPR(tmp_addr),
-            );
-            (Location::Memory(tmp_addr, 0), Location::Memory(tmp_addr, 8))
-        } else {
-            let offset = self
-                .vmoffsets
-                .vmctx_vmmemory_definition(LocalMemoryIndex::new(0));
-            (
-                Location::Memory(Machine::get_vmctx_reg(), offset as i32),
-                Location::Memory(Machine::get_vmctx_reg(), (offset + 8) as i32),
-            )
+        let memory_index = MemoryIndex::new(memarg.memory as usize);
+        let (base_loc, bound_loc) = match self.module.local_memory_index(memory_index) {
+            Some(local_memory_index) => {
+                let offset = self.vmoffsets.vmctx_vmmemory_definition(local_memory_index);
+                (
+                    Location::Memory(Machine::get_vmctx_reg(), offset as i32),
+                    Location::Memory(Machine::get_vmctx_reg(), (offset + 8) as i32),
+                )
+            }
+            None => {
+                // Imported memories require one level of indirection.
+                let offset = self
+                    .vmoffsets
+                    .vmctx_vmmemory_import_definition(memory_index);
+                self.emit_relaxed_binop(
+                    Assembler::emit_mov,
+                    Size::S64,
+                    Location::Memory(Machine::get_vmctx_reg(), offset as i32),
+                    Location::GPR(tmp_addr),
+                );
+                (Location::Memory(tmp_addr, 0), Location::Memory(tmp_addr, 8))
+            }
         };
 
         let tmp_base = self.machine.acquire_temp_gpr().unwrap();
@@ -1437,25 +2189,16 @@ impl<'a> FuncGen<'a> {
         // Load effective address.
         // `base_loc` and `bound_loc` becomes INVALID after this line, because `tmp_addr`
         // might be reused.
-        self.assembler
-            .emit_mov(Size::S32, addr, Location::GPR(tmp_addr));
-
-        // Add offset to memory address.
-        if memarg.offset != 0 {
-            self.assembler.emit_add(
-                Size::S32,
-                Location::Imm32(memarg.offset),
-                Location::GPR(tmp_addr),
-            );
-
-            // Trap if offset calculation overflowed.
-            self.assembler
-                .emit_jmp(Condition::Carry, self.special_labels.heap_access_oob);
-        }
+        self.emit_effective_address(addr, memarg, tmp_addr);
 
         // Wasm linear memory -> real memory
         self.assembler
             .emit_add(Size::S64, Location::GPR(tmp_base), Location::GPR(tmp_addr));
+        if self.memory64(MemoryIndex::new(memarg.memory as usize)) {
+            // 64-bit addresses can wrap around when the base is added.
+            self.assembler
+                .emit_jmp(Condition::Carry, self.special_labels.heap_access_oob);
+        }
 
         if need_check {
             // Trap if the end address of the requested area is above that of the linear memory.
@@ -1494,6 +2237,59 @@ impl<'a> FuncGen<'a> {
         Ok(())
     }
 
+    /// Emits a call to the `memory.atomic.wait*` or `memory.atomic.notify`
+    /// builtin `index`, with the effective address of `addr` followed by
+    /// `args`, and pushes its result.
+    ///
+    /// The builtin checks the bounds and alignment of the address.
+    fn emit_atomic_wait_notify(
+        &mut self,
+        index: VMBuiltinFunctionIndex,
+        memarg: &MemoryImmediate,
+        addr: Location,
+        args: &[Location],
+    ) -> Result<(), CodegenError> {
+        // Compute the 64-bit effective address before the registers of the
+        // operands are released, so that it can't overwrite them.
+        let tmp_addr = self.machine.acquire_temp_gpr().unwrap();
+        self.emit_effective_address(addr, memarg, tmp_addr);
+
+        let mut operands = vec![addr];
+        operands.extend_from_slice(args);
+        self.machine.release_locations_only_regs(&operands);
+        // Released registers are not restored after the call, which would
+        // overwrite its result if `tmp_addr` is RAX.
+        self.machine.release_temp_gpr(tmp_addr);
+
+        // `tmp_addr` may be RAX, so the builtin is only loaded once the
+        // arguments are in place.
+        let builtin = Location::Memory(
+            Machine::get_vmctx_reg(),
+            self.vmoffsets.vmctx_builtin_function(index) as i32,
+        );
+        // [vmctx, memory_index, addr, args...]
+        let mut params = vec![Location::Imm32(memarg.memory), Location::GPR(tmp_addr)];
+        params.extend_from_slice(args);
+        self.emit_call_native(
+            |this| {
+                this.assembler
+                    .emit_mov(Size::S64, builtin, Location::GPR(GPR::RAX));
+                this.assembler.emit_call_register(GPR::RAX);
+            },
+            params.into_iter(),
+        )?;
+        self.machine
+            .release_locations_only_stack(&mut self.assembler, &operands);
+
+        let ret = self
+            .machine
+            .acquire_locations(&mut self.assembler, &[(WpType::I32)], false)[0];
+        self.value_stack.push(ret);
+        self.assembler
+            .emit_mov(Size::S32, Location::GPR(GPR::RAX), ret);
+        Ok(())
+    }
+
     /// Emits a memory operation.
     fn emit_compare_and_swap<F: FnOnce(&mut Self, GPR, GPR)>(
         &mut self,
@@ -1847,6 +2643,10 @@ impl<'a> FuncGen<'a> {
                 alter.goto(self.stack_check_offset);
                 // TODO: check that the value before was 0x7fff_ffff
                 alter.push_u32(depth as u32);
+                for offset in self.tail_call_stack_check_offsets.iter() {
+                    alter.goto(*offset);
+                    alter.push_u32(depth as u32);
+                }
             }
             self.assembler.emit_add(
                 Size::S32,
@@ -1863,6 +2663,9 @@ impl<'a> FuncGen<'a> {
         // `local_types` include parameters as well.
         let depth = self.local_types.len()
             + self.max_stack_depth
+            + self.return_area_size / 8
+            // the stack argument slots reserved by the calls the function makes
+            + RESERVED_STACK_ARGS
             // we add 4 to ensure that deep recursion is prohibited even for local and argument free
             // functions, as they still use stack space for the saved frame base and return address,
             // along with spill area for callee-saved registers.
@@ -1870,6 +2673,38 @@ impl<'a> FuncGen<'a> {
         self.emit_stack_check(enter, depth);
     }
 
+    /// Traps if the epoch of the engine has reached the deadline of the instance.
+    fn emit_epoch_check(&mut self) {
+        if !self.config.enable_epoch_interruption {
+            return;
+        }
+        let epoch_reg = self.machine.acquire_temp_gpr().unwrap();
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Memory(
+                Machine::get_vmctx_reg(),
+                self.vmoffsets.vmctx_epoch_pointer() as i32,
+            ),
+            Location::GPR(epoch_reg),
+        );
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Memory(epoch_reg, 0),
+            Location::GPR(epoch_reg),
+        );
+        self.assembler.emit_cmp(
+            Size::S64,
+            Location::GPR(epoch_reg),
+            Location::Memory(
+                Machine::get_vmctx_reg(),
+                self.vmoffsets.vmctx_epoch_deadline() as i32,
+            ),
+        );
+        self.assembler
+            .emit_jmp(Condition::BelowEqual, self.special_labels.interrupted);
+        self.machine.release_temp_gpr(epoch_reg);
+    }
+
     fn emit_head(&mut self) -> Result<(), CodegenError> {
         // TODO: Patchpoint is not emitted for now, and ARM trampoline is not prepended.
 
@@ -1878,14 +2713,29 @@ impl<'a> FuncGen<'a> {
         self.assembler
             .emit_mov(Size::S64, Location::GPR(GPR::RSP), Location::GPR(GPR::RBP));
         // Initialize locals.
+        let returns_in_memory =
+            matches!(&self.return_layout, Some(layout) if layout.registers.is_none());
+        // The pointer to the memory the values are returned to is the last
+        // hidden local.
+        let return_pointer = if returns_in_memory {
+            Some(self.local_types.len() - 1)
+        } else {
+            None
+        };
         self.locals = self.machine.init_locals(
             &mut self.assembler,
             self.local_types.len(),
             self.signature.params().len(),
+            return_pointer,
+            self.return_area_size,
             self.calling_convention,
         );
+        self.return_pointer = return_pointer.map(|index| self.locals[index]);
+        self.return_area = Location::Memory(GPR::RBP, -(self.machine.get_stack_offset() as i32));
+        self.locals_stack_offset = self.machine.get_stack_offset();
 
         self.emit_function_stack_check(true);
+        self.emit_epoch_check();
 
         self.assembler
             .emit_sub(Size::S64, Location::Imm32(32), Location::GPR(GPR::RSP)); // simulate "red zone" if not supported by the platform
@@ -1894,6 +2744,7 @@ impl<'a> FuncGen<'a> {
             label: self.assembler.get_label(),
             loop_like: false,
             if_else: IfElseState::None,
+            params: smallvec![],
             returns: self
                 .signature
                 .results()
@@ -1902,6 +2753,8 @@ impl<'a> FuncGen<'a> {
                 .collect(),
             value_stack_depth: 0,
             fp_stack_depth: 0,
+            try_state: None,
+            loop_params: None,
         });
 
         Ok(())
@@ -1926,6 +2779,7 @@ impl<'a> FuncGen<'a> {
         local_func_index: LocalFunctionIndex,
         local_types_excluding_arguments: &[WpType],
         calling_convention: CallingConvention,
+        exceptions: bool,
     ) -> Result<FuncGen<'a>, CodegenError> {
         let func_index = module.func_index(local_func_index);
         let sig_index = module.functions[func_index];
@@ -1937,6 +2791,29 @@ impl<'a> FuncGen<'a> {
             .map(|&x| type_to_wp_type(x))
             .collect();
         local_types.extend_from_slice(&local_types_excluding_arguments);
+        let caught_exceptions = if exceptions {
+            // The caught exceptions, allocated by the first catch clause.
+            local_types.push(WpType::I64);
+            Some(local_types.len() - 1)
+        } else {
+            None
+        };
+        let return_layout = ReturnLayout::new(signature.results(), calling_convention);
+        if let Some(ReturnLayout {
+            registers: None, ..
+        }) = return_layout
+        {
+            local_types.push(WpType::I64);
+        }
+        // Values of blocks, calls and returns with several results are
+        // moved through a scratch area large enough for any of them.
+        let return_area_size = module
+            .signatures
+            .values()
+            .filter(|sig| sig.results().len() > 1)
+            .map(|sig| sig.results().len() * 8)
+            .max()
+            .unwrap_or(0);
 
         let mut assembler = Assembler::new(0);
         let special_labels = SpecialLabelSet {
@@ -1949,6 +2826,7 @@ impl<'a> FuncGen<'a> {
             bad_signature: assembler.get_label(),
             gas_limit_exceeded: assembler.get_label(),
             stack_overflow: assembler.get_label(),
+            interrupted: assembler.get_label(),
         };
 
         let mut fg = FuncGen {
@@ -1964,6 +2842,7 @@ impl<'a> FuncGen<'a> {
             value_stack: vec![],
             max_stack_depth: 0,
             stack_check_offset: AssemblyOffset(0),
+            tail_call_stack_check_offsets: vec![],
             fp_stack: vec![],
             control_stack: vec![],
             machine: Machine::new(),
@@ -1973,8 +2852,16 @@ impl<'a> FuncGen<'a> {
             src_loc: 0,
             instructions_address_map: vec![],
             calling_convention,
+            caught_exceptions: None,
+            try_count: 0,
+            locals_stack_offset: 0,
+            return_layout,
+            return_pointer: None,
+            return_area_size,
+            return_area: Location::Imm32(0),
         };
         fg.emit_head()?;
+        fg.caught_exceptions = caught_exceptions.map(|index| fg.locals[index]);
         Ok(fg)
     }
 
@@ -1991,10 +2878,13 @@ impl<'a> FuncGen<'a> {
             was_unreachable = true;
 
             match op {
-                Operator::Block { .. } | Operator::Loop { .. } | Operator::If { .. } => {
+                Operator::Block { .. }
+                | Operator::Loop { .. }
+                | Operator::If { .. }
+                | Operator::Try { .. } => {
                     self.unreachable_depth += 1;
                 }
-                Operator::End => {
+                Operator::End | Operator::Delegate { .. } => {
                     self.unreachable_depth -= 1;
                 }
                 Operator::Else => {
@@ -2007,6 +2897,13 @@ impl<'a> FuncGen<'a> {
                         }
                     }
                 }
+                // We are in a reachable catch clause
+                Operator::Catch { .. } | Operator::CatchAll
+                    if self.unreachable_depth == 1
+                        && self.control_stack.last().unwrap().try_state.is_some() =>
+                {
+                    self.unreachable_depth -= 1;
+                }
                 _ => {}
             }
             if self.unreachable_depth > 0 {
@@ -5139,8 +6036,14 @@ impl<'a> FuncGen<'a> {
                 }
             }
 
-            Operator::Call { function_index } => self.emit_call(function_index)?,
-            Operator::CallIndirect { index, table_index } => {
+            Operator::Call { function_index } => self.emit_call(function_index, false)?,
+            Operator::ReturnCall { function_index } => self.emit_call(function_index, true)?,
+            Operator::CallIndirect { index, table_index }
+            | Operator::ReturnCallIndirect { index, table_index } => {
+                let tail_call = matches!(op, Operator::ReturnCallIndirect { .. });
+                if tail_call {
+                    self.emit_caught_exceptions_drop()?;
+                }
                 // TODO: removed restriction on always being table idx 0;
                 // does any code depend on this?
                 let table_index = TableIndex::new(table_index as _);
@@ -5150,6 +6053,8 @@ impl<'a> FuncGen<'a> {
                     sig.params().iter().cloned().map(type_to_wp_type).collect();
                 let return_types: SmallVec<[WpType; 1]> =
                     sig.results().iter().cloned().map(type_to_wp_type).collect();
+                let return_layout = ReturnLayout::new(sig.results(), self.calling_convention);
+                let vmctx_param = self.vmctx_param_location(return_layout.as_ref());
 
                 let func_index = self.pop_value_released();
 
@@ -5288,53 +6193,60 @@ impl<'a> FuncGen<'a> {
                     self.vmoffsets.vmcaller_checked_anyfunc_func_ptr() as usize;
                 let vmcaller_checked_anyfunc_vmctx =
                     self.vmoffsets.vmcaller_checked_anyfunc_vmctx() as usize;
-                let calling_convention = self.calling_convention;
 
-                self.emit_call_native(
-                    |this| {
-                        if this.assembler.arch_requires_indirect_call_trampoline() {
-                            this.assembler.arch_emit_indirect_call_with_trampoline(
-                                Location::Memory(
-                                    GPR::RAX,
-                                    vmcaller_checked_anyfunc_func_ptr as i32,
-                                ),
-                            );
-                        } else {
-                            // We set the context pointer
-                            this.assembler.emit_mov(
-                                Size::S64,
-                                Location::Memory(GPR::RAX, vmcaller_checked_anyfunc_vmctx as i32),
-                                Machine::get_param_location(0, calling_convention),
-                            );
+                if tail_call {
+                    self.check_tail_call(params.len())?;
+                    self.emit_tail_call(&params, |this| {
+                        this.assembler.emit_mov(
+                            Size::S64,
+                            Location::Memory(GPR::RAX, vmcaller_checked_anyfunc_vmctx as i32),
+                            vmctx_param,
+                        );
+                        this.assembler.emit_mov(
+                            Size::S64,
+                            Location::Memory(GPR::RAX, vmcaller_checked_anyfunc_func_ptr as i32),
+                            Location::GPR(GPR::RAX),
+                        );
+                    })?;
+                    self.machine
+                        .release_locations_only_stack(&mut self.assembler, &params);
+                    self.unreachable_depth = 1;
+                } else {
+                    self.emit_call_native_with_return_layout(
+                        |this| {
+                            if this.assembler.arch_requires_indirect_call_trampoline() {
+                                this.assembler.arch_emit_indirect_call_with_trampoline(
+                                    Location::Memory(
+                                        GPR::RAX,
+                                        vmcaller_checked_anyfunc_func_ptr as i32,
+                                    ),
+                                );
+                            } else {
+                                // We set the context pointer
+                                this.assembler.emit_mov(
+                                    Size::S64,
+                                    Location::Memory(
+                                        GPR::RAX,
+                                        vmcaller_checked_anyfunc_vmctx as i32,
+                                    ),
+                                    vmctx_param,
+                                );
 
-                            this.assembler.emit_call_location(Location::Memory(
-                                GPR::RAX,
-                                vmcaller_checked_anyfunc_func_ptr as i32,
-                            ));
-                        }
-                    },
-                    params.iter().copied(),
-                )?;
+                                this.assembler.emit_call_location(Location::Memory(
+                                    GPR::RAX,
+                                    vmcaller_checked_anyfunc_func_ptr as i32,
+                                ));
+                            }
+                        },
+                        params.iter().copied(),
+                        return_layout.as_ref(),
+                    )?;
 
-                self.machine
-                    .release_locations_only_stack(&mut self.assembler, &params);
+                    self.machine
+                        .release_locations_only_stack(&mut self.assembler, &params);
 
-                if !return_types.is_empty() {
-                    let ret = self.machine.acquire_locations(
-                        &mut self.assembler,
-                        &[return_types[0]],
-                        false,
-                    )[0];
-                    self.value_stack.push(ret);
-                    if return_types[0].is_float() {
-                        self.assembler
-                            .emit_mov(Size::S64, Location::XMM(XMM::XMM0), ret);
-                        self.fp_stack
-                            .push(FloatValue::new(self.value_stack.len() - 1));
-                    } else {
-                        self.assembler
-                            .emit_mov(Size::S64, Location::GPR(GPR::RAX), ret);
-                    }
+                    self.emit_call_results(&return_types, return_layout.as_ref());
+                    self.emit_exception_check()?;
                 }
             }
             Operator::If { ty } => {
@@ -5343,88 +6255,48 @@ impl<'a> FuncGen<'a> {
 
                 let cond = self.pop_value_released();
 
+                let (params, returns) = self.block_signature(ty);
+                let depth = self.value_stack.len() - params.len();
                 let frame = ControlFrame {
                     label: label_end,
                     loop_like: false,
                     if_else: IfElseState::If(label_else),
-                    returns: match ty {
-                        WpTypeOrFuncType::Type(WpType::EmptyBlockType) => smallvec![],
-                        WpTypeOrFuncType::Type(inner_ty) => smallvec![inner_ty],
-                        _ => {
-                            return Err(CodegenError {
-                                message: "If: multi-value returns not yet implemented".to_string(),
-                            })
-                        }
-                    },
-                    value_stack_depth: self.value_stack.len(),
-                    fp_stack_depth: self.fp_stack.len(),
+                    params,
+                    returns,
+                    value_stack_depth: depth,
+                    fp_stack_depth: self.fp_stack_depth_at(depth),
+                    try_state: None,
+                    loop_params: None,
                 };
                 self.control_stack.push(frame);
                 self.emit_relaxed_binop(Assembler::emit_cmp, Size::S32, Location::Imm32(0), cond);
                 self.assembler.emit_jmp(Condition::Equal, label_else);
-            }
-            Operator::Else => {
-                let frame = self.control_stack.last_mut().unwrap();
 
-                if !was_unreachable && !frame.returns.is_empty() {
-                    let first_return = frame.returns[0];
-                    let loc = *self.value_stack.last().unwrap();
-                    if first_return.is_float() {
-                        let fp = self.fp_stack.peek1()?;
-                        if self.assembler.arch_supports_canonicalize_nan()
-                            && self.config.enable_nan_canonicalization
-                            && fp.canonicalization.is_some()
-                        {
-                            self.canonicalize_nan(
-                                match first_return {
-                                    WpType::F32 => Size::S32,
-                                    WpType::F64 => Size::S64,
-                                    _ => unreachable!(),
-                                },
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        } else {
-                            self.emit_relaxed_binop(
-                                Assembler::emit_mov,
-                                Size::S64,
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
+                // The `then` branch works on copies of the parameters, which
+                // the `else` branch gets.
+                for i in depth..self.value_stack.len() {
+                    let loc = self.value_stack[i];
+                    let copy = match loc {
+                        Location::Imm32(_) | Location::Imm64(_) => loc,
+                        _ => {
+                            let ty = self.control_stack.last().unwrap().params[i - depth];
+                            let copy =
+                                self.machine
+                                    .acquire_locations(&mut self.assembler, &[ty], false)[0];
+                            self.emit_relaxed_binop(Assembler::emit_mov, Size::S64, loc, copy);
+                            copy
                         }
-                    } else {
-                        self.emit_relaxed_binop(
-                            Assembler::emit_mov,
-                            Size::S64,
-                            loc,
-                            Location::GPR(GPR::RAX),
-                        );
-                    }
-                }
-
-                self.update_max_stack_depth();
-
-                let mut frame = self.control_stack.last_mut().unwrap();
-
-                let released: &[Location] = &self.value_stack[frame.value_stack_depth..];
-                self.machine
-                    .release_locations(&mut self.assembler, released);
-                self.value_stack.truncate(frame.value_stack_depth);
-                self.fp_stack.truncate(frame.fp_stack_depth);
-
-                match frame.if_else {
-                    IfElseState::If(label) => {
-                        self.assembler.emit_jmp(Condition::None, frame.label);
-                        self.assembler.emit_label(label);
-                        frame.if_else = IfElseState::Else;
-                    }
-                    _ => {
-                        return Err(CodegenError {
-                            message: "Else: frame.if_else unreachable code".to_string(),
-                        })
+                    };
+                    self.value_stack.push(copy);
+                    if let Some(fp) = self.fp_stack.iter().find(|fp| fp.depth == i).copied() {
+                        self.fp_stack.push(FloatValue {
+                            depth: self.value_stack.len() - 1,
+                            ..fp
+                        });
                     }
                 }
             }
+            Operator::Else => self.emit_else(was_unreachable)?,
             // `TypedSelect` must be used for extern refs so ref counting should
             // be done with TypedSelect. But otherwise they're the same.
             Operator::TypedSelect { .. } | Operator::Select => {
@@ -5483,27 +6355,229 @@ impl<'a> FuncGen<'a> {
                 }
                 self.assembler.emit_label(end_label);
             }
-            Operator::Block { ty } => {
+            Operator::Try { ty } => {
+                let (params, returns) = self.block_signature(ty);
+                let depth = self.value_stack.len() - params.len();
+                // The parameters on the machine stack are the last values
+                // allocated there.
+                let params_stack_size = self.value_stack[depth..]
+                    .iter()
+                    .filter(|loc| matches!(loc, Location::Memory(_, _)))
+                    .count()
+                    * 8;
                 let frame = ControlFrame {
                     label: self.assembler.get_label(),
                     loop_like: false,
                     if_else: IfElseState::None,
-                    returns: match ty {
-                        WpTypeOrFuncType::Type(WpType::EmptyBlockType) => smallvec![],
-                        WpTypeOrFuncType::Type(inner_ty) => smallvec![inner_ty],
-                        _ => {
-                            return Err(CodegenError {
-                                message: "Block: multi-value returns not yet implemented"
-                                    .to_string(),
-                            })
+                    params,
+                    returns,
+                    value_stack_depth: depth,
+                    fp_stack_depth: self.fp_stack_depth_at(depth),
+                    try_state: Some(TryState {
+                        slot: self.try_count,
+                        stack_offset: self.machine.get_stack_offset() - params_stack_size,
+                        landing: self.assembler.get_label(),
+                        catching: false,
+                    }),
+                    loop_params: None,
+                };
+                self.try_count += 1;
+                self.control_stack.push(frame);
+            }
+            Operator::Catch { .. } | Operator::CatchAll => {
+                if !was_unreachable {
+                    self.emit_block_results(self.control_stack.len() - 1);
+                }
+
+                self.update_max_stack_depth();
+
+                let frame = self.control_stack.last().unwrap();
+
+                let released: &[Location] = &self.value_stack[frame.value_stack_depth..];
+                self.machine
+                    .release_locations(&mut self.assembler, released);
+                self.value_stack.truncate(frame.value_stack_depth);
+                self.fp_stack.truncate(frame.fp_stack_depth);
+
+                let mut try_state = frame.try_state.ok_or_else(|| CodegenError {
+                    message: "Catch: frame.try_state unreachable code".to_string(),
+                })?;
+                self.assembler.emit_jmp(Condition::None, frame.label);
+                self.assembler.emit_label(try_state.landing);
+                if !try_state.catching {
+                    // Exceptions thrown in the body jump here with an
+                    // arbitrary stack pointer.
+                    self.assembler.emit_lea(
+                        Size::S64,
+                        Location::Memory(GPR::RBP, -((try_state.stack_offset + 32) as i32)),
+                        Location::GPR(GPR::RSP),
+                    );
+                }
+                let caught = self.emit_caught_exceptions()?;
+
+                let tag_index = match op {
+                    Operator::Catch { index } => Some(TagIndex::from_u32(index)),
+                    _ => None,
+                };
+                self.emit_call_builtin(
+                    VMBuiltinFunctionIndex::get_exception_catch_index(),
+                    // [vmctx, caught, slot, tag_index]
+                    [
+                        caught,
+                        Location::Imm32(try_state.slot),
+                        Location::Imm32(tag_index.map_or(u32::MAX, |index| index.as_u32())),
+                    ]
+                    .iter()
+                    .copied(),
+                )?;
+                try_state.landing = self.assembler.get_label();
+                try_state.catching = true;
+                self.assembler
+                    .emit_cmp(Size::S32, Location::Imm32(0), Location::GPR(GPR::RAX));
+                self.assembler.emit_jmp(Condition::Equal, try_state.landing);
+                self.control_stack.last_mut().unwrap().try_state = Some(try_state);
+
+                if let Some(tag_index) = tag_index {
+                    let params = self.module.tags[tag_index].params().to_vec();
+                    for (i, ty) in params.into_iter().enumerate() {
+                        self.emit_call_builtin(
+                            VMBuiltinFunctionIndex::get_exception_payload_index(),
+                            // [vmctx, caught, slot, index]
+                            [
+                                caught,
+                                Location::Imm32(try_state.slot),
+                                Location::Imm32(i as u32),
+                            ]
+                            .iter()
+                            .copied(),
+                        )?;
+                        let ty = type_to_wp_type(ty);
+                        let loc = self
+                            .machine
+                            .acquire_locations(&mut self.assembler, &[ty], false)[0];
+                        self.assembler
+                            .emit_mov(Size::S64, Location::GPR(GPR::RAX), loc);
+                        self.value_stack.push(loc);
+                        if ty.is_float() {
+                            self.fp_stack
+                                .push(FloatValue::new(self.value_stack.len() - 1));
                         }
-                    },
-                    value_stack_depth: self.value_stack.len(),
-                    fp_stack_depth: self.fp_stack.len(),
+                    }
+                }
+            }
+            Operator::Throw { index } => {
+                let tag_index = TagIndex::from_u32(index);
+                let n_values = self.module.tags[tag_index].params().len();
+                let values: SmallVec<[_; 8]> = self
+                    .value_stack
+                    .drain(self.value_stack.len() - n_values..)
+                    .collect();
+                while let Some(fp) = self.fp_stack.last() {
+                    if fp.depth >= self.value_stack.len() {
+                        self.fp_stack.pop().unwrap();
+                    } else {
+                        break;
+                    }
+                }
+
+                self.emit_call_builtin(
+                    VMBuiltinFunctionIndex::get_exception_new_index(),
+                    // [vmctx, tag_index]
+                    iter::once(Location::Imm32(index)),
+                )?;
+                // The values stay acquired until pushed, so that calls
+                // preserve them.
+                for value in values.iter() {
+                    self.emit_call_builtin(
+                        VMBuiltinFunctionIndex::get_exception_push_index(),
+                        // [vmctx, bits]
+                        iter::once(*value),
+                    )?;
+                }
+                self.machine.release_locations(&mut self.assembler, &values);
+
+                let target = self.exception_target(self.control_stack.len());
+                self.assembler.emit_jmp(Condition::None, target);
+                self.unreachable_depth = 1;
+            }
+            Operator::Rethrow { relative_depth } => {
+                let frame =
+                    &self.control_stack[self.control_stack.len() - 1 - (relative_depth as usize)];
+                let try_state = frame.try_state.ok_or_else(|| CodegenError {
+                    message: "Rethrow: frame.try_state unreachable code".to_string(),
+                })?;
+                let caught = self.caught_exceptions.unwrap();
+                self.emit_call_builtin(
+                    VMBuiltinFunctionIndex::get_exception_rethrow_index(),
+                    // [vmctx, caught, slot]
+                    [caught, Location::Imm32(try_state.slot)].iter().copied(),
+                )?;
+
+                let target = self.exception_target(self.control_stack.len());
+                self.assembler.emit_jmp(Condition::None, target);
+                self.unreachable_depth = 1;
+            }
+            Operator::Block { ty } => {
+                let (params, returns) = self.block_signature(ty);
+                let depth = self.value_stack.len() - params.len();
+                let frame = ControlFrame {
+                    label: self.assembler.get_label(),
+                    loop_like: false,
+                    if_else: IfElseState::None,
+                    params,
+                    returns,
+                    value_stack_depth: depth,
+                    fp_stack_depth: self.fp_stack_depth_at(depth),
+                    try_state: None,
+                    loop_params: None,
                 };
                 self.control_stack.push(frame);
             }
             Operator::Loop { ty } => {
+                let (params, returns) = self.block_signature(ty);
+                let depth = self.value_stack.len() - params.len();
+                let fp_stack_depth = self.fp_stack_depth_at(depth);
+                // Branches to the loop move the new parameters to the
+                // locations of the initial ones, which must be canonical and
+                // not immediates for that.
+                let loop_params = if params.is_empty() {
+                    None
+                } else {
+                    if self.value_stack[depth..]
+                        .iter()
+                        .any(|loc| matches!(loc, Location::Imm32(_) | Location::Imm64(_)))
+                    {
+                        let locations =
+                            self.machine
+                                .acquire_locations(&mut self.assembler, &params, false);
+                        for (i, loc) in locations.iter().enumerate() {
+                            self.emit_move_value(depth + i, *loc);
+                        }
+                        self.value_stack.extend_from_slice(&locations);
+                    } else {
+                        for i in depth..self.value_stack.len() {
+                            let loc = self.value_stack[i];
+                            self.emit_move_value(i, loc);
+                        }
+                    }
+                    self.fp_stack.truncate(fp_stack_depth);
+                    let locations: SmallVec<[Location; 1]> = self.value_stack
+                        [self.value_stack.len() - params.len()..]
+                        .iter()
+                        .copied()
+                        .collect();
+                    for (i, ty) in params.iter().enumerate() {
+                        if ty.is_float() {
+                            self.fp_stack
+                                .push(FloatValue::new(self.value_stack.len() - params.len() + i));
+                        }
+                    }
+                    Some(LoopParams {
+                        locations,
+                        stack_offset: self.machine.get_stack_offset(),
+                    })
+                };
+
                 // Pad with NOPs to the next 16-byte boundary.
                 // Here we don't use the dynasm `.align 16` attribute because it pads the alignment with single-byte nops
                 // which may lead to efficiency problems.
@@ -5522,22 +6596,15 @@ impl<'a> FuncGen<'a> {
                     label,
                     loop_like: true,
                     if_else: IfElseState::None,
-                    returns: match ty {
-                        WpTypeOrFuncType::Type(WpType::EmptyBlockType) => smallvec![],
-                        WpTypeOrFuncType::Type(inner_ty) => smallvec![inner_ty],
-                        _ => {
-                            return Err(CodegenError {
-                                message: "Loop: multi-value returns not yet implemented"
-                                    .to_string(),
-                            })
-                        }
-                    },
-                    value_stack_depth: self.value_stack.len(),
-                    fp_stack_depth: self.fp_stack.len(),
+                    params,
+                    returns,
+                    value_stack_depth: depth,
+                    fp_stack_depth,
+                    try_state: None,
+                    loop_params,
                 });
                 self.assembler.emit_label(label);
-
-                // TODO: Re-enable interrupt signal check without branching
+                self.emit_epoch_check();
             }
             Operator::Nop => {}
             Operator::MemorySize { mem, mem_byte: _ } => {
@@ -5547,10 +6614,18 @@ impl<'a> FuncGen<'a> {
                     Location::Memory(
                         Machine::get_vmctx_reg(),
                         self.vmoffsets.vmctx_builtin_function(
-                            if self.module.local_memory_index(memory_index).is_some() {
-                                VMBuiltinFunctionIndex::get_memory32_size_index()
-                            } else {
-                                VMBuiltinFunctionIndex::get_imported_memory32_size_index()
+                            match (
+                                self.module.local_memory_index(memory_index).is_some(),
+                                self.memory64(memory_index),
+                            ) {
+                                (true, false) => VMBuiltinFunctionIndex::get_memory32_size_index(),
+                                (false, false) => {
+                                    VMBuiltinFunctionIndex::get_imported_memory32_size_index()
+                                }
+                                (true, true) => VMBuiltinFunctionIndex::get_memory64_size_index(),
+                                (false, true) => {
+                                    VMBuiltinFunctionIndex::get_imported_memory64_size_index()
+                                }
                             },
                         ) as i32,
                     ),
@@ -5561,7 +6636,7 @@ impl<'a> FuncGen<'a> {
                         this.assembler.emit_call_register(GPR::RAX);
                     },
                     // [vmctx, memory_index]
-                    iter::once(Location::Imm32(memory_index.index() as u32)),
+                    iter::once(Location::Imm32(self.memory_builtin_index(memory_index))),
                 )?;
                 let ret =
                     self.machine
@@ -5576,13 +6651,16 @@ impl<'a> FuncGen<'a> {
                 let dst = self.value_stack.pop().unwrap();
                 self.machine.release_locations_only_regs(&[len, src, dst]);
 
+                let memory_init_index = if self.memory64(MemoryIndex::new(mem as usize)) {
+                    VMBuiltinFunctionIndex::get_memory64_init_index()
+                } else {
+                    VMBuiltinFunctionIndex::get_memory_init_index()
+                };
                 self.assembler.emit_mov(
                     Size::S64,
                     Location::Memory(
                         Machine::get_vmctx_reg(),
-                        self.vmoffsets
-                            .vmctx_builtin_function(VMBuiltinFunctionIndex::get_memory_init_index())
-                            as i32,
+                        self.vmoffsets.vmctx_builtin_function(memory_init_index) as i32,
                     ),
                     Location::GPR(GPR::RAX),
                 );
@@ -5626,8 +6704,6 @@ impl<'a> FuncGen<'a> {
                 )?;
             }
             Operator::MemoryCopy { src, dst } => {
-                // ignore until we support multiple memories
-                let _dst = dst;
                 let len = self.value_stack.pop().unwrap();
                 let src_pos = self.value_stack.pop().unwrap();
                 let dst_pos = self.value_stack.pop().unwrap();
@@ -5635,18 +6711,27 @@ impl<'a> FuncGen<'a> {
                     .release_locations_only_regs(&[len, src_pos, dst_pos]);
 
                 let memory_index = MemoryIndex::new(src as usize);
-                let (memory_copy_index, memory_index) =
-                    if self.module.local_memory_index(memory_index).is_some() {
-                        (
-                            VMBuiltinFunctionIndex::get_memory_copy_index(),
-                            memory_index,
-                        )
-                    } else {
-                        (
-                            VMBuiltinFunctionIndex::get_imported_memory_copy_index(),
-                            memory_index,
-                        )
+                let (memory_copy_index, memory_indices) = if src != dst {
+                    // [dst_memory_index, src_memory_index]
+                    (
+                        VMBuiltinFunctionIndex::get_memory_copy_between_index(),
+                        vec![Location::Imm32(dst), Location::Imm32(src)],
+                    )
+                } else {
+                    let memory_copy_index = match (
+                        self.module.local_memory_index(memory_index).is_some(),
+                        self.memory64(memory_index),
+                    ) {
+                        (true, false) => VMBuiltinFunctionIndex::get_memory_copy_index(),
+                        (false, false) => VMBuiltinFunctionIndex::get_imported_memory_copy_index(),
+                        (true, true) => VMBuiltinFunctionIndex::get_memory64_copy_index(),
+                        (false, true) => VMBuiltinFunctionIndex::get_imported_memory64_copy_index(),
                     };
+                    (
+                        memory_copy_index,
+                        vec![Location::Imm32(self.memory_builtin_index(memory_index))],
+                    )
+                };
 
                 self.assembler.emit_mov(
                     Size::S64,
@@ -5661,15 +6746,10 @@ impl<'a> FuncGen<'a> {
                     |this| {
                         this.assembler.emit_call_register(GPR::RAX);
                     },
-                    // [vmctx, memory_index, dst, src, len]
-                    [
-                        Location::Imm32(memory_index.index() as u32),
-                        dst_pos,
-                        src_pos,
-                        len,
-                    ]
-                    .iter()
-                    .cloned(),
+                    // [vmctx, memory_index(es), dst, src, len]
+                    memory_indices
+                        .into_iter()
+                        .chain([dst_pos, src_pos, len].iter().cloned()),
                 )?;
                 self.machine
                     .release_locations_only_stack(&mut self.assembler, &[dst_pos, src_pos, len]);
@@ -5681,18 +6761,15 @@ impl<'a> FuncGen<'a> {
                 self.machine.release_locations_only_regs(&[len, val, dst]);
 
                 let memory_index = MemoryIndex::new(mem as usize);
-                let (memory_fill_index, memory_index) =
-                    if self.module.local_memory_index(memory_index).is_some() {
-                        (
-                            VMBuiltinFunctionIndex::get_memory_fill_index(),
-                            memory_index,
-                        )
-                    } else {
-                        (
-                            VMBuiltinFunctionIndex::get_imported_memory_fill_index(),
-                            memory_index,
-                        )
-                    };
+                let memory_fill_index = match (
+                    self.module.local_memory_index(memory_index).is_some(),
+                    self.memory64(memory_index),
+                ) {
+                    (true, false) => VMBuiltinFunctionIndex::get_memory_fill_index(),
+                    (false, false) => VMBuiltinFunctionIndex::get_imported_memory_fill_index(),
+                    (true, true) => VMBuiltinFunctionIndex::get_memory64_fill_index(),
+                    (false, true) => VMBuiltinFunctionIndex::get_imported_memory64_fill_index(),
+                };
 
                 self.assembler.emit_mov(
                     Size::S64,
@@ -5708,9 +6785,14 @@ impl<'a> FuncGen<'a> {
                         this.assembler.emit_call_register(GPR::RAX);
                     },
                     // [vmctx, memory_index, dst, src, len]
-                    [Location::Imm32(memory_index.index() as u32), dst, val, len]
-                        .iter()
-                        .cloned(),
+                    [
+                        Location::Imm32(self.memory_builtin_index(memory_index)),
+                        dst,
+                        val,
+                        len,
+                    ]
+                    .iter()
+                    .cloned(),
                 )?;
                 self.machine
                     .release_locations_only_stack(&mut self.assembler, &[dst, val, len]);
@@ -5726,10 +6808,18 @@ impl<'a> FuncGen<'a> {
                     Location::Memory(
                         Machine::get_vmctx_reg(),
                         self.vmoffsets.vmctx_builtin_function(
-                            if self.module.local_memory_index(memory_index).is_some() {
-                                VMBuiltinFunctionIndex::get_memory32_grow_index()
-                            } else {
-                                VMBuiltinFunctionIndex::get_imported_memory32_grow_index()
+                            match (
+                                self.module.local_memory_index(memory_index).is_some(),
+                                self.memory64(memory_index),
+                            ) {
+                                (true, false) => VMBuiltinFunctionIndex::get_memory32_grow_index(),
+                                (false, false) => {
+                                    VMBuiltinFunctionIndex::get_imported_memory32_grow_index()
+                                }
+                                (true, true) => VMBuiltinFunctionIndex::get_memory64_grow_index(),
+                                (false, true) => {
+                                    VMBuiltinFunctionIndex::get_imported_memory64_grow_index()
+                                }
                             },
                         ) as i32,
                     ),
@@ -5741,8 +6831,9 @@ impl<'a> FuncGen<'a> {
                         this.assembler.emit_call_register(GPR::RAX);
                     },
                     // [vmctx, val, memory_index]
-                    iter::once(param_pages)
-                        .chain(iter::once(Location::Imm32(memory_index.index() as u32))),
+                    iter::once(param_pages).chain(iter::once(Location::Imm32(
+                        self.memory_builtin_index(memory_index),
+                    ))),
                 )?;
 
                 self.machine
@@ -6172,155 +7263,18 @@ impl<'a> FuncGen<'a> {
                 self.mark_instruction_address_end(offset);
                 self.unreachable_depth = 1;
             }
-            Operator::Return => {
-                let frame = &self.control_stack[0];
-                if !frame.returns.is_empty() {
-                    if frame.returns.len() != 1 {
-                        return Err(CodegenError {
-                            message: "Return: incorrect frame.returns".to_string(),
-                        });
-                    }
-                    let first_return = frame.returns[0];
-                    let loc = *self.value_stack.last().unwrap();
-                    if first_return.is_float() {
-                        let fp = self.fp_stack.peek1()?;
-                        if self.assembler.arch_supports_canonicalize_nan()
-                            && self.config.enable_nan_canonicalization
-                            && fp.canonicalization.is_some()
-                        {
-                            self.canonicalize_nan(
-                                match first_return {
-                                    WpType::F32 => Size::S32,
-                                    WpType::F64 => Size::S64,
-                                    _ => unreachable!(),
-                                },
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        } else {
-                            self.emit_relaxed_binop(
-                                Assembler::emit_mov,
-                                Size::S64,
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        }
-                    } else {
-                        self.emit_relaxed_binop(
-                            Assembler::emit_mov,
-                            Size::S64,
-                            loc,
-                            Location::GPR(GPR::RAX),
-                        );
-                    }
-                }
-                let frame = &self.control_stack[0];
-                let released = &self.value_stack[frame.value_stack_depth..];
-                self.machine
-                    .release_locations_keep_state(&mut self.assembler, released);
-                self.assembler.emit_jmp(Condition::None, frame.label);
-                self.unreachable_depth = 1;
-            }
+            Operator::Return => self.emit_return()?,
             Operator::Br { relative_depth } => {
-                let frame =
-                    &self.control_stack[self.control_stack.len() - 1 - (relative_depth as usize)];
-                if !frame.loop_like && !frame.returns.is_empty() {
-                    if frame.returns.len() != 1 {
-                        return Err(CodegenError {
-                            message: "Br: incorrect frame.returns".to_string(),
-                        });
-                    }
-                    let first_return = frame.returns[0];
-                    let loc = *self.value_stack.last().unwrap();
-
-                    if first_return.is_float() {
-                        let fp = self.fp_stack.peek1()?;
-                        if self.assembler.arch_supports_canonicalize_nan()
-                            && self.config.enable_nan_canonicalization
-                            && fp.canonicalization.is_some()
-                        {
-                            self.canonicalize_nan(
-                                match first_return {
-                                    WpType::F32 => Size::S32,
-                                    WpType::F64 => Size::S64,
-                                    _ => unreachable!(),
-                                },
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        } else {
-                            self.emit_relaxed_binop(
-                                Assembler::emit_mov,
-                                Size::S64,
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        }
-                    } else {
-                        self.assembler
-                            .emit_mov(Size::S64, loc, Location::GPR(GPR::RAX));
-                    }
-                }
-                let frame =
-                    &self.control_stack[self.control_stack.len() - 1 - (relative_depth as usize)];
-
-                let released = &self.value_stack[frame.value_stack_depth..];
-                self.machine
-                    .release_locations_keep_state(&mut self.assembler, released);
-                self.assembler.emit_jmp(Condition::None, frame.label);
+                self.emit_branch(self.control_stack.len() - 1 - (relative_depth as usize))?;
                 self.unreachable_depth = 1;
             }
-            Operator::BrIf { relative_depth } => {
-                let after = self.assembler.get_label();
-                let cond = self.pop_value_released();
-                self.emit_relaxed_binop(Assembler::emit_cmp, Size::S32, Location::Imm32(0), cond);
-                self.assembler.emit_jmp(Condition::Equal, after);
-
-                let frame =
-                    &self.control_stack[self.control_stack.len() - 1 - (relative_depth as usize)];
-                if !frame.loop_like && !frame.returns.is_empty() {
-                    if frame.returns.len() != 1 {
-                        return Err(CodegenError {
-                            message: "BrIf: incorrect frame.returns".to_string(),
-                        });
-                    }
+            Operator::BrIf { relative_depth } => {
+                let after = self.assembler.get_label();
+                let cond = self.pop_value_released();
+                self.emit_relaxed_binop(Assembler::emit_cmp, Size::S32, Location::Imm32(0), cond);
+                self.assembler.emit_jmp(Condition::Equal, after);
 
-                    let first_return = frame.returns[0];
-                    let loc = *self.value_stack.last().unwrap();
-                    if first_return.is_float() {
-                        let fp = self.fp_stack.peek1()?;
-                        if self.assembler.arch_supports_canonicalize_nan()
-                            && self.config.enable_nan_canonicalization
-                            && fp.canonicalization.is_some()
-                        {
-                            self.canonicalize_nan(
-                                match first_return {
-                                    WpType::F32 => Size::S32,
-                                    WpType::F64 => Size::S64,
-                                    _ => unreachable!(),
-                                },
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        } else {
-                            self.emit_relaxed_binop(
-                                Assembler::emit_mov,
-                                Size::S64,
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        }
-                    } else {
-                        self.assembler
-                            .emit_mov(Size::S64, loc, Location::GPR(GPR::RAX));
-                    }
-                }
-                let frame =
-                    &self.control_stack[self.control_stack.len() - 1 - (relative_depth as usize)];
-                let released = &self.value_stack[frame.value_stack_depth..];
-                self.machine
-                    .release_locations_keep_state(&mut self.assembler, released);
-                self.assembler.emit_jmp(Condition::None, frame.label);
+                self.emit_branch(self.control_stack.len() - 1 - (relative_depth as usize))?;
 
                 self.assembler.emit_label(after);
             }
@@ -6363,104 +7317,10 @@ impl<'a> FuncGen<'a> {
                     let label = self.assembler.get_label();
                     self.assembler.emit_label(label);
                     table.push(label);
-                    let frame =
-                        &self.control_stack[self.control_stack.len() - 1 - (*target as usize)];
-                    if !frame.loop_like && !frame.returns.is_empty() {
-                        if frame.returns.len() != 1 {
-                            return Err(CodegenError {
-                                message: format!(
-                                    "BrTable: incorrect frame.returns for {:?}",
-                                    target
-                                ),
-                            });
-                        }
-
-                        let first_return = frame.returns[0];
-                        let loc = *self.value_stack.last().unwrap();
-                        if first_return.is_float() {
-                            let fp = self.fp_stack.peek1()?;
-                            if self.assembler.arch_supports_canonicalize_nan()
-                                && self.config.enable_nan_canonicalization
-                                && fp.canonicalization.is_some()
-                            {
-                                self.canonicalize_nan(
-                                    match first_return {
-                                        WpType::F32 => Size::S32,
-                                        WpType::F64 => Size::S64,
-                                        _ => unreachable!(),
-                                    },
-                                    loc,
-                                    Location::GPR(GPR::RAX),
-                                );
-                            } else {
-                                self.emit_relaxed_binop(
-                                    Assembler::emit_mov,
-                                    Size::S64,
-                                    loc,
-                                    Location::GPR(GPR::RAX),
-                                );
-                            }
-                        } else {
-                            self.assembler
-                                .emit_mov(Size::S64, loc, Location::GPR(GPR::RAX));
-                        }
-                    }
-                    let frame =
-                        &self.control_stack[self.control_stack.len() - 1 - (*target as usize)];
-                    let released = &self.value_stack[frame.value_stack_depth..];
-                    self.machine
-                        .release_locations_keep_state(&mut self.assembler, released);
-                    self.assembler.emit_jmp(Condition::None, frame.label);
+                    self.emit_branch(self.control_stack.len() - 1 - (*target as usize))?;
                 }
                 self.assembler.emit_label(default_br);
-
-                {
-                    let frame = &self.control_stack
-                        [self.control_stack.len() - 1 - (default_target as usize)];
-                    if !frame.loop_like && !frame.returns.is_empty() {
-                        if frame.returns.len() != 1 {
-                            return Err(CodegenError {
-                                message: "BrTable: incorrect frame.returns".to_string(),
-                            });
-                        }
-
-                        let first_return = frame.returns[0];
-                        let loc = *self.value_stack.last().unwrap();
-                        if first_return.is_float() {
-                            let fp = self.fp_stack.peek1()?;
-                            if self.assembler.arch_supports_canonicalize_nan()
-                                && self.config.enable_nan_canonicalization
-                                && fp.canonicalization.is_some()
-                            {
-                                self.canonicalize_nan(
-                                    match first_return {
-                                        WpType::F32 => Size::S32,
-                                        WpType::F64 => Size::S64,
-                                        _ => unreachable!(),
-                                    },
-                                    loc,
-                                    Location::GPR(GPR::RAX),
-                                );
-                            } else {
-                                self.emit_relaxed_binop(
-                                    Assembler::emit_mov,
-                                    Size::S64,
-                                    loc,
-                                    Location::GPR(GPR::RAX),
-                                );
-                            }
-                        } else {
-                            self.assembler
-                                .emit_mov(Size::S64, loc, Location::GPR(GPR::RAX));
-                        }
-                    }
-                    let frame = &self.control_stack
-                        [self.control_stack.len() - 1 - (default_target as usize)];
-                    let released = &self.value_stack[frame.value_stack_depth..];
-                    self.machine
-                        .release_locations_keep_state(&mut self.assembler, released);
-                    self.assembler.emit_jmp(Condition::None, frame.label);
-                }
+                self.emit_branch(self.control_stack.len() - 1 - (default_target as usize))?;
 
                 self.assembler.emit_label(table_label);
                 for x in table {
@@ -6476,48 +7336,46 @@ impl<'a> FuncGen<'a> {
                     }
                 }
             }
-            Operator::End => {
-                let frame = self.control_stack.pop().unwrap();
-
-                if !was_unreachable && !frame.returns.is_empty() {
-                    let loc = *self.value_stack.last().unwrap();
-                    if frame.returns[0].is_float() {
-                        let fp = self.fp_stack.peek1()?;
-                        if self.assembler.arch_supports_canonicalize_nan()
-                            && self.config.enable_nan_canonicalization
-                            && fp.canonicalization.is_some()
-                        {
-                            self.canonicalize_nan(
-                                match frame.returns[0] {
-                                    WpType::F32 => Size::S32,
-                                    WpType::F64 => Size::S64,
-                                    _ => unreachable!(),
-                                },
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        } else {
-                            self.emit_relaxed_binop(
-                                Assembler::emit_mov,
-                                Size::S64,
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        }
-                    } else {
-                        self.emit_relaxed_binop(
-                            Assembler::emit_mov,
-                            Size::S64,
-                            loc,
-                            Location::GPR(GPR::RAX),
-                        );
-                    }
+            Operator::End | Operator::Delegate { .. } => {
+                let mut was_unreachable = was_unreachable;
+                let frame = self.control_stack.last().unwrap();
+                if let (IfElseState::If(_), false) = (frame.if_else, frame.params.is_empty()) {
+                    // Without an `else` branch, the parameters are the results.
+                    self.emit_else(was_unreachable)?;
+                    was_unreachable = false;
                 }
+                if !was_unreachable {
+                    self.emit_block_results(self.control_stack.len() - 1);
+                }
+                let frame = self.control_stack.pop().unwrap();
 
                 if self.control_stack.is_empty() {
                     self.assembler.emit_label(frame.label);
+                    self.emit_epilogue_caught_exceptions_drop();
                     self.update_max_stack_depth();
                     self.emit_function_stack_check(false);
+                    if let Some(layout) = &self.return_layout {
+                        match self.return_pointer {
+                            Some(return_pointer) => {
+                                self.assembler.emit_mov(
+                                    Size::S64,
+                                    return_pointer,
+                                    Location::GPR(GPR::RAX),
+                                );
+                                emit_copy_return_values(
+                                    &mut self.assembler,
+                                    layout,
+                                    self.return_area,
+                                    Location::Memory(GPR::RAX, 0),
+                                );
+                            }
+                            None => emit_load_return_registers(
+                                &mut self.assembler,
+                                layout,
+                                self.return_area,
+                            ),
+                        }
+                    }
                     self.machine.finalize_locals(
                         &mut self.assembler,
                         &self.locals,
@@ -6550,6 +7408,21 @@ impl<'a> FuncGen<'a> {
                     self.value_stack.truncate(frame.value_stack_depth);
                     self.fp_stack.truncate(frame.fp_stack_depth);
 
+                    if let Some(try_state) = frame.try_state {
+                        // The exceptions left uncaught propagate further, to
+                        // the handlers of the label a `delegate` targets.
+                        let depth = match op {
+                            Operator::Delegate { relative_depth } => {
+                                self.control_stack.len() - relative_depth as usize
+                            }
+                            _ => self.control_stack.len(),
+                        };
+                        let target = self.exception_target(depth);
+                        self.assembler.emit_jmp(Condition::None, frame.label);
+                        self.assembler.emit_label(try_state.landing);
+                        self.assembler.emit_jmp(Condition::None, target);
+                    }
+
                     if !frame.loop_like {
                         self.assembler.emit_label(frame.label);
                     }
@@ -6558,26 +7431,7 @@ impl<'a> FuncGen<'a> {
                         self.assembler.emit_label(label);
                     }
 
-                    if !frame.returns.is_empty() {
-                        if frame.returns.len() != 1 {
-                            return Err(CodegenError {
-                                message: "End: incorrect frame.returns".to_string(),
-                            });
-                        }
-                        let loc = self.machine.acquire_locations(
-                            &mut self.assembler,
-                            &[(frame.returns[0])],
-                            false,
-                        )[0];
-                        self.assembler
-                            .emit_mov(Size::S64, Location::GPR(GPR::RAX), loc);
-                        self.value_stack.push(loc);
-                        if frame.returns[0].is_float() {
-                            self.fp_stack
-                                .push(FloatValue::new(self.value_stack.len() - 1));
-                            // we already canonicalized at the `Br*` instruction or here previously.
-                        }
-                    }
+                    self.emit_block_results_arrival(&frame.returns);
                 }
             }
             Operator::AtomicFence { flags: _ } => {
@@ -6589,6 +7443,38 @@ impl<'a> FuncGen<'a> {
                 // it would lead to data races that weren't present in the
                 // original source language.
             }
+            Operator::MemoryAtomicWait32 { ref memarg } => {
+                let timeout = self.value_stack.pop().unwrap();
+                let expected = self.value_stack.pop().unwrap();
+                let target = self.value_stack.pop().unwrap();
+                self.emit_atomic_wait_notify(
+                    VMBuiltinFunctionIndex::get_memory_atomic_wait32_index(),
+                    memarg,
+                    target,
+                    &[expected, timeout],
+                )?;
+            }
+            Operator::MemoryAtomicWait64 { ref memarg } => {
+                let timeout = self.value_stack.pop().unwrap();
+                let expected = self.value_stack.pop().unwrap();
+                let target = self.value_stack.pop().unwrap();
+                self.emit_atomic_wait_notify(
+                    VMBuiltinFunctionIndex::get_memory_atomic_wait64_index(),
+                    memarg,
+                    target,
+                    &[expected, timeout],
+                )?;
+            }
+            Operator::MemoryAtomicNotify { ref memarg } => {
+                let count = self.value_stack.pop().unwrap();
+                let target = self.value_stack.pop().unwrap();
+                self.emit_atomic_wait_notify(
+                    VMBuiltinFunctionIndex::get_memory_atomic_notify_index(),
+                    memarg,
+                    target,
+                    &[count],
+                )?;
+            }
             Operator::I32AtomicLoad { ref memarg } => {
                 let target = self.pop_value_released();
                 let ret =
@@ -8408,8 +9294,29 @@ impl<'a> FuncGen<'a> {
 
         self.assembler
             .emit_label(self.special_labels.stack_overflow);
+        // The trap unwinds all the frames, so give the instance its whole
+        // stack back for the calls made after it.
+        self.assembler.emit_mov(
+            Size::S32,
+            Location::Memory(
+                Machine::get_vmctx_reg(),
+                self.vmoffsets.vmctx_stack_limit_initial_begin() as i32,
+            ),
+            Location::GPR(GPR::RAX),
+        );
+        self.assembler.emit_mov(
+            Size::S32,
+            Location::GPR(GPR::RAX),
+            Location::Memory(
+                Machine::get_vmctx_reg(),
+                self.vmoffsets.vmctx_stack_limit_begin() as i32,
+            ),
+        );
         self.emit_trap(TrapCode::StackOverflow);
 
+        self.assembler.emit_label(self.special_labels.interrupted);
+        self.emit_trap(TrapCode::Interrupted);
+
         // Notify the assembler backend to generate necessary code at end of function.
         self.assembler.finalize_function();
 
@@ -8432,7 +9339,7 @@ impl<'a> FuncGen<'a> {
     }
 }
 
-fn type_to_wp_type(ty: Type) -> WpType {
+pub(crate) fn type_to_wp_type(ty: Type) -> WpType {
     match ty {
         Type::I32 => WpType::I32,
         Type::I64 => WpType::I64,
@@ -8493,6 +9400,67 @@ fn sort_call_movs(movs: &mut [(Location, GPR)]) {
     */
 }
 
+/// Returns the location `offset` bytes after the memory location `base`.
+fn memory_at(base: Location, offset: i32) -> Location {
+    match base {
+        Location::Memory(reg, start) => Location::Memory(reg, start + offset),
+        _ => unreachable!("memory_at: not a memory location: {:?}", base),
+    }
+}
+
+/// Returns the offsets and sizes of `n` values in 8-byte slots.
+fn return_area_slots(n: usize) -> SmallVec<[(i32, Size); 4]> {
+    (0..n).map(|i| ((i * 8) as i32, Size::S64)).collect()
+}
+
+/// Returns the offsets and sizes of values returned as `layout` describes.
+fn return_layout_fields(layout: &ReturnLayout) -> SmallVec<[(i32, Size); 4]> {
+    layout
+        .fields
+        .iter()
+        .map(|&(offset, size)| (offset as i32, if size == 4 { Size::S32 } else { Size::S64 }))
+        .collect()
+}
+
+fn register_location(reg: X64Register) -> Location {
+    match reg {
+        X64Register::GPR(gpr) => Location::GPR(gpr),
+        X64Register::XMM(xmm) => Location::XMM(xmm),
+    }
+}
+
+/// Stores values returned in registers as `layout` describes to `dst`.
+fn emit_store_return_registers(a: &mut Assembler, layout: &ReturnLayout, dst: Location) {
+    for (i, reg) in layout.registers.iter().flatten().enumerate() {
+        a.emit_mov(
+            Size::S64,
+            register_location(*reg),
+            memory_at(dst, (i * 8) as i32),
+        );
+    }
+}
+
+/// Loads values laid out as `layout` describes from `src` to the registers
+/// they are returned in.
+fn emit_load_return_registers(a: &mut Assembler, layout: &ReturnLayout, src: Location) {
+    for (i, reg) in layout.registers.iter().flatten().enumerate() {
+        a.emit_mov(
+            Size::S64,
+            memory_at(src, (i * 8) as i32),
+            register_location(*reg),
+        );
+    }
+}
+
+/// Copies values laid out as `layout` describes from `src` to `dst`, through
+/// RCX.
+fn emit_copy_return_values(a: &mut Assembler, layout: &ReturnLayout, src: Location, dst: Location) {
+    for &(offset, size) in return_layout_fields(layout).iter() {
+        a.emit_mov(size, memory_at(src, offset), Location::GPR(GPR::RCX));
+        a.emit_mov(size, Location::GPR(GPR::RCX), memory_at(dst, offset));
+    }
+}
+
 // Standard entry trampoline.
 pub(crate) fn gen_std_trampoline(
     sig: &FunctionType,
@@ -8500,13 +9468,23 @@ pub(crate) fn gen_std_trampoline(
 ) -> FunctionBody {
     let mut a = Assembler::new(0);
 
+    // Multiple results are returned in registers or in a buffer we pass a
+    // pointer to as the first parameter, before `callee_vmctx`.
+    let return_layout = ReturnLayout::new(sig.results(), calling_convention);
+    let returns_in_memory = matches!(&return_layout, Some(layout) if layout.registers.is_none());
+    let first_param = if returns_in_memory { 2 } else { 1 };
+
     // Calculate stack offset.
     let mut stack_offset: u32 = 0;
     for (i, _param) in sig.params().iter().enumerate() {
-        if let Location::Memory(_, _) = Machine::get_param_location(1 + i, calling_convention) {
+        if let Location::Memory(_, _) =
+            Machine::get_param_location(first_param + i, calling_convention)
+        {
             stack_offset += 8;
         }
     }
+    // Reserve the stack argument slots the callee may tail call with.
+    stack_offset = max(stack_offset, (RESERVED_STACK_ARGS * 8) as u32);
     let stack_padding: u32 = match calling_convention {
         CallingConvention::WindowsFastcall => 32,
         _ => 0,
@@ -8517,6 +9495,13 @@ pub(crate) fn gen_std_trampoline(
         stack_offset += 8;
     }
 
+    // Buffer the results are returned in, right above the stack arguments.
+    let return_buffer_offset = stack_padding + stack_offset;
+    let return_buffer_size = return_layout
+        .as_ref()
+        .map_or(0, |layout| align_to(layout.size, 16) as u32);
+    let frame_size = stack_offset + stack_padding + return_buffer_size;
+
     // Used callee-saved registers
     a.emit_push(Size::S64, Location::GPR(GPR::R15));
     a.emit_push(Size::S64, Location::GPR(GPR::R14));
@@ -8524,7 +9509,7 @@ pub(crate) fn gen_std_trampoline(
     // Prepare stack space.
     a.emit_sub(
         Size::S64,
-        Location::Imm32(stack_offset + stack_padding),
+        Location::Imm32(frame_size),
         Location::GPR(GPR::RSP),
     );
 
@@ -8540,13 +9525,27 @@ pub(crate) fn gen_std_trampoline(
         Location::GPR(GPR::R14),
     ); // args_rets
 
+    // `callee_vmctx` is already in the first argument register, unless the
+    // pointer to the return buffer goes there.
+    if returns_in_memory {
+        a.emit_mov(
+            Size::S64,
+            Machine::get_param_location(0, calling_convention),
+            Machine::get_param_location(1, calling_convention),
+        );
+        a.emit_lea(
+            Size::S64,
+            Location::Memory(GPR::RSP, return_buffer_offset as i32),
+            Machine::get_param_location(0, calling_convention),
+        );
+    }
+
     // Move arguments to their locations.
-    // `callee_vmctx` is already in the first argument register, so no need to move.
     {
         let mut n_stack_args: usize = 0;
         for (i, _param) in sig.params().iter().enumerate() {
             let src_loc = Location::Memory(GPR::R14, (i * 16) as _); // args_rets[i]
-            let dst_loc = Machine::get_param_location(1 + i, calling_convention);
+            let dst_loc = Machine::get_param_location(first_param + i, calling_convention);
 
             match dst_loc {
                 Location::GPR(_) => {
@@ -8574,15 +9573,23 @@ pub(crate) fn gen_std_trampoline(
     // Call.
     a.emit_call_location(Location::GPR(GPR::R15));
 
-    // Restore stack.
-    a.emit_add(
-        Size::S64,
-        Location::Imm32(stack_offset + stack_padding),
-        Location::GPR(GPR::RSP),
-    );
-
-    // Write return value.
-    if !sig.results().is_empty() {
+    // Write return values.
+    if let Some(layout) = &return_layout {
+        let return_buffer = Location::Memory(GPR::RSP, return_buffer_offset as i32);
+        emit_store_return_registers(&mut a, layout, return_buffer);
+        for (i, &(offset, size)) in return_layout_fields(layout).iter().enumerate() {
+            a.emit_mov(
+                size,
+                memory_at(return_buffer, offset),
+                Location::GPR(GPR::RAX),
+            );
+            a.emit_mov(
+                Size::S64,
+                Location::GPR(GPR::RAX),
+                Location::Memory(GPR::R14, (i * 16) as i32),
+            );
+        }
+    } else if !sig.results().is_empty() {
         a.emit_mov(
             Size::S64,
             Location::GPR(GPR::RAX),
@@ -8590,6 +9597,13 @@ pub(crate) fn gen_std_trampoline(
         );
     }
 
+    // Restore stack.
+    a.emit_add(
+        Size::S64,
+        Location::Imm32(frame_size),
+        Location::GPR(GPR::RSP),
+    );
+
     // Restore callee-saved registers.
     a.emit_pop(Size::S64, Location::GPR(GPR::R14));
     a.emit_pop(Size::S64, Location::GPR(GPR::R15));
@@ -8607,15 +9621,30 @@ pub(crate) fn gen_std_dynamic_import_trampoline(
     vmoffsets: &VMOffsets,
     sig: &FunctionType,
     calling_convention: CallingConvention,
-) -> FunctionBody {
+) -> Result<FunctionBody, CompileError> {
     let mut a = Assembler::new(0);
 
-    // Allocate argument array.
-    let stack_offset: usize = 16 * std::cmp::max(sig.params().len(), sig.results().len()) + 8; // 16 bytes each + 8 bytes sysv call padding
+    // Multiple results are returned as a C struct, in registers or in memory
+    // the caller passes a pointer to as the first parameter.
+    let return_layout = ReturnLayout::new(sig.results(), calling_convention);
+    let returns_in_memory = matches!(&return_layout, Some(layout) if layout.registers.is_none());
+    let vmctx_reg = match Machine::get_param_location(
+        if returns_in_memory { 1 } else { 0 },
+        calling_convention,
+    ) {
+        Location::GPR(gpr) => gpr,
+        _ => unreachable!(),
+    };
+
+    // Allocate argument array, followed by a slot saving the pointer to the
+    // return struct.
+    let values_size: usize = 16 * std::cmp::max(sig.params().len(), sig.results().len());
+    let stack_offset: usize = values_size + 8 + if returns_in_memory { 16 } else { 0 }; // 16 bytes each + 8 bytes sysv call padding
     let stack_padding: usize = match calling_convention {
         CallingConvention::WindowsFastcall => 32,
         _ => 0,
     };
+    let return_pointer_slot = Location::Memory(GPR::RSP, (stack_padding + values_size) as i32);
     a.emit_sub(
         Size::S64,
         Location::Imm32((stack_offset + stack_padding) as _),
@@ -8625,6 +9654,9 @@ pub(crate) fn gen_std_dynamic_import_trampoline(
     // Copy arguments.
     if !sig.params().is_empty() {
         let mut argalloc = ArgumentRegisterAllocator::default();
+        if returns_in_memory {
+            argalloc.next(Type::I64, calling_convention).unwrap(); // skip return pointer
+        }
         argalloc.next(Type::I64, calling_convention).unwrap(); // skip VMContext
 
         let mut stack_param_count: usize = 0;
@@ -8661,17 +9693,28 @@ pub(crate) fn gen_std_dynamic_import_trampoline(
         }
     }
 
+    if returns_in_memory {
+        a.emit_mov(
+            Size::S64,
+            Machine::get_param_location(0, calling_convention),
+            return_pointer_slot,
+        );
+    }
+
     match calling_convention {
         CallingConvention::WindowsFastcall => {
             // Load target address.
             a.emit_mov(
                 Size::S64,
                 Location::Memory(
-                    GPR::RCX,
+                    vmctx_reg,
                     vmoffsets.vmdynamicfunction_import_context_address() as i32,
                 ),
                 Location::GPR(GPR::RAX),
             );
+            if vmctx_reg != GPR::RCX {
+                a.emit_mov(Size::S64, Location::GPR(vmctx_reg), Location::GPR(GPR::RCX));
+            }
             // Load values array.
             a.emit_lea(
                 Size::S64,
@@ -8684,11 +9727,14 @@ pub(crate) fn gen_std_dynamic_import_trampoline(
             a.emit_mov(
                 Size::S64,
                 Location::Memory(
-                    GPR::RDI,
+                    vmctx_reg,
                     vmoffsets.vmdynamicfunction_import_context_address() as i32,
                 ),
                 Location::GPR(GPR::RAX),
             );
+            if vmctx_reg != GPR::RDI {
+                a.emit_mov(Size::S64, Location::GPR(vmctx_reg), Location::GPR(GPR::RDI));
+            }
             // Load values array.
             a.emit_mov(Size::S64, Location::GPR(GPR::RSP), Location::GPR(GPR::RSI));
         }
@@ -8697,9 +9743,26 @@ pub(crate) fn gen_std_dynamic_import_trampoline(
     // Call target.
     a.emit_call_location(Location::GPR(GPR::RAX));
 
-    // Fetch return value.
-    if !sig.results().is_empty() {
-        assert_eq!(sig.results().len(), 1);
+    // Fetch return values.
+    if let Some(layout) = &return_layout {
+        // Pack the values array into the return struct in place. Each value
+        // is moved to a lower address than the ones not yet moved.
+        let values = Location::Memory(GPR::RSP, stack_padding as i32);
+        for (i, &(offset, size)) in return_layout_fields(layout).iter().enumerate() {
+            a.emit_mov(
+                size,
+                memory_at(values, (i * 16) as i32),
+                Location::GPR(GPR::RCX),
+            );
+            a.emit_mov(size, Location::GPR(GPR::RCX), memory_at(values, offset));
+        }
+        if returns_in_memory {
+            a.emit_mov(Size::S64, return_pointer_slot, Location::GPR(GPR::RAX));
+            emit_copy_return_values(&mut a, layout, values, Location::Memory(GPR::RAX, 0));
+        } else {
+            emit_load_return_registers(&mut a, layout, values);
+        }
+    } else if !sig.results().is_empty() {
         a.emit_mov(
             Size::S64,
             Location::Memory(GPR::RSP, stack_padding as i32),
@@ -8717,10 +9780,10 @@ pub(crate) fn gen_std_dynamic_import_trampoline(
     // Return.
     a.emit_ret();
 
-    FunctionBody {
+    Ok(FunctionBody {
         body: a.finalize().unwrap().to_vec(),
         unwind_info: None,
-    }
+    })
 }
 
 // Singlepass calls import functions through a trampoline.
@@ -8729,11 +9792,23 @@ pub(crate) fn gen_import_call_trampoline(
     index: FunctionIndex,
     sig: &FunctionType,
     calling_convention: CallingConvention,
-) -> CustomSection {
+) -> Result<CustomSection, CompileError> {
     let mut a = Assembler::new(0);
 
     // TODO: ARM entry trampoline is not emitted.
 
+    // A function returning multiple values in memory takes the pointer to
+    // them as its first parameter, before the vmctx.
+    let returns_in_memory = matches!(
+        ReturnLayout::new(sig.results(), calling_convention),
+        Some(layout) if layout.registers.is_none()
+    );
+    let first_param = if returns_in_memory { 2 } else { 1 };
+    let vmctx_reg = match Machine::get_param_location(first_param - 1, calling_convention) {
+        Location::GPR(gpr) => gpr,
+        _ => unreachable!(),
+    };
+
     // Singlepass internally treats all arguments as integers
     // For the standard Windows calling convention requires
     //  floating point arguments to be passed in XMM registers for the 4 first arguments only
@@ -8748,21 +9823,13 @@ pub(crate) fn gen_import_call_trampoline(
     {
         match calling_convention {
             CallingConvention::WindowsFastcall => {
-                let mut param_locations: Vec<Location> = vec![];
-                for i in 0..sig.params().len() {
-                    let loc = match i {
-                        0..=2 => {
-                            static PARAM_REGS: &[GPR] = &[GPR::RDX, GPR::R8, GPR::R9];
-                            Location::GPR(PARAM_REGS[i])
-                        }
-                        _ => Location::Memory(GPR::RSP, 32 + 8 + ((i - 3) * 8) as i32), // will not be used anyway
-                    };
-                    param_locations.push(loc);
-                }
                 // Copy Float arguments to XMM from GPR.
                 let mut argalloc = ArgumentRegisterAllocator::default();
+                for _ in 0..first_param {
+                    argalloc.next(Type::I64, calling_convention).unwrap(); // skip VMContext and return pointer
+                }
                 for (i, ty) in sig.params().iter().enumerate() {
-                    let prev_loc = param_locations[i];
+                    let prev_loc = Machine::get_param_location(first_param + i, calling_convention);
                     match argalloc.next(*ty, calling_convention) {
                         Some(X64Register::GPR(_gpr)) => continue,
                         Some(X64Register::XMM(xmm)) => {
@@ -8776,11 +9843,15 @@ pub(crate) fn gen_import_call_trampoline(
                 let mut param_locations: Vec<Location> = vec![];
 
                 // Allocate stack space for arguments.
-                let stack_offset: i32 = if sig.params().len() > 5 {
-                    5 * 8
-                } else {
-                    (sig.params().len() as i32) * 8
-                };
+                let n_reg_params = (0..sig.params().len())
+                    .filter(|i| {
+                        matches!(
+                            Machine::get_param_location(first_param + i, calling_convention),
+                            Location::GPR(_)
+                        )
+                    })
+                    .count();
+                let stack_offset: i32 = (n_reg_params as i32) * 8;
                 if stack_offset > 0 {
                     a.emit_sub(
                         Size::S64,
@@ -8791,22 +9862,28 @@ pub(crate) fn gen_import_call_trampoline(
 
                 // Store all arguments to the stack to prevent overwrite.
                 for i in 0..sig.params().len() {
-                    let loc = match i {
-                        0..=4 => {
-                            static PARAM_REGS: &[GPR] =
-                                &[GPR::RSI, GPR::RDX, GPR::RCX, GPR::R8, GPR::R9];
-                            let loc = Location::Memory(GPR::RSP, (i * 8) as i32);
-                            a.emit_mov(Size::S64, Location::GPR(PARAM_REGS[i]), loc);
-                            loc
-                        }
-                        _ => Location::Memory(GPR::RSP, stack_offset + 8 + ((i - 5) * 8) as i32),
+                    let loc = if i < n_reg_params {
+                        let loc = Location::Memory(GPR::RSP, (i * 8) as i32);
+                        a.emit_mov(
+                            Size::S64,
+                            Machine::get_param_location(first_param + i, calling_convention),
+                            loc,
+                        );
+                        loc
+                    } else {
+                        Location::Memory(
+                            GPR::RSP,
+                            stack_offset + 8 + ((i - n_reg_params) * 8) as i32,
+                        )
                     };
                     param_locations.push(loc);
                 }
 
                 // Copy arguments.
                 let mut argalloc = ArgumentRegisterAllocator::default();
-                argalloc.next(Type::I64, calling_convention).unwrap(); // skip VMContext
+                for _ in 0..first_param {
+                    argalloc.next(Type::I64, calling_convention).unwrap(); // skip VMContext and return pointer
+                }
                 let mut caller_stack_offset: i32 = 0;
                 for (i, ty) in sig.params().iter().enumerate() {
                     let prev_loc = param_locations[i];
@@ -8850,41 +9927,25 @@ pub(crate) fn gen_import_call_trampoline(
     let body_offset = vmoffsets.vmctx_vmfunction_import_body(index);
     let vmctx_offset = vmoffsets.vmctx_vmfunction_import_vmctx(index);
 
-    match calling_convention {
-        CallingConvention::WindowsFastcall => {
-            a.emit_mov(
-                Size::S64,
-                Location::Memory(GPR::RCX, body_offset as i32), // function pointer
-                Location::GPR(GPR::RAX),
-            );
-            a.emit_mov(
-                Size::S64,
-                Location::Memory(GPR::RCX, vmctx_offset as i32), // target vmctx
-                Location::GPR(GPR::RCX),
-            );
-        }
-        _ => {
-            a.emit_mov(
-                Size::S64,
-                Location::Memory(GPR::RDI, body_offset as i32), // function pointer
-                Location::GPR(GPR::RAX),
-            );
-            a.emit_mov(
-                Size::S64,
-                Location::Memory(GPR::RDI, vmctx_offset as i32), // target vmctx
-                Location::GPR(GPR::RDI),
-            );
-        }
-    }
+    a.emit_mov(
+        Size::S64,
+        Location::Memory(vmctx_reg, body_offset as i32), // function pointer
+        Location::GPR(GPR::RAX),
+    );
+    a.emit_mov(
+        Size::S64,
+        Location::Memory(vmctx_reg, vmctx_offset as i32), // target vmctx
+        Location::GPR(vmctx_reg),
+    );
     a.emit_host_redirection(GPR::RAX);
 
     let section_body = SectionBody::new_with_vec(a.finalize().unwrap().to_vec());
 
-    CustomSection {
+    Ok(CustomSection {
         protection: CustomSectionProtection::ReadExecute,
         bytes: section_body,
         relocations: vec![],
-    }
+    })
 }
 
 // Constants for the bounds of truncation operations. These are the least or
@@ -8893,35 +9954,35 @@ pub(crate) fn gen_import_call_trampoline(
 // min (for least) or max (for greatest), when rounding towards zero.
 
 /// Greatest Exact Float (32 bits) less-than i32::MIN when rounding towards zero.
-const GEF32_LT_I32_MIN: f32 = -2147483904.0;
+pub(crate) const GEF32_LT_I32_MIN: f32 = -2147483904.0;
 /// Least Exact Float (32 bits) greater-than i32::MAX when rounding towards zero.
-const LEF32_GT_I32_MAX: f32 = 2147483648.0;
+pub(crate) const LEF32_GT_I32_MAX: f32 = 2147483648.0;
 /// Greatest Exact Float (32 bits) less-than i64::MIN when rounding towards zero.
-const GEF32_LT_I64_MIN: f32 = -9223373136366403584.0;
+pub(crate) const GEF32_LT_I64_MIN: f32 = -9223373136366403584.0;
 /// Least Exact Float (32 bits) greater-than i64::MAX when rounding towards zero.
-const LEF32_GT_I64_MAX: f32 = 9223372036854775808.0;
+pub(crate) const LEF32_GT_I64_MAX: f32 = 9223372036854775808.0;
 /// Greatest Exact Float (32 bits) less-than u32::MIN when rounding towards zero.
-const GEF32_LT_U32_MIN: f32 = -1.0;
+pub(crate) const GEF32_LT_U32_MIN: f32 = -1.0;
 /// Least Exact Float (32 bits) greater-than u32::MAX when rounding towards zero.
-const LEF32_GT_U32_MAX: f32 = 4294967296.0;
+pub(crate) const LEF32_GT_U32_MAX: f32 = 4294967296.0;
 /// Greatest Exact Float (32 bits) less-than u64::MIN when rounding towards zero.
-const GEF32_LT_U64_MIN: f32 = -1.0;
+pub(crate) const GEF32_LT_U64_MIN: f32 = -1.0;
 /// Least Exact Float (32 bits) greater-than u64::MAX when rounding towards zero.
-const LEF32_GT_U64_MAX: f32 = 18446744073709551616.0;
+pub(crate) const LEF32_GT_U64_MAX: f32 = 18446744073709551616.0;
 
 /// Greatest Exact Float (64 bits) less-than i32::MIN when rounding towards zero.
-const GEF64_LT_I32_MIN: f64 = -2147483649.0;
+pub(crate) const GEF64_LT_I32_MIN: f64 = -2147483649.0;
 /// Least Exact Float (64 bits) greater-than i32::MAX when rounding towards zero.
-const LEF64_GT_I32_MAX: f64 = 2147483648.0;
+pub(crate) const LEF64_GT_I32_MAX: f64 = 2147483648.0;
 /// Greatest Exact Float (64 bits) less-than i64::MIN when rounding towards zero.
-const GEF64_LT_I64_MIN: f64 = -9223372036854777856.0;
+pub(crate) const GEF64_LT_I64_MIN: f64 = -9223372036854777856.0;
 /// Least Exact Float (64 bits) greater-than i64::MAX when rounding towards zero.
-const LEF64_GT_I64_MAX: f64 = 9223372036854775808.0;
+pub(crate) const LEF64_GT_I64_MAX: f64 = 9223372036854775808.0;
 /// Greatest Exact Float (64 bits) less-than u32::MIN when rounding towards zero.
-const GEF64_LT_U32_MIN: f64 = -1.0;
+pub(crate) const GEF64_LT_U32_MIN: f64 = -1.0;
 /// Least Exact Float (64 bits) greater-than u32::MAX when rounding towards zero.
-const LEF64_GT_U32_MAX: f64 = 4294967296.0;
+pub(crate) const LEF64_GT_U32_MAX: f64 = 4294967296.0;
 /// Greatest Exact Float (64 bits) less-than u64::MIN when rounding towards zero.
-const GEF64_LT_U64_MIN: f64 = -1.0;
+pub(crate) const GEF64_LT_U64_MIN: f64 = -1.0;
 /// Least Exact Float (64 bits) greater-than u64::MAX when rounding towards zero.
-const LEF64_GT_U64_MAX: f64 = 18446744073709551616.0;
+pub(crate) const LEF64_GT_U64_MAX: f64 = 18446744073709551616.0;
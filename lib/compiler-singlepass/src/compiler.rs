@@ -2,17 +2,16 @@
 // Allow unused imports while developing.
 #![allow(unused_imports, dead_code)]
 
-use crate::codegen_x64::{
-    gen_import_call_trampoline, gen_std_dynamic_import_trampoline, gen_std_trampoline,
-    CodegenError, FuncGen,
-};
+use crate::codegen_x64::CodegenError;
 use crate::config::Singlepass;
+use crate::{codegen_arm64, codegen_x64};
 #[cfg(feature = "rayon")]
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 use std::sync::Arc;
 use wasmer_compiler::{
     Architecture, CallingConvention, Compilation, CompileError, CompileModuleInfo,
     CompiledFunction, Compiler, CompilerConfig, CpuFeature, FunctionBody, FunctionBodyData,
+    FunctionCompilation, MiddlewareOperatorsReader, ModuleMiddleware, ModuleMiddlewareChain,
     ModuleTranslationState, OperatingSystem, SectionIndex, Target, TrapInformation,
 };
 use wasmer_types::entity::{EntityRef, PrimaryMap};
@@ -21,6 +20,113 @@ use wasmer_types::{
 };
 use wasmer_vm::{TrapCode, VMOffsets};
 
+/// Compiles a function with the given codegen module, which must provide
+/// `FuncGen`.
+macro_rules! compile_function_with {
+    ($codegen:ident, $self:ident, $target:ident, $compile_info:ident, $module_translation:ident, $index:ident, $input:ident, $calling_convention:ident) => {{
+        let module = &$compile_info.module;
+        let vmoffsets = vmoffsets($target, module)?;
+        let reader = wasmer_compiler::FunctionReader::new($input.module_offset, $input.data);
+
+        let mut local_reader = reader.get_locals_reader()?;
+        // This local list excludes arguments.
+        let mut locals = vec![];
+        let num_locals = local_reader.get_count();
+        for _ in 0..num_locals {
+            let (count, ty) = local_reader.read()?;
+            for _ in 0..count {
+                locals.push(ty);
+            }
+        }
+
+        let mut generator = $codegen::FuncGen::new(
+            module,
+            $module_translation,
+            &$self.config,
+            &vmoffsets,
+            &$compile_info.table_styles,
+            $index,
+            &locals,
+            $calling_convention,
+            $compile_info.features.exceptions,
+        )
+        .map_err(to_compile_error)?;
+
+        let mut operator_reader = MiddlewareOperatorsReader::new(
+            reader.get_operators_reader()?,
+            $self
+                .config
+                .middlewares
+                .generate_function_middleware_chain($index),
+        );
+        while generator.has_control_frames() {
+            let (op, pos) = operator_reader.expect_operator()?;
+            generator.set_srcloc(pos as u32);
+            generator.feed_operator(op).map_err(to_compile_error)?;
+        }
+
+        Ok(generator.finalize($input))
+    }};
+}
+
+/// Completes the compilation of a module around its compiled functions with
+/// the given codegen module, which must provide the trampoline generators.
+macro_rules! compile_trampolines_with {
+    ($codegen:ident, $target:ident, $compile_info:ident, $functions:ident, $calling_convention:ident) => {{
+        let module = &$compile_info.module;
+        let vmoffsets = vmoffsets($target, module)?;
+        let import_idxs = 0..module.import_counts.functions as usize;
+        let import_trampolines: PrimaryMap<SectionIndex, _> = import_idxs
+            .into_par_iter_if_rayon()
+            .map(|i| {
+                let i = FunctionIndex::new(i);
+                $codegen::gen_import_call_trampoline(
+                    &vmoffsets,
+                    i,
+                    &module.signatures[module.functions[i]],
+                    $calling_convention,
+                )
+            })
+            .collect::<Result<Vec<_>, CompileError>>()?
+            .into_iter()
+            .collect();
+
+        let function_call_trampolines = module
+            .signatures
+            .values()
+            .collect::<Vec<_>>()
+            .into_par_iter_if_rayon()
+            .map(|func_type| $codegen::gen_std_trampoline(&func_type, $calling_convention))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .collect::<PrimaryMap<_, _>>();
+
+        let dynamic_function_trampolines = module
+            .imported_function_types()
+            .collect::<Vec<_>>()
+            .into_par_iter_if_rayon()
+            .map(|func_type| {
+                $codegen::gen_std_dynamic_import_trampoline(
+                    &vmoffsets,
+                    &func_type,
+                    $calling_convention,
+                )
+            })
+            .collect::<Result<Vec<_>, CompileError>>()?
+            .into_iter()
+            .collect::<PrimaryMap<FunctionIndex, FunctionBody>>();
+
+        Ok(Compilation::new(
+            $functions,
+            import_trampolines,
+            function_call_trampolines,
+            dynamic_function_trampolines,
+            None,
+            None,
+        ))
+    }};
+}
+
 /// A compiler that compiles a WebAssembly module with Singlepass.
 /// It does the compilation in one pass
 pub struct SinglepassCompiler {
@@ -37,143 +143,210 @@ impl SinglepassCompiler {
     fn config(&self) -> &Singlepass {
         &self.config
     }
-}
 
-impl Compiler for SinglepassCompiler {
-    /// Compile the module using Singlepass, producing a compilation result with
-    /// associated relocations.
-    fn compile_module(
-        &self,
-        target: &Target,
-        compile_info: &CompileModuleInfo,
-        module_translation: &ModuleTranslationState,
-        function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
-    ) -> Result<Compilation, CompileError> {
+    /// Checks that modules can be compiled for `target`, and returns the
+    /// calling convention to compile them with.
+    fn calling_convention(&self, target: &Target) -> Result<CallingConvention, CompileError> {
         /*if target.triple().operating_system == OperatingSystem::Windows {
             return Err(CompileError::UnsupportedTarget(
                 OperatingSystem::Windows.to_string(),
             ));
         }*/
-        if target.triple().architecture != Architecture::X86_64 {
-            return Err(CompileError::UnsupportedTarget(
-                target.triple().architecture.to_string(),
-            ));
-        }
-        if !target.cpu_features().contains(CpuFeature::AVX) {
-            return Err(CompileError::UnsupportedTarget(
-                "x86_64 without AVX".to_string(),
-            ));
-        }
-        if compile_info.features.multi_value {
-            return Err(CompileError::UnsupportedFeature("multivalue".to_string()));
+        let architecture = target.triple().architecture;
+        if !matches!(
+            architecture,
+            Architecture::X86_64 | Architecture::Aarch64(_)
+        ) {
+            return Err(CompileError::UnsupportedTarget(architecture.to_string()));
         }
         let calling_convention = match target.triple().default_calling_convention() {
             Ok(CallingConvention::WindowsFastcall) => CallingConvention::WindowsFastcall,
             Ok(CallingConvention::SystemV) => CallingConvention::SystemV,
-            //Ok(CallingConvention::AppleAarch64) => AppleAarch64,
+            Ok(CallingConvention::AppleAarch64) => CallingConvention::AppleAarch64,
             _ => panic!("Unsupported Calling convention for Singlepass compiler"),
         };
 
-        let table_styles = &compile_info.table_styles;
-        let module = &compile_info.module;
-        let pointer_width = target
-            .triple()
-            .pointer_width()
-            .map_err(|()| {
-                CompileError::UnsupportedTarget("target with unknown pointer width".into())
-            })?
-            .bytes();
-        let vmoffsets = VMOffsets::new(pointer_width).with_module_info(&module);
-        let import_idxs = 0..module.import_counts.functions as usize;
-        let import_trampolines: PrimaryMap<SectionIndex, _> = import_idxs
-            .into_par_iter_if_rayon()
-            .map(|i| {
-                let i = FunctionIndex::new(i);
-                gen_import_call_trampoline(
-                    &vmoffsets,
-                    i,
-                    &module.signatures[module.functions[i]],
-                    calling_convention,
-                )
-            })
-            .collect::<Vec<_>>()
-            .into_iter()
-            .collect();
+        match architecture {
+            Architecture::X86_64 => {
+                if !target.cpu_features().contains(CpuFeature::AVX) {
+                    return Err(CompileError::UnsupportedTarget(
+                        "x86_64 without AVX".to_string(),
+                    ));
+                }
+            }
+            Architecture::Aarch64(_) => {
+                if calling_convention != CallingConvention::SystemV {
+                    return Err(CompileError::UnsupportedTarget(format!(
+                        "aarch64 with the {:?} calling convention",
+                        calling_convention
+                    )));
+                }
+            }
+            _ => unreachable!(),
+        }
+        Ok(calling_convention)
+    }
+
+    fn compile_function_with_calling_convention(
+        &self,
+        target: &Target,
+        compile_info: &CompileModuleInfo,
+        module_translation: &ModuleTranslationState,
+        index: LocalFunctionIndex,
+        input: &FunctionBodyData<'_>,
+        calling_convention: CallingConvention,
+    ) -> Result<CompiledFunction, CompileError> {
+        match target.triple().architecture {
+            Architecture::X86_64 => compile_function_with!(
+                codegen_x64,
+                self,
+                target,
+                compile_info,
+                module_translation,
+                index,
+                input,
+                calling_convention
+            ),
+            Architecture::Aarch64(_) => compile_function_with!(
+                codegen_arm64,
+                self,
+                target,
+                compile_info,
+                module_translation,
+                index,
+                input,
+                calling_convention
+            ),
+            _ => unreachable!(),
+        }
+    }
+
+    fn compile_trampolines(
+        &self,
+        target: &Target,
+        compile_info: &CompileModuleInfo,
+        functions: PrimaryMap<LocalFunctionIndex, CompiledFunction>,
+        calling_convention: CallingConvention,
+    ) -> Result<Compilation, CompileError> {
+        match target.triple().architecture {
+            Architecture::X86_64 => compile_trampolines_with!(
+                codegen_x64,
+                target,
+                compile_info,
+                functions,
+                calling_convention
+            ),
+            Architecture::Aarch64(_) => compile_trampolines_with!(
+                codegen_arm64,
+                target,
+                compile_info,
+                functions,
+                calling_convention
+            ),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Compiler for SinglepassCompiler {
+    fn name(&self) -> &str {
+        "singlepass"
+    }
+
+    fn version(&self) -> &str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn epoch_interruption(&self) -> bool {
+        self.config.enable_epoch_interruption
+    }
+
+    /// Get the middlewares for this compiler
+    fn get_middlewares(&self) -> &[Arc<dyn ModuleMiddleware>] {
+        &self.config.middlewares
+    }
+
+    /// Compile the module using Singlepass, producing a compilation result with
+    /// associated relocations.
+    fn compile_module(
+        &self,
+        target: &Target,
+        compile_info: &CompileModuleInfo,
+        module_translation: &ModuleTranslationState,
+        function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+    ) -> Result<Compilation, CompileError> {
+        let calling_convention = self.calling_convention(target)?;
         let functions = function_body_inputs
             .iter()
             .collect::<Vec<(LocalFunctionIndex, &FunctionBodyData<'_>)>>()
             .into_par_iter_if_rayon()
             .map(|(i, input)| {
-                let reader = wasmer_compiler::FunctionReader::new(input.module_offset, input.data);
-
-                let mut local_reader = reader.get_locals_reader()?;
-                // This local list excludes arguments.
-                let mut locals = vec![];
-                let num_locals = local_reader.get_count();
-                for _ in 0..num_locals {
-                    let (count, ty) = local_reader.read()?;
-                    for _ in 0..count {
-                        locals.push(ty);
-                    }
-                }
-
-                let mut generator = FuncGen::new(
-                    module,
+                self.compile_function_with_calling_convention(
+                    target,
+                    compile_info,
                     module_translation,
-                    &self.config,
-                    &vmoffsets,
-                    &table_styles,
                     i,
-                    &locals,
+                    input,
                     calling_convention,
                 )
-                .map_err(to_compile_error)?;
-
-                let mut operator_reader = reader.get_operators_reader()?.into_iter_with_offsets();
-                while generator.has_control_frames() {
-                    let (op, pos) = operator_reader.next().unwrap()?;
-                    generator.set_srcloc(pos as u32);
-                    generator.feed_operator(op).map_err(to_compile_error)?;
-                }
-
-                Ok(generator.finalize(&input))
             })
             .collect::<Result<Vec<CompiledFunction>, CompileError>>()?
             .into_iter()
             .collect::<PrimaryMap<LocalFunctionIndex, CompiledFunction>>();
+        self.compile_trampolines(target, compile_info, functions, calling_convention)
+    }
 
-        let function_call_trampolines = module
-            .signatures
-            .values()
-            .collect::<Vec<_>>()
-            .into_par_iter_if_rayon()
-            .map(|func_type| gen_std_trampoline(&func_type, calling_convention))
-            .collect::<Vec<_>>()
-            .into_iter()
-            .collect::<PrimaryMap<_, _>>();
+    fn supports_function_compilation(&self) -> bool {
+        true
+    }
 
-        let dynamic_function_trampolines = module
-            .imported_function_types()
-            .collect::<Vec<_>>()
-            .into_par_iter_if_rayon()
-            .map(|func_type| {
-                gen_std_dynamic_import_trampoline(&vmoffsets, &func_type, calling_convention)
-            })
-            .collect::<Vec<_>>()
-            .into_iter()
-            .collect::<PrimaryMap<FunctionIndex, FunctionBody>>();
+    fn compile_function(
+        &self,
+        target: &Target,
+        compile_info: &CompileModuleInfo,
+        module_translation: &ModuleTranslationState,
+        index: LocalFunctionIndex,
+        function_body: &FunctionBodyData<'_>,
+    ) -> Result<FunctionCompilation, CompileError> {
+        let calling_convention = self.calling_convention(target)?;
+        // The functions only refer to the sections of the module, made in
+        // `compile_module_with_functions`.
+        self.compile_function_with_calling_convention(
+            target,
+            compile_info,
+            module_translation,
+            index,
+            function_body,
+            calling_convention,
+        )
+        .map(FunctionCompilation::from)
+    }
 
-        Ok(Compilation::new(
-            functions,
-            import_trampolines,
-            function_call_trampolines,
-            dynamic_function_trampolines,
-            None,
-            None,
-        ))
+    fn compile_module_with_functions(
+        &self,
+        target: &Target,
+        compile_info: &CompileModuleInfo,
+        functions: PrimaryMap<LocalFunctionIndex, FunctionCompilation>,
+    ) -> Result<Compilation, CompileError> {
+        let calling_convention = self.calling_convention(target)?;
+        let functions = functions
+            .into_iter()
+            .map(|(_, compilation)| compilation.function)
+            .collect();
+        self.compile_trampolines(target, compile_info, functions, calling_convention)
     }
 }
 
+/// The offsets of the `VMContext` of `module` on `target`.
+fn vmoffsets(target: &Target, module: &ModuleInfo) -> Result<VMOffsets, CompileError> {
+    let pointer_width = target
+        .triple()
+        .pointer_width()
+        .map_err(|()| CompileError::UnsupportedTarget("target with unknown pointer width".into()))?
+        .bytes();
+    Ok(VMOffsets::new(pointer_width).with_module_info(module))
+}
+
 trait ToCompileError {
     fn to_compile_error(self) -> CompileError;
 }
@@ -264,4 +437,23 @@ mod tests {
             error => panic!("Unexpected error: {:?}", error),
         };
     }
+
+    #[test]
+    fn supports_aarch64_linux() {
+        let compiler = SinglepassCompiler::new(Singlepass::default());
+
+        let aarch64 = Target::new(triple!("aarch64-unknown-linux-gnu"), CpuFeature::set());
+        let (mut info, translation, inputs) = dummy_compilation_ingredients();
+        let result = compiler.compile_module(&aarch64, &mut info, &translation, inputs);
+        assert!(result.is_ok());
+
+        // Apple uses its own variant of the AAPCS64.
+        let apple = Target::new(triple!("aarch64-apple-darwin"), CpuFeature::set());
+        let (mut info, translation, inputs) = dummy_compilation_ingredients();
+        let result = compiler.compile_module(&apple, &mut info, &translation, inputs);
+        match result.unwrap_err() {
+            CompileError::UnsupportedTarget(_) => {}
+            error => panic!("Unexpected error: {:?}", error),
+        };
+    }
 }
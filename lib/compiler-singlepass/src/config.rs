@@ -2,11 +2,9 @@
 #![allow(unused_imports, dead_code)]
 
 use crate::compiler::SinglepassCompiler;
-use crate::emitter_x64::Location;
-use smallvec::SmallVec;
 use std::sync::Arc;
-use wasmer_compiler::{Compiler, CompilerConfig, CpuFeature, Target};
-use wasmer_types::{Features, FunctionType, Type};
+use wasmer_compiler::{Compiler, CompilerConfig, CpuFeature, ModuleMiddleware};
+use wasmer_types::{FunctionType, Type};
 
 #[derive(Debug, Clone)]
 pub(crate) enum IntrinsicKind {
@@ -24,8 +22,11 @@ pub(crate) struct Intrinsic {
 pub struct Singlepass {
     pub(crate) enable_nan_canonicalization: bool,
     pub(crate) enable_stack_check: bool,
+    pub(crate) enable_epoch_interruption: bool,
     /// Compiler intrinsics.
     pub(crate) intrinsics: Vec<Intrinsic>,
+    /// The middleware chain.
+    pub(crate) middlewares: Vec<Arc<dyn ModuleMiddleware>>,
 }
 
 impl Singlepass {
@@ -35,11 +36,13 @@ impl Singlepass {
         Self {
             enable_nan_canonicalization: true,
             enable_stack_check: false,
+            enable_epoch_interruption: false,
             intrinsics: vec![Intrinsic {
                 kind: IntrinsicKind::Gas,
                 name: "gas".to_string(),
                 signature: ([Type::I32], []).into(),
             }],
+            middlewares: vec![],
         }
     }
 
@@ -55,6 +58,15 @@ impl Singlepass {
         self
     }
 
+    /// Enable epoch interruption.
+    ///
+    /// When enabled, the compiled code traps with `TrapCode::Interrupted`
+    /// once the epoch of the engine reaches the deadline of its instance.
+    pub fn epoch_interruption(&mut self, enable: bool) -> &mut Self {
+        self.enable_epoch_interruption = enable;
+        self
+    }
+
     fn enable_nan_canonicalization(&mut self) {
         self.enable_nan_canonicalization = true;
     }
@@ -71,16 +83,18 @@ impl CompilerConfig for Singlepass {
         // PIC code.
     }
 
+    fn epoch_interruption(&mut self, enable: bool) {
+        self.enable_epoch_interruption = enable;
+    }
+
     /// Transform it into the compiler
     fn compiler(self: Box<Self>) -> Box<dyn Compiler> {
         Box::new(SinglepassCompiler::new(*self))
     }
 
-    /// Gets the default features for this compiler in the given target
-    fn default_features_for_target(&self, _target: &Target) -> Features {
-        let mut features = Features::default();
-        features.multi_value(false);
-        features
+    /// Pushes a middleware onto the back of the middleware chain.
+    fn push_middleware(&mut self, middleware: Arc<dyn ModuleMiddleware>) {
+        self.middlewares.push(middleware);
     }
 }
 
@@ -91,11 +105,13 @@ impl Default for Singlepass {
 }
 
 impl Intrinsic {
-    pub(crate) fn is_params_ok(&self, params: &SmallVec<[Location; 8]>) -> bool {
+    /// Checks the call arguments, given as the value of each argument that is
+    /// an `i32` constant, or `None` if it's only known at runtime.
+    pub(crate) fn is_params_ok(&self, params: &[Option<u32>]) -> bool {
         match self.kind {
             IntrinsicKind::Gas => match params[0] {
-                Location::Imm32(value) => value < i32::MAX as u32,
-                _ => false,
+                Some(value) => value < i32::MAX as u32,
+                None => false,
             },
         }
     }
@@ -0,0 +1,842 @@
+pub(crate) use crate::arm64_decl::{GPR, NEON};
+use dynasmrt::{
+    aarch64::Aarch64Relocation, AssemblyOffset, DynamicLabel, DynasmApi, DynasmLabelApi,
+    VecAssembler,
+};
+
+pub(crate) type Assembler = VecAssembler<Aarch64Relocation>;
+
+/// Force `dynasm!` to use the correct arch (aarch64) when cross-compiling.
+/// `dynasm!` proc-macro tries to auto-detect it by default by looking at the
+/// `target_arch`, but it sees the `target_arch` of the proc-macro itself, which
+/// is always equal to host, even when cross-compiling.
+macro_rules! dynasm {
+    ($a:expr ; $($tt:tt)*) => {
+        dynasm::dynasm!(
+            $a
+            ; .arch aarch64
+            ; $($tt)*
+        )
+    };
+}
+
+/// `dynasm!` only accepts condition codes as literal identifiers, so dispatch
+/// a runtime `Condition` to the matching literal.
+macro_rules! dynasm_cond {
+    ($a:expr, $cond:expr, [$($pre:tt)*], [$($post:tt)*]) => {
+        match $cond {
+            Condition::Eq => dynasm!($a ; $($pre)* eq $($post)*),
+            Condition::Ne => dynasm!($a ; $($pre)* ne $($post)*),
+            Condition::Cs => dynasm!($a ; $($pre)* cs $($post)*),
+            Condition::Cc => dynasm!($a ; $($pre)* cc $($post)*),
+            Condition::Mi => dynasm!($a ; $($pre)* mi $($post)*),
+            Condition::Pl => dynasm!($a ; $($pre)* pl $($post)*),
+            Condition::Vs => dynasm!($a ; $($pre)* vs $($post)*),
+            Condition::Vc => dynasm!($a ; $($pre)* vc $($post)*),
+            Condition::Hi => dynasm!($a ; $($pre)* hi $($post)*),
+            Condition::Ls => dynasm!($a ; $($pre)* ls $($post)*),
+            Condition::Ge => dynasm!($a ; $($pre)* ge $($post)*),
+            Condition::Lt => dynasm!($a ; $($pre)* lt $($post)*),
+            Condition::Gt => dynasm!($a ; $($pre)* gt $($post)*),
+            Condition::Le => dynasm!($a ; $($pre)* le $($post)*),
+        }
+    };
+}
+
+/// Register reserved by the emitter to materialize out-of-range immediates.
+pub(crate) const SCRATCH: GPR = GPR::X17;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub(crate) enum Location {
+    Imm32(u32),
+    Imm64(u64),
+    GPR(GPR),
+    Memory(GPR, i32),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Condition {
+    Eq,
+    Ne,
+    /// Carry set / unsigned higher or same.
+    Cs,
+    /// Carry clear / unsigned lower.
+    Cc,
+    Mi,
+    Pl,
+    Vs,
+    Vc,
+    Hi,
+    Ls,
+    Ge,
+    Lt,
+    Gt,
+    Le,
+}
+
+impl Condition {
+    pub(crate) fn invert(self) -> Condition {
+        match self {
+            Condition::Eq => Condition::Ne,
+            Condition::Ne => Condition::Eq,
+            Condition::Cs => Condition::Cc,
+            Condition::Cc => Condition::Cs,
+            Condition::Mi => Condition::Pl,
+            Condition::Pl => Condition::Mi,
+            Condition::Vs => Condition::Vc,
+            Condition::Vc => Condition::Vs,
+            Condition::Hi => Condition::Ls,
+            Condition::Ls => Condition::Hi,
+            Condition::Ge => Condition::Lt,
+            Condition::Lt => Condition::Ge,
+            Condition::Gt => Condition::Le,
+            Condition::Le => Condition::Gt,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub(crate) enum Size {
+    S8,
+    S16,
+    S32,
+    S64,
+}
+
+impl Size {
+    fn bytes(self) -> i32 {
+        match self {
+            Size::S8 => 1,
+            Size::S16 => 2,
+            Size::S32 => 4,
+            Size::S64 => 8,
+        }
+    }
+}
+
+/// How a `[base, #offset]` operand ends up being encoded.
+#[derive(Copy, Clone, Debug)]
+enum Addressing {
+    /// Signed 9-bit unscaled offset (`ldur`/`stur`).
+    Unscaled(i32),
+    /// Unsigned 12-bit offset, scaled by the access size (`ldr`/`str`).
+    Scaled(u32),
+    /// The offset has been materialized in the scratch register.
+    Register,
+}
+
+/// Arithmetic and logical operations with the shape `dst = a op b`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum BinOp {
+    Add,
+    Sub,
+    Adds,
+    Subs,
+    Mul,
+    Sdiv,
+    Udiv,
+    And,
+    Orr,
+    Eor,
+    Lsl,
+    Lsr,
+    Asr,
+    Ror,
+}
+
+/// Scalar floating-point operations with the shape `dst = a op b`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum FBinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Min,
+    Max,
+}
+
+/// Scalar floating-point operations with the shape `dst = op src`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum FUnOp {
+    Sqrt,
+    /// Round to nearest, ties to even.
+    Nearest,
+    Floor,
+    Ceil,
+    Trunc,
+}
+
+pub(crate) trait EmitterARM64 {
+    fn get_label(&mut self) -> DynamicLabel;
+    fn get_offset(&self) -> AssemblyOffset;
+    fn emit_label(&mut self, label: DynamicLabel);
+
+    /// Materializes an arbitrary immediate with a `movz`/`movk` sequence.
+    fn emit_mov_imm(&mut self, sz: Size, dst: GPR, imm: u64);
+    /// Emits a single `movz dst, #imm, lsl #shift` (64-bit).
+    fn emit_movz(&mut self, dst: GPR, imm: u16, shift: u32);
+    /// Emits a single `movk dst, #imm, lsl #shift` (64-bit).
+    fn emit_movk(&mut self, dst: GPR, imm: u16, shift: u32);
+    fn emit_mov(&mut self, sz: Size, src: GPR, dst: GPR);
+    fn emit_mov_from_sp(&mut self, dst: GPR);
+    fn emit_mov_to_sp(&mut self, src: GPR);
+    /// `sub sp, sp, src`
+    fn emit_sub_sp(&mut self, src: GPR);
+
+    /// Zero-extending load of `sz` bytes.
+    fn emit_ldr(&mut self, sz: Size, dst: GPR, base: GPR, offset: i32);
+    /// Sign-extending load of `sz_src` bytes into a `sz_dst` register.
+    fn emit_ldrs(&mut self, sz_src: Size, sz_dst: Size, dst: GPR, base: GPR, offset: i32);
+    fn emit_str(&mut self, sz: Size, src: GPR, base: GPR, offset: i32);
+    fn emit_ldr_neon(&mut self, sz: Size, dst: NEON, base: GPR, offset: i32);
+    fn emit_str_neon(&mut self, sz: Size, src: NEON, base: GPR, offset: i32);
+    /// `stp a, b, [sp, #-16]!`
+    fn emit_push_pair(&mut self, a: GPR, b: GPR);
+    /// `ldp a, b, [sp], #16`
+    fn emit_pop_pair(&mut self, a: GPR, b: GPR);
+    /// `ldp a, b, [base, #offset]`
+    fn emit_ldp(&mut self, a: GPR, b: GPR, base: GPR, offset: i32);
+
+    fn emit_binop(&mut self, op: BinOp, sz: Size, dst: GPR, a: GPR, b: GPR);
+    /// `dst = a + imm`. `a` and `dst` may be the stack pointer.
+    fn emit_add_imm(&mut self, dst: GPR, a: GPR, imm: u32);
+    /// `dst = a - imm`. `a` and `dst` may be the stack pointer.
+    fn emit_sub_imm(&mut self, dst: GPR, a: GPR, imm: u32);
+    /// `dst = a + (b << shift)` (64-bit).
+    fn emit_add_lsl(&mut self, dst: GPR, a: GPR, b: GPR, shift: u32);
+    /// `dst = c - a * b`
+    fn emit_msub(&mut self, sz: Size, dst: GPR, a: GPR, b: GPR, c: GPR);
+    fn emit_cmp(&mut self, sz: Size, a: GPR, b: GPR);
+    fn emit_cmp_imm(&mut self, sz: Size, a: GPR, imm: u64);
+    fn emit_tst(&mut self, sz: Size, a: GPR, b: GPR);
+    fn emit_neg(&mut self, sz: Size, dst: GPR, src: GPR);
+    fn emit_clz(&mut self, sz: Size, dst: GPR, src: GPR);
+    fn emit_rbit(&mut self, sz: Size, dst: GPR, src: GPR);
+    fn emit_popcnt(&mut self, sz: Size, dst: GPR, src: GPR, tmp: NEON);
+    /// Sign-extends the low `sz_src` bits of `src` into a `sz_dst` register.
+    fn emit_sxt(&mut self, sz_src: Size, sz_dst: Size, dst: GPR, src: GPR);
+    fn emit_cset(&mut self, sz: Size, dst: GPR, cond: Condition);
+
+    fn emit_b_label(&mut self, label: DynamicLabel);
+    /// Conditional branch without the ±1MB range limit of `b.cond`.
+    fn emit_bcond_label(&mut self, cond: Condition, label: DynamicLabel);
+    /// Branches if `reg` is zero, without the ±1MB range limit of `cbz`.
+    fn emit_cbz_label(&mut self, sz: Size, reg: GPR, label: DynamicLabel);
+    /// Branches if `reg` is not zero, without the ±1MB range limit of `cbnz`.
+    fn emit_cbnz_label(&mut self, sz: Size, reg: GPR, label: DynamicLabel);
+    fn emit_adr_label(&mut self, dst: GPR, label: DynamicLabel);
+    fn emit_blr(&mut self, target: GPR);
+    fn emit_br(&mut self, target: GPR);
+    fn emit_ret(&mut self);
+
+    fn emit_fbinop(&mut self, op: FBinOp, sz: Size, dst: NEON, a: NEON, b: NEON);
+    fn emit_funop(&mut self, op: FUnOp, sz: Size, dst: NEON, src: NEON);
+    fn emit_fcmp(&mut self, sz: Size, a: NEON, b: NEON);
+    fn emit_fcsel(&mut self, sz: Size, dst: NEON, a: NEON, b: NEON, cond: Condition);
+    /// `fcvt` between single and double precision.
+    fn emit_fcvt(&mut self, sz_src: Size, sz_dst: Size, dst: NEON, src: NEON);
+    fn emit_fmov_to_gpr(&mut self, sz: Size, dst: GPR, src: NEON);
+    fn emit_fmov_from_gpr(&mut self, sz: Size, dst: NEON, src: GPR);
+    fn emit_scvtf(&mut self, sz_int: Size, sz_fp: Size, dst: NEON, src: GPR);
+    fn emit_ucvtf(&mut self, sz_int: Size, sz_fp: Size, dst: NEON, src: GPR);
+    /// Saturating float to signed integer conversion, NaN converts to 0.
+    fn emit_fcvtzs(&mut self, sz_fp: Size, sz_int: Size, dst: GPR, src: NEON);
+    /// Saturating float to unsigned integer conversion, NaN converts to 0.
+    fn emit_fcvtzu(&mut self, sz_fp: Size, sz_int: Size, dst: GPR, src: NEON);
+
+    fn emit_ldaxr(&mut self, sz: Size, dst: GPR, addr: GPR);
+    /// `stlxr status, src, [addr]`. `status` is 0 on success.
+    fn emit_stlxr(&mut self, sz: Size, status: GPR, src: GPR, addr: GPR);
+    fn emit_ldar(&mut self, sz: Size, dst: GPR, addr: GPR);
+    fn emit_stlr(&mut self, sz: Size, src: GPR, addr: GPR);
+    fn emit_dmb(&mut self);
+}
+
+/// Picks an encoding for `[base, #offset]` with an access of `sz` bytes,
+/// materializing the offset into the scratch register if no immediate form fits.
+fn addressing(a: &mut Assembler, sz: Size, offset: i32) -> Addressing {
+    let scale = sz.bytes();
+    if offset >= 0 && offset % scale == 0 && offset / scale < 4096 {
+        Addressing::Scaled(offset as u32)
+    } else if (-256..256).contains(&offset) {
+        Addressing::Unscaled(offset)
+    } else {
+        a.emit_mov_imm(Size::S64, SCRATCH, offset as i64 as u64);
+        Addressing::Register
+    }
+}
+
+impl EmitterARM64 for Assembler {
+    fn get_label(&mut self) -> DynamicLabel {
+        self.new_dynamic_label()
+    }
+
+    fn get_offset(&self) -> AssemblyOffset {
+        self.offset()
+    }
+
+    fn emit_label(&mut self, label: DynamicLabel) {
+        dynasm!(self ; =>label);
+    }
+
+    fn emit_mov_imm(&mut self, sz: Size, dst: GPR, imm: u64) {
+        let dst = dst.into_index();
+        match sz {
+            Size::S64 => {
+                dynasm!(self ; movz X(dst), (imm & 0xffff) as u32);
+                for shift in &[16u32, 32, 48] {
+                    let chunk = ((imm >> shift) & 0xffff) as u32;
+                    if chunk != 0 {
+                        dynasm!(self ; movk X(dst), chunk, lsl *shift);
+                    }
+                }
+            }
+            _ => {
+                let imm = imm as u32;
+                dynasm!(self ; movz W(dst), imm & 0xffff);
+                if imm >> 16 != 0 {
+                    dynasm!(self ; movk W(dst), imm >> 16, lsl 16);
+                }
+            }
+        }
+    }
+
+    fn emit_movz(&mut self, dst: GPR, imm: u16, shift: u32) {
+        dynasm!(self ; movz X(dst.into_index()), imm as u32, lsl shift);
+    }
+
+    fn emit_movk(&mut self, dst: GPR, imm: u16, shift: u32) {
+        dynasm!(self ; movk X(dst.into_index()), imm as u32, lsl shift);
+    }
+
+    fn emit_mov(&mut self, sz: Size, src: GPR, dst: GPR) {
+        match sz {
+            Size::S64 => dynasm!(self ; mov X(dst.into_index()), X(src.into_index())),
+            _ => dynasm!(self ; mov W(dst.into_index()), W(src.into_index())),
+        }
+    }
+
+    fn emit_mov_from_sp(&mut self, dst: GPR) {
+        dynasm!(self ; mov X(dst.into_index()), sp);
+    }
+
+    fn emit_mov_to_sp(&mut self, src: GPR) {
+        dynasm!(self ; mov sp, X(src.into_index()));
+    }
+
+    fn emit_sub_sp(&mut self, src: GPR) {
+        dynasm!(self ; sub sp, sp, X(src.into_index()), uxtx);
+    }
+
+    fn emit_ldr(&mut self, sz: Size, dst: GPR, base: GPR, offset: i32) {
+        let (d, b, s) = (dst.into_index(), base.into_index(), SCRATCH.into_index());
+        match (sz, addressing(self, sz, offset)) {
+            (Size::S8, Addressing::Scaled(o)) => dynasm!(self ; ldrb W(d), [XSP(b), o]),
+            (Size::S8, Addressing::Unscaled(o)) => dynasm!(self ; ldurb W(d), [XSP(b), o]),
+            (Size::S8, Addressing::Register) => dynasm!(self ; ldrb W(d), [XSP(b), X(s)]),
+            (Size::S16, Addressing::Scaled(o)) => dynasm!(self ; ldrh W(d), [XSP(b), o]),
+            (Size::S16, Addressing::Unscaled(o)) => dynasm!(self ; ldurh W(d), [XSP(b), o]),
+            (Size::S16, Addressing::Register) => dynasm!(self ; ldrh W(d), [XSP(b), X(s)]),
+            (Size::S32, Addressing::Scaled(o)) => dynasm!(self ; ldr W(d), [XSP(b), o]),
+            (Size::S32, Addressing::Unscaled(o)) => dynasm!(self ; ldur W(d), [XSP(b), o]),
+            (Size::S32, Addressing::Register) => dynasm!(self ; ldr W(d), [XSP(b), X(s)]),
+            (Size::S64, Addressing::Scaled(o)) => dynasm!(self ; ldr X(d), [XSP(b), o]),
+            (Size::S64, Addressing::Unscaled(o)) => dynasm!(self ; ldur X(d), [XSP(b), o]),
+            (Size::S64, Addressing::Register) => dynasm!(self ; ldr X(d), [XSP(b), X(s)]),
+        }
+    }
+
+    fn emit_ldrs(&mut self, sz_src: Size, sz_dst: Size, dst: GPR, base: GPR, offset: i32) {
+        let (d, b, s) = (dst.into_index(), base.into_index(), SCRATCH.into_index());
+        match (sz_src, sz_dst, addressing(self, sz_src, offset)) {
+            (Size::S8, Size::S32, Addressing::Scaled(o)) => {
+                dynasm!(self ; ldrsb W(d), [XSP(b), o])
+            }
+            (Size::S8, Size::S32, Addressing::Unscaled(o)) => {
+                dynasm!(self ; ldursb W(d), [XSP(b), o])
+            }
+            (Size::S8, Size::S32, Addressing::Register) => {
+                dynasm!(self ; ldrsb W(d), [XSP(b), X(s)])
+            }
+            (Size::S8, Size::S64, Addressing::Scaled(o)) => {
+                dynasm!(self ; ldrsb X(d), [XSP(b), o])
+            }
+            (Size::S8, Size::S64, Addressing::Unscaled(o)) => {
+                dynasm!(self ; ldursb X(d), [XSP(b), o])
+            }
+            (Size::S8, Size::S64, Addressing::Register) => {
+                dynasm!(self ; ldrsb X(d), [XSP(b), X(s)])
+            }
+            (Size::S16, Size::S32, Addressing::Scaled(o)) => {
+                dynasm!(self ; ldrsh W(d), [XSP(b), o])
+            }
+            (Size::S16, Size::S32, Addressing::Unscaled(o)) => {
+                dynasm!(self ; ldursh W(d), [XSP(b), o])
+            }
+            (Size::S16, Size::S32, Addressing::Register) => {
+                dynasm!(self ; ldrsh W(d), [XSP(b), X(s)])
+            }
+            (Size::S16, Size::S64, Addressing::Scaled(o)) => {
+                dynasm!(self ; ldrsh X(d), [XSP(b), o])
+            }
+            (Size::S16, Size::S64, Addressing::Unscaled(o)) => {
+                dynasm!(self ; ldursh X(d), [XSP(b), o])
+            }
+            (Size::S16, Size::S64, Addressing::Register) => {
+                dynasm!(self ; ldrsh X(d), [XSP(b), X(s)])
+            }
+            (Size::S32, Size::S64, Addressing::Scaled(o)) => {
+                dynasm!(self ; ldrsw X(d), [XSP(b), o])
+            }
+            (Size::S32, Size::S64, Addressing::Unscaled(o)) => {
+                dynasm!(self ; ldursw X(d), [XSP(b), o])
+            }
+            (Size::S32, Size::S64, Addressing::Register) => {
+                dynasm!(self ; ldrsw X(d), [XSP(b), X(s)])
+            }
+            _ => unreachable!("emit_ldrs: {:?} -> {:?}", sz_src, sz_dst),
+        }
+    }
+
+    fn emit_str(&mut self, sz: Size, src: GPR, base: GPR, offset: i32) {
+        let (r, b, s) = (src.into_index(), base.into_index(), SCRATCH.into_index());
+        match (sz, addressing(self, sz, offset)) {
+            (Size::S8, Addressing::Scaled(o)) => dynasm!(self ; strb W(r), [XSP(b), o]),
+            (Size::S8, Addressing::Unscaled(o)) => dynasm!(self ; sturb W(r), [XSP(b), o]),
+            (Size::S8, Addressing::Register) => dynasm!(self ; strb W(r), [XSP(b), X(s)]),
+            (Size::S16, Addressing::Scaled(o)) => dynasm!(self ; strh W(r), [XSP(b), o]),
+            (Size::S16, Addressing::Unscaled(o)) => dynasm!(self ; sturh W(r), [XSP(b), o]),
+            (Size::S16, Addressing::Register) => dynasm!(self ; strh W(r), [XSP(b), X(s)]),
+            (Size::S32, Addressing::Scaled(o)) => dynasm!(self ; str W(r), [XSP(b), o]),
+            (Size::S32, Addressing::Unscaled(o)) => dynasm!(self ; stur W(r), [XSP(b), o]),
+            (Size::S32, Addressing::Register) => dynasm!(self ; str W(r), [XSP(b), X(s)]),
+            (Size::S64, Addressing::Scaled(o)) => dynasm!(self ; str X(r), [XSP(b), o]),
+            (Size::S64, Addressing::Unscaled(o)) => dynasm!(self ; stur X(r), [XSP(b), o]),
+            (Size::S64, Addressing::Register) => dynasm!(self ; str X(r), [XSP(b), X(s)]),
+        }
+    }
+
+    fn emit_ldr_neon(&mut self, sz: Size, dst: NEON, base: GPR, offset: i32) {
+        let (d, b, s) = (dst.into_index(), base.into_index(), SCRATCH.into_index());
+        match (sz, addressing(self, sz, offset)) {
+            (Size::S32, Addressing::Scaled(o)) => dynasm!(self ; ldr S(d), [XSP(b), o]),
+            (Size::S32, Addressing::Unscaled(o)) => dynasm!(self ; ldur S(d), [XSP(b), o]),
+            (Size::S32, Addressing::Register) => dynasm!(self ; ldr S(d), [XSP(b), X(s)]),
+            (Size::S64, Addressing::Scaled(o)) => dynasm!(self ; ldr D(d), [XSP(b), o]),
+            (Size::S64, Addressing::Unscaled(o)) => dynasm!(self ; ldur D(d), [XSP(b), o]),
+            (Size::S64, Addressing::Register) => dynasm!(self ; ldr D(d), [XSP(b), X(s)]),
+            _ => unreachable!("emit_ldr_neon: {:?}", sz),
+        }
+    }
+
+    fn emit_str_neon(&mut self, sz: Size, src: NEON, base: GPR, offset: i32) {
+        let (r, b, s) = (src.into_index(), base.into_index(), SCRATCH.into_index());
+        match (sz, addressing(self, sz, offset)) {
+            (Size::S32, Addressing::Scaled(o)) => dynasm!(self ; str S(r), [XSP(b), o]),
+            (Size::S32, Addressing::Unscaled(o)) => dynasm!(self ; stur S(r), [XSP(b), o]),
+            (Size::S32, Addressing::Register) => dynasm!(self ; str S(r), [XSP(b), X(s)]),
+            (Size::S64, Addressing::Scaled(o)) => dynasm!(self ; str D(r), [XSP(b), o]),
+            (Size::S64, Addressing::Unscaled(o)) => dynasm!(self ; stur D(r), [XSP(b), o]),
+            (Size::S64, Addressing::Register) => dynasm!(self ; str D(r), [XSP(b), X(s)]),
+            _ => unreachable!("emit_str_neon: {:?}", sz),
+        }
+    }
+
+    fn emit_push_pair(&mut self, a: GPR, b: GPR) {
+        dynasm!(self ; stp X(a.into_index()), X(b.into_index()), [sp, -16]!);
+    }
+
+    fn emit_pop_pair(&mut self, a: GPR, b: GPR) {
+        dynasm!(self ; ldp X(a.into_index()), X(b.into_index()), [sp], 16);
+    }
+
+    fn emit_ldp(&mut self, a: GPR, b: GPR, base: GPR, offset: i32) {
+        assert!(offset % 8 == 0 && (-512..512).contains(&offset));
+        dynasm!(self ; ldp X(a.into_index()), X(b.into_index()), [XSP(base.into_index()), offset]);
+    }
+
+    fn emit_binop(&mut self, op: BinOp, sz: Size, dst: GPR, a: GPR, b: GPR) {
+        let (d, a, b) = (dst.into_index(), a.into_index(), b.into_index());
+        match sz {
+            Size::S64 => match op {
+                BinOp::Add => dynasm!(self ; add X(d), X(a), X(b)),
+                BinOp::Sub => dynasm!(self ; sub X(d), X(a), X(b)),
+                BinOp::Adds => dynasm!(self ; adds X(d), X(a), X(b)),
+                BinOp::Subs => dynasm!(self ; subs X(d), X(a), X(b)),
+                BinOp::Mul => dynasm!(self ; mul X(d), X(a), X(b)),
+                BinOp::Sdiv => dynasm!(self ; sdiv X(d), X(a), X(b)),
+                BinOp::Udiv => dynasm!(self ; udiv X(d), X(a), X(b)),
+                BinOp::And => dynasm!(self ; and X(d), X(a), X(b)),
+                BinOp::Orr => dynasm!(self ; orr X(d), X(a), X(b)),
+                BinOp::Eor => dynasm!(self ; eor X(d), X(a), X(b)),
+                BinOp::Lsl => dynasm!(self ; lsl X(d), X(a), X(b)),
+                BinOp::Lsr => dynasm!(self ; lsr X(d), X(a), X(b)),
+                BinOp::Asr => dynasm!(self ; asr X(d), X(a), X(b)),
+                BinOp::Ror => dynasm!(self ; ror X(d), X(a), X(b)),
+            },
+            _ => match op {
+                BinOp::Add => dynasm!(self ; add W(d), W(a), W(b)),
+                BinOp::Sub => dynasm!(self ; sub W(d), W(a), W(b)),
+                BinOp::Adds => dynasm!(self ; adds W(d), W(a), W(b)),
+                BinOp::Subs => dynasm!(self ; subs W(d), W(a), W(b)),
+                BinOp::Mul => dynasm!(self ; mul W(d), W(a), W(b)),
+                BinOp::Sdiv => dynasm!(self ; sdiv W(d), W(a), W(b)),
+                BinOp::Udiv => dynasm!(self ; udiv W(d), W(a), W(b)),
+                BinOp::And => dynasm!(self ; and W(d), W(a), W(b)),
+                BinOp::Orr => dynasm!(self ; orr W(d), W(a), W(b)),
+                BinOp::Eor => dynasm!(self ; eor W(d), W(a), W(b)),
+                BinOp::Lsl => dynasm!(self ; lsl W(d), W(a), W(b)),
+                BinOp::Lsr => dynasm!(self ; lsr W(d), W(a), W(b)),
+                BinOp::Asr => dynasm!(self ; asr W(d), W(a), W(b)),
+                BinOp::Ror => dynasm!(self ; ror W(d), W(a), W(b)),
+            },
+        }
+    }
+
+    fn emit_add_imm(&mut self, dst: GPR, a: GPR, imm: u32) {
+        let (d, a) = (dst.into_index(), a.into_index());
+        if imm < 4096 {
+            dynasm!(self ; add XSP(d), XSP(a), imm);
+        } else {
+            self.emit_mov_imm(Size::S64, SCRATCH, imm as u64);
+            dynasm!(self ; add XSP(d), XSP(a), X(SCRATCH.into_index()), uxtx);
+        }
+    }
+
+    fn emit_sub_imm(&mut self, dst: GPR, a: GPR, imm: u32) {
+        let (d, a) = (dst.into_index(), a.into_index());
+        if imm < 4096 {
+            dynasm!(self ; sub XSP(d), XSP(a), imm);
+        } else {
+            self.emit_mov_imm(Size::S64, SCRATCH, imm as u64);
+            dynasm!(self ; sub XSP(d), XSP(a), X(SCRATCH.into_index()), uxtx);
+        }
+    }
+
+    fn emit_add_lsl(&mut self, dst: GPR, a: GPR, b: GPR, shift: u32) {
+        dynasm!(self ; add X(dst.into_index()), X(a.into_index()), X(b.into_index()), lsl shift);
+    }
+
+    fn emit_msub(&mut self, sz: Size, dst: GPR, a: GPR, b: GPR, c: GPR) {
+        let (d, a, b, c) = (
+            dst.into_index(),
+            a.into_index(),
+            b.into_index(),
+            c.into_index(),
+        );
+        match sz {
+            Size::S64 => dynasm!(self ; msub X(d), X(a), X(b), X(c)),
+            _ => dynasm!(self ; msub W(d), W(a), W(b), W(c)),
+        }
+    }
+
+    fn emit_cmp(&mut self, sz: Size, a: GPR, b: GPR) {
+        match sz {
+            Size::S64 => dynasm!(self ; cmp X(a.into_index()), X(b.into_index())),
+            _ => dynasm!(self ; cmp W(a.into_index()), W(b.into_index())),
+        }
+    }
+
+    fn emit_cmp_imm(&mut self, sz: Size, a: GPR, imm: u64) {
+        let a = a.into_index();
+        if imm < 4096 {
+            let imm = imm as u32;
+            match sz {
+                Size::S64 => dynasm!(self ; cmp X(a), imm),
+                _ => dynasm!(self ; cmp W(a), imm),
+            }
+        } else {
+            self.emit_mov_imm(sz, SCRATCH, imm);
+            let s = SCRATCH.into_index();
+            match sz {
+                Size::S64 => dynasm!(self ; cmp X(a), X(s)),
+                _ => dynasm!(self ; cmp W(a), W(s)),
+            }
+        }
+    }
+
+    fn emit_tst(&mut self, sz: Size, a: GPR, b: GPR) {
+        match sz {
+            Size::S64 => dynasm!(self ; tst X(a.into_index()), X(b.into_index())),
+            _ => dynasm!(self ; tst W(a.into_index()), W(b.into_index())),
+        }
+    }
+
+    fn emit_neg(&mut self, sz: Size, dst: GPR, src: GPR) {
+        match sz {
+            Size::S64 => dynasm!(self ; neg X(dst.into_index()), X(src.into_index())),
+            _ => dynasm!(self ; neg W(dst.into_index()), W(src.into_index())),
+        }
+    }
+
+    fn emit_clz(&mut self, sz: Size, dst: GPR, src: GPR) {
+        match sz {
+            Size::S64 => dynasm!(self ; clz X(dst.into_index()), X(src.into_index())),
+            _ => dynasm!(self ; clz W(dst.into_index()), W(src.into_index())),
+        }
+    }
+
+    fn emit_rbit(&mut self, sz: Size, dst: GPR, src: GPR) {
+        match sz {
+            Size::S64 => dynasm!(self ; rbit X(dst.into_index()), X(src.into_index())),
+            _ => dynasm!(self ; rbit W(dst.into_index()), W(src.into_index())),
+        }
+    }
+
+    fn emit_popcnt(&mut self, sz: Size, dst: GPR, src: GPR, tmp: NEON) {
+        let (d, s, t) = (dst.into_index(), src.into_index(), tmp.into_index());
+        match sz {
+            Size::S64 => dynasm!(self ; fmov D(t), X(s)),
+            _ => dynasm!(self ; fmov S(t), W(s)),
+        }
+        dynasm!(self
+            ; cnt V(t).B8, V(t).B8
+            ; addv B(t), V(t).B8
+            ; umov W(d), V(t).B[0]
+        );
+    }
+
+    fn emit_sxt(&mut self, sz_src: Size, sz_dst: Size, dst: GPR, src: GPR) {
+        let (d, s) = (dst.into_index(), src.into_index());
+        match (sz_src, sz_dst) {
+            (Size::S8, Size::S32) => dynasm!(self ; sxtb W(d), W(s)),
+            (Size::S8, Size::S64) => dynasm!(self ; sxtb X(d), W(s)),
+            (Size::S16, Size::S32) => dynasm!(self ; sxth W(d), W(s)),
+            (Size::S16, Size::S64) => dynasm!(self ; sxth X(d), W(s)),
+            (Size::S32, Size::S64) => dynasm!(self ; sxtw X(d), W(s)),
+            _ => unreachable!("emit_sxt: {:?} -> {:?}", sz_src, sz_dst),
+        }
+    }
+
+    fn emit_cset(&mut self, sz: Size, dst: GPR, cond: Condition) {
+        let d = dst.into_index();
+        match sz {
+            Size::S64 => dynasm_cond!(self, cond, [cset X(d),], []),
+            _ => dynasm_cond!(self, cond, [cset W(d),], []),
+        }
+    }
+
+    fn emit_b_label(&mut self, label: DynamicLabel) {
+        dynasm!(self ; b =>label);
+    }
+
+    fn emit_bcond_label(&mut self, cond: Condition, label: DynamicLabel) {
+        let skip = self.get_label();
+        dynasm_cond!(self, cond.invert(), [b.], [=>skip]);
+        dynasm!(self ; b =>label ; =>skip);
+    }
+
+    fn emit_cbz_label(&mut self, sz: Size, reg: GPR, label: DynamicLabel) {
+        let skip = self.get_label();
+        match sz {
+            Size::S64 => dynasm!(self ; cbnz X(reg.into_index()), =>skip),
+            _ => dynasm!(self ; cbnz W(reg.into_index()), =>skip),
+        }
+        dynasm!(self ; b =>label ; =>skip);
+    }
+
+    fn emit_cbnz_label(&mut self, sz: Size, reg: GPR, label: DynamicLabel) {
+        let skip = self.get_label();
+        match sz {
+            Size::S64 => dynasm!(self ; cbz X(reg.into_index()), =>skip),
+            _ => dynasm!(self ; cbz W(reg.into_index()), =>skip),
+        }
+        dynasm!(self ; b =>label ; =>skip);
+    }
+
+    fn emit_adr_label(&mut self, dst: GPR, label: DynamicLabel) {
+        dynasm!(self ; adr X(dst.into_index()), =>label);
+    }
+
+    fn emit_blr(&mut self, target: GPR) {
+        dynasm!(self ; blr X(target.into_index()));
+    }
+
+    fn emit_br(&mut self, target: GPR) {
+        dynasm!(self ; br X(target.into_index()));
+    }
+
+    fn emit_ret(&mut self) {
+        dynasm!(self ; ret);
+    }
+
+    fn emit_fbinop(&mut self, op: FBinOp, sz: Size, dst: NEON, a: NEON, b: NEON) {
+        let (d, a, b) = (dst.into_index(), a.into_index(), b.into_index());
+        match sz {
+            Size::S64 => match op {
+                FBinOp::Add => dynasm!(self ; fadd D(d), D(a), D(b)),
+                FBinOp::Sub => dynasm!(self ; fsub D(d), D(a), D(b)),
+                FBinOp::Mul => dynasm!(self ; fmul D(d), D(a), D(b)),
+                FBinOp::Div => dynasm!(self ; fdiv D(d), D(a), D(b)),
+                FBinOp::Min => dynasm!(self ; fmin D(d), D(a), D(b)),
+                FBinOp::Max => dynasm!(self ; fmax D(d), D(a), D(b)),
+            },
+            _ => match op {
+                FBinOp::Add => dynasm!(self ; fadd S(d), S(a), S(b)),
+                FBinOp::Sub => dynasm!(self ; fsub S(d), S(a), S(b)),
+                FBinOp::Mul => dynasm!(self ; fmul S(d), S(a), S(b)),
+                FBinOp::Div => dynasm!(self ; fdiv S(d), S(a), S(b)),
+                FBinOp::Min => dynasm!(self ; fmin S(d), S(a), S(b)),
+                FBinOp::Max => dynasm!(self ; fmax S(d), S(a), S(b)),
+            },
+        }
+    }
+
+    fn emit_funop(&mut self, op: FUnOp, sz: Size, dst: NEON, src: NEON) {
+        let (d, s) = (dst.into_index(), src.into_index());
+        match sz {
+            Size::S64 => match op {
+                FUnOp::Sqrt => dynasm!(self ; fsqrt D(d), D(s)),
+                FUnOp::Nearest => dynasm!(self ; frintn D(d), D(s)),
+                FUnOp::Floor => dynasm!(self ; frintm D(d), D(s)),
+                FUnOp::Ceil => dynasm!(self ; frintp D(d), D(s)),
+                FUnOp::Trunc => dynasm!(self ; frintz D(d), D(s)),
+            },
+            _ => match op {
+                FUnOp::Sqrt => dynasm!(self ; fsqrt S(d), S(s)),
+                FUnOp::Nearest => dynasm!(self ; frintn S(d), S(s)),
+                FUnOp::Floor => dynasm!(self ; frintm S(d), S(s)),
+                FUnOp::Ceil => dynasm!(self ; frintp S(d), S(s)),
+                FUnOp::Trunc => dynasm!(self ; frintz S(d), S(s)),
+            },
+        }
+    }
+
+    fn emit_fcmp(&mut self, sz: Size, a: NEON, b: NEON) {
+        match sz {
+            Size::S64 => dynasm!(self ; fcmp D(a.into_index()), D(b.into_index())),
+            _ => dynasm!(self ; fcmp S(a.into_index()), S(b.into_index())),
+        }
+    }
+
+    fn emit_fcsel(&mut self, sz: Size, dst: NEON, a: NEON, b: NEON, cond: Condition) {
+        let (d, a, b) = (dst.into_index(), a.into_index(), b.into_index());
+        match sz {
+            Size::S64 => dynasm_cond!(self, cond, [fcsel D(d), D(a), D(b),], []),
+            _ => dynasm_cond!(self, cond, [fcsel S(d), S(a), S(b),], []),
+        }
+    }
+
+    fn emit_fcvt(&mut self, sz_src: Size, sz_dst: Size, dst: NEON, src: NEON) {
+        let (d, s) = (dst.into_index(), src.into_index());
+        match (sz_src, sz_dst) {
+            (Size::S32, Size::S64) => dynasm!(self ; fcvt D(d), S(s)),
+            (Size::S64, Size::S32) => dynasm!(self ; fcvt S(d), D(s)),
+            _ => unreachable!("emit_fcvt: {:?} -> {:?}", sz_src, sz_dst),
+        }
+    }
+
+    fn emit_fmov_to_gpr(&mut self, sz: Size, dst: GPR, src: NEON) {
+        match sz {
+            Size::S64 => dynasm!(self ; fmov X(dst.into_index()), D(src.into_index())),
+            _ => dynasm!(self ; fmov W(dst.into_index()), S(src.into_index())),
+        }
+    }
+
+    fn emit_fmov_from_gpr(&mut self, sz: Size, dst: NEON, src: GPR) {
+        match sz {
+            Size::S64 => dynasm!(self ; fmov D(dst.into_index()), X(src.into_index())),
+            _ => dynasm!(self ; fmov S(dst.into_index()), W(src.into_index())),
+        }
+    }
+
+    fn emit_scvtf(&mut self, sz_int: Size, sz_fp: Size, dst: NEON, src: GPR) {
+        let (d, s) = (dst.into_index(), src.into_index());
+        match (sz_int, sz_fp) {
+            (Size::S32, Size::S32) => dynasm!(self ; scvtf S(d), W(s)),
+            (Size::S64, Size::S32) => dynasm!(self ; scvtf S(d), X(s)),
+            (Size::S32, Size::S64) => dynasm!(self ; scvtf D(d), W(s)),
+            (Size::S64, Size::S64) => dynasm!(self ; scvtf D(d), X(s)),
+            _ => unreachable!("emit_scvtf: {:?} -> {:?}", sz_int, sz_fp),
+        }
+    }
+
+    fn emit_ucvtf(&mut self, sz_int: Size, sz_fp: Size, dst: NEON, src: GPR) {
+        let (d, s) = (dst.into_index(), src.into_index());
+        match (sz_int, sz_fp) {
+            (Size::S32, Size::S32) => dynasm!(self ; ucvtf S(d), W(s)),
+            (Size::S64, Size::S32) => dynasm!(self ; ucvtf S(d), X(s)),
+            (Size::S32, Size::S64) => dynasm!(self ; ucvtf D(d), W(s)),
+            (Size::S64, Size::S64) => dynasm!(self ; ucvtf D(d), X(s)),
+            _ => unreachable!("emit_ucvtf: {:?} -> {:?}", sz_int, sz_fp),
+        }
+    }
+
+    fn emit_fcvtzs(&mut self, sz_fp: Size, sz_int: Size, dst: GPR, src: NEON) {
+        let (d, s) = (dst.into_index(), src.into_index());
+        match (sz_fp, sz_int) {
+            (Size::S32, Size::S32) => dynasm!(self ; fcvtzs W(d), S(s)),
+            (Size::S32, Size::S64) => dynasm!(self ; fcvtzs X(d), S(s)),
+            (Size::S64, Size::S32) => dynasm!(self ; fcvtzs W(d), D(s)),
+            (Size::S64, Size::S64) => dynasm!(self ; fcvtzs X(d), D(s)),
+            _ => unreachable!("emit_fcvtzs: {:?} -> {:?}", sz_fp, sz_int),
+        }
+    }
+
+    fn emit_fcvtzu(&mut self, sz_fp: Size, sz_int: Size, dst: GPR, src: NEON) {
+        let (d, s) = (dst.into_index(), src.into_index());
+        match (sz_fp, sz_int) {
+            (Size::S32, Size::S32) => dynasm!(self ; fcvtzu W(d), S(s)),
+            (Size::S32, Size::S64) => dynasm!(self ; fcvtzu X(d), S(s)),
+            (Size::S64, Size::S32) => dynasm!(self ; fcvtzu W(d), D(s)),
+            (Size::S64, Size::S64) => dynasm!(self ; fcvtzu X(d), D(s)),
+            _ => unreachable!("emit_fcvtzu: {:?} -> {:?}", sz_fp, sz_int),
+        }
+    }
+
+    fn emit_ldaxr(&mut self, sz: Size, dst: GPR, addr: GPR) {
+        let (d, a) = (dst.into_index(), addr.into_index());
+        match sz {
+            Size::S8 => dynasm!(self ; ldaxrb W(d), [X(a)]),
+            Size::S16 => dynasm!(self ; ldaxrh W(d), [X(a)]),
+            Size::S32 => dynasm!(self ; ldaxr W(d), [X(a)]),
+            Size::S64 => dynasm!(self ; ldaxr X(d), [X(a)]),
+        }
+    }
+
+    fn emit_stlxr(&mut self, sz: Size, status: GPR, src: GPR, addr: GPR) {
+        let (st, s, a) = (status.into_index(), src.into_index(), addr.into_index());
+        match sz {
+            Size::S8 => dynasm!(self ; stlxrb W(st), W(s), [X(a)]),
+            Size::S16 => dynasm!(self ; stlxrh W(st), W(s), [X(a)]),
+            Size::S32 => dynasm!(self ; stlxr W(st), W(s), [X(a)]),
+            Size::S64 => dynasm!(self ; stlxr W(st), X(s), [X(a)]),
+        }
+    }
+
+    fn emit_ldar(&mut self, sz: Size, dst: GPR, addr: GPR) {
+        let (d, a) = (dst.into_index(), addr.into_index());
+        match sz {
+            Size::S8 => dynasm!(self ; ldarb W(d), [X(a)]),
+            Size::S16 => dynasm!(self ; ldarh W(d), [X(a)]),
+            Size::S32 => dynasm!(self ; ldar W(d), [X(a)]),
+            Size::S64 => dynasm!(self ; ldar X(d), [X(a)]),
+        }
+    }
+
+    fn emit_stlr(&mut self, sz: Size, src: GPR, addr: GPR) {
+        let (s, a) = (src.into_index(), addr.into_index());
+        match sz {
+            Size::S8 => dynasm!(self ; stlrb W(s), [X(a)]),
+            Size::S16 => dynasm!(self ; stlrh W(s), [X(a)]),
+            Size::S32 => dynasm!(self ; stlr W(s), [X(a)]),
+            Size::S64 => dynasm!(self ; stlr X(s), [X(a)]),
+        }
+    }
+
+    fn emit_dmb(&mut self) {
+        dynasm!(self ; dmb ish);
+    }
+}
+
+/// Rewrites a `movz dst, #0; movk dst, #0, lsl 16` placeholder emitted at `at`
+/// so that it loads `imm`.
+pub(crate) fn patch_mov_imm32(a: &mut Assembler, at: AssemblyOffset, dst: GPR, imm: u32) {
+    let mut m = a.alter();
+    m.goto(at);
+    dynasm!(m
+        ; movz X(dst.into_index()), imm & 0xffff
+        ; movk X(dst.into_index()), imm >> 16, lsl 16
+    );
+    m.check_exact(AssemblyOffset(at.0 + 8)).unwrap();
+}
@@ -9,9 +9,12 @@
 //! runtime performance.
 
 mod address_map;
+mod arm64_decl;
+mod codegen_arm64;
 mod codegen_x64;
 mod compiler;
 mod config;
+mod emitter_arm64;
 mod emitter_x64;
 mod machine;
 mod x64_decl;
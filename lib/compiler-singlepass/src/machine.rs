@@ -300,11 +300,19 @@ impl Machine {
         }
     }
 
+    /// Allocates `n` locals, the first `n_params` of which are the
+    /// parameters, followed by a scratch area of `return_area_size` bytes
+    /// that isn't initialized.
+    ///
+    /// If the function returns its values in memory, the pointer to it is the
+    /// first parameter and is stored into the local at `return_pointer`.
     pub(crate) fn init_locals<E: Emitter>(
         &mut self,
         a: &mut E,
         n: usize,
         n_params: usize,
+        return_pointer: Option<usize>,
+        return_area_size: usize,
         calling_convention: CallingConvention,
     ) -> Vec<Location> {
         // Determine whether a local should be allocated on the stack.
@@ -357,7 +365,7 @@ impl Machine {
             .collect();
 
         // Add size of locals on stack.
-        static_area_size += num_mem_slots * 8;
+        static_area_size += num_mem_slots * 8 + return_area_size;
 
         // Allocate save area, without actually writing to it.
         a.emit_sub(
@@ -409,8 +417,10 @@ impl Machine {
         // Load in-register parameters into the allocated locations.
         // Locals are allocated on the stack from higher address to lower address,
         // so we won't skip the stack guard page here.
+        // The return pointer, if any, comes before vmctx.
+        let vmctx_param = if return_pointer.is_some() { 1 } else { 0 };
         for i in 0..n_params {
-            let loc = Self::get_param_location(i + 1, calling_convention);
+            let loc = Self::get_param_location(vmctx_param + 1 + i, calling_convention);
             match loc {
                 Location::GPR(_) => {
                     a.emit_mov(Size::S64, loc, locations[i]);
@@ -432,10 +442,19 @@ impl Machine {
         // Load vmctx into R15.
         a.emit_mov(
             Size::S64,
-            Self::get_param_location(0, calling_convention),
+            Self::get_param_location(vmctx_param, calling_convention),
             Location::GPR(GPR::R15),
         );
 
+        // Keep the return pointer in RDX, which zeroing the locals preserves.
+        if return_pointer.is_some() {
+            a.emit_mov(
+                Size::S64,
+                Self::get_param_location(0, calling_convention),
+                Location::GPR(GPR::RDX),
+            );
+        }
+
         // Stack probe.
         //
         // `rep stosq` writes data from low address to high address and may skip the stack guard page.
@@ -470,6 +489,9 @@ impl Machine {
             a.emit_lea(Size::S64, last_stack_loc, Location::GPR(GPR::RDI));
             a.emit_rep_stosq();
         }
+        if let Some(index) = return_pointer {
+            a.emit_mov(Size::S64, Location::GPR(GPR::RDX), locations[index]);
+        }
 
         // Add the size of all locals allocated to stack.
         self.stack_offset.0 += static_area_size - callee_saved_regs_size;
@@ -236,3 +236,84 @@ impl ArgumentRegisterAllocator {
         }
     }
 }
+
+/// How a function returning multiple values returns them, following the C
+/// ABI for a `repr(C)` struct of the values, as host functions do.
+#[derive(Clone, Debug)]
+pub(crate) struct ReturnLayout {
+    /// Offset and size in bytes of each value in the struct.
+    pub(crate) fields: Vec<(usize, usize)>,
+    /// Size in bytes of the struct.
+    pub(crate) size: usize,
+    /// Registers each eightbyte of the struct is returned in, or `None` if
+    /// it's returned in memory the caller passes a pointer to as the first
+    /// parameter.
+    pub(crate) registers: Option<Vec<X64Register>>,
+}
+
+impl ReturnLayout {
+    /// Returns the layout of the values of type `tys`, or `None` if there
+    /// are less than two of them.
+    pub(crate) fn new(tys: &[Type], calling_convention: CallingConvention) -> Option<Self> {
+        if tys.len() < 2 {
+            return None;
+        }
+        let mut fields = Vec::with_capacity(tys.len());
+        let mut size = 0;
+        let mut align = 1;
+        for ty in tys {
+            let field_size = match ty {
+                Type::I32 | Type::F32 => 4,
+                Type::I64 | Type::F64 | Type::ExternRef | Type::FuncRef => 8,
+                Type::V128 => 16,
+            };
+            size = align_to(size, field_size);
+            fields.push((size, field_size));
+            size += field_size;
+            align = std::cmp::max(align, field_size);
+        }
+        let size = align_to(size, align);
+
+        let registers = match calling_convention {
+            CallingConvention::WindowsFastcall => {
+                if size == 8 {
+                    Some(vec![X64Register::GPR(GPR::RAX)])
+                } else {
+                    None
+                }
+            }
+            _ => {
+                if size <= 16 && tys.iter().all(|&ty| ty != Type::V128) {
+                    let mut gprs = [GPR::RAX, GPR::RDX].iter();
+                    let mut xmms = [XMM::XMM0, XMM::XMM1].iter();
+                    let registers = (0..align_to(size, 8) / 8)
+                        .map(|eightbyte| {
+                            let sse = tys.iter().zip(fields.iter()).all(|(ty, (offset, _))| {
+                                offset / 8 != eightbyte || *ty == Type::F32 || *ty == Type::F64
+                            });
+                            if sse {
+                                X64Register::XMM(*xmms.next().unwrap())
+                            } else {
+                                X64Register::GPR(*gprs.next().unwrap())
+                            }
+                        })
+                        .collect();
+                    Some(registers)
+                } else {
+                    None
+                }
+            }
+        };
+
+        Some(Self {
+            fields,
+            size,
+            registers,
+        })
+    }
+}
+
+/// Rounds `size` up to a multiple of `align`, which must be a power of two.
+pub(crate) fn align_to(size: usize, align: usize) -> usize {
+    (size + align - 1) & !(align - 1)
+}
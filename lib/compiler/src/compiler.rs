@@ -2,10 +2,13 @@
 //! compilers will need to implement.
 
 use crate::error::CompileError;
-use crate::function::Compilation;
+use crate::function::{Compilation, FunctionCompilation};
 use crate::lib::std::boxed::Box;
+use crate::lib::std::string::ToString;
+use crate::lib::std::sync::Arc;
 use crate::module::CompileModuleInfo;
 use crate::target::Target;
+use crate::translator::ModuleMiddleware;
 use crate::FunctionBodyData;
 use crate::ModuleTranslationState;
 use crate::SectionIndex;
@@ -53,9 +56,29 @@ pub trait CompilerConfig {
         // in case they create an IR that they can verify.
     }
 
+    /// Enable epoch interruption.
+    ///
+    /// When enabled, the compiled code checks the epoch of the engine
+    /// against the deadline of its instance on entry to each function and
+    /// at each loop header, and traps once the deadline is reached.
+    fn epoch_interruption(&mut self, _enable: bool) {
+        // By default we do nothing, each backend will need to customize this
+        // in case they support epoch interruption.
+    }
+
     /// Gets the custom compiler config
     fn compiler(self: Box<Self>) -> Box<dyn Compiler>;
 
+    /// Pushes a middleware onto the back of the middleware chain.
+    ///
+    /// Middlewares are applied in the order they are pushed: every operator of every function
+    /// is fed to the first middleware, whose output is fed to the next one and so on. The
+    /// compiler generates code for the operators emitted by the last middleware.
+    fn push_middleware(&mut self, _middleware: Arc<dyn ModuleMiddleware>) {
+        // By default we do nothing, each backend will need to customize this
+        // in case they support middlewares.
+    }
+
     /// Gets the default features for this compiler in the given target
     fn default_features_for_target(&self, _target: &Target) -> Features {
         Features::default()
@@ -71,10 +94,53 @@ where
     }
 }
 
+/// The `wasmparser` features to validate modules with when `features` are
+/// enabled.
+pub fn wasmparser_features(features: &Features) -> WasmFeatures {
+    WasmFeatures {
+        bulk_memory: features.bulk_memory,
+        threads: features.threads,
+        reference_types: features.reference_types,
+        multi_value: features.multi_value,
+        simd: features.simd,
+        tail_call: features.tail_call,
+        module_linking: features.module_linking,
+        multi_memory: features.multi_memory,
+        memory64: features.memory64,
+        exceptions: features.exceptions,
+        deterministic_only: false,
+    }
+}
+
 /// An implementation of a Compiler from parsed WebAssembly module to Compiled native code.
-pub trait Compiler: Send {
+pub trait Compiler: Send + Sync {
+    /// The name of the compiler, recorded in the modules it compiles.
+    ///
+    /// Defaults to the name of the type implementing the compiler.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    /// The version of the compiler, recorded in the modules it compiles.
+    ///
+    /// Defaults to the version of this crate.
+    fn version(&self) -> &str {
+        crate::VERSION
+    }
+
+    /// Whether the code compiled by this compiler checks the epoch of the
+    /// engine, see [`CompilerConfig::epoch_interruption`].
+    fn epoch_interruption(&self) -> bool {
+        // By default we do nothing, each backend will need to customize this
+        // in case they support epoch interruption.
+        false
+    }
+
     /// Validates a module.
     ///
+    /// The module is validated once its memory accesses are reordered with
+    /// [`reorder_memargs`](crate::reorder_memargs), like it is compiled.
+    ///
     /// It returns the a succesful Result in case is valid, `CompileError` in case is not.
     fn validate_module<'data>(
         &self,
@@ -82,26 +148,16 @@ pub trait Compiler: Send {
         data: &'data [u8],
     ) -> Result<(), CompileError> {
         let mut validator = Validator::new();
-        let wasm_features = WasmFeatures {
-            bulk_memory: features.bulk_memory,
-            threads: features.threads,
-            reference_types: features.reference_types,
-            multi_value: features.multi_value,
-            simd: features.simd,
-            tail_call: features.tail_call,
-            module_linking: features.module_linking,
-            multi_memory: features.multi_memory,
-            memory64: features.memory64,
-            exceptions: features.exceptions,
-            deterministic_only: false,
-        };
-        validator.wasm_features(wasm_features);
+        validator.wasm_features(wasmparser_features(features));
         validator
-            .validate_all(data)
+            .validate_all(&crate::reorder_memargs(data))
             .map_err(|e| CompileError::Validate(format!("{}", e)))?;
         Ok(())
     }
 
+    /// Get the middlewares for this compiler
+    fn get_middlewares(&self) -> &[Arc<dyn ModuleMiddleware>];
+
     /// Compiles a parsed module.
     ///
     /// It returns the [`Compilation`] or a [`CompileError`].
@@ -114,6 +170,52 @@ pub trait Compiler: Send {
         function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'data>>,
     ) -> Result<Compilation, CompileError>;
 
+    /// Whether the compiler can compile the functions of a module one at a
+    /// time, with [`Compiler::compile_function`] and then
+    /// [`Compiler::compile_module_with_functions`].
+    ///
+    /// This allows compiling the functions of a module while the rest of it
+    /// is still being received.
+    fn supports_function_compilation(&self) -> bool {
+        false
+    }
+
+    /// Compiles the function `index` of a parsed module.
+    ///
+    /// The module only needs to be parsed up to its code section, the
+    /// sections after it don't affect the compiled function.
+    ///
+    /// Only [`Compiler::compile_module_with_functions`] of the same
+    /// compiler uses the result, so the compiler decides how the function
+    /// refers to its custom sections.
+    fn compile_function(
+        &self,
+        _target: &Target,
+        _module: &CompileModuleInfo,
+        _module_translation: &ModuleTranslationState,
+        _index: LocalFunctionIndex,
+        _function_body: &FunctionBodyData<'_>,
+    ) -> Result<FunctionCompilation, CompileError> {
+        Err(CompileError::UnsupportedFeature(
+            "compiling functions one at a time".to_string(),
+        ))
+    }
+
+    /// Compiles a parsed module whose functions were compiled with
+    /// [`Compiler::compile_function`].
+    ///
+    /// It returns the [`Compilation`] or a [`CompileError`].
+    fn compile_module_with_functions(
+        &self,
+        _target: &Target,
+        _module: &CompileModuleInfo,
+        _functions: PrimaryMap<LocalFunctionIndex, FunctionCompilation>,
+    ) -> Result<Compilation, CompileError> {
+        Err(CompileError::UnsupportedFeature(
+            "compiling functions one at a time".to_string(),
+        ))
+    }
+
     /// Compiles a module into a native object file.
     ///
     /// It returns the bytes as a `&[u8]` or a [`CompileError`].
@@ -133,7 +235,7 @@ pub trait Compiler: Send {
 }
 
 /// The kinds of wasmer_types objects that might be found in a native object file.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Symbol {
     /// A function defined in the wasm.
     LocalFunction(LocalFunctionIndex),
@@ -5,11 +5,11 @@
 //! module (`CompiledFunction`).
 
 use crate::lib::std::vec::Vec;
-use crate::section::{CustomSection, SectionIndex};
+use crate::section::{CustomSection, CustomSectionProtection, SectionBody, SectionIndex};
 use crate::trap::TrapInformation;
 use crate::{
     CompiledFunctionUnwindInfo, CompiledFunctionUnwindInfoRef, FunctionAddressMap,
-    JumpTableOffsets, Relocation,
+    JumpTableOffsets, Relocation, RelocationTarget,
 };
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::{FunctionIndex, LocalFunctionIndex, SignatureIndex};
@@ -94,6 +94,117 @@ pub type Functions = PrimaryMap<LocalFunctionIndex, CompiledFunction>;
 /// The custom sections for a Compilation.
 pub type CustomSections = PrimaryMap<SectionIndex, CustomSection>;
 
+/// A function compiled on its own, with
+/// [`Compiler::compile_function`](crate::Compiler::compile_function).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionCompilation {
+    /// The compiled function.
+    pub function: CompiledFunction,
+
+    /// The custom sections only this function uses.
+    pub custom_sections: CustomSections,
+
+    /// The sections of `custom_sections` that hold the exception frames of
+    /// the function.
+    pub eh_frame_sections: Vec<SectionIndex>,
+}
+
+impl FunctionCompilation {
+    /// Moves the custom sections of `functions` after `custom_sections`
+    /// and joins their exception frames into a single `.eh_frame` section.
+    ///
+    /// The relocations to custom sections in each function and in its
+    /// sections must refer to the sections of that function. They are
+    /// updated to refer to where the sections are moved.
+    pub fn merge(
+        functions: PrimaryMap<LocalFunctionIndex, Self>,
+        custom_sections: &mut CustomSections,
+    ) -> (Functions, Option<Dwarf>) {
+        let mut frame_section_bytes = Vec::new();
+        let mut frame_section_relocations = Vec::new();
+        let functions = functions
+            .into_iter()
+            .map(|(_, compilation)| {
+                let Self {
+                    mut function,
+                    custom_sections: sections,
+                    eh_frame_sections,
+                } = compilation;
+
+                // Where each section that isn't exception frames is moved.
+                let mut next = custom_sections.len() as u32;
+                let moved = sections
+                    .keys()
+                    .map(|index| {
+                        if eh_frame_sections.contains(&index) {
+                            None
+                        } else {
+                            next += 1;
+                            Some(SectionIndex::from_u32(next - 1))
+                        }
+                    })
+                    .collect::<PrimaryMap<SectionIndex, _>>();
+                let relocate = |relocations: &mut Vec<Relocation>| {
+                    for reloc in relocations {
+                        if let RelocationTarget::CustomSection(index) = reloc.reloc_target {
+                            reloc.reloc_target = RelocationTarget::CustomSection(
+                                moved[index].expect("relocation to exception frames"),
+                            );
+                        }
+                    }
+                };
+
+                relocate(&mut function.relocations);
+                for (index, mut section) in sections.into_iter() {
+                    relocate(&mut section.relocations);
+                    if moved[index].is_some() {
+                        custom_sections.push(section);
+                        continue;
+                    }
+                    let offset = frame_section_bytes.len() as u32;
+                    for reloc in &mut section.relocations {
+                        reloc.offset += offset;
+                    }
+                    frame_section_bytes.extend_from_slice(section.bytes.as_slice());
+                    frame_section_relocations.extend(section.relocations);
+                }
+                function
+            })
+            .collect::<Functions>();
+
+        if frame_section_bytes.is_empty() {
+            return (functions, None);
+        }
+        // Terminating zero-length CIE.
+        frame_section_bytes.extend_from_slice(&[
+            0x00, 0x00, 0x00, 0x00, // Length
+            0x00, 0x00, 0x00, 0x00, // CIE ID
+            0x10, // Version (must be 1)
+            0x00, // Augmentation data
+            0x00, // Code alignment factor
+            0x00, // Data alignment factor
+            0x00, // Return address register
+            0x00, 0x00, 0x00, // Padding to a multiple of 4 bytes
+        ]);
+        let eh_frame = custom_sections.push(CustomSection {
+            protection: CustomSectionProtection::Read,
+            bytes: SectionBody::new_with_vec(frame_section_bytes),
+            relocations: frame_section_relocations,
+        });
+        (functions, Some(Dwarf::new(eh_frame)))
+    }
+}
+
+impl From<CompiledFunction> for FunctionCompilation {
+    fn from(function: CompiledFunction) -> Self {
+        Self {
+            function,
+            custom_sections: PrimaryMap::new(),
+            eh_frame_sections: Vec::new(),
+        }
+    }
+}
+
 /// The DWARF information for this Compilation.
 ///
 /// It is used for retrieving the unwind information once an exception
@@ -310,3 +421,111 @@ impl<'a> Iterator for Iter<'a> {
         self.iterator.next().map(|(_, b)| b)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RelocationKind;
+    use wasmer_types::entity::EntityRef;
+
+    fn relocation(offset: u32, section: u32) -> Relocation {
+        Relocation {
+            kind: RelocationKind::Abs8,
+            reloc_target: RelocationTarget::CustomSection(SectionIndex::from_u32(section)),
+            offset,
+            addend: 0,
+        }
+    }
+
+    fn section(bytes: &[u8], relocations: Vec<Relocation>) -> CustomSection {
+        CustomSection {
+            protection: CustomSectionProtection::Read,
+            bytes: SectionBody::new_with_vec(bytes.to_vec()),
+            relocations,
+        }
+    }
+
+    /// A function referring to its sections 0 and 1, with its exception
+    /// frames in section 2.
+    fn compilation(byte: u8) -> FunctionCompilation {
+        let mut custom_sections = PrimaryMap::new();
+        custom_sections.push(section(&[byte], vec![relocation(0, 1)]));
+        custom_sections.push(section(&[byte; 2], vec![]));
+        custom_sections.push(section(&[byte; 8], vec![relocation(4, 0)]));
+        FunctionCompilation {
+            function: CompiledFunction {
+                body: FunctionBody {
+                    body: vec![],
+                    unwind_info: None,
+                },
+                relocations: vec![relocation(0, 0), relocation(8, 1)],
+                jt_offsets: JumpTableOffsets::new(),
+                frame_info: CompiledFunctionFrameInfo::default(),
+            },
+            custom_sections,
+            eh_frame_sections: vec![SectionIndex::new(2)],
+        }
+    }
+
+    #[test]
+    fn merges_the_sections_of_the_functions() {
+        let mut functions = PrimaryMap::new();
+        functions.push(compilation(1));
+        functions.push(compilation(2));
+        let mut custom_sections = PrimaryMap::new();
+        custom_sections.push(section(&[0], vec![]));
+
+        let (functions, dwarf) = FunctionCompilation::merge(functions, &mut custom_sections);
+
+        let targets = |relocations: &[Relocation]| {
+            relocations
+                .iter()
+                .map(|reloc| (reloc.offset, reloc.reloc_target))
+                .collect::<Vec<_>>()
+        };
+        let section = |index| RelocationTarget::CustomSection(SectionIndex::new(index));
+        assert_eq!(
+            targets(&functions[LocalFunctionIndex::new(0)].relocations),
+            [(0, section(1)), (8, section(2))]
+        );
+        assert_eq!(
+            targets(&functions[LocalFunctionIndex::new(1)].relocations),
+            [(0, section(3)), (8, section(4))]
+        );
+        assert_eq!(
+            targets(&custom_sections[SectionIndex::new(1)].relocations),
+            [(0, section(2))]
+        );
+        assert_eq!(
+            targets(&custom_sections[SectionIndex::new(3)].relocations),
+            [(0, section(4))]
+        );
+        assert_eq!(
+            custom_sections[SectionIndex::new(4)].bytes.as_slice(),
+            [2, 2]
+        );
+
+        let eh_frame = &custom_sections[dwarf.unwrap().eh_frame];
+        assert_eq!(eh_frame.bytes.len(), 8 + 8 + 16);
+        assert_eq!(
+            &eh_frame.bytes.as_slice()[..16],
+            [1; 8].iter().chain(&[2; 8]).copied().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            targets(&eh_frame.relocations),
+            [(4, section(1)), (12, section(3))]
+        );
+    }
+
+    #[test]
+    fn merges_no_exception_frames() {
+        let mut function = compilation(1).function;
+        function.relocations.clear();
+        let mut functions = PrimaryMap::new();
+        functions.push(FunctionCompilation::from(function));
+        let mut custom_sections = PrimaryMap::new();
+        let (_, dwarf) = FunctionCompilation::merge(functions, &mut custom_sections);
+        assert!(dwarf.is_none());
+        assert!(custom_sections.is_empty());
+    }
+}
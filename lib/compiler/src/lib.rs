@@ -68,13 +68,13 @@ mod sourceloc;
 
 pub use crate::address_map::{FunctionAddressMap, InstructionAddressMap};
 #[cfg(feature = "translator")]
-pub use crate::compiler::{Compiler, CompilerConfig, Symbol, SymbolRegistry};
+pub use crate::compiler::{wasmparser_features, Compiler, CompilerConfig, Symbol, SymbolRegistry};
 pub use crate::error::{
     CompileError, MiddlewareError, ParseCpuFeatureError, WasmError, WasmResult,
 };
 pub use crate::function::{
     Compilation, CompiledFunction, CompiledFunctionFrameInfo, CustomSections, Dwarf, FunctionBody,
-    FunctionBodyRef, Functions, TrampolinesSection,
+    FunctionBodyRef, FunctionCompilation, Functions, TrampolinesSection,
 };
 pub use crate::jump_table::{JumpTable, JumpTableOffsets};
 pub use crate::module::CompileModuleInfo;
@@ -89,8 +89,9 @@ pub use crate::target::{
 };
 #[cfg(feature = "translator")]
 pub use crate::translator::{
-    translate_module, wptype_to_type, FunctionBodyData, FunctionReader, ModuleEnvironment,
-    ModuleTranslationState,
+    reorder_function_memargs, reorder_memargs, translate_module, wptype_to_type, FunctionBodyData,
+    FunctionMiddleware, FunctionReader, MiddlewareOperatorsReader, MiddlewareReaderState,
+    ModuleEnvironment, ModuleMiddleware, ModuleMiddlewareChain, ModuleTranslationState,
 };
 pub use crate::trap::TrapInformation;
 pub use crate::unwind::{CompiledFunctionUnwindInfo, CompiledFunctionUnwindInfoRef};
@@ -5,6 +5,7 @@ use crate::lib::std::borrow::ToOwned;
 use crate::lib::std::string::ToString;
 use crate::lib::std::{boxed::Box, string::String, vec::Vec};
 use crate::translate_module;
+use crate::translator::module::translate_module_from_code_section;
 use crate::{WasmError, WasmResult};
 use std::convert::{TryFrom, TryInto};
 use std::sync::Arc;
@@ -14,7 +15,7 @@ use wasmer_types::{
     CustomSectionIndex, DataIndex, DataInitializer, DataInitializerLocation, ElemIndex,
     ExportIndex, FunctionIndex, GlobalIndex, GlobalInit, GlobalType, ImportIndex,
     LocalFunctionIndex, MemoryIndex, MemoryType, ModuleInfo, OwnedTableInitializer, SignatureIndex,
-    TableIndex, TableType,
+    TableIndex, TableType, TagIndex, TagType,
 };
 pub use wasmparser::FunctionBody as FunctionReader;
 
@@ -66,6 +67,24 @@ impl<'data> ModuleEnvironment<'data> {
         Ok(self)
     }
 
+    /// Translate the sections of a wasm module from its code section on,
+    /// continuing a translation of the sections before it in `self`.
+    ///
+    /// This allows translating the beginning of a module, up to its code
+    /// section, while the rest of it is still being received.
+    pub fn translate_from_code_section(
+        mut self,
+        data: &'data [u8],
+    ) -> WasmResult<ModuleEnvironment<'data>> {
+        let mut module_translation_state = self
+            .module_translation_state
+            .take()
+            .expect("the beginning of the module must be translated first");
+        translate_module_from_code_section(data, &mut module_translation_state, &mut self)?;
+        self.module_translation_state = Some(module_translation_state);
+        Ok(self)
+    }
+
     pub(crate) fn declare_export(&mut self, export: ExportIndex, name: &str) -> WasmResult<()> {
         self.module.exports.insert(String::from(name), export);
         Ok(())
@@ -185,6 +204,27 @@ impl<'data> ModuleEnvironment<'data> {
         Ok(())
     }
 
+    pub(crate) fn declare_tag_import(
+        &mut self,
+        tag: TagType,
+        module: &str,
+        field: &str,
+    ) -> WasmResult<()> {
+        debug_assert_eq!(
+            self.module.tags.len(),
+            self.module.import_counts.tags as usize,
+            "Imported tags must be declared first"
+        );
+        self.declare_import(
+            ImportIndex::Tag(TagIndex::from_u32(self.module.import_counts.tags)),
+            module,
+            field,
+        )?;
+        self.module.tags.push(tag);
+        self.module.import_counts.tags += 1;
+        Ok(())
+    }
+
     pub(crate) fn finish_imports(&mut self) -> WasmResult<()> {
         Ok(())
     }
@@ -249,6 +289,18 @@ impl<'data> ModuleEnvironment<'data> {
         Ok(())
     }
 
+    pub(crate) fn reserve_tags(&mut self, num: u32) -> WasmResult<()> {
+        self.module
+            .tags
+            .reserve_exact(usize::try_from(num).unwrap());
+        Ok(())
+    }
+
+    pub(crate) fn declare_tag(&mut self, tag: TagType) -> WasmResult<()> {
+        self.module.tags.push(tag);
+        Ok(())
+    }
+
     pub(crate) fn reserve_exports(&mut self, num: u32) -> WasmResult<()> {
         self.module.exports.reserve(usize::try_from(num).unwrap());
         Ok(())
@@ -286,6 +338,10 @@ impl<'data> ModuleEnvironment<'data> {
         self.declare_export(ExportIndex::Global(global_index), name)
     }
 
+    pub(crate) fn declare_tag_export(&mut self, tag_index: TagIndex, name: &str) -> WasmResult<()> {
+        self.declare_export(ExportIndex::Tag(tag_index), name)
+    }
+
     pub(crate) fn declare_start_function(&mut self, func_index: FunctionIndex) -> WasmResult<()> {
         debug_assert!(self.module.start_function.is_none());
         self.module.start_function = Some(func_index);
@@ -329,6 +385,13 @@ impl<'data> ModuleEnvironment<'data> {
         Ok(())
     }
 
+    /// Provides the offset in the wasm binary of the contents of the code
+    /// section.
+    pub(crate) fn declare_code_section_offset(&mut self, offset: usize) -> WasmResult<()> {
+        self.module.code_section_offset = offset.try_into().unwrap();
+        Ok(())
+    }
+
     pub(crate) fn define_function_body(
         &mut self,
         _module_translation_state: &ModuleTranslationState,
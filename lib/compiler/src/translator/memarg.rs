@@ -0,0 +1,144 @@
+//! Reordering of the immediates of the memory accesses to other memories
+//! than the first one.
+//!
+//! The multi-memory proposal encodes such an immediate as its flags, with
+//! bit 6 set, followed by the index of the memory and by the offset. The
+//! version of `wasmparser` in use reads the offset before the memory index
+//! instead, as early drafts of the proposal did. Both are LEB128 integers,
+//! so swapping their bytes turns one encoding into the other without
+//! moving any other byte of the module.
+//!
+//! The function bodies are reordered before `wasmparser` reads them. Bodies
+//! which can't be read are left as is, for the validation to report them.
+use std::borrow::Cow;
+use std::ops::Range;
+use std::vec::Vec;
+use wasmparser::{FunctionBody, Parser, Payload};
+
+/// The flag of the immediate of a memory access with a memory index.
+const MEMORY_INDEX_FLAG: u32 = 1 << 6;
+
+/// Reorders the immediates of the memory accesses with a memory index in
+/// the function bodies of the module `data`, which is only copied if it
+/// has such memory accesses.
+pub fn reorder_memargs(data: &[u8]) -> Cow<'_, [u8]> {
+    let mut swaps = Vec::new();
+    for payload in Parser::new(0).parse_all(data) {
+        match payload {
+            Ok(Payload::CodeSectionEntry(body)) => {
+                let start = body.range().start;
+                let body_swaps = memarg_swaps(&body);
+                swaps.extend(
+                    body_swaps
+                        .into_iter()
+                        .map(|(range, mid)| (range.start + start..range.end + start, mid)),
+                );
+            }
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+    if swaps.is_empty() {
+        return Cow::Borrowed(data);
+    }
+    let mut data = data.to_vec();
+    for (range, mid) in swaps {
+        data[range].rotate_left(mid);
+    }
+    Cow::Owned(data)
+}
+
+/// Reorders the immediates of the memory accesses with a memory index in
+/// the function body `body`, found at `offset` in its module.
+pub fn reorder_function_memargs(body: &mut [u8], offset: usize) {
+    let swaps = memarg_swaps(&FunctionBody::new(offset, body));
+    for (range, mid) in swaps {
+        body[range].rotate_left(mid);
+    }
+}
+
+/// The ranges of `body` holding the memory index and the offset of the
+/// immediates to reorder, with the length of the memory index.
+fn memarg_swaps(body: &FunctionBody<'_>) -> Vec<(Range<usize>, usize)> {
+    let start = body.range().start;
+    let mut reader = body.get_binary_reader();
+    let code = match reader.read_bytes(reader.bytes_remaining()) {
+        Ok(code) => code,
+        Err(_) => return Vec::new(),
+    };
+    let mut operators = match body.get_operators_reader() {
+        Ok(operators) => operators,
+        Err(_) => return Vec::new(),
+    };
+    let mut swaps = Vec::new();
+    while !operators.eof() {
+        let position = match operators.read_with_offset() {
+            Ok((_, position)) => position - start,
+            Err(_) => break,
+        };
+        if let Some(swap) = memarg_swap(code, position) {
+            swaps.push(swap);
+        }
+    }
+    swaps
+}
+
+/// The swap of the immediate of the instruction at `position` in `code`,
+/// if it is a memory access with a memory index.
+fn memarg_swap(code: &[u8], position: usize) -> Option<(Range<usize>, usize)> {
+    let immediate = position + 1;
+    let flags = match code[position] {
+        0x28..=0x3e => immediate,
+        prefix @ (0xfd | 0xfe) => {
+            let (opcode, flags) = read_var_u32(code, immediate)?;
+            let has_memarg = match prefix {
+                0xfd => matches!(opcode, 0x00..=0x0b | 0x54..=0x5d),
+                _ => matches!(opcode, 0x00..=0x02 | 0x10..=0x4e),
+            };
+            if !has_memarg {
+                return None;
+            }
+            flags
+        }
+        _ => return None,
+    };
+    let (flags, memory) = read_var_u32(code, flags)?;
+    if flags & MEMORY_INDEX_FLAG == 0 {
+        return None;
+    }
+    let (_, offset) = read_var_u32(code, memory)?;
+    let (_, end) = read_var_u32(code, offset)?;
+    Some((memory..end, offset - memory))
+}
+
+/// Reads the LEB128 integer at `position` in `code`, returning it with the
+/// position following it.
+fn read_var_u32(code: &[u8], mut position: usize) -> Option<(u32, usize)> {
+    let mut value = 0u32;
+    for shift in (0..35).step_by(7) {
+        let byte = *code.get(position)?;
+        position += 1;
+        value |= u32::from(byte & 0x7f).checked_shl(shift)?;
+        if byte & 0x80 == 0 {
+            return Some((value, position));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swaps_the_memory_index_and_the_offset() {
+        // A function body without locals holding `i32.load 1 offset=300`,
+        // `i32.load offset=5` and `end`.
+        let mut body = vec![0x00, 0x28, 0x42, 0x01, 0xac, 0x02, 0x28, 0x02, 0x05, 0x0b];
+        reorder_function_memargs(&mut body, 0);
+        assert_eq!(
+            body,
+            [0x00, 0x28, 0x42, 0xac, 0x02, 0x01, 0x28, 0x02, 0x05, 0x0b]
+        );
+    }
+}
@@ -0,0 +1,353 @@
+//! The middleware parses the function binary bytecodes and transform them
+//! with the chosen functions.
+
+use crate::error::{MiddlewareError, WasmError, WasmResult};
+use crate::lib::std::boxed::Box;
+use crate::lib::std::fmt::Debug;
+use crate::lib::std::vec::Vec;
+use smallvec::SmallVec;
+use std::collections::VecDeque;
+use std::ops::Deref;
+use wasmer_types::{LocalFunctionIndex, ModuleInfo};
+use wasmparser::{Operator, OperatorsIteratorWithOffsets, OperatorsReader};
+
+/// A shared builder for function middlewares.
+pub trait ModuleMiddleware: Debug + Send + Sync {
+    /// Generates a `FunctionMiddleware` for a given function.
+    ///
+    /// Here we generate a separate object for each function instead of executing directly on
+    /// per-function operators, in order to enable concurrent middleware application. Takes
+    /// immutable `&self` because this function can be called concurrently from multiple
+    /// compilation threads.
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware>;
+
+    /// Transforms a `ModuleInfo` struct in-place. This is called before application on
+    /// functions begins.
+    fn transform_module_info(&self, _: &mut ModuleInfo) {}
+}
+
+/// A function middleware specialized for a single function.
+pub trait FunctionMiddleware: Debug {
+    /// Processes the given operator.
+    ///
+    /// The default implementation passes the operator through unchanged. Implementations may
+    /// push any number of operators (including none) into `state` for every operator they are
+    /// fed.
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        state.push_operator(operator);
+        Ok(())
+    }
+}
+
+/// The state of the middleware chain, exposed to middlewares to push their outputs.
+#[derive(Debug)]
+pub struct MiddlewareReaderState<'a> {
+    /// The offset in the original module of the operator currently being processed.
+    original_offset: usize,
+
+    /// The pending operations added by the middleware, along with the offset of the original
+    /// operator they were derived from.
+    pending_operations: VecDeque<(Operator<'a>, usize)>,
+}
+
+impl<'a> MiddlewareReaderState<'a> {
+    /// Push an operator.
+    ///
+    /// The operator is attributed to the offset of the original operator being processed.
+    pub fn push_operator(&mut self, operator: Operator<'a>) {
+        self.pending_operations
+            .push_back((operator, self.original_offset));
+    }
+
+    /// The offset in the original module of the operator currently being processed.
+    pub fn original_offset(&self) -> usize {
+        self.original_offset
+    }
+}
+
+/// Trait for generating middleware chains from "prototype" (generator) chains.
+pub trait ModuleMiddlewareChain {
+    /// Generates a function middleware chain.
+    fn generate_function_middleware_chain(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Vec<Box<dyn FunctionMiddleware>>;
+
+    /// Applies the chain on a `ModuleInfo` struct.
+    fn apply_on_module_info(&self, module_info: &mut ModuleInfo);
+}
+
+impl<T: Deref<Target = dyn ModuleMiddleware>> ModuleMiddlewareChain for [T] {
+    fn generate_function_middleware_chain(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Vec<Box<dyn FunctionMiddleware>> {
+        self.iter()
+            .map(|x| x.generate_function_middleware(local_function_index))
+            .collect()
+    }
+
+    fn apply_on_module_info(&self, module_info: &mut ModuleInfo) {
+        for item in self {
+            item.transform_module_info(module_info);
+        }
+    }
+}
+
+/// An operator reader that runs every operator of a function body through a chain of
+/// function middlewares.
+///
+/// It yields the operators produced by the last middleware in the chain, each along with the
+/// offset in the original module of the operator it was derived from. With an empty chain it
+/// behaves exactly like the underlying [`OperatorsReader`].
+pub struct MiddlewareOperatorsReader<'a> {
+    /// The underlying operators reader.
+    inner: OperatorsIteratorWithOffsets<'a>,
+
+    /// The state shared between the middlewares.
+    state: MiddlewareReaderState<'a>,
+
+    /// The backing middleware chain for this reader.
+    chain: Vec<Box<dyn FunctionMiddleware>>,
+}
+
+impl<'a> MiddlewareOperatorsReader<'a> {
+    /// Create a new `MiddlewareOperatorsReader` reading from `reader` through `chain`.
+    pub fn new(reader: OperatorsReader<'a>, chain: Vec<Box<dyn FunctionMiddleware>>) -> Self {
+        Self {
+            inner: reader.into_iter_with_offsets(),
+            state: MiddlewareReaderState {
+                original_offset: 0,
+                pending_operations: VecDeque::new(),
+            },
+            chain,
+        }
+    }
+
+    /// Read the next operator along with its offset in the original module.
+    ///
+    /// Returns `Ok(None)` once the function body has been fully consumed and the middlewares
+    /// have nothing left to emit.
+    pub fn read_operator(&mut self) -> WasmResult<Option<(Operator<'a>, usize)>> {
+        if self.chain.is_empty() {
+            return Ok(self.inner.next().transpose()?);
+        }
+
+        // Try to fill the `self.state.pending_operations` buffer, until it is non-empty.
+        while self.state.pending_operations.is_empty() {
+            let (raw_op, offset) = match self.inner.next() {
+                Some(op) => op?,
+                None => return Ok(None),
+            };
+            self.state.original_offset = offset;
+
+            // Fill the initial raw operator into pending buffer.
+            self.state.pending_operations.push_back((raw_op, offset));
+
+            // Run the operator through each stage.
+            for stage in &mut self.chain {
+                // Take the outputs from the previous stage...
+                let pending: SmallVec<[(Operator<'a>, usize); 2]> =
+                    self.state.pending_operations.drain(..).collect();
+
+                // ...and feed them into the current stage.
+                for (pending_op, pending_offset) in pending {
+                    self.state.original_offset = pending_offset;
+                    stage.feed(pending_op, &mut self.state)?;
+                }
+            }
+        }
+
+        Ok(self.state.pending_operations.pop_front())
+    }
+
+    /// Read the next operator of a function body whose final `end` hasn't
+    /// been read yet, along with its offset in the original module.
+    ///
+    /// Fails if the operators run out before that `end`, which happens when
+    /// a middleware swallows it.
+    pub fn expect_operator(&mut self) -> WasmResult<(Operator<'a>, usize)> {
+        match self.read_operator()? {
+            Some(operator) => Ok(operator),
+            None => Err(WasmError::InvalidWebAssembly {
+                message: "the function body ended before its final `end`".to_string(),
+                offset: self.state.original_offset,
+            }),
+        }
+    }
+}
+
+impl<'a> Iterator for MiddlewareOperatorsReader<'a> {
+    type Item = WasmResult<(Operator<'a>, usize)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_operator().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::std::string::String;
+    use crate::lib::std::sync::Arc;
+    use crate::FunctionReader;
+
+    /// Replaces every `nop` with two `nop`s.
+    #[derive(Debug)]
+    struct DoubleNops;
+
+    impl ModuleMiddleware for DoubleNops {
+        fn generate_function_middleware(
+            &self,
+            _: LocalFunctionIndex,
+        ) -> Box<dyn FunctionMiddleware> {
+            Box::new(DoubleNops)
+        }
+    }
+
+    impl FunctionMiddleware for DoubleNops {
+        fn feed<'a>(
+            &mut self,
+            operator: Operator<'a>,
+            state: &mut MiddlewareReaderState<'a>,
+        ) -> Result<(), MiddlewareError> {
+            if let Operator::Nop = operator {
+                state.push_operator(Operator::Nop);
+            }
+            state.push_operator(operator);
+            Ok(())
+        }
+    }
+
+    /// Drops every `nop`.
+    #[derive(Debug)]
+    struct DropNops;
+
+    impl ModuleMiddleware for DropNops {
+        fn generate_function_middleware(
+            &self,
+            _: LocalFunctionIndex,
+        ) -> Box<dyn FunctionMiddleware> {
+            Box::new(DropNops)
+        }
+    }
+
+    impl FunctionMiddleware for DropNops {
+        fn feed<'a>(
+            &mut self,
+            operator: Operator<'a>,
+            state: &mut MiddlewareReaderState<'a>,
+        ) -> Result<(), MiddlewareError> {
+            if let Operator::Nop = operator {
+                return Ok(());
+            }
+            state.push_operator(operator);
+            Ok(())
+        }
+    }
+
+    /// No locals, followed by `nop; nop; i32.const 1; drop; end`.
+    const BODY: &[u8] = &[0x00, 0x01, 0x01, 0x41, 0x01, 0x1a, 0x0b];
+
+    fn read_all(chain: &[Arc<dyn ModuleMiddleware>]) -> Vec<(String, usize)> {
+        let reader = FunctionReader::new(100, BODY)
+            .get_operators_reader()
+            .unwrap();
+        let chain = chain.generate_function_middleware_chain(LocalFunctionIndex::from_u32(0));
+        MiddlewareOperatorsReader::new(reader, chain)
+            .map(|op| op.map(|(op, offset)| (format!("{:?}", op), offset)))
+            .collect::<WasmResult<Vec<_>>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn empty_chain_is_transparent() {
+        let expected = FunctionReader::new(100, BODY)
+            .get_operators_reader()
+            .unwrap()
+            .into_iter_with_offsets()
+            .map(|op| op.map(|(op, offset)| (format!("{:?}", op), offset)))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(read_all(&[]), expected);
+    }
+
+    #[test]
+    fn middlewares_are_applied_in_order() {
+        let ops = read_all(&[Arc::new(DoubleNops)]);
+        let names = ops.iter().map(|(op, _)| op.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "Nop",
+                "Nop",
+                "Nop",
+                "Nop",
+                "I32Const { value: 1 }",
+                "Drop",
+                "End"
+            ]
+        );
+        // Inserted operators keep the offset of the operator they were derived from.
+        assert_eq!(ops[0].1, 101);
+        assert_eq!(ops[1].1, 101);
+        assert_eq!(ops[2].1, 102);
+
+        let ops = read_all(&[Arc::new(DoubleNops), Arc::new(DropNops)]);
+        let names = ops.iter().map(|(op, _)| op.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["I32Const { value: 1 }", "Drop", "End"]);
+        assert_eq!(ops[0].1, 103);
+    }
+
+    #[test]
+    fn reader_errors_are_reported() {
+        // A truncated `i32.const`.
+        let reader = FunctionReader::new(0, &[0x00, 0x41])
+            .get_operators_reader()
+            .unwrap();
+        let chain: Vec<Box<dyn FunctionMiddleware>> = vec![Box::new(DoubleNops)];
+        let mut reader = MiddlewareOperatorsReader::new(reader, chain);
+        assert!(reader.read_operator().is_err());
+    }
+
+    /// Drops the `end` of the function.
+    #[derive(Debug)]
+    struct DropEnd;
+
+    impl FunctionMiddleware for DropEnd {
+        fn feed<'a>(
+            &mut self,
+            operator: Operator<'a>,
+            state: &mut MiddlewareReaderState<'a>,
+        ) -> Result<(), MiddlewareError> {
+            if let Operator::End = operator {
+                return Ok(());
+            }
+            state.push_operator(operator);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn swallowed_end_is_reported() {
+        let reader = FunctionReader::new(100, BODY)
+            .get_operators_reader()
+            .unwrap();
+        let chain: Vec<Box<dyn FunctionMiddleware>> = vec![Box::new(DropEnd)];
+        let mut reader = MiddlewareOperatorsReader::new(reader, chain);
+        for _ in 0..4 {
+            reader.expect_operator().unwrap();
+        }
+        match reader.expect_operator() {
+            Err(WasmError::InvalidWebAssembly { offset, .. }) => assert_eq!(offset, 106),
+            other => panic!("unexpected result: {:?}", other.map(|(op, _)| op)),
+        }
+    }
+}
@@ -6,6 +6,8 @@
 //!
 //! [cranelift-wasm]: https://crates.io/crates/cranelift-wasm/
 mod environ;
+mod memarg;
+mod middleware;
 mod module;
 mod state;
 #[macro_use]
@@ -13,6 +15,11 @@ mod error;
 mod sections;
 
 pub use self::environ::{FunctionBodyData, FunctionReader, ModuleEnvironment};
+pub use self::memarg::{reorder_function_memargs, reorder_memargs};
+pub use self::middleware::{
+    FunctionMiddleware, MiddlewareOperatorsReader, MiddlewareReaderState, ModuleMiddleware,
+    ModuleMiddlewareChain,
+};
 pub use self::module::translate_module;
 pub use self::sections::wptype_to_type;
 pub use self::state::ModuleTranslationState;
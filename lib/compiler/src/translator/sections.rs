@@ -22,14 +22,16 @@ use wasmer_types::entity::packed_option::ReservedValue;
 use wasmer_types::entity::EntityRef;
 use wasmer_types::{
     DataIndex, ElemIndex, FunctionIndex, FunctionType, GlobalIndex, GlobalInit, GlobalType,
-    MemoryIndex, MemoryType, Mutability, Pages, SignatureIndex, TableIndex, TableType, Type, V128,
+    MemoryIndex, MemoryType, Mutability, Pages, SignatureIndex, TableIndex, TableType, TagIndex,
+    TagType, Type, V128,
 };
 use wasmparser::{
     self, Data, DataKind, DataSectionReader, Element, ElementItem, ElementItems, ElementKind,
-    ElementSectionReader, Export, ExportSectionReader, ExternalKind, FuncType as WPFunctionType,
-    FunctionSectionReader, GlobalSectionReader, GlobalType as WPGlobalType, ImportSectionEntryType,
-    ImportSectionReader, MemorySectionReader, MemoryType as WPMemoryType, NameSectionReader,
-    Naming, NamingReader, Operator, TableSectionReader, TypeDef, TypeSectionReader,
+    ElementSectionReader, EventSectionReader, EventType, Export, ExportSectionReader, ExternalKind,
+    FuncType as WPFunctionType, FunctionSectionReader, GlobalSectionReader,
+    GlobalType as WPGlobalType, ImportSectionEntryType, ImportSectionReader, MemorySectionReader,
+    MemoryType as WPMemoryType, NameSectionReader, Naming, NamingReader, Operator,
+    TableSectionReader, TypeDef, TypeSectionReader,
 };
 
 /// Helper function translating wasmparser types to Wasm Type.
@@ -105,27 +107,22 @@ pub fn parse_import_section<'data>(
                     field_name.unwrap_or_default(),
                 )?;
             }
-            ImportSectionEntryType::Module(_)
-            | ImportSectionEntryType::Instance(_)
-            | ImportSectionEntryType::Event(_) => {
+            ImportSectionEntryType::Module(_) | ImportSectionEntryType::Instance(_) => {
                 unimplemented!("module linking not implemented yet")
             }
-            ImportSectionEntryType::Memory(WPMemoryType::M32 {
-                limits: ref memlimits,
-                shared,
-            }) => {
-                environ.declare_memory_import(
-                    MemoryType {
-                        minimum: Pages(memlimits.initial),
-                        maximum: memlimits.maximum.map(Pages),
-                        shared,
-                    },
+            ImportSectionEntryType::Event(ref event) => {
+                environ.declare_tag_import(
+                    tag_type(event, environ)?,
                     module_name,
                     field_name.unwrap_or_default(),
                 )?;
             }
-            ImportSectionEntryType::Memory(WPMemoryType::M64 { .. }) => {
-                unimplemented!("64bit memory not implemented yet")
+            ImportSectionEntryType::Memory(ref memory) => {
+                environ.declare_memory_import(
+                    memory_type(memory)?,
+                    module_name,
+                    field_name.unwrap_or_default(),
+                )?;
             }
             ImportSectionEntryType::Global(ref ty) => {
                 environ.declare_global_import(
@@ -208,21 +205,66 @@ pub fn parse_memory_section(
 
     for entry in memories {
         let memory = entry?;
-        match memory {
-            WPMemoryType::M32 { limits, shared } => {
-                environ.declare_memory(MemoryType {
-                    minimum: Pages(limits.initial),
-                    maximum: limits.maximum.map(Pages),
-                    shared,
-                })?;
-            }
-            WPMemoryType::M64 { .. } => unimplemented!("64bit memory not implemented yet"),
+        environ.declare_memory(memory_type(&memory)?)?;
+    }
+
+    Ok(())
+}
+
+/// Converts a wasmparser memory type, which may be indexed with 64-bit
+/// addresses, into a `MemoryType`.
+fn memory_type(memory: &WPMemoryType) -> WasmResult<MemoryType> {
+    match *memory {
+        WPMemoryType::M32 { limits, shared } => Ok(MemoryType {
+            minimum: Pages(limits.initial),
+            maximum: limits.maximum.map(Pages),
+            shared,
+            memory64: false,
+        }),
+        WPMemoryType::M64 { limits, shared } => {
+            let pages = |pages: u64| {
+                u32::try_from(pages).map(Pages).map_err(|_| {
+                    wasm_unsupported!("64-bit memories are limited to {} pages", u32::MAX)
+                })
+            };
+            Ok(MemoryType {
+                minimum: pages(limits.initial)?,
+                maximum: limits.maximum.map(pages).transpose()?,
+                shared,
+                memory64: true,
+            })
         }
     }
+}
+
+/// Parses the Event section of the wasm module, which declares the
+/// module's exception tags.
+pub fn parse_tag_section(
+    tags: EventSectionReader,
+    environ: &mut ModuleEnvironment,
+) -> WasmResult<()> {
+    environ.reserve_tags(tags.get_count())?;
+
+    for entry in tags {
+        let tag = entry?;
+        environ.declare_tag(tag_type(&tag, environ)?)?;
+    }
 
     Ok(())
 }
 
+/// Converts a wasmparser event type into a `TagType`, taking the payload
+/// types from the parameters of the referenced signature.
+fn tag_type(event: &EventType, environ: &ModuleEnvironment) -> WasmResult<TagType> {
+    let sig = &environ.module.signatures[SignatureIndex::from_u32(event.type_index)];
+    if sig.params().iter().any(|ty| ty.is_ref()) {
+        return Err(wasm_unsupported!(
+            "exception tags with reference type payloads are not supported"
+        ));
+    }
+    Ok(TagType::new(sig.params()))
+}
+
 /// Parses the Global section of the wasm module.
 pub fn parse_global_section(
     globals: GlobalSectionReader,
@@ -302,10 +344,8 @@ pub fn parse_export_section<'data>(
             ExternalKind::Global => {
                 environ.declare_global_export(GlobalIndex::new(index), field)?
             }
-            ExternalKind::Type
-            | ExternalKind::Module
-            | ExternalKind::Instance
-            | ExternalKind::Event => {
+            ExternalKind::Event => environ.declare_tag_export(TagIndex::new(index), field)?,
+            ExternalKind::Type | ExternalKind::Module | ExternalKind::Instance => {
                 unimplemented!("module linking not implemented yet")
             }
         }
@@ -402,6 +442,7 @@ pub fn parse_data_section<'data>(
                 let mut init_expr_reader = init_expr.get_binary_reader();
                 let (base, offset) = match init_expr_reader.read_operator()? {
                     Operator::I32Const { value } => (None, value as u32 as usize),
+                    Operator::I64Const { value } => (None, value as u64 as usize),
                     Operator::GlobalGet { global_index } => {
                         (Some(GlobalIndex::from_u32(global_index)), 0)
                     }
@@ -0,0 +1,53 @@
+use crate::{ObjectFileEngine, ObjectKind};
+use wasmer_compiler::{CompilerConfig, Features, Target};
+use wasmer_engine_universal::Universal;
+
+/// The ObjectFile builder
+pub struct ObjectFile {
+    universal: Universal,
+    kind: ObjectKind,
+}
+
+impl ObjectFile {
+    /// Create a new ObjectFile
+    pub fn new<T>(compiler_config: T) -> Self
+    where
+        T: Into<Box<dyn CompilerConfig>>,
+    {
+        Self {
+            universal: Universal::new(compiler_config),
+            kind: ObjectKind::Relocatable,
+        }
+    }
+
+    /// Create a new headless ObjectFile
+    pub fn headless() -> Self {
+        Self {
+            universal: Universal::headless(),
+            kind: ObjectKind::Relocatable,
+        }
+    }
+
+    /// Set the target
+    pub fn target(mut self, target: Target) -> Self {
+        self.universal = self.universal.target(target);
+        self
+    }
+
+    /// Set the features
+    pub fn features(mut self, features: Features) -> Self {
+        self.universal = self.universal.features(features);
+        self
+    }
+
+    /// Set the kind of object files the modules are serialized to
+    pub fn kind(mut self, kind: ObjectKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Build the `ObjectFileEngine` for this configuration
+    pub fn engine(self) -> ObjectFileEngine {
+        ObjectFileEngine::new(self.universal.engine(), self.kind)
+    }
+}
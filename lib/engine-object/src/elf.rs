@@ -0,0 +1,445 @@
+//! Writing and reading the ELF object files of the engine.
+//!
+//! The relocatable object files hold the code of the module in their
+//! `.text` section, and its read-only data sections in `.rodata`, with
+//! the ELF relocations of the code. The functions, the trampolines and
+//! the custom sections are local symbols, named by [`symbol_name`].
+//!
+//! The object files don't refer to the runtime they are linked with:
+//! the code calls the libcalls through stubs, in the
+//! `.text.wasmer_libcalls` section, which jump to the addresses the
+//! loader writes in the descriptor of the code.
+//!
+//! A serialized [`UniversalExecutable`], without its code image, is held
+//! by the allocated `.wasmer_metadata` section, which is part of the
+//! loaded image of the programs and libraries the object file is linked
+//! into. The object files define two global symbols:
+//!
+//! * `WASMER_METADATA`, the serialized executable, sized with its
+//!   length,
+//! * `WASMER_CODE`, the descriptor of the linked code, in `.data`, which
+//!   [`load_linked_object_file`](crate::load_linked_object_file) loads.
+//!
+//! [`UniversalExecutable`]: wasmer_engine_universal::UniversalExecutable
+
+use crate::ObjectKind;
+use object::elf::{
+    R_AARCH64_ABS64, R_AARCH64_ADR_PREL_PG_HI21, R_AARCH64_CALL26, R_AARCH64_LDST64_ABS_LO12_NC,
+    R_AARCH64_MOVW_UABS_G0_NC, R_AARCH64_MOVW_UABS_G1_NC, R_AARCH64_MOVW_UABS_G2_NC,
+    R_AARCH64_MOVW_UABS_G3, R_X86_64_32, R_X86_64_64, R_X86_64_PC32, R_X86_64_PC64, R_X86_64_PLT32,
+};
+use object::read::{File, Object as _, ObjectSection, ObjectSymbol};
+use object::write::{self, Object, SymbolSection};
+use object::{Endianness, RelocationFlags, SectionKind, SymbolFlags, SymbolKind, SymbolScope};
+use std::collections::HashMap;
+use std::env;
+use std::path::Path;
+use std::process::Command;
+use wasmer_compiler::{
+    Architecture, BinaryFormat, CompileError, RelocationKind, RelocationTarget, Symbol, Triple,
+};
+use wasmer_engine::DeserializeError;
+use wasmer_engine_universal::CodeImage;
+use wasmer_types::entity::EntityRef;
+
+/// The name of the section holding the serialized executable.
+pub const METADATA_SECTION: &str = ".wasmer_metadata";
+
+/// The name of the symbol of the serialized executable.
+pub const METADATA_SYMBOL: &str = "WASMER_METADATA";
+
+/// The name of the symbol of the descriptor of the linked code.
+pub const CODE_SYMBOL: &str = "WASMER_CODE";
+
+/// The name of the section of the stubs calling the libcalls.
+const LIBCALLS_SECTION: &str = ".text.wasmer_libcalls";
+
+/// The architecture of the ELF files written for `triple`.
+pub(crate) fn object_architecture(triple: &Triple) -> Result<object::Architecture, CompileError> {
+    let architecture = match triple.architecture {
+        Architecture::X86_64 => object::Architecture::X86_64,
+        Architecture::Aarch64(_) => object::Architecture::Aarch64,
+        _ => return Err(CompileError::UnsupportedTarget(triple.to_string())),
+    };
+    if triple.binary_format != BinaryFormat::Elf {
+        return Err(CompileError::UnsupportedTarget(triple.to_string()));
+    }
+    Ok(architecture)
+}
+
+/// The name of the symbol of a function, a trampoline or a custom
+/// section in the object files.
+pub fn symbol_name(symbol: &Symbol) -> String {
+    match symbol {
+        Symbol::LocalFunction(index) => format!("wasmer_function_{}", index.index()),
+        Symbol::Section(index) => format!("wasmer_section_{}", index.index()),
+        Symbol::FunctionCallTrampoline(index) => {
+            format!("wasmer_trampoline_function_call_{}", index.index())
+        }
+        Symbol::DynamicFunctionTrampoline(index) => {
+            format!("wasmer_trampoline_dynamic_function_{}", index.index())
+        }
+    }
+}
+
+/// The ELF relocation type of `kind` on `architecture`, if the code has
+/// to be relocated for it.
+fn relocation_type(
+    architecture: object::Architecture,
+    kind: RelocationKind,
+) -> Result<Option<u32>, CompileError> {
+    let r_type = match (architecture, kind) {
+        // The loader doesn't apply these either.
+        (_, RelocationKind::X86PCRelRodata4) => return Ok(None),
+        (object::Architecture::X86_64, RelocationKind::Abs4) => R_X86_64_32,
+        (object::Architecture::X86_64, RelocationKind::Abs8) => R_X86_64_64,
+        (object::Architecture::X86_64, RelocationKind::X86PCRel4) => R_X86_64_PC32,
+        (object::Architecture::X86_64, RelocationKind::X86PCRel8) => R_X86_64_PC64,
+        (object::Architecture::X86_64, RelocationKind::X86CallPCRel4)
+        | (object::Architecture::X86_64, RelocationKind::X86CallPLTRel4) => R_X86_64_PLT32,
+        (object::Architecture::Aarch64, RelocationKind::Abs8) => R_AARCH64_ABS64,
+        (object::Architecture::Aarch64, RelocationKind::Arm64Call) => R_AARCH64_CALL26,
+        (object::Architecture::Aarch64, RelocationKind::Arm64Movw0) => R_AARCH64_MOVW_UABS_G0_NC,
+        (object::Architecture::Aarch64, RelocationKind::Arm64Movw1) => R_AARCH64_MOVW_UABS_G1_NC,
+        (object::Architecture::Aarch64, RelocationKind::Arm64Movw2) => R_AARCH64_MOVW_UABS_G2_NC,
+        (object::Architecture::Aarch64, RelocationKind::Arm64Movw3) => R_AARCH64_MOVW_UABS_G3,
+        _ => {
+            return Err(CompileError::Codegen(format!(
+                "the {} relocations can't be written in {:?} object files",
+                kind, architecture
+            )))
+        }
+    };
+    Ok(Some(r_type))
+}
+
+/// The length of the header of the descriptor of the linked code, see
+/// `ObjectCode`, which its libcall slots follow.
+pub(crate) const CODE_HEADER_LENGTH: usize = 40;
+
+/// The length of a libcall slot of the descriptor of the linked code,
+/// see `LibCallSlot`.
+pub(crate) const LIBCALL_SLOT_LENGTH: usize = 16;
+
+/// The stub jumping to the address of the libcall slot it is relocated
+/// for, and the ELF relocations of that address, at their offsets in
+/// the stub, on `architecture`.
+fn libcall_stub(architecture: object::Architecture) -> (&'static [u8], &'static [(u64, u32, i64)]) {
+    match architecture {
+        // jmp *slot(%rip); int3; int3
+        object::Architecture::X86_64 => (
+            &[0xff, 0x25, 0, 0, 0, 0, 0xcc, 0xcc],
+            &[(2, R_X86_64_PC32, -4)],
+        ),
+        // adrp x17, slot; ldr x17, [x17, :lo12:slot]; br x17; brk #0
+        _ => (
+            &[
+                0x11, 0x00, 0x00, 0x90, 0x31, 0x02, 0x40, 0xf9, 0x20, 0x02, 0x1f, 0xd6, 0x00, 0x00,
+                0x20, 0xd4,
+            ],
+            &[
+                (0, R_AARCH64_ADR_PREL_PG_HI21, 0),
+                (4, R_AARCH64_LDST64_ABS_LO12_NC, 0),
+            ],
+        ),
+    }
+}
+
+/// The ELF relocation type of the 64-bit addresses on `architecture`.
+fn address_relocation_type(architecture: object::Architecture) -> u32 {
+    match architecture {
+        object::Architecture::X86_64 => R_X86_64_64,
+        _ => R_AARCH64_ABS64,
+    }
+}
+
+/// Write a little-endian, 64-bit relocatable ELF file for
+/// `architecture` holding the code `image` and `metadata`, the
+/// serialized executable without its code image.
+pub(crate) fn write_relocatable(
+    architecture: object::Architecture,
+    image: &CodeImage,
+    metadata: &[u8],
+) -> Result<Vec<u8>, CompileError> {
+    let mut obj = Object::new(object::BinaryFormat::Elf, architecture, Endianness::Little);
+    let add_relocation = |obj: &mut Object,
+                          section: write::SectionId,
+                          offset: u64,
+                          symbol: write::SymbolId,
+                          addend: i64,
+                          r_type: u32| {
+        obj.add_relocation(
+            section,
+            write::Relocation {
+                offset,
+                symbol,
+                addend,
+                flags: RelocationFlags::Elf { r_type },
+            },
+        )
+        .map_err(|e| CompileError::Codegen(e.to_string()))
+    };
+
+    // The code is laid out as in the image, whose data sections start
+    // on a new page.
+    let text = obj.section_id(write::StandardSection::Text);
+    obj.append_section_data(text, &image.bytes[..image.executable_len], 16);
+    let data_start = image
+        .symbols
+        .iter()
+        .map(|(_, range)| range.start)
+        .filter(|start| *start >= image.executable_len)
+        .min();
+    let rodata = data_start.map(|start| {
+        let rodata = obj.section_id(write::StandardSection::ReadOnlyData);
+        obj.append_section_data(rodata, &image.bytes[start..], 64);
+        (rodata, start)
+    });
+    let place = |offset: usize| match rodata {
+        Some((rodata, start)) if offset >= start => (rodata, offset - start),
+        _ => (text, offset),
+    };
+
+    let mut symbols = HashMap::new();
+    for (symbol, range) in image.symbols.iter() {
+        let (section, offset) = place(range.start);
+        let kind = match symbol {
+            Symbol::Section(_) if section != text => SymbolKind::Data,
+            _ => SymbolKind::Text,
+        };
+        let id = obj.add_symbol(write::Symbol {
+            name: symbol_name(symbol).into_bytes(),
+            value: offset as u64,
+            size: range.len() as u64,
+            kind,
+            scope: SymbolScope::Compilation,
+            weak: false,
+            section: SymbolSection::Section(section),
+            flags: SymbolFlags::None,
+        });
+        symbols.insert(symbol.clone(), id);
+    }
+
+    // The libcalls are called through stubs, in the order they are
+    // first referred to, which jump to the addresses in their slots.
+    let (stub, stub_relocations) = libcall_stub(architecture);
+    let stubs_section = obj.add_section(
+        Vec::new(),
+        LIBCALLS_SECTION.as_bytes().to_vec(),
+        SectionKind::Text,
+    );
+    let mut libcalls = Vec::new();
+    let mut stubs = HashMap::new();
+    for relocation in image.relocations.iter() {
+        if let RelocationTarget::LibCall(libcall) = relocation.reloc_target {
+            stubs.entry(libcall).or_insert_with(|| {
+                let offset = obj.append_section_data(stubs_section, stub, 16);
+                libcalls.push((libcall, offset));
+                obj.add_symbol(write::Symbol {
+                    name: format!("{}_stub", libcall.to_function_name()).into_bytes(),
+                    value: offset,
+                    size: stub.len() as u64,
+                    kind: SymbolKind::Text,
+                    scope: SymbolScope::Compilation,
+                    weak: false,
+                    section: SymbolSection::Section(stubs_section),
+                    flags: SymbolFlags::None,
+                })
+            });
+        }
+    }
+
+    for relocation in image.relocations.iter() {
+        let r_type = match relocation_type(architecture, relocation.kind)? {
+            Some(r_type) => r_type,
+            None => continue,
+        };
+        let symbol = match relocation.reloc_target {
+            RelocationTarget::LocalFunc(index) => symbols[&Symbol::LocalFunction(index)],
+            RelocationTarget::CustomSection(index) => symbols[&Symbol::Section(index)],
+            RelocationTarget::LibCall(libcall) => stubs[&libcall],
+            RelocationTarget::JumpTable(..) => {
+                unreachable!("the code image targets the functions of the jump tables")
+            }
+        };
+        let (section, offset) = place(relocation.offset as usize);
+        add_relocation(
+            &mut obj,
+            section,
+            offset as u64,
+            symbol,
+            relocation.addend,
+            r_type,
+        )?;
+    }
+
+    // The archive of the executable is aligned like its header, so
+    // that it can be used in place once the section is loaded.
+    let metadata_section = obj.add_section(
+        Vec::new(),
+        METADATA_SECTION.as_bytes().to_vec(),
+        SectionKind::ReadOnlyData,
+    );
+    let metadata_offset = obj.append_section_data(metadata_section, metadata, 16);
+    obj.add_symbol(write::Symbol {
+        name: METADATA_SYMBOL.as_bytes().to_vec(),
+        value: metadata_offset,
+        size: metadata.len() as u64,
+        kind: SymbolKind::Data,
+        scope: SymbolScope::Dynamic,
+        weak: false,
+        section: SymbolSection::Section(metadata_section),
+        flags: SymbolFlags::None,
+    });
+
+    // The descriptor of the linked code: the addresses of the metadata,
+    // of the code and of the data of the image, and the slots of the
+    // libcalls, which hold the addresses of their names, followed by
+    // the names.
+    let address = address_relocation_type(architecture);
+    let mut code = vec![0; CODE_HEADER_LENGTH + libcalls.len() * LIBCALL_SLOT_LENGTH];
+    code[8..16].copy_from_slice(&(metadata.len() as u64).to_le_bytes());
+    code[32..40].copy_from_slice(&(libcalls.len() as u64).to_le_bytes());
+    let mut names = Vec::new();
+    for (libcall, _) in libcalls.iter() {
+        names.push(code.len());
+        code.extend(libcall.to_function_name().as_bytes());
+        code.push(0);
+    }
+    let data = obj.section_id(write::StandardSection::Data);
+    let code_offset = obj.append_section_data(data, &code, 16);
+    obj.add_symbol(write::Symbol {
+        name: CODE_SYMBOL.as_bytes().to_vec(),
+        value: code_offset,
+        size: code.len() as u64,
+        kind: SymbolKind::Data,
+        scope: SymbolScope::Dynamic,
+        weak: false,
+        section: SymbolSection::Section(data),
+        flags: SymbolFlags::None,
+    });
+    let data_symbol = obj.section_symbol(data);
+    let mut addresses = vec![
+        (0, obj.section_symbol(metadata_section), metadata_offset),
+        (16, obj.section_symbol(text), 0),
+    ];
+    if let Some((rodata, _)) = rodata {
+        addresses.push((24, obj.section_symbol(rodata), 0));
+    }
+    for (index, name) in names.into_iter().enumerate() {
+        let slot = CODE_HEADER_LENGTH + index * LIBCALL_SLOT_LENGTH;
+        addresses.push((slot, data_symbol, code_offset + name as u64));
+    }
+    for (offset, symbol, addend) in addresses {
+        let offset = code_offset + offset as u64;
+        add_relocation(&mut obj, data, offset, symbol, addend as i64, address)?;
+    }
+    for (index, (_, stub_offset)) in libcalls.iter().enumerate() {
+        let slot = code_offset + (CODE_HEADER_LENGTH + index * LIBCALL_SLOT_LENGTH + 8) as u64;
+        for (offset, r_type, addend) in stub_relocations {
+            let offset = stub_offset + offset;
+            let addend = slot as i64 + addend;
+            add_relocation(
+                &mut obj,
+                stubs_section,
+                offset,
+                data_symbol,
+                addend,
+                *r_type,
+            )?;
+        }
+    }
+
+    // Without this section, linkers assume the object needs an
+    // executable stack.
+    obj.add_section(Vec::new(), b".note.GNU-stack".to_vec(), SectionKind::Note);
+    obj.write()
+        .map_err(|e| CompileError::Codegen(e.to_string()))
+}
+
+/// Link the relocatable object file `object` into the shared object
+/// `output` with the system linker, the C compiler named by `$CC`,
+/// or `cc` by default.
+pub(crate) fn link_shared_object(
+    object: &Path,
+    output: &Path,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let cc = env::var_os("CC").unwrap_or_else(|| "cc".into());
+    let result = Command::new(cc)
+        .arg("-shared")
+        .arg("-nostdlib")
+        .arg(object)
+        .arg("-o")
+        .arg(output)
+        .output()?;
+    if !result.status.success() {
+        return Err(format!(
+            "linking the shared object failed: {}",
+            String::from_utf8_lossy(&result.stderr)
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn parse(bytes: &[u8]) -> Result<File<'_>, DeserializeError> {
+    File::parse(bytes).map_err(|e| DeserializeError::Incompatible(e.to_string()))
+}
+
+/// The kind of the object file `bytes`.
+pub fn read_kind(bytes: &[u8]) -> Result<ObjectKind, DeserializeError> {
+    match parse(bytes)?.kind() {
+        object::ObjectKind::Relocatable => Ok(ObjectKind::Relocatable),
+        object::ObjectKind::Dynamic => Ok(ObjectKind::SharedObject),
+        kind => Err(DeserializeError::Incompatible(format!(
+            "the object file is {:?}, not relocatable or shared",
+            kind
+        ))),
+    }
+}
+
+/// Find the executable code and the data sections of the code image in
+/// the relocatable object file `bytes`, as they are before they are
+/// relocated.
+pub(crate) fn read_code_image(bytes: &[u8]) -> Result<(&[u8], &[u8]), DeserializeError> {
+    let file = parse(bytes)?;
+    let section = |name| match file.section_by_name(name) {
+        Some(section) => section
+            .data()
+            .map_err(|e| DeserializeError::CorruptedBinary(e.to_string())),
+        None => Ok(&[][..]),
+    };
+    Ok((section(".text")?, section(".rodata")?))
+}
+
+/// Find the serialized executable, without its code image, in the
+/// object file, relocatable or shared, `bytes`.
+pub fn read_metadata(bytes: &[u8]) -> Result<&[u8], DeserializeError> {
+    let file = parse(bytes)?;
+    let symbol = file
+        .symbols()
+        .chain(file.dynamic_symbols())
+        .find(|symbol| symbol.name() == Ok(METADATA_SYMBOL))
+        .ok_or_else(|| {
+            DeserializeError::Incompatible(format!(
+                "the object file has no `{}` symbol",
+                METADATA_SYMBOL
+            ))
+        })?;
+    let section = symbol
+        .section_index()
+        .and_then(|index| file.section_by_index(index).ok())
+        .ok_or_else(|| {
+            DeserializeError::CorruptedBinary(format!("`{}` is not defined", METADATA_SYMBOL))
+        })?;
+    section
+        .data_range(symbol.address(), symbol.size())
+        .ok()
+        .flatten()
+        .ok_or_else(|| {
+            DeserializeError::CorruptedBinary(format!(
+                "`{}` is out of the bounds of its section",
+                METADATA_SYMBOL
+            ))
+        })
+}
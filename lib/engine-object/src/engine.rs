@@ -0,0 +1,189 @@
+//! Object file compilation.
+
+use crate::elf::{object_architecture, read_code_image, read_kind, read_metadata};
+#[cfg(unix)]
+use crate::linked::{load_linked, SharedObject};
+use crate::{ObjectFileExecutable, ObjectKind};
+#[cfg(unix)]
+use std::path::Path;
+use std::sync::Arc;
+use wasmer_compiler::{CompileError, Target};
+use wasmer_engine::{DeserializeError, Engine, EngineId, Executable};
+use wasmer_engine_universal::{UniversalArtifact, UniversalEngine, UniversalExecutableRef};
+use wasmer_types::{FunctionType, FunctionTypeRef};
+use wasmer_vm::{Artifact, Tunables, VMCallerCheckedAnyfunc, VMFuncRef, VMSharedSignatureIndex};
+
+/// A WebAssembly engine writing the compiled modules as native object
+/// files.
+///
+/// The modules are compiled by a [`UniversalEngine`]. The object files
+/// hold their code as native code, and the rest of them as serialized
+/// [`UniversalExecutable`]s without their code images. The modules of
+/// the shared objects and of the object files linked into programs run
+/// the code the system linker and loader relocated, while relocatable
+/// object files are linked into the memory of the universal engine.
+#[derive(Clone)]
+pub struct ObjectFileEngine {
+    universal: UniversalEngine,
+    kind: ObjectKind,
+}
+
+impl ObjectFileEngine {
+    /// Create a new `ObjectFileEngine` compiling with `universal`, and
+    /// writing object files of the given kind.
+    pub fn new(universal: UniversalEngine, kind: ObjectKind) -> Self {
+        Self { universal, kind }
+    }
+
+    /// The universal engine the modules are compiled and loaded with.
+    pub fn universal(&self) -> &UniversalEngine {
+        &self.universal
+    }
+
+    /// The kind of object files written by this engine.
+    pub fn kind(&self) -> ObjectKind {
+        self.kind
+    }
+
+    /// Compile a WebAssembly binary into an object file executable.
+    #[cfg(feature = "compiler")]
+    pub fn compile_object_file(
+        &self,
+        binary: &[u8],
+        tunables: &dyn Tunables,
+    ) -> Result<ObjectFileExecutable, CompileError> {
+        let architecture = object_architecture(self.target().triple())?;
+        let executable = self.universal.compile_universal(binary, tunables)?;
+        Ok(ObjectFileExecutable {
+            executable,
+            architecture,
+            kind: self.kind,
+        })
+    }
+
+    /// Load the module of a relocatable object file written by an
+    /// `ObjectFileEngine`.
+    ///
+    /// The engine links the code of the object file itself, like it
+    /// links the code of any other serialized executable, which is
+    /// checked like it is by
+    /// [`UniversalEngine::load_universal_executable_ref`]. Shared objects
+    /// are loaded by the dynamic loader instead, see
+    /// [`ObjectFileEngine::load_shared_object`].
+    ///
+    /// # Safety
+    ///
+    /// The serialized module is not validated: `bytes` must come from
+    /// a trusted source.
+    pub unsafe fn load_object_file(
+        &self,
+        bytes: &[u8],
+        tunables: &dyn Tunables,
+    ) -> Result<UniversalArtifact, DeserializeError> {
+        if read_kind(bytes)? != ObjectKind::Relocatable {
+            return Err(DeserializeError::Incompatible(
+                "shared objects are loaded from their files".to_string(),
+            ));
+        }
+        let metadata = read_metadata(bytes)?;
+        // The archive has to be aligned, which the bytes of the object
+        // file may not be.
+        let mut aligned = rkyv::AlignedVec::with_capacity(metadata.len());
+        aligned.extend_from_slice(metadata);
+        let metadata = unsafe { UniversalExecutableRef::deserialize(&aligned)? };
+        let (text, data) = read_code_image(bytes)?;
+        let serialized = metadata.with_code_image(text, data)?;
+        let executable = unsafe { UniversalExecutableRef::deserialize(&serialized)? };
+        self.universal
+            .load_universal_executable_ref(&executable, tunables)
+    }
+
+    /// Load the module of the shared object at `path`, written by an
+    /// `ObjectFileEngine`.
+    ///
+    /// The shared object is opened with `dlopen`, and the code of the
+    /// module is used where the dynamic loader placed it, see
+    /// [`load_linked_object_file`](crate::load_linked_object_file). The
+    /// shared object stays open for as long as the module is loaded.
+    ///
+    /// # Safety
+    ///
+    /// Opening the shared object runs its initializers, and its module is
+    /// neither validated nor checked against its hash: `path` must come
+    /// from a trusted source.
+    #[cfg(unix)]
+    pub unsafe fn load_shared_object(
+        &self,
+        path: impl AsRef<Path>,
+        tunables: &dyn Tunables,
+    ) -> Result<UniversalArtifact, DeserializeError> {
+        let shared_object = SharedObject::open(path.as_ref())?;
+        let code = shared_object.code()?;
+        unsafe { load_linked(&self.universal, code, Box::new(shared_object), tunables) }
+    }
+}
+
+impl Engine for ObjectFileEngine {
+    /// The target
+    fn target(&self) -> &Target {
+        self.universal.target()
+    }
+
+    /// Register a signature
+    fn register_signature(&self, func_type: FunctionTypeRef<'_>) -> VMSharedSignatureIndex {
+        self.universal.register_signature(func_type)
+    }
+
+    fn register_function_metadata(&self, func_data: VMCallerCheckedAnyfunc) -> VMFuncRef {
+        self.universal.register_function_metadata(func_data)
+    }
+
+    /// Lookup a signature
+    fn lookup_signature(&self, sig: VMSharedSignatureIndex) -> Option<FunctionType> {
+        self.universal.lookup_signature(sig)
+    }
+
+    /// Validates a WebAssembly module
+    fn validate(&self, binary: &[u8]) -> Result<(), CompileError> {
+        self.universal.validate(binary)
+    }
+
+    #[cfg(not(feature = "compiler"))]
+    fn compile(
+        &self,
+        _binary: &[u8],
+        _tunables: &dyn Tunables,
+    ) -> Result<Box<dyn Executable>, CompileError> {
+        Err(CompileError::Codegen(
+            "The ObjectFileEngine is operating in headless mode, so it can not compile Modules."
+                .to_string(),
+        ))
+    }
+
+    /// Compile a WebAssembly binary
+    #[cfg(feature = "compiler")]
+    fn compile(
+        &self,
+        binary: &[u8],
+        tunables: &dyn Tunables,
+    ) -> Result<Box<dyn Executable>, CompileError> {
+        self.compile_object_file(binary, tunables)
+            .map(|ex| Box::new(ex) as _)
+    }
+
+    fn load(
+        &self,
+        executable: &dyn Executable,
+        tunables: &dyn Tunables,
+    ) -> Result<Arc<dyn Artifact>, DeserializeError> {
+        executable.load(self, tunables)
+    }
+
+    fn id(&self) -> &EngineId {
+        self.universal.id()
+    }
+
+    fn cloned(&self) -> Arc<dyn Engine + Send + Sync> {
+        Arc::new(self.clone())
+    }
+}
@@ -0,0 +1,99 @@
+//! Define `ObjectFileExecutable`, the modules compiled by the
+//! `ObjectFileEngine`.
+
+use crate::elf::{link_shared_object, write_relocatable};
+use crate::{ObjectFileEngine, ObjectKind};
+use enumset::EnumSet;
+use std::sync::Arc;
+use wasmer_compiler::{CompileError, CpuFeature, Features};
+use wasmer_engine::{DeserializeError, Engine, Executable};
+use wasmer_engine_universal::{UniversalEngine, UniversalExecutable, UniversalExecutableRef};
+use wasmer_types::FunctionIndex;
+use wasmer_vm::{Artifact, Tunables};
+
+/// A module compiled by the [`ObjectFileEngine`], serialized as an
+/// object file.
+pub struct ObjectFileExecutable {
+    pub(crate) executable: UniversalExecutable,
+    /// The architecture of the object file.
+    pub(crate) architecture: object::Architecture,
+    pub(crate) kind: ObjectKind,
+}
+
+impl ObjectFileExecutable {
+    /// The compiled module, as it is stored in the object file.
+    pub fn executable(&self) -> &UniversalExecutable {
+        &self.executable
+    }
+
+    /// The kind of object file the module is serialized to.
+    pub fn kind(&self) -> ObjectKind {
+        self.kind
+    }
+
+    /// Write the relocatable object file of the module.
+    ///
+    /// This is the object file serialized for
+    /// [`ObjectKind::Relocatable`], and the one linked into the shared
+    /// object for [`ObjectKind::SharedObject`].
+    pub fn relocatable(&self) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        // The code is only in the sections of the object file, not in
+        // the serialized executable.
+        let serialized = self.executable.serialize()?;
+        let mut aligned = rkyv::AlignedVec::with_capacity(serialized.len());
+        aligned.extend_from_slice(&serialized);
+        let metadata = unsafe { UniversalExecutableRef::deserialize(&aligned)? };
+        Ok(write_relocatable(
+            self.architecture,
+            &self.executable.code_image(),
+            &metadata.without_code_image(),
+        )?)
+    }
+}
+
+impl Executable for ObjectFileExecutable {
+    fn load(
+        &self,
+        engine: &(dyn Engine + 'static),
+        _tunables: &dyn Tunables,
+    ) -> Result<Arc<dyn Artifact>, DeserializeError> {
+        let universal = if let Some(engine) = engine.downcast_ref::<ObjectFileEngine>() {
+            engine.universal()
+        } else {
+            engine
+                .downcast_ref::<UniversalEngine>()
+                .ok_or(DeserializeError::Compiler(CompileError::EngineDowncast))?
+        };
+        universal
+            .load_universal_executable(&self.executable)
+            .map(|a| Arc::new(a) as _)
+            .map_err(DeserializeError::Compiler)
+    }
+
+    fn features(&self) -> Features {
+        self.executable.features()
+    }
+
+    fn cpu_features(&self) -> EnumSet<CpuFeature> {
+        self.executable.cpu_features()
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        let relocatable = self.relocatable()?;
+        match self.kind {
+            ObjectKind::Relocatable => Ok(relocatable),
+            ObjectKind::SharedObject => {
+                let dir = tempfile::tempdir()?;
+                let object = dir.path().join("module.o");
+                let shared_object = dir.path().join("module.so");
+                std::fs::write(&object, relocatable)?;
+                link_shared_object(&object, &shared_object)?;
+                Ok(std::fs::read(&shared_object)?)
+            }
+        }
+    }
+
+    fn function_name(&self, index: FunctionIndex) -> Option<&str> {
+        self.executable.function_name(index)
+    }
+}
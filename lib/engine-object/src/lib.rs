@@ -0,0 +1,67 @@
+//! Object file backend for Wasmer compilers.
+//!
+//! Given a compiler (such as `SinglepassCompiler` or `CraneliftCompiler`)
+//! it compiles the modules with the universal engine, and serializes
+//! them as native ELF object files: relocatable objects (`.o`) to be
+//! linked into static libraries and executables, or shared objects
+//! (`.so`). The object files are loaded back into the universal engine,
+//! which runs their code where it is linked.
+
+#![deny(missing_docs, trivial_numeric_casts, unused_extern_crates)]
+#![warn(unused_import_braces)]
+#![warn(unsafe_op_in_unsafe_fn)]
+#![cfg_attr(
+    feature = "cargo-clippy",
+    allow(clippy::new_without_default, clippy::new_without_default)
+)]
+#![cfg_attr(
+    feature = "cargo-clippy",
+    warn(
+        clippy::float_arithmetic,
+        clippy::mut_mut,
+        clippy::nonminimal_bool,
+        clippy::option_map_unwrap_or,
+        clippy::option_map_unwrap_or_else,
+        clippy::print_stdout,
+        clippy::unicode_not_nfc,
+        clippy::use_self
+    )
+)]
+
+mod builder;
+mod elf;
+mod engine;
+mod executable;
+mod linked;
+
+pub use crate::builder::ObjectFile;
+pub use crate::elf::{
+    read_kind, read_metadata, symbol_name, CODE_SYMBOL, METADATA_SECTION, METADATA_SYMBOL,
+};
+pub use crate::engine::ObjectFileEngine;
+pub use crate::executable::ObjectFileExecutable;
+pub use crate::linked::load_linked_object_file;
+
+/// The kind of object files written by an [`ObjectFileEngine`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    /// A relocatable object file, to be linked into a static library
+    /// or an executable.
+    Relocatable,
+    /// A shared object, linked from the relocatable object file by the
+    /// system linker, `$CC` or `cc`.
+    SharedObject,
+}
+
+impl ObjectKind {
+    /// The extension of the files of this kind.
+    pub fn default_extension(&self) -> &'static str {
+        match self {
+            Self::Relocatable => "o",
+            Self::SharedObject => "so",
+        }
+    }
+}
+
+/// Version number of this crate.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
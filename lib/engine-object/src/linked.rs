@@ -0,0 +1,162 @@
+//! Loading the modules of the object files linked into programs and
+//! shared objects, whose code is used where the system linker and
+//! loader placed it.
+
+use crate::elf::{CODE_HEADER_LENGTH, CODE_SYMBOL, LIBCALL_SLOT_LENGTH};
+use std::any::Any;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use wasmer_engine::DeserializeError;
+use wasmer_engine_universal::{
+    LinkedCode, UniversalArtifact, UniversalEngine, UniversalExecutableRef,
+};
+use wasmer_vm::Tunables;
+
+/// The descriptor of the code of an object file, `WASMER_CODE`, once it
+/// is linked. Its libcall slots follow it.
+#[repr(C)]
+struct ObjectCode {
+    metadata: *const u8,
+    metadata_len: u64,
+    text: *const u8,
+    data: *const u8,
+    libcall_count: u64,
+}
+
+/// The slot of a libcall in the descriptor of the code, holding the
+/// address its stub jumps to.
+#[repr(C)]
+struct LibCallSlot {
+    name: *const c_char,
+    address: AtomicUsize,
+}
+
+/// Load the module of an object file written by an
+/// [`ObjectFileEngine`](crate::ObjectFileEngine) and linked into the
+/// program, from the address of its `WASMER_CODE` symbol, with `engine`.
+///
+/// The code of the module is used where it is linked, see
+/// [`UniversalEngine::load_universal_executable_linked`].
+///
+/// # Safety
+///
+/// `code` must be the address of the `WASMER_CODE` symbol of an object
+/// file linked into the program, which is neither validated nor checked
+/// against its hash.
+pub unsafe fn load_linked_object_file(
+    engine: &UniversalEngine,
+    code: *const u8,
+    tunables: &dyn Tunables,
+) -> Result<UniversalArtifact, DeserializeError> {
+    unsafe { load_linked(engine, code, Box::new(()), tunables) }
+}
+
+/// Load the module of the linked object file whose `WASMER_CODE` is at
+/// `code`, which `owner` keeps loaded.
+pub(crate) unsafe fn load_linked(
+    engine: &UniversalEngine,
+    code: *const u8,
+    owner: Box<dyn Any + Send + Sync>,
+    tunables: &dyn Tunables,
+) -> Result<UniversalArtifact, DeserializeError> {
+    debug_assert_eq!(std::mem::size_of::<ObjectCode>(), CODE_HEADER_LENGTH);
+    debug_assert_eq!(std::mem::size_of::<LibCallSlot>(), LIBCALL_SLOT_LENGTH);
+    let header = unsafe { &*(code as *const ObjectCode) };
+    // The archive is used in place, like it is aligned in its section.
+    if header.metadata as usize % 16 != 0 {
+        return Err(DeserializeError::CorruptedBinary(
+            "the serialized executable is not aligned".to_string(),
+        ));
+    }
+    let metadata =
+        unsafe { std::slice::from_raw_parts(header.metadata, header.metadata_len as usize) };
+    let executable = unsafe { UniversalExecutableRef::deserialize(metadata)? };
+
+    // Loading the module again writes the same addresses.
+    let libcalls = executable.libcalls();
+    let slots = unsafe {
+        std::slice::from_raw_parts(
+            code.add(CODE_HEADER_LENGTH) as *const LibCallSlot,
+            header.libcall_count as usize,
+        )
+    };
+    for slot in slots {
+        let name = unsafe { CStr::from_ptr(slot.name) }.to_string_lossy();
+        let libcall = libcalls
+            .iter()
+            .find(|libcall| libcall.to_function_name() == name)
+            .ok_or_else(|| {
+                DeserializeError::CorruptedBinary(format!(
+                    "the code calls `{}`, which the executable doesn't",
+                    name
+                ))
+            })?;
+        slot.address
+            .store(libcall.function_pointer(), Ordering::Relaxed);
+    }
+
+    let code = LinkedCode {
+        text: header.text,
+        data: header.data,
+        owner,
+    };
+    unsafe { engine.load_universal_executable_linked(&executable, code, tunables) }
+}
+
+/// A shared object opened with `dlopen`, and closed once dropped.
+#[cfg(unix)]
+pub(crate) struct SharedObject(*mut std::os::raw::c_void);
+
+// The handles of `dlopen` can be used and closed from any thread.
+#[cfg(unix)]
+unsafe impl Send for SharedObject {}
+#[cfg(unix)]
+unsafe impl Sync for SharedObject {}
+
+#[cfg(unix)]
+impl SharedObject {
+    /// Open the shared object at `path`, resolving all its symbols.
+    pub(crate) fn open(path: &std::path::Path) -> Result<Self, DeserializeError> {
+        use std::os::unix::ffi::OsStrExt;
+        let path = std::ffi::CString::new(path.as_os_str().as_bytes())
+            .map_err(|e| DeserializeError::Generic(e.to_string()))?;
+        let handle = unsafe { libc::dlopen(path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+        if handle.is_null() {
+            return Err(DeserializeError::Generic(dlerror()));
+        }
+        Ok(Self(handle))
+    }
+
+    /// The address of the `WASMER_CODE` symbol of the shared object.
+    pub(crate) fn code(&self) -> Result<*const u8, DeserializeError> {
+        let name = std::ffi::CString::new(CODE_SYMBOL).unwrap();
+        let code = unsafe { libc::dlsym(self.0, name.as_ptr()) };
+        if code.is_null() {
+            return Err(DeserializeError::Incompatible(format!(
+                "the shared object has no `{}` symbol",
+                CODE_SYMBOL
+            )));
+        }
+        Ok(code as *const u8)
+    }
+}
+
+#[cfg(unix)]
+impl Drop for SharedObject {
+    fn drop(&mut self) {
+        unsafe { libc::dlclose(self.0) };
+    }
+}
+
+/// The message of the last error of `dlopen`.
+#[cfg(unix)]
+fn dlerror() -> String {
+    let message = unsafe { libc::dlerror() };
+    if message.is_null() {
+        return "the shared object could not be opened".to_string();
+    }
+    unsafe { CStr::from_ptr(message) }
+        .to_string_lossy()
+        .into_owned()
+}
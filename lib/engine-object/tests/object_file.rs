@@ -0,0 +1,223 @@
+use object::{Object, ObjectSection, ObjectSymbol, RelocationTarget};
+use std::sync::Arc;
+use wasmer::{
+    imports, wat2wasm, BaseTunables, CompilerConfig, CpuFeature, Cranelift, Engine, Instance,
+    Module, Singlepass, Store, Target, UniversalArtifact, Value,
+};
+use wasmer_engine_object::{read_metadata, ObjectFile, ObjectFileEngine, ObjectKind};
+use wasmer_engine_universal::UniversalExecutableRef;
+use wasmer_types::LocalFunctionIndex;
+
+const WAT: &str = r#"
+    (module
+        (memory (export "memory") 1)
+        (data (i32.const 16) "object")
+        (func $add (export "add") (param i32 i32) (result i32)
+            (i32.add (local.get 0) (local.get 1)))
+        (func (export "double") (param i32) (result i32)
+            (call $add (local.get 0) (local.get 0)))
+        (func (export "load") (result i32)
+            (i32.load8_u (i32.const 16))))
+"#;
+
+fn compile(kind: ObjectKind) -> Vec<u8> {
+    compile_with(Singlepass::default(), Target::default(), kind, WAT)
+}
+
+fn compile_with(
+    compiler: impl CompilerConfig + 'static,
+    target: Target,
+    kind: ObjectKind,
+    wat: &str,
+) -> Vec<u8> {
+    let engine = ObjectFile::new(compiler).target(target).kind(kind).engine();
+    let tunables = BaseTunables::for_target(engine.target());
+    let wasm = wat2wasm(wat.as_bytes()).unwrap();
+    let executable = engine.compile(&wasm, &tunables).unwrap();
+    executable.serialize().unwrap()
+}
+
+fn run(bytes: &[u8]) {
+    let engine = ObjectFile::headless().engine();
+    let artifact =
+        unsafe { engine.load_object_file(bytes, &BaseTunables::for_target(engine.target())) };
+    check(&engine, artifact.unwrap());
+}
+
+fn check(engine: &ObjectFileEngine, artifact: UniversalArtifact) {
+    let store = Store::new(engine);
+    let module = Module::from_universal_artifact(&store, Arc::new(artifact));
+    let instance = Instance::new(&module, &imports! {}).unwrap();
+    let add = instance.exports().get_function("add").unwrap();
+    assert_eq!(
+        &*add.call(&[Value::I32(40), Value::I32(2)]).unwrap(),
+        &[Value::I32(42)]
+    );
+    let double = instance.exports().get_function("double").unwrap();
+    assert_eq!(&*double.call(&[Value::I32(21)]).unwrap(), &[Value::I32(42)]);
+    let load = instance.exports().get_function("load").unwrap();
+    assert_eq!(&*load.call(&[]).unwrap(), &[Value::I32(i32::from(b'o'))]);
+}
+
+/// Write the shared object `bytes` to a file, and load its module with
+/// `engine`.
+#[cfg(target_os = "linux")]
+fn load_shared_object(
+    engine: &ObjectFileEngine,
+    bytes: &[u8],
+) -> (tempfile::TempDir, std::path::PathBuf, UniversalArtifact) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("module.so");
+    std::fs::write(&path, bytes).unwrap();
+    let tunables = BaseTunables::for_target(engine.target());
+    let artifact = unsafe { engine.load_shared_object(&path, &tunables).unwrap() };
+    (dir, path, artifact)
+}
+
+#[test]
+fn relocatable_object_file() {
+    let bytes = compile(ObjectKind::Relocatable);
+    assert_eq!(&bytes[..4], b"\x7fELF");
+    assert!(!read_metadata(&bytes).unwrap().is_empty());
+    run(&bytes);
+}
+
+#[test]
+fn relocatable_object_file_holds_the_code() {
+    let bytes = compile(ObjectKind::Relocatable);
+    let file = object::File::parse(&*bytes).unwrap();
+    let text = file.section_by_name(".text").unwrap();
+    let add = file.symbol_by_name("wasmer_function_0").unwrap();
+    let double = file.symbol_by_name("wasmer_function_1").unwrap();
+    assert_eq!(add.section_index(), Some(text.index()));
+    assert_eq!(double.section_index(), Some(text.index()));
+    assert!(add.size() > 0);
+    assert!(text.size() >= add.address() + add.size());
+
+    // The call to `add` is relocated by the linker.
+    let calls = text
+        .relocations()
+        .filter(|(offset, relocation)| {
+            (double.address()..double.address() + double.size()).contains(offset)
+                && relocation.target() == RelocationTarget::Symbol(add.index())
+        })
+        .count();
+    assert_eq!(calls, 1);
+}
+
+#[test]
+fn relocatable_object_file_holds_the_code_once() {
+    let bytes = compile(ObjectKind::Relocatable);
+    let file = object::File::parse(&*bytes).unwrap();
+    assert!(file.symbols().all(|symbol| !symbol.is_undefined()));
+
+    // The serialized executable doesn't hold the code image, and can't
+    // be loaded without it.
+    let metadata = read_metadata(&bytes).unwrap();
+    let mut aligned = rkyv::AlignedVec::new();
+    aligned.extend_from_slice(metadata);
+    let executable = unsafe { UniversalExecutableRef::deserialize(&aligned).unwrap() };
+    assert!(executable.check_code_image().is_err());
+    let engine = ObjectFile::headless().engine();
+    let tunables = BaseTunables::for_target(engine.target());
+    assert!(engine
+        .universal()
+        .load_universal_executable_ref(&executable, &tunables)
+        .is_err());
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn shared_object() {
+    let bytes = compile(ObjectKind::SharedObject);
+    assert_eq!(&bytes[..4], b"\x7fELF");
+    let engine = ObjectFile::headless().engine();
+    let tunables = BaseTunables::for_target(engine.target());
+    assert!(unsafe { engine.load_object_file(&bytes, &tunables) }.is_err());
+    let (_dir, path, artifact) = load_shared_object(&engine, &bytes);
+
+    // The functions run where the dynamic loader mapped them.
+    let add = artifact
+        .function_extent(LocalFunctionIndex::from_u32(0))
+        .unwrap();
+    let address = *add.address as usize;
+    let maps = std::fs::read_to_string("/proc/self/maps").unwrap();
+    let mapping = maps
+        .lines()
+        .find(|line| {
+            let range = line.split(' ').next().unwrap();
+            let (start, end) = range.split_once('-').unwrap();
+            let start = usize::from_str_radix(start, 16).unwrap();
+            let end = usize::from_str_radix(end, 16).unwrap();
+            (start..end).contains(&address)
+        })
+        .unwrap();
+    assert!(mapping.ends_with(path.to_str().unwrap()), "{}", mapping);
+    check(&engine, artifact);
+}
+
+const LIBCALLS_WAT: &str = r#"
+    (module
+        (func (export "ceil") (param f64) (result f64)
+            (f64.ceil (local.get 0))))
+"#;
+
+/// Compile `LIBCALLS_WAT` with Cranelift for x86-64 without SSE4.1, for
+/// `f64.ceil` to call a libcall.
+#[cfg(target_arch = "x86_64")]
+fn compile_libcalls(kind: ObjectKind) -> Vec<u8> {
+    let triple = Target::default().triple().clone();
+    let target = Target::new(triple, CpuFeature::SSE2 | CpuFeature::SSE3);
+    compile_with(Cranelift::default(), target, kind, LIBCALLS_WAT)
+}
+
+#[cfg(target_arch = "x86_64")]
+fn check_libcalls(engine: &ObjectFileEngine, artifact: UniversalArtifact) {
+    let store = Store::new(engine);
+    let module = Module::from_universal_artifact(&store, Arc::new(artifact));
+    let instance = Instance::new(&module, &imports! {}).unwrap();
+    let ceil = instance.exports().get_function("ceil").unwrap();
+    assert_eq!(&*ceil.call(&[Value::F64(1.5)]).unwrap(), &[Value::F64(2.0)]);
+}
+
+#[test]
+#[cfg(target_arch = "x86_64")]
+fn relocatable_object_file_calls_libcalls_through_stubs() {
+    let bytes = compile_libcalls(ObjectKind::Relocatable);
+    let file = object::File::parse(&*bytes).unwrap();
+    assert!(file.symbols().all(|symbol| !symbol.is_undefined()));
+    let stub = file.symbol_by_name("wasmer_vm_f64_ceil_stub").unwrap();
+    let stubs = file.section_by_name(".text.wasmer_libcalls").unwrap();
+    assert_eq!(stub.section_index(), Some(stubs.index()));
+
+    let engine = ObjectFile::headless().engine();
+    let tunables = BaseTunables::for_target(engine.target());
+    let artifact = unsafe { engine.load_object_file(&bytes, &tunables).unwrap() };
+    check_libcalls(&engine, artifact);
+}
+
+#[test]
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+fn shared_object_calls_libcalls() {
+    let bytes = compile_libcalls(ObjectKind::SharedObject);
+    let engine = ObjectFile::headless().engine();
+    let (_dir, _path, artifact) = load_shared_object(&engine, &bytes);
+    check_libcalls(&engine, artifact);
+}
+
+#[test]
+fn load_executable_of_the_engine() {
+    let engine: ObjectFileEngine = ObjectFile::new(Singlepass::default()).engine();
+    let store = Store::new(&engine);
+    let wasm = wat2wasm(WAT.as_bytes()).unwrap();
+    let executable = engine.compile(&wasm, store.tunables()).unwrap();
+    let artifact = engine.load(&*executable, store.tunables()).unwrap();
+    assert!(artifact.downcast_arc::<wasmer::UniversalArtifact>().is_ok());
+}
+
+#[test]
+fn not_an_object_file() {
+    let engine = ObjectFile::headless().engine();
+    let tunables = BaseTunables::for_target(engine.target());
+    assert!(unsafe { engine.load_object_file(b"\0asm\x01\0\0\0", &tunables) }.is_err());
+}
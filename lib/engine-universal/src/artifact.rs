@@ -4,17 +4,17 @@
 use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::sync::Arc;
-use wasmer_engine::InstantiationError;
+use wasmer_engine::{GlobalFrameInfoRegistration, InstantiationError};
 use wasmer_types::entity::{BoxedSlice, EntityRef, PrimaryMap};
 use wasmer_types::{
     DataIndex, ElemIndex, FunctionIndex, GlobalInit, GlobalType, ImportCounts, LocalFunctionIndex,
-    LocalGlobalIndex, MemoryType, OwnedDataInitializer, OwnedTableInitializer, SignatureIndex,
-    TableType,
+    LocalGlobalIndex, LocalMemoryIndex, LocalTagIndex, MemoryType, OwnedDataInitializer,
+    OwnedTableInitializer, SignatureIndex, TableType, TagType,
 };
 use wasmer_vm::{
-    Artifact, FunctionBodyPtr, FunctionExtent, InstanceHandle, Instantiatable, MemoryStyle,
-    Resolver, TableStyle, Tunables, VMImport, VMImportType, VMLocalFunction, VMOffsets,
-    VMSharedSignatureIndex,
+    Artifact, Epoch, FunctionBodyPtr, FunctionExtent, InstanceHandle, Instantiatable, MemoryImage,
+    MemoryStyle, Resolver, TableStyle, Tunables, VMImport, VMImportType, VMLocalFunction,
+    VMOffsets, VMSharedSignatureIndex,
 };
 
 /// A compiled wasm module, containing everything necessary for instantiation.
@@ -31,12 +31,28 @@ pub struct UniversalArtifact {
     pub(crate) signatures: BoxedSlice<SignatureIndex, VMSharedSignatureIndex>,
     pub(crate) local_memories: Vec<(MemoryType, MemoryStyle)>,
     pub(crate) data_segments: Vec<OwnedDataInitializer>,
+    /// The images of the local memories, precomputed from `data_segments`.
+    pub(crate) memory_images: PrimaryMap<LocalMemoryIndex, Option<Arc<MemoryImage>>>,
     pub(crate) passive_data: BTreeMap<DataIndex, Arc<[u8]>>,
     pub(crate) local_tables: Vec<(TableType, TableStyle)>,
     pub(crate) element_segments: Vec<OwnedTableInitializer>,
     // TODO: does this need to be a BTreeMap? Can it be a plain vector?
     pub(crate) passive_elements: BTreeMap<ElemIndex, Box<[FunctionIndex]>>,
     pub(crate) local_globals: Vec<(GlobalType, GlobalInit)>,
+    pub(crate) local_tags: Vec<TagType>,
+    /// Maps the code of `functions` back to the module while it is alive.
+    pub(crate) _frame_info_registration: Option<GlobalFrameInfoRegistration>,
+    /// The compilation of `functions` on their first call, if the module is
+    /// compiled lazily.
+    #[cfg(feature = "compiler")]
+    pub(crate) lazy_functions: Option<Box<crate::lazy::LazyFunctions>>,
+    /// The executable memory backing `functions` and the trampolines. It is unmapped, and its
+    /// unwind information deregistered, once the last reference to this artifact goes away.
+    ///
+    /// It is declared last so that it outlives everything referring to the code, in particular
+    /// the frame information, which traps on other threads may still look up until it is
+    /// deregistered.
+    pub(crate) code_memory: crate::CodeMemory,
 }
 
 impl UniversalArtifact {
@@ -53,6 +69,47 @@ impl UniversalArtifact {
     pub fn engine(&self) -> &crate::UniversalEngine {
         &self.engine
     }
+
+    /// Return the imports of the module, which are resolved by their
+    /// `import_no`.
+    pub fn imports(&self) -> &[VMImport] {
+        &self.imports
+    }
+
+    /// Build the images of the local memories from the data segments
+    /// that initialize them, see [`MemoryImage`].
+    pub(crate) fn memory_images(
+        import_counts: &ImportCounts,
+        local_memories: &[(MemoryType, MemoryStyle)],
+        data_segments: &[OwnedDataInitializer],
+    ) -> PrimaryMap<LocalMemoryIndex, Option<Arc<MemoryImage>>> {
+        local_memories
+            .iter()
+            .enumerate()
+            .map(|(index, (ty, _))| {
+                let index = import_counts.memory_index(LocalMemoryIndex::new(index));
+                let segments = data_segments
+                    .iter()
+                    .filter(|s| s.location.memory_index == index)
+                    .map(Into::into);
+                MemoryImage::new(ty.minimum, segments).map(Arc::new)
+            })
+            .collect()
+    }
+}
+
+impl Drop for UniversalArtifact {
+    /// Release the signatures of the module and the function references to
+    /// its code registered in the engine.
+    fn drop(&mut self) {
+        let mut inner_engine = self.engine.inner_mut();
+        for signature in self.signatures.values() {
+            inner_engine.signatures.unregister(*signature);
+        }
+        inner_engine
+            .func_data()
+            .unregister_code(self.code_memory.range());
+    }
 }
 
 impl Instantiatable for UniversalArtifact {
@@ -82,15 +139,21 @@ impl Instantiatable for UniversalArtifact {
             (imports, import_function_envs)
         };
 
-        let (allocator, memory_definition_locations, table_definition_locations) =
-            wasmer_vm::InstanceAllocator::new(self.vmoffsets.clone());
+        let (allocator, memory_definition_locations, table_definition_locations) = tunables
+            .allocate_instance(self.vmoffsets.clone())
+            .map_err(|e| {
+                InstantiationError::Link(wasmer_engine::LinkError::Resource(format!(
+                    "Failed to allocate instance: {}",
+                    e
+                )))
+            })?;
 
         // Memories
         let mut memories: PrimaryMap<wasmer_types::LocalMemoryIndex, _> =
             PrimaryMap::with_capacity(self.local_memories.len());
-        for (idx, (ty, style)) in (self.import_counts.memories..).zip(self.local_memories.iter()) {
+        for (idx, (ty, style)) in self.local_memories.iter().enumerate() {
             let memory = tunables
-                .create_vm_memory(&ty, &style, memory_definition_locations[idx as usize])
+                .create_vm_memory(ty, style, memory_definition_locations[idx])
                 .map_err(|e| {
                     InstantiationError::Link(wasmer_engine::LinkError::Resource(format!(
                         "Failed to create memory: {}",
@@ -117,6 +180,13 @@ impl Instantiatable for UniversalArtifact {
             globals.push(Arc::new(wasmer_vm::Global::new(*ty)));
         }
 
+        // Tags
+        let tags = self
+            .local_tags
+            .iter()
+            .map(|ty| Arc::new(wasmer_vm::Tag::new(ty.clone())))
+            .collect::<PrimaryMap<LocalTagIndex, _>>();
+
         let passive_data = self.passive_data.clone();
         Ok(InstanceHandle::new(
             self,
@@ -124,6 +194,7 @@ impl Instantiatable for UniversalArtifact {
             memories.into_boxed_slice(),
             tables.into_boxed_slice(),
             globals.into_boxed_slice(),
+            tags.into_boxed_slice(),
             imports,
             passive_data,
             host_state,
@@ -158,6 +229,10 @@ impl Artifact for UniversalArtifact {
         &self.data_segments[..]
     }
 
+    fn memory_image(&self, index: LocalMemoryIndex) -> Option<&Arc<MemoryImage>> {
+        self.memory_images.get(index)?.as_ref()
+    }
+
     fn globals(&self) -> &[(GlobalType, GlobalInit)] {
         &self.local_globals[..]
     }
@@ -170,6 +245,10 @@ impl Artifact for UniversalArtifact {
         self.exports.get(name).cloned()
     }
 
+    fn export_names(&self) -> Vec<&str> {
+        self.exports.keys().map(String::as_str).collect()
+    }
+
     fn signatures(&self) -> &[wasmer_vm::VMSharedSignatureIndex] {
         self.signatures.values().as_slice()
     }
@@ -190,4 +269,8 @@ impl Artifact for UniversalArtifact {
                 .nth(import.index()),
         }
     }
+
+    fn epoch(&self) -> &Epoch {
+        self.engine.epoch()
+    }
 }
@@ -7,6 +7,9 @@ pub struct Universal {
     compiler_config: Option<Box<dyn CompilerConfig>>,
     target: Option<Target>,
     features: Option<Features>,
+    #[allow(dead_code)]
+    lazy_compilation: bool,
+    check_code_hash: bool,
 }
 
 impl Universal {
@@ -19,6 +22,8 @@ impl Universal {
             compiler_config: Some(compiler_config.into()),
             target: None,
             features: None,
+            lazy_compilation: false,
+            check_code_hash: true,
         }
     }
 
@@ -28,6 +33,8 @@ impl Universal {
             compiler_config: None,
             target: None,
             features: None,
+            lazy_compilation: false,
+            check_code_hash: true,
         }
     }
 
@@ -43,6 +50,25 @@ impl Universal {
         self
     }
 
+    /// Set whether the functions of the modules are compiled on their
+    /// first call rather than with the rest of the modules.
+    ///
+    /// See [`UniversalEngine::compile_universal_lazy`].
+    pub fn lazy_compilation(mut self, lazy_compilation: bool) -> Self {
+        self.lazy_compilation = lazy_compilation;
+        self
+    }
+
+    /// Set whether the code of the serialized executables is checked
+    /// against its hash when they are loaded, which is the default.
+    ///
+    /// Hashing takes time proportional to the size of the code, which can
+    /// be saved when the executables can't be corrupted.
+    pub fn check_code_hash(mut self, check_code_hash: bool) -> Self {
+        self.check_code_hash = check_code_hash;
+        self
+    }
+
     /// Build the `UniversalEngine` for this configuration
     #[cfg(feature = "compiler")]
     pub fn engine(self) -> UniversalEngine {
@@ -52,15 +78,22 @@ impl Universal {
                 .features
                 .unwrap_or_else(|| compiler_config.default_features_for_target(&target));
             let compiler = compiler_config.compiler();
-            UniversalEngine::new(compiler, target, features)
+            let engine = UniversalEngine::new(compiler, target, features);
+            engine.inner_mut().lazy_compilation = self.lazy_compilation;
+            engine.inner_mut().check_code_hash = self.check_code_hash;
+            engine
         } else {
-            UniversalEngine::headless()
+            let engine = UniversalEngine::headless();
+            engine.inner_mut().check_code_hash = self.check_code_hash;
+            engine
         }
     }
 
     /// Build the `UniversalEngine` for this configuration
     #[cfg(not(feature = "compiler"))]
     pub fn engine(self) -> UniversalEngine {
-        UniversalEngine::headless()
+        let engine = UniversalEngine::headless();
+        engine.inner_mut().check_code_hash = self.check_code_hash;
+        engine
     }
 }
@@ -3,6 +3,8 @@
 
 //! Memory management for executable code.
 use crate::unwind::UnwindRegistry;
+use std::any::Any;
+use std::ops::Range;
 use wasmer_compiler::{CompiledFunctionUnwindInfoRef, CustomSectionRef, FunctionBodyRef};
 use wasmer_vm::{Mmap, VMFunctionBody};
 
@@ -17,10 +19,147 @@ const ARCH_FUNCTION_ALIGNMENT: usize = 16;
 ///
 const DATA_SECTION_ALIGNMENT: usize = 64;
 
+/// The page size the code images of serialized executables are laid out
+/// for.
+///
+/// The images can be used in place on the hosts whose page size divides
+/// it, which covers both 4 KiB and 16 KiB pages.
+pub(crate) const CODE_IMAGE_PAGE_SIZE: usize = 0x4000;
+
+/// The memory holding the code.
+enum Memory {
+    /// Memory allocated for the code, which is copied into it.
+    Allocated(Mmap),
+    /// A private mapping of the code image of a serialized executable,
+    /// used in place.
+    Mapped(memmap2::MmapMut),
+    /// Code linked in place by the system linker and loader, which the
+    /// engine doesn't write, and whose functions are at `functions`.
+    Linked {
+        functions: Range<usize>,
+        _owner: Box<dyn Any + Send + Sync>,
+    },
+}
+
+impl Memory {
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            Self::Allocated(mmap) => mmap.as_slice(),
+            Self::Mapped(mmap) => &mmap[..],
+            Self::Linked { .. } => &[],
+        }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        match self {
+            Self::Allocated(mmap) => mmap.as_mut_slice(),
+            Self::Mapped(mmap) => &mut mmap[..],
+            Self::Linked { .. } => &mut [],
+        }
+    }
+}
+
+/// Where the functions and the custom sections are placed in code memory.
+pub(crate) struct CodeLayout {
+    /// The bodies of the functions.
+    pub(crate) functions: Vec<Range<usize>>,
+    pub(crate) executable_sections: Vec<Range<usize>>,
+    pub(crate) data_sections: Vec<Range<usize>>,
+    pub(crate) start_of_nonexecutable_pages: usize,
+    len: usize,
+}
+
+impl CodeLayout {
+    fn new(
+        functions: &[FunctionBodyRef<'_>],
+        executable_sections: &[CustomSectionRef<'_>],
+        data_sections: &[CustomSectionRef<'_>],
+        page_size: usize,
+    ) -> Self {
+        // The code memory holds:
+        // - function body size, including all trampolines
+        // -- windows unwind info
+        // -- padding between functions
+        // - executable section body
+        // -- padding between executable sections
+        // - padding until a new page to change page permissions
+        // - data section body size
+        // -- padding between data sections
+        let mut offset = 0;
+        let functions = functions
+            .iter()
+            .map(|func| {
+                let start = offset;
+                offset = round_up(
+                    offset + CodeMemory::function_allocation_size(*func),
+                    ARCH_FUNCTION_ALIGNMENT,
+                );
+                start..start + func.body.len()
+            })
+            .collect();
+        let executable_sections = executable_sections
+            .iter()
+            .map(|section| {
+                let start = offset;
+                offset = round_up(offset + section.bytes.len(), ARCH_FUNCTION_ALIGNMENT);
+                start..offset
+            })
+            .collect();
+        let start_of_nonexecutable_pages = offset;
+        if !data_sections.is_empty() {
+            // Data sections have different page permissions from the executable
+            // code that came before it, so they need to be on different pages.
+            offset = round_up(offset, page_size);
+        }
+        let data_sections = data_sections
+            .iter()
+            .map(|section| {
+                let start = offset;
+                offset = round_up(offset + section.bytes.len(), DATA_SECTION_ALIGNMENT);
+                start..offset
+            })
+            .collect();
+        Self {
+            functions,
+            executable_sections,
+            data_sections,
+            start_of_nonexecutable_pages,
+            len: offset,
+        }
+    }
+
+    /// Copy the code into `buf`, which is at least `self.len` bytes long.
+    fn write(
+        &self,
+        buf: &mut [u8],
+        functions: &[FunctionBodyRef<'_>],
+        executable_sections: &[CustomSectionRef<'_>],
+        data_sections: &[CustomSectionRef<'_>],
+    ) {
+        for (range, func) in self.functions.iter().zip(functions) {
+            buf[range.clone()].copy_from_slice(func.body);
+            if let Some(CompiledFunctionUnwindInfoRef::WindowsX64(info)) = &func.unwind_info {
+                // Windows unwind information is written following the function body
+                // Keep unwind information 32-bit aligned (round up to the nearest 4 byte boundary)
+                let unwind_start = (range.end + 3) & !3;
+                buf[unwind_start..unwind_start + info.len()].copy_from_slice(info);
+            }
+        }
+        let sections = self
+            .executable_sections
+            .iter()
+            .zip(executable_sections)
+            .chain(self.data_sections.iter().zip(data_sections));
+        for (range, section) in sections {
+            buf[range.start..range.start + section.bytes.len()].copy_from_slice(section.bytes);
+        }
+    }
+}
+
 /// Memory manager for executable code.
 pub struct CodeMemory {
     unwind_registry: UnwindRegistry,
-    mmap: Mmap,
+    memory: Memory,
     start_of_nonexecutable_pages: usize,
 }
 
@@ -29,104 +168,159 @@ impl CodeMemory {
     pub fn new() -> Self {
         Self {
             unwind_registry: UnwindRegistry::new(),
-            mmap: Mmap::new(),
+            memory: Memory::Allocated(Mmap::new()),
             start_of_nonexecutable_pages: 0,
         }
     }
 
+    /// Create a `CodeMemory` using the code image `image` in place, rather
+    /// than copying the code.
+    ///
+    /// `image` must hold the code laid out by [`CodeMemory::image`], on
+    /// pages which can be made executable.
+    pub(crate) fn mapped(image: memmap2::MmapMut) -> Self {
+        Self {
+            unwind_registry: UnwindRegistry::new(),
+            memory: Memory::Mapped(image),
+            start_of_nonexecutable_pages: 0,
+        }
+    }
+
+    /// Create a `CodeMemory` for code linked in place, whose functions are
+    /// at `functions`, and which `owner` keeps there.
+    ///
+    /// The code is neither allocated nor published: it is already where
+    /// it runs.
+    pub(crate) fn linked(functions: Range<usize>, owner: Box<dyn Any + Send + Sync>) -> Self {
+        Self {
+            unwind_registry: UnwindRegistry::new(),
+            memory: Memory::Linked {
+                functions,
+                _owner: owner,
+            },
+            start_of_nonexecutable_pages: 0,
+        }
+    }
+
+    /// Whether the code is linked in place, see [`CodeMemory::linked`].
+    pub(crate) fn is_linked(&self) -> bool {
+        matches!(self.memory, Memory::Linked { .. })
+    }
+
+    /// Lay out the functions and custom sections in a code image, which
+    /// [`CodeMemory::mapped`] can use in place once it is mapped at the
+    /// start of a page, and return where they are in it.
+    pub(crate) fn image(
+        functions: &[FunctionBodyRef<'_>],
+        executable_sections: &[CustomSectionRef<'_>],
+        data_sections: &[CustomSectionRef<'_>],
+    ) -> (Vec<u8>, CodeLayout) {
+        let layout = CodeLayout::new(
+            functions,
+            executable_sections,
+            data_sections,
+            CODE_IMAGE_PAGE_SIZE,
+        );
+        let mut image = vec![0; layout.len];
+        layout.write(&mut image, functions, executable_sections, data_sections);
+        (image, layout)
+    }
+
+    /// The addresses of the code memory, or of the functions of linked
+    /// code.
+    pub(crate) fn range(&self) -> Range<usize> {
+        if let Memory::Linked { functions, .. } = &self.memory {
+            return functions.clone();
+        }
+        let memory = self.memory.as_slice();
+        let start = memory.as_ptr() as usize;
+        start..start + memory.len()
+    }
+
     /// Mutably get the UnwindRegistry.
     pub fn unwind_registry_mut(&mut self) -> &mut UnwindRegistry {
         &mut self.unwind_registry
     }
 
     /// Allocate a single contiguous block of memory for the functions and custom sections, and copy the data in place.
+    ///
+    /// A `CodeMemory` created with [`CodeMemory::mapped`] already holds
+    /// the data, and returns where it is in the image instead.
+    #[allow(clippy::type_complexity)]
     pub fn allocate(
         &mut self,
         functions: &[FunctionBodyRef<'_>],
         executable_sections: &[CustomSectionRef<'_>],
         data_sections: &[CustomSectionRef<'_>],
     ) -> Result<(Vec<&mut [VMFunctionBody]>, Vec<&mut [u8]>, Vec<&mut [u8]>), String> {
-        let mut function_result = vec![];
-        let mut data_section_result = vec![];
-        let mut executable_section_result = vec![];
-
-        let page_size = region::page::size();
-
-        // 1. Calculate the total size, that is:
-        // - function body size, including all trampolines
-        // -- windows unwind info
-        // -- padding between functions
-        // - executable section body
-        // -- padding between executable sections
-        // - padding until a new page to change page permissions
-        // - data section body size
-        // -- padding between data sections
-
-        let total_len = round_up(
-            functions.iter().fold(0, |acc, func| {
-                round_up(
-                    acc + Self::function_allocation_size(*func),
-                    ARCH_FUNCTION_ALIGNMENT,
-                )
-            }) + executable_sections.iter().fold(0, |acc, exec| {
-                round_up(acc + exec.bytes.len(), ARCH_FUNCTION_ALIGNMENT)
-            }),
-            page_size,
-        ) + data_sections.iter().fold(0, |acc, data| {
-            round_up(acc + data.bytes.len(), DATA_SECTION_ALIGNMENT)
-        });
-
-        // 2. Allocate the pages. Mark them all read-write.
-
-        self.mmap = Mmap::with_at_least(total_len)?;
-
-        // 3. Determine where the pointers to each function, executable section
-        // or data section are. Copy the functions. Collect the addresses of each and return them.
-
-        let mut bytes = 0;
-        let mut buf = self.mmap.as_mut_slice();
-        for func in functions {
-            let len = round_up(
-                Self::function_allocation_size(*func),
-                ARCH_FUNCTION_ALIGNMENT,
-            );
-            let (func_buf, next_buf) = buf.split_at_mut(len);
-            buf = next_buf;
-            bytes += len;
-
-            let vmfunc = Self::copy_function(&mut self.unwind_registry, *func, func_buf);
-            assert_eq!(vmfunc.as_ptr() as usize % ARCH_FUNCTION_ALIGNMENT, 0);
-            function_result.push(vmfunc);
-        }
-        for section in executable_sections {
-            let section = &section.bytes;
-            assert_eq!(buf.as_mut_ptr() as usize % ARCH_FUNCTION_ALIGNMENT, 0);
-            let len = round_up(section.len(), ARCH_FUNCTION_ALIGNMENT);
-            let (s, next_buf) = buf.split_at_mut(len);
-            buf = next_buf;
-            bytes += len;
-            s[..section.len()].copy_from_slice(*section);
-            executable_section_result.push(s);
-        }
-
-        self.start_of_nonexecutable_pages = bytes;
-
-        if !data_sections.is_empty() {
-            // Data sections have different page permissions from the executable
-            // code that came before it, so they need to be on different pages.
-            let padding = round_up(bytes, page_size) - bytes;
-            buf = buf.split_at_mut(padding).1;
-
-            for section in data_sections {
-                let section = &section.bytes;
-                assert_eq!(buf.as_mut_ptr() as usize % DATA_SECTION_ALIGNMENT, 0);
-                let len = round_up(section.len(), DATA_SECTION_ALIGNMENT);
-                let (s, next_buf) = buf.split_at_mut(len);
-                buf = next_buf;
-                s[..section.len()].copy_from_slice(*section);
-                data_section_result.push(s);
+        let layout = match &mut self.memory {
+            Memory::Allocated(mmap) => {
+                let layout = CodeLayout::new(
+                    functions,
+                    executable_sections,
+                    data_sections,
+                    region::page::size(),
+                );
+                // Allocate the pages, all read-write, and copy the code.
+                *mmap = Mmap::with_at_least(layout.len)?;
+                layout.write(
+                    mmap.as_mut_slice(),
+                    functions,
+                    executable_sections,
+                    data_sections,
+                );
+                layout
             }
-        }
+            Memory::Mapped(image) => {
+                let layout = CodeLayout::new(
+                    functions,
+                    executable_sections,
+                    data_sections,
+                    CODE_IMAGE_PAGE_SIZE,
+                );
+                if image.len() < layout.len {
+                    return Err("the code image is too small".to_string());
+                }
+                layout
+            }
+            Memory::Linked { .. } => return Err("the code is linked in place".to_string()),
+        };
+        self.start_of_nonexecutable_pages = layout.start_of_nonexecutable_pages;
+
+        // Collect the addresses of each function, executable section and
+        // data section.
+        let registry = &mut self.unwind_registry;
+        let mut buf = self.memory.as_mut_slice();
+        let mut consumed = 0;
+        let ranges = layout
+            .functions
+            .iter()
+            .chain(&layout.executable_sections)
+            .chain(&layout.data_sections);
+        let mut slices = ranges
+            .map(|range| {
+                let (_, rest) = std::mem::take(&mut buf).split_at_mut(range.start - consumed);
+                let (slice, rest) = rest.split_at_mut(range.len());
+                buf = rest;
+                consumed = range.end;
+                slice
+            })
+            .collect::<Vec<_>>();
+        let data_section_result = slices.split_off(functions.len() + executable_sections.len());
+        let executable_section_result = slices.split_off(functions.len());
+        let function_result = slices
+            .into_iter()
+            .zip(functions)
+            .map(|(body, func)| {
+                assert_eq!(body.as_ptr() as usize % ARCH_FUNCTION_ALIGNMENT, 0);
+                if let Some(info) = &func.unwind_info {
+                    registry
+                        .register(body.as_ptr() as usize, 0, body.len() as u32, *info)
+                        .expect("failed to register unwind information");
+                }
+                Self::view_as_mut_vmfunc_slice(body)
+            })
+            .collect();
 
         Ok((
             function_result,
@@ -137,18 +331,29 @@ impl CodeMemory {
 
     /// Apply the page permissions.
     pub fn publish(&mut self) {
-        if self.mmap.is_empty() || self.start_of_nonexecutable_pages == 0 {
+        let memory = self.memory.as_mut_slice();
+        if memory.is_empty() || self.start_of_nonexecutable_pages == 0 {
             return;
         }
-        assert!(self.mmap.len() >= self.start_of_nonexecutable_pages);
+        assert!(memory.len() >= self.start_of_nonexecutable_pages);
         unsafe {
             region::protect(
-                self.mmap.as_mut_ptr(),
+                memory.as_mut_ptr(),
                 self.start_of_nonexecutable_pages,
                 region::Protection::READ_EXECUTE,
             )
         }
         .expect("unable to make memory readonly and executable");
+        // AArch64 doesn't keep the instruction cache coherent with data writes, so
+        // stale instructions must be invalidated before the code is run.
+        #[cfg(target_arch = "aarch64")]
+        unsafe {
+            extern "C" {
+                fn __clear_cache(begin: *mut u8, end: *mut u8);
+            }
+            let begin = memory.as_mut_ptr();
+            __clear_cache(begin, begin.add(self.start_of_nonexecutable_pages));
+        }
     }
 
     /// Calculates the allocation size of the given compiled function.
@@ -164,47 +369,84 @@ impl CodeMemory {
         }
     }
 
-    /// Copies the data of the compiled function to the given buffer.
-    ///
-    /// This will also add the function to the current function table.
-    fn copy_function<'a>(
-        registry: &mut UnwindRegistry,
-        func: FunctionBodyRef<'_>,
-        buf: &'a mut [u8],
-    ) -> &'a mut [VMFunctionBody] {
-        assert_eq!(buf.as_ptr() as usize % ARCH_FUNCTION_ALIGNMENT, 0);
+    /// Convert mut a slice from u8 to VMFunctionBody.
+    fn view_as_mut_vmfunc_slice(slice: &mut [u8]) -> &mut [VMFunctionBody] {
+        let byte_ptr: *mut [u8] = slice;
+        let body_ptr = byte_ptr as *mut [VMFunctionBody];
+        unsafe { &mut *body_ptr }
+    }
+}
 
-        let func_len = func.body.len();
+/// The size of the chunks of memory a [`CodePool`] reserves at once.
+#[cfg(feature = "compiler")]
+const CODE_POOL_CHUNK_SIZE: usize = 0x10_0000;
 
-        let (body, remainder) = buf.split_at_mut(func_len);
-        body.copy_from_slice(&func.body);
-        let vmfunc = Self::view_as_mut_vmfunc_slice(body);
+/// Memory manager for code added over time, such as the functions of a
+/// module compiled on their first call.
+///
+/// The code is placed in chunks of memory shared by many functions, rather
+/// than in a mapping of its own. Each function is made executable once it
+/// is added, before the next one is written, so it starts on a new page.
+#[cfg(feature = "compiler")]
+pub(crate) struct CodePool {
+    chunks: Vec<Mmap>,
+    /// The offset of the pages of the last chunk which aren't executable.
+    published: usize,
+    /// The offset of the pages of the last chunk which are still free.
+    free: usize,
+}
 
-        if let Some(CompiledFunctionUnwindInfoRef::WindowsX64(info)) = &func.unwind_info {
-            // Windows unwind information is written following the function body
-            // Keep unwind information 32-bit aligned (round up to the nearest 4 byte boundary)
-            let unwind_start = (func_len + 3) & !3;
-            let unwind_size = info.len();
-            let padding = unwind_start - func_len;
-            assert_eq!((func_len + padding) % 4, 0);
-            let slice = remainder.split_at_mut(padding + unwind_size).0;
-            slice[padding..].copy_from_slice(&info);
+#[cfg(feature = "compiler")]
+impl CodePool {
+    pub(crate) fn new() -> Self {
+        Self {
+            chunks: Vec::new(),
+            published: 0,
+            free: 0,
         }
+    }
 
-        if let Some(info) = &func.unwind_info {
-            registry
-                .register(vmfunc.as_ptr() as usize, 0, func_len as u32, *info)
-                .expect("failed to register unwind information");
+    /// Copy the body of a function into the pool, and return where it is.
+    ///
+    /// It stays writable until [`CodePool::publish`] is called.
+    pub(crate) fn allocate(&mut self, body: &[u8]) -> Result<&mut [VMFunctionBody], String> {
+        let len = round_up(body.len(), region::page::size());
+        let fits = self
+            .chunks
+            .last()
+            .map_or(false, |chunk| chunk.len() - self.free >= len);
+        if !fits {
+            self.chunks
+                .push(Mmap::with_at_least(len.max(CODE_POOL_CHUNK_SIZE))?);
+            self.published = 0;
+            self.free = 0;
         }
-
-        vmfunc
+        let chunk = self.chunks.last_mut().unwrap().as_mut_slice();
+        let start = self.free;
+        self.free += len;
+        let slice = &mut chunk[start..start + body.len()];
+        slice.copy_from_slice(body);
+        Ok(CodeMemory::view_as_mut_vmfunc_slice(slice))
     }
 
-    /// Convert mut a slice from u8 to VMFunctionBody.
-    fn view_as_mut_vmfunc_slice(slice: &mut [u8]) -> &mut [VMFunctionBody] {
-        let byte_ptr: *mut [u8] = slice;
-        let body_ptr = byte_ptr as *mut [VMFunctionBody];
-        unsafe { &mut *body_ptr }
+    /// Make the code allocated since the last call executable.
+    pub(crate) fn publish(&mut self) {
+        let chunk = match self.chunks.last_mut() {
+            Some(chunk) if self.free > self.published => chunk,
+            _ => return,
+        };
+        let start = unsafe { chunk.as_mut_ptr().add(self.published) };
+        let len = self.free - self.published;
+        unsafe { region::protect(start, len, region::Protection::READ_EXECUTE) }
+            .expect("unable to make memory readonly and executable");
+        #[cfg(target_arch = "aarch64")]
+        unsafe {
+            extern "C" {
+                fn __clear_cache(begin: *mut u8, end: *mut u8);
+            }
+            __clear_cache(start, start.add(len));
+        }
+        self.published = self.free;
     }
 }
 
@@ -220,4 +462,31 @@ mod tests {
         fn _assert_send_sync<T: Send + Sync>() {}
         _assert_send_sync::<CodeMemory>();
     }
+
+    #[cfg(feature = "compiler")]
+    #[test]
+    fn pooled_functions_share_chunks() {
+        use super::CodePool;
+        let mut pool = CodePool::new();
+        let mut bodies = Vec::new();
+        for byte in 0..3u8 {
+            let body = pool.allocate(&[byte; 100]).unwrap().as_ptr() as usize;
+            pool.publish();
+            bodies.push(body);
+        }
+        assert_eq!(pool.chunks.len(), 1);
+        let page_size = region::page::size();
+        for (byte, body) in bodies.iter().enumerate() {
+            assert_eq!(body % page_size, 0);
+            let code = unsafe { std::slice::from_raw_parts(*body as *const u8, 100) };
+            assert!(code.iter().all(|b| *b == byte as u8));
+        }
+        assert_eq!(bodies[1] - bodies[0], page_size);
+
+        // Functions larger than a chunk get one of their own.
+        pool.allocate(&vec![0; super::CODE_POOL_CHUNK_SIZE + 1])
+            .unwrap();
+        pool.publish();
+        assert_eq!(pool.chunks.len(), 2);
+    }
 }
@@ -1,28 +1,35 @@
 //! Universal compilation.
 
-use crate::executable::{unrkyv, UniversalExecutableRef};
+use crate::code_memory::CODE_IMAGE_PAGE_SIZE;
+use crate::executable::{unrkyv, ExecutableHeader, LinkedCode, UniversalExecutableRef};
 use crate::{CodeMemory, UniversalArtifact, UniversalExecutable};
+use enumset::EnumSet;
 use rkyv::de::deserializers::SharedDeserializeMap;
 use std::collections::BTreeMap;
 use std::convert::TryFrom;
+use std::fs::File;
+use std::ops::Range;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
-#[cfg(feature = "compiler")]
-use wasmer_compiler::Compiler;
 use wasmer_compiler::{
-    CompileError, CustomSectionProtection, CustomSectionRef, FunctionBodyRef, JumpTable,
-    SectionIndex, Target,
+    CompileError, CompiledFunctionFrameInfo, CpuFeature, CustomSectionProtection, CustomSectionRef,
+    FunctionBodyRef, JumpTable, SectionIndex, Target,
+};
+#[cfg(feature = "compiler")]
+use wasmer_compiler::{Compiler, ModuleMiddlewareChain};
+use wasmer_engine::{
+    DeserializeError, Engine, EngineId, GlobalFrameInfo, GlobalFrameInfoRegistration,
 };
-use wasmer_engine::{Engine, EngineId};
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{
     DataInitializer, ExportIndex, Features, FunctionIndex, FunctionType, FunctionTypeRef,
     GlobalInit, GlobalType, ImportCounts, ImportIndex, LocalFunctionIndex, LocalGlobalIndex,
-    MemoryIndex, SignatureIndex, TableIndex,
+    MemoryIndex, ModuleInfo, SignatureIndex, TableIndex, TagType,
 };
 use wasmer_vm::{
-    FuncDataRegistry, FunctionBodyPtr, SectionBodyPtr, SignatureRegistry, Tunables,
-    VMCallerCheckedAnyfunc, VMFuncRef, VMFunctionBody, VMImportType, VMLocalFunction, VMOffsets,
-    VMSharedSignatureIndex, VMTrampoline,
+    Epoch, FuncDataRegistry, FunctionBodyPtr, FunctionExtent, SectionBodyPtr, SignatureRegistry,
+    Tunables, VMCallerCheckedAnyfunc, VMFuncRef, VMFunctionBody, VMImportType, VMLocalFunction,
+    VMOffsets, VMSharedSignatureIndex, VMTrampoline,
 };
 
 /// A WebAssembly `Universal` Engine.
@@ -32,6 +39,8 @@ pub struct UniversalEngine {
     /// The target for the compiler
     target: Arc<Target>,
     engine_id: EngineId,
+    /// The epoch counter checked by the compiled code.
+    epoch: Arc<Epoch>,
 }
 
 impl UniversalEngine {
@@ -40,14 +49,16 @@ impl UniversalEngine {
     pub fn new(compiler: Box<dyn Compiler>, target: Target, features: Features) -> Self {
         Self {
             inner: Arc::new(Mutex::new(UniversalEngineInner {
-                compiler: Some(compiler),
-                code_memory: vec![],
+                compiler: Some(Arc::from(compiler)),
+                lazy_compilation: false,
+                check_code_hash: true,
                 signatures: SignatureRegistry::new(),
                 func_data: Arc::new(FuncDataRegistry::new()),
                 features,
             })),
             target: Arc::new(target),
             engine_id: EngineId::default(),
+            epoch: Arc::new(Epoch::new()),
         }
     }
 
@@ -69,14 +80,65 @@ impl UniversalEngine {
             inner: Arc::new(Mutex::new(UniversalEngineInner {
                 #[cfg(feature = "compiler")]
                 compiler: None,
-                code_memory: vec![],
+                #[cfg(feature = "compiler")]
+                lazy_compilation: false,
+                check_code_hash: true,
                 signatures: SignatureRegistry::new(),
                 func_data: Arc::new(FuncDataRegistry::new()),
                 features: Features::default(),
             })),
             target: Arc::new(Target::default()),
             engine_id: EngineId::default(),
+            epoch: Arc::new(Epoch::new()),
+        }
+    }
+
+    /// The epoch counter of this engine.
+    ///
+    /// Code compiled with epoch interruption enabled traps once the
+    /// counter reaches the deadline of its instance.
+    pub fn epoch(&self) -> &Epoch {
+        &self.epoch
+    }
+
+    /// Whether the code compiled by this engine checks its epoch, or
+    /// `None` for headless engines, which load code compiled either way.
+    pub fn epoch_interruption(&self) -> Option<bool> {
+        #[cfg(feature = "compiler")]
+        if let Ok(compiler) = self.inner().compiler() {
+            return Some(compiler.epoch_interruption());
         }
+        None
+    }
+
+    /// Advance the epoch counter of this engine by one.
+    ///
+    /// This can be called from any thread, including while instances
+    /// created by this engine are running.
+    pub fn increment_epoch(&self) {
+        self.epoch.increment();
+    }
+
+    /// The Wasm features enabled for the modules compiled by this engine.
+    pub fn features(&self) -> Features {
+        self.inner().features().clone()
+    }
+
+    /// The number of distinct signatures registered in this engine.
+    ///
+    /// The modules loaded into the engine release their signatures once
+    /// dropped.
+    pub fn registered_signatures(&self) -> usize {
+        self.inner().signatures.len()
+    }
+
+    /// The number of distinct function references registered in this
+    /// engine.
+    ///
+    /// The references to the functions of a module are released once the
+    /// module is dropped.
+    pub fn registered_function_references(&self) -> usize {
+        self.inner().func_data().len()
     }
 
     pub(crate) fn inner(&self) -> std::sync::MutexGuard<'_, UniversalEngineInner> {
@@ -97,48 +159,117 @@ impl UniversalEngine {
         let inner_engine = self.inner_mut();
         let features = inner_engine.features();
         let compiler = inner_engine.compiler()?;
+        let binary = wasmer_compiler::reorder_memargs(binary);
         let environ = wasmer_compiler::ModuleEnvironment::new();
-        let translation = environ.translate(binary).map_err(CompileError::Wasm)?;
+        let translation = environ.translate(&binary).map_err(CompileError::Wasm)?;
+        let mut module = translation.module;
+        compiler.get_middlewares().apply_on_module_info(&mut module);
 
-        let memory_styles: PrimaryMap<wasmer_types::MemoryIndex, _> = translation
-            .module
+        // Compile the Module
+        let compile_info = Self::compile_info(module, features, tunables);
+        let compilation = compiler.compile_module(
+            &self.target(),
+            &compile_info,
+            // SAFETY: Calling `unwrap` is correct since
+            // `environ.translate()` above will write some data into
+            // `module_translation_state`.
+            translation.module_translation_state.as_ref().unwrap(),
+            translation.function_body_inputs,
+        )?;
+        Ok(self.universal_executable(
+            compiler,
+            compile_info,
+            compilation,
+            &translation.data_initializers,
+        ))
+    }
+
+    /// Compile a WebAssembly binary while it's being received.
+    ///
+    /// The bytes of the binary are pushed to the returned
+    /// [`StreamingCompilation`](crate::StreamingCompilation) as they
+    /// arrive. Each function is validated as soon as it's received, and
+    /// compiled then if the compiler supports compiling the functions of a
+    /// module one at a time, see
+    /// [`Compiler::supports_function_compilation`](wasmer_compiler::Compiler::supports_function_compilation).
+    /// Other compilers compile the whole module once it's received.
+    #[cfg(feature = "compiler")]
+    pub fn compile_universal_streaming<'a>(
+        &'a self,
+        tunables: &'a dyn Tunables,
+    ) -> Result<crate::StreamingCompilation<'a>, CompileError> {
+        let inner_engine = self.inner();
+        Ok(crate::StreamingCompilation::new(
+            self,
+            inner_engine.shared_compiler()?,
+            inner_engine.features().clone(),
+            tunables,
+        ))
+    }
+
+    /// Compile a WebAssembly binary lazily: its functions are only
+    /// compiled on their first call, by the thread calling them.
+    ///
+    /// This is only supported on x86_64 Unix hosts, with compilers which
+    /// can compile the functions of a module one at a time, and for
+    /// functions which don't need custom sections of their own. Errors
+    /// compiling a function are raised as traps of the call to it.
+    #[cfg(feature = "compiler")]
+    pub fn compile_universal_lazy(
+        &self,
+        binary: &[u8],
+        tunables: &dyn Tunables,
+    ) -> Result<crate::UniversalLazyExecutable, CompileError> {
+        let inner_engine = self.inner();
+        let compiler = inner_engine.shared_compiler()?;
+        let features = inner_engine.features().clone();
+        drop(inner_engine);
+        crate::UniversalLazyExecutable::new(self, compiler, &features, binary, tunables)
+    }
+
+    /// The information to compile `module` with.
+    #[cfg(feature = "compiler")]
+    pub(crate) fn compile_info(
+        module: ModuleInfo,
+        features: &Features,
+        tunables: &dyn Tunables,
+    ) -> wasmer_compiler::CompileModuleInfo {
+        let memory_styles: PrimaryMap<wasmer_types::MemoryIndex, _> = module
             .memories
             .values()
             .map(|memory_type| tunables.memory_style(memory_type))
             .collect();
-        let table_styles: PrimaryMap<wasmer_types::TableIndex, _> = translation
-            .module
+        let table_styles: PrimaryMap<wasmer_types::TableIndex, _> = module
             .tables
             .values()
             .map(|table_type| tunables.table_style(table_type))
             .collect();
-
-        // Compile the Module
-        let compile_info = wasmer_compiler::CompileModuleInfo {
-            module: Arc::new(translation.module),
+        wasmer_compiler::CompileModuleInfo {
+            module: Arc::new(module),
             features: features.clone(),
             memory_styles,
             table_styles,
-        };
-        let compilation = compiler.compile_module(
-            &self.target(),
-            &compile_info,
-            // SAFETY: Calling `unwrap` is correct since
-            // `environ.translate()` above will write some data into
-            // `module_translation_state`.
-            translation.module_translation_state.as_ref().unwrap(),
-            translation.function_body_inputs,
-        )?;
+        }
+    }
+
+    /// Make an executable out of the compilation of a module.
+    #[cfg(feature = "compiler")]
+    pub(crate) fn universal_executable(
+        &self,
+        compiler: &dyn Compiler,
+        compile_info: wasmer_compiler::CompileModuleInfo,
+        compilation: wasmer_compiler::Compilation,
+        data_initializers: &[DataInitializer<'_>],
+    ) -> crate::UniversalExecutable {
         let function_call_trampolines = compilation.get_function_call_trampolines();
         let dynamic_function_trampolines = compilation.get_dynamic_function_trampolines();
-        let data_initializers = translation
-            .data_initializers
+        let data_initializers = data_initializers
             .iter()
             .map(wasmer_types::OwnedDataInitializer::new)
             .collect();
 
         let frame_infos = compilation.get_frame_info();
-        Ok(crate::UniversalExecutable {
+        crate::UniversalExecutable {
             function_bodies: compilation.get_function_bodies(),
             function_relocations: compilation.get_relocations(),
             function_jt_offsets: compilation.get_jt_offsets(),
@@ -152,7 +283,11 @@ impl UniversalEngine {
             compile_info,
             data_initializers,
             cpu_features: self.target().cpu_features().as_u64(),
-        })
+            epoch_interruption: compiler.epoch_interruption(),
+            compiler: compiler.name().to_string(),
+            compiler_version: compiler.version().to_string(),
+            triple: self.target().triple().to_string(),
+        }
     }
 
     /// Load a [`UniversalExecutable`](crate::UniversalExecutable) with this engine.
@@ -160,9 +295,33 @@ impl UniversalEngine {
         &self,
         executable: &UniversalExecutable,
     ) -> Result<UniversalArtifact, CompileError> {
+        self.load_universal_executable_sections(executable)
+            .map(|(artifact, _)| artifact)
+    }
+
+    /// Load a [`UniversalLazyExecutable`](crate::UniversalLazyExecutable)
+    /// with this engine.
+    #[cfg(feature = "compiler")]
+    pub fn load_universal_lazy_executable(
+        &self,
+        executable: &crate::UniversalLazyExecutable,
+    ) -> Result<UniversalArtifact, CompileError> {
+        let (mut artifact, sections) =
+            self.load_universal_executable_sections(executable.executable())?;
+        executable.load_functions(&mut artifact, sections);
+        Ok(artifact)
+    }
+
+    /// Load a [`UniversalExecutable`](crate::UniversalExecutable) with this
+    /// engine, also returning where its custom sections were loaded.
+    fn load_universal_executable_sections(
+        &self,
+        executable: &UniversalExecutable,
+    ) -> Result<(UniversalArtifact, PrimaryMap<SectionIndex, SectionBodyPtr>), CompileError> {
         let info = &executable.compile_info;
         let module = &info.module;
-        let local_memories = (module.import_counts.memories as usize..module.memories.len())
+        let local_memories: Vec<_> = (module.import_counts.memories as usize
+            ..module.memories.len())
             .map(|idx| {
                 let idx = MemoryIndex::new(idx);
                 (module.memories[idx], info.memory_styles[idx].clone())
@@ -184,7 +343,14 @@ impl UniversalEngine {
                 (*t, init)
             })
             .collect();
+        let local_tags: Vec<TagType> = module
+            .tags
+            .iter()
+            .skip(module.import_counts.tags as usize)
+            .map(|(_, t)| t.clone())
+            .collect();
         let mut inner_engine = self.inner_mut();
+        let mut code_memory = CodeMemory::new();
 
         let local_functions = executable.function_bodies.iter().map(|(_, b)| b.into());
         let function_call_trampolines = &executable.function_call_trampolines;
@@ -197,6 +363,7 @@ impl UniversalEngine {
             .into_boxed_slice();
         let (functions, trampolines, dynamic_trampolines, custom_sections) = inner_engine
             .allocate(
+                &mut code_memory,
                 local_functions,
                 function_call_trampolines.iter().map(|(_, b)| b.into()),
                 dynamic_function_trampolines.iter().map(|(_, b)| b.into()),
@@ -228,6 +395,7 @@ impl UniversalEngine {
                         VMImportType::Memory(ty, info.memory_styles[i].clone())
                     }
                     ImportIndex::Global(i) => VMImportType::Global(module.globals[*i]),
+                    ImportIndex::Tag(i) => VMImportType::Tag(module.tags[*i].clone()),
                 },
             })
             .collect();
@@ -244,14 +412,17 @@ impl UniversalEngine {
         );
 
         // Make all code loaded executable.
-        inner_engine.publish_compiled_code();
+        code_memory.publish();
         if let Some(ref d) = executable.debug {
             unsafe {
                 // TODO: safety comment
-                inner_engine.publish_eh_frame(std::slice::from_raw_parts(
-                    *custom_sections[d.eh_frame],
-                    executable.custom_sections[d.eh_frame].bytes.len(),
-                ))?;
+                publish_eh_frame(
+                    &mut code_memory,
+                    std::slice::from_raw_parts(
+                        *custom_sections[d.eh_frame],
+                        executable.custom_sections[d.eh_frame].bytes.len(),
+                    ),
+                )?;
             }
         }
         let exports = module
@@ -259,8 +430,13 @@ impl UniversalEngine {
             .iter()
             .map(|(s, i)| (s.clone(), i.clone()))
             .collect::<BTreeMap<String, ExportIndex>>();
+        let frame_info_registration = register_frame_info(
+            Arc::clone(module),
+            &functions,
+            executable.function_frame_info.clone(),
+        );
 
-        Ok(UniversalArtifact {
+        let artifact = UniversalArtifact {
             engine: self.clone(),
             import_counts: module.import_counts,
             start_function: module.start_function,
@@ -270,6 +446,11 @@ impl UniversalEngine {
             functions: functions.into_boxed_slice(),
             exports,
             signatures,
+            memory_images: UniversalArtifact::memory_images(
+                &module.import_counts,
+                &local_memories,
+                &executable.data_initializers,
+            ),
             local_memories,
             data_segments: executable.data_initializers.clone(),
             passive_data: module.passive_data.clone(),
@@ -277,18 +458,171 @@ impl UniversalEngine {
             element_segments: module.table_initializers.clone(),
             passive_elements: module.passive_elements.clone(),
             local_globals,
-        })
+            local_tags,
+            _frame_info_registration: frame_info_registration,
+            #[cfg(feature = "compiler")]
+            lazy_functions: None,
+            code_memory,
+        };
+        Ok((artifact, custom_sections))
     }
 
     /// Load a [`UniversalExecutableRef`](crate::UniversalExecutableRef) with this engine.
+    ///
+    /// The executable has to be compiled for the target of this engine
+    /// and with the memory styles given by `tunables`, see
+    /// [`UniversalEngine::check_executable`].
     pub fn load_universal_executable_ref(
         &self,
         executable: &UniversalExecutableRef,
+        tunables: &dyn Tunables,
+    ) -> Result<UniversalArtifact, DeserializeError> {
+        self.check_executable(executable, tunables)?;
+        self.load_archived_executable(executable, CodeMemory::new())
+            .map_err(DeserializeError::Compiler)
+    }
+
+    /// Check that a serialized executable can be loaded with this engine:
+    /// its header has to pass [`UniversalEngine::check_executable_header`],
+    /// its memories have to be compiled with the styles `tunables` gives
+    /// them, its code image has to hold its code, and its code has to
+    /// match its hash unless the engine was built without
+    /// [`Universal::check_code_hash`](crate::Universal::check_code_hash).
+    pub fn check_executable(
+        &self,
+        executable: &UniversalExecutableRef,
+        tunables: &dyn Tunables,
+    ) -> Result<(), DeserializeError> {
+        self.check_executable_header(&executable.header())?;
+        executable.check_tunables(tunables)?;
+        executable.check_code_image()?;
+        if self.inner().check_code_hash {
+            executable.check_code_hash()?;
+        }
+        Ok(())
+    }
+
+    /// Check that an executable serialized with `header` can be loaded
+    /// with this engine: it has to be compiled for the target of the
+    /// engine, without using CPU or WebAssembly features the engine
+    /// lacks, and with epoch interruption if and only if the engine
+    /// compiles code with it.
+    pub fn check_executable_header(
+        &self,
+        header: &ExecutableHeader,
+    ) -> Result<(), DeserializeError> {
+        let triple = self.target().triple().to_string();
+        if header.triple != triple {
+            return Err(DeserializeError::IncompatibleTarget {
+                expected: triple,
+                found: header.triple.clone(),
+            });
+        }
+        let missing =
+            EnumSet::<CpuFeature>::from_u64(header.cpu_features) - *self.target().cpu_features();
+        if !missing.is_empty() {
+            let missing = missing.iter().map(|f| format!("{:?}", f));
+            return Err(DeserializeError::MissingCpuFeatures(
+                missing.collect::<Vec<_>>().join(", "),
+            ));
+        }
+        let features = self.features();
+        if !supports_features(&features, &header.features) {
+            return Err(DeserializeError::IncompatibleFeatures {
+                expected: features,
+                found: header.features.clone(),
+            });
+        }
+        if let Some(expected) = self.epoch_interruption() {
+            if header.epoch_interruption != expected {
+                return Err(DeserializeError::IncompatibleEpochInterruption {
+                    expected,
+                    found: header.epoch_interruption,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Load the executable serialized in the file at `path` with this
+    /// engine.
+    ///
+    /// The file is mapped in memory rather than read. When its code image
+    /// can be mapped at the start of a page, which is the case on the
+    /// hosts with pages of 16 KiB or less, the code is used in place in a
+    /// private mapping of the file, and only the pages modified by the
+    /// relocations are copied. Otherwise the code is copied, like it is
+    /// by [`UniversalEngine::load_universal_executable_ref`].
+    ///
+    /// The executable is checked like it is by
+    /// [`UniversalEngine::load_universal_executable_ref`].
+    ///
+    /// # Safety
+    ///
+    /// The serialized executable is not validated: the file must come
+    /// from a trusted source, and must not be modified while it is
+    /// loaded.
+    pub unsafe fn load_universal_executable_file(
+        &self,
+        path: impl AsRef<Path>,
+        tunables: &dyn Tunables,
+    ) -> Result<UniversalArtifact, DeserializeError> {
+        let file = File::open(path)?;
+        let data = unsafe { memmap2::Mmap::map(&file)? };
+        let executable = unsafe { UniversalExecutableRef::deserialize(&data)? };
+        self.check_executable(&executable, tunables)?;
+        let code_memory = match map_code_image(&file, executable.code_image()) {
+            Some(image) => CodeMemory::mapped(image),
+            None => CodeMemory::new(),
+        };
+        self.load_archived_executable(&executable, code_memory)
+            .map_err(DeserializeError::Compiler)
+    }
+
+    /// Load a serialized executable whose code image was taken out by
+    /// [`UniversalExecutableRef::without_code_image`], and linked at
+    /// `code` by the system linker and loader, with this engine.
+    ///
+    /// The code is used where it is: the engine neither copies nor
+    /// relocates it, so the libcalls it calls have to be resolved by
+    /// whoever linked it. The executable is checked like it is by
+    /// [`UniversalEngine::load_universal_executable_ref`], except for the
+    /// hash of its code, which isn't part of the executable anymore.
+    ///
+    /// # Safety
+    ///
+    /// `code` must hold the code image of the executable, relocated for
+    /// where it is, for as long as `code.owner` lives.
+    pub unsafe fn load_universal_executable_linked(
+        &self,
+        executable: &UniversalExecutableRef,
+        code: LinkedCode,
+        tunables: &dyn Tunables,
+    ) -> Result<UniversalArtifact, DeserializeError> {
+        self.check_executable_header(&executable.header())?;
+        executable.check_tunables(tunables)?;
+        let executable = unsafe { executable.with_linked_code(&code) };
+        let functions = (executable.function_call_trampolines())
+            .chain(executable.function_bodies())
+            .chain(executable.dynamic_function_trampolines())
+            .map(|function| function.body.as_ptr_range())
+            .map(|range| range.start as usize..range.end as usize)
+            .reduce(|a, b| a.start.min(b.start)..a.end.max(b.end))
+            .unwrap_or(0..0);
+        let code_memory = CodeMemory::linked(functions, code.owner);
+        self.load_archived_executable(&executable, code_memory)
+            .map_err(DeserializeError::Compiler)
+    }
+
+    fn load_archived_executable(
+        &self,
+        executable: &UniversalExecutableRef,
+        mut code_memory: CodeMemory,
     ) -> Result<UniversalArtifact, CompileError> {
         let info = &executable.compile_info;
         let module = &info.module;
         let import_counts: ImportCounts = unrkyv(&module.import_counts);
-        let local_memories = (import_counts.memories as usize..module.memories.len())
+        let local_memories: Vec<_> = (import_counts.memories as usize..module.memories.len())
             .map(|idx| {
                 let idx = MemoryIndex::new(idx);
                 let mty = &module.memories[&idx];
@@ -312,12 +646,18 @@ impl UniversalEngine {
                 (*t, init)
             })
             .collect();
+        let local_tags: Vec<TagType> = module
+            .tags
+            .iter()
+            .skip(import_counts.tags as _)
+            .map(|(_, t)| t.into())
+            .collect();
 
         let passive_data =
             rkyv::Deserialize::deserialize(&module.passive_data, &mut SharedDeserializeMap::new())
                 .map_err(|_| CompileError::Validate("could not deserialize passive data".into()))?;
         let data_segments = executable.data_initializers.iter();
-        let data_segments = data_segments
+        let data_segments: Vec<wasmer_types::OwnedDataInitializer> = data_segments
             .map(|s| DataInitializer::from(s).into())
             .collect();
         let element_segments = unrkyv(&module.table_initializers);
@@ -327,27 +667,30 @@ impl UniversalEngine {
         let import_counts: ImportCounts = unrkyv(&module.import_counts);
         let mut inner_engine = self.inner_mut();
 
-        let local_functions = executable.function_bodies.iter().map(|(_, b)| b.into());
-        let call_trampolines = executable.function_call_trampolines.iter();
-        let dynamic_trampolines = executable.dynamic_function_trampolines.iter();
         let signatures = module
             .signatures
             .values()
             .map(|sig| inner_engine.signatures.register(sig.into()))
             .collect::<PrimaryMap<SignatureIndex, _>>()
             .into_boxed_slice();
-        let (functions, trampolines, dynamic_trampolines, custom_sections) = inner_engine
-            .allocate(
-                local_functions,
-                call_trampolines.map(|(_, b)| b.into()),
-                dynamic_trampolines.map(|(_, b)| b.into()),
-                executable.custom_sections.iter().map(|(_, s)| s.into()),
-                |idx: LocalFunctionIndex| {
-                    let func_idx = import_counts.function_index(idx);
-                    let sig_idx = module.functions[&func_idx];
-                    (sig_idx, signatures[sig_idx])
-                },
-            )?;
+        let function_signature = |idx: LocalFunctionIndex| {
+            let func_idx = import_counts.function_index(idx);
+            let sig_idx = module.functions[&func_idx];
+            (sig_idx, signatures[sig_idx])
+        };
+        let (functions, trampolines, dynamic_trampolines, custom_sections) =
+            if code_memory.is_linked() {
+                linked_code(executable, function_signature)?
+            } else {
+                inner_engine.allocate(
+                    &mut code_memory,
+                    executable.function_bodies(),
+                    executable.function_call_trampolines(),
+                    executable.dynamic_function_trampolines(),
+                    executable.custom_sections(),
+                    function_signature,
+                )?
+            };
         let imports = {
             module
                 .imports
@@ -370,36 +713,43 @@ impl UniversalEngine {
                             VMImportType::Memory(ty, unrkyv(&info.memory_styles[i]))
                         }
                         ImportIndex::Global(i) => VMImportType::Global(unrkyv(&module.globals[i])),
+                        ImportIndex::Tag(i) => VMImportType::Tag((&module.tags[i]).into()),
                     },
                 })
                 .collect()
         };
 
-        let function_relocations = executable.function_relocations.iter();
-        let section_relocations = executable.custom_section_relocations.iter();
-        crate::link_module(
-            &functions,
-            |func_idx, jt_idx| {
-                let func_idx = rkyv::Archived::<LocalFunctionIndex>::new(func_idx.index());
-                let jt_idx = rkyv::Archived::<JumpTable>::new(jt_idx.index());
-                executable.function_jt_offsets[&func_idx][&jt_idx]
-            },
-            function_relocations.map(|(i, r)| (i, r.iter().map(unrkyv))),
-            &custom_sections,
-            section_relocations.map(|(i, r)| (i, r.iter().map(unrkyv))),
-            &unrkyv(&executable.trampolines),
-        );
+        // Linked code is already relocated, and executable.
+        if !code_memory.is_linked() {
+            let function_relocations = executable.function_relocations.iter();
+            let section_relocations = executable.custom_section_relocations.iter();
+            crate::link_module(
+                &functions,
+                |func_idx, jt_idx| {
+                    let func_idx = rkyv::Archived::<LocalFunctionIndex>::new(func_idx.index());
+                    let jt_idx = rkyv::Archived::<JumpTable>::new(jt_idx.index());
+                    executable.function_jt_offsets[&func_idx][&jt_idx]
+                },
+                function_relocations.map(|(i, r)| (i, r.iter().map(unrkyv))),
+                &custom_sections,
+                section_relocations.map(|(i, r)| (i, r.iter().map(unrkyv))),
+                &unrkyv(&executable.trampolines),
+            );
 
-        // Make all code compiled thus far executable.
-        inner_engine.publish_compiled_code();
+            // Make all code compiled thus far executable.
+            code_memory.publish();
+        }
         if let rkyv::option::ArchivedOption::Some(ref d) = executable.debug {
             unsafe {
                 // TODO: safety comment
-                let s = CustomSectionRef::from(&executable.custom_sections[&d.eh_frame]);
-                inner_engine.publish_eh_frame(std::slice::from_raw_parts(
-                    *custom_sections[unrkyv(&d.eh_frame)],
-                    s.bytes.len(),
-                ))?;
+                let eh_frame = unrkyv(&d.eh_frame);
+                publish_eh_frame(
+                    &mut code_memory,
+                    std::slice::from_raw_parts(
+                        *custom_sections[eh_frame],
+                        executable.custom_section(eh_frame).bytes.len(),
+                    ),
+                )?;
             }
         }
         let exports = module
@@ -407,6 +757,14 @@ impl UniversalEngine {
             .iter()
             .map(|(s, i)| (unrkyv(s), unrkyv(i)))
             .collect::<BTreeMap<String, ExportIndex>>();
+        let module_info: Arc<ModuleInfo> =
+            rkyv::Deserialize::deserialize(module, &mut SharedDeserializeMap::new())
+                .map_err(|_| CompileError::Validate("could not deserialize module info".into()))?;
+        let frame_info_registration = register_frame_info(
+            module_info,
+            &functions,
+            unrkyv(&executable.function_frame_info),
+        );
         Ok(UniversalArtifact {
             engine: self.clone(),
             import_counts,
@@ -417,6 +775,11 @@ impl UniversalEngine {
             functions: functions.into_boxed_slice(),
             exports,
             signatures,
+            memory_images: UniversalArtifact::memory_images(
+                &import_counts,
+                &local_memories,
+                &data_segments,
+            ),
             local_memories,
             data_segments,
             passive_data,
@@ -424,10 +787,78 @@ impl UniversalEngine {
             element_segments,
             passive_elements,
             local_globals,
+            local_tags,
+            _frame_info_registration: frame_info_registration,
+            #[cfg(feature = "compiler")]
+            lazy_functions: None,
+            code_memory,
         })
     }
 }
 
+/// The addresses of the code of an executable linked in place, see
+/// [`UniversalEngine::load_universal_executable_linked`], like the ones
+/// [`UniversalEngineInner::allocate`] returns for the code it copies.
+#[allow(clippy::type_complexity)]
+fn linked_code(
+    executable: &UniversalExecutableRef,
+    function_signature: impl Fn(LocalFunctionIndex) -> (SignatureIndex, VMSharedSignatureIndex),
+) -> Result<
+    (
+        PrimaryMap<LocalFunctionIndex, VMLocalFunction>,
+        PrimaryMap<SignatureIndex, VMTrampoline>,
+        PrimaryMap<FunctionIndex, FunctionBodyPtr>,
+        PrimaryMap<SectionIndex, SectionBodyPtr>,
+    ),
+    CompileError,
+> {
+    let body = |function: FunctionBodyRef| function.body.as_ptr() as *const VMFunctionBody;
+    let trampolines = (executable.function_call_trampolines())
+        .map(|trampoline| unsafe {
+            std::mem::transmute::<*const VMFunctionBody, VMTrampoline>(body(trampoline))
+        })
+        .collect::<PrimaryMap<SignatureIndex, _>>();
+    let functions = (executable.function_bodies())
+        .enumerate()
+        .map(|(index, function)| -> Result<_, CompileError> {
+            let (sig_idx, sig) = function_signature(LocalFunctionIndex::new(index));
+            Ok(VMLocalFunction {
+                body: FunctionBodyPtr(body(function)),
+                length: u32::try_from(function.body.len()).map_err(|_| {
+                    CompileError::Codegen("function body length exceeds 4GiB".into())
+                })?,
+                signature: sig,
+                trampoline: trampolines[sig_idx],
+            })
+        })
+        .collect::<Result<_, _>>()?;
+    let dynamic_trampolines = (executable.dynamic_function_trampolines())
+        .map(|trampoline| FunctionBodyPtr(body(trampoline)))
+        .collect();
+    let custom_sections = (executable.custom_sections())
+        .map(|section| SectionBodyPtr(section.bytes.as_ptr()))
+        .collect();
+    Ok((functions, trampolines, dynamic_trampolines, custom_sections))
+}
+
+/// Register the frame information of the functions of a loaded module, so
+/// that traps can be mapped back to them.
+fn register_frame_info(
+    module: Arc<ModuleInfo>,
+    functions: &PrimaryMap<LocalFunctionIndex, VMLocalFunction>,
+    frame_infos: PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>,
+) -> Option<GlobalFrameInfoRegistration> {
+    let extents = functions
+        .values()
+        .map(|function| FunctionExtent {
+            address: FunctionBodyPtr(function.body.0),
+            length: usize::try_from(function.length).unwrap(),
+        })
+        .collect::<PrimaryMap<LocalFunctionIndex, _>>()
+        .into_boxed_slice();
+    GlobalFrameInfo::register(module, &extents, frame_infos)
+}
+
 impl Engine for UniversalEngine {
     /// The target
     fn target(&self) -> &Target {
@@ -472,6 +903,11 @@ impl Engine for UniversalEngine {
         binary: &[u8],
         tunables: &dyn Tunables,
     ) -> Result<Box<dyn wasmer_engine::Executable>, CompileError> {
+        if self.inner().lazy_compilation {
+            return self
+                .compile_universal_lazy(binary, tunables)
+                .map(|ex| Box::new(ex) as _);
+        }
         self.compile_universal(binary, tunables)
             .map(|ex| Box::new(ex) as _)
     }
@@ -479,8 +915,9 @@ impl Engine for UniversalEngine {
     fn load(
         &self,
         executable: &(dyn wasmer_engine::Executable),
-    ) -> Result<Arc<dyn wasmer_vm::Artifact>, CompileError> {
-        executable.load(self)
+        tunables: &dyn Tunables,
+    ) -> Result<Arc<dyn wasmer_vm::Artifact>, DeserializeError> {
+        executable.load(self, tunables)
     }
 
     fn id(&self) -> &EngineId {
@@ -496,18 +933,23 @@ impl Engine for UniversalEngine {
 pub struct UniversalEngineInner {
     /// The compiler
     #[cfg(feature = "compiler")]
-    compiler: Option<Box<dyn Compiler>>,
+    compiler: Option<Arc<dyn Compiler>>,
+    /// Whether the functions of the modules are compiled on their first
+    /// call rather than with the rest of the modules.
+    #[cfg(feature = "compiler")]
+    pub(crate) lazy_compilation: bool,
+    /// Whether the code of the serialized executables is checked against
+    /// its hash when they are loaded.
+    pub(crate) check_code_hash: bool,
     /// The features to compile the Wasm module with
     features: Features,
-    /// The code memory is responsible of publishing the compiled
-    /// functions to memory.
-    code_memory: Vec<CodeMemory>,
     /// The signature registry is used mainly to operate with trampolines
     /// performantly.
     pub(crate) signatures: SignatureRegistry,
     /// The backing storage of `VMFuncRef`s. This centralized store ensures that 2
     /// functions with the same `VMCallerCheckedAnyfunc` will have the same `VMFuncRef`.
-    /// It also guarantees that the `VMFuncRef`s stay valid until the engine is dropped.
+    /// It also guarantees that the `VMFuncRef`s stay valid until the module of the
+    /// function is dropped, or the engine for host functions.
     func_data: Arc<FuncDataRegistry>,
 }
 
@@ -521,6 +963,14 @@ impl UniversalEngineInner {
         Ok(&**self.compiler.as_ref().unwrap())
     }
 
+    /// Gets a shared reference to the compiler associated to this engine,
+    /// for compiling without holding the engine locked.
+    #[cfg(feature = "compiler")]
+    pub(crate) fn shared_compiler(&self) -> Result<Arc<dyn Compiler>, CompileError> {
+        self.compiler()?;
+        Ok(Arc::clone(self.compiler.as_ref().unwrap()))
+    }
+
     /// Validate the module
     #[cfg(feature = "compiler")]
     pub fn validate<'data>(&self, data: &'data [u8]) -> Result<(), CompileError> {
@@ -541,10 +991,13 @@ impl UniversalEngineInner {
         &self.features
     }
 
-    /// Allocate compiled functions into memory
+    /// Allocate compiled functions into the given code memory.
+    ///
+    /// The returned pointers stay valid for as long as `code_memory` is alive.
     #[allow(clippy::type_complexity)]
     pub(crate) fn allocate<'a>(
-        &mut self,
+        &self,
+        code_memory: &mut CodeMemory,
         local_functions: impl ExactSizeIterator<Item = FunctionBodyRef<'a>>,
         call_trampolines: impl ExactSizeIterator<Item = FunctionBodyRef<'a>>,
         dynamic_trampolines: impl ExactSizeIterator<Item = FunctionBodyRef<'a>>,
@@ -559,7 +1012,6 @@ impl UniversalEngineInner {
         ),
         CompileError,
     > {
-        let code_memory = &mut self.code_memory;
         let function_count = local_functions.len();
         let call_trampoline_count = call_trampolines.len();
         let function_bodies = call_trampolines
@@ -579,9 +1031,6 @@ impl UniversalEngineInner {
             }
             section_types.push(section.protection);
         }
-        code_memory.push(CodeMemory::new());
-        let code_memory = self.code_memory.last_mut().expect("infallible");
-
         let (mut allocated_functions, allocated_executable_sections, allocated_data_sections) =
             code_memory
                 .allocate(
@@ -655,26 +1104,81 @@ impl UniversalEngineInner {
         ))
     }
 
-    /// Make memory containing compiled code executable.
-    pub(crate) fn publish_compiled_code(&mut self) {
-        self.code_memory.last_mut().unwrap().publish();
-    }
-
-    /// Register DWARF-type exception handling information associated with the code.
-    pub(crate) fn publish_eh_frame(&mut self, eh_frame: &[u8]) -> Result<(), CompileError> {
-        self.code_memory
-            .last_mut()
-            .unwrap()
-            .unwind_registry_mut()
-            .publish(eh_frame)
-            .map_err(|e| {
-                CompileError::Resource(format!("Error while publishing the unwind code: {}", e))
-            })?;
-        Ok(())
-    }
-
     /// Shared func metadata registry.
     pub(crate) fn func_data(&self) -> &Arc<FuncDataRegistry> {
         &self.func_data
     }
 }
+
+/// Whether all the features enabled in `used` are enabled in `supported`.
+fn supports_features(supported: &Features, used: &Features) -> bool {
+    // Destructure the features so that adding one can't be forgotten
+    // here.
+    let Features {
+        threads,
+        reference_types,
+        simd,
+        bulk_memory,
+        multi_value,
+        tail_call,
+        module_linking,
+        multi_memory,
+        memory64,
+        exceptions,
+    } = *used;
+    (!threads || supported.threads)
+        && (!reference_types || supported.reference_types)
+        && (!simd || supported.simd)
+        && (!bulk_memory || supported.bulk_memory)
+        && (!multi_value || supported.multi_value)
+        && (!tail_call || supported.tail_call)
+        && (!module_linking || supported.module_linking)
+        && (!multi_memory || supported.multi_memory)
+        && (!memory64 || supported.memory64)
+        && (!exceptions || supported.exceptions)
+}
+
+/// Map the code image at `range` in `file` privately, for a [`CodeMemory`]
+/// to use it in place.
+///
+/// Returns `None` if the image can't be mapped at the start of a page, or
+/// if the pages of the file can't be made executable, e.g. because it is
+/// on a file system mounted `noexec`.
+#[cfg(unix)]
+fn map_code_image(file: &File, range: Range<usize>) -> Option<memmap2::MmapMut> {
+    let page_size = region::page::size();
+    if range.is_empty() || CODE_IMAGE_PAGE_SIZE % page_size != 0 || range.start % page_size != 0 {
+        return None;
+    }
+    let mut image = unsafe {
+        memmap2::MmapOptions::new()
+            .offset(range.start as u64)
+            .len(range.len())
+            .map_copy(file)
+            .ok()?
+    };
+    let ptr = image.as_mut_ptr();
+    unsafe {
+        region::protect(ptr, page_size, region::Protection::READ_EXECUTE).ok()?;
+        region::protect(ptr, page_size, region::Protection::READ_WRITE).ok()?;
+    }
+    Some(image)
+}
+
+/// Executable copy-on-write file mappings aren't used on Windows, where
+/// they would need to be created as executable.
+#[cfg(not(unix))]
+fn map_code_image(_file: &File, _range: Range<usize>) -> Option<memmap2::MmapMut> {
+    None
+}
+
+/// Register DWARF-type exception handling information associated with the code.
+fn publish_eh_frame(code_memory: &mut CodeMemory, eh_frame: &[u8]) -> Result<(), CompileError> {
+    code_memory
+        .unwind_registry_mut()
+        .publish(eh_frame)
+        .map_err(|e| {
+            CompileError::Resource(format!("Error while publishing the unwind code: {}", e))
+        })?;
+    Ok(())
+}
@@ -1,33 +1,96 @@
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 use std::sync::Arc;
 
+use crate::code_memory::CODE_IMAGE_PAGE_SIZE;
+use crate::CodeMemory;
 use enumset::EnumSet;
 use rkyv::de::deserializers::SharedDeserializeMap;
 use rkyv::ser::serializers::{
     AllocScratchError, AllocSerializer, CompositeSerializerError, SharedSerializeMapError,
 };
+use rkyv::with::Skip;
 use wasmer_compiler::{
-    CompileError, CompileModuleInfo, CompiledFunctionFrameInfo, CpuFeature, CustomSection, Dwarf,
-    Features, FunctionBody, JumpTableOffsets, Relocation, SectionIndex, TrampolinesSection,
+    CompileError, CompileModuleInfo, CompiledFunctionFrameInfo, CompiledFunctionUnwindInfo,
+    CpuFeature, CustomSection, CustomSectionProtection, CustomSectionRef, Dwarf, Features,
+    FunctionBody, FunctionBodyRef, JumpTableOffsets, Relocation, RelocationTarget, SectionBody,
+    SectionIndex, Symbol, TrampolinesSection,
 };
 use wasmer_engine::{DeserializeError, Engine};
-use wasmer_types::entity::PrimaryMap;
+use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{
     ExportIndex, FunctionIndex, ImportIndex, LocalFunctionIndex, OwnedDataInitializer,
     SignatureIndex,
 };
-use wasmer_vm::Artifact;
+use wasmer_vm::libcalls::LibCall;
+use wasmer_vm::{Artifact, MemoryStyle, Tunables};
 
-const MAGIC_HEADER: [u8; 32] = {
-    let value = *b"\0wasmer-universal\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF";
-    let _length_must_be_multiple_of_16: bool = [true][value.len() % 16];
-    value
-};
+/// The bytes starting the serialized executables.
+const MAGIC: &[u8; 17] = b"\0wasmer-universal";
+
+/// The version of the format of the serialized executables, which
+/// follows [`MAGIC`].
+const FORMAT_VERSION: u8 = 5;
+
+/// The length of the preamble of the serialized executables: the magic
+/// bytes, the version of the format, padding, and the length of the
+/// header.
+const PREAMBLE_LENGTH: usize = 32;
+
+/// The length of the positions ending the serialized executables.
+const TRAILER_LENGTH: usize = 24;
+
+/// The header of a serialized [`UniversalExecutable`], describing how it
+/// was produced and what it needs to be loaded.
+///
+/// It can be read without deserializing the executable, which is only
+/// done once the header is checked against the loading engine.
+#[derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct ExecutableHeader {
+    /// The version of the engine which serialized the executable.
+    pub engine_version: String,
+    /// The name of the compiler which compiled the executable.
+    pub compiler: String,
+    /// The version of the compiler which compiled the executable.
+    pub compiler_version: String,
+    /// The triple of the target the executable was compiled for.
+    pub triple: String,
+    /// The CPU features used by the code of the executable.
+    pub cpu_features: u64,
+    /// Whether the code of the executable checks the epoch of the engine.
+    pub epoch_interruption: bool,
+    /// The WebAssembly features the module was compiled with.
+    pub features: Features,
+    /// The styles the tunables gave to the memories of the module.
+    pub memory_styles: Vec<MemoryStyle>,
+    /// The BLAKE3 hash of the serialized executable following the
+    /// header, except for its code image.
+    pub metadata_hash: [u8; 32],
+    /// The BLAKE3 hash of the code image, which is checked when the
+    /// executable is loaded, see
+    /// [`UniversalEngine::check_executable`](crate::UniversalEngine::check_executable).
+    pub code_hash: [u8; 32],
+}
 
 /// A 0-copy view of the encoded `UniversalExecutable` payload.
 #[derive(Clone, Copy)]
 pub struct UniversalExecutableRef<'a> {
     buffer: &'a [u8],
+    header: &'a ArchivedExecutableHeader,
     archive: &'a ArchivedUniversalExecutable,
+    code: &'a ArchivedCodeIndex,
+    code_image: usize,
+    linked: Option<LinkedImage>,
+}
+
+/// Where the code image of a serialized executable is once it is linked
+/// in place, see [`LinkedCode`].
+#[derive(Clone, Copy)]
+struct LinkedImage {
+    text: usize,
+    data: usize,
+    data_start: usize,
 }
 
 impl<'a> std::ops::Deref for UniversalExecutableRef<'a> {
@@ -37,19 +100,57 @@ impl<'a> std::ops::Deref for UniversalExecutableRef<'a> {
     }
 }
 
+/// The layout of a serialized executable, read from its preamble and
+/// trailer.
+struct Layout {
+    header_length: usize,
+    payload: usize,
+    code_image: usize,
+    position: usize,
+    code_index: usize,
+}
+
+impl Layout {
+    fn read(data: &[u8]) -> Self {
+        let u64_at = |offset: usize| {
+            let mut value = [0u8; 8];
+            value.copy_from_slice(&data[offset..offset + 8]);
+            u64::from_le_bytes(value) as usize
+        };
+        let header_length = u64_at(PREAMBLE_LENGTH - 8);
+        Self {
+            header_length,
+            payload: PREAMBLE_LENGTH.saturating_add(round_up(header_length, 16)),
+            code_image: u64_at(data.len() - TRAILER_LENGTH),
+            position: u64_at(data.len() - 16),
+            code_index: u64_at(data.len() - 8),
+        }
+    }
+}
+
+fn round_up(size: usize, multiple: usize) -> usize {
+    size.saturating_add(multiple - 1) / multiple * multiple
+}
+
 impl<'a> UniversalExecutableRef<'a> {
     /// Verify the buffer for whether it is a valid `UniversalExecutable`.
     pub fn verify_serialized(data: &[u8]) -> Result<(), &'static str> {
-        if !data.starts_with(&MAGIC_HEADER) {
+        if !data.starts_with(MAGIC) {
             return Err("the provided bytes are not wasmer-universal");
         }
-        if data.len() < MAGIC_HEADER.len() + 8 {
+        if data.len() < PREAMBLE_LENGTH + TRAILER_LENGTH {
             return Err("the data buffer is too small to be valid");
         }
-        let (remaining, position) = data.split_at(data.len() - 8);
-        let mut position_value = [0u8; 8];
-        position_value.copy_from_slice(position);
-        if u64::from_le_bytes(position_value) > remaining.len() as u64 {
+        if data[MAGIC.len()] != FORMAT_VERSION {
+            return Err("the version of the format is not supported");
+        }
+        let layout = Layout::read(data);
+        if layout.header_length < std::mem::size_of::<ArchivedExecutableHeader>()
+            || layout.payload > layout.code_image
+            || layout.code_image > data.len() - TRAILER_LENGTH
+            || layout.position > layout.code_image - layout.payload
+            || layout.code_index > layout.code_image - layout.payload
+        {
             return Err("the buffer is malformed");
         }
         // TODO(0-copy): bytecheck too.
@@ -63,45 +164,352 @@ impl<'a> UniversalExecutableRef<'a> {
     /// Right now we are not doing any extra work for validation, but
     /// `rkyv` has an option to do bytecheck on the serialized data before
     /// serializing (via `rkyv::check_archived_value`).
+    ///
+    /// The header is checked though: the executable must have been
+    /// serialized by this version of the engine, and its contents but
+    /// the code image must match their hash. Hashing the code image takes
+    /// time proportional to the size of the code, so it is left to
+    /// [`UniversalExecutableRef::check_code_hash`], which the engine calls
+    /// when loading the executable.
     pub unsafe fn deserialize(
         data: &'a [u8],
     ) -> Result<UniversalExecutableRef<'a>, DeserializeError> {
+        if data.starts_with(MAGIC) && data.len() > MAGIC.len() {
+            let version = data[MAGIC.len()];
+            if version != FORMAT_VERSION {
+                return Err(DeserializeError::IncompatibleFormat {
+                    expected: FORMAT_VERSION,
+                    found: version,
+                });
+            }
+        }
         Self::verify_serialized(data).map_err(|e| DeserializeError::Incompatible(e.to_string()))?;
-        let (archive, position) = data.split_at(data.len() - 8);
-        let mut position_value = [0u8; 8];
-        position_value.copy_from_slice(position);
-        let (_, data) = archive.split_at(MAGIC_HEADER.len());
+        let layout = Layout::read(data);
+        let header = unsafe {
+            rkyv::archived_root::<ExecutableHeader>(
+                &data[PREAMBLE_LENGTH..PREAMBLE_LENGTH + layout.header_length],
+            )
+        };
+        if header.engine_version.as_str() != crate::VERSION {
+            return Err(DeserializeError::IncompatibleEngine {
+                expected: crate::VERSION.to_string(),
+                found: header.engine_version.to_string(),
+            });
+        }
+        let mut metadata_hash = blake3::Hasher::new();
+        metadata_hash.update(&data[layout.payload..layout.code_image]);
+        metadata_hash.update(&data[data.len() - TRAILER_LENGTH..]);
+        if metadata_hash.finalize() != header.metadata_hash {
+            return Err(DeserializeError::ContentHashMismatch);
+        }
+        let archive = &data[layout.payload..layout.code_image];
         Ok(UniversalExecutableRef {
             buffer: data,
-            archive: rkyv::archived_value::<UniversalExecutable>(
-                data,
-                u64::from_le_bytes(position_value) as usize,
-            ),
+            header,
+            archive: unsafe {
+                rkyv::archived_value::<UniversalExecutable>(archive, layout.position)
+            },
+            code: unsafe { rkyv::archived_value::<CodeIndex>(archive, layout.code_index) },
+            code_image: layout.code_image,
+            linked: None,
         })
     }
 
+    /// The header of the serialized executable.
+    pub fn header(&self) -> ExecutableHeader {
+        unrkyv(self.header)
+    }
+
+    /// Check that the code image matches its hash.
+    pub fn check_code_hash(&self) -> Result<(), DeserializeError> {
+        if blake3::hash(&self.buffer[self.code_image()]) != self.header.code_hash {
+            return Err(DeserializeError::ContentHashMismatch);
+        }
+        Ok(())
+    }
+
+    /// Check that the code image holds all the code the executable
+    /// locates in it, which it doesn't once it is taken out by
+    /// [`UniversalExecutableRef::without_code_image`].
+    pub fn check_code_image(&self) -> Result<(), DeserializeError> {
+        let code = self.code;
+        let functions = (code.function_bodies.values())
+            .chain(code.function_call_trampolines.values())
+            .chain(code.dynamic_function_trampolines.values())
+            .map(|function| function.start + function.len);
+        let sections = (code.custom_sections.values()).map(|section| section.start + section.len);
+        let len = self.code_image().len() as u64;
+        if functions.chain(sections).any(|end| end > len) {
+            return Err(DeserializeError::CorruptedBinary(
+                "the code image doesn't hold the code of the executable".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check that the memories of the module were compiled for the
+    /// styles `tunables` gives them.
+    pub fn check_tunables(&self, tunables: &dyn Tunables) -> Result<(), DeserializeError> {
+        let module = &self.compile_info.module;
+        let styles = self.header.memory_styles.iter();
+        for (index, ((_, memory), style)) in module.memories.iter().zip(styles).enumerate() {
+            let expected = tunables.memory_style(&unrkyv(memory));
+            let found: MemoryStyle = unrkyv(style);
+            if expected != found {
+                return Err(DeserializeError::IncompatibleMemoryStyle {
+                    index: index as u32,
+                    expected,
+                    found,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// The range of the serialized executable holding the code image,
+    /// which is the code of the executable laid out for a
+    /// [`CodeMemory`](crate::CodeMemory) to use it in place.
+    pub(crate) fn code_image(&self) -> std::ops::Range<usize> {
+        self.code_image..self.buffer.len() - TRAILER_LENGTH
+    }
+
+    /// The serialized executable without its code image, for the code
+    /// to be stored apart, like it is in the sections of an object file.
+    ///
+    /// The result deserializes like an executable with an empty code
+    /// image. It can only be loaded once its code is given back, with
+    /// [`UniversalExecutableRef::with_code_image`], or linked in place,
+    /// see [`UniversalEngine::load_universal_executable_linked`](crate::UniversalEngine::load_universal_executable_linked).
+    pub fn without_code_image(&self) -> Vec<u8> {
+        let image = self.code_image();
+        let mut metadata = Vec::with_capacity(self.buffer.len() - image.len());
+        metadata.extend_from_slice(&self.buffer[..image.start]);
+        metadata.extend_from_slice(&self.buffer[image.end..]);
+        metadata
+    }
+
+    /// The serialized executable, taken out of its code image by
+    /// [`UniversalExecutableRef::without_code_image`], with the code image
+    /// holding the executable code `text` and the data sections `data`.
+    ///
+    /// The data sections follow the executable code on a new page in the
+    /// image, which is padded accordingly.
+    pub fn with_code_image(
+        &self,
+        text: &[u8],
+        data: &[u8],
+    ) -> Result<rkyv::AlignedVec, DeserializeError> {
+        if !self.code_image().is_empty() {
+            return Err(DeserializeError::Incompatible(
+                "the executable already has a code image".to_string(),
+            ));
+        }
+        let data_start = self.data_start().unwrap_or(text.len());
+        if text.len() > data_start || (self.data_start().is_none() && !data.is_empty()) {
+            return Err(DeserializeError::CorruptedBinary(
+                "the code doesn't fit the layout of the code image".to_string(),
+            ));
+        }
+        let trailer = self.buffer.len() - TRAILER_LENGTH;
+        let mut buffer =
+            rkyv::AlignedVec::with_capacity(self.buffer.len() + data_start + data.len());
+        buffer.extend_from_slice(&self.buffer[..trailer]);
+        buffer.extend_from_slice(text);
+        buffer.resize(trailer + data_start, 0);
+        buffer.extend_from_slice(data);
+        buffer.extend_from_slice(&self.buffer[trailer..]);
+        Ok(buffer)
+    }
+
+    /// Use the code image linked in place at `code` rather than the one of
+    /// the buffer, which is empty, see
+    /// [`UniversalEngine::load_universal_executable_linked`](crate::UniversalEngine::load_universal_executable_linked).
+    ///
+    /// # Safety
+    ///
+    /// `code` must hold the code image of the executable.
+    pub(crate) unsafe fn with_linked_code(mut self, code: &LinkedCode) -> Self {
+        self.linked = Some(LinkedImage {
+            text: code.text as usize,
+            data: code.data as usize,
+            data_start: self.data_start().unwrap_or(usize::MAX),
+        });
+        self
+    }
+
+    /// The libcalls the code of the executable refers to.
+    pub fn libcalls(&self) -> HashSet<LibCall> {
+        let function_relocations = self.function_relocations.iter();
+        let section_relocations = self.custom_section_relocations.iter();
+        function_relocations
+            .map(|(_, relocations)| relocations)
+            .chain(section_relocations.map(|(_, relocations)| relocations))
+            .flat_map(|relocations| relocations.iter())
+            .filter_map(|relocation| match unrkyv(&relocation.reloc_target) {
+                RelocationTarget::LibCall(libcall) => Some(libcall),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The start of the data sections in the code image, which follow
+    /// its executable code.
+    fn data_start(&self) -> Option<usize> {
+        (self.code.custom_sections.values())
+            .filter(|code| {
+                unrkyv::<CustomSectionProtection>(&code.protection)
+                    != CustomSectionProtection::ReadExecute
+            })
+            .map(|code| code.start as usize)
+            .min()
+    }
+
+    /// The `len` bytes at `start` in the code image.
+    fn image(&self, start: u64, len: u64) -> &'a [u8] {
+        let (start, len) = (start as usize, len as usize);
+        match self.linked {
+            None => &self.buffer[self.code_image + start..][..len],
+            Some(linked) => {
+                let address = if start >= linked.data_start {
+                    linked.data + (start - linked.data_start)
+                } else {
+                    linked.text + start
+                };
+                unsafe { std::slice::from_raw_parts(address as *const u8, len) }
+            }
+        }
+    }
+
+    fn function_body(&self, code: &'a ArchivedFunctionCode) -> FunctionBodyRef<'a> {
+        FunctionBodyRef {
+            body: self.image(code.start, code.len),
+            unwind_info: code.unwind_info.as_ref().map(Into::into),
+        }
+    }
+
+    /// The bodies of the local functions, in the code image.
+    pub(crate) fn function_bodies(&self) -> impl ExactSizeIterator<Item = FunctionBodyRef<'a>> {
+        let this = *self;
+        let bodies = self.code.function_bodies.values();
+        bodies.map(move |code| this.function_body(code))
+    }
+
+    /// The function call trampolines, in the code image.
+    pub(crate) fn function_call_trampolines(
+        &self,
+    ) -> impl ExactSizeIterator<Item = FunctionBodyRef<'a>> {
+        let this = *self;
+        let trampolines = self.code.function_call_trampolines.values();
+        trampolines.map(move |code| this.function_body(code))
+    }
+
+    /// The dynamic function trampolines, in the code image.
+    pub(crate) fn dynamic_function_trampolines(
+        &self,
+    ) -> impl ExactSizeIterator<Item = FunctionBodyRef<'a>> {
+        let this = *self;
+        let trampolines = self.code.dynamic_function_trampolines.values();
+        trampolines.map(move |code| this.function_body(code))
+    }
+
+    /// The custom section `index`, in the code image.
+    pub(crate) fn custom_section(&self, index: SectionIndex) -> CustomSectionRef<'a> {
+        let code = &self.code.custom_sections[&rkyv::Archived::<SectionIndex>::new(index.index())];
+        CustomSectionRef {
+            protection: unrkyv(&code.protection),
+            bytes: self.image(code.start, code.len),
+        }
+    }
+
+    /// The custom sections, in the code image.
+    pub(crate) fn custom_sections(&self) -> impl ExactSizeIterator<Item = CustomSectionRef<'a>> {
+        let this = *self;
+        let sections = self.code.custom_sections.iter();
+        sections.map(move |(index, _)| this.custom_section(index))
+    }
+
     // TODO(0-copy): this should never fail.
     /// Convert this reference to an owned `UniversalExecutable` value.
     pub fn to_owned(self) -> Result<UniversalExecutable, DeserializeError> {
         let mut deserializer = SharedDeserializeMap::new();
-        rkyv::Deserialize::deserialize(self.archive, &mut deserializer)
-            .map_err(|e| DeserializeError::CorruptedBinary(format!("{:?}", e)))
+        let mut executable: UniversalExecutable =
+            rkyv::Deserialize::deserialize(self.archive, &mut deserializer)
+                .map_err(|e| DeserializeError::CorruptedBinary(format!("{:?}", e)))?;
+        let body = |code| {
+            let body = self.function_body(code);
+            FunctionBody {
+                body: body.body.to_vec(),
+                unwind_info: unrkyv(&code.unwind_info),
+            }
+        };
+        executable.function_bodies = self.code.function_bodies.values().map(body).collect();
+        executable.function_call_trampolines = (self.code.function_call_trampolines.values())
+            .map(body)
+            .collect();
+        executable.dynamic_function_trampolines = (self.code.dynamic_function_trampolines.values())
+            .map(body)
+            .collect();
+        executable.custom_sections = (self.code.custom_sections.iter())
+            .map(|(index, _)| {
+                let section = self.custom_section(index);
+                CustomSection {
+                    protection: section.protection,
+                    bytes: SectionBody::new_with_vec(section.bytes.to_vec()),
+                    relocations: executable.custom_section_relocations[index].clone(),
+                }
+            })
+            .collect();
+        Ok(executable)
     }
 }
 
+/// Where the code of a serialized executable is in its code image,
+/// which holds it instead of the archive of the [`UniversalExecutable`].
+#[derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+struct CodeIndex {
+    function_bodies: PrimaryMap<LocalFunctionIndex, FunctionCode>,
+    function_call_trampolines: PrimaryMap<SignatureIndex, FunctionCode>,
+    dynamic_function_trampolines: PrimaryMap<FunctionIndex, FunctionCode>,
+    custom_sections: PrimaryMap<SectionIndex, SectionCode>,
+}
+
+/// The body of a function in a code image.
+#[derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+struct FunctionCode {
+    start: u64,
+    len: u64,
+    unwind_info: Option<CompiledFunctionUnwindInfo>,
+}
+
+/// A custom section in a code image.
+#[derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+struct SectionCode {
+    start: u64,
+    len: u64,
+    protection: CustomSectionProtection,
+}
+
 /// A wasm module compiled to some shape, ready to be loaded with `UniversalEngine` to produce an
 /// `UniversalArtifact`.
 ///
 /// This is the result obtained after validating and compiling a WASM module with any of the
 /// supported compilers. This type falls in-between a module and [`Artifact`](crate::Artifact).
+///
+/// The code of the functions, the trampolines and the custom sections
+/// isn't part of the archive of a serialized executable, but of its
+/// code image, where a `CodeIndex` locates it.
 #[derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
 pub struct UniversalExecutable {
+    #[with(Skip)]
     pub(crate) function_bodies: PrimaryMap<LocalFunctionIndex, FunctionBody>,
     pub(crate) function_relocations: PrimaryMap<LocalFunctionIndex, Vec<Relocation>>,
     pub(crate) function_jt_offsets: PrimaryMap<LocalFunctionIndex, JumpTableOffsets>,
     pub(crate) function_frame_info: PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>,
+    #[with(Skip)]
     pub(crate) function_call_trampolines: PrimaryMap<SignatureIndex, FunctionBody>,
+    #[with(Skip)]
     pub(crate) dynamic_function_trampolines: PrimaryMap<FunctionIndex, FunctionBody>,
+    #[with(Skip)]
     pub(crate) custom_sections: PrimaryMap<SectionIndex, CustomSection>,
     pub(crate) custom_section_relocations: PrimaryMap<SectionIndex, Vec<Relocation>>,
     // The section indices corresponding to the Dwarf debug info
@@ -111,6 +519,175 @@ pub struct UniversalExecutable {
     pub(crate) compile_info: CompileModuleInfo,
     pub(crate) data_initializers: Vec<OwnedDataInitializer>,
     pub(crate) cpu_features: u64,
+    pub(crate) epoch_interruption: bool,
+    // The name and version of the compiler and the triple of the target,
+    // for the header
+    pub(crate) compiler: String,
+    pub(crate) compiler_version: String,
+    pub(crate) triple: String,
+}
+
+impl UniversalExecutable {
+    /// The header of the executable once serialized, with the hashes of
+    /// the serialized contents.
+    fn header(&self, metadata_hash: [u8; 32], code_hash: [u8; 32]) -> ExecutableHeader {
+        ExecutableHeader {
+            engine_version: crate::VERSION.to_string(),
+            compiler: self.compiler.clone(),
+            compiler_version: self.compiler_version.clone(),
+            triple: self.triple.clone(),
+            cpu_features: self.cpu_features,
+            epoch_interruption: self.epoch_interruption,
+            features: self.compile_info.features.clone(),
+            memory_styles: self.compile_info.memory_styles.values().cloned().collect(),
+            metadata_hash,
+            code_hash,
+        }
+    }
+
+    /// Locate the code of the executable in its code `image`.
+    fn code_index(&self, image: &CodeImage) -> CodeIndex {
+        let starts = image
+            .symbols
+            .iter()
+            .map(|(symbol, range)| (symbol, range.start as u64))
+            .collect::<HashMap<_, _>>();
+        let function = |symbol, body: &FunctionBody| FunctionCode {
+            start: starts[&symbol],
+            len: body.body.len() as u64,
+            unwind_info: body.unwind_info.clone(),
+        };
+        CodeIndex {
+            function_bodies: (self.function_bodies.iter())
+                .map(|(index, body)| function(Symbol::LocalFunction(index), body))
+                .collect(),
+            function_call_trampolines: (self.function_call_trampolines.iter())
+                .map(|(index, body)| function(Symbol::FunctionCallTrampoline(index), body))
+                .collect(),
+            dynamic_function_trampolines: (self.dynamic_function_trampolines.iter())
+                .map(|(index, body)| function(Symbol::DynamicFunctionTrampoline(index), body))
+                .collect(),
+            custom_sections: (self.custom_sections.iter())
+                .map(|(index, section)| SectionCode {
+                    start: starts[&Symbol::Section(index)],
+                    len: section.bytes.len() as u64,
+                    protection: section.protection,
+                })
+                .collect(),
+        }
+    }
+
+    /// Lay out the code of the executable like the code image of its
+    /// serialized form.
+    pub fn code_image(&self) -> CodeImage {
+        let function_symbols = self
+            .function_call_trampolines
+            .keys()
+            .map(Symbol::FunctionCallTrampoline)
+            .chain(self.function_bodies.keys().map(Symbol::LocalFunction))
+            .chain(
+                self.dynamic_function_trampolines
+                    .keys()
+                    .map(Symbol::DynamicFunctionTrampoline),
+            );
+        let functions = self
+            .function_call_trampolines
+            .values()
+            .chain(self.function_bodies.values())
+            .chain(self.dynamic_function_trampolines.values())
+            .map(FunctionBodyRef::from)
+            .collect::<Vec<_>>();
+        let (executable_sections, data_sections): (Vec<_>, Vec<_>) = self
+            .custom_sections
+            .iter()
+            .map(|(index, section)| (index, CustomSectionRef::from(section)))
+            .partition(|(_, section)| section.protection == CustomSectionProtection::ReadExecute);
+        let executable_refs = executable_sections
+            .iter()
+            .map(|(_, s)| *s)
+            .collect::<Vec<_>>();
+        let data_refs = data_sections.iter().map(|(_, s)| *s).collect::<Vec<_>>();
+        let (bytes, layout) = CodeMemory::image(&functions, &executable_refs, &data_refs);
+
+        let mut symbols = function_symbols.zip(layout.functions).collect::<Vec<_>>();
+        let mut section_starts = vec![0; self.custom_sections.len()];
+        let sections = executable_sections
+            .iter()
+            .zip(layout.executable_sections)
+            .chain(data_sections.iter().zip(layout.data_sections));
+        for ((index, section), range) in sections {
+            section_starts[index.index()] = range.start;
+            symbols.push((
+                Symbol::Section(*index),
+                range.start..range.start + section.bytes.len(),
+            ));
+        }
+
+        let first_function = self.function_call_trampolines.len();
+        let function_starts = &symbols[first_function..first_function + self.function_bodies.len()];
+        let relocations = self
+            .function_relocations
+            .iter()
+            .map(|(index, relocations)| (function_starts[index.index()].1.start, relocations))
+            .chain(
+                self.custom_section_relocations
+                    .iter()
+                    .map(|(index, relocations)| (section_starts[index.index()], relocations)),
+            )
+            .flat_map(|(start, relocations)| {
+                relocations.iter().map(move |relocation| {
+                    let mut relocation = relocation.clone();
+                    relocation.offset += start as u32;
+                    if let RelocationTarget::JumpTable(index, jt) = relocation.reloc_target {
+                        relocation.reloc_target = RelocationTarget::LocalFunc(index);
+                        relocation.addend += i64::from(self.function_jt_offsets[index][jt]);
+                    }
+                    relocation
+                })
+            })
+            .collect();
+
+        CodeImage {
+            bytes,
+            executable_len: layout.start_of_nonexecutable_pages,
+            symbols,
+            relocations,
+        }
+    }
+}
+
+/// The code of a [`UniversalExecutable`], laid out in an image, with
+/// the relocations the loader applies to it.
+pub struct CodeImage {
+    /// The bytes of the image.
+    pub bytes: Vec<u8>,
+    /// The length of the executable code starting the image, which the
+    /// read-only data sections follow.
+    pub executable_len: usize,
+    /// Where the functions, the trampolines and the custom sections are
+    /// in the image.
+    pub symbols: Vec<(Symbol, Range<usize>)>,
+    /// The relocations of the image, at their offsets in the image.
+    ///
+    /// The relocations to the jump tables of the functions target the
+    /// functions, with the offsets of the tables added to their addends.
+    pub relocations: Vec<Relocation>,
+}
+
+/// The code image of a serialized executable, placed and relocated by
+/// the system linker and loader rather than by the engine, like the code
+/// of the object files linked into programs.
+///
+/// The image is split in two: its executable code, which starts it, and
+/// its data sections, which follow it on a new page in the image.
+pub struct LinkedCode {
+    /// The address of the executable code of the image.
+    pub text: *const u8,
+    /// The address of the data sections of the image, if it has some.
+    pub data: *const u8,
+    /// What keeps the code where it is for as long as it is used, such
+    /// as the handle of the shared object holding it.
+    pub owner: Box<dyn Any + Send + Sync>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -124,18 +701,29 @@ pub enum ExecutableSerializeError {
             SharedSerializeMapError,
         >,
     ),
+    #[error("could not serialize the executable header")]
+    Header(
+        #[source]
+        CompositeSerializerError<
+            std::convert::Infallible,
+            AllocScratchError,
+            SharedSerializeMapError,
+        >,
+    ),
 }
 
 impl wasmer_engine::Executable for UniversalExecutable {
     fn load(
         &self,
         engine: &(dyn Engine + 'static),
-    ) -> Result<std::sync::Arc<dyn Artifact>, CompileError> {
+        _tunables: &dyn Tunables,
+    ) -> Result<std::sync::Arc<dyn Artifact>, DeserializeError> {
         engine
             .downcast_ref::<crate::UniversalEngine>()
-            .ok_or(CompileError::EngineDowncast)?
+            .ok_or(DeserializeError::Compiler(CompileError::EngineDowncast))?
             .load_universal_executable(self)
             .map(|a| Arc::new(a) as _)
+            .map_err(DeserializeError::Compiler)
     }
 
     fn features(&self) -> Features {
@@ -149,20 +737,71 @@ impl wasmer_engine::Executable for UniversalExecutable {
     fn serialize(&self) -> Result<Vec<u8>, Box<(dyn std::error::Error + Send + Sync + 'static)>> {
         // The format is as thus:
         //
+        // MAGIC
+        // FORMAT VERSION
+        // PADDING
+        // HEADER LENGTH
         // HEADER
+        // PADDING
         // RKYV PAYLOAD
+        // PADDING
+        // CODE IMAGE
+        // CODE IMAGE POSITION
         // RKYV POSITION
+        // CODE INDEX POSITION
+        //
+        // The header is a separate archive, with the hash of everything
+        // following it but the CODE IMAGE, and the hash of the CODE IMAGE.
+        // The code image is aligned to `CODE_IMAGE_PAGE_SIZE`
+        // in the output, so that a loader mapping the output can use it in
+        // place. The code is only in the image: the RKYV PAYLOAD holds the
+        // executable without it, and the `CodeIndex` locating it.
         //
         // It is expected that any framing for message length is handled by the caller.
+        let code_image = self.code_image();
         let mut serializer = AllocSerializer::<1024>::default();
         let pos = rkyv::ser::Serializer::serialize_value(&mut serializer, self)
             .map_err(ExecutableSerializeError::Executable)? as u64;
-        let pos_bytes = pos.to_le_bytes();
+        let code_index = self.code_index(&code_image);
+        let code_index_pos = rkyv::ser::Serializer::serialize_value(&mut serializer, &code_index)
+            .map_err(ExecutableSerializeError::Executable)? as u64;
         let data = serializer.into_serializer().into_inner();
-        let mut out = Vec::with_capacity(MAGIC_HEADER.len() + pos_bytes.len() + data.len());
-        out.extend(&MAGIC_HEADER);
-        out.extend(data.as_slice());
-        out.extend(&pos_bytes);
+
+        // The length of the header doesn't depend on the hash it records,
+        // which can only be computed once the rest of the output is laid
+        // out after the header.
+        let mut header = self.header([0; 32], [0; 32]);
+        let header_length = rkyv::to_bytes::<_, 256>(&header)
+            .map_err(ExecutableSerializeError::Header)?
+            .len();
+        let payload = PREAMBLE_LENGTH + round_up(header_length, 16);
+
+        let mut contents = Vec::new();
+        contents.extend(data.as_slice());
+        let code_image_pos = round_up(payload + contents.len(), CODE_IMAGE_PAGE_SIZE);
+        contents.resize(code_image_pos - payload, 0);
+        contents.extend(&code_image.bytes);
+        let trailer = contents.len();
+        contents.extend(&(code_image_pos as u64).to_le_bytes());
+        contents.extend(&pos.to_le_bytes());
+        contents.extend(&code_index_pos.to_le_bytes());
+
+        let mut metadata_hash = blake3::Hasher::new();
+        metadata_hash.update(&contents[..code_image_pos - payload]);
+        metadata_hash.update(&contents[trailer..]);
+        header.metadata_hash = metadata_hash.finalize().into();
+        header.code_hash = blake3::hash(&code_image.bytes).into();
+        let header = rkyv::to_bytes::<_, 256>(&header).map_err(ExecutableSerializeError::Header)?;
+        debug_assert_eq!(header.len(), header_length);
+
+        let mut out = Vec::with_capacity(payload + contents.len());
+        out.extend(MAGIC);
+        out.push(FORMAT_VERSION);
+        out.resize(PREAMBLE_LENGTH - 8, 0xFF);
+        out.extend(&(header_length as u64).to_le_bytes());
+        out.extend(header.as_slice());
+        out.resize(payload, 0);
+        out.extend(&contents);
         Ok(out)
     }
 
@@ -192,11 +831,12 @@ impl<'a> wasmer_engine::Executable for UniversalExecutableRef<'a> {
     fn load(
         &self,
         engine: &(dyn Engine + 'static),
-    ) -> Result<std::sync::Arc<dyn Artifact>, CompileError> {
+        tunables: &dyn Tunables,
+    ) -> Result<std::sync::Arc<dyn Artifact>, DeserializeError> {
         engine
             .downcast_ref::<crate::UniversalEngine>()
-            .ok_or_else(|| CompileError::Codegen("can't downcast TODO FIXME".into()))?
-            .load_universal_executable_ref(self)
+            .ok_or(DeserializeError::Compiler(CompileError::EngineDowncast))?
+            .load_universal_executable_ref(self, tunables)
             .map(|a| Arc::new(a) as _)
     }
 
@@ -0,0 +1,463 @@
+//! Compilation of the functions of a module on their first call.
+//!
+//! Each function of a lazily compiled module starts out as a stub, which
+//! jumps through a slot of a table initially pointing back into the stub,
+//! to code pushing the index of the function and jumping to a thunk shared
+//! by the module. The thunk saves the argument registers, calls
+//! [`wasmer_lazy_compile`] to compile the function, patches its slot to
+//! point to the compiled code and continues to it. The stubs stay the entry
+//! points of the functions, so that calls, function references and exports
+//! don't change once the functions are compiled.
+
+use crate::code_memory::CodePool;
+use crate::link::link_function;
+use crate::{UniversalArtifact, UniversalEngine, UniversalExecutable};
+use enumset::EnumSet;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use wasmer_compiler::{
+    reorder_memargs, Architecture, CallingConvention, CompileError, CompileModuleInfo,
+    CompiledFunctionFrameInfo, Compiler, CpuFeature, CustomSection, CustomSectionProtection,
+    Features, FunctionBody, FunctionBodyData, ModuleEnvironment, ModuleMiddlewareChain,
+    ModuleTranslationState, Relocation, RelocationKind, RelocationTarget, SectionBody,
+    SectionIndex, Target,
+};
+use wasmer_engine::{
+    DeserializeError, Engine, Executable, GlobalFrameInfo, GlobalFrameInfoRegistration,
+};
+use wasmer_types::entity::{EntityRef, PrimaryMap};
+use wasmer_types::{FunctionIndex, LocalFunctionIndex};
+use wasmer_vm::{
+    raise_user_trap, resume_panic, Artifact, FunctionBodyPtr, FunctionExtent, SectionBodyPtr,
+    Tunables, VMLocalFunction,
+};
+
+/// The offset in the stub of a function of the code its slot initially
+/// points to.
+const STUB_ENTRY: u32 = 13;
+
+/// A WebAssembly module whose functions are compiled on their first call,
+/// created with [`UniversalEngine::compile_universal_lazy`].
+///
+/// Opposed to a [`UniversalExecutable`], it can't be serialized.
+pub struct UniversalLazyExecutable {
+    /// The module, with stubs in place of its functions.
+    executable: UniversalExecutable,
+    module: Arc<LazyModule>,
+}
+
+/// What's needed to compile the functions of a module after it's loaded.
+struct LazyModule {
+    compiler: Arc<dyn Compiler>,
+    target: Target,
+    compile_info: CompileModuleInfo,
+    module_translation: ModuleTranslationState,
+    binary: Box<[u8]>,
+    /// The range of the body of each function in `binary`.
+    bodies: PrimaryMap<LocalFunctionIndex, Range<usize>>,
+    /// The section of the slots the stubs jump through, followed by the
+    /// address of the [`LazyFunctions`] of the module and the address of
+    /// [`wasmer_lazy_compile`], which the thunk reads.
+    slots: SectionIndex,
+}
+
+impl UniversalLazyExecutable {
+    pub(crate) fn new(
+        engine: &UniversalEngine,
+        compiler: Arc<dyn Compiler>,
+        features: &Features,
+        binary: &[u8],
+        tunables: &dyn Tunables,
+    ) -> Result<Self, CompileError> {
+        let target = engine.target();
+        let architecture = target.triple().architecture;
+        if !cfg!(all(target_arch = "x86_64", unix))
+            || architecture != Architecture::X86_64
+            || target.triple().default_calling_convention() != Ok(CallingConvention::SystemV)
+        {
+            return Err(CompileError::UnsupportedTarget(format!(
+                "lazy compilation on {}",
+                target.triple()
+            )));
+        }
+        if !compiler.supports_function_compilation() {
+            return Err(CompileError::UnsupportedFeature(
+                "lazy compilation with a compiler which can't compile functions one at a time"
+                    .to_string(),
+            ));
+        }
+
+        let binary = reorder_memargs(binary);
+        let translation = ModuleEnvironment::new()
+            .translate(&binary)
+            .map_err(CompileError::Wasm)?;
+        let mut module = translation.module;
+        compiler.get_middlewares().apply_on_module_info(&mut module);
+        let compile_info = UniversalEngine::compile_info(module, features, tunables);
+        let compilation =
+            compiler.compile_module_with_functions(target, &compile_info, PrimaryMap::new())?;
+        let mut executable = engine.universal_executable(
+            &*compiler,
+            compile_info.clone(),
+            compilation,
+            &translation.data_initializers,
+        );
+        let bodies = translation
+            .function_body_inputs
+            .values()
+            .map(|body| body.module_offset..body.module_offset + body.data.len())
+            .collect::<PrimaryMap<LocalFunctionIndex, _>>();
+        let slots = add_stubs(&mut executable, bodies.len());
+
+        Ok(Self {
+            executable,
+            module: Arc::new(LazyModule {
+                compiler,
+                target: target.clone(),
+                compile_info,
+                // SAFETY: Calling `unwrap` is correct since
+                // `environ.translate()` above will write some data into
+                // `module_translation_state`.
+                module_translation: translation.module_translation_state.unwrap(),
+                binary: binary.into_owned().into(),
+                bodies,
+                slots,
+            }),
+        })
+    }
+
+    /// Set up the compilation of the functions of `artifact`, loaded from
+    /// this executable with its custom sections at `sections`.
+    pub(crate) fn load_functions(
+        &self,
+        artifact: &mut UniversalArtifact,
+        sections: PrimaryMap<SectionIndex, SectionBodyPtr>,
+    ) {
+        let functions = Box::new(LazyFunctions {
+            module: Arc::clone(&self.module),
+            stubs: artifact.functions.values().copied().collect(),
+            sections,
+            compiled: Mutex::new(LazyCompiledFunctions {
+                functions: BTreeMap::new(),
+                code: CodePool::new(),
+            }),
+        });
+        let count = self.module.bodies.len();
+        functions.slot(count).store(
+            &*functions as *const LazyFunctions as usize,
+            Ordering::SeqCst,
+        );
+        functions
+            .slot(count + 1)
+            .store(wasmer_lazy_compile as *const () as usize, Ordering::SeqCst);
+        artifact.lazy_functions = Some(functions);
+    }
+
+    pub(crate) fn executable(&self) -> &UniversalExecutable {
+        &self.executable
+    }
+}
+
+impl Executable for UniversalLazyExecutable {
+    fn load(
+        &self,
+        engine: &(dyn Engine + 'static),
+        _tunables: &dyn Tunables,
+    ) -> Result<Arc<dyn Artifact>, DeserializeError> {
+        engine
+            .downcast_ref::<UniversalEngine>()
+            .ok_or(DeserializeError::Compiler(CompileError::EngineDowncast))?
+            .load_universal_lazy_executable(self)
+            .map(|a| Arc::new(a) as _)
+            .map_err(DeserializeError::Compiler)
+    }
+
+    fn features(&self) -> Features {
+        self.executable.features()
+    }
+
+    fn cpu_features(&self) -> EnumSet<CpuFeature> {
+        self.executable.cpu_features()
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        Err("modules compiled lazily can't be serialized".into())
+    }
+
+    fn function_name(&self, index: FunctionIndex) -> Option<&str> {
+        self.executable.function_name(index)
+    }
+}
+
+/// Puts stubs in place of the `count` functions of `executable`, and adds
+/// the thunk they jump to and the section of their slots, whose index is
+/// returned.
+fn add_stubs(executable: &mut UniversalExecutable, count: usize) -> SectionIndex {
+    let thunk = SectionIndex::new(executable.custom_sections.len());
+    let slots = SectionIndex::new(thunk.index() + 1);
+    let abs8 = |offset: usize, reloc_target| Relocation {
+        kind: RelocationKind::Abs8,
+        reloc_target,
+        offset: offset as u32,
+        addend: 0,
+    };
+
+    for index in 0..count {
+        let (body, relocations) = stub(index, thunk, slots);
+        executable.function_bodies.push(FunctionBody {
+            body,
+            unwind_info: None,
+        });
+        executable.function_relocations.push(relocations);
+        executable.function_jt_offsets.push(Default::default());
+        executable
+            .function_frame_info
+            .push(CompiledFunctionFrameInfo::default());
+    }
+
+    let (code, state, compile) = thunk_code();
+    executable.custom_sections.push(CustomSection {
+        protection: CustomSectionProtection::ReadExecute,
+        bytes: SectionBody::new_with_vec(code),
+        relocations: Vec::new(),
+    });
+    executable.custom_section_relocations.push(vec![
+        Relocation {
+            addend: (count * 8) as i64,
+            ..abs8(state, RelocationTarget::CustomSection(slots))
+        },
+        Relocation {
+            addend: ((count + 1) * 8) as i64,
+            ..abs8(compile, RelocationTarget::CustomSection(slots))
+        },
+    ]);
+
+    // The slots of the functions initially point to the entries of their
+    // stubs. They're written once the module is loaded.
+    executable.custom_sections.push(CustomSection {
+        protection: CustomSectionProtection::Read,
+        bytes: SectionBody::new_with_vec(vec![0; (count + 2) * 8]),
+        relocations: Vec::new(),
+    });
+    executable.custom_section_relocations.push(
+        (0..count)
+            .map(|index| Relocation {
+                addend: STUB_ENTRY.into(),
+                ..abs8(
+                    index * 8,
+                    RelocationTarget::LocalFunc(LocalFunctionIndex::new(index)),
+                )
+            })
+            .collect(),
+    );
+    slots
+}
+
+/// The stub of the function at `index`, with its relocations.
+fn stub(index: usize, thunk: SectionIndex, slots: SectionIndex) -> (Vec<u8>, Vec<Relocation>) {
+    let mut code = Vec::with_capacity(31);
+    // movabs r11, slot
+    code.extend_from_slice(&[0x49, 0xbb, 0, 0, 0, 0, 0, 0, 0, 0]);
+    // jmp [r11]
+    code.extend_from_slice(&[0x41, 0xff, 0x23]);
+    debug_assert_eq!(code.len(), STUB_ENTRY as usize);
+    // push index
+    code.push(0x68);
+    code.extend_from_slice(&(index as u32).to_le_bytes());
+    // movabs r11, thunk
+    code.extend_from_slice(&[0x49, 0xbb, 0, 0, 0, 0, 0, 0, 0, 0]);
+    // jmp r11
+    code.extend_from_slice(&[0x41, 0xff, 0xe3]);
+
+    let relocations = vec![
+        Relocation {
+            kind: RelocationKind::Abs8,
+            reloc_target: RelocationTarget::CustomSection(slots),
+            offset: 2,
+            addend: (index * 8) as i64,
+        },
+        Relocation {
+            kind: RelocationKind::Abs8,
+            reloc_target: RelocationTarget::CustomSection(thunk),
+            offset: 20,
+            addend: 0,
+        },
+    ];
+    (code, relocations)
+}
+
+/// The code of the thunk the stubs jump to with the index of their function
+/// pushed, with the offsets of the addresses of the [`LazyFunctions`] of the
+/// module and of [`wasmer_lazy_compile`] to relocate.
+///
+/// It preserves the registers arguments can be passed in, aligns the stack
+/// for the call and returns to the compiled function, with the stack as it
+/// was on entry to the stub.
+fn thunk_code() -> (Vec<u8>, usize, usize) {
+    let mut code = vec![
+        0x50, 0x51, 0x52, 0x56, 0x57, // push rax, rcx, rdx, rsi, rdi
+        0x41, 0x50, 0x41, 0x51, 0x41, 0x52, 0x41, 0x53, // push r8, r9, r10, r11
+        0x53, // push rbx
+        0x48, 0x89, 0xe3, // mov rbx, rsp
+        0x48, 0x83, 0xe4, 0xf0, // and rsp, -16
+        0x48, 0x81, 0xec, 0x80, 0x00, 0x00, 0x00, // sub rsp, 128
+    ];
+    for xmm in 0..8 {
+        // movdqu [rsp + 16 * xmm], xmm
+        code.extend_from_slice(&[0xf3, 0x0f, 0x7f, 0x44 | xmm << 3, 0x24, xmm * 16]);
+    }
+    // mov rsi, [rbx + 80], the index of the function.
+    code.extend_from_slice(&[0x48, 0x8b, 0x73, 0x50]);
+    // movabs rdi, address of the functions; mov rdi, [rdi]
+    let state = code.len() + 2;
+    code.extend_from_slice(&[0x48, 0xbf, 0, 0, 0, 0, 0, 0, 0, 0]);
+    code.extend_from_slice(&[0x48, 0x8b, 0x3f]);
+    // movabs rax, address of `wasmer_lazy_compile`; call [rax]
+    let compile = code.len() + 2;
+    code.extend_from_slice(&[0x48, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0]);
+    code.extend_from_slice(&[0xff, 0x10]);
+    // mov [rbx + 80], rax, in place of the index, for `ret` to jump to.
+    code.extend_from_slice(&[0x48, 0x89, 0x43, 0x50]);
+    for xmm in 0..8 {
+        // movdqu xmm, [rsp + 16 * xmm]
+        code.extend_from_slice(&[0xf3, 0x0f, 0x6f, 0x44 | xmm << 3, 0x24, xmm * 16]);
+    }
+    code.extend_from_slice(&[
+        0x48, 0x89, 0xdc, // mov rsp, rbx
+        0x5b, // pop rbx
+        0x41, 0x5b, 0x41, 0x5a, 0x41, 0x59, 0x41, 0x58, // pop r11, r10, r9, r8
+        0x5f, 0x5e, 0x5a, 0x59, 0x58, // pop rdi, rsi, rdx, rcx, rax
+        0xc3, // ret
+    ]);
+    (code, state, compile)
+}
+
+/// The functions of a loaded module, compiled on their first call.
+pub(crate) struct LazyFunctions {
+    module: Arc<LazyModule>,
+    /// The stubs of the functions, which calls go through.
+    stubs: PrimaryMap<LocalFunctionIndex, VMLocalFunction>,
+    sections: PrimaryMap<SectionIndex, SectionBodyPtr>,
+    compiled: Mutex<LazyCompiledFunctions>,
+}
+
+// SAFETY: `sections` points to the code memory of the artifact, which is
+// only written to through the atomic slots once loaded.
+unsafe impl Send for LazyFunctions {}
+unsafe impl Sync for LazyFunctions {}
+
+/// The functions compiled so far, and the memory holding their code.
+struct LazyCompiledFunctions {
+    functions: BTreeMap<LocalFunctionIndex, LazyCompiledFunction>,
+    /// Declared after the functions, so that the code outlives their frame
+    /// information.
+    code: CodePool,
+}
+
+struct LazyCompiledFunction {
+    body: usize,
+    _frame_info_registration: GlobalFrameInfoRegistration,
+}
+
+impl LazyFunctions {
+    fn slot(&self, index: usize) -> &AtomicUsize {
+        let slots = *self.sections[self.module.slots] as *const AtomicUsize;
+        // SAFETY: the section has `functions + 2` slots, aligned as the
+        // data sections are.
+        unsafe { &*slots.add(index) }
+    }
+
+    /// Compiles the function at `index` unless it already is, and returns
+    /// the address of its code.
+    fn compile(&self, index: LocalFunctionIndex) -> Result<usize, CompileError> {
+        let mut compiled = self.compiled.lock().unwrap();
+        if let Some(function) = compiled.functions.get(&index) {
+            return Ok(function.body);
+        }
+
+        let module = &*self.module;
+        let range = module.bodies[index].clone();
+        let compilation = module.compiler.compile_function(
+            &module.target,
+            &module.compile_info,
+            &module.module_translation,
+            index,
+            &FunctionBodyData {
+                data: &module.binary[range.clone()],
+                module_offset: range.start,
+            },
+        )?;
+        // The exception frames aren't registered, like the unwind
+        // information of the functions isn't.
+        if compilation
+            .custom_sections
+            .keys()
+            .any(|section| !compilation.eh_frame_sections.contains(&section))
+        {
+            return Err(CompileError::UnsupportedFeature(
+                "lazy compilation of a function with custom sections".to_string(),
+            ));
+        }
+        let function = compilation.function;
+
+        let body = compiled
+            .code
+            .allocate(&function.body.body)
+            .map_err(|message| {
+                CompileError::Resource(format!(
+                    "failed to allocate memory for a function: {}",
+                    message
+                ))
+            })?
+            .as_ptr() as usize;
+        link_function(
+            body,
+            &function.relocations,
+            &self.stubs,
+            |jt| function.jt_offsets[jt],
+            &self.sections,
+        );
+        compiled.code.publish();
+
+        let frame_info_registration = GlobalFrameInfo::register_function(
+            Arc::clone(&module.compile_info.module),
+            index,
+            FunctionExtent {
+                address: FunctionBodyPtr(body as _),
+                length: function.body.body.len(),
+            },
+            function.frame_info,
+        );
+        self.slot(index.index()).store(body, Ordering::SeqCst);
+        compiled.functions.insert(
+            index,
+            LazyCompiledFunction {
+                body,
+                _frame_info_registration: frame_info_registration,
+            },
+        );
+        Ok(body)
+    }
+}
+
+/// Compiles the function at `index` of `functions`, called by the thunk of
+/// its module, and returns the address of its code.
+///
+/// Errors and panics are raised as traps of the WebAssembly call the
+/// function was called from.
+unsafe extern "C" fn wasmer_lazy_compile(functions: *const LazyFunctions, index: u64) -> usize {
+    // SAFETY: the thunk passes the `LazyFunctions` of its module, which
+    // outlive its code.
+    let functions = unsafe { &*functions };
+    let index = LocalFunctionIndex::new(index as usize);
+    match panic::catch_unwind(AssertUnwindSafe(|| functions.compile(index))) {
+        Ok(Ok(body)) => body,
+        // SAFETY: the function is called from WebAssembly code, within
+        // `catch_traps`.
+        Ok(Err(error)) => unsafe { raise_user_trap(Box::new(error)) },
+        Err(panic) => unsafe { resume_panic(panic) },
+    }
+}
@@ -30,15 +30,25 @@ mod builder;
 mod code_memory;
 mod engine;
 mod executable;
+#[cfg(feature = "compiler")]
+mod lazy;
 mod link;
+#[cfg(feature = "compiler")]
+mod streaming;
 mod unwind;
 
 pub use crate::artifact::UniversalArtifact;
 pub use crate::builder::Universal;
 pub use crate::code_memory::CodeMemory;
 pub use crate::engine::UniversalEngine;
-pub use crate::executable::{UniversalExecutable, UniversalExecutableRef};
+pub use crate::executable::{
+    CodeImage, ExecutableHeader, LinkedCode, UniversalExecutable, UniversalExecutableRef,
+};
+#[cfg(feature = "compiler")]
+pub use crate::lazy::UniversalLazyExecutable;
 pub use crate::link::link_module;
+#[cfg(feature = "compiler")]
+pub use crate::streaming::StreamingCompilation;
 
 /// Version number of this crate.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -67,16 +67,13 @@ fn fill_trampoline_map(
     map
 }
 
-fn apply_relocation(
-    body: usize,
+fn relocation_target(
     r: &Relocation,
     allocated_functions: &PrimaryMap<LocalFunctionIndex, VMLocalFunction>,
     jt_offsets: impl Fn(LocalFunctionIndex, JumpTable) -> wasmer_compiler::CodeOffset,
     allocated_sections: &PrimaryMap<SectionIndex, SectionBodyPtr>,
-    trampolines: &Option<TrampolinesSection>,
-    trampolines_map: &mut HashMap<usize, usize>,
-) {
-    let target_func_address: usize = match r.reloc_target {
+) -> usize {
+    match r.reloc_target {
         RelocationTarget::LocalFunc(index) => *allocated_functions[index].body as usize,
         RelocationTarget::LibCall(libcall) => libcall.function_pointer(),
         RelocationTarget::CustomSection(custom_section) => {
@@ -86,8 +83,17 @@ fn apply_relocation(
             let offset = jt_offsets(func_index, jt);
             *allocated_functions[func_index].body as usize + offset as usize
         }
-    };
+    }
+}
 
+fn apply_relocation(
+    body: usize,
+    r: &Relocation,
+    target_func_address: usize,
+    allocated_sections: &PrimaryMap<SectionIndex, SectionBodyPtr>,
+    trampolines: &Option<TrampolinesSection>,
+    trampolines_map: &mut HashMap<usize, usize>,
+) {
     match r.kind {
         #[cfg(target_pointer_width = "64")]
         RelocationKind::Abs8 => unsafe {
@@ -180,11 +186,12 @@ pub fn link_module(
     for (i, section_relocs) in section_relocations {
         let body = *allocated_sections[i] as usize;
         for r in section_relocs {
+            let target =
+                relocation_target(&r, allocated_functions, &jt_offsets, allocated_sections);
             apply_relocation(
                 body,
                 &r,
-                allocated_functions,
-                &jt_offsets,
+                target,
                 allocated_sections,
                 trampolines,
                 &mut trampolines_map,
@@ -194,11 +201,12 @@ pub fn link_module(
     for (i, function_relocs) in function_relocations {
         let body = *allocated_functions[i].body as usize;
         for r in function_relocs {
+            let target =
+                relocation_target(&r, allocated_functions, &jt_offsets, allocated_sections);
             apply_relocation(
                 body,
                 &r,
-                allocated_functions,
-                &jt_offsets,
+                target,
                 allocated_sections,
                 trampolines,
                 &mut trampolines_map,
@@ -206,3 +214,38 @@ pub fn link_module(
         }
     }
 }
+
+/// Links a function compiled after the rest of its module was loaded and
+/// linked, such as a lazily compiled function, at `body`.
+///
+/// Its jump tables are looked up in `jt_offsets`, while its calls to the
+/// functions of the module go to their entries in `allocated_functions`.
+#[cfg(feature = "compiler")]
+pub(crate) fn link_function(
+    body: usize,
+    relocations: &[Relocation],
+    allocated_functions: &PrimaryMap<LocalFunctionIndex, VMLocalFunction>,
+    jt_offsets: impl Fn(JumpTable) -> wasmer_compiler::CodeOffset,
+    allocated_sections: &PrimaryMap<SectionIndex, SectionBodyPtr>,
+) {
+    let mut trampolines_map = HashMap::new();
+    for r in relocations {
+        let target = match r.reloc_target {
+            RelocationTarget::JumpTable(_, jt) => body + jt_offsets(jt) as usize,
+            _ => relocation_target(
+                r,
+                allocated_functions,
+                |_, _| unreachable!(),
+                allocated_sections,
+            ),
+        };
+        apply_relocation(
+            body,
+            r,
+            target,
+            allocated_sections,
+            &None,
+            &mut trampolines_map,
+        );
+    }
+}
@@ -0,0 +1,293 @@
+//! Compilation of a WebAssembly module while it's being received.
+
+use crate::{UniversalEngine, UniversalExecutable};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use wasmer_compiler::wasmparser::{
+    Chunk, FuncValidator, FunctionBody, Parser, Payload, ValidPayload, Validator,
+    ValidatorResources,
+};
+use wasmer_compiler::{
+    reorder_function_memargs, wasmparser_features, CompileError, CompileModuleInfo, Compiler,
+    FunctionBodyData, FunctionCompilation, ModuleEnvironment, ModuleMiddlewareChain,
+    ModuleTranslationState, WasmError,
+};
+use wasmer_engine::Engine;
+use wasmer_types::entity::{EntityRef, PrimaryMap};
+use wasmer_types::{Features, LocalFunctionIndex, ModuleInfo};
+use wasmer_vm::Tunables;
+
+/// A WebAssembly module being compiled while it's received, created with
+/// [`UniversalEngine::compile_universal_streaming`].
+///
+/// The beginning of the module is translated once its code section starts,
+/// and from then on each function is validated as soon as its body is
+/// received, and compiled on the `rayon` thread pool. The rest of the module
+/// is compiled once the whole module is received, with [`Self::finish`].
+///
+/// Compilers that can't compile the functions of a module one at a time,
+/// see [`Compiler::supports_function_compilation`], only validate them as
+/// they are received, and compile the whole module once it's received.
+pub struct StreamingCompilation<'a> {
+    engine: &'a UniversalEngine,
+    tunables: &'a dyn Tunables,
+    compiler: Arc<dyn Compiler>,
+    features: Features,
+    /// The bytes of the module received so far.
+    buffer: Vec<u8>,
+    /// How many bytes of `buffer` were parsed.
+    parsed: usize,
+    parser: Parser,
+    validator: Validator,
+    /// Whether the end of the module was parsed.
+    ended: bool,
+    /// The compilation of the functions, once the code section started.
+    code: Option<CodeCompilation>,
+}
+
+/// The functions of a module being compiled.
+struct CodeCompilation {
+    /// The module up to its code section, the functions are compiled with.
+    compile_info: Arc<CompileModuleInfo>,
+    module_translation: Arc<ModuleTranslationState>,
+    /// The translation of the module up to its code section, which the
+    /// translation of the rest of it continues.
+    module: ModuleInfo,
+    module_translation_state: ModuleTranslationState,
+    /// How many functions were received.
+    functions: usize,
+    sender: Sender<FunctionResult>,
+    receiver: Receiver<FunctionResult>,
+}
+
+/// The result of compiling a function, sent by the task compiling it.
+type FunctionResult = (
+    LocalFunctionIndex,
+    Result<FunctionCompilation, CompileError>,
+);
+
+impl<'a> StreamingCompilation<'a> {
+    pub(crate) fn new(
+        engine: &'a UniversalEngine,
+        compiler: Arc<dyn Compiler>,
+        features: Features,
+        tunables: &'a dyn Tunables,
+    ) -> Self {
+        let mut validator = Validator::new();
+        validator.wasm_features(wasmparser_features(&features));
+        Self {
+            engine,
+            tunables,
+            compiler,
+            features,
+            buffer: Vec::new(),
+            parsed: 0,
+            parser: Parser::new(0),
+            validator,
+            ended: false,
+            code: None,
+        }
+    }
+
+    /// Pushes the next bytes of the module.
+    ///
+    /// This returns an error as soon as the bytes received so far are
+    /// known to be invalid, including the bodies of the functions. Errors
+    /// compiling the functions are only returned by [`Self::finish`].
+    pub fn push(&mut self, bytes: &[u8]) -> Result<(), CompileError> {
+        if self.ended {
+            return Err(CompileError::Wasm(WasmError::Generic(
+                "unexpected bytes after the end of the module".to_string(),
+            )));
+        }
+        self.buffer.extend_from_slice(bytes);
+        self.parse(false)
+    }
+
+    /// Finishes the compilation, once all the bytes of the module were
+    /// pushed.
+    pub fn finish(mut self) -> Result<UniversalExecutable, CompileError> {
+        if !self.ended {
+            self.parse(true)?;
+        }
+        let code = match self.code.take() {
+            Some(code) => code,
+            None => return self.engine.compile_universal(&self.buffer, self.tunables),
+        };
+
+        // Only the tasks compiling the functions can send results now, so
+        // that this doesn't wait forever if one of them panics.
+        drop(code.sender);
+        let mut functions = (0..code.functions).map(|_| None).collect::<Vec<_>>();
+        for _ in 0..code.functions {
+            let (index, function) = code.receiver.recv().map_err(|_| {
+                CompileError::Codegen("the compilation of a function panicked".to_string())
+            })?;
+            functions[index.index()] = Some(function);
+        }
+        let functions = functions
+            .into_iter()
+            .map(Option::unwrap)
+            .collect::<Result<PrimaryMap<LocalFunctionIndex, _>, _>>()?;
+
+        let environ = ModuleEnvironment {
+            module: code.module,
+            module_translation_state: Some(code.module_translation_state),
+            ..ModuleEnvironment::new()
+        };
+        let translation = environ
+            .translate_from_code_section(&self.buffer)
+            .map_err(CompileError::Wasm)?;
+        let compile_info =
+            UniversalEngine::compile_info(translation.module, &self.features, self.tunables);
+        let compilation = self.compiler.compile_module_with_functions(
+            self.engine.target(),
+            &compile_info,
+            functions,
+        )?;
+        Ok(self.engine.universal_executable(
+            &*self.compiler,
+            compile_info,
+            compilation,
+            &translation.data_initializers,
+        ))
+    }
+
+    /// Parses as much of the received bytes as possible.
+    fn parse(&mut self, eof: bool) -> Result<(), CompileError> {
+        while !self.ended {
+            let section_start = self.parsed;
+            let (consumed, payload) = match self.parser.parse(&self.buffer[section_start..], eof)? {
+                Chunk::NeedMoreData(_) => return Ok(()),
+                Chunk::Parsed { consumed, payload } => (consumed, payload),
+            };
+            self.parsed += consumed;
+
+            match self
+                .validator
+                .payload(&payload)
+                .map_err(|e| CompileError::Validate(format!("{}", e)))?
+            {
+                ValidPayload::Ok => {}
+                ValidPayload::Submodule(_) => {
+                    return Err(CompileError::UnsupportedFeature(
+                        "module linking".to_string(),
+                    ))
+                }
+                ValidPayload::Func(validator, body) => match &mut self.code {
+                    Some(code) => {
+                        code.compile_function(&self.compiler, self.engine, validator, body)?
+                    }
+                    // The function is compiled with the whole module.
+                    None => {
+                        let (module_offset, data) = reordered_body(&body)?;
+                        validate_function(validator, &FunctionBody::new(module_offset, &data))?
+                    }
+                },
+            }
+
+            match payload {
+                Payload::CodeSectionStart { .. }
+                    if self.compiler.supports_function_compilation() =>
+                {
+                    self.code = Some(CodeCompilation::new(
+                        &*self.compiler,
+                        &self.features,
+                        self.tunables,
+                        &self.buffer[..section_start],
+                    )?);
+                }
+                Payload::End => self.ended = true,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CodeCompilation {
+    /// Prepares compiling the functions of the module starting with
+    /// `prefix`, which ends where its code section starts.
+    fn new(
+        compiler: &dyn Compiler,
+        features: &Features,
+        tunables: &dyn Tunables,
+        prefix: &[u8],
+    ) -> Result<Self, CompileError> {
+        let translation = ModuleEnvironment::new()
+            .translate(prefix)
+            .map_err(CompileError::Wasm)?;
+        let mut module = translation.module;
+        compiler.get_middlewares().apply_on_module_info(&mut module);
+        let module_translation_state = translation.module_translation_state.unwrap();
+        let (sender, receiver) = channel();
+        Ok(Self {
+            compile_info: Arc::new(UniversalEngine::compile_info(
+                module.clone(),
+                features,
+                tunables,
+            )),
+            module_translation: Arc::new(module_translation_state.clone()),
+            module,
+            module_translation_state,
+            functions: 0,
+            sender,
+            receiver,
+        })
+    }
+
+    /// Validates the next function, and compiles it on the `rayon` thread
+    /// pool.
+    fn compile_function(
+        &mut self,
+        compiler: &Arc<dyn Compiler>,
+        engine: &UniversalEngine,
+        validator: FuncValidator<ValidatorResources>,
+        body: FunctionBody<'_>,
+    ) -> Result<(), CompileError> {
+        let index = LocalFunctionIndex::new(self.functions);
+        self.functions += 1;
+        let (module_offset, data) = reordered_body(&body)?;
+        validate_function(validator, &FunctionBody::new(module_offset, &data))?;
+
+        let compiler = Arc::clone(compiler);
+        let target = engine.target().clone();
+        let compile_info = Arc::clone(&self.compile_info);
+        let module_translation = Arc::clone(&self.module_translation);
+        let sender = self.sender.clone();
+        rayon::spawn(move || {
+            let result = compiler.compile_function(
+                &target,
+                &compile_info,
+                &module_translation,
+                index,
+                &FunctionBodyData {
+                    data: &data,
+                    module_offset,
+                },
+            );
+            // The receiver is gone if the compilation was abandoned.
+            let _ = sender.send((index, result));
+        });
+        Ok(())
+    }
+}
+
+/// The offset of `body` in its module and a copy of it, with the memory
+/// accesses reordered with [`reorder_function_memargs`].
+fn reordered_body(body: &FunctionBody<'_>) -> Result<(usize, Vec<u8>), CompileError> {
+    let mut reader = body.get_binary_reader();
+    let module_offset = reader.original_position();
+    let mut data = reader.read_bytes(reader.bytes_remaining())?.to_vec();
+    reorder_function_memargs(&mut data, module_offset);
+    Ok((module_offset, data))
+}
+
+fn validate_function(
+    mut validator: FuncValidator<ValidatorResources>,
+    body: &FunctionBody<'_>,
+) -> Result<(), CompileError> {
+    validator
+        .validate(body)
+        .map_err(|e| CompileError::Validate(format!("{}", e)))
+}
@@ -1,5 +1,6 @@
 //! Engine trait and associated types.
 
+use crate::DeserializeError;
 use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
 use std::sync::Arc;
 use wasmer_compiler::{CompileError, Target};
@@ -40,8 +41,14 @@ pub trait Engine {
     ) -> Result<Box<dyn crate::Executable>, CompileError>;
 
     /// Load a compiled executable with this engine.
-    fn load(&self, executable: &(dyn crate::Executable))
-        -> Result<Arc<dyn Artifact>, CompileError>;
+    ///
+    /// A serialized executable has to be compiled with the memory styles
+    /// given by `tunables`.
+    fn load(
+        &self,
+        executable: &(dyn crate::Executable),
+        tunables: &dyn Tunables,
+    ) -> Result<Arc<dyn Artifact>, DeserializeError>;
 
     /// A unique identifier for this object.
     ///
@@ -3,7 +3,8 @@ use crate::trap::RuntimeError;
 use std::io;
 use thiserror::Error;
 use wasmer_compiler::CompileError;
-use wasmer_types::ExternType;
+use wasmer_types::{ExternType, Features};
+use wasmer_vm::MemoryStyle;
 
 /// The Deserialize error can occur when loading a
 /// compiled Module from a binary.
@@ -25,6 +26,71 @@ pub enum DeserializeError {
     /// trying to allocate the required resources.
     #[error(transparent)]
     Compiler(CompileError),
+    /// The binary was serialized in another version of the format
+    #[error("incompatible binary: format version {found}, expected version {expected}")]
+    IncompatibleFormat {
+        /// The version of the format which can be loaded
+        expected: u8,
+        /// The version of the format of the binary
+        found: u8,
+    },
+    /// The binary was produced by another version of the engine
+    #[error(
+        "incompatible binary: produced by engine version {found}, expected version {expected}"
+    )]
+    IncompatibleEngine {
+        /// The version of the engine loading the binary
+        expected: String,
+        /// The version of the engine which produced the binary
+        found: String,
+    },
+    /// The binary was compiled for another target
+    #[error("incompatible binary: compiled for {found}, expected {expected}")]
+    IncompatibleTarget {
+        /// The target triple of the engine
+        expected: String,
+        /// The target triple of the binary
+        found: String,
+    },
+    /// The binary uses CPU features the target of the engine doesn't have
+    #[error("incompatible binary: uses the CPU features {0}, which the target lacks")]
+    MissingCpuFeatures(String),
+    /// The binary was compiled with other WebAssembly features than
+    /// the engine's
+    #[error("incompatible binary: compiled with the features {found:?}, expected {expected:?}")]
+    IncompatibleFeatures {
+        /// The features of the engine
+        expected: Features,
+        /// The features the binary was compiled with
+        found: Features,
+    },
+    /// The binary was compiled with epoch interruption while the engine
+    /// compiles without it, or the other way around
+    #[error(
+        "incompatible binary: compiled with epoch interruption set to {found}, expected {expected}"
+    )]
+    IncompatibleEpochInterruption {
+        /// Whether the engine compiles with epoch interruption
+        expected: bool,
+        /// Whether the binary was compiled with epoch interruption
+        found: bool,
+    },
+    /// A memory of the binary was compiled for another style than the
+    /// one the tunables give it
+    #[error(
+        "incompatible binary: memory {index} was compiled for {found:?}, expected {expected:?}"
+    )]
+    IncompatibleMemoryStyle {
+        /// The index of the memory
+        index: u32,
+        /// The style the tunables give the memory
+        expected: MemoryStyle,
+        /// The style the memory was compiled for
+        found: MemoryStyle,
+    },
+    /// The contents of the binary don't match the hash recorded in it
+    #[error("corrupted binary: the contents don't match their hash")]
+    ContentHashMismatch,
 }
 
 /// An ImportError.
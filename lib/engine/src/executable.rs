@@ -1,8 +1,8 @@
-use crate::Engine;
+use crate::{DeserializeError, Engine};
 use enumset::EnumSet;
-use wasmer_compiler::{CompileError, CpuFeature, Features};
+use wasmer_compiler::{CpuFeature, Features};
 use wasmer_types::FunctionIndex;
-use wasmer_vm::Artifact;
+use wasmer_vm::{Artifact, Tunables};
 
 mod private {
     pub struct Internal(pub(super) ());
@@ -15,11 +15,13 @@ mod private {
 pub trait Executable {
     /// Load this executable with the specified engine.
     ///
-    /// TODO(0-copy): change error type here.
+    /// A serialized executable has to be compiled with the memory styles
+    /// given by `tunables`.
     fn load(
         &self,
         engine: &(dyn Engine + 'static),
-    ) -> Result<std::sync::Arc<dyn Artifact>, CompileError>;
+        tunables: &dyn Tunables,
+    ) -> Result<std::sync::Arc<dyn Artifact>, DeserializeError>;
 
     /// The features with which this `Executable` was built.
     fn features(&self) -> Features;
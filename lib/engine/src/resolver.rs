@@ -43,6 +43,7 @@ pub fn resolve_imports(
     let mut table_imports = PrimaryMap::with_capacity(import_counts.tables as _);
     let mut memory_imports = PrimaryMap::with_capacity(import_counts.memories as _);
     let mut global_imports = PrimaryMap::with_capacity(import_counts.globals as _);
+    let mut tag_imports = PrimaryMap::with_capacity(import_counts.tags as _);
     for VMImport {
         import_no,
         module,
@@ -55,6 +56,7 @@ pub fn resolve_imports(
             &VMImportType::Table(t) => ExternType::Table(t),
             &VMImportType::Memory(t, _) => ExternType::Memory(t),
             &VMImportType::Global(t) => ExternType::Global(t),
+            VMImportType::Tag(t) => ExternType::Tag(t.clone()),
             &VMImportType::Function {
                 sig,
                 static_trampoline: _,
@@ -87,6 +89,7 @@ pub fn resolve_imports(
                 let global = g.from.ty();
                 ExternType::Global(*global)
             }
+            Export::Tag(ref t) => ExternType::Tag(t.from.ty().clone()),
         };
         match (&resolved, ty) {
             (
@@ -213,6 +216,9 @@ pub fn resolve_imports(
                     from: ex.from.clone(),
                 });
             }
+            (Export::Tag(ex), VMImportType::Tag(im)) if ex.from.ty() == im => {
+                tag_imports.push(ex.from.clone());
+            }
             _ => {
                 return Err(LinkError::Import(
                     module.to_string(),
@@ -228,5 +234,6 @@ pub fn resolve_imports(
         table_imports,
         memory_imports,
         global_imports,
+        tag_imports,
     ))
 }
@@ -0,0 +1,217 @@
+//! Source locations of WebAssembly instructions, read from the DWARF
+//! sections of a module.
+//!
+//! Modules built with debug information carry DWARF in custom sections
+//! named after the usual object file ones (`.debug_info`, `.debug_line`,
+//! ...), where addresses are offsets from the start of the contents of the
+//! code section. Only the line programs are read: they map each instruction
+//! to a file, a line and a column.
+
+use gimli::{ColumnType, EndianSlice, LittleEndian};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use wasmer_types::ModuleInfo;
+
+type Reader<'a> = EndianSlice<'a, LittleEndian>;
+
+/// A position in a source file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SourceLocation {
+    pub(crate) file: Arc<str>,
+    pub(crate) line: Option<u32>,
+    pub(crate) column: Option<u32>,
+}
+
+/// The line programs of a module, flattened into a table sorted by
+/// address.
+#[derive(Debug, Default)]
+pub(crate) struct LineTable {
+    /// The location of the instructions from each address on. `None` marks
+    /// the end of a sequence of instructions.
+    rows: Vec<(u64, Option<SourceLocation>)>,
+}
+
+impl LineTable {
+    /// Read the line programs of `module`.
+    ///
+    /// The table is empty if the module has no DWARF sections, or if they
+    /// can't be parsed.
+    pub(crate) fn new(module: &ModuleInfo) -> Self {
+        if !module.custom_sections.contains_key(".debug_line") {
+            return Self::default();
+        }
+        Self::parse(module).unwrap_or_default()
+    }
+
+    fn parse(module: &ModuleInfo) -> gimli::Result<Self> {
+        let dwarf = gimli::Dwarf::load(|id| -> gimli::Result<Reader> {
+            let data = match module.custom_sections.get(id.name()) {
+                Some(index) => &module.custom_sections_data[*index][..],
+                None => &[],
+            };
+            Ok(EndianSlice::new(data, LittleEndian))
+        })?;
+
+        let mut rows = Vec::new();
+        let mut units = dwarf.units();
+        while let Some(header) = units.next()? {
+            let unit = dwarf.unit(header)?;
+            let program = match unit.line_program.clone() {
+                Some(program) => program,
+                None => continue,
+            };
+            let mut files = HashMap::new();
+            let mut program_rows = program.rows();
+            while let Some((header, row)) = program_rows.next_row()? {
+                if row.end_sequence() {
+                    rows.push((row.address(), None));
+                    continue;
+                }
+                let file = match files.get(&row.file_index()) {
+                    Some(file) => Arc::clone(file),
+                    None => {
+                        let file: Arc<str> = match row.file(header) {
+                            Some(entry) => {
+                                let mut path = PathBuf::new();
+                                if let Some(dir) = &unit.comp_dir {
+                                    path.push(&*dir.to_string_lossy());
+                                }
+                                if let Some(dir) = entry.directory(header) {
+                                    path.push(&*dwarf.attr_string(&unit, dir)?.to_string_lossy());
+                                }
+                                let name = dwarf.attr_string(&unit, entry.path_name())?;
+                                path.push(&*name.to_string_lossy());
+                                path.to_string_lossy().into()
+                            }
+                            None => "<unknown>".into(),
+                        };
+                        files.insert(row.file_index(), Arc::clone(&file));
+                        file
+                    }
+                };
+                let column = match row.column() {
+                    ColumnType::LeftEdge => None,
+                    ColumnType::Column(column) => Some(column.get() as u32),
+                };
+                rows.push((
+                    row.address(),
+                    Some(SourceLocation {
+                        file,
+                        line: row.line().map(|line| line.get() as u32),
+                        column,
+                    }),
+                ));
+            }
+        }
+
+        // The end of a sequence may be the start of the next one: sort the
+        // ends first so that lookups find the start.
+        rows.sort_by_key(|(address, location)| (*address, location.is_some()));
+        Ok(Self { rows })
+    }
+
+    /// Return whether the table has no rows.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Find the location of the instruction at `address`, relative to the
+    /// start of the code section.
+    pub(crate) fn lookup(&self, address: u64) -> Option<&SourceLocation> {
+        let index = self.rows.partition_point(|(start, _)| *start <= address);
+        let (_, location) = self.rows.get(index.checked_sub(1)?)?;
+        location.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gimli::write::{
+        Address, AttributeValue, DwarfUnit, EndianVec, LineProgram, LineString, Sections,
+    };
+    use wasmer_types::CustomSectionIndex;
+
+    fn module_with_line_program() -> ModuleInfo {
+        let encoding = gimli::Encoding {
+            format: gimli::Format::Dwarf32,
+            version: 4,
+            address_size: 4,
+        };
+        let mut dwarf = DwarfUnit::new(encoding);
+        let mut program = LineProgram::new(
+            encoding,
+            gimli::LineEncoding::default(),
+            LineString::String(b"/src".to_vec()),
+            LineString::String(b"lib.rs".to_vec()),
+            None,
+        );
+        let dir = program.default_directory();
+        let lib = program.add_file(LineString::String(b"lib.rs".to_vec()), dir, None);
+        let util = program.add_file(LineString::String(b"util.rs".to_vec()), dir, None);
+
+        program.begin_sequence(Some(Address::Constant(0x10)));
+        program.row().file = lib;
+        program.row().line = 3;
+        program.row().column = 5;
+        program.generate_row();
+        program.row().address_offset = 4;
+        program.row().file = util;
+        program.row().line = 12;
+        program.row().column = 0;
+        program.generate_row();
+        program.end_sequence(0x10);
+        dwarf.unit.line_program = program;
+
+        let root = dwarf.unit.root();
+        let root = dwarf.unit.get_mut(root);
+        root.set(
+            gimli::DW_AT_comp_dir,
+            AttributeValue::String(b"/src".to_vec()),
+        );
+        root.set(
+            gimli::DW_AT_name,
+            AttributeValue::String(b"lib.rs".to_vec()),
+        );
+
+        let mut sections = Sections::new(EndianVec::new(LittleEndian));
+        dwarf.write(&mut sections).unwrap();
+        let mut module = ModuleInfo::new();
+        sections
+            .for_each(|id, data| -> gimli::write::Result<()> {
+                if !data.slice().is_empty() {
+                    let index =
+                        CustomSectionIndex::from_u32(module.custom_sections_data.len() as _);
+                    module.custom_sections.insert(id.name().to_string(), index);
+                    module.custom_sections_data.push(Arc::from(data.slice()));
+                }
+                Ok(())
+            })
+            .unwrap();
+        module
+    }
+
+    #[test]
+    fn lookup_finds_enclosing_row() {
+        let table = LineTable::new(&module_with_line_program());
+        assert_eq!(table.lookup(0xf), None);
+
+        let location = table.lookup(0x12).unwrap();
+        assert_eq!(&*location.file, "/src/lib.rs");
+        assert_eq!(location.line, Some(3));
+        assert_eq!(location.column, Some(5));
+
+        let location = table.lookup(0x1f).unwrap();
+        assert_eq!(&*location.file, "/src/util.rs");
+        assert_eq!(location.line, Some(12));
+        assert_eq!(location.column, None);
+
+        assert_eq!(table.lookup(0x20), None);
+    }
+
+    #[test]
+    fn modules_without_dwarf_have_no_rows() {
+        assert!(LineTable::new(&ModuleInfo::new()).is_empty());
+    }
+}
@@ -3,7 +3,7 @@ use backtrace::Backtrace;
 use std::error::Error;
 use std::fmt;
 use std::sync::Arc;
-use wasmer_vm::{raise_user_trap, Trap, TrapCode};
+use wasmer_vm::{raise_user_trap, Exception, Trap, TrapCode};
 
 /// A struct representing an aborted instruction execution, with a message
 /// indicating the cause.
@@ -19,6 +19,7 @@ enum RuntimeErrorSource {
     OOM,
     User(Box<dyn Error + Send + Sync>),
     Trap(TrapCode),
+    Exception(Exception),
 }
 
 impl fmt::Display for RuntimeErrorSource {
@@ -28,6 +29,7 @@ impl fmt::Display for RuntimeErrorSource {
             Self::User(s) => write!(f, "{}", s),
             Self::OOM => write!(f, "Wasmer VM out of memory"),
             Self::Trap(s) => write!(f, "{}", s.message()),
+            Self::Exception(e) => write!(f, "uncaught exception {}", e.tag().ty()),
         }
     }
 }
@@ -103,6 +105,16 @@ impl RuntimeError {
                 trap_code,
                 backtrace,
             } => Self::new_with_trace(&info, None, RuntimeErrorSource::Trap(trap_code), backtrace),
+            // A wasm exception that no wasm code caught
+            Trap::Exception {
+                exception,
+                backtrace,
+            } => Self::new_with_trace(
+                &info,
+                None,
+                RuntimeErrorSource::Exception(exception),
+                backtrace,
+            ),
         }
     }
 
@@ -191,6 +203,24 @@ impl RuntimeError {
         }
     }
 
+    /// Returns the WebAssembly exception, if the error is an exception that
+    /// no WebAssembly code caught.
+    pub fn exception(&self) -> Option<&Exception> {
+        match &self.inner.source {
+            RuntimeErrorSource::Exception(exception) => Some(exception),
+            _ => None,
+        }
+    }
+
+    /// Returns a reference to the user error of type `T` this
+    /// `RuntimeError` was created from, if any.
+    pub fn downcast_ref<T: Error + 'static>(&self) -> Option<&T> {
+        match &self.inner.source {
+            RuntimeErrorSource::User(err) => err.downcast_ref::<T>(),
+            _ => None,
+        }
+    }
+
     /// Returns true if the `RuntimeError` is the same as T
     pub fn is<T: Error + 'static>(&self) -> bool {
         match &self.inner.source {
@@ -236,6 +266,15 @@ impl fmt::Display for RuntimeError {
                 func_index,
                 frame.module_offset()
             )?;
+            if let Some(file) = frame.file() {
+                write!(f, "\n        at {}", file)?;
+                if let Some(line) = frame.line() {
+                    write!(f, ":{}", line)?;
+                    if let Some(column) = frame.column() {
+                        write!(f, ":{}", column)?;
+                    }
+                }
+            }
         }
         Ok(())
     }
@@ -9,13 +9,16 @@
 //! use wasmer_types::ModuleInfo;
 //!
 //! let module: ModuleInfo = ...;
-//! FRAME_INFO.register(module, compiled_functions);
+//! GlobalFrameInfo::register(module, &finished_functions, frame_infos);
 //! ```
+use super::dwarf::{LineTable, SourceLocation};
+use std::cmp;
 use std::collections::BTreeMap;
 use std::sync::{Arc, RwLock};
 use wasmer_compiler::{CompiledFunctionFrameInfo, SourceLoc, TrapInformation};
-use wasmer_types::entity::{EntityRef, PrimaryMap};
+use wasmer_types::entity::{BoxedSlice, EntityRef, PrimaryMap};
 use wasmer_types::{LocalFunctionIndex, ModuleInfo};
+use wasmer_vm::FunctionExtent;
 
 lazy_static::lazy_static! {
     /// This is a global cache of backtrace frame information for all active
@@ -26,6 +29,8 @@ lazy_static::lazy_static! {
     pub static ref FRAME_INFO: RwLock<GlobalFrameInfo> = Default::default();
 }
 
+/// The frame information of all the loaded modules, keyed by the address
+/// ranges of their code.
 #[derive(Default)]
 pub struct GlobalFrameInfo {
     /// An internal map that keeps track of backtrace frame information for
@@ -53,14 +58,11 @@ struct ModuleInfoFrameInfo {
     start: usize,
     functions: BTreeMap<usize, FunctionInfo>,
     module: Arc<ModuleInfo>,
-    frame_infos: PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>,
+    /// The line table read from the DWARF sections of the module, if any.
+    lines: Option<LineTable>,
 }
 
 impl ModuleInfoFrameInfo {
-    fn function_debug_info(&self, local_index: LocalFunctionIndex) -> &CompiledFunctionFrameInfo {
-        &self.frame_infos.get(local_index).unwrap()
-    }
-
     /// Gets a function given a pc
     fn function_info(&self, pc: usize) -> Option<&FunctionInfo> {
         let (end, func) = self.functions.range(pc..).next()?;
@@ -76,6 +78,7 @@ impl ModuleInfoFrameInfo {
 struct FunctionInfo {
     start: usize,
     local_index: LocalFunctionIndex,
+    frame_info: CompiledFunctionFrameInfo,
 }
 
 impl GlobalFrameInfo {
@@ -91,7 +94,7 @@ impl GlobalFrameInfo {
         // machine instruction that corresponds to `pc`, which then allows us to
         // map that to a wasm original source location.
         let rel_pos = pc - func.start;
-        let instr_map = &module.function_debug_info(func.local_index).address_map;
+        let instr_map = &func.frame_info.address_map;
         let pos = match instr_map
             .instructions
             .binary_search_by_key(&rel_pos, |map| map.code_offset)
@@ -127,12 +130,19 @@ impl GlobalFrameInfo {
             None => instr_map.start_srcloc,
         };
         let func_index = module.module.func_index(func.local_index);
+        let source = module.lines.as_ref().and_then(|lines| {
+            let address = instr
+                .bits()
+                .checked_sub(module.module.code_section_offset)?;
+            lines.lookup(u64::from(address)).cloned()
+        });
         Some(FrameInfo {
             module_name: module.module.name(),
             func_index: func_index.index() as u32,
             function_name: module.module.function_names.get(&func_index).cloned(),
             instr,
             func_start: instr_map.start_srcloc,
+            source,
         })
     }
 
@@ -140,13 +150,98 @@ impl GlobalFrameInfo {
     pub fn lookup_trap_info(&self, pc: usize) -> Option<&TrapInformation> {
         let module = self.module_info(pc)?;
         let func = module.function_info(pc)?;
-        let traps = &module.function_debug_info(func.local_index).traps;
+        let traps = &func.frame_info.traps;
         let idx = traps
             .binary_search_by_key(&((pc - func.start) as u32), |info| info.code_offset)
             .ok()?;
         Some(&traps[idx])
     }
 
+    /// Registers the frame information of a module's compiled functions.
+    ///
+    /// The DWARF sections of the module, if any, are read here so that
+    /// frames can be mapped back to source locations.
+    ///
+    /// Returns `None` if the module has no functions. Otherwise the returned
+    /// object, when dropped, unregisters the information.
+    pub fn register(
+        module: Arc<ModuleInfo>,
+        finished_functions: &BoxedSlice<LocalFunctionIndex, FunctionExtent>,
+        frame_infos: PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>,
+    ) -> Option<GlobalFrameInfoRegistration> {
+        let functions = finished_functions
+            .iter()
+            .zip(frame_infos)
+            .map(|((local_index, extent), (_, frame_info))| (local_index, *extent, frame_info));
+        Self::register_functions(module, functions)
+    }
+
+    /// Registers the frame information of a single function of `module`
+    /// compiled after the rest of it, such as a lazily compiled function.
+    ///
+    /// The returned object, when dropped, unregisters the information.
+    pub fn register_function(
+        module: Arc<ModuleInfo>,
+        local_index: LocalFunctionIndex,
+        extent: FunctionExtent,
+        frame_info: CompiledFunctionFrameInfo,
+    ) -> GlobalFrameInfoRegistration {
+        let function = (local_index, extent, frame_info);
+        Self::register_functions(module, std::iter::once(function)).unwrap()
+    }
+
+    fn register_functions(
+        module: Arc<ModuleInfo>,
+        finished_functions: impl Iterator<
+            Item = (
+                LocalFunctionIndex,
+                FunctionExtent,
+                CompiledFunctionFrameInfo,
+            ),
+        >,
+    ) -> Option<GlobalFrameInfoRegistration> {
+        let mut min = usize::MAX;
+        let mut max = 0;
+        let mut functions = BTreeMap::new();
+        for (local_index, extent, frame_info) in finished_functions {
+            let start = *extent.address as usize;
+            // Keep the end inclusive, as the lookups are.
+            let end = start + extent.length.max(1) - 1;
+            min = cmp::min(min, start);
+            max = cmp::max(max, end);
+            let func = FunctionInfo {
+                start,
+                local_index,
+                frame_info,
+            };
+            assert!(functions.insert(end, func).is_none());
+        }
+        if functions.is_empty() {
+            return None;
+        }
+
+        let lines = Some(LineTable::new(&module)).filter(|lines| !lines.is_empty());
+        let mut info = FRAME_INFO.write().unwrap();
+        // The code of distinct modules never overlaps.
+        if let Some((_, prev)) = info.ranges.range(max..).next() {
+            assert!(prev.start > max);
+        }
+        if let Some((prev_end, _)) = info.ranges.range(..=min).next_back() {
+            assert!(*prev_end < min);
+        }
+        let prev = info.ranges.insert(
+            max,
+            ModuleInfoFrameInfo {
+                start: min,
+                functions,
+                module,
+                lines,
+            },
+        );
+        assert!(prev.is_none());
+        Some(GlobalFrameInfoRegistration { key: max })
+    }
+
     /// Gets a module given a pc
     fn module_info(&self, pc: usize) -> Option<&ModuleInfoFrameInfo> {
         let (end, module_info) = self.ranges.range(pc..).next()?;
@@ -181,6 +276,7 @@ pub struct FrameInfo {
     function_name: Option<String>,
     func_start: SourceLoc,
     instr: SourceLoc,
+    source: Option<SourceLocation>,
 }
 
 impl FrameInfo {
@@ -241,4 +337,23 @@ impl FrameInfo {
     pub fn func_offset(&self) -> usize {
         (self.instr.bits() - self.func_start.bits()) as usize
     }
+    /// Returns the source file of the instruction this frame points to.
+    ///
+    /// This is only available when the module carries DWARF debug
+    /// information, in its `.debug_*` custom sections.
+    pub fn file(&self) -> Option<&str> {
+        self.source.as_ref().map(|source| &*source.file)
+    }
+
+    /// Returns the line in [`FrameInfo::file`] of the instruction this frame
+    /// points to, starting at 1.
+    pub fn line(&self) -> Option<u32> {
+        self.source.as_ref().and_then(|source| source.line)
+    }
+
+    /// Returns the column in [`FrameInfo::line`] of the instruction this
+    /// frame points to, starting at 1.
+    pub fn column(&self) -> Option<u32> {
+        self.source.as_ref().and_then(|source| source.column)
+    }
 }
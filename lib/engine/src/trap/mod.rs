@@ -1,4 +1,5 @@
+mod dwarf;
 mod error;
 mod frame_info;
 pub use error::RuntimeError;
-pub use frame_info::{FrameInfo, GlobalFrameInfoRegistration};
+pub use frame_info::{FrameInfo, GlobalFrameInfo, GlobalFrameInfoRegistration};
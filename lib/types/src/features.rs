@@ -219,6 +219,25 @@ impl Features {
         self.memory64 = enable;
         self
     }
+
+    /// Configures whether the WebAssembly exception-handling proposal
+    /// will be enabled.
+    ///
+    /// The [WebAssembly exception-handling proposal][proposal] is not
+    /// currently fully standardized and is undergoing development.
+    /// Support for this feature can be enabled through this method for
+    /// appropriate WebAssembly modules.
+    ///
+    /// This feature adds exception tags and the `try`, `catch`,
+    /// `throw`, `rethrow` and `delegate` instructions.
+    ///
+    /// This is `false` by default.
+    ///
+    /// [proposal]: https://github.com/WebAssembly/exception-handling
+    pub fn exceptions(&mut self, enable: bool) -> &mut Self {
+        self.exceptions = enable;
+        self
+    }
 }
 
 impl Default for Features {
@@ -325,4 +344,11 @@ mod test_features {
         features.memory64(true);
         assert!(features.memory64);
     }
+
+    #[test]
+    fn enable_exceptions() {
+        let mut features = Features::new();
+        features.exceptions(true);
+        assert!(features.exceptions);
+    }
 }
@@ -1,6 +1,8 @@
 use crate::lib::std::cell::Cell;
+use crate::lib::std::convert::TryFrom;
 use crate::lib::std::marker::PhantomData;
 use crate::lib::std::ops::Deref;
+use crate::lib::std::ptr;
 // use crate::lib::std::ops::{Bound, RangeBounds};
 use crate::lib::std::slice;
 use crate::lib::std::sync::atomic::{
@@ -46,42 +48,80 @@ impl Atomicity for Atomically {}
 pub struct NonAtomically;
 impl Atomicity for NonAtomically {}
 
+/// The type of the offsets and lengths of a [`MemoryView`]: `u32` for
+/// the memories indexed with 32-bit addresses, and `u64` for those
+/// indexed with 64-bit addresses.
+pub trait MemoryViewIndex: Copy {
+    /// The index as a `usize`, or `None` if it exceeds the address space
+    /// of the host.
+    fn to_usize(self) -> Option<usize>;
+}
+
+impl MemoryViewIndex for u32 {
+    fn to_usize(self) -> Option<usize> {
+        usize::try_from(self).ok()
+    }
+}
+
+impl MemoryViewIndex for u64 {
+    fn to_usize(self) -> Option<usize> {
+        usize::try_from(self).ok()
+    }
+}
+
 /// A view into a memory.
-pub struct MemoryView<'a, T: 'a, A = NonAtomically> {
+///
+/// Its offsets and length are `I`s, see [`MemoryViewIndex`].
+pub struct MemoryView<'a, T: 'a, A = NonAtomically, I = u32> {
     ptr: *mut T,
     // Note: the length is in the terms of `size::<T>()`.
     // The total length in memory is `size::<T>() * length`.
     length: usize,
-    _phantom: PhantomData<(&'a [Cell<T>], A)>,
+    _phantom: PhantomData<(&'a [Cell<T>], A, I)>,
 }
 
-impl<'a, T> MemoryView<'a, T, NonAtomically>
+/// A view into a memory indexed with 64-bit addresses, whose offsets and
+/// length are `u64`s.
+pub type MemoryView64<'a, T, A = NonAtomically> = MemoryView<'a, T, A, u64>;
+
+impl<'a, T, I> MemoryView<'a, T, NonAtomically, I>
 where
     T: ValueType,
+    I: MemoryViewIndex,
 {
     /// Creates a new MemoryView given a `pointer` and `length`.
-    pub unsafe fn new(ptr: *mut T, length: u32) -> Self {
+    ///
+    /// # Panics
+    ///
+    /// Panics if `length` exceeds the address space of the host.
+    pub unsafe fn new(ptr: *mut T, length: I) -> Self {
         Self {
             ptr,
-            length: length as usize,
+            length: length
+                .to_usize()
+                .expect("The length exceeds the address space"),
             _phantom: PhantomData,
         }
     }
 
     /// Creates a subarray view from this `MemoryView`.
-    pub fn subarray(&self, start: u32, end: u32) -> Self {
-        assert!(
-            (start as usize) < self.length,
-            "The range start is bigger than current length"
-        );
-        assert!(
-            (end as usize) < self.length,
-            "The range end is bigger than current length"
-        );
+    ///
+    /// # Panics
+    ///
+    /// Panics if `start..end` isn't a range within the view.
+    pub fn subarray(&self, start: I, end: I) -> Self {
+        let end = end
+            .to_usize()
+            .filter(|end| *end <= self.length)
+            .expect("The range end is bigger than current length");
+        let start = start
+            .to_usize()
+            .filter(|start| *start <= end)
+            .expect("The range start is bigger than the range end");
 
         Self {
-            ptr: unsafe { self.ptr.add(start as usize) },
-            length: (end - start) as usize,
+            ptr: unsafe { self.ptr.add(start) },
+            length: end - start,
             _phantom: PhantomData,
         }
     }
@@ -89,7 +129,8 @@ where
     /// Copy the contents of the source slice into this `MemoryView`.
     ///
     /// This function will efficiently copy the memory from within the wasm
-    /// module’s own linear memory to this typed array.
+    /// module’s own linear memory to this typed array. It copies as many
+    /// values as both `src` and the view hold.
     ///
     /// # Safety
     ///
@@ -97,16 +138,14 @@ where
     /// there are no data races when copying memory into the view.
     pub unsafe fn copy_from(&self, src: &[T]) {
         // We cap at a max length
-        let sliced_src = &src[..self.length];
-        for (i, byte) in sliced_src.iter().enumerate() {
-            *self.ptr.offset(i as isize) = *byte;
-        }
+        let len = src.len().min(self.length);
+        ptr::copy_nonoverlapping(src.as_ptr(), self.ptr, len);
     }
 }
 
-impl<'a, T: Atomic> MemoryView<'a, T> {
+impl<'a, T: Atomic, I> MemoryView<'a, T, NonAtomically, I> {
     /// Get atomic access to a memory view.
-    pub fn atomically(&self) -> MemoryView<'a, T::Output, Atomically> {
+    pub fn atomically(&self) -> MemoryView<'a, T::Output, Atomically, I> {
         MemoryView {
             ptr: self.ptr as *mut T::Output,
             length: self.length,
@@ -115,7 +154,7 @@ impl<'a, T: Atomic> MemoryView<'a, T> {
     }
 }
 
-impl<'a, T> Deref for MemoryView<'a, T, NonAtomically> {
+impl<'a, T, I> Deref for MemoryView<'a, T, NonAtomically, I> {
     type Target = [Cell<T>];
     fn deref(&self) -> &[Cell<T>] {
         let mut_slice: &mut [T] = unsafe { slice::from_raw_parts_mut(self.ptr, self.length) };
@@ -124,9 +163,47 @@ impl<'a, T> Deref for MemoryView<'a, T, NonAtomically> {
     }
 }
 
-impl<'a, T> Deref for MemoryView<'a, T, Atomically> {
+impl<'a, T, I> Deref for MemoryView<'a, T, Atomically, I> {
     type Target = [T];
     fn deref(&self) -> &[T] {
         unsafe { slice::from_raw_parts(self.ptr as *const T, self.length) }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subarray_covers_the_whole_view() {
+        let mut data = [1u8, 2, 3, 4];
+        let view = unsafe { MemoryView::<u8>::new(data.as_mut_ptr(), 4) };
+        let whole = view.subarray(0, 4);
+        assert_eq!(whole.len(), 4);
+        assert_eq!(view.subarray(4, 4).len(), 0);
+        let middle = view.subarray(1, 3);
+        assert_eq!(middle[0].get(), 2);
+        assert_eq!(middle.len(), 2);
+
+        let view = unsafe { MemoryView64::<u8>::new(data.as_mut_ptr(), 4) };
+        assert_eq!(view.subarray(2, 4)[1].get(), 4);
+        assert_eq!(view.atomically().len(), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "The range end is bigger than current length")]
+    fn subarray_past_the_end() {
+        let mut data = [0u8; 4];
+        let view = unsafe { MemoryView64::<u8>::new(data.as_mut_ptr(), 4) };
+        view.subarray(0, u64::MAX);
+    }
+
+    #[test]
+    fn copy_from_a_short_source() {
+        let mut data = [0u32; 4];
+        let view = unsafe { MemoryView::<u32>::new(data.as_mut_ptr(), 4) };
+        unsafe { view.copy_from(&[7, 8]) };
+        unsafe { view.subarray(3, 4).copy_from(&[9, 10]) };
+        assert_eq!(data, [7, 8, 0, 9]);
+    }
+}
@@ -9,8 +9,8 @@ use crate::ArchivableIndexMap;
 use crate::{
     CustomSectionIndex, DataIndex, ElemIndex, ExportIndex, FunctionIndex, FunctionType,
     GlobalIndex, GlobalInit, GlobalType, ImportIndex, LocalFunctionIndex, LocalGlobalIndex,
-    LocalMemoryIndex, LocalTableIndex, MemoryIndex, MemoryType, OwnedTableInitializer,
-    SignatureIndex, TableIndex, TableType,
+    LocalMemoryIndex, LocalTableIndex, LocalTagIndex, MemoryIndex, MemoryType,
+    OwnedTableInitializer, SignatureIndex, TableIndex, TableType, TagIndex, TagType,
 };
 use indexmap::IndexMap;
 use rkyv::{
@@ -60,6 +60,9 @@ pub struct ImportCounts {
 
     /// Number of imported globals in the module.
     pub globals: u32,
+
+    /// Number of imported exception tags in the module.
+    pub tags: u32,
 }
 
 impl ImportCounts {
@@ -93,6 +96,11 @@ impl ImportCounts {
         Self::make_local(idx, self.globals)
     }
 
+    /// Convert the `TagIndex` to a `LocalTagIndex`.
+    pub fn local_tag_index(&self, idx: TagIndex) -> Result<LocalTagIndex, TagIndex> {
+        Self::make_local(idx, self.tags)
+    }
+
     fn make_index<R: EntityRef, I: EntityRef>(idx: I, imports: u32) -> R {
         let imports = imports as usize;
         R::new(idx.index() + imports)
@@ -117,6 +125,11 @@ impl ImportCounts {
     pub fn global_index(&self, idx: LocalGlobalIndex) -> GlobalIndex {
         Self::make_index(idx, self.globals)
     }
+
+    /// Convert the `LocalTagIndex` to a `TagIndex`.
+    pub fn tag_index(&self, idx: LocalTagIndex) -> TagIndex {
+        Self::make_index(idx, self.tags)
+    }
 }
 
 /// A translated WebAssembly module, excluding the function bodies and
@@ -179,12 +192,20 @@ pub struct ModuleInfo {
     /// WebAssembly global variables (imported and local).
     pub globals: PrimaryMap<GlobalIndex, GlobalType>,
 
+    /// WebAssembly exception tags (imported and local).
+    pub tags: PrimaryMap<TagIndex, TagType>,
+
     /// Custom sections in the module.
     pub custom_sections: IndexMap<String, CustomSectionIndex>,
 
     /// The data for each CustomSection in the module.
     pub custom_sections_data: PrimaryMap<CustomSectionIndex, Arc<[u8]>>,
 
+    /// The offset in the wasm binary of the contents of the code section.
+    ///
+    /// Addresses in the DWARF sections of the module are relative to it.
+    pub code_section_offset: u32,
+
     /// The counts of imported entities.
     pub import_counts: ImportCounts,
 }
@@ -206,8 +227,10 @@ pub struct ArchivableModuleInfo {
     pub tables: PrimaryMap<TableIndex, TableType>,
     pub memories: PrimaryMap<MemoryIndex, MemoryType>,
     pub globals: PrimaryMap<GlobalIndex, GlobalType>,
+    pub tags: PrimaryMap<TagIndex, TagType>,
     pub custom_sections: ArchivableIndexMap<String, CustomSectionIndex>,
     pub custom_sections_data: PrimaryMap<CustomSectionIndex, Arc<[u8]>>,
+    pub code_section_offset: u32,
     pub import_counts: ImportCounts,
 }
 
@@ -228,8 +251,10 @@ impl From<ModuleInfo> for ArchivableModuleInfo {
             tables: it.tables,
             memories: it.memories,
             globals: it.globals,
+            tags: it.tags,
             custom_sections: ArchivableIndexMap::from(it.custom_sections),
             custom_sections_data: it.custom_sections_data,
+            code_section_offset: it.code_section_offset,
             import_counts: it.import_counts,
         }
     }
@@ -253,8 +278,10 @@ impl From<ArchivableModuleInfo> for ModuleInfo {
             tables: it.tables,
             memories: it.memories,
             globals: it.globals,
+            tags: it.tags,
             custom_sections: it.custom_sections.into(),
             custom_sections_data: it.custom_sections_data,
+            code_section_offset: it.code_section_offset,
             import_counts: it.import_counts,
         }
     }
@@ -310,8 +337,10 @@ impl PartialEq for ModuleInfo {
             && self.tables == other.tables
             && self.memories == other.memories
             && self.globals == other.globals
+            && self.tags == other.tags
             && self.custom_sections == other.custom_sections
             && self.custom_sections_data == other.custom_sections_data
+            && self.code_section_offset == other.code_section_offset
             && self.import_counts == other.import_counts
     }
 }
@@ -420,6 +449,22 @@ impl ModuleInfo {
         self.local_global_index(index).is_none()
     }
 
+    /// Convert a `LocalTagIndex` into a `TagIndex`.
+    pub fn tag_index(&self, local_tag: LocalTagIndex) -> TagIndex {
+        self.import_counts.tag_index(local_tag)
+    }
+
+    /// Convert a `TagIndex` into a `LocalTagIndex`. Returns None if the
+    /// index is an imported tag.
+    pub fn local_tag_index(&self, tag: TagIndex) -> Option<LocalTagIndex> {
+        self.import_counts.local_tag_index(tag).ok()
+    }
+
+    /// Test whether the given tag index is for an imported tag.
+    pub fn is_imported_tag(&self, index: TagIndex) -> bool {
+        self.local_tag_index(index).is_none()
+    }
+
     /// Get the Module name
     pub fn name(&self) -> String {
         match self.name {
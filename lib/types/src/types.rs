@@ -3,7 +3,7 @@ use crate::lib::std::fmt;
 use crate::lib::std::format;
 use crate::lib::std::string::{String, ToString};
 use crate::lib::std::vec::Vec;
-use crate::units::Pages;
+use crate::units::{Pages, WASM64_MAX_PAGES};
 use crate::values::{Value, WasmValueType};
 use std::cell::UnsafeCell;
 use std::rc::Rc;
@@ -117,6 +117,8 @@ pub enum ExternType {
     Table(TableType),
     /// This external type is the type of a WebAssembly memory.
     Memory(MemoryType),
+    /// This external type is the type of a WebAssembly exception tag.
+    Tag(TagType),
 }
 
 macro_rules! accessors {
@@ -149,6 +151,7 @@ impl ExternType {
         (Global(GlobalType) global unwrap_global)
         (Table(TableType) table unwrap_table)
         (Memory(MemoryType) memory unwrap_memory)
+        (Tag(TagType) tag unwrap_tag)
     }
 }
 
@@ -455,6 +458,8 @@ pub struct MemoryType {
     pub maximum: Option<Pages>,
     /// Whether the memory may be shared between multiple threads.
     pub shared: bool,
+    /// Whether the memory is indexed with 64-bit addresses.
+    pub memory64: bool,
 }
 
 impl MemoryType {
@@ -468,6 +473,28 @@ impl MemoryType {
             minimum: minimum.into(),
             maximum: maximum.map(Into::into),
             shared,
+            memory64: false,
+        }
+    }
+
+    /// Creates a new descriptor for a WebAssembly memory indexed with
+    /// 64-bit addresses, given the specified limits of the memory.
+    pub fn new64<IntoPages>(minimum: IntoPages, maximum: Option<IntoPages>, shared: bool) -> Self
+    where
+        IntoPages: Into<Pages>,
+    {
+        Self {
+            memory64: true,
+            ..Self::new(minimum, maximum, shared)
+        }
+    }
+
+    /// The largest number of pages the memory can be indexed with.
+    pub fn max_pages(&self) -> Pages {
+        if self.memory64 {
+            Pages(WASM64_MAX_PAGES)
+        } else {
+            Pages::max_value()
         }
     }
 }
@@ -475,14 +502,63 @@ impl MemoryType {
 impl fmt::Display for MemoryType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let shared = if self.shared { "shared" } else { "not shared" };
+        let index = if self.memory64 { "i64 " } else { "" };
         if let Some(maximum) = self.maximum {
-            write!(f, "{} ({:?}..{:?})", shared, self.minimum, maximum)
+            write!(f, "{}{} ({:?}..{:?})", index, shared, self.minimum, maximum)
         } else {
-            write!(f, "{} ({:?}..)", shared, self.minimum)
+            write!(f, "{}{} ({:?}..)", index, shared, self.minimum)
         }
     }
 }
 
+// Tag Types
+
+/// A descriptor for an exception tag in a WebAssembly module.
+///
+/// Tags identify the exceptions thrown by `throw` and matched by `catch`,
+/// and describe the types of the values the exceptions carry.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, rkyv::Serialize, rkyv::Deserialize, rkyv::Archive)]
+pub struct TagType {
+    /// The types of the values carried by the exceptions.
+    params: Arc<[Type]>,
+}
+
+impl TagType {
+    /// Creates a new tag descriptor for exceptions carrying values of the
+    /// given types.
+    pub fn new<Params>(params: Params) -> Self
+    where
+        Params: Into<Arc<[Type]>>,
+    {
+        Self {
+            params: params.into(),
+        }
+    }
+
+    /// The types of the values carried by the exceptions.
+    pub fn params(&self) -> &[Type] {
+        &self.params
+    }
+}
+
+impl From<&ArchivedTagType> for TagType {
+    fn from(ArchivedTagType { params }: &ArchivedTagType) -> Self {
+        Self::new(&**params)
+    }
+}
+
+impl fmt::Display for TagType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let params = self
+            .params
+            .iter()
+            .map(|p| format!("{:?}", p))
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(f, "[{}]", params)
+    }
+}
+
 // Import Types
 
 /// A descriptor for an imported value into a wasm module.
@@ -624,6 +700,9 @@ pub struct InstanceConfig {
     default_gas_counter: Option<Rc<UnsafeCell<FastGasCounter>>>,
     /// Stack limit, in 8-byte slots.
     pub stack_limit: i32,
+    /// Number of increments of the engine epoch after which execution
+    /// traps, counted from instantiation.
+    pub epoch_deadline: u64,
 }
 
 // Default stack limit, in 8-byte stack slots.
@@ -641,6 +720,7 @@ impl InstanceConfig {
             gas_counter: result.get(),
             default_gas_counter: Some(result),
             stack_limit: DEFAULT_STACK_LIMIT,
+            epoch_deadline: u64::MAX,
         }
     }
 
@@ -658,6 +738,16 @@ impl InstanceConfig {
         self.stack_limit = stack_limit;
         self
     }
+
+    /// Create instance configuration with given epoch deadline, in
+    /// increments of the engine epoch.
+    ///
+    /// The deadline is only checked by code compiled with epoch
+    /// interruption enabled.
+    pub fn with_epoch_deadline(mut self, ticks: u64) -> Self {
+        self.epoch_deadline = ticks;
+        self
+    }
 }
 
 #[cfg(test)]
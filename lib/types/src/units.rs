@@ -13,6 +13,12 @@ pub const WASM_PAGE_SIZE: usize = 0x10000;
 /// The number of pages we can have before we run out of byte index space.
 pub const WASM_MAX_PAGES: u32 = 0x10000;
 
+/// The number of pages a memory indexed with 64-bit addresses can have.
+///
+/// The memory64 proposal allows up to 2^48 pages, but we are bounded by
+/// what [`Pages`] can represent.
+pub const WASM64_MAX_PAGES: u32 = u32::MAX;
+
 /// The minimum number of pages allowed.
 pub const WASM_MIN_PAGES: u32 = 0x100;
 
@@ -1,9 +1,11 @@
-//! Runtime build script compiles C code using setjmp for trap handling.
+//! Runtime build script compiles C code using setjmp for trap handling, and
+//! the stack switching used by fibers.
 
 use std::env;
 
 fn main() {
     println!("cargo:rerun-if-changed=src/trap/handlers.c");
+    println!("cargo:rerun-if-changed=src/fiber.c");
 
     cc::Build::new()
         .warnings(true)
@@ -15,5 +17,6 @@ fn main() {
             None,
         )
         .file("src/trap/handlers.c")
+        .file("src/fiber.c")
         .compile("handlers");
 }
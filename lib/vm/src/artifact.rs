@@ -1,8 +1,11 @@
-use crate::{InstanceHandle, Resolver, Tunables, VMLocalFunction, VMSharedSignatureIndex};
+use crate::{
+    Epoch, InstanceHandle, MemoryImage, Resolver, Tunables, VMLocalFunction, VMSharedSignatureIndex,
+};
 use std::{any::Any, collections::BTreeMap, sync::Arc};
 use wasmer_types::{
     entity::BoxedSlice, ElemIndex, FunctionIndex, GlobalInit, GlobalType, ImportCounts,
-    InstanceConfig, LocalFunctionIndex, OwnedDataInitializer, OwnedTableInitializer,
+    InstanceConfig, LocalFunctionIndex, LocalMemoryIndex, OwnedDataInitializer,
+    OwnedTableInitializer,
 };
 
 mod private {
@@ -66,6 +69,15 @@ pub trait Artifact: Send + Sync {
     /// TODO: consider making it an iterator of `DataInitializer`s instead?
     fn data_segments(&self) -> &[OwnedDataInitializer];
 
+    /// The precomputed image of a local memory, holding the data of all
+    /// its data segments.
+    ///
+    /// When a memory has an image, and the memory can map it, its data
+    /// segments are not copied at instantiation.
+    fn memory_image(&self, _index: LocalMemoryIndex) -> Option<&Arc<MemoryImage>> {
+        None
+    }
+
     /// Passive table elements.
     fn globals(&self) -> &[(GlobalType, GlobalInit)];
 
@@ -75,11 +87,23 @@ pub trait Artifact: Send + Sync {
     /// Function by export name.
     fn export_field(&self, name: &str) -> Option<wasmer_types::ExportIndex>;
 
+    /// The names of all the exports.
+    fn export_names(&self) -> Vec<&str>;
+
     /// Mapping between module SignatureIndex and VMSharedSignatureIndex.
     fn signatures(&self) -> &[VMSharedSignatureIndex];
 
     /// Obtain the function signature for either the import or local definition.
     fn function_signature(&self, index: FunctionIndex) -> Option<VMSharedSignatureIndex>;
+
+    /// The epoch counter checked by the code of this artifact.
+    ///
+    /// Artifacts that are not tied to an engine get a counter that never
+    /// advances.
+    fn epoch(&self) -> &Epoch {
+        static NEVER: Epoch = Epoch::new();
+        &NEVER
+    }
 }
 
 impl dyn Artifact {
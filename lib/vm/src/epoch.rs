@@ -0,0 +1,40 @@
+//! The epoch counter used to interrupt running WebAssembly code.
+//!
+//! An engine owns a single [`Epoch`], which any host thread may advance.
+//! Code compiled with epoch interruption enabled compares it with the
+//! deadline of its instance on function entry and at every loop header,
+//! and traps with [`TrapCode::Interrupted`] once the deadline is reached.
+//!
+//! [`TrapCode::Interrupted`]: crate::TrapCode::Interrupted
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A counter shared by all the instances of an engine.
+#[derive(Debug, Default)]
+#[repr(transparent)]
+pub struct Epoch(AtomicU64);
+
+impl Epoch {
+    /// Create a new counter, starting at zero.
+    pub const fn new() -> Self {
+        Self(AtomicU64::new(0))
+    }
+
+    /// The current value of the counter.
+    pub fn current(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Advance the counter by one, returning its previous value.
+    ///
+    /// Instances whose deadline is reached will trap the next time they
+    /// enter a function or a loop.
+    pub fn increment(&self) -> u64 {
+        self.0.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// A pointer to the value of the counter, as read by compiled code.
+    pub fn as_ptr(&self) -> *const u64 {
+        &self.0 as *const AtomicU64 as *const u64
+    }
+}
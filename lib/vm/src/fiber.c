@@ -0,0 +1,154 @@
+// Stack switching for fibers.
+//
+// `wasmer_fiber_switch` saves the callee-saved registers of the current
+// context on its stack, stores the resulting stack pointer in `*from_sp`,
+// and restores the context whose stack pointer is `to_sp`.
+//
+// `wasmer_fiber_init` prepares a fresh stack so that the first switch to it
+// calls `entry(arg)`. `entry` must never return: it has to switch away for
+// the last time instead.
+
+#include <stddef.h>
+#include <stdint.h>
+
+#if defined(__APPLE__)
+#define SYMBOL(name) "_" #name
+#define TYPE(name)
+#define SIZE(name)
+#else
+#define SYMBOL(name) #name
+#define TYPE(name) ".type " #name ", @function\n"
+#define SIZE(name) ".size " #name ", .-" #name "\n"
+#endif
+
+#if defined(__x86_64__) && !defined(_WIN32)
+
+#define WASMER_FIBER_SUPPORTED 1
+
+__asm__(
+    ".text\n"
+    ".globl " SYMBOL(wasmer_fiber_switch) "\n"
+    ".p2align 4\n"
+    TYPE(wasmer_fiber_switch)
+    SYMBOL(wasmer_fiber_switch) ":\n"
+    "  pushq %rbp\n"
+    "  pushq %rbx\n"
+    "  pushq %r12\n"
+    "  pushq %r13\n"
+    "  pushq %r14\n"
+    "  pushq %r15\n"
+    "  movq %rsp, (%rdi)\n"
+    "  movq %rsi, %rsp\n"
+    "  popq %r15\n"
+    "  popq %r14\n"
+    "  popq %r13\n"
+    "  popq %r12\n"
+    "  popq %rbx\n"
+    "  popq %rbp\n"
+    "  ret\n"
+    SIZE(wasmer_fiber_switch)
+    "\n"
+    ".p2align 4\n"
+    TYPE(wasmer_fiber_start)
+    SYMBOL(wasmer_fiber_start) ":\n"
+    "  movq %r13, %rdi\n"
+    "  callq *%r12\n"
+    "  ud2\n"
+    SIZE(wasmer_fiber_start)
+);
+
+extern void wasmer_fiber_start(void);
+
+void *wasmer_fiber_init(void *stack_top, void (*entry)(void *), void *arg) {
+  uintptr_t *sp = (uintptr_t *)((uintptr_t)stack_top & ~(uintptr_t)15);
+  // Popped by the first switch: r15, r14, r13, r12, rbx, rbp and the
+  // return address. The stack is aligned again once they are all popped.
+  *--sp = (uintptr_t)wasmer_fiber_start;
+  *--sp = 0;                 // rbp
+  *--sp = 0;                 // rbx
+  *--sp = (uintptr_t)entry;  // r12
+  *--sp = (uintptr_t)arg;    // r13
+  *--sp = 0;                 // r14
+  *--sp = 0;                 // r15
+  return sp;
+}
+
+#elif defined(__aarch64__) && !defined(_WIN32)
+
+#define WASMER_FIBER_SUPPORTED 1
+
+__asm__(
+    ".text\n"
+    ".globl " SYMBOL(wasmer_fiber_switch) "\n"
+    ".p2align 2\n"
+    TYPE(wasmer_fiber_switch)
+    SYMBOL(wasmer_fiber_switch) ":\n"
+    "  sub sp, sp, #160\n"
+    "  stp x19, x20, [sp, #0]\n"
+    "  stp x21, x22, [sp, #16]\n"
+    "  stp x23, x24, [sp, #32]\n"
+    "  stp x25, x26, [sp, #48]\n"
+    "  stp x27, x28, [sp, #64]\n"
+    "  stp x29, x30, [sp, #80]\n"
+    "  stp d8, d9, [sp, #96]\n"
+    "  stp d10, d11, [sp, #112]\n"
+    "  stp d12, d13, [sp, #128]\n"
+    "  stp d14, d15, [sp, #144]\n"
+    "  mov x2, sp\n"
+    "  str x2, [x0]\n"
+    "  mov sp, x1\n"
+    "  ldp x19, x20, [sp, #0]\n"
+    "  ldp x21, x22, [sp, #16]\n"
+    "  ldp x23, x24, [sp, #32]\n"
+    "  ldp x25, x26, [sp, #48]\n"
+    "  ldp x27, x28, [sp, #64]\n"
+    "  ldp x29, x30, [sp, #80]\n"
+    "  ldp d8, d9, [sp, #96]\n"
+    "  ldp d10, d11, [sp, #112]\n"
+    "  ldp d12, d13, [sp, #128]\n"
+    "  ldp d14, d15, [sp, #144]\n"
+    "  add sp, sp, #160\n"
+    "  ret\n"
+    SIZE(wasmer_fiber_switch)
+    "\n"
+    ".p2align 2\n"
+    TYPE(wasmer_fiber_start)
+    SYMBOL(wasmer_fiber_start) ":\n"
+    "  mov x0, x20\n"
+    "  blr x19\n"
+    "  brk #1\n"
+    SIZE(wasmer_fiber_start)
+);
+
+extern void wasmer_fiber_start(void);
+
+void *wasmer_fiber_init(void *stack_top, void (*entry)(void *), void *arg) {
+  uintptr_t *sp = (uintptr_t *)((uintptr_t)stack_top & ~(uintptr_t)15);
+  // The frame restored by the first switch: x19-x30 then d8-d15.
+  sp -= 20;
+  for (int i = 0; i < 20; i++) {
+    sp[i] = 0;
+  }
+  sp[0] = (uintptr_t)entry;               // x19
+  sp[1] = (uintptr_t)arg;                 // x20
+  sp[11] = (uintptr_t)wasmer_fiber_start; // x30
+  return sp;
+}
+
+#endif
+
+#if !defined(WASMER_FIBER_SUPPORTED)
+
+void wasmer_fiber_switch(void **from_sp, void *to_sp) {
+  (void)from_sp;
+  (void)to_sp;
+}
+
+void *wasmer_fiber_init(void *stack_top, void (*entry)(void *), void *arg) {
+  (void)stack_top;
+  (void)entry;
+  (void)arg;
+  return NULL;
+}
+
+#endif
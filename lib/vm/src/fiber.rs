@@ -0,0 +1,274 @@
+//! Fibers: computations running on a stack of their own, which can suspend
+//! themselves and be resumed later.
+//!
+//! They let host functions wait on asynchronous operations without blocking
+//! the thread: WebAssembly code runs on a [`Fiber`], and a host function
+//! which has nothing to return yet calls [`Suspend::suspend`] to hand control
+//! back to whoever called [`Fiber::resume`].
+//!
+//! The trap handling state of the calls into WebAssembly made on a fiber is
+//! taken off the thread while it is suspended, and put back when it resumes,
+//! so traps raised on a fiber are always caught on that fiber.
+//!
+//! Fibers are supported on x86_64 and AArch64 Unix hosts. Elsewhere
+//! [`Fiber::new`] returns an error.
+
+use crate::mmap::round_up_to_page_size;
+use crate::trap::traphandlers::{current_tls, CallThreadState, TlsSegment};
+use crate::Mmap;
+use std::cell::Cell;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::thread;
+
+extern "C" {
+    fn wasmer_fiber_init(
+        stack_top: *mut u8,
+        entry: unsafe extern "C" fn(*mut u8),
+        arg: *mut u8,
+    ) -> *mut u8;
+    fn wasmer_fiber_switch(from_sp: *mut *mut u8, to_sp: *mut u8);
+}
+
+/// The default size in bytes of the stack of a fiber.
+pub const DEFAULT_FIBER_STACK_SIZE: usize = 8 << 20;
+
+/// The stack of a fiber, with a guard page below it.
+#[derive(Debug)]
+pub struct FiberStack {
+    mmap: Mmap,
+}
+
+impl FiberStack {
+    /// Allocate a stack of at least `size` bytes.
+    pub fn new(size: usize) -> Result<Self, String> {
+        let page_size = region::page::size();
+        let size = round_up_to_page_size(size.max(page_size), page_size);
+        let mut mmap = Mmap::accessible_reserved(0, size + page_size)?;
+        mmap.make_accessible(page_size, size)?;
+        Ok(Self { mmap })
+    }
+
+    fn top(&mut self) -> *mut u8 {
+        unsafe { self.mmap.as_mut_ptr().add(self.mmap.len()) }
+    }
+}
+
+/// Returned by [`Suspend::suspend`] when the fiber is dropped before it
+/// completes. The computation should then return as soon as possible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "fiber was cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// The handle a computation running on a fiber uses to suspend itself.
+pub struct Suspend {
+    resumer_sp: Cell<*mut u8>,
+    fiber_sp: Cell<*mut u8>,
+    done: Cell<bool>,
+    cancelled: Cell<bool>,
+    /// The trap handling state of the thread which last resumed the fiber.
+    tls_base: Cell<*const CallThreadState>,
+    /// The trap handling state of the fiber, while it is suspended.
+    tls: Cell<Option<TlsSegment>>,
+}
+
+impl Suspend {
+    /// Suspend the fiber, returning from the call to [`Fiber::resume`] that
+    /// started or resumed it. This returns once the fiber is resumed again.
+    pub fn suspend(&self) -> Result<(), Cancelled> {
+        if self.cancelled.get() {
+            return Err(Cancelled);
+        }
+        unsafe {
+            let tls = TlsSegment::detach(self.tls_base.get())
+                .expect("tls should be previously initialized");
+            self.tls.set(Some(tls));
+            wasmer_fiber_switch(self.fiber_sp.as_ptr(), self.resumer_sp.get());
+        }
+        if self.cancelled.get() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Return whether the fiber was dropped before it completed.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.get()
+    }
+}
+
+type FiberFunc<'a, R> = Box<dyn FnOnce(&Suspend) -> R + 'a>;
+
+struct Inner<'a, R> {
+    func: Option<FiberFunc<'a, R>>,
+    result: Option<thread::Result<R>>,
+    suspend: Suspend,
+}
+
+/// A computation running on a stack of its own.
+///
+/// Dropping a fiber which was started but has not completed resumes it with
+/// [`Suspend::suspend`] returning [`Cancelled`], until it completes.
+pub struct Fiber<'a, R> {
+    inner: Box<Inner<'a, R>>,
+    // Only there to be freed after the computation is over.
+    _stack: FiberStack,
+}
+
+impl<'a, R> Fiber<'a, R> {
+    /// Create a fiber which will run `func` on `stack` when it is first
+    /// resumed.
+    pub fn new(
+        mut stack: FiberStack,
+        func: impl FnOnce(&Suspend) -> R + 'a,
+    ) -> Result<Self, String> {
+        let mut inner = Box::new(Inner {
+            func: Some(Box::new(func) as FiberFunc<'a, R>),
+            result: None,
+            suspend: Suspend {
+                resumer_sp: Cell::new(ptr::null_mut()),
+                fiber_sp: Cell::new(ptr::null_mut()),
+                done: Cell::new(false),
+                cancelled: Cell::new(false),
+                tls_base: Cell::new(ptr::null()),
+                tls: Cell::new(None),
+            },
+        });
+        let sp = unsafe {
+            wasmer_fiber_init(
+                stack.top(),
+                fiber_entry::<R>,
+                &mut *inner as *mut Inner<'a, R> as *mut u8,
+            )
+        };
+        if sp.is_null() {
+            return Err("fibers are not supported on this platform".to_string());
+        }
+        inner.suspend.fiber_sp.set(sp);
+        Ok(Self {
+            inner,
+            _stack: stack,
+        })
+    }
+
+    /// Run the fiber until it suspends itself or completes.
+    ///
+    /// Returns `None` if it suspended itself, and the result of the
+    /// computation once it completed. A panic of the computation is resumed
+    /// here.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fiber has already completed.
+    pub fn resume(&mut self) -> Option<R> {
+        assert!(!self.is_done(), "cannot resume a completed fiber");
+        self.switch_in();
+        if !self.is_done() {
+            return None;
+        }
+        match self.inner.result.take().unwrap() {
+            Ok(result) => Some(result),
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+
+    /// Return whether the computation has completed.
+    pub fn is_done(&self) -> bool {
+        self.inner.suspend.done.get()
+    }
+
+    fn switch_in(&mut self) {
+        let suspend = &self.inner.suspend;
+        unsafe {
+            suspend.tls_base.set(current_tls());
+            if let Some(tls) = suspend.tls.take() {
+                tls.attach().expect("tls should be previously initialized");
+            }
+            wasmer_fiber_switch(suspend.resumer_sp.as_ptr(), suspend.fiber_sp.get());
+        }
+    }
+}
+
+impl<'a, R> Drop for Fiber<'a, R> {
+    fn drop(&mut self) {
+        if self.inner.func.is_some() {
+            return;
+        }
+        self.inner.suspend.cancelled.set(true);
+        while !self.is_done() {
+            self.switch_in();
+        }
+    }
+}
+
+unsafe extern "C" fn fiber_entry<R>(arg: *mut u8) {
+    let inner = &mut *(arg as *mut Inner<'_, R>);
+    let func = inner.func.take().unwrap();
+    let suspend = &inner.suspend;
+    let result = panic::catch_unwind(AssertUnwindSafe(|| func(suspend)));
+    inner.result = Some(result);
+    inner.suspend.done.set(true);
+    wasmer_fiber_switch(
+        inner.suspend.fiber_sp.as_ptr(),
+        inner.suspend.resumer_sp.get(),
+    );
+    // A completed fiber is never resumed.
+    std::process::abort();
+}
+
+#[cfg(all(test, unix, any(target_arch = "x86_64", target_arch = "aarch64")))]
+mod tests {
+    use super::*;
+
+    fn stack() -> FiberStack {
+        FiberStack::new(64 * 1024).unwrap()
+    }
+
+    #[test]
+    fn suspend_and_resume() {
+        let mut steps = Vec::new();
+        let mut fiber = Fiber::new(stack(), |suspend| {
+            for i in 0..3 {
+                steps.push(i);
+                suspend.suspend().unwrap();
+            }
+            42
+        })
+        .unwrap();
+        assert_eq!(fiber.resume(), None);
+        assert_eq!(fiber.resume(), None);
+        assert_eq!(fiber.resume(), None);
+        assert_eq!(fiber.resume(), Some(42));
+        assert!(fiber.is_done());
+        drop(fiber);
+        assert_eq!(steps, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn drop_cancels_suspended_fiber() {
+        let mut outcome = None;
+        let mut fiber = Fiber::new(stack(), |suspend| {
+            outcome = Some(suspend.suspend());
+        })
+        .unwrap();
+        assert_eq!(fiber.resume(), None);
+        drop(fiber);
+        assert_eq!(outcome, Some(Err(Cancelled)));
+    }
+
+    #[test]
+    fn panics_are_resumed() {
+        let mut fiber = Fiber::new(stack(), |_| panic!("boom")).unwrap();
+        let payload = panic::catch_unwind(AssertUnwindSafe(|| fiber.resume())).unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"boom"));
+    }
+}
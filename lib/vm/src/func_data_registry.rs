@@ -6,6 +6,7 @@
 
 use crate::vmcontext::VMCallerCheckedAnyfunc;
 use std::collections::HashMap;
+use std::ops::Range;
 use std::sync::Mutex;
 
 /// The registry that holds the values that `VMFuncRef`s point to.
@@ -88,8 +89,9 @@ unsafe impl Sync for VMFuncRef {}
 
 #[derive(Debug, Default)]
 struct Inner {
-    func_data: Vec<VMCallerCheckedAnyfunc>,
-    anyfunc_to_index: HashMap<VMCallerCheckedAnyfunc, usize>,
+    /// The registered functions. They are boxed so that their `VMFuncRef`s
+    /// stay valid while other functions are registered.
+    func_data: HashMap<VMCallerCheckedAnyfunc, Box<VMCallerCheckedAnyfunc>>,
 }
 
 impl FuncDataRegistry {
@@ -103,14 +105,30 @@ impl FuncDataRegistry {
     /// Register a signature and return its unique index.
     pub fn register(&self, anyfunc: VMCallerCheckedAnyfunc) -> VMFuncRef {
         let mut inner = self.inner.lock().unwrap();
-        let data = if let Some(&idx) = inner.anyfunc_to_index.get(&anyfunc) {
-            &inner.func_data[idx]
-        } else {
-            let idx = inner.func_data.len();
-            inner.func_data.push(anyfunc);
-            inner.anyfunc_to_index.insert(anyfunc, idx);
-            &inner.func_data[idx]
-        };
-        VMFuncRef(data)
+        let data = inner
+            .func_data
+            .entry(anyfunc)
+            .or_insert_with(|| Box::new(anyfunc));
+        VMFuncRef(&**data)
+    }
+
+    /// Remove the functions whose code is in `code`, once it is unmapped.
+    ///
+    /// The `VMFuncRef`s of these functions must not be used anymore.
+    pub fn unregister_code(&self, code: Range<usize>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner
+            .func_data
+            .retain(|anyfunc, _| !code.contains(&(anyfunc.func_ptr as usize)));
+    }
+
+    /// The number of functions registered.
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().func_data.len()
+    }
+
+    /// Whether no function is registered.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 }
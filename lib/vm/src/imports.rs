@@ -2,10 +2,12 @@
 // Attributions: https://github.com/wasmerio/wasmer/blob/master/ATTRIBUTIONS.md
 
 use crate::instance::ImportFunctionEnv;
+use crate::tag::Tag;
 use crate::vmcontext::{VMFunctionImport, VMGlobalImport, VMMemoryImport, VMTableImport};
 use crate::{VMSharedSignatureIndex, VMTrampoline};
+use std::sync::Arc;
 use wasmer_types::entity::{BoxedSlice, PrimaryMap};
-use wasmer_types::{FunctionIndex, GlobalIndex, MemoryIndex, TableIndex};
+use wasmer_types::{FunctionIndex, GlobalIndex, MemoryIndex, TableIndex, TagIndex};
 
 /// Type of the import.
 pub enum VMImportType {
@@ -22,6 +24,8 @@ pub enum VMImportType {
     Table(wasmer_types::TableType),
     /// Some memory.
     Memory(wasmer_types::MemoryType, crate::MemoryStyle),
+    /// An exception tag.
+    Tag(wasmer_types::TagType),
 }
 
 /// A module import.
@@ -64,6 +68,9 @@ pub struct Imports {
 
     /// Resolved addresses for imported globals.
     pub globals: BoxedSlice<GlobalIndex, VMGlobalImport>,
+
+    /// Resolved imported exception tags.
+    pub tags: BoxedSlice<TagIndex, Arc<Tag>>,
 }
 
 impl Imports {
@@ -74,6 +81,7 @@ impl Imports {
         table_imports: PrimaryMap<TableIndex, VMTableImport>,
         memory_imports: PrimaryMap<MemoryIndex, VMMemoryImport>,
         global_imports: PrimaryMap<GlobalIndex, VMGlobalImport>,
+        tag_imports: PrimaryMap<TagIndex, Arc<Tag>>,
     ) -> Self {
         Self {
             functions: function_imports.into_boxed_slice(),
@@ -81,6 +89,7 @@ impl Imports {
             tables: table_imports.into_boxed_slice(),
             memories: memory_imports.into_boxed_slice(),
             globals: global_imports.into_boxed_slice(),
+            tags: tag_imports.into_boxed_slice(),
         }
     }
 
@@ -92,6 +101,7 @@ impl Imports {
             tables: PrimaryMap::new().into_boxed_slice(),
             memories: PrimaryMap::new().into_boxed_slice(),
             globals: PrimaryMap::new().into_boxed_slice(),
+            tags: PrimaryMap::new().into_boxed_slice(),
         }
     }
 
@@ -1,4 +1,5 @@
 use super::{Instance, InstanceRef};
+use crate::pool::InstanceSlot;
 use crate::vmcontext::{VMMemoryDefinition, VMTableDefinition};
 use crate::VMOffsets;
 use std::alloc::{self, Layout};
@@ -8,6 +9,15 @@ use std::ptr::{self, NonNull};
 use wasmer_types::entity::EntityRef;
 use wasmer_types::{LocalMemoryIndex, LocalTableIndex};
 
+/// The data of an instance allocated by [`InstanceAllocator::new`]: the
+/// allocator, and the pointers to the definitions of the local memories
+/// and tables.
+pub type InstanceAllocation = (
+    InstanceAllocator,
+    Vec<NonNull<VMMemoryDefinition>>,
+    Vec<NonNull<VMTableDefinition>>,
+);
+
 /// This is an intermediate type that manages the raw allocation and
 /// metadata when creating an [`Instance`].
 ///
@@ -30,29 +40,52 @@ pub struct InstanceAllocator {
     /// The buffer that will contain the [`Instance`] and dynamic fields.
     instance_ptr: NonNull<Instance>,
 
-    /// The layout of the `instance_ptr` buffer.
-    instance_layout: Layout,
+    /// Where the `instance_ptr` buffer comes from.
+    ///
+    /// This is `None` once this type has transferred ownership of the
+    /// `instance_ptr` buffer. If it has not when being dropped, the
+    /// buffer should be freed.
+    storage: Option<InstanceStorage>,
 
     /// Information about the offsets into the `instance_ptr` buffer for
     /// the dynamic fields.
     offsets: VMOffsets,
-
-    /// Whether or not this type has transferred ownership of the
-    /// `instance_ptr` buffer. If it has not when being dropped,
-    /// the buffer should be freed.
-    consumed: bool,
 }
 
 impl Drop for InstanceAllocator {
     fn drop(&mut self) {
-        if !self.consumed {
-            // If `consumed` has not been set, then we still have ownership
+        if let Some(storage) = self.storage.take() {
+            // If the storage has not been taken, then we still have ownership
             // over the buffer and must free it.
-            let instance_ptr = self.instance_ptr.as_ptr();
+            unsafe { storage.deallocate(self.instance_ptr) };
+        }
+    }
+}
+
+/// The backing storage of an [`Instance`].
+#[derive(Debug)]
+pub(crate) enum InstanceStorage {
+    /// The buffer was allocated by the global allocator with this layout.
+    Heap(Layout),
 
-            unsafe {
-                std::alloc::dealloc(instance_ptr as *mut u8, self.instance_layout);
-            }
+    /// The buffer lives in a slot of an [`InstancePool`]; the slot goes
+    /// back to the pool when it is dropped.
+    ///
+    /// [`InstancePool`]: crate::InstancePool
+    Pooled(InstanceSlot),
+}
+
+impl InstanceStorage {
+    /// Free the `instance_ptr` buffer.
+    ///
+    /// # Safety
+    ///
+    /// `instance_ptr` must be the buffer described by `self`, and the
+    /// `Instance` it may contain must already have been dropped.
+    pub(crate) unsafe fn deallocate(self, instance_ptr: NonNull<Instance>) {
+        match self {
+            Self::Heap(layout) => alloc::dealloc(instance_ptr.as_ptr() as *mut u8, layout),
+            Self::Pooled(slot) => drop(slot),
         }
     }
 }
@@ -85,25 +118,51 @@ impl InstanceAllocator {
             alloc::handle_alloc_error(instance_layout);
         };
 
+        // # Safety
+        // The pointer was allocated above with a layout computed from
+        // the same `offsets`.
+        unsafe {
+            Self::with_storage(
+                offsets,
+                instance_ptr,
+                InstanceStorage::Heap(instance_layout),
+            )
+        }
+    }
+
+    /// Wrap an already allocated `instance_ptr` buffer, see [`Self::new`].
+    ///
+    /// # Safety
+    ///
+    /// `instance_ptr` must point to a buffer described by `storage`, at
+    /// least as large and as aligned as `Self::instance_layout(&offsets)`.
+    pub(crate) unsafe fn with_storage(
+        offsets: VMOffsets,
+        instance_ptr: NonNull<Instance>,
+        storage: InstanceStorage,
+    ) -> (
+        Self,
+        Vec<NonNull<VMMemoryDefinition>>,
+        Vec<NonNull<VMTableDefinition>>,
+    ) {
         let allocator = Self {
             instance_ptr,
-            instance_layout,
+            storage: Some(storage),
             offsets,
-            consumed: false,
         };
 
         // # Safety
-        // Both of these calls are safe because we allocate the pointer
-        // above with the same `offsets` that these functions use.
-        // Thus there will be enough valid memory for both of them.
-        let memories = unsafe { allocator.memory_definition_locations() };
-        let tables = unsafe { allocator.table_definition_locations() };
+        // Both of these calls are safe because the caller guarantees
+        // that the pointer is backed by enough memory for `offsets`,
+        // which these functions use.
+        let memories = allocator.memory_definition_locations();
+        let tables = allocator.table_definition_locations();
 
         (allocator, memories, tables)
     }
 
     /// Calculate the appropriate layout for the [`Instance`].
-    fn instance_layout(offsets: &VMOffsets) -> Layout {
+    pub(crate) fn instance_layout(offsets: &VMOffsets) -> Layout {
         let vmctx_size = usize::try_from(offsets.size_of_vmctx())
             .expect("Failed to convert the size of `vmctx` to a `usize`");
 
@@ -127,7 +186,7 @@ impl InstanceAllocator {
     /// - `Self.instance_ptr` must point to enough memory that all of
     ///   the offsets in `Self.offsets` point to valid locations in
     ///   memory, i.e. `Self.instance_ptr` must have been allocated by
-    ///   `Self::new`.
+    ///   `Self::new` or handed to `Self::with_storage`.
     unsafe fn memory_definition_locations(&self) -> Vec<NonNull<VMMemoryDefinition>> {
         let num_memories = self.offsets.num_local_memories;
         let num_memories = usize::try_from(num_memories).unwrap();
@@ -161,7 +220,7 @@ impl InstanceAllocator {
     /// - `Self.instance_ptr` must point to enough memory that all of
     ///   the offsets in `Self.offsets` point to valid locations in
     ///   memory, i.e. `Self.instance_ptr` must have been allocated by
-    ///   `Self::new`.
+    ///   `Self::new` or handed to `Self::with_storage`.
     unsafe fn table_definition_locations(&self) -> Vec<NonNull<VMTableDefinition>> {
         let num_tables = self.offsets.num_local_tables;
         let num_tables = usize::try_from(num_tables).unwrap();
@@ -189,7 +248,10 @@ impl InstanceAllocator {
     pub(crate) fn write_instance(mut self, instance: Instance) -> InstanceRef {
         // Prevent the old state's drop logic from being called as we
         // transition into the new state.
-        self.consumed = true;
+        let storage = self
+            .storage
+            .take()
+            .expect("`InstanceAllocator` storage was already consumed");
 
         unsafe {
             // `instance` is moved at `Self.instance_ptr`. This
@@ -199,10 +261,9 @@ impl InstanceAllocator {
             // Now `instance_ptr` is correctly initialized!
         }
         let instance = self.instance_ptr;
-        let instance_layout = self.instance_layout;
 
         // This is correct because of the invariants of `Self` and
         // because we write `Instance` to the pointer in this function.
-        unsafe { InstanceRef::new(instance, instance_layout) }
+        unsafe { InstanceRef::new(instance, storage) }
     }
 }
@@ -9,17 +9,22 @@
 
 mod allocator;
 mod r#ref;
+mod snapshot;
 
-pub use allocator::InstanceAllocator;
+pub(crate) use allocator::InstanceStorage;
+pub use allocator::{InstanceAllocation, InstanceAllocator};
 pub use r#ref::{InstanceRef, WeakInstanceRef, WeakOrStrongInstanceRef};
+pub use snapshot::{InstanceSnapshot, SnapshotError};
 
 use crate::func_data_registry::VMFuncRef;
 use crate::global::Global;
 use crate::imports::Imports;
 use crate::memory::{Memory, MemoryError};
+use crate::parking_spot::WaitResult;
 use crate::sig_registry::VMSharedSignatureIndex;
 use crate::table::{Table, TableElement};
-use crate::trap::traphandlers::get_trap_handler;
+use crate::tag::Tag;
+use crate::trap::traphandlers::{get_trap_handler, PENDING_EXCEPTIONS};
 use crate::trap::{catch_traps, Trap, TrapCode};
 use crate::vmcontext::{
     VMBuiltinFunctionsArray, VMCallerCheckedAnyfunc, VMContext, VMFunctionBody,
@@ -27,7 +32,7 @@ use crate::vmcontext::{
     VMLocalFunction, VMMemoryDefinition, VMMemoryImport, VMTableDefinition, VMTableImport,
 };
 use crate::{wasmer_call_trampoline, Artifact, VMOffsets, VMTrampoline};
-use crate::{VMExtern, VMFunction, VMGlobal};
+use crate::{VMExtern, VMFunction, VMGlobal, VMTag};
 use memoffset::offset_of;
 use more_asserts::assert_lt;
 use std::any::Any;
@@ -39,12 +44,14 @@ use std::fmt;
 use std::mem;
 use std::ptr::{self, NonNull};
 use std::slice;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use wasmer_types::entity::{packed_option::ReservedValue, BoxedSlice, EntityRef, PrimaryMap};
 use wasmer_types::{
     DataIndex, DataInitializer, ElemIndex, ExportIndex, FastGasCounter, FunctionIndex, GlobalIndex,
-    GlobalInit, InstanceConfig, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex,
-    OwnedTableInitializer, Pages, TableIndex,
+    GlobalInit, InstanceConfig, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, LocalTagIndex,
+    MemoryIndex, OwnedTableInitializer, Pages, TableIndex, TagIndex,
 };
 
 /// The function pointer to call with data and an [`Instance`] pointer to
@@ -74,6 +81,9 @@ pub(crate) struct Instance {
     /// WebAssembly global data.
     globals: BoxedSlice<LocalGlobalIndex, Arc<Global>>,
 
+    /// WebAssembly exception tags.
+    tags: BoxedSlice<LocalTagIndex, Arc<Tag>>,
+
     /// Passive elements in this instantiation. As `elem.drop`s happen, these
     /// entries get removed.
     passive_elements: RefCell<BTreeMap<ElemIndex, Box<[VMFuncRef]>>>,
@@ -97,6 +107,11 @@ pub(crate) struct Instance {
     /// functions from other Wasm modules.
     imported_function_envs: BoxedSlice<FunctionIndex, ImportFunctionEnv>,
 
+    /// The imported functions, tables, memories, globals and tags. The generated
+    /// code uses bitwise copies of them in `vmctx`: these keep the imported
+    /// entities alive for as long as the instance.
+    imports: Imports,
+
     /// Additional context used by compiled WebAssembly code. This
     /// field is last, and represents a dynamically-sized array that
     /// extends beyond the nominal end of the struct (similar to a
@@ -334,6 +349,14 @@ impl Instance {
         unsafe { self.vmctx_plus_offset(self.offsets().vmctx_globals_begin()) }
     }
 
+    /// Return the indexed exception tag.
+    pub(crate) fn tag(&self, index: TagIndex) -> Arc<Tag> {
+        match self.artifact.import_counts().local_tag_index(index) {
+            Ok(local) => Arc::clone(&self.tags[local]),
+            Err(import) => Arc::clone(&self.imports.tags[import]),
+        }
+    }
+
     /// Return a pointer to the `VMBuiltinFunctionsArray`.
     fn builtin_functions_ptr(&self) -> *mut VMBuiltinFunctionsArray {
         unsafe { self.vmctx_plus_offset(self.offsets().vmctx_builtin_functions_begin()) }
@@ -375,6 +398,29 @@ impl Instance {
         unsafe { self.vmctx_plus_offset(self.offsets().vmctx_stack_limit_begin()) }
     }
 
+    /// Return a pointer to the epoch counter pointer.
+    fn epoch_ptr(&self) -> *mut *const u64 {
+        unsafe { self.vmctx_plus_offset(self.offsets().vmctx_epoch_pointer()) }
+    }
+
+    /// Return a pointer to the epoch deadline.
+    fn epoch_deadline_ptr(&self) -> *mut u64 {
+        unsafe { self.vmctx_plus_offset(self.offsets().vmctx_epoch_deadline()) }
+    }
+
+    /// Return a pointer to the pointer to the number of pending exceptions.
+    fn pending_exceptions_ptr(&self) -> *mut *const AtomicU32 {
+        unsafe { self.vmctx_plus_offset(self.offsets().vmctx_pending_exceptions_pointer()) }
+    }
+
+    /// Set the epoch deadline to `ticks` increments of the epoch from now.
+    fn set_epoch_deadline(&self, ticks: u64) {
+        let deadline = self.artifact.epoch().current().saturating_add(ticks);
+        unsafe {
+            *(self.epoch_deadline_ptr()) = deadline;
+        }
+    }
+
     /// Invoke the WebAssembly start function of the instance, if one is present.
     fn invoke_start_function(&self) -> Result<(), Trap> {
         let start_index = match self.artifact.start_function() {
@@ -688,9 +734,9 @@ impl Instance {
     pub(crate) fn local_memory_copy(
         &self,
         memory_index: LocalMemoryIndex,
-        dst: u32,
-        src: u32,
-        len: u32,
+        dst: u64,
+        src: u64,
+        len: u64,
     ) -> Result<(), Trap> {
         // https://webassembly.github.io/reference-types/core/exec/instructions.html#exec-memory-copy
         let memory = unsafe { self.memory_ptr(memory_index).as_ref() };
@@ -702,15 +748,64 @@ impl Instance {
     pub(crate) fn imported_memory_copy(
         &self,
         memory_index: MemoryIndex,
-        dst: u32,
-        src: u32,
-        len: u32,
+        dst: u64,
+        src: u64,
+        len: u64,
     ) -> Result<(), Trap> {
         let import = self.imported_memory(memory_index);
         // The following memory copy is not synchronized and is not atomic:
         unsafe { import.from.vmmemory().as_ref().memory_copy(dst, src, len) }
     }
 
+    /// Perform a `memory.copy` between two memories, which may be local or
+    /// imported.
+    ///
+    /// Addresses into 32-bit memories only use their low 32 bits, as does
+    /// the length unless both memories are 64-bit.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Trap` error when the source or destination ranges are out of
+    /// bounds.
+    pub(crate) fn memory_copy_between(
+        &self,
+        dst_index: MemoryIndex,
+        src_index: MemoryIndex,
+        dst: u64,
+        src: u64,
+        len: u64,
+    ) -> Result<(), Trap> {
+        // https://webassembly.github.io/multi-memory/core/exec/instructions.html#exec-memory-copy
+        let dst_memory64 = self.get_memory(dst_index).ty().memory64;
+        let src_memory64 = self.get_memory(src_index).ty().memory64;
+        let truncate = |value: u64, memory64: bool| {
+            if memory64 {
+                value
+            } else {
+                u64::from(value as u32)
+            }
+        };
+        let dst = truncate(dst, dst_memory64);
+        let src = truncate(src, src_memory64);
+        let len = truncate(len, dst_memory64 && src_memory64);
+
+        let dst_memory = self.memory_definition(dst_index);
+        let src_memory = self.memory_definition(src_index);
+        let in_bounds = |start: u64, memory: &VMMemoryDefinition| matches!(start.checked_add(len), Some(end) if end <= memory.current_length as u64);
+        if !in_bounds(src, src_memory) || !in_bounds(dst, dst_memory) {
+            return Err(Trap::lib(TrapCode::HeapAccessOutOfBounds));
+        }
+
+        // The following memory copy is not synchronized and is not atomic.
+        // Both memories may be the same one, so the ranges may overlap.
+        unsafe {
+            let dst = dst_memory.base.add(usize::try_from(dst).unwrap());
+            let src = src_memory.base.add(usize::try_from(src).unwrap());
+            ptr::copy(src, dst, usize::try_from(len).unwrap());
+        }
+        Ok(())
+    }
+
     /// Perform the `memory.fill` operation on a locally defined memory.
     ///
     /// # Errors
@@ -719,9 +814,9 @@ impl Instance {
     pub(crate) fn local_memory_fill(
         &self,
         memory_index: LocalMemoryIndex,
-        dst: u32,
+        dst: u64,
         val: u32,
-        len: u32,
+        len: u64,
     ) -> Result<(), Trap> {
         let memory = unsafe { self.memory_ptr(memory_index).as_ref() };
         // The following memory fill is not synchronized and is not atomic:
@@ -736,9 +831,9 @@ impl Instance {
     pub(crate) fn imported_memory_fill(
         &self,
         memory_index: MemoryIndex,
-        dst: u32,
+        dst: u64,
         val: u32,
-        len: u32,
+        len: u64,
     ) -> Result<(), Trap> {
         let import = self.imported_memory(memory_index);
         // The following memory fill is not synchronized and is not atomic:
@@ -756,7 +851,7 @@ impl Instance {
         &self,
         memory_index: MemoryIndex,
         data_index: DataIndex,
-        dst: u32,
+        dst: u64,
         src: u32,
         len: u32,
     ) -> Result<(), Trap> {
@@ -769,9 +864,9 @@ impl Instance {
         let oob_access = src
             .checked_add(len)
             .map_or(true, |n| n as usize > data.len())
-            || dst.checked_add(len).map_or(true, |m| {
-                usize::try_from(m).unwrap() > memory.current_length
-            });
+            || dst
+                .checked_add(len.into())
+                .map_or(true, |m| m > memory.current_length as u64);
 
         if oob_access {
             return Err(Trap::lib(TrapCode::HeapAccessOutOfBounds));
@@ -791,6 +886,93 @@ impl Instance {
         passive_data.remove(&data_index);
     }
 
+    /// Get a memory by index regardless of whether it is locally-defined or
+    /// an imported, foreign memory.
+    pub(crate) fn get_memory(&self, memory_index: MemoryIndex) -> &dyn Memory {
+        match self
+            .artifact
+            .import_counts()
+            .local_memory_index(memory_index)
+        {
+            Ok(local) => self.memories[local].as_ref(),
+            Err(import) => self.imported_memory(import).from.as_ref(),
+        }
+    }
+
+    /// Return a pointer to the `size`-byte value at `addr` in a memory, for
+    /// an atomic access.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Trap` error if the value is out of the memory's bounds, or
+    /// if `addr` is not aligned to `size`.
+    fn atomic_address(
+        &self,
+        memory_index: MemoryIndex,
+        addr: u64,
+        size: u64,
+    ) -> Result<*mut u8, Trap> {
+        let memory = self.memory_definition(memory_index);
+        if addr
+            .checked_add(size)
+            .map_or(true, |end| end > memory.current_length as u64)
+        {
+            return Err(Trap::lib(TrapCode::HeapAccessOutOfBounds));
+        }
+        if addr % size != 0 {
+            return Err(Trap::lib(TrapCode::UnalignedAtomic));
+        }
+        Ok(unsafe { memory.base.add(addr as usize) })
+    }
+
+    /// Performs the `memory.atomic.wait32` and `memory.atomic.wait64`
+    /// operations: park the current thread until it is notified, or until
+    /// `timeout` nanoseconds elapse, if the value at `addr` is `expected`.
+    /// A negative `timeout` never expires.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Trap` error if the value is out of the memory's bounds or
+    /// unaligned, or if the memory is not shared.
+    pub(crate) fn memory_atomic_wait<T: AtomicValue>(
+        &self,
+        memory_index: MemoryIndex,
+        addr: u64,
+        expected: T,
+        timeout: i64,
+    ) -> Result<WaitResult, Trap> {
+        let ptr = self.atomic_address(memory_index, addr, mem::size_of::<T>() as u64)?;
+        let parking_spot = self
+            .get_memory(memory_index)
+            .parking_spot()
+            .ok_or_else(|| Trap::lib(TrapCode::UnsharedMemoryWait))?;
+        let timeout = u64::try_from(timeout).ok().map(Duration::from_nanos);
+        // The memory is shared, so it never moves: the key of an address
+        // is the same in every instance using the memory.
+        Ok(parking_spot.wait(ptr as u64, || unsafe { T::load(ptr) } == expected, timeout))
+    }
+
+    /// Performs the `memory.atomic.notify` operation: wake up at most
+    /// `count` of the threads waiting on `addr`, and return how many were
+    /// woken up. Nothing can wait on a memory which is not shared.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Trap` error if the address is out of the memory's bounds
+    /// or unaligned.
+    pub(crate) fn memory_atomic_notify(
+        &self,
+        memory_index: MemoryIndex,
+        addr: u64,
+        count: u32,
+    ) -> Result<u32, Trap> {
+        let ptr = self.atomic_address(memory_index, addr, 4)?;
+        Ok(match self.get_memory(memory_index).parking_spot() {
+            Some(parking_spot) => parking_spot.notify(ptr as u64, count),
+            None => 0,
+        })
+    }
+
     /// Get a table by index regardless of whether it is locally-defined or an
     /// imported, foreign table.
     pub(crate) fn get_table(&self, table_index: TableIndex) -> &dyn Table {
@@ -853,6 +1035,7 @@ impl InstanceHandle {
         finished_memories: BoxedSlice<LocalMemoryIndex, Arc<dyn Memory>>,
         finished_tables: BoxedSlice<LocalTableIndex, Arc<dyn Table>>,
         finished_globals: BoxedSlice<LocalGlobalIndex, Arc<Global>>,
+        finished_tags: BoxedSlice<LocalTagIndex, Arc<Tag>>,
         imports: Imports,
         passive_data: BTreeMap<DataIndex, Arc<[u8]>>,
         host_state: Box<dyn Any>,
@@ -876,11 +1059,13 @@ impl InstanceHandle {
                 memories: finished_memories,
                 tables: finished_tables,
                 globals: finished_globals,
+                tags: finished_tags,
                 passive_elements: Default::default(),
                 passive_data,
                 host_state,
                 funcrefs,
                 imported_function_envs,
+                imports,
                 vmctx: VMContext {},
             };
 
@@ -891,7 +1076,7 @@ impl InstanceHandle {
                 let instance = instance_ref.as_mut().unwrap();
                 let vmctx_ptr = instance.vmctx_ptr();
                 instance.funcrefs = build_funcrefs(
-                    &imports,
+                    &instance.imports,
                     instance.artifact.functions().iter().map(|(_, f)| f),
                     vmctx_ptr,
                 );
@@ -899,6 +1084,9 @@ impl InstanceHandle {
                 *(instance.gas_counter_ptr()) = instance_config.gas_counter;
                 *(instance.stack_limit_ptr()) = instance_config.stack_limit;
                 *(instance.stack_limit_initial_ptr()) = instance_config.stack_limit;
+                *(instance.epoch_ptr()) = instance.artifact.epoch().as_ptr();
+                instance.set_epoch_deadline(instance_config.epoch_deadline);
+                *(instance.pending_exceptions_ptr()) = &PENDING_EXCEPTIONS;
             }
 
             Self {
@@ -914,24 +1102,24 @@ impl InstanceHandle {
         );
 
         ptr::copy(
-            imports.functions.values().as_slice().as_ptr(),
+            instance.imports.functions.values().as_slice().as_ptr(),
             instance.imported_functions_ptr() as *mut VMFunctionImport,
-            imports.functions.len(),
+            instance.imports.functions.len(),
         );
         ptr::copy(
-            imports.tables.values().as_slice().as_ptr(),
+            instance.imports.tables.values().as_slice().as_ptr(),
             instance.imported_tables_ptr() as *mut VMTableImport,
-            imports.tables.len(),
+            instance.imports.tables.len(),
         );
         ptr::copy(
-            imports.memories.values().as_slice().as_ptr(),
+            instance.imports.memories.values().as_slice().as_ptr(),
             instance.imported_memories_ptr() as *mut VMMemoryImport,
-            imports.memories.len(),
+            instance.imports.memories.len(),
         );
         ptr::copy(
-            imports.globals.values().as_slice().as_ptr(),
+            instance.imports.globals.values().as_slice().as_ptr(),
             instance.imported_globals_ptr() as *mut VMGlobalImport,
-            imports.globals.len(),
+            instance.imports.globals.len(),
         );
         // these should already be set, add asserts here? for:
         // - instance.tables_ptr() as *mut VMTableDefinition
@@ -979,6 +1167,30 @@ impl InstanceHandle {
         Ok(())
     }
 
+    /// Finishes the instantiation process started by `Instance::new` by
+    /// restoring `snapshot`, instead of running the initializers and the
+    /// start function.
+    ///
+    /// # Safety
+    ///
+    /// Only safe to call immediately after instantiation.
+    pub unsafe fn restore_snapshot(
+        &self,
+        snapshot: &InstanceSnapshot,
+    ) -> Result<(), SnapshotError> {
+        self.instance().as_ref().restore(snapshot)
+    }
+
+    /// Take a snapshot of the state of the instance, to be restored into
+    /// fresh instances of the same module with
+    /// [`InstanceHandle::restore_snapshot`].
+    ///
+    /// Returns an error if the state of the instance references functions
+    /// of other instances, or host data through externrefs.
+    pub fn snapshot(&self) -> Result<InstanceSnapshot, SnapshotError> {
+        self.instance().as_ref().snapshot()
+    }
+
     /// See [`traphandlers::wasmer_call_trampoline`].
     pub unsafe fn invoke_function(
         &self,
@@ -1092,6 +1304,14 @@ impl InstanceHandle {
         })
     }
 
+    /// Obtain a reference to an exception tag by its index.
+    pub fn tag_by_index(&self, index: TagIndex) -> Option<VMTag> {
+        Some(VMTag {
+            from: self.instance.as_ref().tag(index),
+            instance_ref: Some(WeakOrStrongInstanceRef::Strong(self.instance().clone())),
+        })
+    }
+
     /// Lookup an exported function with the given name.
     pub fn lookup(&self, field: &str) -> Option<VMExtern> {
         let instance = self.instance.as_ref();
@@ -1100,14 +1320,49 @@ impl InstanceHandle {
             ExportIndex::Table(idx) => VMExtern::Table(self.table_by_index(idx)?),
             ExportIndex::Global(idx) => VMExtern::Global(self.global_by_index(idx)?),
             ExportIndex::Memory(idx) => VMExtern::Memory(self.memory_by_index(idx)?),
+            ExportIndex::Tag(idx) => VMExtern::Tag(self.tag_by_index(idx)?),
         })
     }
 
+    /// Lookup all the exports, with their names.
+    pub fn exports(&self) -> Vec<(String, VMExtern)> {
+        let instance = self.instance.as_ref();
+        instance
+            .artifact
+            .export_names()
+            .into_iter()
+            .filter_map(|name| Some((name.to_string(), self.lookup(name)?)))
+            .collect()
+    }
+
+    /// Lookup all the exported memories, with their names.
+    pub fn memories(&self) -> Vec<(String, crate::VMMemory)> {
+        let instance = self.instance.as_ref();
+        instance
+            .artifact
+            .export_names()
+            .into_iter()
+            .filter_map(|name| match instance.artifact.export_field(name)? {
+                ExportIndex::Memory(idx) => Some((name.to_string(), self.memory_by_index(idx)?)),
+                _ => None,
+            })
+            .collect()
+    }
+
     /// Return a reference to the custom state attached to this instance.
     pub fn host_state(&self) -> &dyn Any {
         self.instance().as_ref().host_state()
     }
 
+    /// Set the epoch deadline of this instance to `ticks` increments of
+    /// the engine epoch from now.
+    ///
+    /// Only code compiled with epoch interruption enabled checks the
+    /// deadline.
+    pub fn set_epoch_deadline(&self, ticks: u64) {
+        self.instance().as_ref().set_epoch_deadline(ticks)
+    }
+
     /// Return the memory index for the given `VMMemoryDefinition` in this instance.
     pub fn memory_index(&self, memory: &VMMemoryDefinition) -> LocalMemoryIndex {
         self.instance().as_ref().memory_index(memory)
@@ -1211,11 +1466,41 @@ pub unsafe fn initialize_host_envs<Err: Sized>(
     Ok(())
 }
 
+/// A value `memory.atomic.wait*` can wait on.
+pub(crate) trait AtomicValue: Copy + PartialEq {
+    /// Atomically load the value at `ptr`.
+    ///
+    /// # Safety
+    /// `ptr` must be valid and aligned for the value.
+    unsafe fn load(ptr: *mut u8) -> Self;
+}
+
+impl AtomicValue for u32 {
+    unsafe fn load(ptr: *mut u8) -> Self {
+        (*(ptr as *const AtomicU32)).load(Ordering::SeqCst)
+    }
+}
+
+impl AtomicValue for u64 {
+    unsafe fn load(ptr: *mut u8) -> Self {
+        (*(ptr as *const AtomicU64)).load(Ordering::SeqCst)
+    }
+}
+
 /// Compute the offset for a memory data initializer.
 fn get_memory_init_start(init: &DataInitializer<'_>, instance: &Instance) -> usize {
     let mut start = init.location.offset;
     if let Some(base) = init.location.base {
-        let val = instance.global(base).to_u32();
+        let global = instance.global(base);
+        let val = if instance
+            .get_memory(init.location.memory_index)
+            .ty()
+            .memory64
+        {
+            global.to_u64()
+        } else {
+            global.to_u32().into()
+        };
         start += usize::try_from(val).unwrap();
     }
     start
@@ -1297,11 +1582,38 @@ fn initialize_passive_elements(instance: &Instance) {
 }
 
 /// Initialize the table memory from the provided initializers.
+///
+/// Local memories with an image are initialized by mapping it, and their
+/// data initializers are skipped.
 fn initialize_memories<'a>(
     instance: &Instance,
     data_initializers: impl Iterator<Item = DataInitializer<'a>>,
 ) -> Result<(), Trap> {
+    let imaged = instance
+        .memories
+        .iter()
+        .map(|(index, memory)| {
+            instance
+                .artifact
+                .memory_image(index)
+                .map_or(false, |image| {
+                    // If the image can't be mapped, fall back to copying.
+                    memory.initialize_with_image(image).unwrap_or(false)
+                })
+        })
+        .collect::<Vec<_>>();
+
     for init in data_initializers {
+        if let Ok(index) = instance
+            .artifact
+            .import_counts()
+            .local_memory_index(init.location.memory_index)
+        {
+            if imaged[index.index()] {
+                continue;
+            }
+        }
+
         let memory = instance.memory_definition(init.location.memory_index);
 
         let start = get_memory_init_start(&init, instance);
@@ -1,5 +1,5 @@
+use super::allocator::InstanceStorage;
 use super::Instance;
-use std::alloc::Layout;
 use std::convert::TryFrom;
 use std::ptr::{self, NonNull};
 use std::sync::{Arc, Weak};
@@ -16,8 +16,9 @@ use std::sync::{Arc, Weak};
 #[derive(Debug)]
 #[repr(C)]
 struct InstanceInner {
-    /// The layout of `Instance` (which can vary).
-    instance_layout: Layout,
+    /// Where `Instance` is stored, along with its layout (which
+    /// can vary). It is `None` once the `Instance` is deallocated.
+    storage: Option<InstanceStorage>,
 
     /// The `Instance` itself. It must be the last field of
     /// `InstanceRef` since `Instance` is dyamically-sized.
@@ -43,7 +44,9 @@ impl InstanceInner {
         let instance_ptr = self.instance.as_ptr();
 
         ptr::drop_in_place(instance_ptr);
-        std::alloc::dealloc(instance_ptr as *mut u8, self.instance_layout);
+        if let Some(storage) = self.storage.take() {
+            storage.deallocate(self.instance);
+        }
     }
 
     /// Get a reference to the `Instance`.
@@ -113,9 +116,9 @@ impl InstanceRef {
     /// and correctly initialized pointer to `Instance`. See
     /// [`InstanceAllocator`] for an example of how to correctly use
     /// this API.
-    pub(super) unsafe fn new(instance: NonNull<Instance>, instance_layout: Layout) -> Self {
+    pub(super) unsafe fn new(instance: NonNull<Instance>, storage: InstanceStorage) -> Self {
         Self(Arc::new(InstanceInner {
-            instance_layout,
+            storage: Some(storage),
             instance,
         }))
     }
@@ -0,0 +1,373 @@
+//! Snapshots of the state of an instance.
+//!
+//! A snapshot holds everything a WebAssembly instance can change while it
+//! runs: the contents of its linear memories, the elements of its tables,
+//! the values of its globals, the passive segments it has not dropped yet
+//! and the gas it has burnt. Restoring it into a fresh instance of the same
+//! module, instead of running the initializers and the start function,
+//! yields an instance in the same state.
+//!
+//! Only the entities defined by the instance are part of its snapshot:
+//! imported memories, tables and globals belong to the instance or host
+//! which exports them.
+
+use super::Instance;
+use crate::func_data_registry::VMFuncRef;
+use crate::memory::MemoryError;
+use crate::table::TableElement;
+use crate::vmcontext::VMCallerCheckedAnyfunc;
+use rkyv::ser::serializers::AllocSerializer;
+use std::collections::HashMap;
+use std::mem;
+use std::ptr;
+use std::slice;
+use thiserror::Error;
+use wasmer_types::entity::EntityRef;
+use wasmer_types::{DataIndex, ElemIndex, FunctionIndex, Pages, Type};
+
+const MAGIC_HEADER: [u8; 16] = *b"\0wasmer-snapshot";
+
+/// An error while taking or restoring a snapshot.
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    /// The instance holds state which cannot be part of a snapshot.
+    #[error("cannot snapshot {0}")]
+    Unsupported(String),
+
+    /// The snapshot was taken from an instance of another module.
+    #[error("the snapshot does not match the module: {0}")]
+    Incompatible(String),
+
+    /// A memory of the instance could not grow to the size it has in the
+    /// snapshot.
+    #[error(transparent)]
+    Memory(#[from] MemoryError),
+
+    /// The snapshot could not be serialized.
+    #[error("could not serialize the snapshot: {0}")]
+    Serialize(String),
+
+    /// The bytes are not a serialized snapshot.
+    #[error("could not deserialize the snapshot: {0}")]
+    Deserialize(String),
+}
+
+/// The state of an instance, taken by [`InstanceHandle::snapshot`].
+///
+/// [`InstanceHandle::snapshot`]: crate::InstanceHandle::snapshot
+#[derive(Debug, Clone, PartialEq, Eq, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct InstanceSnapshot {
+    memories: Vec<MemorySnapshot>,
+    /// The elements of each local table, as indices of the functions of
+    /// the instance.
+    tables: Vec<Vec<Option<u32>>>,
+    globals: Vec<GlobalSnapshot>,
+    /// The passive element segments which were not dropped.
+    passive_elements: Vec<u32>,
+    /// The passive data segments which were not dropped.
+    passive_data: Vec<u32>,
+    burnt_gas: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+struct MemorySnapshot {
+    pages: u32,
+    /// The contents of the memory, without the trailing zeroes.
+    data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+enum GlobalSnapshot {
+    /// The bits of a numeric or vector value.
+    Bits(u128),
+    /// A reference, as the index of a function of the instance.
+    Ref(Option<u32>),
+}
+
+impl InstanceSnapshot {
+    /// The amount of gas burnt by the instance when it was taken.
+    pub fn burnt_gas(&self) -> u64 {
+        self.burnt_gas
+    }
+
+    /// Serialize the snapshot into bytes.
+    pub fn serialize(&self) -> Result<Vec<u8>, SnapshotError> {
+        // The format is the one of executables:
+        //
+        // HEADER
+        // RKYV PAYLOAD
+        // RKYV POSITION
+        let mut serializer = AllocSerializer::<1024>::default();
+        let pos = rkyv::ser::Serializer::serialize_value(&mut serializer, self)
+            .map_err(|e| SnapshotError::Serialize(e.to_string()))? as u64;
+        let data = serializer.into_serializer().into_inner();
+        let mut out = Vec::with_capacity(MAGIC_HEADER.len() + data.len() + 8);
+        out.extend(&MAGIC_HEADER);
+        out.extend(data.as_slice());
+        out.extend(&pos.to_le_bytes());
+        Ok(out)
+    }
+
+    /// Deserialize a snapshot serialized by [`InstanceSnapshot::serialize`].
+    ///
+    /// # Safety
+    ///
+    /// The contents of the payload are not validated: `bytes` must come
+    /// from [`InstanceSnapshot::serialize`].
+    pub unsafe fn deserialize(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        if !bytes.starts_with(&MAGIC_HEADER) {
+            return Err(SnapshotError::Deserialize(
+                "the provided bytes are not a snapshot".to_string(),
+            ));
+        }
+        let payload = &bytes[MAGIC_HEADER.len()..];
+        if payload.len() < 8 {
+            return Err(SnapshotError::Deserialize(
+                "the data buffer is too small to be valid".to_string(),
+            ));
+        }
+        let (archive, position) = payload.split_at(payload.len() - 8);
+        let mut position_value = [0u8; 8];
+        position_value.copy_from_slice(position);
+        let position = u64::from_le_bytes(position_value) as usize;
+        if position
+            .checked_add(mem::size_of::<ArchivedInstanceSnapshot>())
+            .map_or(true, |end| end > archive.len())
+        {
+            return Err(SnapshotError::Deserialize(
+                "the buffer is malformed".to_string(),
+            ));
+        }
+        // The archive has to be aligned, which `bytes` may not be.
+        let mut aligned = rkyv::AlignedVec::with_capacity(archive.len());
+        aligned.extend_from_slice(archive);
+        let archived = rkyv::archived_value::<Self>(&aligned, position);
+        Ok(Result::<_, std::convert::Infallible>::unwrap(
+            rkyv::Deserialize::deserialize(archived, &mut rkyv::Infallible),
+        ))
+    }
+}
+
+impl Instance {
+    /// Take a snapshot of the state of the instance.
+    pub(super) fn snapshot(&self) -> Result<InstanceSnapshot, SnapshotError> {
+        let functions: HashMap<*const VMCallerCheckedAnyfunc, FunctionIndex> = self
+            .funcrefs
+            .iter()
+            .map(|(index, anyfunc)| (anyfunc as *const _, index))
+            .collect();
+        let function_index = |funcref: VMFuncRef, what: &dyn Fn() -> String| {
+            if funcref.is_null() {
+                return Ok(None);
+            }
+            match functions.get(&funcref.0) {
+                Some(index) => Ok(Some(index.as_u32())),
+                None => Err(SnapshotError::Unsupported(format!(
+                    "a reference to a function of another instance in {}",
+                    what()
+                ))),
+            }
+        };
+
+        let memories = self
+            .memories
+            .values()
+            .map(|memory| {
+                let definition = unsafe { memory.vmmemory().as_ref() };
+                let data =
+                    unsafe { slice::from_raw_parts(definition.base, definition.current_length) };
+                let len = data
+                    .iter()
+                    .rposition(|byte| *byte != 0)
+                    .map_or(0, |i| i + 1);
+                MemorySnapshot {
+                    pages: memory.size().0,
+                    data: data[..len].to_vec(),
+                }
+            })
+            .collect();
+
+        let tables = self
+            .tables
+            .iter()
+            .map(|(index, table)| {
+                let what = || format!("table {}", index.index());
+                (0..table.size())
+                    .map(|i| match table.get(i) {
+                        Some(TableElement::FuncRef(funcref)) => function_index(funcref, &what),
+                        Some(TableElement::ExternRef(externref)) if externref.is_null() => Ok(None),
+                        Some(TableElement::ExternRef(_)) => Err(SnapshotError::Unsupported(
+                            format!("an externref in {}", what()),
+                        )),
+                        None => unreachable!("the index is within the size of the table"),
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, _>>()?;
+
+        let globals = self
+            .globals
+            .iter()
+            .map(|(index, global)| {
+                let what = || format!("global {}", index.index());
+                let definition = unsafe { global.vmglobal().as_ref() };
+                match global.ty().ty {
+                    Type::FuncRef => {
+                        function_index(definition.to_funcref(), &what).map(GlobalSnapshot::Ref)
+                    }
+                    Type::ExternRef if definition.to_externref().is_null() => {
+                        Ok(GlobalSnapshot::Ref(None))
+                    }
+                    Type::ExternRef => Err(SnapshotError::Unsupported(format!(
+                        "an externref in {}",
+                        what()
+                    ))),
+                    _ => Ok(GlobalSnapshot::Bits(definition.to_u128())),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(InstanceSnapshot {
+            memories,
+            tables,
+            globals,
+            passive_elements: self
+                .passive_elements
+                .borrow()
+                .keys()
+                .map(|index| index.as_u32())
+                .collect(),
+            passive_data: self
+                .passive_data
+                .borrow()
+                .keys()
+                .map(|index| index.as_u32())
+                .collect(),
+            burnt_gas: unsafe { (*self.config.gas_counter).burnt_gas },
+        })
+    }
+
+    /// Put the instance in the state of `snapshot`.
+    ///
+    /// # Safety
+    ///
+    /// Only safe to call on a fresh instance, in place of the
+    /// initializers and the start function.
+    pub(super) unsafe fn restore(&self, snapshot: &InstanceSnapshot) -> Result<(), SnapshotError> {
+        let counts = |what, snapshot: usize, instance: usize| {
+            if snapshot == instance {
+                Ok(())
+            } else {
+                Err(SnapshotError::Incompatible(format!(
+                    "the snapshot has {} local {}, the module {}",
+                    snapshot, what, instance
+                )))
+            }
+        };
+        counts("memories", snapshot.memories.len(), self.memories.len())?;
+        counts("tables", snapshot.tables.len(), self.tables.len())?;
+        counts("globals", snapshot.globals.len(), self.globals.len())?;
+        let function = |index: u32| {
+            let index = FunctionIndex::from_u32(index);
+            if index.index() < self.funcrefs.len() {
+                Ok(self.get_vm_funcref(index))
+            } else {
+                Err(SnapshotError::Incompatible(format!(
+                    "the snapshot references the missing function {}",
+                    index.index()
+                )))
+            }
+        };
+
+        for (memory, saved) in self.memories.values().zip(&snapshot.memories) {
+            let pages = memory.size().0;
+            if saved.pages < pages {
+                return Err(SnapshotError::Incompatible(format!(
+                    "a memory has {} pages in the snapshot, less than its minimum of {}",
+                    saved.pages, pages
+                )));
+            }
+            if saved.pages > pages {
+                memory.grow(Pages(saved.pages - pages))?;
+            }
+            let definition = memory.vmmemory().as_ref();
+            if saved.data.len() > definition.current_length {
+                return Err(SnapshotError::Incompatible(
+                    "the data of a memory exceeds its size".to_string(),
+                ));
+            }
+            ptr::copy_nonoverlapping(saved.data.as_ptr(), definition.base, saved.data.len());
+        }
+
+        for (table, saved) in self.tables.values().zip(&snapshot.tables) {
+            let size = table.size();
+            let saved_size = saved.len() as u32;
+            if saved_size < size {
+                return Err(SnapshotError::Incompatible(format!(
+                    "a table has {} elements in the snapshot, less than its minimum of {}",
+                    saved_size, size
+                )));
+            }
+            if saved_size > size {
+                table
+                    .grow(saved_size - size, TableElement::FuncRef(VMFuncRef::null()))
+                    .ok_or_else(|| {
+                        SnapshotError::Incompatible(format!(
+                            "a table cannot grow to the {} elements it has in the snapshot",
+                            saved_size
+                        ))
+                    })?;
+            }
+            for (i, element) in saved.iter().enumerate() {
+                if let Some(index) = element {
+                    table
+                        .set(i as u32, TableElement::FuncRef(function(*index)?))
+                        .expect("the index is within the size of the table");
+                }
+            }
+        }
+
+        for (global, saved) in self.globals.values().zip(&snapshot.globals) {
+            let definition = global.vmglobal().as_ptr();
+            match saved {
+                GlobalSnapshot::Bits(bits) => *(*definition).as_u128_mut() = *bits,
+                GlobalSnapshot::Ref(None) => *(*definition).as_funcref_mut() = VMFuncRef::null(),
+                GlobalSnapshot::Ref(Some(index)) => {
+                    *(*definition).as_funcref_mut() = function(*index)?
+                }
+            }
+        }
+
+        self.passive_elements
+            .borrow_mut()
+            .retain(|index, _| snapshot.passive_elements.contains(&index.as_u32()));
+        self.passive_data
+            .borrow_mut()
+            .retain(|index, _| snapshot.passive_data.contains(&index.as_u32()));
+        if let Some(index) = snapshot.passive_elements.iter().find(|index| {
+            !self
+                .passive_elements
+                .borrow()
+                .contains_key(&ElemIndex::from_u32(**index))
+        }) {
+            return Err(SnapshotError::Incompatible(format!(
+                "the module has no passive element segment {}",
+                index
+            )));
+        }
+        if let Some(index) = snapshot.passive_data.iter().find(|index| {
+            !self
+                .passive_data
+                .borrow()
+                .contains_key(&DataIndex::from_u32(**index))
+        }) {
+            return Err(SnapshotError::Incompatible(format!(
+                "the module has no passive data segment {}",
+                index
+            )));
+        }
+
+        (*self.config.gas_counter).burnt_gas = snapshot.burnt_gas;
+        Ok(())
+    }
+}
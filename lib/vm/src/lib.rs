@@ -22,17 +22,23 @@
 )]
 
 mod artifact;
+mod epoch;
 mod export;
+mod fiber;
 mod func_data_registry;
 mod global;
 mod imports;
 mod instance;
 mod memory;
+mod memory_image;
 mod mmap;
+mod parking_spot;
+mod pool;
 mod probestack;
 mod resolver;
 mod sig_registry;
 mod table;
+mod tag;
 mod trap;
 mod tunables;
 mod vmcontext;
@@ -41,16 +47,21 @@ mod vmoffsets;
 pub mod libcalls;
 
 pub use crate::artifact::{Artifact, Instantiatable};
+pub use crate::epoch::Epoch;
 pub use crate::export::*;
+pub use crate::fiber::{Cancelled, Fiber, FiberStack, Suspend, DEFAULT_FIBER_STACK_SIZE};
 pub use crate::func_data_registry::{FuncDataRegistry, VMFuncRef};
 pub use crate::global::*;
 pub use crate::imports::{Imports, VMImport, VMImportType};
 pub use crate::instance::{
-    initialize_host_envs, ImportFunctionEnv, ImportInitializerFuncPtr, InstanceAllocator,
-    InstanceHandle, WeakOrStrongInstanceRef,
+    initialize_host_envs, ImportFunctionEnv, ImportInitializerFuncPtr, InstanceAllocation,
+    InstanceAllocator, InstanceHandle, InstanceSnapshot, SnapshotError, WeakOrStrongInstanceRef,
 };
 pub use crate::memory::{LinearMemory, Memory, MemoryError, MemoryStyle};
+pub use crate::memory_image::MemoryImage;
 pub use crate::mmap::Mmap;
+pub use crate::parking_spot::{ParkingSpot, WaitResult};
+pub use crate::pool::{InstancePool, PoolingLimits};
 pub use crate::probestack::PROBESTACK;
 pub use crate::resolver::{
     ChainableNamedResolver, Export, ExportFunction, ExportFunctionMetadata, NamedResolver,
@@ -58,6 +69,7 @@ pub use crate::resolver::{
 };
 pub use crate::sig_registry::{SignatureRegistry, VMSharedSignatureIndex};
 pub use crate::table::{LinearTable, Table, TableElement, TableStyle};
+pub use crate::tag::{Exception, Tag};
 pub use crate::trap::*;
 pub use crate::tunables::Tunables;
 pub use crate::vmcontext::{
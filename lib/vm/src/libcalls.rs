@@ -40,13 +40,17 @@
 use crate::func_data_registry::VMFuncRef;
 use crate::probestack::PROBESTACK;
 use crate::table::{RawTableElement, TableElement};
+use crate::tag::{CaughtExceptions, Exception};
+use crate::trap::traphandlers::with_call_state;
 use crate::trap::{raise_lib_trap, Trap, TrapCode};
 use crate::vmcontext::VMContext;
 use crate::VMExternRef;
+use std::convert::TryFrom;
 use std::fmt;
+use std::sync::Arc;
 use wasmer_types::{
     DataIndex, ElemIndex, FunctionIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex,
-    TableIndex, Type,
+    TableIndex, TagIndex, Type,
 };
 
 /// Implementation of f32.ceil
@@ -208,6 +212,75 @@ pub unsafe extern "C" fn wasmer_vm_imported_memory32_size(
     instance.imported_memory_size(memory_index).0
 }
 
+/// Implementation of memory.grow for locally-defined 64-bit memories.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_memory64_grow(
+    vmctx: *mut VMContext,
+    delta: u64,
+    memory_index: u32,
+) -> u64 {
+    let instance = (&*vmctx).instance();
+    let memory_index = LocalMemoryIndex::from_u32(memory_index);
+
+    u32::try_from(delta)
+        .ok()
+        .and_then(|delta| instance.memory_grow(memory_index, delta).ok())
+        .map_or(u64::max_value(), |pages| pages.0.into())
+}
+
+/// Implementation of memory.grow for imported 64-bit memories.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_imported_memory64_grow(
+    vmctx: *mut VMContext,
+    delta: u64,
+    memory_index: u32,
+) -> u64 {
+    let instance = (&*vmctx).instance();
+    let memory_index = MemoryIndex::from_u32(memory_index);
+
+    u32::try_from(delta)
+        .ok()
+        .and_then(|delta| instance.imported_memory_grow(memory_index, delta).ok())
+        .map_or(u64::max_value(), |pages| pages.0.into())
+}
+
+/// Implementation of memory.size for locally-defined 64-bit memories.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_memory64_size(vmctx: *mut VMContext, memory_index: u32) -> u64 {
+    let instance = (&*vmctx).instance();
+    let memory_index = LocalMemoryIndex::from_u32(memory_index);
+
+    instance.memory_size(memory_index).0.into()
+}
+
+/// Implementation of memory.size for imported 64-bit memories.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_imported_memory64_size(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+) -> u64 {
+    let instance = (&*vmctx).instance();
+    let memory_index = MemoryIndex::from_u32(memory_index);
+
+    instance.imported_memory_size(memory_index).0.into()
+}
+
 /// Implementation of `table.copy`.
 ///
 /// # Safety
@@ -540,7 +613,7 @@ pub unsafe extern "C" fn wasmer_vm_memory32_copy(
     let result = {
         let memory_index = LocalMemoryIndex::from_u32(memory_index);
         let instance = (&*vmctx).instance();
-        instance.local_memory_copy(memory_index, dst, src, len)
+        instance.local_memory_copy(memory_index, dst.into(), src.into(), len.into())
     };
     if let Err(trap) = result {
         raise_lib_trap(trap);
@@ -563,7 +636,35 @@ pub unsafe extern "C" fn wasmer_vm_imported_memory32_copy(
     let result = {
         let memory_index = MemoryIndex::from_u32(memory_index);
         let instance = (&*vmctx).instance();
-        instance.imported_memory_copy(memory_index, dst, src, len)
+        instance.imported_memory_copy(memory_index, dst.into(), src.into(), len.into())
+    };
+    if let Err(trap) = result {
+        raise_lib_trap(trap);
+    }
+}
+
+/// Implementation of `memory.copy` between two different memories.
+///
+/// Both memory indices are module-wide indices, so each memory may be
+/// either local or imported.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_memory_copy_between(
+    vmctx: *mut VMContext,
+    dst_memory_index: u32,
+    src_memory_index: u32,
+    dst: u64,
+    src: u64,
+    len: u64,
+) {
+    let result = {
+        let dst_memory_index = MemoryIndex::from_u32(dst_memory_index);
+        let src_memory_index = MemoryIndex::from_u32(src_memory_index);
+        let instance = (&*vmctx).instance();
+        instance.memory_copy_between(dst_memory_index, src_memory_index, dst, src, len)
     };
     if let Err(trap) = result {
         raise_lib_trap(trap);
@@ -586,7 +687,7 @@ pub unsafe extern "C" fn wasmer_vm_memory32_fill(
     let result = {
         let memory_index = LocalMemoryIndex::from_u32(memory_index);
         let instance = (&*vmctx).instance();
-        instance.local_memory_fill(memory_index, dst, val, len)
+        instance.local_memory_fill(memory_index, dst.into(), val, len.into())
     };
     if let Err(trap) = result {
         raise_lib_trap(trap);
@@ -609,7 +710,7 @@ pub unsafe extern "C" fn wasmer_vm_imported_memory32_fill(
     let result = {
         let memory_index = MemoryIndex::from_u32(memory_index);
         let instance = (&*vmctx).instance();
-        instance.imported_memory_fill(memory_index, dst, val, len)
+        instance.imported_memory_fill(memory_index, dst.into(), val, len.into())
     };
     if let Err(trap) = result {
         raise_lib_trap(trap);
@@ -629,6 +730,123 @@ pub unsafe extern "C" fn wasmer_vm_memory32_init(
     dst: u32,
     src: u32,
     len: u32,
+) {
+    let result = {
+        let memory_index = MemoryIndex::from_u32(memory_index);
+        let data_index = DataIndex::from_u32(data_index);
+        let instance = (&*vmctx).instance();
+        instance.memory_init(memory_index, data_index, dst.into(), src, len)
+    };
+    if let Err(trap) = result {
+        raise_lib_trap(trap);
+    }
+}
+
+/// Implementation of `memory.copy` for locally defined 64-bit memories.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_memory64_copy(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    dst: u64,
+    src: u64,
+    len: u64,
+) {
+    let result = {
+        let memory_index = LocalMemoryIndex::from_u32(memory_index);
+        let instance = (&*vmctx).instance();
+        instance.local_memory_copy(memory_index, dst, src, len)
+    };
+    if let Err(trap) = result {
+        raise_lib_trap(trap);
+    }
+}
+
+/// Implementation of `memory.copy` for imported 64-bit memories.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_imported_memory64_copy(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    dst: u64,
+    src: u64,
+    len: u64,
+) {
+    let result = {
+        let memory_index = MemoryIndex::from_u32(memory_index);
+        let instance = (&*vmctx).instance();
+        instance.imported_memory_copy(memory_index, dst, src, len)
+    };
+    if let Err(trap) = result {
+        raise_lib_trap(trap);
+    }
+}
+
+/// Implementation of `memory.fill` for locally defined 64-bit memories.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_memory64_fill(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    dst: u64,
+    val: u32,
+    len: u64,
+) {
+    let result = {
+        let memory_index = LocalMemoryIndex::from_u32(memory_index);
+        let instance = (&*vmctx).instance();
+        instance.local_memory_fill(memory_index, dst, val, len)
+    };
+    if let Err(trap) = result {
+        raise_lib_trap(trap);
+    }
+}
+
+/// Implementation of `memory.fill` for imported 64-bit memories.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_imported_memory64_fill(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    dst: u64,
+    val: u32,
+    len: u64,
+) {
+    let result = {
+        let memory_index = MemoryIndex::from_u32(memory_index);
+        let instance = (&*vmctx).instance();
+        instance.imported_memory_fill(memory_index, dst, val, len)
+    };
+    if let Err(trap) = result {
+        raise_lib_trap(trap);
+    }
+}
+
+/// Implementation of `memory.init` for 64-bit memories.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_memory64_init(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    data_index: u32,
+    dst: u64,
+    src: u32,
+    len: u32,
 ) {
     let result = {
         let memory_index = MemoryIndex::from_u32(memory_index);
@@ -653,6 +871,214 @@ pub unsafe extern "C" fn wasmer_vm_data_drop(vmctx: *mut VMContext, data_index:
     instance.data_drop(data_index)
 }
 
+/// Implementation of `memory.atomic.wait32`.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_memory32_atomic_wait32(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    addr: u64,
+    expected: u32,
+    timeout: i64,
+) -> u32 {
+    let result = {
+        let memory_index = MemoryIndex::from_u32(memory_index);
+        let instance = (&*vmctx).instance();
+        instance.memory_atomic_wait(memory_index, addr, expected, timeout)
+    };
+    match result {
+        Ok(result) => result as u32,
+        Err(trap) => raise_lib_trap(trap),
+    }
+}
+
+/// Implementation of `memory.atomic.wait64`.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_memory32_atomic_wait64(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    addr: u64,
+    expected: u64,
+    timeout: i64,
+) -> u32 {
+    let result = {
+        let memory_index = MemoryIndex::from_u32(memory_index);
+        let instance = (&*vmctx).instance();
+        instance.memory_atomic_wait(memory_index, addr, expected, timeout)
+    };
+    match result {
+        Ok(result) => result as u32,
+        Err(trap) => raise_lib_trap(trap),
+    }
+}
+
+/// Implementation of `memory.atomic.notify`.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_memory32_atomic_notify(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    addr: u64,
+    count: u32,
+) -> u32 {
+    let result = {
+        let memory_index = MemoryIndex::from_u32(memory_index);
+        let instance = (&*vmctx).instance();
+        instance.memory_atomic_notify(memory_index, addr, count)
+    };
+    match result {
+        Ok(woken) => woken,
+        Err(trap) => raise_lib_trap(trap),
+    }
+}
+
+/// Implementation of `throw`: starts propagating a new exception with the
+/// given tag. Its payload is then pushed with `wasmer_vm_exception_push`.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable, and wasm code must be on the stack.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_exception_new(vmctx: *mut VMContext, tag_index: u32) {
+    let instance = (&*vmctx).instance();
+    let tag = instance.tag(TagIndex::from_u32(tag_index));
+    with_call_state(|state| state.set_exception(Exception::new(tag)));
+}
+
+/// Appends a value to the payload of the exception being thrown.
+///
+/// # Safety
+///
+/// Wasm code must be on the stack, and must have called
+/// `wasmer_vm_exception_new` first.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_exception_push(_vmctx: *mut VMContext, bits: u64) {
+    with_call_state(|state| {
+        state.with_exception(|exception| {
+            exception
+                .expect("no exception is being thrown")
+                .push(bits.into())
+        })
+    });
+}
+
+/// Returns whether an exception is being propagated.
+///
+/// # Safety
+///
+/// Wasm code must be on the stack.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_exception_pending(_vmctx: *mut VMContext) -> u32 {
+    with_call_state(|state| state.with_exception(|exception| exception.is_some()) as u32)
+}
+
+/// Allocates the storage for the exceptions caught by a wasm activation,
+/// indexed by the ordinal of the `try` block that caught them.
+///
+/// # Safety
+///
+/// Wasm code must be on the stack.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_exception_caught_new(
+    _vmctx: *mut VMContext,
+) -> *mut CaughtExceptions {
+    with_call_state(|state| state.new_caught_exceptions())
+}
+
+/// Frees storage allocated by `wasmer_vm_exception_caught_new`.
+///
+/// # Safety
+///
+/// Wasm code must be on the stack, and `caught` must have been allocated
+/// while the current call into wasm was active.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_exception_caught_drop(
+    _vmctx: *mut VMContext,
+    caught: *mut CaughtExceptions,
+) {
+    with_call_state(|state| state.drop_caught_exceptions(caught));
+}
+
+/// Implementation of `catch` and `catch_all`: if the exception being
+/// propagated has the given tag, stops propagating it and stores it in
+/// `caught` at `slot`. A `tag_index` of `u32::MAX` matches any tag.
+///
+/// Returns whether the exception was caught.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable, and `caught` must be live.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_exception_catch(
+    vmctx: *mut VMContext,
+    caught: *mut CaughtExceptions,
+    slot: u32,
+    tag_index: u32,
+) -> u32 {
+    let instance = (&*vmctx).instance();
+    let tag = if tag_index == u32::MAX {
+        None
+    } else {
+        Some(instance.tag(TagIndex::from_u32(tag_index)))
+    };
+    with_call_state(|state| {
+        let matches = state.with_exception(|exception| {
+            let exception = exception.expect("no exception is being thrown");
+            match &tag {
+                Some(tag) => Arc::ptr_eq(tag, exception.tag()),
+                None => true,
+            }
+        });
+        if matches {
+            let exception = state.take_exception().unwrap();
+            (*caught).set(slot, exception);
+        }
+        matches as u32
+    })
+}
+
+/// Reads a value of the payload of a caught exception.
+///
+/// # Safety
+///
+/// `caught` must be live, and must hold an exception at `slot`.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_exception_payload(
+    _vmctx: *mut VMContext,
+    caught: *mut CaughtExceptions,
+    slot: u32,
+    index: u32,
+) -> u64 {
+    (*caught).get(slot).payload_bits(index as usize) as u64
+}
+
+/// Implementation of `rethrow`: starts propagating a caught exception
+/// again.
+///
+/// # Safety
+///
+/// Wasm code must be on the stack, `caught` must be live, and must hold an
+/// exception at `slot`.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_exception_rethrow(
+    _vmctx: *mut VMContext,
+    caught: *mut CaughtExceptions,
+    slot: u32,
+) {
+    let exception = (*caught).get(slot).clone();
+    with_call_state(|state| state.set_exception(exception));
+}
+
 /// Implementation for raising a trap
 ///
 /// # Safety
@@ -711,6 +1137,12 @@ pub enum LibCall {
     /// memory.size for imported functions
     ImportedMemory32Size,
 
+    /// memory.size for local 64-bit memories
+    Memory64Size,
+
+    /// memory.size for imported 64-bit memories
+    ImportedMemory64Size,
+
     /// table.copy
     TableCopy,
 
@@ -765,9 +1197,36 @@ pub enum LibCall {
     /// memory.init
     Memory32Init,
 
+    /// memory.copy for local 64-bit memories
+    Memory64Copy,
+
+    /// memory.copy for imported 64-bit memories
+    ImportedMemory64Copy,
+
+    /// memory.fill for local 64-bit memories
+    Memory64Fill,
+
+    /// memory.fill for imported 64-bit memories
+    ImportedMemory64Fill,
+
+    /// memory.init for 64-bit memories
+    Memory64Init,
+
+    /// memory.copy between two different memories
+    MemoryCopyBetween,
+
     /// data.drop
     DataDrop,
 
+    /// memory.atomic.wait32
+    MemoryAtomicWait32,
+
+    /// memory.atomic.wait64
+    MemoryAtomicWait64,
+
+    /// memory.atomic.notify
+    MemoryAtomicNotify,
+
     /// A custom trap
     RaiseTrap,
 
@@ -790,6 +1249,8 @@ impl LibCall {
             Self::TruncF64 => wasmer_vm_f64_trunc as usize,
             Self::Memory32Size => wasmer_vm_memory32_size as usize,
             Self::ImportedMemory32Size => wasmer_vm_imported_memory32_size as usize,
+            Self::Memory64Size => wasmer_vm_memory64_size as usize,
+            Self::ImportedMemory64Size => wasmer_vm_imported_memory64_size as usize,
             Self::TableCopy => wasmer_vm_table_copy as usize,
             Self::TableInit => wasmer_vm_table_init as usize,
             Self::TableFill => wasmer_vm_table_fill as usize,
@@ -806,9 +1267,18 @@ impl LibCall {
             Self::Memory32Copy => wasmer_vm_memory32_copy as usize,
             Self::ImportedMemory32Copy => wasmer_vm_imported_memory32_copy as usize,
             Self::Memory32Fill => wasmer_vm_memory32_fill as usize,
-            Self::ImportedMemory32Fill => wasmer_vm_memory32_fill as usize,
+            Self::ImportedMemory32Fill => wasmer_vm_imported_memory32_fill as usize,
             Self::Memory32Init => wasmer_vm_memory32_init as usize,
+            Self::Memory64Copy => wasmer_vm_memory64_copy as usize,
+            Self::ImportedMemory64Copy => wasmer_vm_imported_memory64_copy as usize,
+            Self::Memory64Fill => wasmer_vm_memory64_fill as usize,
+            Self::ImportedMemory64Fill => wasmer_vm_imported_memory64_fill as usize,
+            Self::Memory64Init => wasmer_vm_memory64_init as usize,
+            Self::MemoryCopyBetween => wasmer_vm_memory_copy_between as usize,
             Self::DataDrop => wasmer_vm_data_drop as usize,
+            Self::MemoryAtomicWait32 => wasmer_vm_memory32_atomic_wait32 as usize,
+            Self::MemoryAtomicWait64 => wasmer_vm_memory32_atomic_wait64 as usize,
+            Self::MemoryAtomicNotify => wasmer_vm_memory32_atomic_notify as usize,
             Self::Probestack => wasmer_vm_probestack as usize,
             Self::RaiseTrap => wasmer_vm_raise_trap as usize,
         }
@@ -827,6 +1297,8 @@ impl LibCall {
             Self::TruncF64 => "wasmer_vm_f64_trunc",
             Self::Memory32Size => "wasmer_vm_memory32_size",
             Self::ImportedMemory32Size => "wasmer_vm_imported_memory32_size",
+            Self::Memory64Size => "wasmer_vm_memory64_size",
+            Self::ImportedMemory64Size => "wasmer_vm_imported_memory64_size",
             Self::TableCopy => "wasmer_vm_table_copy",
             Self::TableInit => "wasmer_vm_table_init",
             Self::TableFill => "wasmer_vm_table_fill",
@@ -845,7 +1317,16 @@ impl LibCall {
             Self::Memory32Fill => "wasmer_vm_memory32_fill",
             Self::ImportedMemory32Fill => "wasmer_vm_imported_memory32_fill",
             Self::Memory32Init => "wasmer_vm_memory32_init",
+            Self::Memory64Copy => "wasmer_vm_memory64_copy",
+            Self::ImportedMemory64Copy => "wasmer_vm_imported_memory64_copy",
+            Self::Memory64Fill => "wasmer_vm_memory64_fill",
+            Self::ImportedMemory64Fill => "wasmer_vm_imported_memory64_fill",
+            Self::Memory64Init => "wasmer_vm_memory64_init",
+            Self::MemoryCopyBetween => "wasmer_vm_memory_copy_between",
             Self::DataDrop => "wasmer_vm_data_drop",
+            Self::MemoryAtomicWait32 => "wasmer_vm_memory32_atomic_wait32",
+            Self::MemoryAtomicWait64 => "wasmer_vm_memory32_atomic_wait64",
+            Self::MemoryAtomicNotify => "wasmer_vm_memory32_atomic_notify",
             Self::RaiseTrap => "wasmer_vm_raise_trap",
             // We have to do this because macOS requires a leading `_` and it's not
             // a normal function, it's a static variable, so we have to do it manually.
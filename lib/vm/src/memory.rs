@@ -5,7 +5,9 @@
 //!
 //! `LinearMemory` is to WebAssembly linear memories what `Table` is to WebAssembly tables.
 
+use crate::memory_image::MemoryImage;
 use crate::mmap::Mmap;
+use crate::parking_spot::ParkingSpot;
 use crate::vmcontext::VMMemoryDefinition;
 use more_asserts::assert_ge;
 use std::borrow::BorrowMut;
@@ -13,7 +15,7 @@ use std::cell::UnsafeCell;
 use std::convert::TryInto;
 use std::fmt;
 use std::ptr::NonNull;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use thiserror::Error;
 use wasmer_types::{Bytes, MemoryType, Pages};
 
@@ -112,6 +114,20 @@ pub trait Memory: fmt::Debug + Send + Sync {
     ///
     /// The pointer returned in [`VMMemoryDefinition`] must be valid for the lifetime of this memory.
     fn vmmemory(&self) -> NonNull<VMMemoryDefinition>;
+
+    /// Initialize this freshly created memory with the contents of `image`,
+    /// mapping it copy-on-write.
+    ///
+    /// Returns `false` if this memory can't map images, in which case its data
+    /// segments are copied instead. This is what the default implementation does.
+    fn initialize_with_image(&self, _image: &Arc<MemoryImage>) -> Result<bool, MemoryError> {
+        Ok(false)
+    }
+
+    /// The threads waiting on the addresses of this memory, if it is shared.
+    fn parking_spot(&self) -> Option<&ParkingSpot> {
+        None
+    }
 }
 
 /// A linear memory instance.
@@ -135,6 +151,9 @@ pub struct LinearMemory {
 
     /// The owned memory definition used by the generated code
     vm_memory_definition: VMMemoryDefinitionOwnership,
+
+    /// The threads waiting on addresses of this memory, if it is shared.
+    parking_spot: Option<ParkingSpot>,
 }
 
 /// A type to help manage who is responsible for the backing memory of them
@@ -200,18 +219,19 @@ impl LinearMemory {
         style: &MemoryStyle,
         vm_memory_location: Option<NonNull<VMMemoryDefinition>>,
     ) -> Result<Self, MemoryError> {
-        if memory.minimum > Pages::max_value() {
+        if memory.minimum > memory.max_pages() {
             return Err(MemoryError::MinimumMemoryTooLarge {
                 min_requested: memory.minimum,
-                max_allowed: Pages::max_value(),
+                max_allowed: memory.max_pages(),
             });
         }
-        // `maximum` cannot be set to more than `65536` pages.
+        // `maximum` cannot be set to more than `65536` pages, unless the
+        // memory is indexed with 64-bit addresses.
         if let Some(max) = memory.maximum {
-            if max > Pages::max_value() {
+            if max > memory.max_pages() {
                 return Err(MemoryError::MaximumMemoryTooLarge {
                     max_requested: max,
-                    max_allowed: Pages::max_value(),
+                    max_allowed: memory.max_pages(),
                 });
             }
             if max < memory.minimum {
@@ -223,6 +243,11 @@ impl LinearMemory {
                 });
             }
         }
+        if memory.shared && memory.maximum.is_none() {
+            return Err(MemoryError::InvalidMemory {
+                reason: "shared memories must have a maximum size".to_string(),
+            });
+        }
 
         let offset_guard_bytes = style.offset_guard_size() as usize;
 
@@ -233,6 +258,12 @@ impl LinearMemory {
                 *bound
             }
         };
+        // A shared memory is accessed by other threads while it grows, so it
+        // can never move: reserve its maximum size up front.
+        let minimum_pages = match memory.maximum {
+            Some(maximum) if memory.shared => minimum_pages.max(maximum),
+            _ => minimum_pages,
+        };
         let minimum_bytes = minimum_pages.bytes().0;
         let request_bytes = minimum_bytes.checked_add(offset_guard_bytes).unwrap();
         let mapped_pages = memory.minimum;
@@ -268,6 +299,11 @@ impl LinearMemory {
             },
             memory: *memory,
             style: style.clone(),
+            parking_spot: if memory.shared {
+                Some(ParkingSpot::default())
+            } else {
+                None
+            },
         })
     }
 
@@ -323,13 +359,15 @@ impl Memory for LinearMemory {
             return Ok(mmap.size);
         }
 
-        let new_pages = mmap
-            .size
-            .checked_add(delta)
-            .ok_or(MemoryError::CouldNotGrow {
-                current: mmap.size,
-                attempted_delta: delta,
-            })?;
+        let new_pages =
+            mmap.size
+                .0
+                .checked_add(delta.0)
+                .map(Pages)
+                .ok_or(MemoryError::CouldNotGrow {
+                    current: mmap.size,
+                    attempted_delta: delta,
+                })?;
         let prev_pages = mmap.size;
 
         if let Some(maximum) = self.maximum {
@@ -344,7 +382,7 @@ impl Memory for LinearMemory {
         // Wasm linear memories are never allowed to grow beyond what is
         // indexable. If the memory has no maximum, enforce the greatest
         // limit here.
-        if new_pages >= Pages::max_value() {
+        if new_pages >= self.memory.max_pages() {
             // Linear memory size would exceed the index range.
             return Err(MemoryError::CouldNotGrow {
                 current: mmap.size,
@@ -400,4 +438,23 @@ impl Memory for LinearMemory {
         let _mmap_guard = self.mmap.lock().unwrap();
         unsafe { self.get_vm_memory_definition() }
     }
+
+    /// Map `image` copy-on-write over this memory.
+    fn initialize_with_image(&self, image: &Arc<MemoryImage>) -> Result<bool, MemoryError> {
+        let mut mmap_guard = self.mmap.lock().unwrap();
+        let mmap = mmap_guard.borrow_mut();
+        if image.offset() + image.len() > mmap.size.bytes().0 {
+            return Ok(false);
+        }
+
+        // The mapping is private to this memory, and the image fits in
+        // its accessible part.
+        unsafe { image.map_at(mmap.alloc.as_mut_ptr()) }.map_err(MemoryError::Region)?;
+        Ok(true)
+    }
+
+    /// The threads waiting on the addresses of this memory, if it is shared.
+    fn parking_spot(&self) -> Option<&ParkingSpot> {
+        self.parking_spot.as_ref()
+    }
 }
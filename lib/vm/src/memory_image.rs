@@ -0,0 +1,264 @@
+//! Copy-on-write images of the initial contents of linear memories.
+//!
+//! When all the active data segments of a memory have constant offsets,
+//! its initial contents are known ahead of instantiation. A
+//! [`MemoryImage`] holds them in a page-aligned, sealed `memfd` that new
+//! instances map privately over their memory instead of copying every
+//! data segment.
+//!
+//! Images are only supported on Linux. Elsewhere [`MemoryImage::new`]
+//! returns `None`, and data segments are copied as usual.
+
+use std::fmt;
+use wasmer_types::{DataInitializer, Pages};
+
+/// The initial contents of a linear memory, ready to be mapped.
+pub struct MemoryImage {
+    /// The offset in bytes of the image in the linear memory. It is a
+    /// multiple of the host page size.
+    offset: usize,
+
+    /// The length in bytes of the image. It is a multiple of the host
+    /// page size.
+    len: usize,
+
+    /// The file holding the image.
+    #[cfg(target_os = "linux")]
+    file: std::fs::File,
+}
+
+impl fmt::Debug for MemoryImage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MemoryImage")
+            .field("offset", &self.offset)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+impl MemoryImage {
+    /// Build the image of a memory of `minimum` pages from its active
+    /// data segments.
+    ///
+    /// Returns `None` if an image can't be used for this memory: when
+    /// there are no segments, when a segment has a global base or doesn't
+    /// fit in the memory, or when images aren't supported on this host.
+    pub fn new<'a>(
+        minimum: Pages,
+        segments: impl IntoIterator<Item = DataInitializer<'a>>,
+    ) -> Option<Self> {
+        let segments = segments.into_iter().collect::<Vec<_>>();
+        if segments.is_empty() || segments.iter().any(|s| s.location.base.is_some()) {
+            return None;
+        }
+
+        let mut start = usize::max_value();
+        let mut end = 0;
+        for segment in segments.iter() {
+            start = start.min(segment.location.offset);
+            end = end.max(segment.location.offset.checked_add(segment.data.len())?);
+        }
+
+        let page_size = region::page::size();
+        let start = start & !(page_size - 1);
+        let end = end.checked_add(page_size - 1)? & !(page_size - 1);
+        if end > minimum.bytes().0 || end <= start {
+            return None;
+        }
+
+        let mut contents = vec![0; end - start];
+        for segment in segments.iter() {
+            let offset = segment.location.offset - start;
+            contents[offset..offset + segment.data.len()].copy_from_slice(segment.data);
+        }
+
+        Self::from_contents(start, &contents)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn from_contents(offset: usize, contents: &[u8]) -> Option<Self> {
+        use std::io::Write;
+        use std::os::unix::io::FromRawFd;
+
+        let fd = unsafe {
+            libc::memfd_create(
+                b"wasm-memory-image\0".as_ptr() as *const libc::c_char,
+                libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING,
+            )
+        };
+        if fd < 0 {
+            return None;
+        }
+        let mut file = unsafe { std::fs::File::from_raw_fd(fd) };
+        file.write_all(contents).ok()?;
+
+        // The image is shared by every instance, so make sure it can't
+        // change anymore.
+        let seals =
+            libc::F_SEAL_SEAL | libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_WRITE;
+        if unsafe { libc::fcntl(fd, libc::F_ADD_SEALS, seals) } != 0 {
+            return None;
+        }
+
+        Some(Self {
+            offset,
+            len: contents.len(),
+            file,
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn from_contents(_offset: usize, _contents: &[u8]) -> Option<Self> {
+        None
+    }
+
+    /// The offset in bytes of the image in the linear memory.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The length in bytes of the image.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return whether the image is empty. It never is.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Map the image copy-on-write over the linear memory starting at
+    /// `base`.
+    ///
+    /// # Safety
+    /// - `base` must be page-aligned, and the `self.offset() + self.len()`
+    ///   bytes starting at `base` must be an accessible part of a private
+    ///   mapping that nothing else references.
+    #[cfg(target_os = "linux")]
+    pub unsafe fn map_at(&self, base: *mut u8) -> Result<(), String> {
+        use std::os::unix::io::AsRawFd;
+
+        let ptr = libc::mmap(
+            base.add(self.offset) as *mut libc::c_void,
+            self.len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_FIXED,
+            self.file.as_raw_fd(),
+            0,
+        );
+        if ptr == libc::MAP_FAILED {
+            let error = std::io::Error::last_os_error().to_string();
+            // A failed fixed mapping may leave a hole behind: fill it so
+            // that the memory can still be initialized by copying.
+            self.unmap_at(base)?;
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    /// Map the image copy-on-write over the linear memory starting at
+    /// `base`.
+    ///
+    /// # Safety
+    /// - `base` must be page-aligned, and the `self.offset() + self.len()`
+    ///   bytes starting at `base` must be an accessible part of a private
+    ///   mapping that nothing else references.
+    #[cfg(not(target_os = "linux"))]
+    pub unsafe fn map_at(&self, _base: *mut u8) -> Result<(), String> {
+        Err("memory images are not supported on this platform".to_string())
+    }
+
+    /// Replace the image mapped by [`MemoryImage::map_at`] with
+    /// accessible anonymous memory.
+    ///
+    /// This is needed before the memory is reused, since releasing the
+    /// pages of a file mapping brings back the contents of the file
+    /// rather than zeroes.
+    ///
+    /// # Safety
+    /// - The image must have been mapped at `base` by
+    ///   [`MemoryImage::map_at`], and nothing may reference it anymore.
+    #[cfg(target_os = "linux")]
+    pub unsafe fn unmap_at(&self, base: *mut u8) -> Result<(), String> {
+        let ptr = libc::mmap(
+            base.add(self.offset) as *mut libc::c_void,
+            self.len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANON | libc::MAP_FIXED,
+            -1,
+            0,
+        );
+        if ptr == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+        Ok(())
+    }
+
+    /// Replace the image mapped by [`MemoryImage::map_at`] with
+    /// accessible anonymous memory.
+    ///
+    /// # Safety
+    /// - The image must have been mapped at `base` by
+    ///   [`MemoryImage::map_at`], and nothing may reference it anymore.
+    #[cfg(not(target_os = "linux"))]
+    pub unsafe fn unmap_at(&self, _base: *mut u8) -> Result<(), String> {
+        Err("memory images are not supported on this platform".to_string())
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::Mmap;
+    use wasmer_types::{DataInitializerLocation, GlobalIndex, MemoryIndex};
+
+    fn segment(offset: usize, data: &[u8]) -> DataInitializer<'_> {
+        DataInitializer {
+            location: DataInitializerLocation {
+                memory_index: MemoryIndex::from_u32(0),
+                base: None,
+                offset,
+            },
+            data,
+        }
+    }
+
+    #[test]
+    fn image_is_mapped_copy_on_write() {
+        let page_size = region::page::size();
+        let image = MemoryImage::new(
+            Pages(1),
+            vec![
+                segment(page_size + 1, b"abc"),
+                segment(page_size + 2, b"de"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(image.offset(), page_size);
+        assert_eq!(image.len(), page_size);
+
+        let mut first = Mmap::with_at_least(Pages(1).bytes().0).unwrap();
+        let mut second = Mmap::with_at_least(Pages(1).bytes().0).unwrap();
+        unsafe {
+            image.map_at(first.as_mut_ptr()).unwrap();
+            image.map_at(second.as_mut_ptr()).unwrap();
+        }
+        assert_eq!(&first.as_slice()[page_size..page_size + 4], b"\0ade");
+
+        first.as_mut_slice()[page_size + 1] = b'z';
+        assert_eq!(&first.as_slice()[page_size..page_size + 4], b"\0zde");
+        assert_eq!(&second.as_slice()[page_size..page_size + 4], b"\0ade");
+
+        unsafe { image.unmap_at(first.as_mut_ptr()).unwrap() };
+        assert!(first.as_slice().iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn segments_must_be_static_and_fit() {
+        let mut with_base = segment(0, b"abc");
+        with_base.location.base = Some(GlobalIndex::from_u32(0));
+        assert!(MemoryImage::new(Pages(1), vec![with_base]).is_none());
+        assert!(MemoryImage::new(Pages(1), vec![segment(Pages(1).bytes().0, b"a")]).is_none());
+        assert!(MemoryImage::new(Pages(1), vec![]).is_none());
+    }
+}
@@ -5,13 +5,12 @@
 //! of memory.
 
 use more_asserts::assert_le;
-use more_asserts::assert_lt;
 use std::io;
 use std::ptr;
 use std::slice;
 
 /// Round `size` up to the nearest multiple of `page_size`.
-fn round_up_to_page_size(size: usize, page_size: usize) -> usize {
+pub(crate) fn round_up_to_page_size(size: usize, page_size: usize) -> usize {
     (size + (page_size - 1)) & !(page_size - 1)
 }
 
@@ -186,8 +185,8 @@ impl Mmap {
         let page_size = region::page::size();
         assert_eq!(start & (page_size - 1), 0);
         assert_eq!(len & (page_size - 1), 0);
-        assert_lt!(len, self.len);
-        assert_lt!(start, self.len - len);
+        assert_le!(len, self.len);
+        assert_le!(start, self.len - len);
 
         // Commit the accessible size.
         let ptr = self.ptr as *const u8;
@@ -206,8 +205,8 @@ impl Mmap {
         let page_size = region::page::size();
         assert_eq!(start & (page_size - 1), 0);
         assert_eq!(len & (page_size - 1), 0);
-        assert_lt!(len, self.len);
-        assert_lt!(start, self.len - len);
+        assert_le!(len, self.len);
+        assert_le!(start, self.len - len);
 
         // Commit the accessible size.
         let ptr = self.ptr as *const u8;
@@ -227,6 +226,83 @@ impl Mmap {
         Ok(())
     }
 
+    /// Release the physical pages backing the memory starting at `start` and
+    /// extending for `len` bytes, and make them inaccessible again. The range
+    /// stays reserved and reads back as zeroes once made accessible.
+    /// `start` and `len` must be native page-size multiples and describe a
+    /// range within `self`'s reserved memory.
+    #[cfg(not(target_os = "windows"))]
+    pub fn decommit(&mut self, start: usize, len: usize) -> Result<(), String> {
+        let page_size = region::page::size();
+        assert_eq!(start & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+        assert_le!(len, self.len);
+        assert_le!(start, self.len - len);
+
+        if len == 0 {
+            return Ok(());
+        }
+
+        let ptr = unsafe { (self.ptr as *mut u8).add(start) } as *mut libc::c_void;
+
+        // On Linux `MADV_DONTNEED` drops private anonymous pages, so
+        // they are zero-filled on the next access.
+        #[cfg(target_os = "linux")]
+        unsafe {
+            if libc::madvise(ptr, len, libc::MADV_DONTNEED) != 0 {
+                return Err(io::Error::last_os_error().to_string());
+            }
+            region::protect(ptr as *const u8, len, region::Protection::NONE)
+                .map_err(|e| e.to_string())
+        }
+
+        // Elsewhere `MADV_DONTNEED` may keep the contents, so map fresh
+        // pages over the range instead.
+        #[cfg(not(target_os = "linux"))]
+        unsafe {
+            let new_ptr = libc::mmap(
+                ptr,
+                len,
+                libc::PROT_NONE,
+                libc::MAP_PRIVATE | libc::MAP_ANON | libc::MAP_FIXED,
+                -1,
+                0,
+            );
+            if new_ptr as isize == -1_isize {
+                return Err(io::Error::last_os_error().to_string());
+            }
+            Ok(())
+        }
+    }
+
+    /// Release the physical pages backing the memory starting at `start` and
+    /// extending for `len` bytes, and make them inaccessible again. The range
+    /// stays reserved and reads back as zeroes once made accessible.
+    /// `start` and `len` must be native page-size multiples and describe a
+    /// range within `self`'s reserved memory.
+    #[cfg(target_os = "windows")]
+    pub fn decommit(&mut self, start: usize, len: usize) -> Result<(), String> {
+        use winapi::ctypes::c_void;
+        use winapi::um::memoryapi::VirtualFree;
+        use winapi::um::winnt::MEM_DECOMMIT;
+        let page_size = region::page::size();
+        assert_eq!(start & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+        assert_le!(len, self.len);
+        assert_le!(start, self.len - len);
+
+        if len == 0 {
+            return Ok(());
+        }
+
+        let ptr = self.ptr as *const u8;
+        if unsafe { VirtualFree(ptr.add(start) as *mut c_void, len, MEM_DECOMMIT) } == 0 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+
+        Ok(())
+    }
+
     /// Return the allocated memory as a slice of u8.
     pub fn as_slice(&self) -> &[u8] {
         unsafe { slice::from_raw_parts(self.ptr as *const u8, self.len) }
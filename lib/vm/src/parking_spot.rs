@@ -0,0 +1,153 @@
+//! A futex-like table of the threads waiting on addresses of a shared
+//! memory, backing the `memory.atomic.wait*` and `memory.atomic.notify`
+//! instructions.
+//!
+//! Waiters park on a condition variable per address, and are woken up in
+//! batches by notifications: a notification hands out a number of wake-ups
+//! which the waiters on its address consume.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// The outcome of [`ParkingSpot::wait`], as returned by `memory.atomic.wait*`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum WaitResult {
+    /// The thread was woken up by a notification.
+    Ok = 0,
+    /// The value in memory was not the expected one.
+    Mismatch = 1,
+    /// The timeout expired before a notification.
+    TimedOut = 2,
+}
+
+#[derive(Debug, Default)]
+struct Spot {
+    /// The number of threads waiting on the address.
+    parked: u32,
+    /// The number of wake-ups handed out by notifications, not yet
+    /// consumed by the waiters.
+    unparked: u32,
+    condvar: Arc<Condvar>,
+}
+
+/// The threads waiting on the addresses of a shared memory.
+#[derive(Debug, Default)]
+pub struct ParkingSpot {
+    spots: Mutex<HashMap<u64, Spot>>,
+}
+
+impl ParkingSpot {
+    /// Park the current thread on `address` until it is notified, or until
+    /// `timeout` expires.
+    ///
+    /// `validate` is called before parking, with the lock taken by
+    /// notifications: the thread is not parked if it returns `false`, so no
+    /// notification can be missed between the check and the wait.
+    pub fn wait(
+        &self,
+        address: u64,
+        validate: impl FnOnce() -> bool,
+        timeout: Option<Duration>,
+    ) -> WaitResult {
+        let mut spots = self.spots.lock().unwrap();
+        if !validate() {
+            return WaitResult::Mismatch;
+        }
+        let spot = spots.entry(address).or_default();
+        spot.parked += 1;
+        let condvar = Arc::clone(&spot.condvar);
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        loop {
+            spots = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now < deadline {
+                        condvar.wait_timeout(spots, deadline - now).unwrap().0
+                    } else {
+                        spots
+                    }
+                }
+                None => condvar.wait(spots).unwrap(),
+            };
+            let spot = spots.get_mut(&address).unwrap();
+            let result = if spot.unparked > 0 {
+                spot.unparked -= 1;
+                WaitResult::Ok
+            } else if deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+                WaitResult::TimedOut
+            } else {
+                // A spurious wake-up.
+                continue;
+            };
+            spot.parked -= 1;
+            if spot.parked == 0 {
+                spots.remove(&address);
+            }
+            return result;
+        }
+    }
+
+    /// Wake up at most `count` of the threads waiting on `address`, and
+    /// return how many were woken up.
+    pub fn notify(&self, address: u64, count: u32) -> u32 {
+        let mut spots = self.spots.lock().unwrap();
+        let spot = match spots.get_mut(&address) {
+            Some(spot) => spot,
+            None => return 0,
+        };
+        let count = count.min(spot.parked - spot.unparked);
+        if count > 0 {
+            spot.unparked += count;
+            spot.condvar.notify_all();
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::thread;
+
+    #[test]
+    fn wait_checks_the_value() {
+        let spot = ParkingSpot::default();
+        assert_eq!(spot.wait(0, || false, None), WaitResult::Mismatch);
+        assert_eq!(
+            spot.wait(0, || true, Some(Duration::from_millis(1))),
+            WaitResult::TimedOut
+        );
+        assert_eq!(spot.notify(0, 1), 0);
+    }
+
+    #[test]
+    fn notify_wakes_up_waiters() {
+        let spot = Arc::new(ParkingSpot::default());
+        let woken = Arc::new(AtomicU32::new(0));
+        let waiters: Vec<_> = (0..3)
+            .map(|_| {
+                let spot = Arc::clone(&spot);
+                let woken = Arc::clone(&woken);
+                thread::spawn(move || {
+                    assert_eq!(spot.wait(8, || true, None), WaitResult::Ok);
+                    woken.fetch_add(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+
+        let mut notified = 0;
+        while notified < 3 {
+            notified += spot.notify(8, 2);
+            thread::yield_now();
+        }
+        for waiter in waiters {
+            waiter.join().unwrap();
+        }
+        assert_eq!(woken.load(Ordering::SeqCst), 3);
+        assert_eq!(spot.notify(8, 1), 0);
+    }
+}
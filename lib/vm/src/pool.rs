@@ -0,0 +1,857 @@
+//! Pooling allocation of instances, memories and tables.
+//!
+//! By default every instantiation allocates its `Instance` on the heap
+//! and reserves a fresh mapping for each of its memories. An
+//! [`InstancePool`] instead reserves everything up front for a fixed
+//! number of instances, and recycles the slots when they are dropped:
+//! their pages are released to the OS but the address space stays
+//! reserved for the next instance.
+
+use crate::instance::{InstanceAllocation, InstanceAllocator, InstanceStorage};
+use crate::memory::{Memory, MemoryError, MemoryStyle};
+use crate::memory_image::MemoryImage;
+use crate::mmap::{round_up_to_page_size, Mmap};
+use crate::parking_spot::ParkingSpot;
+use crate::table::{RawTableElement, Table, TableElement, TableStyle};
+use crate::trap::{Trap, TrapCode};
+use crate::vmcontext::{VMMemoryDefinition, VMTableDefinition};
+use crate::{VMExternRef, VMOffsets};
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
+use std::mem;
+use std::ptr::NonNull;
+use std::slice;
+use std::sync::{Arc, Mutex};
+use wasmer_types::{Bytes, MemoryType, Pages, TableType, Type as ValType};
+
+/// The limits of an [`InstancePool`].
+///
+/// Every slot of the pool is sized for these limits, so they bound both
+/// the modules that can be instantiated and the address space reserved
+/// by the pool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolingLimits {
+    /// The maximum number of instances alive at the same time.
+    pub instances: u32,
+
+    /// The maximum size in bytes of an `Instance`, including its
+    /// `VMContext`.
+    pub instance_size: usize,
+
+    /// The maximum number of local memories of an instance.
+    pub memories: u32,
+
+    /// The maximum size in wasm pages of a local memory.
+    pub memory_pages: Pages,
+
+    /// The size in bytes of the offset guard after each memory.
+    pub memory_offset_guard_size: u64,
+
+    /// The maximum number of local tables of an instance.
+    pub tables: u32,
+
+    /// The maximum number of elements of a local table.
+    pub table_elements: u32,
+}
+
+impl Default for PoolingLimits {
+    fn default() -> Self {
+        Self {
+            instances: 1000,
+            instance_size: 0x10_0000,
+            memories: 1,
+            memory_pages: Pages(160),
+            memory_offset_guard_size: 0x1_0000,
+            tables: 1,
+            table_elements: 10_000,
+        }
+    }
+}
+
+/// A pool of pre-reserved instance, memory and table slots.
+///
+/// Instances are allocated from the pool with
+/// [`InstancePool::allocate_instance`], and their local memories and
+/// tables with [`InstancePool::create_memory`] and
+/// [`InstancePool::create_table`]. Each of them goes back to the pool
+/// once dropped.
+pub struct InstancePool {
+    limits: PoolingLimits,
+
+    /// The free instance slots.
+    instances: Mutex<Vec<Mmap>>,
+
+    /// The free memory slots.
+    memories: Mutex<Vec<Mmap>>,
+
+    /// The free table slots.
+    tables: Mutex<Vec<Mmap>>,
+}
+
+impl fmt::Debug for InstancePool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InstancePool")
+            .field("limits", &self.limits)
+            .finish()
+    }
+}
+
+impl InstancePool {
+    /// Reserve the slots of a new pool with the given limits.
+    pub fn new(limits: PoolingLimits) -> Result<Self, String> {
+        if limits.memory_pages > Pages::max_value() {
+            return Err(format!(
+                "the memory limit ({} pages) is greater than the maximum allowed memory ({} pages)",
+                limits.memory_pages.0,
+                Pages::max_value().0
+            ));
+        }
+
+        let instances = usize::try_from(limits.instances)
+            .map_err(|_| "the instance limit is bigger than usize".to_string())?;
+        let memories = usize::try_from(limits.memories)
+            .ok()
+            .and_then(|memories| memories.checked_mul(instances))
+            .ok_or_else(|| "the memory limit is bigger than usize".to_string())?;
+        let tables = usize::try_from(limits.tables)
+            .ok()
+            .and_then(|tables| tables.checked_mul(instances))
+            .ok_or_else(|| "the table limit is bigger than usize".to_string())?;
+
+        let page_size = region::page::size();
+        let memory_slot_size = limits
+            .memory_pages
+            .bytes()
+            .0
+            .checked_add(round_up_to_page_size(
+                limits.memory_offset_guard_size as usize,
+                page_size,
+            ))
+            .ok_or_else(|| "the memory slot size is bigger than usize".to_string())?;
+
+        // Instance and table slots are followed by a guard page.
+        let instance_slot_size = round_up_to_page_size(limits.instance_size, page_size) + page_size;
+        let table_slot_size =
+            round_up_to_page_size(Self::table_storage_size(&limits), page_size) + page_size;
+
+        let reserve = |count: usize, size: usize| -> Result<Vec<Mmap>, String> {
+            (0..count)
+                .map(|_| Mmap::accessible_reserved(0, size))
+                .collect()
+        };
+
+        Ok(Self {
+            instances: Mutex::new(reserve(instances, instance_slot_size)?),
+            memories: Mutex::new(reserve(memories, memory_slot_size)?),
+            tables: Mutex::new(reserve(tables, table_slot_size)?),
+            limits,
+        })
+    }
+
+    /// The limits of this pool.
+    pub fn limits(&self) -> &PoolingLimits {
+        &self.limits
+    }
+
+    /// The style of the memories created by this pool.
+    ///
+    /// Modules instantiated from the pool must be compiled for it.
+    pub fn memory_style(&self) -> MemoryStyle {
+        MemoryStyle::Static {
+            bound: self.limits.memory_pages,
+            offset_guard_size: self.limits.memory_offset_guard_size,
+        }
+    }
+
+    /// Allocate instance data for use with [`InstanceHandle::new`] from
+    /// a free instance slot, see [`InstanceAllocator::new`].
+    ///
+    /// Fails if the instance is larger than allowed by the limits, or
+    /// if all the instance slots are in use.
+    ///
+    /// [`InstanceHandle::new`]: crate::InstanceHandle::new
+    pub fn allocate_instance(
+        self: &Arc<Self>,
+        offsets: VMOffsets,
+    ) -> Result<InstanceAllocation, String> {
+        // `num_local_*` also count the imported entities, see
+        // `VMOffsets::with_module_info`.
+        let memories = offsets
+            .num_local_memories
+            .saturating_sub(offsets.num_imported_memories);
+        if memories > self.limits.memories {
+            return Err(format!(
+                "the instance has {} memories, but the pool allows at most {}",
+                memories, self.limits.memories
+            ));
+        }
+        let tables = offsets
+            .num_local_tables
+            .saturating_sub(offsets.num_imported_tables);
+        if tables > self.limits.tables {
+            return Err(format!(
+                "the instance has {} tables, but the pool allows at most {}",
+                tables, self.limits.tables
+            ));
+        }
+        let layout = InstanceAllocator::instance_layout(&offsets);
+        if layout.size() > self.limits.instance_size {
+            return Err(format!(
+                "the instance takes {} bytes, but the pool allows at most {}",
+                layout.size(),
+                self.limits.instance_size
+            ));
+        }
+
+        let mut alloc = self.instances.lock().unwrap().pop().ok_or_else(|| {
+            format!(
+                "all the {} instance slots of the pool are in use",
+                self.limits.instances
+            )
+        })?;
+        let committed = round_up_to_page_size(layout.size(), region::page::size());
+        if let Err(e) = alloc.make_accessible(0, committed) {
+            self.instances.lock().unwrap().push(alloc);
+            return Err(e);
+        }
+
+        let instance_ptr = NonNull::new(alloc.as_mut_ptr()).unwrap().cast();
+        let slot = InstanceSlot {
+            pool: Arc::clone(self),
+            alloc,
+            committed,
+        };
+
+        // # Safety
+        // The slot is page-aligned and at least `layout.size()` bytes
+        // are accessible.
+        Ok(unsafe {
+            InstanceAllocator::with_storage(offsets, instance_ptr, InstanceStorage::Pooled(slot))
+        })
+    }
+
+    /// Create a memory owned by the VM from a free memory slot.
+    ///
+    /// The minimum of the memory must fit in the limits of the pool.
+    /// Its maximum may not, in which case the memory can only grow up
+    /// to the limits.
+    ///
+    /// # Safety
+    /// - `vm_definition_location` must point to a valid, owned `VMMemoryDefinition`,
+    ///   for example in `VMContext`.
+    pub unsafe fn create_memory(
+        self: &Arc<Self>,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<Arc<dyn Memory>, MemoryError> {
+        if *style != self.memory_style() {
+            return Err(MemoryError::InvalidMemory {
+                reason: format!(
+                    "its style ({:?}) is not the style of the pool ({:?})",
+                    style,
+                    self.memory_style()
+                ),
+            });
+        }
+        if ty.minimum > self.limits.memory_pages {
+            return Err(MemoryError::MinimumMemoryTooLarge {
+                min_requested: ty.minimum,
+                max_allowed: self.limits.memory_pages,
+            });
+        }
+        if let Some(max) = ty.maximum {
+            if max < ty.minimum {
+                return Err(MemoryError::InvalidMemory {
+                    reason: format!(
+                        "the maximum ({} pages) is less than the minimum ({} pages)",
+                        max.0, ty.minimum.0
+                    ),
+                });
+            }
+        }
+
+        let mut alloc = self.memories.lock().unwrap().pop().ok_or_else(|| {
+            MemoryError::Region(format!(
+                "all the {} memory slots of the pool are in use",
+                self.limits.memories as usize * self.limits.instances as usize
+            ))
+        })?;
+        let mem_length = ty.minimum.bytes().0;
+        if mem_length > 0 {
+            if let Err(e) = alloc.make_accessible(0, mem_length) {
+                self.memories.lock().unwrap().push(alloc);
+                return Err(MemoryError::Region(e));
+            }
+        }
+
+        {
+            let mut ptr = vm_definition_location;
+            let md = ptr.as_mut();
+            md.base = alloc.as_mut_ptr();
+            md.current_length = mem_length;
+        }
+
+        let maximum = ty.maximum.map_or(self.limits.memory_pages, |max| {
+            max.min(self.limits.memory_pages)
+        });
+        Ok(Arc::new(PooledMemory {
+            pool: Arc::clone(self),
+            slot: Mutex::new(MemorySlot { alloc, image: None }),
+            maximum,
+            memory: *ty,
+            style: style.clone(),
+            vm_memory_definition: vm_definition_location,
+            parking_spot: if ty.shared {
+                Some(ParkingSpot::default())
+            } else {
+                None
+            },
+        }))
+    }
+
+    /// Create a table owned by the VM from a free table slot.
+    ///
+    /// The minimum of the table must fit in the limits of the pool.
+    /// Its maximum may not, in which case the table can only grow up
+    /// to the limits.
+    ///
+    /// # Safety
+    /// - `vm_definition_location` must point to a valid, owned `VMTableDefinition`,
+    ///   for example in `VMContext`.
+    pub unsafe fn create_table(
+        self: &Arc<Self>,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<Arc<dyn Table>, String> {
+        match ty.ty {
+            ValType::FuncRef | ValType::ExternRef => (),
+            ty => {
+                return Err(format!(
+                    "tables of types other than funcref or externref ({})",
+                    ty
+                ))
+            }
+        };
+        if let Some(max) = ty.maximum {
+            if max < ty.minimum {
+                return Err(format!(
+                    "Table minimum ({}) is larger than maximum ({})!",
+                    ty.minimum, max
+                ));
+            }
+        }
+        if ty.minimum > self.limits.table_elements {
+            return Err(format!(
+                "Table minimum ({}) is larger than the pool limit ({})!",
+                ty.minimum, self.limits.table_elements
+            ));
+        }
+
+        let mut alloc = self.tables.lock().unwrap().pop().ok_or_else(|| {
+            format!(
+                "all the {} table slots of the pool are in use",
+                self.limits.tables as usize * self.limits.instances as usize
+            )
+        })?;
+        let committed = alloc.len() - region::page::size();
+        if committed > 0 {
+            if let Err(e) = alloc.make_accessible(0, committed) {
+                self.tables.lock().unwrap().push(alloc);
+                return Err(e);
+            }
+        }
+
+        let base = alloc.as_mut_ptr() as *mut RawTableElement;
+        for i in 0..ty.minimum as usize {
+            base.add(i).write(RawTableElement::default());
+        }
+
+        {
+            let mut ptr = vm_definition_location;
+            let td = ptr.as_mut();
+            td.base = base as _;
+            td.current_elements = ty.minimum;
+        }
+
+        let maximum = ty.maximum.map_or(self.limits.table_elements, |max| {
+            max.min(self.limits.table_elements)
+        });
+        Ok(Arc::new(PooledTable {
+            pool: Arc::clone(self),
+            alloc: Mutex::new(alloc),
+            maximum,
+            table: *ty,
+            style: style.clone(),
+            vm_table_definition: vm_definition_location,
+        }))
+    }
+
+    /// The size in bytes of the storage of a table.
+    fn table_storage_size(limits: &PoolingLimits) -> usize {
+        limits.table_elements as usize * mem::size_of::<RawTableElement>()
+    }
+
+    /// Reset the first `accessible` bytes of `alloc` and put it back
+    /// in `free`. A slot that can't be reset is unmapped instead.
+    fn release(free: &Mutex<Vec<Mmap>>, mut alloc: Mmap, accessible: usize) {
+        if alloc.decommit(0, accessible).is_ok() {
+            free.lock().unwrap().push(alloc);
+        }
+    }
+}
+
+/// An instance slot borrowed from an [`InstancePool`].
+pub(crate) struct InstanceSlot {
+    pool: Arc<InstancePool>,
+    alloc: Mmap,
+    /// The number of accessible bytes at the start of `alloc`.
+    committed: usize,
+}
+
+impl fmt::Debug for InstanceSlot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InstanceSlot")
+            .field("alloc", &self.alloc)
+            .field("committed", &self.committed)
+            .finish()
+    }
+}
+
+impl Drop for InstanceSlot {
+    fn drop(&mut self) {
+        let alloc = mem::replace(&mut self.alloc, Mmap::new());
+        InstancePool::release(&self.pool.instances, alloc, self.committed);
+    }
+}
+
+/// A linear memory in a memory slot of an [`InstancePool`].
+///
+/// Unlike [`LinearMemory`], it never moves: it can only grow within its
+/// slot.
+///
+/// [`LinearMemory`]: crate::LinearMemory
+struct PooledMemory {
+    pool: Arc<InstancePool>,
+
+    /// The memory slot.
+    slot: Mutex<MemorySlot>,
+
+    /// The size in wasm pages this memory can grow to.
+    maximum: Pages,
+
+    /// The WebAssembly linear memory description.
+    memory: MemoryType,
+
+    /// Our chosen implementation style.
+    style: MemoryStyle,
+
+    /// The memory definition owned by the instance, used by the generated code.
+    vm_memory_definition: NonNull<VMMemoryDefinition>,
+
+    /// The threads waiting on addresses of this memory, if it is shared.
+    parking_spot: Option<ParkingSpot>,
+}
+
+/// A memory slot borrowed from an [`InstancePool`].
+#[derive(Debug)]
+struct MemorySlot {
+    alloc: Mmap,
+
+    /// The image mapped over the memory, if any. It must be unmapped
+    /// before the slot is reused.
+    image: Option<Arc<MemoryImage>>,
+}
+
+/// This is correct because synchronization of memory accesses is
+/// controlled by the VM, as for `LinearMemory`.
+unsafe impl Send for PooledMemory {}
+/// This is correct because all internal mutability is protected by a mutex.
+unsafe impl Sync for PooledMemory {}
+
+impl fmt::Debug for PooledMemory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PooledMemory")
+            .field("slot", &self.slot)
+            .field("maximum", &self.maximum)
+            .field("memory", &self.memory)
+            .field("style", &self.style)
+            .finish()
+    }
+}
+
+impl Memory for PooledMemory {
+    /// Returns the type for this memory.
+    fn ty(&self) -> MemoryType {
+        let mut out = self.memory;
+        out.minimum = self.size();
+        out
+    }
+
+    /// Returns the memory style for this memory.
+    fn style(&self) -> &MemoryStyle {
+        &self.style
+    }
+
+    /// Returns the number of allocated wasm pages.
+    fn size(&self) -> Pages {
+        unsafe {
+            let md = self.vm_memory_definition.as_ref();
+            Bytes::from(md.current_length).try_into().unwrap()
+        }
+    }
+
+    /// Grow memory by the specified amount of wasm pages.
+    fn grow(&self, delta: Pages) -> Result<Pages, MemoryError> {
+        let mut slot = self.slot.lock().unwrap();
+        let prev_pages = self.size();
+        // Optimization of memory.grow 0 calls.
+        if delta.0 == 0 {
+            return Ok(prev_pages);
+        }
+
+        let new_pages = prev_pages
+            .checked_add(delta)
+            .filter(|new_pages| *new_pages <= self.maximum)
+            .ok_or(MemoryError::CouldNotGrow {
+                current: prev_pages,
+                attempted_delta: delta,
+            })?;
+
+        slot.alloc
+            .make_accessible(prev_pages.bytes().0, delta.bytes().0)
+            .map_err(MemoryError::Region)?;
+
+        unsafe {
+            let mut md_ptr = self.vm_memory_definition;
+            md_ptr.as_mut().current_length = new_pages.bytes().0;
+        }
+
+        Ok(prev_pages)
+    }
+
+    /// Return a `VMMemoryDefinition` for exposing the memory to compiled wasm code.
+    fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
+        self.vm_memory_definition
+    }
+
+    /// Map `image` copy-on-write over this memory.
+    fn initialize_with_image(&self, image: &Arc<MemoryImage>) -> Result<bool, MemoryError> {
+        let mut slot = self.slot.lock().unwrap();
+        if slot.image.is_some() || image.offset() + image.len() > self.size().bytes().0 {
+            return Ok(false);
+        }
+
+        // The slot is private to this memory, and the image fits in its
+        // accessible part.
+        unsafe { image.map_at(slot.alloc.as_mut_ptr()) }.map_err(MemoryError::Region)?;
+        slot.image = Some(Arc::clone(image));
+        Ok(true)
+    }
+
+    /// The threads waiting on the addresses of this memory, if it is shared.
+    fn parking_spot(&self) -> Option<&ParkingSpot> {
+        self.parking_spot.as_ref()
+    }
+}
+
+impl Drop for PooledMemory {
+    fn drop(&mut self) {
+        let accessible = self.size().bytes().0;
+        let slot = self.slot.get_mut().unwrap();
+        let mut alloc = mem::replace(&mut slot.alloc, Mmap::new());
+        if let Some(image) = slot.image.take() {
+            // Releasing the pages of the image would bring its contents
+            // back, so put anonymous memory in its place first. A slot
+            // where this fails is unmapped instead of being reused.
+            if unsafe { image.unmap_at(alloc.as_mut_ptr()) }.is_err() {
+                return;
+            }
+        }
+        InstancePool::release(&self.pool.memories, alloc, accessible);
+    }
+}
+
+/// A table in a table slot of an [`InstancePool`].
+struct PooledTable {
+    pool: Arc<InstancePool>,
+
+    /// The table slot, holding the elements.
+    alloc: Mutex<Mmap>,
+
+    /// The number of elements this table can grow to.
+    maximum: u32,
+
+    /// The WebAssembly table description.
+    table: TableType,
+
+    /// Our chosen implementation style.
+    style: TableStyle,
+
+    /// The table definition owned by the instance, used by the generated code.
+    vm_table_definition: NonNull<VMTableDefinition>,
+}
+
+/// This is correct because there is no thread-specific data tied to this type.
+unsafe impl Send for PooledTable {}
+/// This is correct because all internal mutability is protected by a mutex.
+unsafe impl Sync for PooledTable {}
+
+impl fmt::Debug for PooledTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PooledTable")
+            .field("alloc", &self.alloc)
+            .field("maximum", &self.maximum)
+            .field("table", &self.table)
+            .field("style", &self.style)
+            .finish()
+    }
+}
+
+impl PooledTable {
+    /// Get the elements of the table.
+    ///
+    /// # Safety
+    /// - You must ensure that you have mutually exclusive access before calling
+    ///   this function. You can get this by locking the `alloc` mutex.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn elements(&self) -> &mut [RawTableElement] {
+        let td = self.vm_table_definition.as_ref();
+        slice::from_raw_parts_mut(
+            td.base as *mut RawTableElement,
+            td.current_elements as usize,
+        )
+    }
+}
+
+impl Table for PooledTable {
+    /// Returns the type for this Table.
+    fn ty(&self) -> &TableType {
+        &self.table
+    }
+
+    /// Returns the style for this Table.
+    fn style(&self) -> &TableStyle {
+        &self.style
+    }
+
+    /// Returns the number of allocated elements.
+    fn size(&self) -> u32 {
+        unsafe { self.vm_table_definition.as_ref().current_elements }
+    }
+
+    /// Grow table by the specified amount of elements.
+    ///
+    /// Returns `None` if table can't be grown by the specified amount
+    /// of elements, otherwise returns the previous size of the table.
+    fn grow(&self, delta: u32, init_value: TableElement) -> Option<u32> {
+        let _alloc = self.alloc.lock().unwrap();
+        let size = self.size();
+        let new_len = size.checked_add(delta)?;
+        if new_len > self.maximum {
+            return None;
+        }
+        if new_len == size {
+            debug_assert_eq!(delta, 0);
+            return Some(size);
+        }
+
+        // Update the ref count
+        let element = match init_value {
+            TableElement::ExternRef(extern_ref) => {
+                let extern_ref: VMExternRef = extern_ref.into();
+                // We reduce the amount we increment by because `into` prevents
+                // dropping `init_value` (which is a caller-inc'd ref).
+                if let Some(val) = (new_len as usize).checked_sub(size as usize + 1) {
+                    extern_ref.ref_inc_by(val);
+                }
+                RawTableElement { extern_ref }
+            }
+            TableElement::FuncRef(func_ref) => RawTableElement { func_ref },
+        };
+
+        // The slot is accessible up to the limit, so growing only
+        // writes the new elements.
+        unsafe {
+            let mut td_ptr = self.vm_table_definition;
+            let td = td_ptr.as_mut();
+            let base = td.base as *mut RawTableElement;
+            for i in size as usize..new_len as usize {
+                base.add(i).write(element);
+            }
+            td.current_elements = new_len;
+        }
+        Some(size)
+    }
+
+    /// Get reference to the specified element.
+    ///
+    /// Returns `None` if the index is out of bounds.
+    fn get(&self, index: u32) -> Option<TableElement> {
+        let _alloc = self.alloc.lock().unwrap();
+        let raw_data = unsafe { self.elements() }.get(index as usize).cloned()?;
+        match self.table.ty {
+            ValType::ExternRef => Some(TableElement::ExternRef(
+                unsafe { raw_data.extern_ref.ref_clone() }.into(),
+            )),
+            ValType::FuncRef => Some(TableElement::FuncRef(unsafe { raw_data.func_ref })),
+            // Tables of other types can't be created.
+            _ => None,
+        }
+    }
+
+    /// Set reference to the specified element.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index is out of bounds.
+    fn set(&self, index: u32, reference: TableElement) -> Result<(), Trap> {
+        let _alloc = self.alloc.lock().unwrap();
+        match unsafe { self.elements() }.get_mut(index as usize) {
+            Some(slot) => {
+                match (self.table.ty, reference) {
+                    (ValType::ExternRef, TableElement::ExternRef(extern_ref)) => {
+                        let extern_ref = extern_ref.into();
+                        unsafe {
+                            let elem = &mut *slot;
+                            elem.extern_ref.ref_drop();
+                            elem.extern_ref = extern_ref
+                        }
+                    }
+                    (ValType::FuncRef, r @ TableElement::FuncRef(_)) => {
+                        *slot = r.into();
+                    }
+                    // This path should never be hit by the generated code due to Wasm
+                    // validation.
+                    (ty, v) => {
+                        panic!(
+                            "Attempted to set a table of type {} with the value {:?}",
+                            ty, v
+                        )
+                    }
+                };
+
+                Ok(())
+            }
+            None => Err(Trap::lib(TrapCode::TableAccessOutOfBounds)),
+        }
+    }
+
+    /// Return a `VMTableDefinition` for exposing the table to compiled wasm code.
+    fn vmtable(&self) -> NonNull<VMTableDefinition> {
+        self.vm_table_definition
+    }
+}
+
+impl Drop for PooledTable {
+    fn drop(&mut self) {
+        let alloc = self.alloc.get_mut().unwrap();
+        let accessible = alloc.len() - region::page::size();
+        let alloc = mem::replace(alloc, Mmap::new());
+        InstancePool::release(&self.pool.tables, alloc, accessible);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> PoolingLimits {
+        PoolingLimits {
+            instances: 2,
+            memory_pages: Pages(2),
+            table_elements: 8,
+            ..Default::default()
+        }
+    }
+
+    fn offsets(memories: u32) -> VMOffsets {
+        let mut offsets = VMOffsets::for_host();
+        offsets.num_local_memories = memories;
+        offsets
+    }
+
+    #[test]
+    fn instance_slots_are_recycled() {
+        let pool = Arc::new(InstancePool::new(limits()).unwrap());
+        let first = pool.allocate_instance(offsets(1)).unwrap();
+        let second = pool.allocate_instance(offsets(1)).unwrap();
+        let err = pool.allocate_instance(offsets(1)).err().unwrap();
+        assert_eq!(err, "all the 2 instance slots of the pool are in use");
+
+        drop(first);
+        pool.allocate_instance(offsets(1)).unwrap();
+        drop(second);
+    }
+
+    #[test]
+    fn instance_limits() {
+        let pool = Arc::new(InstancePool::new(limits()).unwrap());
+        let err = pool.allocate_instance(offsets(2)).err().unwrap();
+        assert_eq!(
+            err,
+            "the instance has 2 memories, but the pool allows at most 1"
+        );
+    }
+
+    #[test]
+    fn memories_are_zeroed_and_bounded() {
+        let pool = Arc::new(InstancePool::new(limits()).unwrap());
+        let style = pool.memory_style();
+        let mut definition = VMMemoryDefinition {
+            base: std::ptr::null_mut(),
+            current_length: 0,
+        };
+        let location = NonNull::from(&mut definition);
+
+        let memory =
+            unsafe { pool.create_memory(&MemoryType::new(1, None, false), &style, location) }
+                .unwrap();
+        unsafe { *definition.base = 42 };
+        assert_eq!(memory.grow(Pages(1)).unwrap(), Pages(1));
+        assert_eq!(
+            memory.grow(Pages(1)).unwrap_err(),
+            MemoryError::CouldNotGrow {
+                current: Pages(2),
+                attempted_delta: Pages(1),
+            }
+        );
+        drop(memory);
+
+        // The slots are handed out last-in first-out, so we get the
+        // same one back.
+        let memory =
+            unsafe { pool.create_memory(&MemoryType::new(1, None, false), &style, location) }
+                .unwrap();
+        assert_eq!(unsafe { *definition.base }, 0);
+        drop(memory);
+
+        let err = unsafe { pool.create_memory(&MemoryType::new(3, None, false), &style, location) }
+            .unwrap_err();
+        assert_eq!(
+            err,
+            MemoryError::MinimumMemoryTooLarge {
+                min_requested: Pages(3),
+                max_allowed: Pages(2),
+            }
+        );
+    }
+
+    #[test]
+    fn tables_are_bounded() {
+        let pool = Arc::new(InstancePool::new(limits()).unwrap());
+        let mut definition = VMTableDefinition {
+            base: std::ptr::null_mut(),
+            current_elements: 0,
+        };
+        let location = NonNull::from(&mut definition);
+        let ty = TableType::new(ValType::FuncRef, 4, None);
+
+        let table = unsafe { pool.create_table(&ty, &TableStyle::CallerChecksSignature, location) }
+            .unwrap();
+        assert_eq!(table.grow(4, TableElement::default()), Some(4));
+        assert_eq!(table.grow(1, TableElement::default()), None);
+        assert_eq!(table.size(), 8);
+    }
+}
@@ -25,10 +25,25 @@ impl VMSharedSignatureIndex {
 /// call must match. To implement this efficiently, keep a registry of all
 /// signatures, shared by all instances, so that call sites can just do an
 /// index comparison.
+///
+/// Signatures are reference-counted: each call to [`register`] must be
+/// balanced by a call to [`unregister`] once the index isn't used anymore,
+/// after which the index may be given to another signature.
+///
+/// [`register`]: SignatureRegistry::register
+/// [`unregister`]: SignatureRegistry::unregister
 #[derive(Debug)]
 pub struct SignatureRegistry {
     type_to_index: HashMap<FunctionType, VMSharedSignatureIndex>,
-    index_to_data: Vec<FunctionType>,
+    index_to_data: Vec<Option<RegisteredSignature>>,
+    /// The indices of the unregistered signatures, to reuse.
+    free_indices: Vec<VMSharedSignatureIndex>,
+}
+
+#[derive(Debug)]
+struct RegisteredSignature {
+    ty: FunctionType,
+    references: usize,
 }
 
 impl SignatureRegistry {
@@ -37,6 +52,7 @@ impl SignatureRegistry {
         Self {
             type_to_index: HashMap::new(),
             index_to_data: Vec::new(),
+            free_indices: Vec::new(),
         }
     }
 
@@ -52,25 +68,107 @@ impl SignatureRegistry {
         // Consider `transmute` or `hashbrown`'s raw_entry.
         let sig = FunctionType::new(sig.params(), sig.results());
         match self.type_to_index.entry(sig.clone()) {
-            hash_map::Entry::Occupied(entry) => *entry.get(),
+            hash_map::Entry::Occupied(entry) => {
+                let sig_id = *entry.get();
+                if let Some(data) = &mut self.index_to_data[sig_id.0 as usize] {
+                    data.references += 1;
+                }
+                sig_id
+            }
             hash_map::Entry::Vacant(entry) => {
-                debug_assert!(
-                    u32::try_from(len).is_ok(),
-                    "invariant: can't have more than 2³²-1 signatures!"
-                );
-                let sig_id = VMSharedSignatureIndex::new(u32::try_from(len).unwrap());
+                let data = Some(RegisteredSignature {
+                    ty: sig,
+                    references: 1,
+                });
+                let sig_id = match self.free_indices.pop() {
+                    Some(sig_id) => {
+                        self.index_to_data[sig_id.0 as usize] = data;
+                        sig_id
+                    }
+                    None => {
+                        debug_assert!(
+                            u32::try_from(len).is_ok(),
+                            "invariant: can't have more than 2³²-1 signatures!"
+                        );
+                        self.index_to_data.push(data);
+                        VMSharedSignatureIndex::new(u32::try_from(len).unwrap())
+                    }
+                };
                 entry.insert(sig_id);
-                self.index_to_data.push(sig);
                 sig_id
             }
         }
     }
 
+    /// Release a reference to a signature taken by [`register`], removing
+    /// the signature once it isn't referenced anymore.
+    ///
+    /// [`register`]: SignatureRegistry::register
+    pub fn unregister(&mut self, idx: VMSharedSignatureIndex) {
+        let slot = match self.index_to_data.get_mut(idx.0 as usize) {
+            Some(slot) => slot,
+            None => return,
+        };
+        if let Some(data) = slot {
+            data.references -= 1;
+            if data.references == 0 {
+                self.type_to_index.remove(&data.ty);
+                *slot = None;
+                self.free_indices.push(idx);
+            }
+        }
+    }
+
     /// Looks up a shared signature index within this registry.
     ///
     /// Note that for this operation to be semantically correct the `idx` must
     /// have previously come from a call to `register` of this same object.
     pub fn lookup(&self, idx: VMSharedSignatureIndex) -> Option<&FunctionType> {
-        self.index_to_data.get(idx.0 as usize)
+        self.index_to_data
+            .get(idx.0 as usize)?
+            .as_ref()
+            .map(|data| &data.ty)
+    }
+
+    /// The number of signatures registered.
+    pub fn len(&self) -> usize {
+        self.type_to_index.len()
+    }
+
+    /// Whether no signature is registered.
+    pub fn is_empty(&self) -> bool {
+        self.type_to_index.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmer_types::Type;
+
+    #[test]
+    fn signatures_are_released_once_unregistered() {
+        let mut registry = SignatureRegistry::new();
+        let i32_to_i64 = FunctionType::new([Type::I32], [Type::I64]);
+        let f32_to_f64 = FunctionType::new([Type::F32], [Type::F64]);
+
+        let first = registry.register((&i32_to_i64).into());
+        assert_eq!(registry.register((&i32_to_i64).into()), first);
+        let second = registry.register((&f32_to_f64).into());
+        assert_ne!(first, second);
+        assert_eq!(registry.len(), 2);
+
+        registry.unregister(first);
+        assert_eq!(registry.lookup(first), Some(&i32_to_i64));
+        registry.unregister(first);
+        assert_eq!(registry.lookup(first), None);
+        assert_eq!(registry.len(), 1);
+
+        // The index is reused for the next new signature.
+        let i64_to_i32 = FunctionType::new([Type::I64], [Type::I32]);
+        assert_eq!(registry.register((&i64_to_i32).into()), first);
+        assert_eq!(registry.lookup(first), Some(&i64_to_i32));
+        assert_eq!(registry.lookup(second), Some(&f32_to_f64));
+        assert_eq!(registry.len(), 2);
     }
 }
@@ -0,0 +1,98 @@
+//! Exception tags and the exceptions thrown with them.
+
+use std::sync::Arc;
+use wasmer_types::{TagType, Type, Value, WasmValueType};
+
+/// A WebAssembly exception tag instance.
+///
+/// Tags have no state: two tags are the same only if they are the same
+/// allocation, so an exception can only be caught by the instances that
+/// share the `Arc<Tag>` it was thrown with.
+#[derive(Debug)]
+pub struct Tag {
+    ty: TagType,
+}
+
+impl Tag {
+    /// Create a new tag from a [`TagType`].
+    pub fn new(ty: TagType) -> Self {
+        Self { ty }
+    }
+
+    /// Get the type of the tag.
+    pub fn ty(&self) -> &TagType {
+        &self.ty
+    }
+}
+
+/// A WebAssembly exception: a tag and the values thrown with it.
+#[derive(Debug, Clone)]
+pub struct Exception {
+    tag: Arc<Tag>,
+    payload: Vec<u128>,
+}
+
+impl Exception {
+    pub(crate) fn new(tag: Arc<Tag>) -> Self {
+        let payload = Vec::with_capacity(tag.ty().params().len());
+        Self { tag, payload }
+    }
+
+    pub(crate) fn push(&mut self, bits: u128) {
+        debug_assert!(self.payload.len() < self.tag.ty().params().len());
+        self.payload.push(bits);
+    }
+
+    pub(crate) fn payload_bits(&self, index: usize) -> u128 {
+        self.payload[index]
+    }
+
+    /// The tag the exception was thrown with.
+    pub fn tag(&self) -> &Arc<Tag> {
+        &self.tag
+    }
+
+    /// The values thrown with the exception, typed after the parameters of
+    /// its tag.
+    pub fn values<T: WasmValueType>(&self) -> Vec<Value<T>> {
+        self.tag
+            .ty()
+            .params()
+            .iter()
+            .zip(&self.payload)
+            .map(|(ty, &bits)| match ty {
+                Type::I32 => Value::I32(bits as i32),
+                Type::I64 => Value::I64(bits as i64),
+                Type::F32 => Value::F32(f32::from_bits(bits as u32)),
+                Type::F64 => Value::F64(f64::from_bits(bits as u64)),
+                Type::V128 => Value::V128(bits),
+                Type::ExternRef | Type::FuncRef => {
+                    unreachable!("reference types can't be thrown")
+                }
+            })
+            .collect()
+    }
+}
+
+/// The exceptions caught by a wasm activation, indexed by the ordinal of
+/// the `try` block that caught them, so that they can be rethrown.
+#[derive(Default)]
+pub struct CaughtExceptions {
+    slots: Vec<Option<Exception>>,
+}
+
+impl CaughtExceptions {
+    pub(crate) fn set(&mut self, slot: u32, exception: Exception) {
+        let slot = slot as usize;
+        if self.slots.len() <= slot {
+            self.slots.resize(slot + 1, None);
+        }
+        self.slots[slot] = Some(exception);
+    }
+
+    pub(crate) fn get(&self, slot: u32) -> &Exception {
+        self.slots[slot as usize]
+            .as_ref()
+            .expect("no exception was caught in this slot")
+    }
+}
@@ -69,6 +69,12 @@ pub enum TrapCode {
 
     /// Hit the gas limit.
     GasExceeded = 12,
+
+    /// The epoch deadline of the instance was reached.
+    Interrupted = 13,
+
+    /// An `atomic.wait` was attempted on a memory which is not shared.
+    UnsharedMemoryWait = 14,
 }
 
 impl TrapCode {
@@ -88,6 +94,8 @@ impl TrapCode {
             Self::UnreachableCodeReached => "unreachable",
             Self::UnalignedAtomic => "unaligned atomic access",
             Self::GasExceeded => "gas limit exceeded",
+            Self::Interrupted => "interrupted",
+            Self::UnsharedMemoryWait => "expected shared memory",
         }
     }
 }
@@ -108,6 +116,8 @@ impl Display for TrapCode {
             Self::UnreachableCodeReached => "unreachable",
             Self::UnalignedAtomic => "unalign_atom",
             Self::GasExceeded => "out_of_gas",
+            Self::Interrupted => "interrupt",
+            Self::UnsharedMemoryWait => "unshared_wait",
         };
         f.write_str(identifier)
     }
@@ -130,6 +140,8 @@ impl FromStr for TrapCode {
             "bad_toint" => Ok(Self::BadConversionToInteger),
             "unreachable" => Ok(Self::UnreachableCodeReached),
             "unalign_atom" => Ok(Self::UnalignedAtomic),
+            "interrupt" => Ok(Self::Interrupted),
+            "unshared_wait" => Ok(Self::UnsharedMemoryWait),
             _ => Err(()),
         }
     }
@@ -140,7 +152,7 @@ mod tests {
     use super::*;
 
     // Everything but user-defined codes.
-    const CODES: [TrapCode; 12] = [
+    const CODES: [TrapCode; 14] = [
         TrapCode::StackOverflow,
         TrapCode::HeapAccessOutOfBounds,
         TrapCode::HeapMisaligned,
@@ -153,6 +165,8 @@ mod tests {
         TrapCode::BadConversionToInteger,
         TrapCode::UnreachableCodeReached,
         TrapCode::UnalignedAtomic,
+        TrapCode::Interrupted,
+        TrapCode::UnsharedMemoryWait,
     ];
 
     #[test]
@@ -5,14 +5,17 @@
 //! signalhandling mechanisms.
 
 use super::trapcode::TrapCode;
+use crate::tag::{CaughtExceptions, Exception};
 use crate::vmcontext::{VMFunctionBody, VMFunctionEnvironment, VMTrampoline};
 use backtrace::Backtrace;
 use std::any::Any;
-use std::cell::{Cell, UnsafeCell};
+use std::cell::{Cell, RefCell, UnsafeCell};
 use std::error::Error;
 use std::mem::{self, MaybeUninit};
 use std::ptr;
+use std::sync::atomic::{AtomicU32, Ordering};
 pub use tls::TlsRestore;
+pub(crate) use tls::{current as current_tls, TlsSegment};
 
 extern "C" {
     fn wasmer_register_setjmp(
@@ -54,6 +57,15 @@ pub unsafe fn raise_lib_trap(trap: Trap) -> ! {
     tls::with(|info| info.unwrap().unwind_with(UnwindReason::LibTrap(trap)))
 }
 
+/// Runs `closure` with the state of the innermost call into wasm.
+///
+/// # Panics
+///
+/// Panics if no wasm code is on the stack.
+pub(crate) fn with_call_state<R>(closure: impl FnOnce(&CallThreadState) -> R) -> R {
+    tls::with(|info| closure(info.expect("no wasm code is on the stack")))
+}
+
 /// Carries a Rust panic across wasm code and resumes the panic on the other
 /// side.
 ///
@@ -101,6 +113,16 @@ pub enum Trap {
         /// Native stack backtrace at the time the OOM occurred
         backtrace: Backtrace,
     },
+
+    /// A WebAssembly exception that was not caught before reaching the host.
+    ///
+    /// Note: this trap is deterministic (assuming a deterministic host implementation)
+    Exception {
+        /// The uncaught exception.
+        exception: Exception,
+        /// Native stack backtrace at the time the exception reached the host
+        backtrace: Backtrace,
+    },
 }
 
 impl Trap {
@@ -209,12 +231,26 @@ where
     Ok(global_results.assume_init())
 }
 
+/// The number of exceptions being propagated by wasm code, on all threads.
+///
+/// Compiled code checks it after calls, and only asks the runtime whether
+/// the callee threw when it is not zero.
+pub(crate) static PENDING_EXCEPTIONS: AtomicU32 = AtomicU32::new(0);
+
 /// Temporary state stored on the stack which is registered in the `tls` module
 /// below for calls into wasm.
 pub struct CallThreadState {
     unwind: UnsafeCell<MaybeUninit<UnwindReason>>,
     jmp_buf: Cell<*const u8>,
     prev: Cell<tls::Ptr>,
+    /// The exception being propagated by the wasm code running on top of
+    /// this state, if any.
+    exception: Cell<Option<Exception>>,
+    /// The exceptions caught by the activations running on top of this
+    /// state. They are owned here so that a trap doesn't leak them, and
+    /// boxed as wasm code holds pointers to them.
+    #[allow(clippy::vec_box)]
+    caught_exceptions: RefCell<Vec<Box<CaughtExceptions>>>,
 }
 
 enum UnwindReason {
@@ -239,13 +275,22 @@ impl<'a> CallThreadState {
             unwind: UnsafeCell::new(MaybeUninit::uninit()),
             jmp_buf: Cell::new(ptr::null()),
             prev: Cell::new(ptr::null()),
+            exception: Cell::new(None),
+            caught_exceptions: RefCell::new(Vec::new()),
         }
     }
 
     fn with(self, closure: impl FnOnce(&Self) -> i32) -> Result<(), Trap> {
         let ret = tls::set(&self, || closure(&self))?;
+        let exception = self.take_exception();
         if ret != 0 {
-            return Ok(());
+            return match exception {
+                Some(exception) => Err(Trap::Exception {
+                    exception,
+                    backtrace: Backtrace::new_unresolved(),
+                }),
+                None => Ok(()),
+            };
         }
         // We will only reach this path if ret == 0. And that will
         // only happen if a trap did happen. As such, it's safe to
@@ -263,6 +308,48 @@ impl<'a> CallThreadState {
         }
     }
 
+    /// Starts propagating `exception`.
+    pub(crate) fn set_exception(&self, exception: Exception) {
+        if self.exception.replace(Some(exception)).is_none() {
+            PENDING_EXCEPTIONS.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Runs `f` on the exception being propagated, if any.
+    pub(crate) fn with_exception<R>(&self, f: impl FnOnce(Option<&mut Exception>) -> R) -> R {
+        let mut exception = self.exception.take();
+        let ret = f(exception.as_mut());
+        self.exception.set(exception);
+        ret
+    }
+
+    /// Stops propagating the current exception, and returns it.
+    pub(crate) fn take_exception(&self) -> Option<Exception> {
+        let exception = self.exception.take();
+        if exception.is_some() {
+            PENDING_EXCEPTIONS.fetch_sub(1, Ordering::SeqCst);
+        }
+        exception
+    }
+
+    /// Allocates storage for the exceptions caught by a wasm activation.
+    pub(crate) fn new_caught_exceptions(&self) -> *mut CaughtExceptions {
+        let mut caught = Box::new(CaughtExceptions::default());
+        let ptr = &mut *caught as *mut CaughtExceptions;
+        self.caught_exceptions.borrow_mut().push(caught);
+        ptr
+    }
+
+    /// Frees storage allocated by `new_caught_exceptions`.
+    pub(crate) fn drop_caught_exceptions(&self, ptr: *mut CaughtExceptions) {
+        let mut all = self.caught_exceptions.borrow_mut();
+        let position = all
+            .iter()
+            .rposition(|caught| ptr::eq(&**caught, ptr))
+            .expect("caught exceptions freed twice");
+        all.remove(position);
+    }
+
     fn unwind_with(&self, reason: UnwindReason) -> ! {
         unsafe {
             (*self.unwind.get()).as_mut_ptr().write(reason);
@@ -370,6 +457,54 @@ mod tls {
         }
     }
 
+    /// The states pushed on top of a base state by code running on a fiber,
+    /// taken off the thread while the fiber is suspended.
+    ///
+    /// Unlike [`TlsRestore`], which handles a single state, a segment may hold
+    /// any number of nested calls into wasm. It also allows the fiber to be
+    /// resumed on top of a different base, e.g. from another thread.
+    pub struct TlsSegment {
+        head: raw::Ptr,
+        base: raw::Ptr,
+    }
+
+    impl TlsSegment {
+        /// Takes every state pushed on top of `base` off this thread, and
+        /// makes `base` the current state again.
+        ///
+        /// # Safety
+        ///
+        /// `base` must be the current state or one of its predecessors.
+        pub unsafe fn detach(base: raw::Ptr) -> Result<Self, Trap> {
+            let head = raw::replace(base)?;
+            Ok(Self { head, base })
+        }
+
+        /// Pushes the states back on top of the current state of this thread.
+        ///
+        /// # Safety
+        ///
+        /// The states must still be alive, as they are while the fiber that
+        /// pushed them is suspended.
+        pub unsafe fn attach(self) -> Result<(), Trap> {
+            if self.head == self.base {
+                return Ok(());
+            }
+            let mut bottom = self.head;
+            while (*bottom).prev.get() != self.base {
+                bottom = (*bottom).prev.get();
+            }
+            (*bottom).prev.set(raw::get());
+            raw::replace(self.head)?;
+            Ok(())
+        }
+    }
+
+    /// Returns the state currently configured on this thread.
+    pub fn current() -> Ptr {
+        raw::get()
+    }
+
     /// Configures thread local state such that for the duration of the
     /// execution of `closure` any call to `with` will yield `ptr`, unless this
     /// is recursively called again.
@@ -1,4 +1,5 @@
 use crate::MemoryError;
+use crate::{InstanceAllocation, InstanceAllocator, VMOffsets};
 use crate::{Memory, Table};
 use crate::{MemoryStyle, TableStyle};
 use crate::{VMMemoryDefinition, VMTableDefinition};
@@ -50,4 +51,12 @@ pub trait Tunables {
         style: &TableStyle,
         vm_definition_location: NonNull<VMTableDefinition>,
     ) -> Result<Arc<dyn Table>, String>;
+
+    /// Allocate the data of an instance described by `offsets`, see
+    /// [`InstanceAllocator::new`].
+    ///
+    /// The default implementation allocates it on the heap.
+    fn allocate_instance(&self, offsets: VMOffsets) -> Result<InstanceAllocation, String> {
+        Ok(InstanceAllocator::new(offsets))
+    }
 }
@@ -62,7 +62,7 @@ impl std::hash::Hash for VMFunctionEnvironment {
 
 /// Represents a continuous region of executable memory starting with a function
 /// entry point.
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 #[repr(C)]
 pub struct FunctionExtent {
     /// Entry point for normal entry of the function. All addresses in the
@@ -420,14 +420,14 @@ impl VMMemoryDefinition {
     ///
     /// The memory is not copied atomically and is not synchronized: it's the
     /// caller's responsibility to synchronize.
-    pub(crate) unsafe fn memory_copy(&self, dst: u32, src: u32, len: u32) -> Result<(), Trap> {
+    pub(crate) unsafe fn memory_copy(&self, dst: u64, src: u64, len: u64) -> Result<(), Trap> {
         // https://webassembly.github.io/reference-types/core/exec/instructions.html#exec-memory-copy
         if src
             .checked_add(len)
-            .map_or(true, |n| usize::try_from(n).unwrap() > self.current_length)
+            .map_or(true, |n| n > self.current_length as u64)
             || dst
                 .checked_add(len)
-                .map_or(true, |m| usize::try_from(m).unwrap() > self.current_length)
+                .map_or(true, |m| m > self.current_length as u64)
         {
             return Err(Trap::lib(TrapCode::HeapAccessOutOfBounds));
         }
@@ -454,10 +454,10 @@ impl VMMemoryDefinition {
     /// # Safety
     /// The memory is not filled atomically and is not synchronized: it's the
     /// caller's responsibility to synchronize.
-    pub(crate) unsafe fn memory_fill(&self, dst: u32, val: u32, len: u32) -> Result<(), Trap> {
+    pub(crate) unsafe fn memory_fill(&self, dst: u64, val: u32, len: u64) -> Result<(), Trap> {
         if dst
             .checked_add(len)
-            .map_or(true, |m| usize::try_from(m).unwrap() > self.current_length)
+            .map_or(true, |m| m > self.current_length as u64)
         {
             return Err(Trap::lib(TrapCode::HeapAccessOutOfBounds));
         }
@@ -885,6 +885,32 @@ mod test_vmcaller_checked_anyfunc {
     }
 }
 
+#[cfg(test)]
+mod test_fast_gas_counter {
+    use crate::VMOffsets;
+    use memoffset::offset_of;
+    use std::mem::size_of;
+    use wasmer_types::{FastGasCounter, ModuleInfo};
+
+    #[test]
+    fn check_fast_gas_counter_offsets() {
+        let module = ModuleInfo::new();
+        let offsets = VMOffsets::new(size_of::<*mut u8>() as u8).with_module_info(&module);
+        assert_eq!(
+            offset_of!(FastGasCounter, burnt_gas),
+            usize::from(offsets.fast_gas_counter_burnt_gas())
+        );
+        assert_eq!(
+            offset_of!(FastGasCounter, gas_limit),
+            usize::from(offsets.fast_gas_counter_gas_limit())
+        );
+        assert_eq!(
+            offset_of!(FastGasCounter, opcode_cost),
+            usize::from(offsets.fast_gas_counter_opcode_cost())
+        );
+    }
+}
+
 /// An index type for builtin functions.
 #[derive(Copy, Clone, Debug)]
 pub struct VMBuiltinFunctionIndex(u32);
@@ -995,9 +1021,93 @@ impl VMBuiltinFunctionIndex {
     pub const fn get_externref_dec_index() -> Self {
         Self(25)
     }
+    /// Returns an index for wasm's `memory.atomic.wait32` instruction.
+    pub const fn get_memory_atomic_wait32_index() -> Self {
+        Self(26)
+    }
+    /// Returns an index for wasm's `memory.atomic.wait64` instruction.
+    pub const fn get_memory_atomic_wait64_index() -> Self {
+        Self(27)
+    }
+    /// Returns an index for wasm's `memory.atomic.notify` instruction.
+    pub const fn get_memory_atomic_notify_index() -> Self {
+        Self(28)
+    }
+    /// Returns an index for wasm's `memory.grow` builtin function for 64-bit memories.
+    pub const fn get_memory64_grow_index() -> Self {
+        Self(29)
+    }
+    /// Returns an index for wasm's imported `memory.grow` builtin function for 64-bit memories.
+    pub const fn get_imported_memory64_grow_index() -> Self {
+        Self(30)
+    }
+    /// Returns an index for wasm's `memory.size` builtin function for 64-bit memories.
+    pub const fn get_memory64_size_index() -> Self {
+        Self(31)
+    }
+    /// Returns an index for wasm's imported `memory.size` builtin function for 64-bit memories.
+    pub const fn get_imported_memory64_size_index() -> Self {
+        Self(32)
+    }
+    /// Returns an index for wasm's `memory.copy` for locally defined 64-bit memories.
+    pub const fn get_memory64_copy_index() -> Self {
+        Self(33)
+    }
+    /// Returns an index for wasm's `memory.copy` for imported 64-bit memories.
+    pub const fn get_imported_memory64_copy_index() -> Self {
+        Self(34)
+    }
+    /// Returns an index for wasm's `memory.fill` for locally defined 64-bit memories.
+    pub const fn get_memory64_fill_index() -> Self {
+        Self(35)
+    }
+    /// Returns an index for wasm's `memory.fill` for imported 64-bit memories.
+    pub const fn get_imported_memory64_fill_index() -> Self {
+        Self(36)
+    }
+    /// Returns an index for wasm's `memory.init` instruction for 64-bit memories.
+    pub const fn get_memory64_init_index() -> Self {
+        Self(37)
+    }
+    /// Returns an index for the start of wasm's `throw` instruction.
+    pub const fn get_exception_new_index() -> Self {
+        Self(38)
+    }
+    /// Returns an index for pushing a value to the payload of a thrown exception.
+    pub const fn get_exception_push_index() -> Self {
+        Self(39)
+    }
+    /// Returns an index for checking whether an exception is being propagated.
+    pub const fn get_exception_pending_index() -> Self {
+        Self(40)
+    }
+    /// Returns an index for allocating the exceptions caught by a function.
+    pub const fn get_exception_caught_new_index() -> Self {
+        Self(41)
+    }
+    /// Returns an index for freeing the exceptions caught by a function.
+    pub const fn get_exception_caught_drop_index() -> Self {
+        Self(42)
+    }
+    /// Returns an index for wasm's `catch` and `catch_all` instructions.
+    pub const fn get_exception_catch_index() -> Self {
+        Self(43)
+    }
+    /// Returns an index for reading the payload of a caught exception.
+    pub const fn get_exception_payload_index() -> Self {
+        Self(44)
+    }
+    /// Returns an index for wasm's `rethrow` instruction.
+    pub const fn get_exception_rethrow_index() -> Self {
+        Self(45)
+    }
+    /// Returns an index for wasm's `memory.copy` between two different memories.
+    pub const fn get_memory_copy_between_index() -> Self {
+        Self(46)
+    }
     /// Returns the total number of builtin functions.
     pub const fn builtin_functions_total_number() -> u32 {
-        26
+        47
     }
 
     /// Return the index as an u32 number.
@@ -1079,6 +1189,48 @@ impl VMBuiltinFunctionsArray {
             wasmer_vm_externref_inc as usize;
         ptrs[VMBuiltinFunctionIndex::get_externref_dec_index().index() as usize] =
             wasmer_vm_externref_dec as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory_atomic_wait32_index().index() as usize] =
+            wasmer_vm_memory32_atomic_wait32 as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory_atomic_wait64_index().index() as usize] =
+            wasmer_vm_memory32_atomic_wait64 as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory_atomic_notify_index().index() as usize] =
+            wasmer_vm_memory32_atomic_notify as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory64_grow_index().index() as usize] =
+            wasmer_vm_memory64_grow as usize;
+        ptrs[VMBuiltinFunctionIndex::get_imported_memory64_grow_index().index() as usize] =
+            wasmer_vm_imported_memory64_grow as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory64_size_index().index() as usize] =
+            wasmer_vm_memory64_size as usize;
+        ptrs[VMBuiltinFunctionIndex::get_imported_memory64_size_index().index() as usize] =
+            wasmer_vm_imported_memory64_size as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory64_copy_index().index() as usize] =
+            wasmer_vm_memory64_copy as usize;
+        ptrs[VMBuiltinFunctionIndex::get_imported_memory64_copy_index().index() as usize] =
+            wasmer_vm_imported_memory64_copy as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory64_fill_index().index() as usize] =
+            wasmer_vm_memory64_fill as usize;
+        ptrs[VMBuiltinFunctionIndex::get_imported_memory64_fill_index().index() as usize] =
+            wasmer_vm_imported_memory64_fill as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory64_init_index().index() as usize] =
+            wasmer_vm_memory64_init as usize;
+        ptrs[VMBuiltinFunctionIndex::get_exception_new_index().index() as usize] =
+            wasmer_vm_exception_new as usize;
+        ptrs[VMBuiltinFunctionIndex::get_exception_push_index().index() as usize] =
+            wasmer_vm_exception_push as usize;
+        ptrs[VMBuiltinFunctionIndex::get_exception_pending_index().index() as usize] =
+            wasmer_vm_exception_pending as usize;
+        ptrs[VMBuiltinFunctionIndex::get_exception_caught_new_index().index() as usize] =
+            wasmer_vm_exception_caught_new as usize;
+        ptrs[VMBuiltinFunctionIndex::get_exception_caught_drop_index().index() as usize] =
+            wasmer_vm_exception_caught_drop as usize;
+        ptrs[VMBuiltinFunctionIndex::get_exception_catch_index().index() as usize] =
+            wasmer_vm_exception_catch as usize;
+        ptrs[VMBuiltinFunctionIndex::get_exception_payload_index().index() as usize] =
+            wasmer_vm_exception_payload as usize;
+        ptrs[VMBuiltinFunctionIndex::get_exception_rethrow_index().index() as usize] =
+            wasmer_vm_exception_rethrow as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory_copy_between_index().index() as usize] =
+            wasmer_vm_memory_copy_between as usize;
 
         debug_assert!(ptrs.iter().cloned().all(|p| p != 0));
 
@@ -378,6 +378,26 @@ impl VMOffsets {
     }
 }
 
+/// Offsets for [`FastGasCounter`].
+///
+/// [`FastGasCounter`]: wasmer_types::FastGasCounter
+impl VMOffsets {
+    /// The offset of the `burnt_gas` field.
+    pub const fn fast_gas_counter_burnt_gas(&self) -> u8 {
+        0
+    }
+
+    /// The offset of the `gas_limit` field.
+    pub const fn fast_gas_counter_gas_limit(&self) -> u8 {
+        8
+    }
+
+    /// The offset of the `opcode_cost` field.
+    pub const fn fast_gas_counter_opcode_cost(&self) -> u8 {
+        16
+    }
+}
+
 /// Offset base by num_items items of size item_size, panicking on overflow
 fn offset_by(base: u32, num_items: u32, prev_item_size: u32, next_item_align: usize) -> u32 {
     align(
@@ -513,12 +533,37 @@ impl VMOffsets {
         self.vmctx_stack_limit_begin().checked_add(4).unwrap()
     }
 
+    /// The offset of the epoch counter pointer.
+    pub fn vmctx_epoch_pointer(&self) -> u32 {
+        offset_by(
+            self.vmctx_stack_limit_initial_begin(),
+            1,
+            4,
+            align_of::<*const u64>(),
+        )
+    }
+
+    /// The offset of the epoch deadline.
+    pub fn vmctx_epoch_deadline(&self) -> u32 {
+        offset_by(
+            self.vmctx_epoch_pointer(),
+            1,
+            u32::from(self.pointer_size),
+            align_of::<u64>(),
+        )
+    }
+
+    /// The offset of the pointer to the number of pending exceptions.
+    pub fn vmctx_pending_exceptions_pointer(&self) -> u32 {
+        offset_by(self.vmctx_epoch_deadline(), 1, 8, align_of::<*const u32>())
+    }
+
     /// Return the size of the [`VMContext`] allocation.
     ///
     /// [`VMContext`]: crate::vmcontext::VMContext
     pub fn size_of_vmctx(&self) -> u32 {
-        self.vmctx_stack_limit_initial_begin()
-            .checked_add(4)
+        self.vmctx_pending_exceptions_pointer()
+            .checked_add(u32::from(self.pointer_size))
             .unwrap()
     }
 
@@ -0,0 +1,94 @@
+use super::{FileSystem, FsError, Metadata, OpenOptions, VirtualFile};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A [`FileSystem`] exposing a directory of the host.
+///
+/// Symbolic links are followed as long as they lead to the directory or
+/// inside of it.
+#[derive(Debug, Clone)]
+pub struct HostFs {
+    /// The canonical path of the directory.
+    root: PathBuf,
+}
+
+impl HostFs {
+    /// Exposes the directory at `root`.
+    pub fn new(root: impl AsRef<Path>) -> Result<Self, FsError> {
+        let root = root.as_ref().canonicalize()?;
+        if !root.is_dir() {
+            return Err(FsError::NotADirectory);
+        }
+        Ok(Self { root })
+    }
+
+    /// Returns the host path of `path`, if it's inside the directory once
+    /// its symbolic links are resolved.
+    fn host_path(&self, path: &Path) -> Result<PathBuf, FsError> {
+        let joined = self.root.join(path);
+        let resolved = match joined.canonicalize() {
+            Ok(resolved) => resolved,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                // The file doesn't exist yet, unless it's a dangling link.
+                if fs::symlink_metadata(&joined).is_ok() {
+                    return Err(FsError::PermissionDenied);
+                }
+                match (joined.parent(), joined.file_name()) {
+                    (Some(parent), Some(name)) => parent.canonicalize()?.join(name),
+                    _ => return Err(FsError::NotFound),
+                }
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if resolved.starts_with(&self.root) {
+            Ok(resolved)
+        } else {
+            Err(FsError::PermissionDenied)
+        }
+    }
+}
+
+impl FileSystem for HostFs {
+    fn open(&self, path: &Path, options: &OpenOptions) -> Result<Box<dyn VirtualFile>, FsError> {
+        let path = self.host_path(path)?;
+        if path.is_dir() {
+            return Err(FsError::IsADirectory);
+        }
+        let file = fs::OpenOptions::new()
+            .read(options.read)
+            .write(options.write)
+            .append(options.append)
+            .truncate(options.truncate)
+            .create(options.create)
+            .create_new(options.create_new)
+            .open(path)?;
+        Ok(Box::new(file))
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, FsError> {
+        let metadata = fs::metadata(self.host_path(path)?)?;
+        Ok(Metadata {
+            is_dir: metadata.is_dir(),
+            len: metadata.len(),
+        })
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<(), FsError> {
+        Ok(fs::create_dir(self.host_path(path)?)?)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<(), FsError> {
+        let path = self.host_path(path)?;
+        if path.is_dir() {
+            return Err(FsError::IsADirectory);
+        }
+        Ok(fs::remove_file(path)?)
+    }
+}
+
+impl VirtualFile for fs::File {
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+}
@@ -0,0 +1,277 @@
+use super::{FileSystem, FsError, Metadata, OpenOptions, VirtualFile};
+use std::collections::BTreeMap;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// A [`FileSystem`] keeping its files in memory.
+///
+/// Cloning a `MemFs` is cheap, and the clones share their files.
+#[derive(Debug, Clone, Default)]
+pub struct MemFs {
+    nodes: Arc<Mutex<BTreeMap<PathBuf, Node>>>,
+}
+
+#[derive(Debug)]
+enum Node {
+    Dir,
+    File(Arc<Mutex<Vec<u8>>>),
+}
+
+impl MemFs {
+    /// Creates an empty filesystem.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates or replaces the file at `path` with `contents`, creating its
+    /// parent directories.
+    pub fn write_file(
+        &self,
+        path: impl AsRef<Path>,
+        contents: impl Into<Vec<u8>>,
+    ) -> Result<(), FsError> {
+        let path = path.as_ref();
+        let mut nodes = self.nodes.lock().unwrap();
+        for parent in path.ancestors().skip(1) {
+            if parent.as_os_str().is_empty() {
+                break;
+            }
+            match nodes.entry(parent.to_path_buf()).or_insert(Node::Dir) {
+                Node::Dir => {}
+                Node::File(_) => return Err(FsError::NotADirectory),
+            }
+        }
+        match nodes.get(path) {
+            Some(Node::Dir) => Err(FsError::IsADirectory),
+            _ => {
+                let file = Node::File(Arc::new(Mutex::new(contents.into())));
+                nodes.insert(path.to_path_buf(), file);
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the contents of the file at `path`.
+    pub fn read_file(&self, path: impl AsRef<Path>) -> Result<Vec<u8>, FsError> {
+        match self.nodes.lock().unwrap().get(path.as_ref()) {
+            Some(Node::File(data)) => Ok(data.lock().unwrap().clone()),
+            Some(Node::Dir) => Err(FsError::IsADirectory),
+            None => Err(FsError::NotFound),
+        }
+    }
+
+    /// Checks that the parent of `path` is a directory.
+    fn check_parent(nodes: &BTreeMap<PathBuf, Node>, path: &Path) -> Result<(), FsError> {
+        match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => match nodes.get(parent) {
+                Some(Node::Dir) => Ok(()),
+                Some(Node::File(_)) => Err(FsError::NotADirectory),
+                None => Err(FsError::NotFound),
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+impl FileSystem for MemFs {
+    fn open(&self, path: &Path, options: &OpenOptions) -> Result<Box<dyn VirtualFile>, FsError> {
+        if path.as_os_str().is_empty() {
+            return Err(FsError::IsADirectory);
+        }
+        let mut nodes = self.nodes.lock().unwrap();
+        let data = match nodes.get(path) {
+            Some(Node::Dir) => return Err(FsError::IsADirectory),
+            Some(Node::File(_)) if options.create_new => return Err(FsError::AlreadyExists),
+            Some(Node::File(data)) => Arc::clone(data),
+            None if options.create || options.create_new => {
+                Self::check_parent(&nodes, path)?;
+                let data = Arc::new(Mutex::new(Vec::new()));
+                nodes.insert(path.to_path_buf(), Node::File(Arc::clone(&data)));
+                data
+            }
+            None => return Err(FsError::NotFound),
+        };
+        if options.truncate {
+            data.lock().unwrap().clear();
+        }
+        Ok(Box::new(MemFile {
+            data,
+            position: 0,
+            options: *options,
+        }))
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, FsError> {
+        if path.as_os_str().is_empty() {
+            return Ok(Metadata {
+                is_dir: true,
+                len: 0,
+            });
+        }
+        match self.nodes.lock().unwrap().get(path) {
+            Some(Node::Dir) => Ok(Metadata {
+                is_dir: true,
+                len: 0,
+            }),
+            Some(Node::File(data)) => Ok(Metadata {
+                is_dir: false,
+                len: data.lock().unwrap().len() as u64,
+            }),
+            None => Err(FsError::NotFound),
+        }
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<(), FsError> {
+        let mut nodes = self.nodes.lock().unwrap();
+        if path.as_os_str().is_empty() || nodes.contains_key(path) {
+            return Err(FsError::AlreadyExists);
+        }
+        Self::check_parent(&nodes, path)?;
+        nodes.insert(path.to_path_buf(), Node::Dir);
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<(), FsError> {
+        let mut nodes = self.nodes.lock().unwrap();
+        match nodes.get(path) {
+            Some(Node::File(_)) => {
+                nodes.remove(path);
+                Ok(())
+            }
+            Some(Node::Dir) => Err(FsError::IsADirectory),
+            None => Err(FsError::NotFound),
+        }
+    }
+}
+
+/// A file opened in a [`MemFs`].
+#[derive(Debug)]
+struct MemFile {
+    data: Arc<Mutex<Vec<u8>>>,
+    position: u64,
+    options: OpenOptions,
+}
+
+fn permission_denied() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        "the file wasn't opened for this",
+    )
+}
+
+impl Read for MemFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.options.read {
+            return Err(permission_denied());
+        }
+        let data = self.data.lock().unwrap();
+        let start = (self.position as usize).min(data.len());
+        let read = buf.len().min(data.len() - start);
+        buf[..read].copy_from_slice(&data[start..start + read]);
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Write for MemFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.options.write && !self.options.append {
+            return Err(permission_denied());
+        }
+        let mut data = self.data.lock().unwrap();
+        if self.options.append {
+            self.position = data.len() as u64;
+        }
+        let start = self.position as usize;
+        let end = start + buf.len();
+        if end > data.len() {
+            data.resize(end, 0);
+        }
+        data[start..end].copy_from_slice(buf);
+        self.position = end as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for MemFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(offset) => (0, offset as i64),
+            SeekFrom::Current(offset) => (self.position, offset),
+            SeekFrom::End(offset) => (self.data.lock().unwrap().len() as u64, offset),
+        };
+        let position = (base as i64)
+            .checked_add(offset)
+            .filter(|position| *position >= 0)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid seek"))?;
+        self.position = position as u64;
+        Ok(self.position)
+    }
+}
+
+impl VirtualFile for MemFile {
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.data.lock().unwrap().len() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_are_shared_between_handles() {
+        let fs = MemFs::new();
+        fs.write_file("dir/file", "hello").unwrap();
+        assert!(fs.metadata(Path::new("dir")).unwrap().is_dir);
+
+        let options = OpenOptions {
+            read: true,
+            append: true,
+            ..Default::default()
+        };
+        let mut file = fs.open(Path::new("dir/file"), &options).unwrap();
+        file.write_all(b" world").unwrap();
+        file.seek(SeekFrom::Start(6)).unwrap();
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "world");
+        assert_eq!(fs.read_file("dir/file").unwrap(), b"hello world");
+    }
+
+    #[test]
+    fn files_are_created_in_existing_directories() {
+        let fs = MemFs::new();
+        let options = OpenOptions {
+            write: true,
+            create: true,
+            ..Default::default()
+        };
+        assert!(matches!(
+            fs.open(Path::new("dir/file"), &options),
+            Err(FsError::NotFound)
+        ));
+        fs.create_dir(Path::new("dir")).unwrap();
+        fs.open(Path::new("dir/file"), &options).unwrap();
+        assert!(matches!(
+            fs.open(
+                Path::new("dir/file"),
+                &OpenOptions {
+                    create_new: true,
+                    ..options
+                }
+            ),
+            Err(FsError::AlreadyExists)
+        ));
+        fs.remove_file(Path::new("dir/file")).unwrap();
+        assert!(matches!(
+            fs.metadata(Path::new("dir/file")),
+            Err(FsError::NotFound)
+        ));
+    }
+}
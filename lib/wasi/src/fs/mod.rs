@@ -0,0 +1,102 @@
+//! The filesystems backing the preopened directories of WASI programs.
+
+mod host_fs;
+mod mem_fs;
+
+pub use self::host_fs::HostFs;
+pub use self::mem_fs::MemFs;
+
+use std::fmt;
+use std::io;
+use std::path::Path;
+use thiserror::Error;
+
+/// A filesystem the preopened directories of WASI programs can be backed by.
+///
+/// The paths given to a `FileSystem` are relative to its root and
+/// normalized: they are only made of file and directory names, the empty
+/// path being the root itself.
+pub trait FileSystem: fmt::Debug + Send + Sync {
+    /// Opens the file at `path`.
+    fn open(&self, path: &Path, options: &OpenOptions) -> Result<Box<dyn VirtualFile>, FsError>;
+
+    /// Returns the metadata of the file or directory at `path`.
+    fn metadata(&self, path: &Path) -> Result<Metadata, FsError>;
+
+    /// Creates the directory at `path`, whose parent must exist.
+    fn create_dir(&self, path: &Path) -> Result<(), FsError>;
+
+    /// Removes the file at `path`.
+    fn remove_file(&self, path: &Path) -> Result<(), FsError>;
+}
+
+/// A file opened in a [`FileSystem`].
+pub trait VirtualFile: io::Read + io::Write + io::Seek + fmt::Debug + Send {
+    /// Returns the size of the file, in bytes.
+    fn size(&self) -> io::Result<u64>;
+}
+
+/// How a file is opened, like [`std::fs::OpenOptions`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpenOptions {
+    /// Whether the file can be read.
+    pub read: bool,
+    /// Whether the file can be written.
+    pub write: bool,
+    /// Whether writes are appended to the end of the file.
+    pub append: bool,
+    /// Whether the file is truncated once opened.
+    pub truncate: bool,
+    /// Whether the file is created if it doesn't exist.
+    pub create: bool,
+    /// Whether the file is created, failing if it already exists.
+    pub create_new: bool,
+}
+
+/// The metadata of a file or a directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metadata {
+    /// Whether this is a directory.
+    pub is_dir: bool,
+    /// The size of the file, in bytes.
+    pub len: u64,
+}
+
+/// An error of a [`FileSystem`].
+#[derive(Error, Debug)]
+pub enum FsError {
+    /// The file or directory doesn't exist.
+    #[error("no such file or directory")]
+    NotFound,
+    /// The file or directory already exists.
+    #[error("the file or directory already exists")]
+    AlreadyExists,
+    /// A directory was expected.
+    #[error("not a directory")]
+    NotADirectory,
+    /// A file was expected.
+    #[error("is a directory")]
+    IsADirectory,
+    /// The operation isn't permitted, or the path leads outside of the
+    /// filesystem.
+    #[error("permission denied")]
+    PermissionDenied,
+    /// The operation was given invalid arguments.
+    #[error("invalid input")]
+    InvalidInput,
+    /// Another IO error.
+    #[error(transparent)]
+    Io(io::Error),
+}
+
+impl From<io::Error> for FsError {
+    fn from(error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::NotFound => Self::NotFound,
+            io::ErrorKind::AlreadyExists => Self::AlreadyExists,
+            io::ErrorKind::PermissionDenied => Self::PermissionDenied,
+            io::ErrorKind::InvalidInput => Self::InvalidInput,
+            _ => Self::Io(error),
+        }
+    }
+}
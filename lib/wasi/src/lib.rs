@@ -0,0 +1,309 @@
+//! The `wasmer-wasi` crate implements `wasi_snapshot_preview1`, the
+//! WebAssembly System Interface, for the modules running in Wasmer.
+//!
+//! The state of a program is built with [`WasiState::new`], into a
+//! [`WasiEnv`] providing the imports of the program:
+//!
+//! ```
+//! use wasmer::{Instance, Module, Store};
+//! use wasmer_wasi::{MemFs, WasiError, WasiState};
+//! # fn main() -> anyhow::Result<()> {
+//! let store = Store::default();
+//! let module = Module::new(
+//!     &store,
+//!     r#"(module
+//!         (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+//!         (memory (export "memory") 1)
+//!         (func (export "_start") (call $proc_exit (i32.const 3))))"#,
+//! )?;
+//!
+//! let wasi_env = WasiState::new("program")
+//!     .arg("--verbose")
+//!     .env("LOG", "debug")
+//!     .preopen("/data", MemFs::new())
+//!     .finalize()?;
+//! let instance = Instance::new(&module, &wasi_env.import_object(&store))?;
+//!
+//! let start = instance.lookup_function("_start").unwrap();
+//! let error = start.call(&[]).unwrap_err();
+//! assert!(matches!(error.downcast_ref(), Some(WasiError::Exit(3))));
+//! # Ok(())
+//! # }
+//! ```
+
+#![deny(missing_docs, trivial_numeric_casts, unused_extern_crates)]
+#![warn(unused_import_braces)]
+#![cfg_attr(
+    feature = "cargo-clippy",
+    allow(clippy::new_without_default, clippy::new_ret_no_self)
+)]
+#![cfg_attr(
+    feature = "cargo-clippy",
+    warn(
+        clippy::float_arithmetic,
+        clippy::mut_mut,
+        clippy::nonminimal_bool,
+        clippy::option_map_unwrap_or,
+        clippy::option_map_unwrap_or_else,
+        clippy::print_stdout,
+        clippy::unicode_not_nfc,
+        clippy::use_self
+    )
+)]
+
+mod fs;
+mod state;
+mod syscalls;
+pub mod types;
+
+pub use crate::fs::{FileSystem, FsError, HostFs, MemFs, Metadata, OpenOptions, VirtualFile};
+pub use crate::state::{WasiState, WasiStateBuilder, WasiStateCreationError};
+
+use crate::syscalls::*;
+use std::sync::{Arc, Mutex, MutexGuard};
+use thiserror::Error;
+use wasmer::{Exports, Function, ImportObject, LazyInit, Memory, Store, WasmerEnv};
+
+/// Version number of this crate.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The namespace of the imports of WASI programs.
+pub const WASI_NAMESPACE: &str = "wasi_snapshot_preview1";
+
+/// An error ending a WASI program, which the call into the program fails
+/// with, wrapped in a [`RuntimeError`](wasmer::RuntimeError).
+#[derive(Error, Debug)]
+pub enum WasiError {
+    /// The program called `proc_exit` with this exit code.
+    #[error("WASI exited with code: {0}")]
+    Exit(u32),
+}
+
+/// The environment of the WASI functions imported by a program.
+///
+/// Each instance needs its own environment, as it refers to the memory of
+/// the instance once instantiated.
+#[derive(WasmerEnv, Clone)]
+pub struct WasiEnv {
+    state: Arc<Mutex<WasiState>>,
+    #[wasmer(export)]
+    memory: LazyInit<Memory>,
+}
+
+impl WasiEnv {
+    pub(crate) fn new(state: WasiState) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(state)),
+            memory: LazyInit::new(),
+        }
+    }
+
+    pub(crate) fn state(&self) -> MutexGuard<'_, WasiState> {
+        self.state.lock().unwrap()
+    }
+
+    pub(crate) fn memory(&self) -> &Memory {
+        self.memory_ref()
+            .expect("WASI functions can't be called before the instance is initialized")
+    }
+
+    /// Creates the imports of the program, in the `wasi_snapshot_preview1`
+    /// namespace.
+    pub fn import_object(&self, store: &Store) -> ImportObject {
+        let mut import_object = ImportObject::new();
+        import_object.register(WASI_NAMESPACE, self.exports(store));
+        import_object
+    }
+
+    /// Creates the WASI functions, to be registered under
+    /// [`WASI_NAMESPACE`] alongside other imports.
+    pub fn exports(&self, store: &Store) -> Exports {
+        let functions = [
+            (
+                "args_get",
+                Function::new_native_with_env(store, self.clone(), args_get),
+            ),
+            (
+                "args_sizes_get",
+                Function::new_native_with_env(store, self.clone(), args_sizes_get),
+            ),
+            (
+                "environ_get",
+                Function::new_native_with_env(store, self.clone(), environ_get),
+            ),
+            (
+                "environ_sizes_get",
+                Function::new_native_with_env(store, self.clone(), environ_sizes_get),
+            ),
+            (
+                "clock_res_get",
+                Function::new_native_with_env(store, self.clone(), clock_res_get),
+            ),
+            (
+                "clock_time_get",
+                Function::new_native_with_env(store, self.clone(), clock_time_get),
+            ),
+            (
+                "fd_advise",
+                Function::new_native_with_env(store, self.clone(), fd_advise),
+            ),
+            (
+                "fd_allocate",
+                Function::new_native_with_env(store, self.clone(), fd_allocate),
+            ),
+            (
+                "fd_close",
+                Function::new_native_with_env(store, self.clone(), fd_close),
+            ),
+            (
+                "fd_datasync",
+                Function::new_native_with_env(store, self.clone(), fd_datasync),
+            ),
+            (
+                "fd_fdstat_get",
+                Function::new_native_with_env(store, self.clone(), fd_fdstat_get),
+            ),
+            (
+                "fd_fdstat_set_flags",
+                Function::new_native_with_env(store, self.clone(), fd_fdstat_set_flags),
+            ),
+            (
+                "fd_fdstat_set_rights",
+                Function::new_native_with_env(store, self.clone(), fd_fdstat_set_rights),
+            ),
+            (
+                "fd_filestat_get",
+                Function::new_native_with_env(store, self.clone(), fd_filestat_get),
+            ),
+            (
+                "fd_filestat_set_size",
+                Function::new_native_with_env(store, self.clone(), fd_filestat_set_size),
+            ),
+            (
+                "fd_filestat_set_times",
+                Function::new_native_with_env(store, self.clone(), fd_filestat_set_times),
+            ),
+            (
+                "fd_pread",
+                Function::new_native_with_env(store, self.clone(), fd_pread),
+            ),
+            (
+                "fd_prestat_get",
+                Function::new_native_with_env(store, self.clone(), fd_prestat_get),
+            ),
+            (
+                "fd_prestat_dir_name",
+                Function::new_native_with_env(store, self.clone(), fd_prestat_dir_name),
+            ),
+            (
+                "fd_pwrite",
+                Function::new_native_with_env(store, self.clone(), fd_pwrite),
+            ),
+            (
+                "fd_read",
+                Function::new_native_with_env(store, self.clone(), fd_read),
+            ),
+            (
+                "fd_readdir",
+                Function::new_native_with_env(store, self.clone(), fd_readdir),
+            ),
+            (
+                "fd_renumber",
+                Function::new_native_with_env(store, self.clone(), fd_renumber),
+            ),
+            (
+                "fd_seek",
+                Function::new_native_with_env(store, self.clone(), fd_seek),
+            ),
+            (
+                "fd_sync",
+                Function::new_native_with_env(store, self.clone(), fd_sync),
+            ),
+            (
+                "fd_tell",
+                Function::new_native_with_env(store, self.clone(), fd_tell),
+            ),
+            (
+                "fd_write",
+                Function::new_native_with_env(store, self.clone(), fd_write),
+            ),
+            (
+                "path_create_directory",
+                Function::new_native_with_env(store, self.clone(), path_create_directory),
+            ),
+            (
+                "path_filestat_get",
+                Function::new_native_with_env(store, self.clone(), path_filestat_get),
+            ),
+            (
+                "path_filestat_set_times",
+                Function::new_native_with_env(store, self.clone(), path_filestat_set_times),
+            ),
+            (
+                "path_link",
+                Function::new_native_with_env(store, self.clone(), path_link),
+            ),
+            (
+                "path_open",
+                Function::new_native_with_env(store, self.clone(), path_open),
+            ),
+            (
+                "path_readlink",
+                Function::new_native_with_env(store, self.clone(), path_readlink),
+            ),
+            (
+                "path_remove_directory",
+                Function::new_native_with_env(store, self.clone(), path_remove_directory),
+            ),
+            (
+                "path_rename",
+                Function::new_native_with_env(store, self.clone(), path_rename),
+            ),
+            (
+                "path_symlink",
+                Function::new_native_with_env(store, self.clone(), path_symlink),
+            ),
+            (
+                "path_unlink_file",
+                Function::new_native_with_env(store, self.clone(), path_unlink_file),
+            ),
+            (
+                "poll_oneoff",
+                Function::new_native_with_env(store, self.clone(), poll_oneoff),
+            ),
+            (
+                "proc_exit",
+                Function::new_native_with_env(store, self.clone(), proc_exit),
+            ),
+            (
+                "proc_raise",
+                Function::new_native_with_env(store, self.clone(), proc_raise),
+            ),
+            (
+                "random_get",
+                Function::new_native_with_env(store, self.clone(), random_get),
+            ),
+            (
+                "sched_yield",
+                Function::new_native_with_env(store, self.clone(), sched_yield),
+            ),
+            (
+                "sock_recv",
+                Function::new_native_with_env(store, self.clone(), sock_recv),
+            ),
+            (
+                "sock_send",
+                Function::new_native_with_env(store, self.clone(), sock_send),
+            ),
+            (
+                "sock_shutdown",
+                Function::new_native_with_env(store, self.clone(), sock_shutdown),
+            ),
+        ];
+        let mut exports = Exports::new();
+        for (name, function) in functions {
+            exports.insert(name, function);
+        }
+        exports
+    }
+}
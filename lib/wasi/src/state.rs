@@ -0,0 +1,285 @@
+//! The state of a WASI program: its arguments, its environment variables
+//! and its file descriptors.
+
+use crate::fs::{FileSystem, FsError, VirtualFile};
+use crate::types::*;
+use crate::WasiEnv;
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use thiserror::Error;
+
+/// An error while creating a [`WasiState`].
+#[derive(Error, Debug)]
+pub enum WasiStateCreationError {
+    /// An argument contains a nul byte.
+    #[error("invalid argument `{0}`")]
+    InvalidArgument(String),
+    /// An environment variable has an invalid name, or contains a nul byte.
+    #[error("invalid environment variable `{0}`")]
+    InvalidEnvironmentVariable(String),
+    /// A preopened directory has an empty path.
+    #[error("invalid preopened directory `{0}`")]
+    InvalidPreopen(String),
+}
+
+/// The state of a WASI program.
+///
+/// It's created with [`WasiState::new`], which returns a builder.
+pub struct WasiState {
+    pub(crate) args: Vec<String>,
+    /// The environment variables, as `KEY=value`.
+    pub(crate) envs: Vec<String>,
+    pub(crate) fds: BTreeMap<u32, Fd>,
+    /// When the program started, which the monotonic clocks count from.
+    pub(crate) start: Instant,
+}
+
+/// An open file descriptor.
+pub(crate) enum Fd {
+    Stdin(Box<dyn Read + Send>),
+    Stdout(Box<dyn Write + Send>),
+    File {
+        file: Box<dyn VirtualFile>,
+        append: bool,
+    },
+    Dir {
+        fs: Arc<dyn FileSystem>,
+        /// The path of the directory in `fs`.
+        path: PathBuf,
+        /// The path the program knows a preopened directory by.
+        preopen: Option<String>,
+    },
+}
+
+impl WasiState {
+    /// Starts building the state of the program named `program_name`.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(program_name: impl Into<String>) -> WasiStateBuilder {
+        WasiStateBuilder {
+            args: vec![program_name.into()],
+            envs: Vec::new(),
+            preopens: Vec::new(),
+            stdin: None,
+            stdout: None,
+            stderr: None,
+        }
+    }
+
+    pub(crate) fn fd(&mut self, fd: u32) -> Result<&mut Fd, Errno> {
+        self.fds.get_mut(&fd).ok_or(ERRNO_BADF)
+    }
+
+    /// Opens `fd` under the lowest free number.
+    pub(crate) fn insert_fd(&mut self, fd: Fd) -> u32 {
+        let number = (0..)
+            .zip(self.fds.keys())
+            .find(|(free, used)| free != *used)
+            .map_or(self.fds.len() as u32, |(free, _)| free);
+        self.fds.insert(number, fd);
+        number
+    }
+}
+
+impl Fd {
+    pub(crate) fn read(&mut self, buf: &mut [u8]) -> Result<usize, Errno> {
+        match self {
+            Self::Stdin(reader) => reader.read(buf).map_err(io_errno),
+            Self::File { file, .. } => file.read(buf).map_err(io_errno),
+            Self::Stdout(_) => Err(ERRNO_BADF),
+            Self::Dir { .. } => Err(ERRNO_ISDIR),
+        }
+    }
+
+    pub(crate) fn write(&mut self, buf: &[u8]) -> Result<(), Errno> {
+        match self {
+            Self::Stdout(writer) => writer.write_all(buf).map_err(io_errno),
+            Self::File { file, .. } => file.write_all(buf).map_err(io_errno),
+            Self::Stdin(_) => Err(ERRNO_BADF),
+            Self::Dir { .. } => Err(ERRNO_ISDIR),
+        }
+    }
+
+    pub(crate) fn seek(&mut self, pos: io::SeekFrom) -> Result<u64, Errno> {
+        match self {
+            Self::File { file, .. } => file.seek(pos).map_err(io_errno),
+            Self::Stdin(_) | Self::Stdout(_) => Err(ERRNO_SPIPE),
+            Self::Dir { .. } => Err(ERRNO_ISDIR),
+        }
+    }
+}
+
+/// Resolves `path`, relative to the directory at `dir` in a filesystem,
+/// without leaving the filesystem.
+pub(crate) fn resolve_path(dir: &Path, path: &str) -> Result<PathBuf, Errno> {
+    let mut resolved = dir.to_path_buf();
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(name) => resolved.push(name),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !resolved.pop() {
+                    return Err(ERRNO_NOTCAPABLE);
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return Err(ERRNO_NOTCAPABLE),
+        }
+    }
+    Ok(resolved)
+}
+
+pub(crate) fn fs_errno(error: FsError) -> Errno {
+    match error {
+        FsError::NotFound => ERRNO_NOENT,
+        FsError::AlreadyExists => ERRNO_EXIST,
+        FsError::NotADirectory => ERRNO_NOTDIR,
+        FsError::IsADirectory => ERRNO_ISDIR,
+        FsError::PermissionDenied => ERRNO_ACCES,
+        FsError::InvalidInput => ERRNO_INVAL,
+        FsError::Io(_) => ERRNO_IO,
+    }
+}
+
+pub(crate) fn io_errno(error: io::Error) -> Errno {
+    fs_errno(error.into())
+}
+
+/// A builder of a [`WasiState`], created with [`WasiState::new`].
+pub struct WasiStateBuilder {
+    args: Vec<String>,
+    envs: Vec<(String, String)>,
+    preopens: Vec<(String, Arc<dyn FileSystem>)>,
+    stdin: Option<Box<dyn Read + Send>>,
+    stdout: Option<Box<dyn Write + Send>>,
+    stderr: Option<Box<dyn Write + Send>>,
+}
+
+impl WasiStateBuilder {
+    /// Adds an argument.
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Adds arguments.
+    pub fn args<I>(mut self, args: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Adds an environment variable.
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.envs.push((key.into(), value.into()));
+        self
+    }
+
+    /// Adds environment variables.
+    pub fn envs<I, K, V>(mut self, envs: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.envs.extend(
+            envs.into_iter()
+                .map(|(key, value)| (key.into(), value.into())),
+        );
+        self
+    }
+
+    /// Preopens the root of `fs` as the directory at `path` for the
+    /// program, which can't access anything outside of it.
+    pub fn preopen(mut self, path: impl Into<String>, fs: impl FileSystem + 'static) -> Self {
+        self.preopens.push((path.into(), Arc::new(fs)));
+        self
+    }
+
+    /// Sets the standard input of the program, the one of the host by default.
+    pub fn stdin(mut self, stdin: Box<dyn Read + Send>) -> Self {
+        self.stdin = Some(stdin);
+        self
+    }
+
+    /// Sets the standard output of the program, the one of the host by default.
+    pub fn stdout(mut self, stdout: Box<dyn Write + Send>) -> Self {
+        self.stdout = Some(stdout);
+        self
+    }
+
+    /// Sets the standard error of the program, the one of the host by default.
+    pub fn stderr(mut self, stderr: Box<dyn Write + Send>) -> Self {
+        self.stderr = Some(stderr);
+        self
+    }
+
+    /// Creates the state, in the environment to run the program in.
+    pub fn finalize(self) -> Result<WasiEnv, WasiStateCreationError> {
+        if let Some(arg) = self.args.iter().find(|arg| arg.contains('\0')) {
+            return Err(WasiStateCreationError::InvalidArgument(arg.clone()));
+        }
+        let envs = self
+            .envs
+            .into_iter()
+            .map(|(key, value)| {
+                let env = format!("{}={}", key, value);
+                if key.is_empty() || key.contains('=') || env.contains('\0') {
+                    Err(WasiStateCreationError::InvalidEnvironmentVariable(env))
+                } else {
+                    Ok(env)
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut fds = BTreeMap::new();
+        fds.insert(
+            0,
+            Fd::Stdin(self.stdin.unwrap_or_else(|| Box::new(io::stdin()))),
+        );
+        fds.insert(
+            1,
+            Fd::Stdout(self.stdout.unwrap_or_else(|| Box::new(io::stdout()))),
+        );
+        fds.insert(
+            2,
+            Fd::Stdout(self.stderr.unwrap_or_else(|| Box::new(io::stderr()))),
+        );
+        for (path, fs) in self.preopens {
+            if path.is_empty() {
+                return Err(WasiStateCreationError::InvalidPreopen(path));
+            }
+            let fd = Fd::Dir {
+                fs,
+                path: PathBuf::new(),
+                preopen: Some(path),
+            };
+            fds.insert(fds.len() as u32, fd);
+        }
+
+        Ok(WasiEnv::new(WasiState {
+            args: self.args,
+            envs,
+            fds,
+            start: Instant::now(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_are_resolved_inside_the_filesystem() {
+        let dir = Path::new("a/b");
+        assert_eq!(resolve_path(dir, "c/./d"), Ok(PathBuf::from("a/b/c/d")));
+        assert_eq!(resolve_path(dir, "../../c"), Ok(PathBuf::from("c")));
+        assert_eq!(resolve_path(dir, "../../../c"), Err(ERRNO_NOTCAPABLE));
+        assert_eq!(resolve_path(dir, "/etc/passwd"), Err(ERRNO_NOTCAPABLE));
+    }
+}
@@ -0,0 +1,480 @@
+//! The functions of `wasi_snapshot_preview1`.
+//!
+//! They return an [`Errno`] instead of failing, except for `proc_exit`.
+//! Those that aren't supported return `ERRNO_NOSYS`.
+
+use crate::fs::OpenOptions;
+use crate::state::{fs_errno, resolve_path, Fd};
+use crate::types::*;
+use crate::{WasiEnv, WasiError};
+use std::io::SeekFrom;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use wasmer::{Array, Memory, ValueType, WasmCell, WasmPtr};
+
+/// Returns the error code of `$expr` if it's an error.
+macro_rules! wasi_try {
+    ($expr:expr) => {
+        match $expr {
+            Ok(value) => value,
+            Err(errno) => return errno,
+        }
+    };
+}
+
+fn deref<T: Copy + ValueType>(memory: &Memory, ptr: WasmPtr<T>) -> Result<WasmCell<'_, T>, Errno> {
+    ptr.deref(memory).ok_or(ERRNO_FAULT)
+}
+
+fn deref_array<T: Copy + ValueType>(
+    memory: &Memory,
+    ptr: WasmPtr<T, Array>,
+    len: u32,
+) -> Result<Vec<WasmCell<'_, T>>, Errno> {
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+    ptr.deref(memory, 0, len).ok_or(ERRNO_FAULT)
+}
+
+fn write_bytes(memory: &Memory, ptr: WasmPtr<u8, Array>, bytes: &[u8]) -> Result<(), Errno> {
+    let cells = deref_array(memory, ptr, bytes.len() as u32)?;
+    for (cell, byte) in cells.iter().zip(bytes) {
+        cell.set(*byte);
+    }
+    Ok(())
+}
+
+/// Writes `strings` as nul-terminated strings to `buf`, and pointers to
+/// them to `ptrs`.
+fn write_strings(
+    memory: &Memory,
+    strings: &[String],
+    ptrs: WasmPtr<u32, Array>,
+    buf: WasmPtr<u8, Array>,
+) -> Errno {
+    let ptrs = wasi_try!(deref_array(memory, ptrs, strings.len() as u32));
+    let mut offset = buf.offset();
+    for (ptr, string) in ptrs.iter().zip(strings) {
+        ptr.set(offset);
+        wasi_try!(write_bytes(memory, WasmPtr::new(offset), string.as_bytes()));
+        let end = wasi_try!(offset
+            .checked_add(string.len() as u32)
+            .ok_or(ERRNO_OVERFLOW));
+        wasi_try!(write_bytes(memory, WasmPtr::new(end), &[0]));
+        offset = wasi_try!(end.checked_add(1).ok_or(ERRNO_OVERFLOW));
+    }
+    ERRNO_SUCCESS
+}
+
+/// Writes how many `strings` there are, and the size of their buffer.
+fn write_strings_sizes(
+    memory: &Memory,
+    strings: &[String],
+    count: WasmPtr<u32>,
+    buf_size: WasmPtr<u32>,
+) -> Errno {
+    let size = strings.iter().map(|string| string.len() as u32 + 1).sum();
+    wasi_try!(deref(memory, count)).set(strings.len() as u32);
+    wasi_try!(deref(memory, buf_size)).set(size);
+    ERRNO_SUCCESS
+}
+
+pub(crate) fn args_get(
+    env: &WasiEnv,
+    argv: WasmPtr<u32, Array>,
+    argv_buf: WasmPtr<u8, Array>,
+) -> Errno {
+    write_strings(env.memory(), &env.state().args, argv, argv_buf)
+}
+
+pub(crate) fn args_sizes_get(
+    env: &WasiEnv,
+    argc: WasmPtr<u32>,
+    argv_buf_size: WasmPtr<u32>,
+) -> Errno {
+    write_strings_sizes(env.memory(), &env.state().args, argc, argv_buf_size)
+}
+
+pub(crate) fn environ_get(
+    env: &WasiEnv,
+    environ: WasmPtr<u32, Array>,
+    environ_buf: WasmPtr<u8, Array>,
+) -> Errno {
+    write_strings(env.memory(), &env.state().envs, environ, environ_buf)
+}
+
+pub(crate) fn environ_sizes_get(
+    env: &WasiEnv,
+    environc: WasmPtr<u32>,
+    environ_buf_size: WasmPtr<u32>,
+) -> Errno {
+    write_strings_sizes(env.memory(), &env.state().envs, environc, environ_buf_size)
+}
+
+pub(crate) fn clock_res_get(env: &WasiEnv, clock_id: u32, resolution: WasmPtr<u64>) -> Errno {
+    match clock_id {
+        CLOCK_REALTIME | CLOCK_MONOTONIC | CLOCK_PROCESS_CPUTIME_ID | CLOCK_THREAD_CPUTIME_ID => {
+            wasi_try!(deref(env.memory(), resolution)).set(1);
+            ERRNO_SUCCESS
+        }
+        _ => ERRNO_INVAL,
+    }
+}
+
+/// The time of `clock_id`, in nanoseconds.
+///
+/// The CPU time clocks are approximated by the time elapsed since the
+/// program started.
+pub(crate) fn clock_time_get(
+    env: &WasiEnv,
+    clock_id: u32,
+    _precision: u64,
+    time: WasmPtr<u64>,
+) -> Errno {
+    let now = match clock_id {
+        CLOCK_REALTIME => wasi_try!(SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| ERRNO_IO)),
+        CLOCK_MONOTONIC | CLOCK_PROCESS_CPUTIME_ID | CLOCK_THREAD_CPUTIME_ID => {
+            env.state().start.elapsed()
+        }
+        _ => return ERRNO_INVAL,
+    };
+    wasi_try!(deref(env.memory(), time)).set(now.as_nanos() as u64);
+    ERRNO_SUCCESS
+}
+
+pub(crate) fn random_get(env: &WasiEnv, buf: WasmPtr<u8, Array>, buf_len: u32) -> Errno {
+    let cells = wasi_try!(deref_array(env.memory(), buf, buf_len));
+    let mut bytes = vec![0; cells.len()];
+    wasi_try!(getrandom::getrandom(&mut bytes).map_err(|_| ERRNO_IO));
+    for (cell, byte) in cells.iter().zip(bytes) {
+        cell.set(byte);
+    }
+    ERRNO_SUCCESS
+}
+
+pub(crate) fn fd_read(
+    env: &WasiEnv,
+    fd: u32,
+    iovs: WasmPtr<Iovec, Array>,
+    iovs_len: u32,
+    nread: WasmPtr<u32>,
+) -> Errno {
+    let memory = env.memory();
+    let mut state = env.state();
+    let fd = wasi_try!(state.fd(fd));
+    let mut read = 0;
+    for iov in wasi_try!(deref_array(memory, iovs, iovs_len)) {
+        let iov = iov.get();
+        let cells = wasi_try!(deref_array(
+            memory,
+            WasmPtr::<u8, Array>::new(iov.buf),
+            iov.buf_len
+        ));
+        let mut buf = vec![0; cells.len()];
+        let len = wasi_try!(fd.read(&mut buf));
+        for (cell, byte) in cells.iter().zip(&buf[..len]) {
+            cell.set(*byte);
+        }
+        read += len as u32;
+        if len < buf.len() {
+            break;
+        }
+    }
+    wasi_try!(deref(memory, nread)).set(read);
+    ERRNO_SUCCESS
+}
+
+pub(crate) fn fd_write(
+    env: &WasiEnv,
+    fd: u32,
+    iovs: WasmPtr<Iovec, Array>,
+    iovs_len: u32,
+    nwritten: WasmPtr<u32>,
+) -> Errno {
+    let memory = env.memory();
+    let mut state = env.state();
+    let fd = wasi_try!(state.fd(fd));
+    let mut written = 0;
+    for iov in wasi_try!(deref_array(memory, iovs, iovs_len)) {
+        let iov = iov.get();
+        let cells = wasi_try!(deref_array(
+            memory,
+            WasmPtr::<u8, Array>::new(iov.buf),
+            iov.buf_len
+        ));
+        let buf = cells.iter().map(|cell| cell.get()).collect::<Vec<_>>();
+        wasi_try!(fd.write(&buf));
+        written += iov.buf_len;
+    }
+    wasi_try!(deref(memory, nwritten)).set(written);
+    ERRNO_SUCCESS
+}
+
+pub(crate) fn fd_seek(
+    env: &WasiEnv,
+    fd: u32,
+    offset: i64,
+    whence: u8,
+    newoffset: WasmPtr<u64>,
+) -> Errno {
+    let pos = match whence {
+        WHENCE_SET if offset >= 0 => SeekFrom::Start(offset as u64),
+        WHENCE_CUR => SeekFrom::Current(offset),
+        WHENCE_END => SeekFrom::End(offset),
+        _ => return ERRNO_INVAL,
+    };
+    let position = wasi_try!(wasi_try!(env.state().fd(fd)).seek(pos));
+    wasi_try!(deref(env.memory(), newoffset)).set(position);
+    ERRNO_SUCCESS
+}
+
+pub(crate) fn fd_tell(env: &WasiEnv, fd: u32, offset: WasmPtr<u64>) -> Errno {
+    let position = wasi_try!(wasi_try!(env.state().fd(fd)).seek(SeekFrom::Current(0)));
+    wasi_try!(deref(env.memory(), offset)).set(position);
+    ERRNO_SUCCESS
+}
+
+pub(crate) fn fd_close(env: &WasiEnv, fd: u32) -> Errno {
+    match env.state().fds.remove(&fd) {
+        Some(_) => ERRNO_SUCCESS,
+        None => ERRNO_BADF,
+    }
+}
+
+pub(crate) fn fd_fdstat_get(env: &WasiEnv, fd: u32, buf: WasmPtr<Fdstat>) -> Errno {
+    let (filetype, flags, rights) = match wasi_try!(env.state().fd(fd)) {
+        // The standard streams aren't seekable, which tells programs
+        // they are terminals.
+        Fd::Stdin(_) | Fd::Stdout(_) => (
+            FILETYPE_CHARACTER_DEVICE,
+            0,
+            RIGHTS_ALL & !(RIGHTS_FD_SEEK | RIGHTS_FD_TELL),
+        ),
+        Fd::File { append, .. } => (
+            FILETYPE_REGULAR_FILE,
+            if *append { FDFLAGS_APPEND } else { 0 },
+            RIGHTS_ALL,
+        ),
+        Fd::Dir { .. } => (FILETYPE_DIRECTORY, 0, RIGHTS_ALL),
+    };
+    wasi_try!(deref(env.memory(), buf)).set(Fdstat {
+        fs_filetype: filetype,
+        _padding0: 0,
+        fs_flags: flags,
+        _padding1: 0,
+        fs_rights_base: rights,
+        fs_rights_inheriting: rights,
+    });
+    ERRNO_SUCCESS
+}
+
+pub(crate) fn fd_filestat_get(env: &WasiEnv, fd: u32, buf: WasmPtr<Filestat>) -> Errno {
+    let (filetype, size) = match wasi_try!(env.state().fd(fd)) {
+        Fd::Stdin(_) | Fd::Stdout(_) => (FILETYPE_CHARACTER_DEVICE, 0),
+        Fd::File { file, .. } => (
+            FILETYPE_REGULAR_FILE,
+            wasi_try!(file.size().map_err(crate::state::io_errno)),
+        ),
+        Fd::Dir { .. } => (FILETYPE_DIRECTORY, 0),
+    };
+    wasi_try!(deref(env.memory(), buf)).set(Filestat {
+        dev: 0,
+        ino: 0,
+        filetype,
+        _padding: [0; 7],
+        nlink: 1,
+        size,
+        atim: 0,
+        mtim: 0,
+        ctim: 0,
+    });
+    ERRNO_SUCCESS
+}
+
+pub(crate) fn fd_prestat_get(env: &WasiEnv, fd: u32, buf: WasmPtr<Prestat>) -> Errno {
+    let name_len = match wasi_try!(env.state().fd(fd)) {
+        Fd::Dir {
+            preopen: Some(name),
+            ..
+        } => name.len() as u32,
+        _ => return ERRNO_BADF,
+    };
+    wasi_try!(deref(env.memory(), buf)).set(Prestat {
+        tag: PREOPENTYPE_DIR,
+        _padding: [0; 3],
+        pr_name_len: name_len,
+    });
+    ERRNO_SUCCESS
+}
+
+pub(crate) fn fd_prestat_dir_name(
+    env: &WasiEnv,
+    fd: u32,
+    path: WasmPtr<u8, Array>,
+    path_len: u32,
+) -> Errno {
+    let mut state = env.state();
+    let name = match wasi_try!(state.fd(fd)) {
+        Fd::Dir {
+            preopen: Some(name),
+            ..
+        } => name,
+        _ => return ERRNO_BADF,
+    };
+    if (path_len as usize) < name.len() {
+        return ERRNO_NAMETOOLONG;
+    }
+    wasi_try!(write_bytes(env.memory(), path, name.as_bytes()));
+    ERRNO_SUCCESS
+}
+
+/// Opens the file or directory at `path`, relative to the directory `fd`.
+///
+/// Files are opened for reading or writing depending on whether
+/// `fs_rights_base` has the rights to read or write them.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn path_open(
+    env: &WasiEnv,
+    fd: u32,
+    _dirflags: u32,
+    path: WasmPtr<u8, Array>,
+    path_len: u32,
+    oflags: u16,
+    fs_rights_base: u64,
+    _fs_rights_inheriting: u64,
+    fdflags: u16,
+    opened_fd: WasmPtr<u32>,
+) -> Errno {
+    let memory = env.memory();
+    let path = wasi_try!(path.get_utf8_string(memory, path_len).ok_or(ERRNO_INVAL));
+    let mut state = env.state();
+    let (fs, path) = match wasi_try!(state.fd(fd)) {
+        Fd::Dir { fs, path: dir, .. } => (Arc::clone(fs), wasi_try!(resolve_path(dir, &path))),
+        _ => return ERRNO_NOTDIR,
+    };
+
+    let is_dir = match fs.metadata(&path) {
+        Ok(metadata) => metadata.is_dir,
+        Err(_) if oflags & OFLAGS_DIRECTORY == 0 => false,
+        Err(e) => return fs_errno(e),
+    };
+    let fd = if is_dir {
+        if oflags & (OFLAGS_CREAT | OFLAGS_TRUNC) != 0 {
+            return ERRNO_ISDIR;
+        }
+        Fd::Dir {
+            fs,
+            path,
+            preopen: None,
+        }
+    } else if oflags & OFLAGS_DIRECTORY != 0 {
+        return ERRNO_NOTDIR;
+    } else {
+        let append = fdflags & FDFLAGS_APPEND != 0;
+        let write = fs_rights_base & RIGHTS_FD_WRITE != 0;
+        let options = OpenOptions {
+            read: fs_rights_base & RIGHTS_FD_READ != 0 || !write,
+            write,
+            append,
+            truncate: oflags & OFLAGS_TRUNC != 0,
+            create: oflags & OFLAGS_CREAT != 0,
+            create_new: oflags & OFLAGS_CREAT != 0 && oflags & OFLAGS_EXCL != 0,
+        };
+        let file = wasi_try!(fs.open(&path, &options).map_err(fs_errno));
+        Fd::File { file, append }
+    };
+    wasi_try!(deref(memory, opened_fd)).set(state.insert_fd(fd));
+    ERRNO_SUCCESS
+}
+
+pub(crate) fn path_create_directory(
+    env: &WasiEnv,
+    fd: u32,
+    path: WasmPtr<u8, Array>,
+    path_len: u32,
+) -> Errno {
+    let path = wasi_try!(path
+        .get_utf8_string(env.memory(), path_len)
+        .ok_or(ERRNO_INVAL));
+    match wasi_try!(env.state().fd(fd)) {
+        Fd::Dir { fs, path: dir, .. } => {
+            wasi_try!(fs
+                .create_dir(&wasi_try!(resolve_path(dir, &path)))
+                .map_err(fs_errno));
+            ERRNO_SUCCESS
+        }
+        _ => ERRNO_NOTDIR,
+    }
+}
+
+pub(crate) fn path_unlink_file(
+    env: &WasiEnv,
+    fd: u32,
+    path: WasmPtr<u8, Array>,
+    path_len: u32,
+) -> Errno {
+    let path = wasi_try!(path
+        .get_utf8_string(env.memory(), path_len)
+        .ok_or(ERRNO_INVAL));
+    match wasi_try!(env.state().fd(fd)) {
+        Fd::Dir { fs, path: dir, .. } => {
+            wasi_try!(fs
+                .remove_file(&wasi_try!(resolve_path(dir, &path)))
+                .map_err(fs_errno));
+            ERRNO_SUCCESS
+        }
+        _ => ERRNO_NOTDIR,
+    }
+}
+
+/// Terminates the program, by failing with [`WasiError::Exit`].
+pub(crate) fn proc_exit(_env: &WasiEnv, code: u32) -> Result<(), WasiError> {
+    Err(WasiError::Exit(code))
+}
+
+pub(crate) fn sched_yield(_env: &WasiEnv) -> Errno {
+    std::thread::yield_now();
+    ERRNO_SUCCESS
+}
+
+/// Defines functions with the given parameters, returning `ERRNO_NOSYS`.
+macro_rules! unsupported {
+    ($($name:ident($($param:ty),*);)*) => {
+        $(
+            #[allow(clippy::too_many_arguments)]
+            pub(crate) fn $name(_env: &WasiEnv, $(_: $param),*) -> Errno {
+                ERRNO_NOSYS
+            }
+        )*
+    };
+}
+
+unsupported! {
+    fd_advise(u32, u64, u64, u32);
+    fd_allocate(u32, u64, u64);
+    fd_datasync(u32);
+    fd_fdstat_set_flags(u32, u32);
+    fd_fdstat_set_rights(u32, u64, u64);
+    fd_filestat_set_size(u32, u64);
+    fd_filestat_set_times(u32, u64, u64, u32);
+    fd_pread(u32, u32, u32, u64, u32);
+    fd_pwrite(u32, u32, u32, u64, u32);
+    fd_readdir(u32, u32, u32, u64, u32);
+    fd_renumber(u32, u32);
+    fd_sync(u32);
+    path_filestat_get(u32, u32, u32, u32, u32);
+    path_filestat_set_times(u32, u32, u32, u32, u64, u64, u32);
+    path_link(u32, u32, u32, u32, u32, u32, u32);
+    path_readlink(u32, u32, u32, u32, u32, u32);
+    path_remove_directory(u32, u32, u32);
+    path_rename(u32, u32, u32, u32, u32, u32);
+    path_symlink(u32, u32, u32, u32, u32);
+    poll_oneoff(u32, u32, u32, u32);
+    proc_raise(u32);
+    sock_recv(u32, u32, u32, u32, u32, u32);
+    sock_send(u32, u32, u32, u32, u32);
+    sock_shutdown(u32, u32);
+}
@@ -0,0 +1,106 @@
+//! The types and constants of `wasi_snapshot_preview1`, laid out as in the
+//! memory of WebAssembly programs.
+
+#![allow(missing_docs)]
+
+use wasmer::ValueType;
+
+/// An error code returned by a WASI function.
+pub type Errno = u16;
+
+pub const ERRNO_SUCCESS: Errno = 0;
+pub const ERRNO_ACCES: Errno = 2;
+pub const ERRNO_BADF: Errno = 8;
+pub const ERRNO_EXIST: Errno = 20;
+pub const ERRNO_FAULT: Errno = 21;
+pub const ERRNO_INVAL: Errno = 28;
+pub const ERRNO_IO: Errno = 29;
+pub const ERRNO_ISDIR: Errno = 31;
+pub const ERRNO_NAMETOOLONG: Errno = 37;
+pub const ERRNO_NOENT: Errno = 44;
+pub const ERRNO_NOSYS: Errno = 52;
+pub const ERRNO_NOTDIR: Errno = 54;
+pub const ERRNO_OVERFLOW: Errno = 61;
+pub const ERRNO_SPIPE: Errno = 70;
+pub const ERRNO_NOTCAPABLE: Errno = 76;
+
+pub const CLOCK_REALTIME: u32 = 0;
+pub const CLOCK_MONOTONIC: u32 = 1;
+pub const CLOCK_PROCESS_CPUTIME_ID: u32 = 2;
+pub const CLOCK_THREAD_CPUTIME_ID: u32 = 3;
+
+pub const FILETYPE_CHARACTER_DEVICE: u8 = 2;
+pub const FILETYPE_DIRECTORY: u8 = 3;
+pub const FILETYPE_REGULAR_FILE: u8 = 4;
+
+pub const FDFLAGS_APPEND: u16 = 1 << 0;
+
+pub const OFLAGS_CREAT: u16 = 1 << 0;
+pub const OFLAGS_DIRECTORY: u16 = 1 << 1;
+pub const OFLAGS_EXCL: u16 = 1 << 2;
+pub const OFLAGS_TRUNC: u16 = 1 << 3;
+
+pub const RIGHTS_FD_READ: u64 = 1 << 1;
+pub const RIGHTS_FD_SEEK: u64 = 1 << 2;
+pub const RIGHTS_FD_TELL: u64 = 1 << 5;
+pub const RIGHTS_FD_WRITE: u64 = 1 << 6;
+/// All the rights defined by `wasi_snapshot_preview1`.
+pub const RIGHTS_ALL: u64 = (1 << 29) - 1;
+
+pub const WHENCE_SET: u8 = 0;
+pub const WHENCE_CUR: u8 = 1;
+pub const WHENCE_END: u8 = 2;
+
+pub const PREOPENTYPE_DIR: u8 = 0;
+
+/// A buffer of the program, as `iovec` and `ciovec`.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct Iovec {
+    pub buf: u32,
+    pub buf_len: u32,
+}
+
+unsafe impl ValueType for Iovec {}
+
+/// The attributes of a file descriptor, as `fdstat`.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct Fdstat {
+    pub fs_filetype: u8,
+    pub _padding0: u8,
+    pub fs_flags: u16,
+    pub _padding1: u32,
+    pub fs_rights_base: u64,
+    pub fs_rights_inheriting: u64,
+}
+
+unsafe impl ValueType for Fdstat {}
+
+/// The attributes of a file, as `filestat`.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct Filestat {
+    pub dev: u64,
+    pub ino: u64,
+    pub filetype: u8,
+    pub _padding: [u8; 7],
+    pub nlink: u64,
+    pub size: u64,
+    pub atim: u64,
+    pub mtim: u64,
+    pub ctim: u64,
+}
+
+unsafe impl ValueType for Filestat {}
+
+/// The description of a preopened directory, as `prestat`.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct Prestat {
+    pub tag: u8,
+    pub _padding: [u8; 3],
+    pub pr_name_len: u32,
+}
+
+unsafe impl ValueType for Prestat {}
@@ -0,0 +1,442 @@
+use anyhow::Result;
+use std::convert::TryInto;
+use std::io::{self, Cursor, Write};
+use std::sync::{Arc, Mutex};
+use wasmer::*;
+use wasmer_wasi::types::*;
+use wasmer_wasi::{FileSystem, HostFs, MemFs, WasiEnv, WasiError, WasiState};
+
+/// A module re-exporting the WASI functions it imports, so that the tests
+/// can call them directly.
+const WASI_FUNCTIONS: &str = r#"
+(module
+  (func (export "args_get") (import "wasi_snapshot_preview1" "args_get") (param i32 i32) (result i32))
+  (func (export "args_sizes_get") (import "wasi_snapshot_preview1" "args_sizes_get") (param i32 i32) (result i32))
+  (func (export "environ_get") (import "wasi_snapshot_preview1" "environ_get") (param i32 i32) (result i32))
+  (func (export "environ_sizes_get") (import "wasi_snapshot_preview1" "environ_sizes_get") (param i32 i32) (result i32))
+  (func (export "clock_time_get") (import "wasi_snapshot_preview1" "clock_time_get") (param i32 i64 i32) (result i32))
+  (func (export "random_get") (import "wasi_snapshot_preview1" "random_get") (param i32 i32) (result i32))
+  (func (export "fd_read") (import "wasi_snapshot_preview1" "fd_read") (param i32 i32 i32 i32) (result i32))
+  (func (export "fd_write") (import "wasi_snapshot_preview1" "fd_write") (param i32 i32 i32 i32) (result i32))
+  (func (export "fd_seek") (import "wasi_snapshot_preview1" "fd_seek") (param i32 i64 i32 i32) (result i32))
+  (func (export "fd_tell") (import "wasi_snapshot_preview1" "fd_tell") (param i32 i32) (result i32))
+  (func (export "fd_close") (import "wasi_snapshot_preview1" "fd_close") (param i32) (result i32))
+  (func (export "fd_filestat_get") (import "wasi_snapshot_preview1" "fd_filestat_get") (param i32 i32) (result i32))
+  (func (export "fd_prestat_get") (import "wasi_snapshot_preview1" "fd_prestat_get") (param i32 i32) (result i32))
+  (func (export "fd_prestat_dir_name") (import "wasi_snapshot_preview1" "fd_prestat_dir_name") (param i32 i32 i32) (result i32))
+  (func (export "path_open") (import "wasi_snapshot_preview1" "path_open") (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32))
+  (func (export "path_create_directory") (import "wasi_snapshot_preview1" "path_create_directory") (param i32 i32 i32) (result i32))
+  (func (export "path_unlink_file") (import "wasi_snapshot_preview1" "path_unlink_file") (param i32 i32 i32) (result i32))
+  (func (export "proc_exit") (import "wasi_snapshot_preview1" "proc_exit") (param i32))
+  (func (export "sock_send") (import "wasi_snapshot_preview1" "sock_send") (param i32 i32 i32 i32 i32) (result i32))
+  (memory (export "memory") 1))
+"#;
+
+/// A writer whose output can be read after it's given to a program.
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Output {
+    fn contents(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct Program {
+    instance: Instance,
+    memory: Memory,
+}
+
+impl Program {
+    fn new(wasi_env: &WasiEnv) -> Result<Self> {
+        let store = Store::default();
+        let module = Module::new(&store, WASI_FUNCTIONS)?;
+        let instance = Instance::new(&module, &wasi_env.import_object(&store))?;
+        let memory = instance.exports().get_memory("memory")?;
+        Ok(Self { instance, memory })
+    }
+
+    /// Calls the WASI function `name`, returning its error code.
+    fn call(&self, name: &str, params: &[Val]) -> Result<Errno> {
+        let function = self.instance.lookup_function(name).unwrap();
+        Ok(function.call(params)?[0].unwrap_i32() as Errno)
+    }
+
+    fn write(&self, offset: u32, bytes: &[u8]) {
+        let offset = offset as usize;
+        unsafe {
+            self.memory.data_unchecked_mut()[offset..offset + bytes.len()].copy_from_slice(bytes)
+        }
+    }
+
+    fn read(&self, offset: u32, len: u32) -> Vec<u8> {
+        let offset = offset as usize;
+        unsafe { self.memory.data_unchecked()[offset..offset + len as usize].to_vec() }
+    }
+
+    fn read_u32(&self, offset: u32) -> u32 {
+        u32::from_le_bytes(self.read(offset, 4).try_into().unwrap())
+    }
+
+    fn read_u64(&self, offset: u32) -> u64 {
+        u64::from_le_bytes(self.read(offset, 8).try_into().unwrap())
+    }
+
+    /// Writes an `iovec` of `len` bytes at `buf` to `offset`.
+    fn write_iovec(&self, offset: u32, buf: u32, len: u32) {
+        self.write(offset, &buf.to_le_bytes());
+        self.write(offset + 4, &len.to_le_bytes());
+    }
+
+    /// Opens `path`, written at 1024, in the directory `dir`.
+    fn path_open(
+        &self,
+        dir: u32,
+        path: &str,
+        oflags: u16,
+        rights: u64,
+    ) -> Result<Result<u32, Errno>> {
+        self.write(1024, path.as_bytes());
+        let errno = self.call(
+            "path_open",
+            &[
+                Val::I32(dir as i32),
+                Val::I32(0),
+                Val::I32(1024),
+                Val::I32(path.len() as i32),
+                Val::I32(oflags as i32),
+                Val::I64(rights as i64),
+                Val::I64(rights as i64),
+                Val::I32(0),
+                Val::I32(0),
+            ],
+        )?;
+        Ok(match errno {
+            ERRNO_SUCCESS => Ok(self.read_u32(0)),
+            errno => Err(errno),
+        })
+    }
+}
+
+#[test]
+fn args_and_environment_variables() -> Result<()> {
+    let wasi_env = WasiState::new("program")
+        .args(vec!["--verbose", "file"])
+        .env("LOG", "debug")
+        .finalize()?;
+    let program = Program::new(&wasi_env)?;
+
+    let sizes = [Val::I32(0), Val::I32(4)];
+    assert_eq!(program.call("args_sizes_get", &sizes)?, ERRNO_SUCCESS);
+    assert_eq!((program.read_u32(0), program.read_u32(4)), (3, 23));
+    assert_eq!(
+        program.call("args_get", &[Val::I32(16), Val::I32(64)])?,
+        ERRNO_SUCCESS
+    );
+    assert_eq!(
+        (
+            program.read_u32(16),
+            program.read_u32(20),
+            program.read_u32(24)
+        ),
+        (64, 72, 82)
+    );
+    assert_eq!(program.read(64, 23), b"program\0--verbose\0file\0");
+
+    assert_eq!(program.call("environ_sizes_get", &sizes)?, ERRNO_SUCCESS);
+    assert_eq!((program.read_u32(0), program.read_u32(4)), (1, 10));
+    assert_eq!(
+        program.call("environ_get", &[Val::I32(16), Val::I32(64)])?,
+        ERRNO_SUCCESS
+    );
+    assert_eq!(program.read_u32(16), 64);
+    assert_eq!(program.read(64, 10), b"LOG=debug\0");
+
+    // The buffer doesn't fit in the memory.
+    assert_eq!(
+        program.call("args_get", &[Val::I32(16), Val::I32(65530)])?,
+        ERRNO_FAULT
+    );
+    Ok(())
+}
+
+#[test]
+fn invalid_environment_variables_are_rejected() {
+    assert!(WasiState::new("program")
+        .env("A=B", "C")
+        .finalize()
+        .is_err());
+    assert!(WasiState::new("program").arg("a\0b").finalize().is_err());
+}
+
+#[test]
+fn clocks_and_random() -> Result<()> {
+    let program = Program::new(&WasiState::new("program").finalize()?)?;
+
+    for clock in &[CLOCK_REALTIME, CLOCK_MONOTONIC] {
+        let params = [Val::I32(*clock as i32), Val::I64(1), Val::I32(0)];
+        assert_eq!(program.call("clock_time_get", &params)?, ERRNO_SUCCESS);
+        let first = program.read_u64(0);
+        assert_eq!(program.call("clock_time_get", &params)?, ERRNO_SUCCESS);
+        assert!(program.read_u64(0) >= first);
+    }
+    let params = [Val::I32(42), Val::I64(1), Val::I32(0)];
+    assert_eq!(program.call("clock_time_get", &params)?, ERRNO_INVAL);
+
+    assert_eq!(
+        program.call("random_get", &[Val::I32(0), Val::I32(64)])?,
+        ERRNO_SUCCESS
+    );
+    assert_ne!(program.read(0, 64), vec![0; 64]);
+    Ok(())
+}
+
+#[test]
+fn standard_streams() -> Result<()> {
+    let stdout = Output::default();
+    let stderr = Output::default();
+    let wasi_env = WasiState::new("program")
+        .stdin(Box::new(Cursor::new("input")))
+        .stdout(Box::new(stdout.clone()))
+        .stderr(Box::new(stderr.clone()))
+        .finalize()?;
+    let program = Program::new(&wasi_env)?;
+
+    program.write(64, b"hello world");
+    program.write_iovec(16, 64, 6);
+    program.write_iovec(24, 70, 5);
+    let write = |fd| [Val::I32(fd), Val::I32(16), Val::I32(2), Val::I32(0)];
+    assert_eq!(program.call("fd_write", &write(1))?, ERRNO_SUCCESS);
+    assert_eq!(program.read_u32(0), 11);
+    assert_eq!(program.call("fd_write", &write(2))?, ERRNO_SUCCESS);
+    assert_eq!(stdout.contents(), "hello world");
+    assert_eq!(stderr.contents(), "hello world");
+    assert_eq!(program.call("fd_write", &write(0))?, ERRNO_BADF);
+    assert_eq!(program.call("fd_write", &write(9))?, ERRNO_BADF);
+
+    program.write_iovec(16, 128, 16);
+    let read = [Val::I32(0), Val::I32(16), Val::I32(1), Val::I32(0)];
+    assert_eq!(program.call("fd_read", &read)?, ERRNO_SUCCESS);
+    assert_eq!(program.read_u32(0), 5);
+    assert_eq!(program.read(128, 5), b"input");
+
+    let tell = [Val::I32(1), Val::I32(0)];
+    assert_eq!(program.call("fd_tell", &tell)?, ERRNO_SPIPE);
+    Ok(())
+}
+
+#[test]
+fn files_in_a_preopened_directory() -> Result<()> {
+    let fs = MemFs::new();
+    fs.write_file("data/input", "some input")?;
+    let wasi_env = WasiState::new("program")
+        .preopen("/sandbox", fs.clone())
+        .finalize()?;
+    let program = Program::new(&wasi_env)?;
+
+    assert_eq!(
+        program.call("fd_prestat_get", &[Val::I32(3), Val::I32(0)])?,
+        ERRNO_SUCCESS
+    );
+    assert_eq!(program.read(0, 1), [PREOPENTYPE_DIR]);
+    assert_eq!(program.read_u32(4), 8);
+    assert_eq!(
+        program.call(
+            "fd_prestat_dir_name",
+            &[Val::I32(3), Val::I32(64), Val::I32(8)]
+        )?,
+        ERRNO_SUCCESS
+    );
+    assert_eq!(program.read(64, 8), b"/sandbox");
+    assert_eq!(
+        program.call("fd_prestat_get", &[Val::I32(4), Val::I32(0)])?,
+        ERRNO_BADF
+    );
+
+    let rights = RIGHTS_FD_READ | RIGHTS_FD_WRITE;
+    let input = program
+        .path_open(3, "data/input", 0, RIGHTS_FD_READ)?
+        .unwrap();
+    assert_eq!(input, 4);
+    program.write_iovec(16, 128, 64);
+    let read = [Val::I32(4), Val::I32(16), Val::I32(1), Val::I32(0)];
+    assert_eq!(program.call("fd_read", &read)?, ERRNO_SUCCESS);
+    assert_eq!(program.read(128, program.read_u32(0)), b"some input");
+
+    let output = program
+        .path_open(3, "data/../output", OFLAGS_CREAT | OFLAGS_EXCL, rights)?
+        .unwrap();
+    assert_eq!(output, 5);
+    program.write(128, b"hello world");
+    program.write_iovec(16, 128, 11);
+    let write = [Val::I32(5), Val::I32(16), Val::I32(1), Val::I32(0)];
+    assert_eq!(program.call("fd_write", &write)?, ERRNO_SUCCESS);
+    let seek = [
+        Val::I32(5),
+        Val::I64(-5),
+        Val::I32(WHENCE_END as i32),
+        Val::I32(0),
+    ];
+    assert_eq!(program.call("fd_seek", &seek)?, ERRNO_SUCCESS);
+    assert_eq!(program.read_u64(0), 6);
+    program.write_iovec(16, 256, 64);
+    let read = [Val::I32(5), Val::I32(16), Val::I32(1), Val::I32(0)];
+    assert_eq!(program.call("fd_read", &read)?, ERRNO_SUCCESS);
+    assert_eq!(program.read(256, program.read_u32(0)), b"world");
+    assert_eq!(
+        program.call("fd_tell", &[Val::I32(5), Val::I32(0)])?,
+        ERRNO_SUCCESS
+    );
+    assert_eq!(program.read_u64(0), 11);
+    assert_eq!(
+        program.call("fd_filestat_get", &[Val::I32(5), Val::I32(64)])?,
+        ERRNO_SUCCESS
+    );
+    assert_eq!(program.read(80, 1), [FILETYPE_REGULAR_FILE]);
+    assert_eq!(program.read_u64(96), 11);
+    assert_eq!(fs.read_file("output")?, b"hello world");
+
+    assert_eq!(
+        program.path_open(3, "output", OFLAGS_CREAT | OFLAGS_EXCL, rights)?,
+        Err(ERRNO_EXIST)
+    );
+    assert_eq!(
+        program.path_open(3, "missing", 0, rights)?,
+        Err(ERRNO_NOENT)
+    );
+    assert_eq!(program.call("fd_close", &[Val::I32(4)])?, ERRNO_SUCCESS);
+    assert_eq!(program.call("fd_close", &[Val::I32(4)])?, ERRNO_BADF);
+
+    // The lowest free file descriptor is reused.
+    let dir = program
+        .path_open(3, "data", OFLAGS_DIRECTORY, rights)?
+        .unwrap();
+    assert_eq!(dir, 4);
+    assert_eq!(program.path_open(dir, "input", 0, rights)?, Ok(6));
+    assert_eq!(
+        program.path_open(dir, "input", OFLAGS_DIRECTORY, rights)?,
+        Err(ERRNO_NOTDIR)
+    );
+
+    program.write(1024, b"logs");
+    let path = [Val::I32(3), Val::I32(1024), Val::I32(4)];
+    assert_eq!(program.call("path_create_directory", &path)?, ERRNO_SUCCESS);
+    assert!(fs.metadata("logs".as_ref())?.is_dir);
+    program.write(1024, b"output");
+    let path = [Val::I32(3), Val::I32(1024), Val::I32(6)];
+    assert_eq!(program.call("path_unlink_file", &path)?, ERRNO_SUCCESS);
+    assert!(fs.read_file("output").is_err());
+    Ok(())
+}
+
+#[test]
+fn programs_cannot_escape_preopened_directories() -> Result<()> {
+    let fs = MemFs::new();
+    fs.write_file("data/input", "some input")?;
+    let wasi_env = WasiState::new("program").preopen(".", fs).finalize()?;
+    let program = Program::new(&wasi_env)?;
+
+    let dir = program.path_open(3, "data", OFLAGS_DIRECTORY, 0)?.unwrap();
+    assert!(program.path_open(dir, "../data/input", 0, 0)?.is_ok());
+    for path in &["../input", "/etc/passwd", "../../etc/passwd"] {
+        assert_eq!(program.path_open(3, path, 0, 0)?, Err(ERRNO_NOTCAPABLE));
+    }
+    assert_eq!(
+        program.path_open(dir, "../../input", 0, 0)?,
+        Err(ERRNO_NOTCAPABLE)
+    );
+    Ok(())
+}
+
+#[test]
+fn host_directories() -> Result<()> {
+    let root = tempfile::tempdir()?;
+    let dir = root.path().join("sandbox");
+    std::fs::create_dir(&dir)?;
+    std::fs::write(dir.join("input"), "some input")?;
+    std::fs::write(root.path().join("secret"), "secret")?;
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(root.path().join("secret"), dir.join("link"))?;
+
+    let wasi_env = WasiState::new("program")
+        .preopen("/sandbox", HostFs::new(&dir)?)
+        .finalize()?;
+    let program = Program::new(&wasi_env)?;
+
+    let rights = RIGHTS_FD_READ | RIGHTS_FD_WRITE;
+    assert!(program.path_open(3, "input", 0, rights)?.is_ok());
+    let output = program
+        .path_open(3, "output", OFLAGS_CREAT, rights)?
+        .unwrap();
+    program.write(128, b"hello");
+    program.write_iovec(16, 128, 5);
+    let write = [
+        Val::I32(output as i32),
+        Val::I32(16),
+        Val::I32(1),
+        Val::I32(0),
+    ];
+    assert_eq!(program.call("fd_write", &write)?, ERRNO_SUCCESS);
+    assert_eq!(std::fs::read_to_string(dir.join("output"))?, "hello");
+
+    assert_eq!(
+        program.path_open(3, "../secret", 0, rights)?,
+        Err(ERRNO_NOTCAPABLE)
+    );
+    #[cfg(unix)]
+    assert_eq!(program.path_open(3, "link", 0, rights)?, Err(ERRNO_ACCES));
+    Ok(())
+}
+
+#[test]
+fn unsupported_functions() -> Result<()> {
+    let program = Program::new(&WasiState::new("program").finalize()?)?;
+    let params = vec![Val::I32(0); 5];
+    assert_eq!(program.call("sock_send", &params)?, ERRNO_NOSYS);
+    Ok(())
+}
+
+#[test]
+fn proc_exit() -> Result<()> {
+    let program = Program::new(&WasiState::new("program").finalize()?)?;
+    let function = program.instance.lookup_function("proc_exit").unwrap();
+    let error = function.call(&[Val::I32(42)]).unwrap_err();
+    assert!(matches!(error.downcast_ref(), Some(WasiError::Exit(42))));
+    Ok(())
+}
+
+#[test]
+fn hello_world() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"
+(module
+  (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 8) "Hello, World!\n")
+  (func (export "_start")
+    (i32.store (i32.const 0) (i32.const 8))
+    (i32.store (i32.const 4) (i32.const 14))
+    (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 32)))))
+"#,
+    )?;
+    let stdout = Output::default();
+    let wasi_env = WasiState::new("hello")
+        .stdout(Box::new(stdout.clone()))
+        .finalize()?;
+    let instance = Instance::new(&module, &wasi_env.import_object(&store))?;
+    instance.lookup_function("_start").unwrap().call(&[])?;
+    assert_eq!(stdout.contents(), "Hello, World!\n");
+    Ok(())
+}
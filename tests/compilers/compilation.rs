@@ -1,7 +1,7 @@
 use wasmer::*;
 use wasmer_engine::{Engine, Executable};
 use wasmer_engine_universal::Universal;
-use wasmer_vm::Artifact;
+use wasmer_vm::{Artifact, TrapCode};
 
 fn slow_to_compile_contract(n_fns: usize, n_locals: usize) -> Vec<u8> {
     let fns = format!("(func (local {}))\n", "i32 ".repeat(n_locals)).repeat(n_fns);
@@ -84,7 +84,9 @@ fn profiling() {
             let serialized = art.serialize().unwrap();
             let executable =
                 wasmer_engine_universal::UniversalExecutableRef::deserialize(&serialized).unwrap();
-            let artifact = engine.load_universal_executable_ref(&executable).unwrap();
+            let artifact = engine
+                .load_universal_executable_ref(&executable, store.tunables())
+                .unwrap();
             let info = artifact
                 .functions()
                 .iter()
@@ -106,3 +108,171 @@ fn profiling() {
         }
     }
 }
+
+#[test]
+fn singlepass_cross_compiles_to_aarch64() {
+    let wat = r#"
+       (import "env" "gas" (func $gas (param i32)))
+       (import "env" "impf" (func $impf (param f32 i64 f64) (result f64)))
+       (memory 1)
+       (table 2 funcref)
+       (global $g (mut f64) (f64.const 0))
+       (elem (i32.const 0) $arith $floats)
+       (type $t (func (param i32 i32) (result i32)))
+       (func $arith (param i32 i32) (result i32)
+         (call $gas (i32.const 3))
+         (i32.add
+           (i32.div_s (local.get 0) (local.get 1))
+           (i32.rem_u (i32.popcnt (local.get 0)) (i32.ctz (local.get 1)))))
+       (func $floats (param f32 f64) (result f64)
+         (global.set $g (f64.min (f64.promote_f32 (local.get 0)) (local.get 1)))
+         (call $impf (local.get 0) (i64.trunc_f64_s (local.get 1)) (global.get $g)))
+       (func (export "main") (param i32) (result i32)
+         (local i64)
+         (i64.store offset=8 (local.get 0) (i64.atomic.rmw.add (i32.const 0) (i64.const 1)))
+         (block $b
+           (loop $l
+             (br_table $b $l (local.get 0))))
+         (call_indirect (type $t) (i32.const 1) (i32.const 2) (i32.const 0)))
+    "#;
+    let wasm = wat2wasm(wat.as_bytes()).unwrap();
+    let mut features = Features::default();
+    features.threads(true);
+    let target = Target::new(
+        "aarch64-unknown-linux-gnu".parse().unwrap(),
+        CpuFeature::set(),
+    );
+    let engine = Universal::new(Singlepass::default())
+        .target(target)
+        .features(features)
+        .engine();
+    let store = Store::new(&engine);
+    let executable = compile_uncached(&store, &engine, &wasm, false).unwrap();
+    executable.serialize().unwrap();
+}
+
+/// Runs code compiled by Singlepass for the host, which on AArch64 hosts,
+/// and under `make test-singlepass-universal-aarch64-qemu`, executes the code
+/// of the AArch64 backend cross-compiled above.
+#[test]
+fn singlepass_executes_native_code() {
+    let wat = r#"
+       (import "env" "gas" (func $gas (param i32)))
+       (import "env" "impf" (func $impf (param f32 i64 f64) (result f64)))
+       (memory (export "memory") 1)
+       (table 2 funcref)
+       (global $g (mut f64) (f64.const 0))
+       (elem (i32.const 0) $arith $floats)
+       (type $t (func (param i32 i32) (result i32)))
+       (type $f (func (param f32 f64) (result f64)))
+       (func $arith (param i32 i32) (result i32)
+         (call $gas (i32.const 3))
+         (i32.add
+           (i32.div_s (local.get 0) (local.get 1))
+           (i32.rem_u (i32.popcnt (local.get 0)) (i32.ctz (local.get 1)))))
+       (func $floats (param f32 f64) (result f64)
+         (global.set $g (f64.min (f64.promote_f32 (local.get 0)) (local.get 1)))
+         (call $impf (local.get 0) (i64.trunc_f64_s (local.get 1)) (global.get $g)))
+       (func (export "arith") (param i32 i32) (result i32)
+         (call_indirect (type $t) (local.get 0) (local.get 1) (i32.const 0)))
+       (func (export "floats") (param f32 f64) (result f64)
+         (call_indirect (type $f) (local.get 0) (local.get 1) (i32.const 1)))
+       (func (export "count") (param i32) (result i64)
+         (local i64)
+         (block $b
+           (loop $l
+             (local.set 1 (i64.load (i32.const 0)))
+             (i64.store (i32.const 0) (i64.add (local.get 1) (i64.const 1)))
+             (br_table $b $l (i32.ne (i32.wrap_i64 (local.get 1)) (local.get 0)))))
+         (i64.load (i32.const 0)))
+       (func (export "divide") (param i32 i32) (result i32)
+         (i32.div_u (local.get 0) (local.get 1)))
+    "#;
+    let engine = Universal::new(Singlepass::default()).engine();
+    let store = Store::new(&engine);
+    let module = Module::new(&store, wat).unwrap();
+    let impf = Function::new_native(&store, |a: f32, b: i64, c: f64| a as f64 + b as f64 + c);
+    let gas = Function::new_native(&store, |_: i32| {});
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "env" => {
+                "gas" => gas,
+                "impf" => impf,
+            },
+        },
+    )
+    .unwrap();
+
+    let arith = instance
+        .lookup_function("arith")
+        .unwrap()
+        .native::<(i32, i32), i32>()
+        .unwrap();
+    assert_eq!(arith.call(7, 4).unwrap(), 2);
+    assert_eq!(arith.call(-9, 2).unwrap(), -4);
+    let floats = instance
+        .lookup_function("floats")
+        .unwrap()
+        .native::<(f32, f64), f64>()
+        .unwrap();
+    assert_eq!(floats.call(1.5, 2.75).unwrap(), 5.0);
+    assert_eq!(floats.call(-0.5, -3.0).unwrap(), -6.5);
+    let count = instance
+        .lookup_function("count")
+        .unwrap()
+        .native::<i32, i64>()
+        .unwrap();
+    assert_eq!(count.call(5).unwrap(), 6);
+    let divide = instance
+        .lookup_function("divide")
+        .unwrap()
+        .native::<(i32, i32), i32>()
+        .unwrap();
+    assert_eq!(divide.call(7, 2).unwrap(), 3);
+    let trap = divide.call(7, 0).unwrap_err();
+    assert_eq!(trap.to_trap(), Some(TrapCode::IntegerDivisionByZero));
+}
+
+#[test]
+fn singlepass_cross_compiles_exceptions_to_aarch64() {
+    let wat = r#"
+       (tag $e (param i32 f64))
+       (tag $empty)
+       (func $throw (param i32)
+         (throw $e (local.get 0) (f64.const 1)))
+       (func (export "main") (param i32) (result i32)
+         try (result i32)
+           try (result i32)
+             (call $throw (local.get 0))
+             (i32.const 0)
+           delegate 0
+         catch $e
+           drop
+         catch $empty
+           (i32.const 1)
+         catch_all
+           try
+             rethrow 1
+           catch_all
+           end
+           (return_call $main2 (local.get 0))
+         end)
+       (func $main2 (param i32) (result i32)
+         (local.get 0))
+    "#;
+    let wasm = wat2wasm(wat.as_bytes()).unwrap();
+    let mut features = Features::default();
+    features.exceptions(true).tail_call(true);
+    let target = Target::new(
+        "aarch64-unknown-linux-gnu".parse().unwrap(),
+        CpuFeature::set(),
+    );
+    let engine = Universal::new(Singlepass::default())
+        .target(target)
+        .features(features)
+        .engine();
+    let store = Store::new(&engine);
+    let executable = compile_uncached(&store, &engine, &wasm, false).unwrap();
+    executable.serialize().unwrap();
+}
@@ -1,4 +1,5 @@
-use wasmer::{CompilerConfig, Engine as WasmerEngine, Features, Store};
+use std::sync::Arc;
+use wasmer::{CompilerConfig, Engine as WasmerEngine, Features, ModuleMiddleware, Store};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Compiler {
@@ -18,7 +19,9 @@ pub struct Config {
     pub compiler: Compiler,
     pub engine: Engine,
     pub features: Option<Features>,
+    pub middlewares: Vec<Arc<dyn ModuleMiddleware>>,
     pub canonicalize_nans: bool,
+    pub epoch_interruption: bool,
 }
 
 impl Config {
@@ -27,7 +30,9 @@ impl Config {
             compiler,
             engine,
             features: None,
+            middlewares: vec![],
             canonicalize_nans: false,
+            epoch_interruption: false,
         }
     }
 
@@ -35,10 +40,18 @@ impl Config {
         self.features = Some(features);
     }
 
+    pub fn set_middlewares(&mut self, middlewares: Vec<Arc<dyn ModuleMiddleware>>) {
+        self.middlewares = middlewares;
+    }
+
     pub fn set_nan_canonicalization(&mut self, canonicalize_nans: bool) {
         self.canonicalize_nans = canonicalize_nans;
     }
 
+    pub fn set_epoch_interruption(&mut self, epoch_interruption: bool) {
+        self.epoch_interruption = epoch_interruption;
+    }
+
     pub fn store(&self) -> Store {
         let compiler_config = self.compiler_config(self.canonicalize_nans);
         let engine = self.engine(compiler_config);
@@ -99,6 +112,8 @@ impl Config {
                 let mut compiler = wasmer_compiler_cranelift::Cranelift::new();
                 compiler.canonicalize_nans(canonicalize_nans);
                 compiler.enable_verifier();
+                compiler.epoch_interruption(self.epoch_interruption);
+                self.add_middlewares(&mut compiler);
                 Box::new(compiler)
             }
             #[cfg(feature = "llvm")]
@@ -106,6 +121,8 @@ impl Config {
                 let mut compiler = wasmer_compiler_llvm::LLVM::new();
                 compiler.canonicalize_nans(canonicalize_nans);
                 compiler.enable_verifier();
+                compiler.epoch_interruption(self.epoch_interruption);
+                self.add_middlewares(&mut compiler);
                 Box::new(compiler)
             }
             #[cfg(feature = "singlepass")]
@@ -113,6 +130,8 @@ impl Config {
                 let mut compiler = wasmer_compiler_singlepass::Singlepass::new();
                 compiler.canonicalize_nans(canonicalize_nans);
                 compiler.enable_verifier();
+                compiler.epoch_interruption(self.epoch_interruption);
+                self.add_middlewares(&mut compiler);
                 Box::new(compiler)
             }
             #[allow(unreachable_patterns)]
@@ -124,4 +143,11 @@ impl Config {
             }
         }
     }
+
+    #[allow(dead_code)]
+    fn add_middlewares(&self, config: &mut dyn CompilerConfig) {
+        for middleware in self.middlewares.iter() {
+            config.push_middleware(middleware.clone());
+        }
+    }
 }
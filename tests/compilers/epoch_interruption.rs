@@ -0,0 +1,117 @@
+use std::thread;
+use std::time::Duration;
+use wasmer::*;
+use wasmer_engine_universal::{Universal, UniversalEngine, UniversalExecutableRef};
+use wasmer_types::InstanceConfig;
+use wasmer_vm::TrapCode;
+
+fn get_engine(mut config: crate::Config) -> UniversalEngine {
+    config.set_epoch_interruption(true);
+    Universal::new(config.compiler_config(false)).engine()
+}
+
+fn get_module(store: &Store) -> Module {
+    let wat = r#"
+        (func (export "add") (param i32 i32) (result i32)
+            (i32.add (local.get 0) (local.get 1)))
+        (func (export "spin")
+            (loop $l
+                (br $l)))
+    "#;
+
+    Module::new(&store, &wat).unwrap()
+}
+
+fn trap_code(result: Result<Box<[Val]>, RuntimeError>) -> Option<TrapCode> {
+    result.err().map(|e| e.to_trap().unwrap())
+}
+
+#[compiler_test(epoch_interruption)]
+fn loop_is_interrupted(config: crate::Config) {
+    let engine = get_engine(config);
+    let store = Store::new(&engine);
+    let module = get_module(&store);
+    let instance = Instance::new_with_config(
+        &module,
+        InstanceConfig::default().with_epoch_deadline(1),
+        &imports! {},
+    )
+    .unwrap();
+    let spin = instance.lookup_function("spin").unwrap();
+
+    let ticker = {
+        let engine = engine.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            engine.increment_epoch();
+        })
+    };
+    assert_eq!(trap_code(spin.call(&[])), Some(TrapCode::Interrupted));
+    ticker.join().unwrap();
+}
+
+#[compiler_test(epoch_interruption)]
+fn deadline_is_checked_on_entry(config: crate::Config) {
+    let engine = get_engine(config);
+    let store = Store::new(&engine);
+    let module = get_module(&store);
+    let instance = Instance::new_with_config(
+        &module,
+        InstanceConfig::default().with_epoch_deadline(0),
+        &imports! {},
+    )
+    .unwrap();
+    let add = instance.lookup_function("add").unwrap();
+    let args = [Val::I32(1), Val::I32(2)];
+
+    assert_eq!(trap_code(add.call(&args)), Some(TrapCode::Interrupted));
+
+    instance.set_epoch_deadline(2);
+    assert_eq!(add.call(&args).unwrap().to_vec(), vec![Val::I32(3)]);
+    engine.increment_epoch();
+    assert_eq!(add.call(&args).unwrap().to_vec(), vec![Val::I32(3)]);
+    engine.increment_epoch();
+    assert_eq!(trap_code(add.call(&args)), Some(TrapCode::Interrupted));
+}
+
+#[compiler_test(epoch_interruption)]
+fn default_deadline_is_never_reached(config: crate::Config) {
+    let engine = get_engine(config);
+    let store = Store::new(&engine);
+    let module = get_module(&store);
+    let instance = Instance::new(&module, &imports! {}).unwrap();
+    let add = instance.lookup_function("add").unwrap();
+
+    for _ in 0..3 {
+        engine.increment_epoch();
+    }
+    assert_eq!(
+        add.call(&[Val::I32(1), Val::I32(2)]).unwrap().to_vec(),
+        vec![Val::I32(3)]
+    );
+}
+
+#[compiler_test(epoch_interruption)]
+fn serialized_modules_record_epoch_interruption(mut config: crate::Config) -> anyhow::Result<()> {
+    let engine = get_engine(config.clone());
+    let wasm = wat2wasm(b"(module (func (export \"nop\")))")?;
+    let tunables = BaseTunables::for_target(engine.target());
+    let serialized = engine.compile(&wasm, &tunables)?.serialize().unwrap();
+    let executable = unsafe { UniversalExecutableRef::deserialize(&serialized)? };
+    assert!(executable.header().epoch_interruption);
+
+    config.set_epoch_interruption(false);
+    let other = Universal::new(config.compiler_config(false)).engine();
+    assert!(matches!(
+        other.load_universal_executable_ref(&executable, &tunables),
+        Err(DeserializeError::IncompatibleEpochInterruption {
+            expected: false,
+            found: true,
+        })
+    ));
+
+    let headless = Universal::headless().engine();
+    assert_eq!(headless.epoch_interruption(), None);
+    headless.load_universal_executable_ref(&executable, &tunables)?;
+    Ok(())
+}
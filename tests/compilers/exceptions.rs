@@ -0,0 +1,347 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use wasmer::*;
+
+fn exceptions_config(mut config: crate::Config) -> crate::Config {
+    let mut features = Features::default();
+    features.exceptions(true);
+    config.set_features(features);
+    config
+}
+
+#[compiler_test(exceptions)]
+fn throw_and_catch(config: crate::Config) -> anyhow::Result<()> {
+    let store = exceptions_config(config).store();
+    let wat = r#"
+        (tag $e32 (param i32))
+        (tag $e64 (param i64 f64))
+        (tag $empty)
+        (func $throw (param i32)
+            (if (i32.eq (local.get 0) (i32.const 1))
+                (then (throw $e32 (i32.const 42))))
+            (if (i32.eq (local.get 0) (i32.const 2))
+                (then (throw $e64 (i64.const 7) (f64.const 0.5))))
+            (if (i32.eq (local.get 0) (i32.const 3))
+                (then (throw $empty))))
+        (func (export "run") (param i32) (result i64)
+            try (result i64)
+                (call $throw (local.get 0))
+                (i64.const -1)
+            catch $e32
+                i64.extend_i32_s
+            catch $e64
+                f64.const 20
+                f64.mul
+                i64.trunc_f64_s
+                i64.add
+            catch_all
+                (i64.const 100)
+            end)
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let run = instance.get_native_function::<i32, i64>("run")?;
+
+    assert_eq!(run.call(0)?, -1);
+    assert_eq!(run.call(1)?, 42);
+    assert_eq!(run.call(2)?, 17);
+    assert_eq!(run.call(3)?, 100);
+
+    Ok(())
+}
+
+#[compiler_test(exceptions)]
+fn nested_try_and_rethrow(config: crate::Config) -> anyhow::Result<()> {
+    let store = exceptions_config(config).store();
+    let wat = r#"
+        (tag $e (param i32))
+        (func $thrower (param i32) (result i32)
+            (throw $e (local.get 0)))
+        (func (export "rethrow") (param i32) (result i32)
+            try (result i32)
+                try (result i32)
+                    (call $thrower (local.get 0))
+                catch $e
+                    drop
+                    rethrow 0
+                end
+            catch $e
+                (i32.add (i32.const 1))
+            end)
+        (func (export "inner") (param i32) (result i32)
+            (local i32)
+            try
+                try
+                    (drop (call $thrower (local.get 0)))
+                catch $e
+                    local.set 1
+                end
+                (local.set 1 (i32.mul (local.get 1) (i32.const 2)))
+            catch_all
+                (local.set 1 (i32.const -1))
+            end
+            (local.get 1))
+        (func (export "delegate") (param i32) (result i32)
+            try (result i32)
+                try (result i32)
+                    try (result i32)
+                        (call $thrower (local.get 0))
+                    delegate 1
+                catch $e
+                    drop
+                    (i32.const -1)
+                end
+            catch $e
+                (i32.mul (i32.const -1))
+            end)
+        (func (export "loop") (param i32) (result i32)
+            (local $sum i32)
+            (loop $continue
+                try
+                    (drop (call $thrower (local.get 0)))
+                catch $e
+                    (local.set $sum (i32.add (local.get $sum)))
+                end
+                (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+                (br_if $continue (local.get 0)))
+            (local.get $sum))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let rethrow = instance.get_native_function::<i32, i32>("rethrow")?;
+    let inner = instance.get_native_function::<i32, i32>("inner")?;
+    let delegate = instance.get_native_function::<i32, i32>("delegate")?;
+    let loop_ = instance.get_native_function::<i32, i32>("loop")?;
+
+    assert_eq!(rethrow.call(41)?, 42);
+    assert_eq!(inner.call(21)?, 42);
+    assert_eq!(delegate.call(42)?, -42);
+    assert_eq!(loop_.call(100)?, 5050);
+
+    Ok(())
+}
+
+#[compiler_test(exceptions)]
+fn uncaught_exceptions(config: crate::Config) -> anyhow::Result<()> {
+    let store = exceptions_config(config).store();
+    let wat = r#"
+        (tag $e (export "e") (param i32 f32))
+        (tag $other (param i32))
+        (func (export "throw") (param i32)
+            try
+                (throw $e (local.get 0) (f32.const 1.5))
+            catch $other
+                drop
+            end)
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let throw = instance.get_native_function::<i32, ()>("throw")?;
+    let tag: Tag = instance.get_with_generics::<_, (), ()>("e")?;
+    assert_eq!(tag.ty().params(), &[Type::I32, Type::F32]);
+
+    let err = throw.call(7).unwrap_err();
+    let exception = err.exception().expect("the error is an exception");
+    assert_eq!(
+        tag.values(exception),
+        Some(vec![Val::I32(7), Val::F32(1.5)])
+    );
+    let other = Tag::new(&store, tag.ty().clone());
+    assert_eq!(other.values(exception), None);
+    assert_eq!(err.to_trap(), None);
+
+    // The exception doesn't linger after reaching the host.
+    let err = throw.call(8).unwrap_err();
+    assert_eq!(
+        tag.values(err.exception().unwrap()),
+        Some(vec![Val::I32(8), Val::F32(1.5)])
+    );
+
+    Ok(())
+}
+
+#[compiler_test(exceptions)]
+fn exceptions_across_instances(config: crate::Config) -> anyhow::Result<()> {
+    let store = exceptions_config(config).store();
+    let thrower = r#"
+        (tag $e (import "env" "e") (param i64))
+        (func (export "throw") (param i64)
+            (throw $e (local.get 0)))
+    "#;
+    let catcher = r#"
+        (tag $e (import "env" "e") (param i64))
+        (tag $local (param i64))
+        (import "env" "throw" (func $throw (param i64)))
+        (func (export "catch") (param i64) (result i64)
+            try (result i64)
+                (call $throw (local.get 0))
+                (i64.const 0)
+            catch $local
+            catch $e
+                (i64.add (i64.const 1))
+            end)
+    "#;
+    let tag = Tag::new(&store, TagType::new(vec![Type::I64]));
+    let thrower = Instance::new(
+        &Module::new(&store, thrower)?,
+        &imports! { "env" => { "e" => tag.clone() } },
+    )?;
+    let throw = thrower.lookup_function("throw").unwrap();
+    let catcher = Instance::new(
+        &Module::new(&store, catcher)?,
+        &imports! { "env" => { "e" => tag.clone(), "throw" => throw.clone() } },
+    )?;
+    let catch = catcher.get_native_function::<i64, i64>("catch")?;
+    assert_eq!(catch.call(41)?, 42);
+
+    // Catch clauses only match the very tag the exception was thrown with.
+    let unrelated = Tag::new(&store, TagType::new(vec![Type::I64]));
+    let catcher = Instance::new(
+        catcher.module(),
+        &imports! { "env" => { "e" => unrelated, "throw" => throw.clone() } },
+    )?;
+    let catch = catcher.get_native_function::<i64, i64>("catch")?;
+    let err = catch.call(41).unwrap_err();
+    assert_eq!(
+        tag.values(err.exception().unwrap()),
+        Some(vec![Val::I64(41)])
+    );
+
+    Ok(())
+}
+
+#[compiler_test(exceptions)]
+fn exceptions_through_the_host(config: crate::Config) -> anyhow::Result<()> {
+    let store = exceptions_config(config).store();
+    let wat = r#"
+        (tag $e (export "e") (param i32))
+        (import "host" "call" (func $call (param i32)))
+        (func (export "throw") (param i32)
+            (throw $e (local.get 0)))
+        (func (export "run") (param i32) (result i32)
+            try (result i32)
+                (call $call (local.get 0))
+                (i32.const 0)
+            catch $e
+            end)
+    "#;
+    let module = Module::new(&store, wat)?;
+    let throw_slot = Arc::new(Mutex::new(None::<Function>));
+    let slot = throw_slot.clone();
+    let call = Function::new(
+        &store,
+        FunctionType::new(vec![Type::I32], vec![]),
+        move |args| {
+            let throw = slot.lock().unwrap().clone().unwrap();
+            let err = throw.call(args).unwrap_err();
+            assert!(err.exception().is_some());
+            Err(err)
+        },
+    );
+    let instance = Instance::new(&module, &imports! { "host" => { "call" => call } })?;
+    *throw_slot.lock().unwrap() = instance.lookup_function("throw");
+    let run = instance.get_native_function::<i32, i32>("run")?;
+
+    // Host functions don't propagate exceptions: they reach the caller of
+    // the outer wasm function as errors.
+    let err = run.call(5).unwrap_err();
+    let tag: Tag = instance.get_with_generics::<_, (), ()>("e")?;
+    assert_eq!(
+        tag.values(err.exception().unwrap()),
+        Some(vec![Val::I32(5)])
+    );
+
+    Ok(())
+}
+
+#[compiler_test(exceptions)]
+fn tail_calls_leave_try_blocks(mut config: crate::Config) -> anyhow::Result<()> {
+    let mut features = Features::default();
+    features.exceptions(true).tail_call(true);
+    config.set_features(features);
+    let store = config.store();
+    let wat = r#"
+        (tag $e (param i32))
+        (func $throw (param i32) (result i32)
+            (throw $e (local.get 0)))
+        (func $countdown (export "countdown") (param i32) (result i32)
+            try
+                (throw $e (local.get 0))
+            catch $e
+                drop
+            end
+            (if (result i32) (i32.eqz (local.get 0))
+                (then (i32.const 0))
+                (else (return_call $countdown (i32.sub (local.get 0) (i32.const 1))))))
+        (func (export "run") (param i32) (result i32)
+            try (result i32)
+                (return_call $throw (local.get 0))
+            catch $e
+            end)
+        (func (export "catch") (param i32) (result i32)
+            try (result i32)
+                (call $countdown (local.get 0))
+                (return_call $throw)
+            catch $e
+            end)
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let countdown = instance.get_native_function::<i32, i32>("countdown")?;
+    let run = instance.get_native_function::<i32, i32>("run")?;
+    let catch = instance.get_native_function::<i32, i32>("catch")?;
+
+    // Each activation frees what it caught before being replaced.
+    assert_eq!(countdown.call(100_000)?, 0);
+    // The callee of a tail call replaces the `try` block.
+    let err = run.call(3).unwrap_err();
+    assert!(err.exception().is_some());
+    let err = catch.call(3).unwrap_err();
+    assert!(err.exception().is_some());
+
+    Ok(())
+}
+
+#[compiler_test(exceptions)]
+fn exceptions_through_callers_without_exceptions(config: crate::Config) -> anyhow::Result<()> {
+    let store = exceptions_config(config.clone()).store();
+    let thrower = r#"
+        (tag $e (export "e") (param i32))
+        (func (export "throw") (param i32)
+            (throw $e (local.get 0)))
+    "#;
+    let caller = r#"
+        (import "env" "throw" (func $throw (param i32)))
+        (global $after (export "after") (mut i32) (i32.const 0))
+        (func (export "run") (param i32)
+            (call $throw (local.get 0))
+            (global.set $after (i32.const 1)))
+    "#;
+    let thrower = Instance::new(&Module::new(&store, thrower)?, &imports! {})?;
+    let throw = thrower.lookup_function("throw").unwrap();
+
+    // The caller is compiled without the exception handling proposal, and
+    // loaded by an engine that has it.
+    let compiling = config.store();
+    let executable = compiling
+        .engine()
+        .compile(&wat2wasm(caller.as_bytes())?, compiling.tunables())?;
+    let mut file = tempfile::NamedTempFile::new()?;
+    file.write_all(&executable.serialize().unwrap())?;
+    file.flush()?;
+    let caller = unsafe { Module::deserialize_from_file(&store, file.path())? };
+    let caller = Instance::new(&caller, &imports! { "env" => { "throw" => throw } })?;
+    let run = caller.get_native_function::<i32, ()>("run")?;
+
+    // The exception leaves the caller right after the call.
+    let err = run.call(3).unwrap_err();
+    let tag: Tag = thrower.get_with_generics::<_, (), ()>("e")?;
+    assert_eq!(
+        tag.values(err.exception().unwrap()),
+        Some(vec![Val::I32(3)])
+    );
+    let after: Global = caller.get_with_generics::<_, (), ()>("after")?;
+    assert_eq!(after.get(), Val::I32(0));
+
+    Ok(())
+}
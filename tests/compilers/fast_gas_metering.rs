@@ -1,10 +1,12 @@
 use std::ptr;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::SeqCst;
+use std::sync::Arc;
 use wasmer::*;
 use wasmer_compiler_singlepass::Singlepass;
 use wasmer_engine_universal::Universal;
 use wasmer_types::{FastGasCounter, InstanceConfig};
+use wasmer_vm::TrapCode;
 
 fn get_module_with_start(store: &Store) -> Module {
     let wat = r#"
@@ -288,3 +290,101 @@ fn test_gas_intrinsic_tricky() {
     // Ensure "gas" was called.
     assert_eq!(HITS.load(SeqCst), 2);
 }
+
+fn get_module_metered_loop(store: &Store) -> Module {
+    let wat = r#"
+        (import "host" "gas" (func $gas (param i32)))
+        (func (export "run") (param $n i32)
+            (loop $l
+                (call $gas (i32.const 7))
+                (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+                (br_if $l (local.get $n))))
+        (func (export "tricky")
+            (call $gas (i32.const -2)))
+    "#;
+
+    Module::new(&store, &wat).unwrap()
+}
+
+/// Calls `name` with `args` on a fresh instance of `get_module_metered_loop` and returns the
+/// final state of the gas counter along with the trap the call ended with, if any.
+fn run_metered(
+    store: &Store,
+    mut gas_counter: FastGasCounter,
+    name: &str,
+    args: &[Val],
+) -> (FastGasCounter, Option<TrapCode>, usize) {
+    let module = get_module_metered_loop(store);
+    let host_calls = Arc::new(AtomicUsize::new(0));
+    let host_calls_in_import = host_calls.clone();
+    let instance = Instance::new_with_config(
+        &module,
+        unsafe { InstanceConfig::default().with_counter(ptr::addr_of_mut!(gas_counter)) },
+        &imports! {
+            "host" => {
+                "gas" => Function::new(store, FunctionType::new(vec![ValType::I32], vec![]), move |_| {
+                    host_calls_in_import.fetch_add(1, SeqCst);
+                    Ok(vec![])
+                }),
+            },
+        },
+    )
+    .unwrap();
+    let func = instance.lookup_function(name).unwrap();
+    let trap = func.call(args).err().map(|e| e.to_trap().unwrap());
+    drop(instance);
+    (gas_counter, trap, host_calls.load(SeqCst))
+}
+
+#[compiler_test(fast_gas_metering)]
+fn gas_intrinsic_matches_singlepass(config: crate::Config) {
+    let store = config.store();
+    let singlepass_store = get_store();
+    let cases = [
+        // Enough gas for the whole loop.
+        (FastGasCounter::new(u64::MAX, 3), "run", vec![Val::I32(10)]),
+        // Runs out of gas in the middle of the loop.
+        (FastGasCounter::new(1000, 3), "run", vec![Val::I32(100)]),
+        // Exactly enough gas for the whole loop.
+        (FastGasCounter::new(210, 3), "run", vec![Val::I32(10)]),
+        // The counter overflows before the limit is checked.
+        (
+            FastGasCounter {
+                burnt_gas: i64::MAX as u64 - 10,
+                gas_limit: u64::MAX,
+                opcode_cost: 1,
+            },
+            "run",
+            vec![Val::I32(10)],
+        ),
+        // Calls with a negative count aren't intrinsified.
+        (FastGasCounter::new(1000, 3), "tricky", vec![]),
+    ];
+    for (gas_counter, name, args) in cases.iter() {
+        let expected = run_metered(&singlepass_store, gas_counter.clone(), name, args);
+        let actual = run_metered(&store, gas_counter.clone(), name, args);
+        assert_eq!(actual, expected, "calling {} with {}", name, gas_counter);
+    }
+
+    assert_eq!(
+        run_metered(
+            &store,
+            FastGasCounter::new(1000, 3),
+            "run",
+            &[Val::I32(100)]
+        ),
+        (
+            FastGasCounter {
+                burnt_gas: 1008,
+                gas_limit: 1000,
+                opcode_cost: 3,
+            },
+            Some(TrapCode::GasExceeded),
+            0
+        )
+    );
+    assert_eq!(
+        run_metered(&store, FastGasCounter::new(1000, 3), "tricky", &[]),
+        (FastGasCounter::new(1000, 3), None, 1)
+    );
+}
@@ -333,6 +333,7 @@ fn dynamic_function_with_env_wasmer_env_init_works(config: crate::Config) -> Res
                 minimum: 0.into(),
                 maximum: None,
                 shared: false,
+                memory64: false,
             },
         )?,
     };
@@ -0,0 +1,192 @@
+use anyhow::Result;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+use std::thread;
+use wasmer::*;
+use wasmer_compiler_singlepass::Singlepass;
+use wasmer_engine_universal::Universal;
+use wasmer_types::{FastGasCounter, InstanceConfig};
+use wasmer_vm::TrapCode;
+
+fn get_store(config: crate::Config) -> Store {
+    let engine = Universal::new(config.compiler_config(false))
+        .lazy_compilation(true)
+        .engine();
+    Store::new(&engine)
+}
+
+const WAT: &str = r#"
+    (module
+        (type $binary (func (param i64 i64) (result i64)))
+        (table 2 funcref)
+        (elem (i32.const 0) $add $mul)
+        (func $add (type $binary) (i64.add (local.get 0) (local.get 1)))
+        (func $mul (type $binary) (i64.mul (local.get 0) (local.get 1)))
+        (func $fac (export "fac") (param i64) (result i64)
+            (if (result i64) (i64.eqz (local.get 0))
+                (then (i64.const 1))
+                (else
+                    (call $mul
+                        (local.get 0)
+                        (call $fac (i64.sub (local.get 0) (i64.const 1)))))))
+        (func (export "apply") (param i64 i64 i32) (result i64)
+            (call_indirect (type $binary) (local.get 0) (local.get 1) (local.get 2)))
+        (func $mix (param i32 f64 i64 f32 i32 f64 i64 f32 i32 f64) (result f64)
+            (f64.add
+                (f64.add
+                    (f64.convert_i32_s (i32.add (local.get 0) (i32.add (local.get 4) (local.get 8))))
+                    (f64.add (local.get 1) (f64.add (local.get 5) (local.get 9))))
+                (f64.add
+                    (f64.convert_i64_s (i64.add (local.get 2) (local.get 6)))
+                    (f64.promote_f32 (f32.add (local.get 3) (local.get 7))))))
+        (func (export "mix") (result f64)
+            (call $mix
+                (i32.const 1) (f64.const 0.5) (i64.const 10) (f32.const 0.25) (i32.const 100)
+                (f64.const 1000) (i64.const 10000) (f32.const 0.125) (i32.const 100000)
+                (f64.const 1000000)))
+        (func (export "divide") (param i32 i32) (result i32)
+            (i32.div_s (local.get 0) (local.get 1)))
+        (func $fail (export "fail") unreachable)
+        (func (export "call_fail") (call $fail)))
+"#;
+
+#[compiler_test(lazy_compilation)]
+fn lazily_compiled_functions_run(config: crate::Config) -> Result<()> {
+    let store = get_store(config);
+    let module = Module::new(&store, WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let fac = instance.lookup_function("fac").unwrap();
+    let apply = instance.lookup_function("apply").unwrap();
+    let mix = instance.lookup_function("mix").unwrap();
+
+    for _ in 0..2 {
+        assert_eq!(&*fac.call(&[Val::I64(10)])?, &[Val::I64(3628800)]);
+        let args = [Val::I64(6), Val::I64(7), Val::I32(0)];
+        assert_eq!(&*apply.call(&args)?, &[Val::I64(13)]);
+        let args = [Val::I64(6), Val::I64(7), Val::I32(1)];
+        assert_eq!(&*apply.call(&args)?, &[Val::I64(42)]);
+        assert_eq!(&*mix.call(&[])?, &[Val::F64(1111111.875)]);
+    }
+    Ok(())
+}
+
+#[compiler_test(lazy_compilation)]
+fn lazily_compiled_functions_call_host_functions(config: crate::Config) -> Result<()> {
+    let store = get_store(config);
+    let wat = r#"
+        (import "host" "scale" (func $scale (param f64 i32) (result f64)))
+        (func (export "run") (param f64) (result f64)
+            (call $scale (local.get 0) (i32.const 3)))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let scale = Function::new_native(&store, |x: f64, factor: i32| x * f64::from(factor));
+    let instance = Instance::new(&module, &imports! { "host" => { "scale" => scale } })?;
+    let run = instance.lookup_function("run").unwrap();
+    assert_eq!(&*run.call(&[Val::F64(1.5)])?, &[Val::F64(4.5)]);
+    Ok(())
+}
+
+#[compiler_test(lazy_compilation)]
+fn lazily_compiled_functions_trap(config: crate::Config) -> Result<()> {
+    let store = get_store(config);
+    let module = Module::new(&store, WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let divide = instance.lookup_function("divide").unwrap();
+    let call_fail = instance.lookup_function("call_fail").unwrap();
+
+    let args = [Val::I32(1), Val::I32(0)];
+    let error = divide.call(&args).unwrap_err();
+    assert_eq!(error.to_trap(), Some(TrapCode::IntegerDivisionByZero));
+    assert_eq!(&*divide.call(&[Val::I32(7), Val::I32(2)])?, &[Val::I32(3)]);
+
+    let error = call_fail.call(&[]).unwrap_err();
+    let names = error
+        .trace()
+        .iter()
+        .map(|frame| frame.function_name().map(str::to_string))
+        .collect::<Vec<_>>();
+    assert_eq!(names[0].as_deref(), Some("fail"));
+    assert_eq!(error.to_trap(), Some(TrapCode::UnreachableCodeReached));
+    Ok(())
+}
+
+#[compiler_test(lazy_compilation)]
+fn functions_are_compiled_once_across_threads(config: crate::Config) -> Result<()> {
+    let store = get_store(config);
+    let module = Module::new(&store, WAT)?;
+    let threads = (0..8)
+        .map(|_| {
+            let module = module.clone();
+            thread::spawn(move || {
+                let instance = Instance::new(&module, &imports! {}).unwrap();
+                let fac = instance.lookup_function("fac").unwrap();
+                fac.call(&[Val::I64(5)]).unwrap()[0].unwrap_i64()
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        assert_eq!(thread.join().unwrap(), 120);
+    }
+    Ok(())
+}
+
+#[compiler_test(lazy_compilation)]
+fn lazily_compiled_modules_cant_be_serialized(config: crate::Config) -> Result<()> {
+    let store = get_store(config);
+    let executable = store
+        .engine()
+        .compile(&wat2wasm(WAT.as_bytes())?, store.tunables())?;
+    assert!(executable.serialize().is_err());
+    Ok(())
+}
+
+#[test]
+fn lazily_compiled_functions_burn_gas() -> Result<()> {
+    let engine = Universal::new(Singlepass::default())
+        .lazy_compilation(true)
+        .engine();
+    let store = Store::new(&engine);
+    let wat = r#"
+        (import "host" "func" (func))
+        (import "host" "gas" (func (param i32)))
+        (func (export "bar")
+            call 0
+            i32.const 100
+            call 1)
+        (func (export "zoo")
+            loop
+                i32.const 100
+                call 1
+                br 0
+            end)
+    "#;
+    let module = Module::new(&store, wat)?;
+    let mut gas_counter = FastGasCounter::new(500, 3);
+    static HITS: AtomicUsize = AtomicUsize::new(0);
+    let instance = Instance::new_with_config(
+        &module,
+        unsafe { InstanceConfig::default().with_counter(ptr::addr_of_mut!(gas_counter)) },
+        &imports! {
+            "host" => {
+                "func" => Function::new(&store, FunctionType::new(vec![], vec![]), |_| {
+                    HITS.fetch_add(1, SeqCst);
+                    Ok(vec![])
+                }),
+                "gas" => Function::new(&store, FunctionType::new(vec![ValType::I32], vec![]), |_| {
+                    // It shall be never called, as call is intrinsified.
+                    panic!("gas was called");
+                }),
+            },
+        },
+    )?;
+    let bar = instance.lookup_function("bar").unwrap();
+    let zoo = instance.lookup_function("zoo").unwrap();
+
+    bar.call(&[])?;
+    assert_eq!(HITS.load(SeqCst), 1);
+    assert_eq!(gas_counter.burnt(), 300);
+    let error = zoo.call(&[]).unwrap_err();
+    assert_eq!(error.message(), "gas limit exceeded");
+    assert_eq!(gas_counter.burnt(), 600);
+    Ok(())
+}
@@ -7,14 +7,26 @@ extern crate compiler_test_derive;
 
 mod config;
 mod deterministic;
+mod epoch_interruption;
+mod exceptions;
 mod fast_gas_metering;
 mod imports;
 mod issues;
+#[cfg(all(target_arch = "x86_64", unix))]
+mod lazy_compilation;
+mod memory64;
+mod middlewares;
+mod multi_memory;
+mod multi_value;
 // mod multi_value_imports;
 mod compilation;
 mod native_functions;
 mod serialize;
+mod snapshot;
 mod stack_limiter;
+mod streaming;
+mod tail_call;
+mod threads;
 mod traps;
 mod wast;
 
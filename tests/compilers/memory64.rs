@@ -0,0 +1,132 @@
+use std::cell::Cell;
+use wasmer::*;
+use wasmer_vm::TrapCode;
+
+fn memory64_config(mut config: crate::Config) -> crate::Config {
+    let mut features = Features::default();
+    features.memory64(true);
+    config.set_features(features);
+    config
+}
+
+fn get_instance(store: &Store) -> anyhow::Result<Instance> {
+    let wat = r#"
+        (memory (export "memory") i64 1 4)
+        (data (i64.const 16) "hello")
+        (data $passive "world")
+        (func (export "load") (param i64) (result i64)
+            (i64.load (local.get 0)))
+        (func (export "load_offset") (param i64) (result i32)
+            (i32.load8_u offset=0x10000 (local.get 0)))
+        (func (export "store") (param i64 i64)
+            (i64.store (local.get 0) (local.get 1)))
+        (func (export "size") (result i64)
+            (memory.size))
+        (func (export "grow") (param i64) (result i64)
+            (memory.grow (local.get 0)))
+        (func (export "fill") (param i64 i32 i64)
+            (memory.fill (local.get 0) (local.get 1) (local.get 2)))
+        (func (export "copy") (param i64 i64 i64)
+            (memory.copy (local.get 0) (local.get 1) (local.get 2)))
+        (func (export "init") (param i64 i32 i32)
+            (memory.init $passive (local.get 0) (local.get 1) (local.get 2)))
+    "#;
+    let module = Module::new(&store, wat)?;
+    Ok(Instance::new(&module, &imports! {})?)
+}
+
+#[compiler_test(memory64)]
+fn load_store_and_grow(config: crate::Config) -> anyhow::Result<()> {
+    let store = memory64_config(config).store();
+    let instance = get_instance(&store)?;
+    let memory: Memory = instance.get_with_generics::<_, (), ()>("memory")?;
+    assert!(memory.ty().memory64);
+
+    let load = instance.get_native_function::<i64, i64>("load")?;
+    let store_fn = instance.get_native_function::<(i64, i64), ()>("store")?;
+    let size = instance.get_native_function::<(), i64>("size")?;
+    let grow = instance.get_native_function::<i64, i64>("grow")?;
+
+    store_fn.call(65528, 0x0102_0304_0506_0708)?;
+    assert_eq!(load.call(65528)?, 0x0102_0304_0506_0708);
+
+    assert_eq!(size.call()?, 1);
+    assert_eq!(grow.call(1)?, 1);
+    assert_eq!(size.call()?, 2);
+    assert_eq!(grow.call(3)?, -1);
+    assert_eq!(grow.call(i64::MAX)?, -1);
+    store_fn.call(2 * 65536 - 8, 42)?;
+    assert_eq!(load.call(2 * 65536 - 8)?, 42);
+
+    Ok(())
+}
+
+#[compiler_test(memory64)]
+fn out_of_bounds_accesses_trap(config: crate::Config) -> anyhow::Result<()> {
+    let store = memory64_config(config).store();
+    let instance = get_instance(&store)?;
+
+    let load = instance.get_native_function::<i64, i64>("load")?;
+    let load_offset = instance.get_native_function::<i64, i32>("load_offset")?;
+
+    for addr in [65529, 0x1_0000_0000, -8, -1] {
+        let err = load.call(addr).unwrap_err();
+        assert_eq!(err.to_trap(), Some(TrapCode::HeapAccessOutOfBounds));
+    }
+    // The static offset doesn't wrap around the address space.
+    let err = load_offset.call(-0x8000).unwrap_err();
+    assert_eq!(err.to_trap(), Some(TrapCode::HeapAccessOutOfBounds));
+
+    Ok(())
+}
+
+#[compiler_test(memory64)]
+fn bulk_memory(config: crate::Config) -> anyhow::Result<()> {
+    let store = memory64_config(config).store();
+    let instance = get_instance(&store)?;
+    let memory: Memory = instance.get_with_generics::<_, (), ()>("memory")?;
+
+    let fill = instance.get_native_function::<(i64, i32, i64), ()>("fill")?;
+    let copy = instance.get_native_function::<(i64, i64, i64), ()>("copy")?;
+    let init = instance.get_native_function::<(i64, i32, i32), ()>("init")?;
+
+    let bytes = |start: u64, end: u64| -> Vec<u8> {
+        memory.view64::<u8>()[start as usize..end as usize]
+            .iter()
+            .map(Cell::get)
+            .collect()
+    };
+    assert_eq!(bytes(16, 21), b"hello");
+
+    fill.call(100, 7, 4)?;
+    assert_eq!(bytes(99, 105), [0, 7, 7, 7, 7, 0]);
+    copy.call(200, 16, 5)?;
+    assert_eq!(bytes(200, 205), b"hello");
+    init.call(300, 0, 5)?;
+    assert_eq!(bytes(300, 305), b"world");
+
+    assert!(fill.call(65535, 0, 2).is_err());
+    assert!(copy.call(0, 0x1_0000_0000, 1).is_err());
+    assert!(init.call(-1, 0, 1).is_err());
+
+    Ok(())
+}
+
+#[compiler_test(memory64)]
+fn host_access(config: crate::Config) -> anyhow::Result<()> {
+    let store = memory64_config(config).store();
+    let instance = get_instance(&store)?;
+    let memory: Memory = instance.get_with_generics::<_, (), ()>("memory")?;
+    let load = instance.get_native_function::<i64, i64>("load")?;
+
+    let ptr = WasmPtr64::<u64>::new(1024);
+    ptr.deref(&memory).unwrap().set(0x1122_3344_5566_7788);
+    assert_eq!(load.call(1024)?, 0x1122_3344_5566_7788);
+
+    let hello = WasmPtr64::<u8, Array>::new(16);
+    assert_eq!(hello.get_utf8_string(&memory, 5).unwrap(), "hello");
+    assert!(WasmPtr64::<u64>::new(65529).deref(&memory).is_none());
+    assert!(hello.deref(&memory, 0, u64::MAX).is_none());
+
+    Ok(())
+}
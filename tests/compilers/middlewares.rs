@@ -0,0 +1,174 @@
+use anyhow::Result;
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+use std::sync::Arc;
+use wasmer::wasmparser::Operator;
+use wasmer::*;
+
+/// Replaces every `i32.add` with an `i32.mul`.
+#[derive(Debug)]
+struct AddToMul;
+
+impl ModuleMiddleware for AddToMul {
+    fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
+        Box::new(AddToMul)
+    }
+}
+
+impl FunctionMiddleware for AddToMul {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        match operator {
+            Operator::I32Add => state.push_operator(Operator::I32Mul),
+            _ => state.push_operator(operator),
+        }
+        Ok(())
+    }
+}
+
+/// Adds one to the result of every `i32.mul`.
+#[derive(Debug)]
+struct IncrementMul;
+
+impl ModuleMiddleware for IncrementMul {
+    fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
+        Box::new(IncrementMul)
+    }
+}
+
+impl FunctionMiddleware for IncrementMul {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        match operator {
+            Operator::I32Mul => {
+                state.push_operator(operator);
+                state.push_operator(Operator::I32Const { value: 1 });
+                state.push_operator(Operator::I32Add);
+            }
+            _ => state.push_operator(operator),
+        }
+        Ok(())
+    }
+}
+
+/// Counts the functions it is asked to generate a middleware for.
+#[derive(Debug, Default)]
+struct CountFunctions {
+    functions: AtomicUsize,
+}
+
+impl ModuleMiddleware for CountFunctions {
+    fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
+        self.functions.fetch_add(1, SeqCst);
+        Box::new(PassThrough)
+    }
+}
+
+#[derive(Debug)]
+struct PassThrough;
+
+impl FunctionMiddleware for PassThrough {}
+
+/// Swallows the `end` of every function.
+#[derive(Debug)]
+struct DropEnd;
+
+impl ModuleMiddleware for DropEnd {
+    fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
+        Box::new(DropEnd)
+    }
+}
+
+impl FunctionMiddleware for DropEnd {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        if let Operator::End = operator {
+            return Ok(());
+        }
+        state.push_operator(operator);
+        Ok(())
+    }
+}
+
+const ADD_WAT: &str = r#"
+    (module
+        (func (export "add") (param i32 i32) (result i32)
+            (i32.add (local.get 0) (local.get 1)))
+        (func (export "mul") (param i32 i32) (result i32)
+            (i32.mul (local.get 0) (local.get 1))))
+"#;
+
+fn call(instance: &Instance, name: &str, a: i32, b: i32) -> Result<i32> {
+    let f: NativeFunc<(i32, i32), i32> = instance.get_native_function(name)?;
+    Ok(f.call(a, b)?)
+}
+
+#[compiler_test(middlewares)]
+fn middleware_basic(mut config: crate::Config) -> Result<()> {
+    config.set_middlewares(vec![Arc::new(AddToMul) as Arc<dyn ModuleMiddleware>]);
+    let store = config.store();
+    let module = Module::new(&store, ADD_WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+
+    assert_eq!(call(&instance, "add", 4, 6)?, 24);
+    assert_eq!(call(&instance, "mul", 4, 6)?, 24);
+    Ok(())
+}
+
+#[compiler_test(middlewares)]
+fn middleware_chain_order_matters(mut config: crate::Config) -> Result<()> {
+    config.set_middlewares(vec![
+        Arc::new(AddToMul) as Arc<dyn ModuleMiddleware>,
+        Arc::new(IncrementMul),
+    ]);
+    let store = config.store();
+    let module = Module::new(&store, ADD_WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    assert_eq!(call(&instance, "add", 4, 6)?, 25);
+
+    config.set_middlewares(vec![
+        Arc::new(IncrementMul) as Arc<dyn ModuleMiddleware>,
+        Arc::new(AddToMul),
+    ]);
+    let store = config.store();
+    let module = Module::new(&store, ADD_WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    // The `i32.add` inserted by `IncrementMul` is rewritten as well.
+    assert_eq!(call(&instance, "add", 4, 6)?, 24);
+    assert_eq!(call(&instance, "mul", 4, 6)?, 24);
+    Ok(())
+}
+
+#[compiler_test(middlewares)]
+fn middleware_runs_once_per_local_function(mut config: crate::Config) -> Result<()> {
+    let counter = Arc::new(CountFunctions::default());
+    config.set_middlewares(vec![counter.clone() as Arc<dyn ModuleMiddleware>]);
+    let store = config.store();
+    let module = Module::new(&store, ADD_WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+
+    assert_eq!(counter.functions.load(SeqCst), 2);
+    assert_eq!(call(&instance, "add", 4, 6)?, 10);
+    Ok(())
+}
+
+#[compiler_test(middlewares)]
+fn middleware_swallowing_the_end_fails_compilation(mut config: crate::Config) -> Result<()> {
+    config.set_middlewares(vec![Arc::new(DropEnd) as Arc<dyn ModuleMiddleware>]);
+    let store = config.store();
+    let error = Module::new(&store, ADD_WAT).unwrap_err();
+    assert!(
+        error.to_string().contains("ended before its final `end`"),
+        "{}",
+        error
+    );
+    Ok(())
+}
@@ -0,0 +1,163 @@
+use std::cell::Cell;
+use wasmer::*;
+use wasmer_vm::TrapCode;
+
+fn multi_memory_config(mut config: crate::Config) -> crate::Config {
+    let mut features = Features::default();
+    features.multi_memory(true).memory64(true);
+    config.set_features(features);
+    config
+}
+
+fn get_instance(store: &Store, persistent: &Memory) -> anyhow::Result<Instance> {
+    let wat = r#"
+        (memory $persistent (import "env" "persistent") 1)
+        (memory $scratch (export "scratch") 1 2)
+        (memory $wide (export "wide") i64 1)
+        (data (memory $scratch) (i32.const 8) "scratch")
+        (data (memory $wide) (i64.const 8) "wide")
+        (func (export "load") (param i32 i32) (result i32)
+            (if (result i32) (i32.eqz (local.get 0))
+                (then (i32.load8_u (local.get 1)))
+                (else (i32.load8_u $scratch (local.get 1)))))
+        (func (export "load_wide") (param i64) (result i32)
+            (i32.load8_u $wide offset=5 (local.get 0)))
+        (func (export "store") (param i32 i32 i32)
+            (if (i32.eqz (local.get 0))
+                (then (i32.store8 (local.get 1) (local.get 2)))
+                (else (i32.store8 $scratch offset=1000 (local.get 1) (local.get 2)))))
+        (func (export "sizes") (result i64)
+            (i64.or
+                (i64.shl (i64.extend_i32_u (memory.size $scratch)) (i64.const 32))
+                (i64.extend_i32_u (memory.size $persistent))))
+        (func (export "grow_scratch") (param i32) (result i32)
+            (memory.grow $scratch (local.get 0)))
+        (func (export "fill_scratch") (param i32 i32 i32)
+            (memory.fill $scratch (local.get 0) (local.get 1) (local.get 2)))
+        (func (export "save") (param i32 i32 i32)
+            (memory.copy $persistent $scratch (local.get 0) (local.get 1) (local.get 2)))
+        (func (export "restore") (param i32 i32 i32)
+            (memory.copy $scratch $persistent (local.get 0) (local.get 1) (local.get 2)))
+        (func (export "move") (param i32 i32 i32)
+            (memory.copy $scratch $scratch (local.get 0) (local.get 1) (local.get 2)))
+        (func (export "widen") (param i64 i32 i32)
+            (memory.copy $wide $scratch (local.get 0) (local.get 1) (local.get 2)))
+    "#;
+    let module = Module::new(&store, wat)?;
+    Ok(Instance::new(
+        &module,
+        &imports! { "env" => { "persistent" => persistent.clone() } },
+    )?)
+}
+
+fn bytes(memory: &Memory, start: usize, end: usize) -> Vec<u8> {
+    memory.view::<u8>()[start..end]
+        .iter()
+        .map(Cell::get)
+        .collect()
+}
+
+#[compiler_test(multi_memory)]
+fn memories_are_isolated(config: crate::Config) -> anyhow::Result<()> {
+    let store = multi_memory_config(config).store();
+    let persistent = Memory::new(&store, MemoryType::new(1, None, false))?;
+    let instance = get_instance(&store, &persistent)?;
+    let scratch: Memory = instance.get_with_generics::<_, (), ()>("scratch")?;
+
+    let load = instance.get_native_function::<(i32, i32), i32>("load")?;
+    let load_wide = instance.get_native_function::<i64, i32>("load_wide")?;
+    let store_fn = instance.get_native_function::<(i32, i32, i32), ()>("store")?;
+    let sizes = instance.get_native_function::<(), i64>("sizes")?;
+    let grow_scratch = instance.get_native_function::<i32, i32>("grow_scratch")?;
+    let fill_scratch = instance.get_native_function::<(i32, i32, i32), ()>("fill_scratch")?;
+
+    assert_eq!(bytes(&scratch, 8, 15), b"scratch");
+    assert_eq!(load.call(1, 8)?, i32::from(b's'));
+    assert_eq!(load.call(0, 8)?, 0);
+    assert_eq!(load_wide.call(3)?, i32::from(b'w'));
+
+    store_fn.call(0, 100, 1)?;
+    store_fn.call(1, 99, 2)?;
+    assert_eq!(bytes(&persistent, 100, 101), [1]);
+    assert_eq!(bytes(&scratch, 1099, 1100), [2]);
+    assert_eq!(load.call(1, 1099)?, 2);
+    fill_scratch.call(200, 3, 2)?;
+    assert_eq!(bytes(&scratch, 199, 203), [0, 3, 3, 0]);
+    assert_eq!(bytes(&persistent, 199, 203), [0, 0, 0, 0]);
+
+    assert_eq!(sizes.call()?, 1 << 32 | 1);
+    assert_eq!(grow_scratch.call(1)?, 1);
+    assert_eq!(grow_scratch.call(1)?, -1);
+    assert_eq!(sizes.call()?, 2 << 32 | 1);
+    assert_eq!(persistent.size(), Pages(1));
+    store_fn.call(1, 64536, 4)?;
+    assert_eq!(load.call(1, 65536)?, 4);
+    assert_eq!(bytes(&scratch, 65536, 65537), [4]);
+    let err = store_fn.call(0, 65536, 4).unwrap_err();
+    assert_eq!(err.to_trap(), Some(TrapCode::HeapAccessOutOfBounds));
+    let err = store_fn.call(1, 130072, 5).unwrap_err();
+    assert_eq!(err.to_trap(), Some(TrapCode::HeapAccessOutOfBounds));
+
+    Ok(())
+}
+
+#[compiler_test(multi_memory)]
+fn copy_between_memories(config: crate::Config) -> anyhow::Result<()> {
+    let store = multi_memory_config(config).store();
+    let persistent = Memory::new(&store, MemoryType::new(1, None, false))?;
+    let instance = get_instance(&store, &persistent)?;
+    let scratch: Memory = instance.get_with_generics::<_, (), ()>("scratch")?;
+    let wide: Memory = instance.get_with_generics::<_, (), ()>("wide")?;
+
+    let save = instance.get_native_function::<(i32, i32, i32), ()>("save")?;
+    let restore = instance.get_native_function::<(i32, i32, i32), ()>("restore")?;
+    let move_ = instance.get_native_function::<(i32, i32, i32), ()>("move")?;
+    let widen = instance.get_native_function::<(i64, i32, i32), ()>("widen")?;
+
+    save.call(1000, 8, 7)?;
+    assert_eq!(bytes(&persistent, 1000, 1007), b"scratch");
+    restore.call(2000, 1000, 7)?;
+    assert_eq!(bytes(&scratch, 2000, 2007), b"scratch");
+    move_.call(10, 8, 7)?;
+    assert_eq!(bytes(&scratch, 8, 17), b"scscratch");
+    widen.call(60000, 8, 4)?;
+    assert_eq!(bytes(&wide, 60000, 60004), b"scsc");
+
+    // Each range is checked against its own memory.
+    for (dst, src) in [(65533, 0), (0, 65533), (-1, 0), (0, -1)] {
+        let err = save.call(dst, src, 4).unwrap_err();
+        assert_eq!(err.to_trap(), Some(TrapCode::HeapAccessOutOfBounds));
+    }
+    let err = widen.call(1 << 32, 0, 1).unwrap_err();
+    assert_eq!(err.to_trap(), Some(TrapCode::HeapAccessOutOfBounds));
+    save.call(65532, 65532, 4)?;
+
+    Ok(())
+}
+
+#[compiler_test(multi_memory)]
+fn instance_exports_every_memory(config: crate::Config) -> anyhow::Result<()> {
+    let store = multi_memory_config(config).store();
+    let persistent = Memory::new(&store, MemoryType::new(1, None, false))?;
+    let wat = r#"
+        (memory (import "env" "persistent") 1)
+        (memory (export "scratch") 1)
+        (export "persistent" (memory 0))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(
+        &module,
+        &imports! { "env" => { "persistent" => persistent.clone() } },
+    )?;
+
+    let memories = instance.memories();
+    let names = memories
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["persistent", "scratch"]);
+    assert!(memories[0].1.same(&persistent));
+    assert!(!memories[1].1.same(&persistent));
+
+    Ok(())
+}
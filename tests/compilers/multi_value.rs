@@ -0,0 +1,267 @@
+use wasmer::*;
+
+#[compiler_test(multi_value)]
+fn multiple_results(config: crate::Config) -> anyhow::Result<()> {
+    let store = config.store();
+    // Small results come back in registers, the others through memory the
+    // caller provides.
+    let wat = r#"
+        (func $pair (param i32) (result i32 i64)
+            (local.get 0)
+            (i64.extend_i32_s (i32.mul (local.get 0) (i32.const 2))))
+        (func $floats (param f64) (result f64 f32)
+            (f64.neg (local.get 0))
+            (f32.demote_f64 (local.get 0)))
+        (func $many (param i64 i64 i64 i64 i64 i64 i64 f64)
+                    (result i64 f64 i32 i64 f32)
+            (i64.add (local.get 0) (local.get 6))
+            (f64.add (local.get 7) (f64.const 0.5))
+            (i32.wrap_i64 (local.get 5))
+            (i64.mul (local.get 1) (local.get 2))
+            (f32.const 1.25))
+        (func (export "pair") (param i32) (result i32 i64)
+            (call $pair (local.get 0)))
+        (func (export "floats") (param f64) (result f64 f32)
+            (call $floats (local.get 0)))
+        (func (export "many") (param i64) (result i64 f64 i32 i64 f32)
+            (call $many
+                (local.get 0) (i64.const 2) (i64.const 3) (i64.const 4)
+                (i64.const 5) (i64.const 6) (i64.const 7) (f64.const 8)))
+        (func (export "sum") (param i64) (result i64)
+            (local i64 f64 i32 i64 f32)
+            (call $many
+                (local.get 0) (i64.const 2) (i64.const 3) (i64.const 4)
+                (i64.const 5) (i64.const 6) (i64.const 7) (f64.const 8))
+            (local.set 5)
+            (local.set 4)
+            (local.set 3)
+            (local.set 2)
+            (local.set 1)
+            (i64.add (local.get 1) (local.get 4))
+            (i64.add (i64.extend_i32_u (local.get 3)))
+            (i64.add (i64.trunc_f64_s (local.get 2)))
+            (i64.add (i64.trunc_f32_s (local.get 5))))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let pair = instance.get_native_function::<i32, (i32, i64)>("pair")?;
+    let floats = instance.get_native_function::<f64, (f64, f32)>("floats")?;
+    let many = instance.get_native_function::<i64, (i64, f64, i32, i64, f32)>("many")?;
+    let sum = instance.get_native_function::<i64, i64>("sum")?;
+
+    assert_eq!(pair.call(21)?, (21, 42));
+    assert_eq!(floats.call(1.5)?, (-1.5, 1.5));
+    assert_eq!(many.call(1)?, (8, 8.5, 6, 6, 1.25));
+    assert_eq!(sum.call(1)?, 8 + 6 + 6 + 8 + 1);
+
+    let many = instance.lookup_function("many").unwrap();
+    assert_eq!(
+        &*many.call(&[Val::I64(10)])?,
+        &[
+            Val::I64(17),
+            Val::F64(8.5),
+            Val::I32(6),
+            Val::I64(6),
+            Val::F32(1.25)
+        ]
+    );
+
+    Ok(())
+}
+
+#[compiler_test(multi_value)]
+fn blocks(config: crate::Config) -> anyhow::Result<()> {
+    let store = config.store();
+    let wat = r#"
+        (func (export "swap") (param i32 i64) (result i64 i32)
+            (local.get 0)
+            (local.get 1)
+            (block (param i32 i64) (result i64 i32)
+                (local.set 1)
+                (local.set 0)
+                (local.get 1)
+                (local.get 0)))
+        (func (export "fib") (param i32) (result i64)
+            (i64.const 0)
+            (i64.const 1)
+            (loop $l (param i64 i64) (result i64)
+                (if (param i64 i64) (result i64) (i32.eqz (local.get 0))
+                    (then (drop))
+                    (else
+                        (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+                        (call $fib_step)
+                        (br $l)))))
+        (func $fib_step (param i64 i64) (result i64 i64)
+            (local.get 1)
+            (i64.add (local.get 0) (local.get 1)))
+        (func (export "select") (param i32) (result i32 f64)
+            (block $a (result i32 f64)
+                (block $b (result i32 f64)
+                    (block $c (result i32 f64)
+                        (i32.const 1)
+                        (f64.const 1.5)
+                        (local.get 0)
+                        (br_table $a $b $c))
+                    (drop)
+                    (i32.add (i32.const 30))
+                    (f64.const 3.5)
+                    (return))
+                (drop)
+                (i32.add (i32.const 20))
+                (f64.const 2.5)))
+        (func (export "early") (param i32) (result i32 i32)
+            (block (result i32 i32)
+                (i32.const 1)
+                (i32.const 2)
+                (br_if 0 (local.get 0))
+                (drop)
+                (drop)
+                (i32.const 3)
+                (i32.const 4)))
+        (func (export "if_params") (param i32 i32) (result i32)
+            (local.get 1)
+            (local.get 1)
+            (if (param i32 i32) (result i32) (local.get 0)
+                (then (i32.add))
+                (else (i32.mul))))
+        (func (export "if_no_else") (param i32) (result f32 i32)
+            (f32.const 1.5)
+            (i32.const 7)
+            (if (param f32 i32) (result f32 i32) (local.get 0)
+                (then
+                    (drop)
+                    (f32.neg)
+                    (i32.const 9))))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let swap = instance.get_native_function::<(i32, i64), (i64, i32)>("swap")?;
+    let fib = instance.get_native_function::<i32, i64>("fib")?;
+    let select = instance.get_native_function::<i32, (i32, f64)>("select")?;
+    let early = instance.get_native_function::<i32, (i32, i32)>("early")?;
+    let if_params = instance.get_native_function::<(i32, i32), i32>("if_params")?;
+    let if_no_else = instance.get_native_function::<i32, (f32, i32)>("if_no_else")?;
+
+    assert_eq!(swap.call(1, 2)?, (2, 1));
+    assert_eq!(fib.call(0)?, 0);
+    assert_eq!(fib.call(1)?, 1);
+    assert_eq!(fib.call(50)?, 12_586_269_025);
+    assert_eq!(select.call(0)?, (1, 1.5));
+    assert_eq!(select.call(1)?, (21, 2.5));
+    assert_eq!(select.call(2)?, (31, 3.5));
+    assert_eq!(select.call(3)?, (31, 3.5));
+    assert_eq!(early.call(1)?, (1, 2));
+    assert_eq!(early.call(0)?, (3, 4));
+    assert_eq!(if_params.call(1, 5)?, 10);
+    assert_eq!(if_params.call(0, 5)?, 25);
+    assert_eq!(if_no_else.call(1)?, (-1.5, 9));
+    assert_eq!(if_no_else.call(0)?, (1.5, 7));
+
+    Ok(())
+}
+
+#[compiler_test(multi_value)]
+fn host_functions(config: crate::Config) -> anyhow::Result<()> {
+    let store = config.store();
+    let wat = r#"
+        (type $small (func (param i32 i32) (result f32 i32)))
+        (type $large (func (param i32 i64) (result i64 f64 i32)))
+        (import "host" "small" (func $small (type $small)))
+        (import "host" "large" (func $large (type $large)))
+        (import "host" "dynamic_small" (func $dynamic_small (type $small)))
+        (import "host" "dynamic_large" (func $dynamic_large (type $large)))
+        (import "host" "floats" (func $floats (param f32 i32 f64) (result f64 i32 f32)))
+        (table funcref (elem $small $large $dynamic_small $dynamic_large))
+        (func (export "small") (param i32 i32) (result f32 i32)
+            (call $small (local.get 0) (local.get 1)))
+        (func (export "large") (param i32 i64) (result i64 f64 i32)
+            (call $large (local.get 0) (local.get 1)))
+        (func (export "dynamic_small") (param i32 i32) (result f32 i32)
+            (call $dynamic_small (local.get 0) (local.get 1)))
+        (func (export "dynamic_large") (param i32 i64) (result i64 f64 i32)
+            (call $dynamic_large (local.get 0) (local.get 1)))
+        (func (export "floats") (param f32 i32 f64) (result f64 i32 f32)
+            (call $floats (local.get 0) (local.get 1) (local.get 2)))
+        (func (export "indirect_small") (param i32 i32 i32) (result f32 i32)
+            (call_indirect (type $small) (local.get 0) (local.get 1) (local.get 2)))
+        (func (export "indirect_large") (param i32 i64 i32) (result i64 f64 i32)
+            (call_indirect (type $large) (local.get 0) (local.get 1) (local.get 2)))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let small_type = FunctionType::new(
+        vec![ValType::I32, ValType::I32],
+        vec![ValType::F32, ValType::I32],
+    );
+    let large_type = FunctionType::new(
+        vec![ValType::I32, ValType::I64],
+        vec![ValType::I64, ValType::F64, ValType::I32],
+    );
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "host" => {
+                "small" => Function::new_native(&store, |a: i32, b: i32| (b as f32 * 2.0, a + 1)),
+                "large" => Function::new_native(&store, |a: i32, b: i64| (b * 3, a as f64 / 2.0, a - 1)),
+                "dynamic_small" => Function::new(&store, small_type, |values| {
+                    Ok(vec![Val::F32(values[1].unwrap_i32() as f32 * 4.0), Val::I32(values[0].unwrap_i32() + 2)])
+                }),
+                "dynamic_large" => Function::new(&store, large_type, |values| {
+                    let a = values[0].unwrap_i32();
+                    Ok(vec![Val::I64(values[1].unwrap_i64() * 5), Val::F64(a as f64 / 4.0), Val::I32(a - 2)])
+                }),
+                "floats" => Function::new_native(&store, |a: f32, b: i32, c: f64| (c * 2.0, b + 1, a / 2.0)),
+            },
+        },
+    )?;
+    let small = instance.get_native_function::<(i32, i32), (f32, i32)>("small")?;
+    let large = instance.get_native_function::<(i32, i64), (i64, f64, i32)>("large")?;
+    let dynamic_small = instance.get_native_function::<(i32, i32), (f32, i32)>("dynamic_small")?;
+    let dynamic_large =
+        instance.get_native_function::<(i32, i64), (i64, f64, i32)>("dynamic_large")?;
+    let floats = instance.get_native_function::<(f32, i32, f64), (f64, i32, f32)>("floats")?;
+    let indirect_small =
+        instance.get_native_function::<(i32, i32, i32), (f32, i32)>("indirect_small")?;
+    let indirect_large =
+        instance.get_native_function::<(i32, i64, i32), (i64, f64, i32)>("indirect_large")?;
+
+    assert_eq!(small.call(1, 3)?, (6.0, 2));
+    assert_eq!(large.call(7, 3)?, (9, 3.5, 6));
+    assert_eq!(dynamic_small.call(1, 3)?, (12.0, 3));
+    assert_eq!(dynamic_large.call(7, 3)?, (15, 1.75, 5));
+    assert_eq!(floats.call(1.5, 2, 2.5)?, (5.0, 3, 0.75));
+    assert_eq!(indirect_small.call(1, 3, 0)?, (6.0, 2));
+    assert_eq!(indirect_large.call(7, 3, 1)?, (9, 3.5, 6));
+    assert_eq!(indirect_small.call(1, 3, 2)?, (12.0, 3));
+    assert_eq!(indirect_large.call(7, 3, 3)?, (15, 1.75, 5));
+
+    Ok(())
+}
+
+#[compiler_test(multi_value)]
+fn tail_calls(config: crate::Config) -> anyhow::Result<()> {
+    let mut config = config;
+    let mut features = Features::default();
+    features.tail_call(true);
+    config.set_features(features);
+    let store = config.store();
+    let wat = r#"
+        (func $count (export "count") (param i64 i64 i64) (result i64 i64 i64)
+            (if (result i64 i64 i64) (i64.eqz (local.get 0))
+                (then (local.get 0) (local.get 1) (local.get 2))
+                (else (return_call $count
+                    (i64.sub (local.get 0) (i64.const 1))
+                    (i64.add (local.get 1) (i64.const 1))
+                    (i64.add (local.get 2) (local.get 0))))))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let count = instance.get_native_function::<(i64, i64, i64), (i64, i64, i64)>("count")?;
+
+    // Deeper than the native stack could hold without tail calls.
+    assert_eq!(
+        count.call(10_000_000, 0, 0)?,
+        (0, 10_000_000, 50_000_005_000_000)
+    );
+
+    Ok(())
+}
@@ -1,5 +1,9 @@
 use anyhow::Result;
+use std::io::Write;
+use std::sync::Arc;
 use wasmer::*;
+use wasmer_engine::Executable;
+use wasmer_engine_universal::UniversalExecutableRef;
 
 #[compiler_test(serialize)]
 fn test_serialize(config: crate::Config) -> Result<()> {
@@ -22,6 +26,259 @@ fn test_serialize(config: crate::Config) -> Result<()> {
     Ok(())
 }
 
+const CALLS_WAT: &str = r#"
+    (module
+        (import "host" "double" (func $double (param i32) (result i32)))
+        (memory (export "memory") 1)
+        (data (i32.const 8) "\2a")
+        (func $load (result i32)
+            (i32.load8_u (i32.const 8)))
+        (func (export "run") (result i32)
+            (call $double (call $load))))
+"#;
+
+fn run_calls(module: &Module) -> Result<()> {
+    let store = module.store();
+    let double = Function::new_native(store, |x: i32| x * 2);
+    let instance = Instance::new(module, &imports! { "host" => { "double" => double } })?;
+    let run = instance.exports().get_function("run")?;
+    assert_eq!(&*run.call(&[])?, &[Value::I32(84)]);
+    Ok(())
+}
+
+fn serialize_calls(store: &Store) -> Vec<u8> {
+    let wasm = wat2wasm(CALLS_WAT.as_bytes()).unwrap();
+    let engine = store.engine();
+    let executable = engine.compile(&wasm, store.tunables()).unwrap();
+    executable.serialize().unwrap()
+}
+
+#[compiler_test(serialize)]
+fn test_deserialize_from_file(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let mut file = tempfile::NamedTempFile::new()?;
+    file.write_all(&serialize_calls(&store))?;
+    file.flush()?;
+
+    let headless_store = config.headless_store();
+    let module = unsafe { Module::deserialize_from_file(&headless_store, file.path())? };
+    run_calls(&module)?;
+    // Loading the file again maps it again: the relocations of the first
+    // module didn't modify it.
+    let module = unsafe { Module::deserialize_from_file(&headless_store, file.path())? };
+    run_calls(&module)
+}
+
+#[compiler_test(serialize)]
+fn test_deserialize_copying_the_code(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let serialized = serialize_calls(&store);
+    let executable = unsafe { UniversalExecutableRef::deserialize(&serialized)? };
+    let engine: &dyn Engine = &**store.engine();
+    let engine = engine.downcast_ref::<UniversalEngine>().unwrap();
+    let artifact = engine.load_universal_executable_ref(&executable, store.tunables())?;
+    run_calls(&Module::from_universal_artifact(&store, Arc::new(artifact)))
+}
+
+#[compiler_test(serialize)]
+fn test_serialized_code_is_only_in_the_code_image(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wasm = wat2wasm(CALLS_WAT.as_bytes()).unwrap();
+    let engine: &dyn Engine = &**store.engine();
+    let engine = engine.downcast_ref::<UniversalEngine>().unwrap();
+    let executable = engine.compile_universal(&wasm, store.tunables())?;
+    let serialized = executable.serialize().unwrap();
+    let image = executable.code_image();
+    for (symbol, range) in image.symbols.iter() {
+        let code = &image.bytes[range.clone()];
+        let copies = serialized
+            .windows(code.len())
+            .filter(|window| *window == code)
+            .count();
+        assert_eq!(copies, 1, "{:?} is serialized {} times", symbol, copies);
+    }
+
+    // The code is read back from the code image.
+    let executable = unsafe { UniversalExecutableRef::deserialize(&serialized)? };
+    let artifact = engine.load_universal_executable_ref(&executable, store.tunables())?;
+    run_calls(&Module::from_universal_artifact(&store, Arc::new(artifact)))?;
+    let owned = executable.to_owned()?;
+    assert_eq!(owned.code_image().bytes, image.bytes);
+    Ok(())
+}
+
+#[compiler_test(serialize)]
+fn test_deserialize_from_truncated_file(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let serialized = serialize_calls(&store);
+    let mut file = tempfile::NamedTempFile::new()?;
+    file.write_all(&serialized[..40])?;
+    file.flush()?;
+
+    let result = unsafe { Module::deserialize_from_file(&store, file.path()) };
+    assert!(matches!(result, Err(DeserializeError::Incompatible(_))));
+    Ok(())
+}
+
+#[compiler_test(serialize)]
+fn test_serialized_header(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let serialized = serialize_calls(&store);
+    let executable = unsafe { UniversalExecutableRef::deserialize(&serialized)? };
+    let header = executable.header();
+    let compiler = match config.compiler {
+        crate::Compiler::LLVM => "llvm",
+        crate::Compiler::Cranelift => "cranelift",
+        crate::Compiler::Singlepass => "singlepass",
+    };
+    assert_eq!(header.engine_version, wasmer_engine_universal::VERSION);
+    assert_eq!(header.compiler, compiler);
+    let version = config
+        .compiler_config(false)
+        .compiler()
+        .version()
+        .to_string();
+    assert_eq!(header.compiler_version, version);
+    assert_eq!(header.triple, store.engine().target().triple().to_string());
+    assert_eq!(header.features, executable.features());
+    assert_eq!(header.memory_styles.len(), 1);
+    Ok(())
+}
+
+#[compiler_test(serialize)]
+fn test_deserialize_corrupted(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let serialized = serialize_calls(&store);
+
+    // The name of the exported memory, in the metadata.
+    let mut corrupted = serialized.clone();
+    let index = corrupted
+        .windows(b"memory".len())
+        .position(|window| window == b"memory")
+        .unwrap();
+    corrupted[index] ^= 0xFF;
+    let result = unsafe { UniversalExecutableRef::deserialize(&corrupted) };
+    assert!(matches!(result, Err(DeserializeError::ContentHashMismatch)));
+
+    // The last byte of the code image, which is hashed when the
+    // executable is loaded rather than deserialized.
+    let mut corrupted = serialized;
+    let index = corrupted.len() - 25;
+    corrupted[index] ^= 0xFF;
+    let executable = unsafe { UniversalExecutableRef::deserialize(&corrupted)? };
+    assert!(matches!(
+        executable.check_code_hash(),
+        Err(DeserializeError::ContentHashMismatch)
+    ));
+    let result = store.engine().load(&executable, store.tunables());
+    assert!(matches!(result, Err(DeserializeError::ContentHashMismatch)));
+    let mut file = tempfile::NamedTempFile::new()?;
+    file.write_all(&corrupted)?;
+    file.flush()?;
+    let result = unsafe { Module::deserialize_from_file(&store, file.path()) };
+    assert!(matches!(result, Err(DeserializeError::ContentHashMismatch)));
+
+    // Unless the engine is told not to check the code.
+    let engine = Universal::headless().check_code_hash(false).engine();
+    engine.load_universal_executable_ref(&executable, store.tunables())?;
+    Ok(())
+}
+
+#[compiler_test(serialize)]
+fn test_deserialize_other_versions(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let serialized = serialize_calls(&store);
+
+    // The version of the format follows `\0wasmer-universal`.
+    let mut other_format = serialized.clone();
+    other_format[17] = 2;
+    let result = unsafe { UniversalExecutableRef::deserialize(&other_format) };
+    assert!(matches!(
+        result,
+        Err(DeserializeError::IncompatibleFormat {
+            expected: 5,
+            found: 2
+        })
+    ));
+
+    let version = wasmer_engine_universal::VERSION.as_bytes();
+    let mut other_engine = serialized;
+    let index = other_engine
+        .windows(version.len())
+        .position(|window| window == version)
+        .unwrap()
+        + version.len()
+        - 1;
+    other_engine[index] = if other_engine[index] == b'0' {
+        b'1'
+    } else {
+        b'0'
+    };
+    let result = unsafe { UniversalExecutableRef::deserialize(&other_engine) };
+    match result {
+        Err(DeserializeError::IncompatibleEngine { expected, found }) => {
+            assert_eq!(expected, wasmer_engine_universal::VERSION);
+            assert_ne!(found, expected);
+        }
+        _ => panic!("the engine version wasn't checked"),
+    }
+    Ok(())
+}
+
+#[compiler_test(serialize)]
+fn test_deserialize_with_other_tunables(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let mut file = tempfile::NamedTempFile::new()?;
+    file.write_all(&serialize_calls(&store))?;
+    file.flush()?;
+
+    let headless_store = config.headless_store();
+    let mut tunables = BaseTunables::for_target(headless_store.engine().target());
+    tunables.static_memory_bound = Pages(16);
+    let store = Store::new_with_tunables(&**headless_store.engine(), tunables);
+    let result = unsafe { Module::deserialize_from_file(&store, file.path()) };
+    assert!(matches!(
+        result,
+        Err(DeserializeError::IncompatibleMemoryStyle { index: 0, .. })
+    ));
+
+    // Loading the executable from memory checks the tunables too, and
+    // keeps the error typed through the `Engine` trait.
+    let serialized = std::fs::read(file.path())?;
+    let executable = unsafe { UniversalExecutableRef::deserialize(&serialized)? };
+    let result = store.engine().load(&executable, store.tunables());
+    assert!(matches!(
+        result,
+        Err(DeserializeError::IncompatibleMemoryStyle { index: 0, .. })
+    ));
+    Ok(())
+}
+
+#[test]
+#[cfg(target_arch = "x86_64")]
+fn deserialize_for_other_target() -> Result<()> {
+    let target = Target::new(
+        "aarch64-unknown-linux-gnu".parse().unwrap(),
+        CpuFeature::set(),
+    );
+    let engine = Universal::new(Singlepass::default())
+        .target(target)
+        .engine();
+    let serialized = serialize_calls(&Store::new(&engine));
+    let executable = unsafe { UniversalExecutableRef::deserialize(&serialized)? };
+
+    let engine = Universal::new(Singlepass::default()).engine();
+    let tunables = BaseTunables::for_target(engine.target());
+    let result = engine.load_universal_executable_ref(&executable, &tunables);
+    match result {
+        Err(DeserializeError::IncompatibleTarget { found, .. }) => {
+            assert_eq!(found, "aarch64-unknown-linux-gnu");
+        }
+        _ => panic!("the target wasn't checked"),
+    }
+    Ok(())
+}
+
 // #[compiler_test(serialize)]
 // fn test_deserialize(config: crate::Config) -> Result<()> {
 //     let store = config.store();
@@ -0,0 +1,119 @@
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+use std::sync::Arc;
+use wasmer::*;
+use wasmer_types::{FastGasCounter, InstanceConfig};
+
+fn get_module(store: &Store) -> Module {
+    let wat = r#"
+        (import "host" "init" (func $init))
+        (memory (export "memory") 1)
+        (table 2 funcref)
+        (global $counter (export "counter") (mut i32) (i32.const 0))
+        (data $greeting "hello")
+        (elem (i32.const 1) $forty_two)
+        (func $forty_two (result i32) (i32.const 42))
+        (func $start
+            (call $init)
+            (memory.init $greeting (i32.const 16) (i32.const 0) (i32.const 5))
+            (data.drop $greeting)
+            (drop (memory.grow (i32.const 1)))
+            (i32.store (i32.const 65544) (i32.const 0x01020304))
+            (global.set $counter (i32.const 7)))
+        (start $start)
+        (func (export "call") (param i32) (result i32)
+            (call_indirect (result i32) (local.get 0)))
+        (func (export "bump") (result i32)
+            (global.set $counter (i32.add (global.get $counter) (i32.const 1)))
+            (global.get $counter))
+        (func (export "reinit")
+            (memory.init $greeting (i32.const 0) (i32.const 0) (i32.const 5)))
+    "#;
+
+    Module::new(&store, &wat).unwrap()
+}
+
+fn get_imports(store: &Store, inits: Arc<AtomicUsize>) -> ImportObject {
+    imports! {
+        "host" => {
+            "init" => Function::new(store, FunctionType::new(vec![], vec![]), move |_| {
+                inits.fetch_add(1, SeqCst);
+                Ok(vec![])
+            }),
+        },
+    }
+}
+
+#[compiler_test(snapshot)]
+fn restore_skips_start(config: crate::Config) -> anyhow::Result<()> {
+    let store = config.store();
+    let module = get_module(&store);
+    let inits = Arc::new(AtomicUsize::new(0));
+    let imports = get_imports(&store, inits.clone());
+
+    let mut gas_counter = FastGasCounter::new(u64::MAX, 0);
+    let instance = Instance::new_with_config(
+        &module,
+        unsafe { InstanceConfig::default().with_counter(ptr::addr_of_mut!(gas_counter)) },
+        &imports,
+    )?;
+    assert_eq!(inits.load(SeqCst), 1);
+    gas_counter.burnt_gas = 1234;
+    let bytes = instance.snapshot()?.serialize()?;
+
+    // Changes made after the snapshot are not part of it.
+    let bump = instance.get_native_function::<(), i32>("bump")?;
+    assert_eq!(bump.call()?, 8);
+
+    let snapshot = unsafe { InstanceSnapshot::deserialize(&bytes)? };
+    assert_eq!(snapshot.burnt_gas(), 1234);
+    let mut restored_counter = FastGasCounter::new(u64::MAX, 0);
+    let restored = Instance::new_from_snapshot(
+        &module,
+        unsafe { InstanceConfig::default().with_counter(ptr::addr_of_mut!(restored_counter)) },
+        &imports,
+        &snapshot,
+    )?;
+    assert_eq!(inits.load(SeqCst), 1);
+    assert_eq!(restored_counter.burnt(), 1234);
+
+    let memory: Memory = restored.get_with_generics::<_, (), ()>("memory")?;
+    assert_eq!(memory.size(), Pages(2));
+    let view = memory.view::<u8>();
+    let greeting: Vec<u8> = view[16..21].iter().map(|cell| cell.get()).collect();
+    assert_eq!(greeting, b"hello");
+    assert_eq!(view[65544].get(), 0x04);
+    assert_eq!(view[65547].get(), 0x01);
+
+    let counter: Global = restored.get_with_generics::<_, (), ()>("counter")?;
+    assert_eq!(counter.get(), Val::I32(7));
+    let bump = restored.get_native_function::<(), i32>("bump")?;
+    assert_eq!(bump.call()?, 8);
+
+    let call = restored.get_native_function::<i32, i32>("call")?;
+    assert_eq!(call.call(1)?, 42);
+    assert!(call.call(0).is_err());
+
+    // The passive segment dropped by the start function stays dropped.
+    let reinit = restored.get_native_function::<(), ()>("reinit")?;
+    assert!(reinit.call().is_err());
+
+    Ok(())
+}
+
+#[compiler_test(snapshot)]
+fn restore_checks_module(config: crate::Config) -> anyhow::Result<()> {
+    let store = config.store();
+    let module = get_module(&store);
+    let imports = get_imports(&store, Arc::new(AtomicUsize::new(0)));
+    let snapshot = Instance::new(&module, &imports)?.snapshot()?;
+
+    let other = Module::new(&store, "(module (memory 1))")?;
+    let result =
+        Instance::new_from_snapshot(&other, InstanceConfig::default(), &imports! {}, &snapshot);
+    assert!(matches!(result, Err(InstantiationError::Snapshot(_))));
+
+    assert!(unsafe { InstanceSnapshot::deserialize(b"not a snapshot") }.is_err());
+
+    Ok(())
+}
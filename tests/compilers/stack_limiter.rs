@@ -194,6 +194,35 @@ fn stack_limit_no_args() {
     }
 }
 
+#[test]
+fn stack_limit_restored_after_overflow() {
+    let wat = r#"
+        (func $foo (param $depth i32)
+            (br_if 0 (i32.eqz (local.get $depth)))
+            (call $foo (i32.sub (local.get $depth) (i32.const 1))))
+        (func (export "main") (param $depth i32)
+            (call $foo (local.get $depth)))
+    "#;
+
+    let store = get_store();
+    let module = Module::new(&store, &wat).unwrap();
+    let instance = Instance::new_with_config(
+        &module,
+        unsafe { InstanceConfig::default().with_stack_limit(1000) },
+        &imports! {},
+    )
+    .unwrap();
+    let main_func = instance
+        .lookup_function("main")
+        .expect("expected function main");
+    // The frames unwound by the trap give their stack space back.
+    for _ in 0..10 {
+        let trap = main_func.call(&[Val::I32(10000)]).unwrap_err();
+        assert_eq!(trap.to_trap(), Some(TrapCode::StackOverflow));
+        assert!(main_func.call(&[Val::I32(10)]).is_ok());
+    }
+}
+
 #[test]
 fn deep_but_sane() {
     let wat = r#"
@@ -232,3 +261,50 @@ fn deep_but_sane() {
     let e = main_func.call(&[]);
     assert!(e.is_ok());
 }
+
+#[test]
+fn stack_limit_tail_calls() {
+    let wat = r#"
+        (func $tail (param $n i32)
+            (if (i32.eqz (local.get $n))
+                (then (return)))
+            (return_call $tail (i32.sub (local.get $n) (i32.const 1))))
+        (func $nested (param $n i32)
+            (if (i32.eqz (local.get $n))
+                (then (return)))
+            (call $nested (i32.sub (local.get $n) (i32.const 1))))
+        (func (export "tail")
+            (call $tail (i32.const 1000000)))
+        (func (export "nested")
+            (call $nested (i32.const 1000000)))
+    "#;
+
+    let mut features = Features::default();
+    features.tail_call(true);
+    let compiler = Singlepass::default();
+    let store = Store::new(&Universal::new(compiler).features(features).engine());
+    let module = Module::new(&store, &wat).unwrap();
+    let instance = Instance::new_with_config(
+        &module,
+        unsafe { InstanceConfig::default().with_stack_limit(1000) },
+        &imports! {},
+    );
+    assert!(instance.is_ok());
+    let instance = instance.unwrap();
+    // Each tail call releases the stack space of the frame it replaces.
+    let tail_func = instance
+        .lookup_function("tail")
+        .expect("expected function tail");
+    assert!(tail_func.call(&[]).is_ok());
+    assert!(tail_func.call(&[]).is_ok());
+    let nested_func = instance
+        .lookup_function("nested")
+        .expect("expected function nested");
+    match nested_func.call(&[]) {
+        Err(err) => {
+            let trap = err.to_trap().unwrap();
+            assert_eq!(trap, TrapCode::StackOverflow);
+        }
+        _ => assert!(false),
+    }
+}
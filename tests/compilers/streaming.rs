@@ -0,0 +1,180 @@
+use anyhow::Result;
+use std::io::{self, Read};
+use std::sync::Arc;
+use wasmer::wasmparser::Operator;
+use wasmer::*;
+use wasmer_engine::Executable;
+
+/// Reads the bytes it wraps `chunk_size` at a time.
+struct ChunkedReader<'a> {
+    bytes: &'a [u8],
+    chunk_size: usize,
+}
+
+impl Read for ChunkedReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.chunk_size.min(buf.len()).min(self.bytes.len());
+        buf[..read].copy_from_slice(&self.bytes[..read]);
+        self.bytes = &self.bytes[read..];
+        Ok(read)
+    }
+}
+
+fn from_chunks(store: &Store, wat: &str, chunk_size: usize) -> Result<Module, IoCompileError> {
+    let wasm = wat2wasm(wat.as_bytes()).unwrap();
+    Module::from_reader(
+        store,
+        ChunkedReader {
+            bytes: &wasm,
+            chunk_size,
+        },
+    )
+}
+
+const WAT: &str = r#"
+    (module
+        (memory (export "memory") 1)
+        (global $g (mut i32) (i32.const 2))
+        (func $add (export "add") (param i32 i32) (result i32)
+            (i32.add (local.get 0) (local.get 1)))
+        (func (export "load") (param i32) (result i32)
+            (i32.load8_u (local.get 0)))
+        (func (export "add_global") (param i32) (result i32)
+            (call $add (local.get 0) (global.get $g)))
+        (data (i32.const 0) "\2a"))
+"#;
+
+#[compiler_test(streaming)]
+fn streamed_module_runs(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    for &chunk_size in &[1, 7, 1 << 20] {
+        let module = from_chunks(&store, WAT, chunk_size)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let add = instance.lookup_function("add").unwrap();
+        let load = instance.lookup_function("load").unwrap();
+        let add_global = instance.lookup_function("add_global").unwrap();
+        assert_eq!(&*add.call(&[Val::I32(3), Val::I32(4)])?, &[Val::I32(7)]);
+        assert_eq!(&*load.call(&[Val::I32(0)])?, &[Val::I32(42)]);
+        assert_eq!(&*add_global.call(&[Val::I32(5)])?, &[Val::I32(7)]);
+    }
+    Ok(())
+}
+
+#[compiler_test(streaming)]
+fn streamed_module_is_compiled_like_whole_module(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let engine: &dyn Engine = &**store.engine();
+    let engine = engine.downcast_ref::<UniversalEngine>().unwrap();
+    let wasm = wat2wasm(
+        br#"
+        (module
+            (func $pick (export "pick") (param i32) (result f64)
+                (block $c (block $b (block $a
+                    (br_table $a $b $c (local.get 0)))
+                    (return (f64.const 1.5)))
+                    (return (f64.const -2.25)))
+                (f64.const 3.125))
+            (func (export "sum") (result f64)
+                (f64.add (call $pick (i32.const 0)) (call $pick (i32.const 2)))))
+        "#,
+    )?;
+
+    let whole = engine.compile_universal(&wasm, store.tunables())?;
+    let mut streaming = engine.compile_universal_streaming(store.tunables())?;
+    for chunk in wasm.chunks(3) {
+        streaming.push(chunk)?;
+    }
+    let streamed = streaming.finish()?;
+    assert_eq!(streamed.serialize().unwrap(), whole.serialize().unwrap());
+    Ok(())
+}
+
+#[compiler_test(streaming)]
+fn streamed_module_without_code(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let module = from_chunks(&store, r#"(module (memory (export "memory") 1))"#, 1)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    assert!(instance.lookup("memory").is_some());
+    Ok(())
+}
+
+#[compiler_test(streaming)]
+fn streamed_invalid_function(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wat = r#"
+        (module
+            (func (result i32) (i32.const 1))
+            (func (result i32) (i64.const 1)))
+    "#;
+    match from_chunks(&store, wat, 3) {
+        Err(IoCompileError::Compile(CompileError::Validate(_))) => {}
+        result => panic!("unexpected result: {:?}", result.map(|_| ())),
+    }
+    Ok(())
+}
+
+#[compiler_test(streaming)]
+fn streamed_truncated_module(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wasm = wat2wasm(WAT.as_bytes()).unwrap();
+    let truncated = &wasm[..wasm.len() - 3];
+    let result = Module::from_reader(
+        &store,
+        ChunkedReader {
+            bytes: truncated,
+            chunk_size: 5,
+        },
+    );
+    assert!(matches!(result, Err(IoCompileError::Compile(_))));
+    Ok(())
+}
+
+#[compiler_test(streaming)]
+fn streamed_read_error(config: crate::Config) -> Result<()> {
+    struct FailingReader;
+    impl Read for FailingReader {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::Other, "disconnected"))
+        }
+    }
+
+    let store = config.store();
+    let result = Module::from_reader(&store, FailingReader);
+    assert!(matches!(result, Err(IoCompileError::Io(_))));
+    Ok(())
+}
+
+/// Replaces every `i32.add` with an `i32.mul`.
+#[derive(Debug)]
+struct AddToMul;
+
+impl ModuleMiddleware for AddToMul {
+    fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
+        Box::new(AddToMul)
+    }
+}
+
+impl FunctionMiddleware for AddToMul {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        match operator {
+            Operator::I32Add => state.push_operator(Operator::I32Mul),
+            _ => state.push_operator(operator),
+        }
+        Ok(())
+    }
+}
+
+#[compiler_test(streaming)]
+fn streamed_module_with_middleware(mut config: crate::Config) -> Result<()> {
+    config.set_middlewares(vec![Arc::new(AddToMul) as Arc<dyn ModuleMiddleware>]);
+    let store = config.store();
+    let module = from_chunks(&store, WAT, 2)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let add = instance.lookup_function("add").unwrap();
+    assert_eq!(&*add.call(&[Val::I32(3), Val::I32(4)])?, &[Val::I32(12)]);
+    Ok(())
+}
@@ -0,0 +1,210 @@
+use std::ptr;
+use wasmer::*;
+use wasmer_types::{FastGasCounter, InstanceConfig};
+use wasmer_vm::TrapCode;
+
+fn tail_call_config(mut config: crate::Config) -> crate::Config {
+    let mut features = Features::default();
+    features.tail_call(true);
+    config.set_features(features);
+    config
+}
+
+#[compiler_test(tail_call)]
+fn deep_mutual_recursion(config: crate::Config) -> anyhow::Result<()> {
+    let store = tail_call_config(config).store();
+    let wat = r#"
+        (func $even (export "is_even") (param i64) (result i32)
+            (if (result i32) (i64.eqz (local.get 0))
+                (then (i32.const 1))
+                (else (return_call $odd (i64.sub (local.get 0) (i64.const 1))))))
+        (func $odd (param i64) (result i32)
+            (if (result i32) (i64.eqz (local.get 0))
+                (then (i32.const 0))
+                (else (return_call $even (i64.sub (local.get 0) (i64.const 1))))))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let is_even = instance.get_native_function::<i64, i32>("is_even")?;
+
+    // Way deeper than the native stack could hold without tail calls.
+    assert_eq!(is_even.call(10_000_000)?, 1);
+    assert_eq!(is_even.call(10_000_001)?, 0);
+
+    Ok(())
+}
+
+#[compiler_test(tail_call)]
+fn indirect_tail_calls(config: crate::Config) -> anyhow::Result<()> {
+    let store = tail_call_config(config).store();
+    let wat = r#"
+        (type $t (func (param i64 i64) (result i64)))
+        (table 2 funcref)
+        (elem (i32.const 0) $sum $other)
+        (func $sum (type $t)
+            (if (result i64) (i64.eqz (local.get 0))
+                (then (local.get 1))
+                (else (return_call_indirect (type $t)
+                    (i64.sub (local.get 0) (i64.const 1))
+                    (i64.add (local.get 1) (local.get 0))
+                    (i32.const 0)))))
+        (func $other (param i32) (result i32)
+            (local.get 0))
+        (global $index (export "index") (mut i32) (i32.const 0))
+        (func (export "sum") (param i64) (result i64)
+            (call $sum (local.get 0) (i64.const 0)))
+        (func (export "call") (type $t)
+            (return_call_indirect (type $t) (local.get 0) (local.get 1) (global.get $index)))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let sum = instance.get_native_function::<i64, i64>("sum")?;
+    let call = instance.get_native_function::<(i64, i64), i64>("call")?;
+    let index = instance.exports().get_global("index")?;
+
+    assert_eq!(sum.call(10_000_000)?, 50_000_005_000_000);
+    assert_eq!(call.call(1, 2)?, 3);
+
+    index.set(Value::I32(1))?;
+    let err = call.call(1, 2).unwrap_err();
+    assert_eq!(err.to_trap(), Some(TrapCode::BadSignature));
+    index.set(Value::I32(2))?;
+    let err = call.call(1, 2).unwrap_err();
+    assert_eq!(err.to_trap(), Some(TrapCode::TableAccessOutOfBounds));
+
+    Ok(())
+}
+
+#[compiler_test(tail_call)]
+fn stack_arguments(config: crate::Config) -> anyhow::Result<()> {
+    let store = tail_call_config(config).store();
+    // Enough parameters for some of them to be passed on the stack, rotated
+    // on each call.
+    let wat = r#"
+        (func $rotate (param i64 i64 i64 i64 i64 i64 i64 i64 i64 f64) (result i64)
+            (if (result i64) (i64.eqz (local.get 0))
+                (then
+                    (i64.add (local.get 1)
+                    (i64.add (i64.mul (local.get 2) (i64.const 10))
+                    (i64.add (i64.mul (local.get 3) (i64.const 100))
+                    (i64.add (i64.mul (local.get 4) (i64.const 1000))
+                    (i64.add (i64.mul (local.get 5) (i64.const 10000))
+                    (i64.add (i64.mul (local.get 6) (i64.const 100000))
+                    (i64.add (i64.mul (local.get 7) (i64.const 1000000))
+                    (i64.add (i64.mul (local.get 8) (i64.const 10000000))
+                        (i64.trunc_f64_s (local.get 9)))))))))))
+                (else
+                    (return_call $rotate
+                        (i64.sub (local.get 0) (i64.const 1))
+                        (local.get 8) (local.get 1) (local.get 2) (local.get 3)
+                        (local.get 4) (local.get 5) (local.get 6) (local.get 7)
+                        (f64.add (local.get 9) (f64.const 1))))))
+        (func (export "rotate") (param i64) (result i64)
+            (call $rotate (local.get 0)
+                (i64.const 1) (i64.const 2) (i64.const 3) (i64.const 4)
+                (i64.const 5) (i64.const 6) (i64.const 7) (i64.const 8)
+                (f64.const 0)))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let rotate = instance.get_native_function::<i64, i64>("rotate")?;
+
+    let expected = |n: i64| {
+        let mut args = [1, 2, 3, 4, 5, 6, 7, 8];
+        args.rotate_right((n % 8) as usize);
+        args.iter().rev().fold(0, |acc, arg| acc * 10 + arg) + n
+    };
+    for n in [0, 1, 7, 1_000_000, 1_000_003] {
+        assert_eq!(rotate.call(n)?, expected(n));
+    }
+
+    Ok(())
+}
+
+#[compiler_test(tail_call)]
+fn mutual_recursion_with_more_stack_arguments(config: crate::Config) -> anyhow::Result<()> {
+    let store = tail_call_config(config).store();
+    // `$pong` takes some of its arguments on the stack, while `$ping` takes
+    // none: the calls of `$ping` reserve the room `$pong` needs.
+    let wat = r#"
+        (func $ping (export "ping") (param i64 i64) (result i64)
+            (if (result i64) (i64.eqz (local.get 0))
+                (then (local.get 1))
+                (else
+                    (return_call $pong
+                        (i64.sub (local.get 0) (i64.const 1)) (local.get 1)
+                        (i64.const 1) (i64.const 2) (i64.const 3) (i64.const 4)
+                        (i64.const 5) (i64.const 6) (i64.const 7) (i64.const 8)))))
+        (func $pong (param i64 i64 i64 i64 i64 i64 i64 i64 i64 i64) (result i64)
+            (return_call $ping (local.get 0)
+                (i64.add (local.get 1) (i64.add (local.get 2) (local.get 9)))))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let ping = instance.get_native_function::<(i64, i64), i64>("ping")?;
+
+    // Way deeper than the native stack could hold without tail calls.
+    assert_eq!(ping.call(10_000_000, 0)?, 90_000_000);
+
+    Ok(())
+}
+
+#[compiler_test(tail_call)]
+fn tail_calls_which_cannot_be_made_are_rejected(config: crate::Config) {
+    let store = tail_call_config(config).store();
+    // Too many stack arguments for the room reserved by the calls of `$f`.
+    let wat = r#"
+        (func $f (result i64)
+            (return_call $g
+                (i64.const 0) (i64.const 1) (i64.const 2) (i64.const 3) (i64.const 4)
+                (i64.const 5) (i64.const 6) (i64.const 7) (i64.const 8) (i64.const 9)
+                (i64.const 10) (i64.const 11) (i64.const 12) (i64.const 13) (i64.const 14)
+                (i64.const 15) (i64.const 16) (i64.const 17) (i64.const 18) (i64.const 19)))
+        (func $g (param i64 i64 i64 i64 i64 i64 i64 i64 i64 i64
+                        i64 i64 i64 i64 i64 i64 i64 i64 i64 i64) (result i64)
+            (local.get 19))
+    "#;
+    let error = Module::new(&store, wat).unwrap_err();
+    assert!(error.to_string().contains("return_call"), "{}", error);
+}
+
+#[compiler_test(tail_call)]
+fn gas_across_tail_calls(config: crate::Config) -> anyhow::Result<()> {
+    let store = tail_call_config(config).store();
+    let wat = r#"
+        (import "host" "gas" (func $gas (param i32)))
+        (func $countdown (export "countdown") (param i32)
+            (call $gas (i32.const 5))
+            (if (i32.eqz (local.get 0))
+                (then (return)))
+            (return_call $countdown (i32.sub (local.get 0) (i32.const 1))))
+        (func (export "charge")
+            (return_call $gas (i32.const 7)))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let mut gas_counter = FastGasCounter::new(u64::MAX, 2);
+    let instance = Instance::new_with_config(
+        &module,
+        unsafe { InstanceConfig::default().with_counter(ptr::addr_of_mut!(gas_counter)) },
+        &imports! {
+            "host" => {
+                "gas" => Function::new(&store, FunctionType::new(vec![ValType::I32], vec![]), |_| {
+                    panic!("the gas intrinsic must not call the host");
+                }),
+            },
+        },
+    )?;
+    let countdown = instance.get_native_function::<i32, ()>("countdown")?;
+    let charge = instance.get_native_function::<(), ()>("charge")?;
+
+    countdown.call(1_000_000)?;
+    assert_eq!(gas_counter.burnt(), 5 * 2 * 1_000_001);
+    charge.call()?;
+    assert_eq!(gas_counter.burnt(), 5 * 2 * 1_000_001 + 7 * 2);
+
+    gas_counter.gas_limit = gas_counter.burnt() + 1000;
+    let err = countdown.call(1_000_000).unwrap_err();
+    assert_eq!(err.to_trap(), Some(TrapCode::GasExceeded));
+
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use std::thread;
+use std::time::Duration;
+use wasmer::*;
+use wasmer_vm::TrapCode;
+
+fn get_module(store: &Store) -> Module {
+    let wat = r#"
+        (import "env" "memory" (memory 1 1 shared))
+        (func (export "wait") (param i32 i32) (result i32)
+            (memory.atomic.wait32 (local.get 0) (local.get 1) (i64.const -1)))
+        (func (export "wait_timeout") (param i32 i64) (result i32)
+            (memory.atomic.wait64 (local.get 0) (local.get 1) (i64.const 1000000)))
+        (func (export "notify") (param i32 i32) (result i32)
+            (memory.atomic.notify offset=4 (local.get 0) (local.get 1)))
+        (func (export "store") (param i32 i32)
+            (i32.atomic.store (local.get 0) (local.get 1)))
+    "#;
+
+    Module::new(&store, &wat).unwrap()
+}
+
+fn threads_config(mut config: crate::Config) -> crate::Config {
+    let mut features = Features::default();
+    features.threads(true);
+    config.set_features(features);
+    config
+}
+
+#[compiler_test(threads)]
+fn wait_notify_across_instances(config: crate::Config) -> anyhow::Result<()> {
+    let store = threads_config(config).store();
+    let module = get_module(&store);
+    let memory = Memory::new(&store, MemoryType::new(1, Some(1), true))?;
+
+    let waiter = {
+        let module = module.clone();
+        let memory = memory.clone();
+        thread::spawn(move || -> anyhow::Result<i32> {
+            let instance = Instance::new(&module, &imports! { "env" => { "memory" => memory } })?;
+            let wait = instance.get_native_function::<(i32, i32), i32>("wait")?;
+            Ok(wait.call(8, 0)?)
+        })
+    };
+
+    let instance = Instance::new(&module, &imports! { "env" => { "memory" => memory } })?;
+    let notify = instance.get_native_function::<(i32, i32), i32>("notify")?;
+    // The waiter may not be parked yet, in which case nothing is woken up.
+    while notify.call(4, 1)? == 0 {
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(waiter.join().unwrap()?, 0);
+    assert_eq!(notify.call(4, 1)?, 0);
+
+    Ok(())
+}
+
+#[compiler_test(threads)]
+fn wait_checks_value_and_timeout(config: crate::Config) -> anyhow::Result<()> {
+    let store = threads_config(config).store();
+    let module = get_module(&store);
+    let memory = Memory::new(&store, MemoryType::new(1, Some(1), true))?;
+    let instance = Instance::new(&module, &imports! { "env" => { "memory" => memory } })?;
+
+    let wait = instance.get_native_function::<(i32, i32), i32>("wait")?;
+    let wait_timeout = instance.get_native_function::<(i32, i64), i32>("wait_timeout")?;
+    let store = instance.get_native_function::<(i32, i32), ()>("store")?;
+
+    store.call(0, 5)?;
+    assert_eq!(wait.call(0, 0)?, 1);
+    assert_eq!(wait_timeout.call(0, 5)?, 2);
+
+    // Out of bounds and unaligned accesses trap.
+    assert!(wait.call(65536, 0).is_err());
+    assert!(wait.call(2, 0).is_err());
+
+    Ok(())
+}
+
+#[compiler_test(threads)]
+fn shared_memory_requires_maximum(config: crate::Config) -> anyhow::Result<()> {
+    let store = threads_config(config).store();
+    assert!(Memory::new(&store, MemoryType::new(1, None, true)).is_err());
+
+    // Growing a shared memory doesn't move it.
+    let memory = Memory::new(&store, MemoryType::new(1, Some(4), true))?;
+    let base = memory.data_ptr();
+    memory.grow(3)?;
+    assert_eq!(memory.data_ptr(), base);
+    assert_eq!(memory.size(), Pages(4));
+
+    Ok(())
+}
+
+#[compiler_test(threads)]
+fn wait_on_unshared_memory_traps(config: crate::Config) -> anyhow::Result<()> {
+    let store = threads_config(config).store();
+    let wat = r#"
+        (memory 1)
+        (func (export "wait") (result i32)
+            (memory.atomic.wait32 (i32.const 0) (i32.const 0) (i64.const 0)))
+        (func (export "notify") (result i32)
+            (memory.atomic.notify (i32.const 0) (i32.const 1)))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+
+    let wait = instance.get_native_function::<(), i32>("wait")?;
+    let err = wait.call().unwrap_err();
+    assert_eq!(err.to_trap(), Some(TrapCode::UnsharedMemoryWait));
+    let notify = instance.get_native_function::<(), i32>("notify")?;
+    assert_eq!(notify.call()?, 0);
+
+    Ok(())
+}
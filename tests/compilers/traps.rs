@@ -465,3 +465,168 @@ fn present_after_module_drop(config: crate::Config) -> Result<()> {
         // assert_eq!(t.trace()[0].func_index(), 0);
     }
 }
+
+#[compiler_test(traps)]
+fn trap_after_dropping_many_modules(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wat = r#"(func (export "foo") unreachable)"#;
+    // Every module owns its own code memory and unwind information, which get released when the
+    // module is dropped. Make sure that loading new modules and trapping in them keeps working.
+    for _ in 0..100 {
+        let module = Module::new(&store, wat)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let func: Function = instance.lookup_function("foo").unwrap();
+        let err = func.call(&[]).unwrap_err();
+        assert_eq!(
+            err.to_trap(),
+            Some(wasmer_vm::TrapCode::UnreachableCodeReached)
+        );
+    }
+    Ok(())
+}
+
+/// Append DWARF sections to `wasm` which map its whole code section to
+/// `file:line`.
+fn with_line_info(mut wasm: Vec<u8>, file: &str, line: u64) -> Vec<u8> {
+    use gimli::write::{Address, DwarfUnit, EndianVec, LineProgram, LineString, Sections};
+
+    fn read_leb(bytes: &[u8], pos: &mut usize) -> usize {
+        let (mut value, mut shift) = (0, 0);
+        loop {
+            let byte = bytes[*pos];
+            *pos += 1;
+            value |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return value;
+            }
+            shift += 7;
+        }
+    }
+
+    fn write_leb(bytes: &mut Vec<u8>, mut value: usize) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                bytes.push(byte);
+                return;
+            }
+            bytes.push(byte | 0x80);
+        }
+    }
+
+    let mut pos = 8;
+    let code_len = loop {
+        let id = wasm[pos];
+        pos += 1;
+        let size = read_leb(&wasm, &mut pos);
+        if id == 10 {
+            break size;
+        }
+        pos += size;
+    };
+
+    let encoding = gimli::Encoding {
+        format: gimli::Format::Dwarf32,
+        version: 4,
+        address_size: 4,
+    };
+    let mut dwarf = DwarfUnit::new(encoding);
+    let mut program = LineProgram::new(
+        encoding,
+        gimli::LineEncoding::default(),
+        LineString::String(vec![]),
+        LineString::String(file.as_bytes().to_vec()),
+        None,
+    );
+    let dir = program.default_directory();
+    let file = program.add_file(LineString::String(file.as_bytes().to_vec()), dir, None);
+    program.begin_sequence(Some(Address::Constant(0)));
+    program.row().file = file;
+    program.row().line = line;
+    program.generate_row();
+    program.end_sequence(code_len as u64);
+    dwarf.unit.line_program = program;
+
+    let mut sections = Sections::new(EndianVec::new(gimli::LittleEndian));
+    dwarf.write(&mut sections).unwrap();
+    sections
+        .for_each(|id, data| -> gimli::write::Result<()> {
+            let mut payload = vec![];
+            write_leb(&mut payload, id.name().len());
+            payload.extend_from_slice(id.name().as_bytes());
+            payload.extend_from_slice(data.slice());
+            wasm.push(0);
+            write_leb(&mut wasm, payload.len());
+            wasm.extend_from_slice(&payload);
+            Ok(())
+        })
+        .unwrap();
+    wasm
+}
+
+#[cfg_attr(target_env = "musl", ignore)]
+#[compiler_test(traps)]
+fn trap_trace_has_source_locations(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wat = r#"
+        (module $dwarf_mod
+            (func (export "run") (call $fail))
+            (func $fail (unreachable))
+        )
+    "#;
+    let wasm = with_line_info(wat::parse_str(wat)?, "/src/contract.rs", 42);
+
+    let module = Module::new(&store, &wasm)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let run_func = instance.lookup_function("run").unwrap();
+    let e = run_func.call(&[]).unwrap_err();
+
+    let trace = e.trace();
+    assert!(!trace.is_empty());
+    for frame in trace {
+        assert_eq!(frame.file(), Some("/src/contract.rs"));
+        assert_eq!(frame.line(), Some(42));
+        assert_eq!(frame.column(), None);
+    }
+    assert!(e.to_string().contains("at /src/contract.rs:42"), "{}", e);
+
+    Ok(())
+}
+
+#[compiler_test(traps)]
+fn dropping_modules_releases_their_registrations(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let engine: &dyn wasmer_engine::Engine = &**store.engine();
+    let engine = engine
+        .downcast_ref::<wasmer_engine_universal::UniversalEngine>()
+        .unwrap();
+    let wat = r#"
+        (table (export "table") 1 funcref)
+        (func (export "foo") (param i64 f32 i32 f64 i64 i32) (result f32)
+            (local.get 1))
+    "#;
+    // Load the module once beforehand, so that the registrations made
+    // once per engine are counted in the baseline.
+    drop(Module::new(&store, wat)?);
+    let signatures = engine.registered_signatures();
+    let function_references = engine.registered_function_references();
+
+    for _ in 0..10 {
+        let module = Module::new(&store, wat)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let exports = instance.exports();
+        let foo = exports.get_function("foo")?;
+        exports
+            .get_table("table")?
+            .set(0, Val::FuncRef(Some(foo)))?;
+        assert_eq!(engine.registered_signatures(), signatures + 1);
+        assert_eq!(
+            engine.registered_function_references(),
+            function_references + 1
+        );
+    }
+    assert_eq!(engine.registered_signatures(), signatures);
+    assert_eq!(engine.registered_function_references(), function_references);
+    Ok(())
+}
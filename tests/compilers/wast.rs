@@ -22,14 +22,15 @@ pub fn run_wast(mut config: crate::Config, wast_path: &str) -> anyhow::Result<()
     let mut features = Features::default();
     let is_bulkmemory = wast_path.contains("bulk-memory");
     let is_simd = wast_path.contains("simd");
+    let is_multimemory = wast_path.contains("multi-memory");
     if is_bulkmemory {
         features.bulk_memory(true);
     }
     if is_simd {
         features.simd(true);
     }
-    if config.compiler == crate::Compiler::Singlepass {
-        features.multi_value(false);
+    if is_multimemory {
+        features.multi_memory(true);
     }
     config.set_features(features);
     config.set_nan_canonicalization(try_nan_canonicalization);
@@ -63,13 +64,6 @@ pub fn run_wast(mut config: crate::Config, wast_path: &str) -> anyhow::Result<()
             "Validation error: Invalid var_u32",
         ]);
     }
-    if config.compiler == crate::Compiler::Singlepass {
-        // We don't support multivalue yet in singlepass
-        wast.allow_instantiation_failures(&[
-            "Validation error: invalid result arity: func type returns multiple values",
-            "Validation error: blocks, loops, and ifs accept no parameters when multi-value is not enabled",
-        ]);
-    }
     wast.fail_fast = false;
     let path = Path::new(wast_path);
     wast.run_file(path)
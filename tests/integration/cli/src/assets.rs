@@ -21,12 +21,12 @@ pub const WASMER_PATH: &str = concat!(
 #[cfg(not(windows))]
 pub const LIBWASMER_PATH: &str = concat!(
     env!("CARGO_MANIFEST_DIR"),
-    "/../../../target/release/libwasmer.a"
+    "/../../../target/release/libwasmer_c_api.a"
 );
 #[cfg(windows)]
 pub const LIBWASMER_PATH: &str = concat!(
     env!("CARGO_MANIFEST_DIR"),
-    "/../../../target/release/wasmer.lib"
+    "/../../../target/release/wasmer_c_api.lib"
 );
 
 /// Get the path to the `libwasmer.a` static library.
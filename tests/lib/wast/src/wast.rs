@@ -96,7 +96,7 @@ impl Wast {
         match exec {
             wast::WastExecute::Invoke(invoke) => self.perform_invoke(invoke),
             wast::WastExecute::Module(mut module) => {
-                let binary = module.encode()?;
+                let binary = encode(&mut module)?;
                 let result = self.instantiate(&binary);
                 result.map(|_| Vec::new())
             }
@@ -163,7 +163,7 @@ impl Wast {
 
         match directive {
             Module(mut module) => {
-                let binary = module.encode()?;
+                let binary = encode(&mut module)?;
                 self.module(module.id.map(|s| s.name()), &binary)?;
             }
             Register {
@@ -210,7 +210,7 @@ impl Wast {
                 message,
             } => {
                 let wasm = match module {
-                    wast::QuoteModule::Module(mut m) => m.encode()?,
+                    wast::QuoteModule::Module(mut m) => encode(&mut m)?,
                     wast::QuoteModule::Quote(list) => self.parse_quote_module(test, &list)?,
                 };
                 let err = match self.module(None, &wasm) {
@@ -243,7 +243,7 @@ impl Wast {
                     // interested in.
                     wast::QuoteModule::Quote(_) => return Ok(()),
                 };
-                let bytes = module.encode()?;
+                let bytes = encode(&mut module)?;
                 if self.module(None, &bytes).is_ok() {
                     bail!("expected malformed module to fail to instantiate");
                 }
@@ -253,7 +253,7 @@ impl Wast {
                 mut module,
                 message,
             } => {
-                let bytes = module.encode()?;
+                let bytes = encode(&mut module)?;
                 let err = match self.module(None, &bytes) {
                     Ok(()) => bail!("expected module to fail to link"),
                     Err(e) => e,
@@ -339,7 +339,7 @@ impl Wast {
         if ret.contains("offset=4294967296") && !test.iter().any(|t| t == "memory64") {
             bail!("i32 constant out of bounds");
         }
-        Ok(wat.module.encode()?)
+        encode(&mut wat.module)
     }
 
     /// Run a wast script from a file.
@@ -521,6 +521,20 @@ impl NamedResolver for Wast {
     }
 }
 
+/// Encodes `module` as the proposals do.
+///
+/// `wast` writes the offset of the memory accesses to other memories than
+/// the first one before their memory index, as early drafts of the
+/// multi-memory proposal did, so the text modules are reordered. Binary
+/// modules are kept as written.
+fn encode(module: &mut wast::Module<'_>) -> Result<Vec<u8>> {
+    let binary = module.encode()?;
+    Ok(match module.kind {
+        wast::ModuleKind::Text(_) => wasmer_compiler::reorder_memargs(&binary).into_owned(),
+        wast::ModuleKind::Binary(_) => binary,
+    })
+}
+
 fn extract_lane_as_i8(bytes: u128, lane: usize) -> i8 {
     (bytes >> (lane * 8)) as i8
 }
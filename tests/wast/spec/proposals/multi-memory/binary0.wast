@@ -0,0 +1,81 @@
+;; Memory accesses to other memories than the first one, written as the
+;; proposal encodes them: their flags with bit 6 set, the memory index and
+;; the offset.
+
+(module binary
+  "\00asm" "\01\00\00\00"
+  "\01\05\01"                          ;; type section
+  "\60\00\01\7f"                       ;; type 0: [] -> [i32]
+  "\03\04\03\00\00\00"                 ;; function section: 3 functions
+  "\05\05\02"                          ;; memory section: 2 memories
+  "\00\01"                             ;; memory 0: min 1
+  "\00\01"                             ;; memory 1: min 1
+  "\07\19\03"                          ;; export section: 3 exports
+  "\05load0\00\00"
+  "\05load1\00\01"
+  "\05load2\00\02"
+  "\0a\1e\03"                          ;; code section: 3 functions
+  "\08\00"
+  "\41\00"                             ;; i32.const 0
+  "\2d\00\ac\02"                       ;; i32.load8_u offset=300
+  "\0b"
+  "\09\00"
+  "\41\00"                             ;; i32.const 0
+  "\2d\40\01\ac\02"                    ;; i32.load8_u 1 offset=300
+  "\0b"
+  "\09\00"
+  "\41\ac\02"                          ;; i32.const 300
+  "\2d\40\01\00"                       ;; i32.load8_u 1
+  "\0b"
+  "\0b\09\01"                          ;; data section: 1 segment
+  "\02\01\41\ac\02\0b"                 ;; memory 1, offset 300
+  "\01\2a"                             ;; "\2a"
+)
+
+(assert_return (invoke "load0") (i32.const 0))
+(assert_return (invoke "load1") (i32.const 42))
+(assert_return (invoke "load2") (i32.const 42))
+
+;; A memory index with a multi-byte encoding.
+(module binary
+  "\00asm" "\01\00\00\00"
+  "\01\06\01"                          ;; type section
+  "\60\01\7f\01\7f"                    ;; type 0: [i32] -> [i32]
+  "\03\02\01\00"                       ;; function section: 1 function
+  "\05\05\02"                          ;; memory section: 2 memories
+  "\00\00"                             ;; memory 0: min 0
+  "\00\01"                             ;; memory 1: min 1
+  "\07\08\01"                          ;; export section: 1 export
+  "\04load\00\00"
+  "\0a\0b\01"                          ;; code section: 1 function
+  "\09\00"
+  "\20\00"                             ;; local.get 0
+  "\2d\40\81\00\03"                    ;; i32.load8_u 1 offset=3
+  "\0b"
+  "\0b\09\01"                          ;; data section: 1 segment
+  "\02\01\41\05\0b"                    ;; memory 1, offset 5
+  "\02\11\22"                          ;; "\11\22"
+)
+
+(assert_return (invoke "load" (i32.const 2)) (i32.const 0x11))
+(assert_return (invoke "load" (i32.const 3)) (i32.const 0x22))
+(assert_return (invoke "load" (i32.const 65532)) (i32.const 0))
+(assert_trap (invoke "load" (i32.const 65533)) "out of bounds memory access")
+
+(assert_invalid
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\01\04\01"                        ;; type section
+    "\60\00\00"                        ;; type 0: [] -> []
+    "\03\02\01\00"                     ;; function section: 1 function
+    "\05\03\01"                        ;; memory section: 1 memory
+    "\00\01"                           ;; memory 0: min 1
+    "\0a\0b\01"                        ;; code section: 1 function
+    "\09\00"
+    "\41\00"                           ;; i32.const 0
+    "\2d\40\01\00"                     ;; i32.load8_u 1
+    "\1a"                              ;; drop
+    "\0b"
+  )
+  "unknown memory 1"
+)
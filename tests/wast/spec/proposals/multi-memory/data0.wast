@@ -0,0 +1,52 @@
+;; Test the data section
+
+(module
+  (global (import "spectest" "global_i32") i32)
+  (memory $mem0 1)
+  (memory $mem1 1)
+  (memory $mem2 1)
+
+  (data (memory $mem0) (i32.const 0))
+  (data (memory $mem1) (i32.const 1) "a" "" "bcd")
+  (data (memory $mem2) (i32.const 0x1_0000) "")
+  (data (memory $mem1) (offset (i32.const 0)) "\ff")
+  (data (memory $mem2) (offset (global.get 0)) "xyz")
+
+  (func (export "load0") (param i32) (result i32) (i32.load8_u (memory $mem0) (local.get 0)))
+  (func (export "load1") (param i32) (result i32) (i32.load8_u (memory $mem1) (local.get 0)))
+  (func (export "load2") (param i32) (result i32) (i32.load8_u (memory $mem2) (local.get 0)))
+)
+
+(assert_return (invoke "load0" (i32.const 1)) (i32.const 0))
+(assert_return (invoke "load1" (i32.const 0)) (i32.const 0xff))
+(assert_return (invoke "load1" (i32.const 1)) (i32.const 0x61))
+(assert_return (invoke "load1" (i32.const 4)) (i32.const 0x64))
+(assert_return (invoke "load2" (i32.const 666)) (i32.const 0x78))
+(assert_return (invoke "load2" (i32.const 668)) (i32.const 0x7a))
+(assert_return (invoke "load2" (i32.const 1)) (i32.const 0))
+
+(assert_trap
+  (module
+    (memory $mem0 1)
+    (memory $mem1 0)
+    (data (memory $mem1) (i32.const 0) "a")
+  )
+  "out of bounds memory access"
+)
+
+(assert_trap
+  (module
+    (memory $mem0 0)
+    (memory $mem1 1)
+    (data (memory $mem1) (i32.const 0x1_0000) "a")
+  )
+  "out of bounds memory access"
+)
+
+(assert_invalid
+  (module
+    (memory 1)
+    (data (memory 1) (i32.const 0))
+  )
+  "unknown memory 1"
+)
@@ -0,0 +1,14 @@
+;; Memories
+
+(module (memory 0) (memory 0) (export "a" (memory 0)) (export "b" (memory 1)))
+(module (memory 0) (memory $m 0) (export "a" (memory $m)) (export "b" (memory $m)))
+(module (memory 0) (memory 0) (export "a" (memory 1)) (export "b" (memory 0)))
+
+(assert_invalid
+  (module (memory 0) (memory 0) (export "a" (memory 2)))
+  "unknown memory"
+)
+(assert_invalid
+  (module (memory 0) (memory 0) (export "a" (memory 0)) (export "a" (memory 1)))
+  "duplicate export name"
+)
@@ -0,0 +1,40 @@
+(module
+  (func (export "f"))
+  (memory (export "mem0") 1 2)
+  (memory (export "mem1") 1 1)
+)
+(register "test")
+
+(module
+  (import "test" "mem0" (memory 1 2))
+  (import "test" "mem1" (memory $m 1 1))
+  (memory 2)
+  (func (export "size") (param i32) (result i32)
+    (if (result i32) (i32.eqz (local.get 0))
+      (then (memory.size 0))
+      (else
+        (if (result i32) (i32.eq (local.get 0) (i32.const 1))
+          (then (memory.size $m))
+          (else (memory.size 2))
+        )
+      )
+    )
+  )
+)
+
+(assert_return (invoke "size" (i32.const 0)) (i32.const 1))
+(assert_return (invoke "size" (i32.const 1)) (i32.const 1))
+(assert_return (invoke "size" (i32.const 2)) (i32.const 2))
+
+(assert_unlinkable
+  (module (import "test" "mem0" (memory 3)))
+  "incompatible import type"
+)
+(assert_unlinkable
+  (module (import "test" "mem1" (memory 1)) (import "test" "mem0" (memory 1 1)))
+  "incompatible import type"
+)
+(assert_unlinkable
+  (module (import "test" "mem1" (memory 0)) (import "test" "f" (memory 1)))
+  "incompatible import type"
+)
@@ -0,0 +1,36 @@
+(module
+  (memory 0)
+  (memory 0)
+  (memory 0)
+  (memory $m 1)
+
+  (data (memory $m) (i32.const 0) "\00\01\02\03\04\05\06\07\08\09\0a\0b\0c\0d\0e\0f")
+  (data (memory $m) (i32.const 65530) "\aa\bb\cc\dd\ee\ff")
+
+  (func (export "load") (param i32) (result i32)
+    (i32.load8_u (memory $m) (local.get 0))
+  )
+  (func (export "load_offset") (param i32) (result i32)
+    (i32.load8_u (memory $m) offset=7 (local.get 0))
+  )
+  (func (export "load16_offset") (param i32) (result i32)
+    (i32.load16_u (memory $m) offset=300 align=1 (local.get 0))
+  )
+  (func (export "load64_offset") (param i32) (result i64)
+    (i64.load (memory $m) offset=65528 align=2 (local.get 0))
+  )
+)
+
+(assert_return (invoke "load" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "load" (i32.const 15)) (i32.const 15))
+(assert_return (invoke "load_offset" (i32.const 0)) (i32.const 7))
+(assert_return (invoke "load_offset" (i32.const 8)) (i32.const 15))
+(assert_return (invoke "load16_offset" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "load16_offset" (i32.const 65230)) (i32.const 0xbbaa))
+(assert_return (invoke "load64_offset" (i32.const 0)) (i64.const 0xffeeddccbbaa0000))
+(assert_return (invoke "load" (i32.const 65535)) (i32.const 0xff))
+(assert_trap (invoke "load" (i32.const 65536)) "out of bounds memory access")
+(assert_trap (invoke "load_offset" (i32.const 65529)) "out of bounds memory access")
+(assert_trap (invoke "load16_offset" (i32.const 65235)) "out of bounds memory access")
+(assert_trap (invoke "load64_offset" (i32.const 1)) "out of bounds memory access")
+(assert_trap (invoke "load64_offset" (i32.const -65528)) "out of bounds memory access")
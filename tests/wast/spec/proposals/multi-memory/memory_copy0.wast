@@ -0,0 +1,56 @@
+;; memory.copy between and within memories
+
+(module
+  (memory $mem0 1)
+  (memory $mem1 1)
+  (memory $mem2 1)
+
+  (data (memory $mem2) (i32.const 2) "\03\01\04\01")
+  (data (memory $mem2) (i32.const 12) "\07\05\02\03\06")
+
+  (func (export "copy") (param $dst i32) (param $src i32) (param $len i32)
+    (memory.copy $mem1 $mem2 (local.get $dst) (local.get $src) (local.get $len))
+  )
+  (func (export "copy_within") (param $dst i32) (param $src i32) (param $len i32)
+    (memory.copy $mem2 $mem2 (local.get $dst) (local.get $src) (local.get $len))
+  )
+  (func (export "load0") (param i32) (result i32)
+    (i32.load8_u (memory $mem0) (local.get 0))
+  )
+  (func (export "load1") (param i32) (result i32)
+    (i32.load8_u (memory $mem1) (local.get 0))
+  )
+  (func (export "load2") (param i32) (result i32)
+    (i32.load8_u (memory $mem2) (local.get 0))
+  )
+)
+
+(invoke "copy" (i32.const 10) (i32.const 0) (i32.const 18))
+(assert_return (invoke "load1" (i32.const 9)) (i32.const 0))
+(assert_return (invoke "load1" (i32.const 12)) (i32.const 3))
+(assert_return (invoke "load1" (i32.const 13)) (i32.const 1))
+(assert_return (invoke "load1" (i32.const 14)) (i32.const 4))
+(assert_return (invoke "load1" (i32.const 15)) (i32.const 1))
+(assert_return (invoke "load1" (i32.const 22)) (i32.const 7))
+(assert_return (invoke "load1" (i32.const 23)) (i32.const 5))
+(assert_return (invoke "load1" (i32.const 24)) (i32.const 2))
+(assert_return (invoke "load1" (i32.const 25)) (i32.const 3))
+(assert_return (invoke "load1" (i32.const 26)) (i32.const 6))
+(assert_return (invoke "load1" (i32.const 27)) (i32.const 0))
+(assert_return (invoke "load0" (i32.const 12)) (i32.const 0))
+(assert_return (invoke "load2" (i32.const 12)) (i32.const 7))
+
+(invoke "copy_within" (i32.const 13) (i32.const 12) (i32.const 5))
+(assert_return (invoke "load2" (i32.const 12)) (i32.const 7))
+(assert_return (invoke "load2" (i32.const 13)) (i32.const 7))
+(assert_return (invoke "load2" (i32.const 14)) (i32.const 5))
+(assert_return (invoke "load2" (i32.const 17)) (i32.const 6))
+(assert_return (invoke "load1" (i32.const 13)) (i32.const 1))
+
+(assert_trap (invoke "copy" (i32.const 65530) (i32.const 0) (i32.const 7))
+    "out of bounds memory access")
+(assert_trap (invoke "copy" (i32.const 0) (i32.const 65530) (i32.const 7))
+    "out of bounds memory access")
+(assert_return (invoke "copy" (i32.const 65536) (i32.const 0) (i32.const 0)))
+(assert_trap (invoke "copy" (i32.const 65537) (i32.const 0) (i32.const 0))
+    "out of bounds memory access")
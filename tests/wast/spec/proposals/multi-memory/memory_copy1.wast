@@ -0,0 +1,50 @@
+;; memory.copy between memories of different sizes, one of them imported
+
+(module $M
+  (memory (export "mem") 1)
+  (data (i32.const 100) "\0a\0b\0c\0d")
+
+  (func (export "load") (param i32) (result i32)
+    (i32.load8_u (local.get 0))
+  )
+)
+(register "M")
+
+(module
+  (memory $big (import "M" "mem") 1)
+  (memory $small 1)
+  (memory $empty 0)
+
+  (func (export "copy_in") (param $dst i32) (param $src i32) (param $len i32)
+    (memory.copy $small $big (local.get $dst) (local.get $src) (local.get $len))
+  )
+  (func (export "copy_out") (param $dst i32) (param $src i32) (param $len i32)
+    (memory.copy $big $small (local.get $dst) (local.get $src) (local.get $len))
+  )
+  (func (export "copy_empty") (param $dst i32) (param $src i32) (param $len i32)
+    (memory.copy $empty $small (local.get $dst) (local.get $src) (local.get $len))
+  )
+  (func (export "load") (param i32) (result i32)
+    (i32.load8_u (memory $small) (local.get 0))
+  )
+  (func (export "grow_big") (param i32) (result i32)
+    (memory.grow $big (local.get 0))
+  )
+)
+
+(invoke "copy_in" (i32.const 0) (i32.const 100) (i32.const 4))
+(assert_return (invoke "load" (i32.const 0)) (i32.const 10))
+(assert_return (invoke "load" (i32.const 3)) (i32.const 13))
+(invoke "copy_out" (i32.const 200) (i32.const 1) (i32.const 2))
+(assert_return (invoke $M "load" (i32.const 200)) (i32.const 11))
+(assert_return (invoke $M "load" (i32.const 201)) (i32.const 12))
+(assert_return (invoke $M "load" (i32.const 202)) (i32.const 0))
+
+(assert_return (invoke "grow_big" (i32.const 1)) (i32.const 1))
+(invoke "copy_out" (i32.const 131068) (i32.const 0) (i32.const 4))
+(assert_return (invoke $M "load" (i32.const 131071)) (i32.const 13))
+(assert_trap (invoke "copy_in" (i32.const 65533) (i32.const 100) (i32.const 4))
+    "out of bounds memory access")
+(assert_return (invoke "copy_empty" (i32.const 0) (i32.const 0) (i32.const 0)))
+(assert_trap (invoke "copy_empty" (i32.const 0) (i32.const 0) (i32.const 1))
+    "out of bounds memory access")
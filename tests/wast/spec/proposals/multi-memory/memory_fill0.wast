@@ -0,0 +1,39 @@
+(module
+  (memory $mem0 0)
+  (memory $mem1 0)
+  (memory $mem2 1)
+
+  (func (export "fill") (param i32 i32 i32)
+    (memory.fill $mem2 (local.get 0) (local.get 1) (local.get 2))
+  )
+  (func (export "fill0") (param i32 i32 i32)
+    (memory.fill $mem0 (local.get 0) (local.get 1) (local.get 2))
+  )
+  (func (export "load8_u") (param i32) (result i32)
+    (i32.load8_u (memory $mem2) (local.get 0))
+  )
+)
+
+(invoke "fill" (i32.const 1) (i32.const 0xff) (i32.const 3))
+(assert_return (invoke "load8_u" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 1)) (i32.const 0xff))
+(assert_return (invoke "load8_u" (i32.const 2)) (i32.const 0xff))
+(assert_return (invoke "load8_u" (i32.const 3)) (i32.const 0xff))
+(assert_return (invoke "load8_u" (i32.const 4)) (i32.const 0))
+
+(invoke "fill" (i32.const 0) (i32.const 0xbbaa) (i32.const 2))
+(assert_return (invoke "load8_u" (i32.const 0)) (i32.const 0xaa))
+(assert_return (invoke "load8_u" (i32.const 1)) (i32.const 0xaa))
+
+(invoke "fill" (i32.const 0) (i32.const 0) (i32.const 0x10000))
+(assert_return (invoke "load8_u" (i32.const 0xffff)) (i32.const 0))
+
+(assert_trap (invoke "fill" (i32.const 0xff00) (i32.const 1) (i32.const 0x101))
+    "out of bounds memory access")
+(assert_return (invoke "load8_u" (i32.const 0xff00)) (i32.const 0))
+(assert_return (invoke "fill" (i32.const 0x10000) (i32.const 0) (i32.const 0)))
+(assert_trap (invoke "fill" (i32.const 0x10001) (i32.const 0) (i32.const 0))
+    "out of bounds memory access")
+(assert_return (invoke "fill0" (i32.const 0) (i32.const 1) (i32.const 0)))
+(assert_trap (invoke "fill0" (i32.const 0) (i32.const 1) (i32.const 1))
+    "out of bounds memory access")
@@ -0,0 +1,53 @@
+(module
+  (memory $mem1 1)
+  (memory $mem2 2)
+
+  (func (export "grow1") (param $sz i32) (result i32)
+    (memory.grow $mem1 (local.get $sz))
+  )
+  (func (export "grow2") (param $sz i32) (result i32)
+    (memory.grow $mem2 (local.get $sz))
+  )
+  (func (export "size1") (result i32) (memory.size $mem1))
+  (func (export "size2") (result i32) (memory.size $mem2))
+)
+
+(assert_return (invoke "size1") (i32.const 1))
+(assert_return (invoke "size2") (i32.const 2))
+(assert_return (invoke "grow1" (i32.const 3)) (i32.const 1))
+(assert_return (invoke "size1") (i32.const 4))
+(assert_return (invoke "size2") (i32.const 2))
+(assert_return (invoke "grow2" (i32.const 1)) (i32.const 2))
+(assert_return (invoke "size1") (i32.const 4))
+(assert_return (invoke "size2") (i32.const 3))
+
+(module
+  (memory $mem1 1 2)
+  (memory $mem2 0 3)
+
+  (func (export "grow1") (param i32) (result i32)
+    (memory.grow $mem1 (local.get 0))
+  )
+  (func (export "grow2") (param i32) (result i32)
+    (memory.grow $mem2 (local.get 0))
+  )
+  (func (export "load2") (param i32) (result i32)
+    (i32.load8_u (memory $mem2) offset=100 (local.get 0))
+  )
+  (func (export "store2") (param i32 i32)
+    (i32.store8 (memory $mem2) offset=100 (local.get 0) (local.get 1))
+  )
+)
+
+(assert_trap (invoke "load2" (i32.const 0)) "out of bounds memory access")
+(assert_return (invoke "grow1" (i32.const 2)) (i32.const -1))
+(assert_return (invoke "grow2" (i32.const 1)) (i32.const 0))
+(assert_return (invoke "load2" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "store2" (i32.const 65435) (i32.const 42)))
+(assert_return (invoke "load2" (i32.const 65435)) (i32.const 42))
+(assert_trap (invoke "load2" (i32.const 65436)) "out of bounds memory access")
+(assert_return (invoke "grow1" (i32.const 1)) (i32.const 1))
+(assert_return (invoke "grow1" (i32.const 1)) (i32.const -1))
+(assert_return (invoke "grow2" (i32.const 2)) (i32.const 1))
+(assert_return (invoke "grow2" (i32.const 1)) (i32.const -1))
+(assert_return (invoke "load2" (i32.const 65436)) (i32.const 0))
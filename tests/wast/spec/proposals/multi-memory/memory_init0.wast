@@ -0,0 +1,40 @@
+(module
+  (memory $mem0 0)
+  (memory $mem1 0)
+  (memory $mem2 1)
+  (memory $mem3 0)
+  (data $d "\aa\bb\cc\dd")
+
+  (func (export "init") (param i32 i32 i32)
+    (memory.init $d $mem2 (local.get 0) (local.get 1) (local.get 2))
+  )
+  (func (export "init3") (param i32 i32 i32)
+    (memory.init $d $mem3 (local.get 0) (local.get 1) (local.get 2))
+  )
+  (func (export "drop") (data.drop $d))
+  (func (export "load8_u") (param i32) (result i32)
+    (i32.load8_u (memory $mem2) (local.get 0))
+  )
+)
+
+(invoke "init" (i32.const 0) (i32.const 1) (i32.const 2))
+(assert_return (invoke "load8_u" (i32.const 0)) (i32.const 0xbb))
+(assert_return (invoke "load8_u" (i32.const 1)) (i32.const 0xcc))
+(assert_return (invoke "load8_u" (i32.const 2)) (i32.const 0))
+
+(invoke "init" (i32.const 0xfffc) (i32.const 0) (i32.const 4))
+(assert_return (invoke "load8_u" (i32.const 0xfffc)) (i32.const 0xaa))
+(assert_return (invoke "load8_u" (i32.const 0xffff)) (i32.const 0xdd))
+
+(assert_trap (invoke "init" (i32.const 0xfffd) (i32.const 0) (i32.const 4))
+    "out of bounds memory access")
+(assert_trap (invoke "init" (i32.const 0) (i32.const 1) (i32.const 4))
+    "out of bounds memory access")
+(assert_return (invoke "init3" (i32.const 0) (i32.const 0) (i32.const 0)))
+(assert_trap (invoke "init3" (i32.const 0) (i32.const 0) (i32.const 1))
+    "out of bounds memory access")
+
+(invoke "drop")
+(assert_return (invoke "init" (i32.const 0) (i32.const 0) (i32.const 0)))
+(assert_trap (invoke "init" (i32.const 0) (i32.const 0) (i32.const 1))
+    "out of bounds memory access")
@@ -0,0 +1,48 @@
+(module
+  (memory 0)
+  (memory 0)
+  (memory $m 1)
+  (data (memory $m) (i32.const 0) "abcdefghijklmnopqrstuvwxyz")
+
+  (func (export "i32.load") (param $a i32) (result i32)
+    (i32.load (memory $m) (local.get $a))
+  )
+  (func (export "i64.load") (param $a i32) (result i64)
+    (i64.load (memory $m) (local.get $a))
+  )
+  (func (export "i32.load8_s") (param $a i32) (result i32)
+    (i32.load8_s (memory $m) offset=16 (local.get $a))
+  )
+  (func (export "i64.load32_u") (param $a i32) (result i64)
+    (i64.load32_u (memory $m) offset=65532 (local.get $a))
+  )
+  (func (export "f64.store") (param $a i32) (param $v f64)
+    (f64.store (memory $m) offset=4096 (local.get $a) (local.get $v))
+  )
+  (func (export "f64.load") (param $a i32) (result f64)
+    (f64.load (memory $m) offset=4096 (local.get $a))
+  )
+  (func (export "i32.store16") (param $a i32) (param $v i32)
+    (i32.store16 (memory $m) offset=1 align=1 (local.get $a) (local.get $v))
+  )
+  (func (export "i32.load16_u") (param $a i32) (result i32)
+    (i32.load16_u (memory $m) offset=1 align=1 (local.get $a))
+  )
+)
+
+(assert_return (invoke "i32.load" (i32.const 0)) (i32.const 0x64636261))
+(assert_return (invoke "i64.load" (i32.const 8)) (i64.const 0x706f6e6d6c6b6a69))
+(assert_return (invoke "i32.load8_s" (i32.const 9)) (i32.const 0x7a))
+(assert_return (invoke "i32.load8_s" (i32.const 65519)) (i32.const 0))
+(assert_trap (invoke "i32.load8_s" (i32.const 65520)) "out of bounds memory access")
+(assert_return (invoke "i64.load32_u" (i32.const 0)) (i64.const 0))
+(assert_trap (invoke "i64.load32_u" (i32.const 1)) "out of bounds memory access")
+(assert_trap (invoke "i64.load32_u" (i32.const -65532)) "out of bounds memory access")
+(assert_return (invoke "f64.store" (i32.const 61432) (f64.const 1.5)))
+(assert_return (invoke "f64.load" (i32.const 61432)) (f64.const 1.5))
+(assert_trap (invoke "f64.store" (i32.const 61433) (f64.const 1.5)) "out of bounds memory access")
+(assert_trap (invoke "f64.load" (i32.const 61433)) "out of bounds memory access")
+(assert_return (invoke "i32.store16" (i32.const 65533) (i32.const 0xbeef)))
+(assert_return (invoke "i32.load16_u" (i32.const 65533)) (i32.const 0xbeef))
+(assert_trap (invoke "i32.store16" (i32.const 65534) (i32.const 0xbeef)) "out of bounds memory access")
+(assert_trap (invoke "i32.load16_u" (i32.const 65534)) "out of bounds memory access")